@@ -1,3 +1,4 @@
+pub(crate) mod blur;
 pub(crate) mod border;
 pub(crate) mod brush;
 pub(crate) mod color;
@@ -0,0 +1,187 @@
+use crate::elements::border::GsBorderRadius;
+use crate::elements::rect::GsRect;
+use crate::elements::text::GsText;
+use crate::scene::draw_rounded_rect;
+use crate::CairoBackend;
+use gosub_interface::render_backend::{RenderBlurredRect, RenderText, ShadowClip, FP};
+
+/// Blurred shapes and text. Cairo has no blur filter, so they are drawn into an image surface that is blurred with
+/// three box blurs, which comes close to a gaussian blur.
+pub struct GsBlur;
+
+/// Largest image surface that is blurred. Larger shadows are painted without a blur.
+const MAX_PIXELS: i64 = 16 * 1024 * 1024;
+
+impl GsBlur {
+    pub(crate) fn render_rect(obj: &RenderBlurredRect<CairoBackend>, cr: &cairo::Context) {
+        let extent = 3.0 * f64::from(obj.std_dev.max(0.0));
+        let color = obj.color;
+
+        _ = cr.save();
+
+        let (area, inset) = match &obj.clip {
+            ShadowClip::None => (grow(&obj.rect, extent), false),
+            ShadowClip::Outside(shape, radius) => {
+                // Everything around the box, which is a hole in the clip
+                let area = grow(&obj.rect, extent);
+                cr.rectangle(area.0, area.1, area.2, area.3);
+                shape_path(cr, shape, radius.as_ref());
+                cr.set_fill_rule(cairo::FillRule::EvenOdd);
+                cr.clip();
+
+                (area, false)
+            }
+            ShadowClip::Inset(shape, radius) => {
+                shape_path(cr, shape, radius.as_ref());
+                cr.clip();
+
+                (grow(shape, extent), true)
+            }
+        };
+
+        blurred(cr, area, obj.std_dev, |target| {
+            target.set_source_rgba(color.r, color.g, color.b, color.a);
+            if inset {
+                // The area around the rectangle is painted, the rectangle itself is cut out
+                _ = target.paint();
+                target.set_operator(cairo::Operator::Clear);
+            }
+
+            shape_path(target, &obj.rect, obj.radius.as_ref());
+            _ = target.fill();
+        });
+
+        _ = cr.restore();
+    }
+
+    pub(crate) fn render_text(obj: &RenderText<CairoBackend>, std_dev: FP, cr: &cairo::Context) {
+        // Glyphs can extend beyond the box of the text, like descenders and italics
+        let extent = 3.0 * f64::from(std_dev.max(0.0)) + obj.rect.height;
+
+        blurred(cr, grow(&obj.rect, extent), std_dev, |target| {
+            GsText::render(obj, target)
+        });
+    }
+}
+
+/// Returns the area of the rectangle as `(x, y, width, height)`, grown by `extent` on all sides
+fn grow(rect: &GsRect, extent: f64) -> (f64, f64, f64, f64) {
+    (
+        rect.x - extent,
+        rect.y - extent,
+        rect.width + 2.0 * extent,
+        rect.height + 2.0 * extent,
+    )
+}
+
+fn shape_path(cr: &cairo::Context, rect: &GsRect, radius: Option<&GsBorderRadius>) {
+    match radius {
+        Some(radius) => draw_rounded_rect(
+            cr,
+            rect.x as FP,
+            rect.y as FP,
+            rect.width as FP,
+            rect.height as FP,
+            radius,
+        ),
+        None => cr.rectangle(rect.x, rect.y, rect.width, rect.height),
+    }
+}
+
+/// Draws into an image surface that covers `area`, blurs it and paints it onto the context. The drawing is done in the
+/// coordinates of the context.
+fn blurred(cr: &cairo::Context, area: (f64, f64, f64, f64), std_dev: FP, draw: impl FnOnce(&cairo::Context)) {
+    let x = area.0.floor();
+    let y = area.1.floor();
+    let width = (area.0 + area.2 - x).ceil() as i32;
+    let height = (area.1 + area.3 - y).ceil() as i32;
+
+    if width <= 0 || height <= 0 {
+        return;
+    }
+
+    let surface = (std_dev > 0.0 && i64::from(width) * i64::from(height) <= MAX_PIXELS)
+        .then(|| cairo::ImageSurface::create(cairo::Format::ARgb32, width, height).ok())
+        .flatten();
+
+    let Some(mut surface) = surface else {
+        // Drawn in a group of its own, so clearing parts of it does not clear the content below
+        cr.push_group();
+        draw(cr);
+        _ = cr.pop_group_to_source();
+        _ = cr.paint();
+        return;
+    };
+
+    {
+        let Ok(target) = cairo::Context::new(&surface) else {
+            return;
+        };
+        target.translate(-x, -y);
+        draw(&target);
+    }
+
+    let stride = surface.stride() as usize;
+    if let Ok(mut data) = surface.data() {
+        blur(&mut data, width as usize, height as usize, stride, std_dev);
+    }
+
+    _ = cr.set_source_surface(&surface, x, y);
+    _ = cr.paint();
+}
+
+/// Blurs premultiplied ARGB pixels with three box blurs in both directions
+fn blur(data: &mut [u8], width: usize, height: usize, stride: usize, std_dev: FP) {
+    // Three box blurs of `2 * radius + 1` pixels have a variance of `((2 * radius + 1)² - 1) / 4`
+    let radius = (((4.0 * std_dev * std_dev + 1.0).sqrt() - 1.0) / 2.0).round() as usize;
+    if radius == 0 {
+        return;
+    }
+
+    let mut line = Vec::new();
+    for _ in 0..3 {
+        for row in 0..height {
+            box_blur(data, row * stride, 4, width, radius, &mut line);
+        }
+        for col in 0..width {
+            box_blur(data, col * 4, stride, height, radius, &mut line);
+        }
+    }
+}
+
+/// Blurs `len` pixels that start at byte `start` and are `step` bytes apart with a box of `2 * radius + 1` pixels.
+/// Pixels outside of the surface are transparent.
+fn box_blur(data: &mut [u8], start: usize, step: usize, len: usize, radius: usize, line: &mut Vec<[u8; 4]>) {
+    line.clear();
+    line.extend((0..len).map(|idx| {
+        let pos = start + idx * step;
+        [data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]
+    }));
+
+    let window = (2 * radius + 1) as u32;
+    let mut sum = [0u32; 4];
+
+    for pixel in line.iter().take(radius) {
+        for (total, channel) in sum.iter_mut().zip(pixel) {
+            *total += u32::from(*channel);
+        }
+    }
+
+    for idx in 0..len {
+        if let Some(pixel) = line.get(idx + radius) {
+            for (total, channel) in sum.iter_mut().zip(pixel) {
+                *total += u32::from(*channel);
+            }
+        }
+        if idx > radius {
+            for (total, channel) in sum.iter_mut().zip(&line[idx - radius - 1]) {
+                *total -= u32::from(*channel);
+            }
+        }
+
+        let pos = start + idx * step;
+        for (channel, total) in data[pos..pos + 4].iter_mut().zip(sum) {
+            *channel = ((total + window / 2) / window) as u8;
+        }
+    }
+}
@@ -2,7 +2,7 @@ use crate::CairoBackend;
 use gosub_interface::render_backend::{ColorStops, Gradient as TGradient};
 use gosub_shared::geo::{Point as GsPoint, FP};
 use peniko::color::{AlphaColor, DynamicColor, Srgb};
-use peniko::{ColorStop, Gradient as ExtGradient};
+use peniko::{ColorStop, Extend, Gradient as ExtGradient};
 use smallvec::SmallVec;
 
 #[allow(unused)]
@@ -57,6 +57,10 @@ impl TGradient<CairoBackend> for GsGradient {
 
         GsGradient::new(g)
     }
+
+    fn repeat(self) -> Self {
+        GsGradient::new(self.gradient.with_extend(Extend::Repeat))
+    }
 }
 
 fn to_stop_vec(stops: ColorStops<CairoBackend>) -> SmallVec<[ColorStop; 4]> {
@@ -1,3 +1,4 @@
+use crate::elements::blur::GsBlur;
 use crate::elements::border::GsBorderRadius;
 use crate::elements::rect::GsRect;
 use crate::elements::text::GsText;
@@ -5,7 +6,8 @@ use crate::elements::transform::GsTransform;
 use crate::CairoBackend;
 use gosub_interface::font::FontBlob;
 use gosub_interface::render_backend::{
-    Point, Radius, RenderBackend, RenderRect, RenderText, Scene as TScene, Transform as TTransform, FP,
+    BlendMode, ClipShape, Point, Radius, RenderBackend, RenderBlurredRect, RenderRect, RenderText, Scene as TScene,
+    Transform as TTransform, FP,
 };
 use std::fmt::{Debug, Formatter};
 
@@ -16,6 +18,10 @@ pub enum SceneCommand {
     Rectangle(Box<RenderRect<CairoBackend>>),
     // Draw a text
     Text(Box<RenderText<CairoBackend>>),
+    // Draw a blurred rectangle, used for box shadows
+    BlurredRect(Box<RenderBlurredRect<CairoBackend>>),
+    // Draw a text blurred with the given standard deviation, used for text shadows
+    BlurredText(Box<RenderText<CairoBackend>>, FP),
     // Draw a simple text without too much decoration and in a single font / color
     SimpleText {
         text: String,
@@ -27,6 +33,13 @@ pub enum SceneCommand {
         children: Vec<SceneCommand>,
         transform: GsTransform,
     },
+    // Draw a list of commands clipped to a shape, and composite them with an opacity and blend mode
+    Layer {
+        children: Vec<SceneCommand>,
+        clip: Box<ClipShape<CairoBackend>>,
+        opacity: FP,
+        blend_mode: BlendMode,
+    },
 }
 
 impl SceneCommand {
@@ -56,11 +69,29 @@ impl Debug for SceneCommand {
         match self {
             SceneCommand::Rectangle(rect) => f.debug_struct("Rectangle").field("rect", &rect).finish(),
             SceneCommand::Text(text) => f.debug_struct("Text").field("text", &text).finish(),
+            SceneCommand::BlurredRect(rect) => f.debug_struct("BlurredRect").field("rect", &rect).finish(),
+            SceneCommand::BlurredText(text, std_dev) => f
+                .debug_struct("BlurredText")
+                .field("text", &text)
+                .field("std_dev", &std_dev)
+                .finish(),
             SceneCommand::Group { children, transform } => f
                 .debug_struct("Group")
                 .field("children", &children)
                 .field("transform", &transform)
                 .finish(),
+            SceneCommand::Layer {
+                children,
+                clip,
+                opacity,
+                blend_mode,
+            } => f
+                .debug_struct("Layer")
+                .field("children", &children)
+                .field("clip", &clip)
+                .field("opacity", opacity)
+                .field("blend_mode", blend_mode)
+                .finish(),
             SceneCommand::SimpleText { text, font, pos } => f
                 .debug_struct("SimpleText")
                 .field("text", text)
@@ -76,6 +107,8 @@ impl Debug for SceneCommand {
 pub struct Scene {
     /// Root node is always a `SceneCommand::Group()`
     pub(crate) root: SceneCommand,
+    /// Layers that are pushed but not popped yet. New commands are added to the last one.
+    pub(crate) layers: Vec<SceneCommand>,
 }
 
 impl Default for Scene {
@@ -89,6 +122,21 @@ impl Scene {
     pub fn new() -> Self {
         Self {
             root: SceneCommand::new_group(),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Returns the list that new commands are added to
+    fn current_children(&mut self) -> Option<&mut Vec<SceneCommand>> {
+        match self.layers.last_mut().unwrap_or(&mut self.root) {
+            SceneCommand::Group { children, .. } | SceneCommand::Layer { children, .. } => Some(children),
+            _ => None,
+        }
+    }
+
+    fn push_command(&mut self, command: SceneCommand) {
+        if let Some(children) = self.current_children() {
+            children.push(command);
         }
     }
 
@@ -98,12 +146,34 @@ impl Scene {
 
     fn render_scene_command(scene_command: &SceneCommand, cr: &cairo::Context) {
         match scene_command {
-            SceneCommand::Group { children, .. } => {
-                // let affine = transform.map(|t| t.0).unwrap_or_default();
-                // cr.transform(affine);
+            SceneCommand::Group { children, transform } => {
+                let [a, b, c, d, e, f] = transform.as_coeffs();
+
+                _ = cr.save();
+                cr.transform(cairo::Matrix::new(a, b, c, d, e, f));
+                for child in children {
+                    Self::render_scene_command(child, cr);
+                }
+                _ = cr.restore();
+            }
+            SceneCommand::Layer {
+                children,
+                clip,
+                opacity,
+                blend_mode,
+            } => {
+                _ = cr.save();
+                Self::clip_to_shape(clip, cr);
+
+                cr.push_group();
                 for child in children {
                     Self::render_scene_command(child, cr);
                 }
+                _ = cr.pop_group_to_source();
+
+                cr.set_operator(operator(*blend_mode));
+                _ = cr.paint_with_alpha(f64::from(*opacity));
+                _ = cr.restore();
             }
             SceneCommand::Rectangle(rect) => {
                 GsRect::render(rect, cr);
@@ -111,6 +181,12 @@ impl Scene {
             SceneCommand::Text(text) => {
                 GsText::render(text, cr);
             }
+            SceneCommand::BlurredRect(rect) => {
+                GsBlur::render_rect(rect, cr);
+            }
+            SceneCommand::BlurredText(text, std_dev) => {
+                GsBlur::render_text(text, *std_dev, cr);
+            }
             SceneCommand::SimpleText {
                 text: _,
                 font: _,
@@ -129,53 +205,125 @@ impl Scene {
             }
         }
     }
+
+    fn clip_to_shape(clip: &ClipShape<CairoBackend>, cr: &cairo::Context) {
+        match clip {
+            ClipShape::None => return,
+            ClipShape::Rect(rect, radius) => match radius {
+                Some(radius) => draw_rounded_rect(
+                    cr,
+                    rect.x as FP,
+                    rect.y as FP,
+                    rect.width as FP,
+                    rect.height as FP,
+                    radius,
+                ),
+                None => cr.rectangle(rect.x, rect.y, rect.width, rect.height),
+            },
+            ClipShape::Ellipse {
+                center,
+                radius_x,
+                radius_y,
+            } => {
+                if *radius_x <= 0.0 || *radius_y <= 0.0 {
+                    // Nothing of the content is visible
+                    cr.rectangle(0.0, 0.0, 0.0, 0.0);
+                } else {
+                    // The path is kept in device space, so it stays an ellipse when the scale is restored
+                    _ = cr.save();
+                    cr.translate(f64::from(center.x), f64::from(center.y));
+                    cr.scale(f64::from(*radius_x), f64::from(*radius_y));
+                    cr.arc(0.0, 0.0, 1.0, 0.0, 2.0 * std::f64::consts::PI);
+                    _ = cr.restore();
+                }
+            }
+        }
+
+        cr.clip();
+    }
 }
 
 impl Clone for Scene {
     fn clone(&self) -> Self {
         Self {
             root: self.root.clone(),
+            layers: self.layers.clone(),
         }
     }
 }
 
 impl TScene<CairoBackend> for Scene {
     fn draw_rect(&mut self, rect: &RenderRect<CairoBackend>) {
-        if let SceneCommand::Group { ref mut children, .. } = &mut self.root {
-            children.push(SceneCommand::Rectangle(Box::new(rect.clone())));
-        }
+        self.push_command(SceneCommand::Rectangle(Box::new(rect.clone())));
     }
 
     fn draw_text(&mut self, text: &RenderText<CairoBackend>) {
-        if let SceneCommand::Group { ref mut children, .. } = &mut self.root {
-            children.push(SceneCommand::Text(Box::new(text.clone())));
-        }
+        self.push_command(SceneCommand::Text(Box::new(text.clone())));
+    }
+
+    fn draw_blurred_rect(&mut self, rect: &RenderBlurredRect<CairoBackend>) {
+        self.push_command(SceneCommand::BlurredRect(Box::new(rect.clone())));
+    }
+
+    fn draw_blurred_text(&mut self, text: &RenderText<CairoBackend>, std_dev: FP) {
+        self.push_command(SceneCommand::BlurredText(Box::new(text.clone()), std_dev));
     }
 
     fn debug_draw_simple_text(&mut self, text: &str, pos: Point, size: FP) {
-        if let SceneCommand::Group { ref mut children, .. } = &mut self.root {
-            children.push(SceneCommand::simple_text(text.to_string(), pos, size));
-        }
+        self.push_command(SceneCommand::simple_text(text.to_string(), pos, size));
     }
 
     fn apply_scene(&mut self, scene: &<CairoBackend as RenderBackend>::Scene, transform: Option<GsTransform>) {
-        if let SceneCommand::Group { ref mut children, .. } = &mut self.root {
-            children.push(SceneCommand::Group {
-                children: vec![scene.root.clone()],
-                transform: transform.unwrap_or(GsTransform::IDENTITY),
-            });
-        }
+        self.push_command(SceneCommand::Group {
+            children: vec![scene.root.clone()],
+            transform: transform.unwrap_or(GsTransform::IDENTITY),
+        });
     }
 
     fn reset(&mut self) {
         self.root = SceneCommand::new_group();
+        self.layers.clear();
     }
 
-    fn new() -> Self {
-        Self {
-            root: SceneCommand::new_group(),
+    fn push_layer(&mut self, clip: &ClipShape<CairoBackend>, opacity: FP, blend_mode: BlendMode) {
+        self.layers.push(SceneCommand::Layer {
+            children: vec![],
+            clip: Box::new(clip.clone()),
+            opacity,
+            blend_mode,
+        });
+    }
+
+    fn pop_layer(&mut self) {
+        if let Some(layer) = self.layers.pop() {
+            self.push_command(layer);
         }
     }
+
+    fn new() -> Self {
+        Scene::new()
+    }
+}
+
+fn operator(blend_mode: BlendMode) -> cairo::Operator {
+    match blend_mode {
+        BlendMode::Normal => cairo::Operator::Over,
+        BlendMode::Multiply => cairo::Operator::Multiply,
+        BlendMode::Screen => cairo::Operator::Screen,
+        BlendMode::Overlay => cairo::Operator::Overlay,
+        BlendMode::Darken => cairo::Operator::Darken,
+        BlendMode::Lighten => cairo::Operator::Lighten,
+        BlendMode::ColorDodge => cairo::Operator::ColorDodge,
+        BlendMode::ColorBurn => cairo::Operator::ColorBurn,
+        BlendMode::HardLight => cairo::Operator::HardLight,
+        BlendMode::SoftLight => cairo::Operator::SoftLight,
+        BlendMode::Difference => cairo::Operator::Difference,
+        BlendMode::Exclusion => cairo::Operator::Exclusion,
+        BlendMode::Hue => cairo::Operator::HslHue,
+        BlendMode::Saturation => cairo::Operator::HslSaturation,
+        BlendMode::Color => cairo::Operator::HslColor,
+        BlendMode::Luminosity => cairo::Operator::HslLuminosity,
+    }
 }
 
 /// Draws a rounded rectangle with specified border radii.
@@ -197,10 +345,11 @@ pub fn draw_rounded_rect(cr: &cairo::Context, x: FP, y: FP, width: FP, height: F
     // Top edge and top-right corner
     cr.line_to(f64::from(x + width - tr_rx), f64::from(y));
     if tr_rx > 0.0 && tr_ry > 0.0 {
-        cr.arc(
+        elliptical_arc(
+            cr,
             f64::from(x + width - tr_rx),
             f64::from(y + tr_ry),
-            f64::from(tr_rx.min(tr_ry)),
+            (tr_rx, tr_ry),
             -std::f64::consts::FRAC_PI_2,
             0.0,
         );
@@ -209,10 +358,11 @@ pub fn draw_rounded_rect(cr: &cairo::Context, x: FP, y: FP, width: FP, height: F
     // Right edge and bottom-right corner
     cr.line_to(f64::from(x + width), f64::from(y + height - br_ry));
     if br_rx > 0.0 && br_ry > 0.0 {
-        cr.arc(
+        elliptical_arc(
+            cr,
             f64::from(x + width - br_rx),
             f64::from(y + height - br_ry),
-            f64::from(br_rx.min(br_ry)),
+            (br_rx, br_ry),
             0.0,
             std::f64::consts::FRAC_PI_2,
         );
@@ -221,10 +371,11 @@ pub fn draw_rounded_rect(cr: &cairo::Context, x: FP, y: FP, width: FP, height: F
     // Bottom edge and bottom-left corner
     cr.line_to(f64::from(x + bl_rx), f64::from(y + height));
     if bl_rx > 0.0 && bl_ry > 0.0 {
-        cr.arc(
+        elliptical_arc(
+            cr,
             f64::from(x + bl_rx),
             f64::from(y + height - bl_ry),
-            f64::from(bl_rx.min(bl_ry)),
+            (bl_rx, bl_ry),
             std::f64::consts::FRAC_PI_2,
             std::f64::consts::PI,
         );
@@ -233,10 +384,11 @@ pub fn draw_rounded_rect(cr: &cairo::Context, x: FP, y: FP, width: FP, height: F
     // Left edge and top-left corner
     cr.line_to(f64::from(x), f64::from(y + tl_ry));
     if tl_rx > 0.0 && tl_ry > 0.0 {
-        cr.arc(
+        elliptical_arc(
+            cr,
             f64::from(x + tl_rx),
             f64::from(y + tl_ry),
-            f64::from(tl_rx.min(tl_ry)),
+            (tl_rx, tl_ry),
             std::f64::consts::PI,
             3.0 * std::f64::consts::FRAC_PI_2,
         );
@@ -244,3 +396,15 @@ pub fn draw_rounded_rect(cr: &cairo::Context, x: FP, y: FP, width: FP, height: F
 
     cr.close_path();
 }
+
+/// Adds a part of an ellipse with the radii `(rx, ry)` to the path. Cairo only draws circular arcs, so the arc is drawn
+/// as a unit circle that is scaled to the ellipse.
+fn elliptical_arc(cr: &cairo::Context, cx: f64, cy: f64, (rx, ry): (FP, FP), angle1: f64, angle2: f64) {
+    let matrix = cr.matrix();
+
+    cr.translate(cx, cy);
+    cr.scale(f64::from(rx), f64::from(ry));
+    cr.arc(0.0, 0.0, 1.0, angle1, angle2);
+
+    cr.set_matrix(matrix);
+}
@@ -1,5 +1,6 @@
 use log::warn;
 
+use crate::font_face::parse_font_face;
 use crate::node::{Node as CssNode, NodeType};
 use crate::stylesheet::{
     AttributeSelector, Combinator, CssDeclaration, CssRule, CssSelector, CssSelectorPart, CssStylesheet, CssValue,
@@ -71,9 +72,27 @@ pub fn convert_ast_to_stylesheet(css_ast: &CssNode, origin: CssOrigin, url: &str
         origin,
         url: url.to_string(),
         parse_log: vec![],
+        font_faces: vec![],
     };
 
     for node in css_ast.as_stylesheet() {
+        if node.is_at_rule() {
+            let (name, _, block) = node.as_at_rule();
+            if !name.eq_ignore_ascii_case("font-face") {
+                continue;
+            }
+
+            let Some(block) = block.as_ref().filter(|block| block.is_block()) else {
+                continue;
+            };
+
+            match parse_font_face(&convert_declarations(block)) {
+                Some(font_face) => sheet.font_faces.push(font_face),
+                None => warn!("Ignoring @font-face rule without font-family or src"),
+            }
+            continue;
+        }
+
         if !node.is_rule() {
             continue;
         }
@@ -176,48 +195,56 @@ pub fn convert_ast_to_stylesheet(css_ast: &CssNode, origin: CssOrigin, url: &str
             rule.selectors.push(selector);
         }
 
-        if let Some(declaration) = declarations {
-            if !declaration.is_block() {
+        if let Some(block) = declarations {
+            if !block.is_block() {
                 continue;
             }
 
-            let block = declaration.as_block();
-            for declaration in block {
-                if !declaration.is_declaration() {
-                    continue;
-                }
+            rule.declarations = convert_declarations(block);
+        }
 
-                let (property, nodes, important) = declaration.as_declaration();
+        sheet.rules.push(rule);
+    }
+    Ok(sheet)
+}
 
-                // Convert the nodes into CSS Values
-                let mut css_values = vec![];
-                for node in nodes {
-                    if let Ok(value) = CssValue::parse_ast_node(node) {
-                        css_values.push(value);
-                    }
-                }
+/// Converts the declarations in a block node into CSS declarations
+pub(crate) fn convert_declarations(block: &CssNode) -> Vec<CssDeclaration> {
+    let mut result = vec![];
 
-                if css_values.is_empty() {
-                    continue;
-                }
+    for declaration in block.as_block() {
+        if !declaration.is_declaration() {
+            continue;
+        }
 
-                let value = if css_values.len() == 1 {
-                    css_values.pop().expect("unreachable")
-                } else {
-                    CssValue::List(css_values)
-                };
-
-                rule.declarations.push(CssDeclaration {
-                    property: property.clone(),
-                    value,
-                    important: *important,
-                });
+        let (property, nodes, important) = declaration.as_declaration();
+
+        // Convert the nodes into CSS Values
+        let mut css_values = vec![];
+        for node in nodes {
+            if let Ok(value) = CssValue::parse_ast_node(node) {
+                css_values.push(value);
             }
         }
 
-        sheet.rules.push(rule);
+        if css_values.is_empty() {
+            continue;
+        }
+
+        let value = if css_values.len() == 1 {
+            css_values.pop().expect("unreachable")
+        } else {
+            CssValue::List(css_values)
+        };
+
+        result.push(CssDeclaration {
+            property: property.clone(),
+            value,
+            important: *important,
+        });
     }
-    Ok(sheet)
+
+    result
 }
 
 #[cfg(test)]
@@ -3,7 +3,7 @@ use std::fmt::Debug;
 use std::str::FromStr;
 
 use colors_transform::Color;
-use colors_transform::{AlphaColor, Hsl, Rgb};
+use colors_transform::{Hsl, Rgb};
 use lazy_static::lazy_static;
 // Values for this table is taken from https://www.w3.org/TR/CSS21/propidx.html
 // Probably not the complete list, but it will do for now
@@ -56,6 +56,9 @@ impl From<&str> for RgbColor {
             // @todo: implement currentcolor
             return RgbColor::default();
         }
+        if value == "transparent" {
+            return RgbColor::new(0.0, 0.0, 0.0, 0.0);
+        }
 
         if value.starts_with('#') {
             return parse_hex(value);
@@ -70,13 +73,14 @@ impl From<&str> for RgbColor {
             return RgbColor::new(rgb.get_red(), rgb.get_green(), rgb.get_blue(), 255.0);
         }
         if value.starts_with("rgba(") {
-            // Rgba function
-            let rgb = Rgb::from_str(value);
-            if rgb.is_err() {
+            // Rgba function. The parser only handles three components, so the alpha is split off first.
+            let Some((rgb, alpha)) = split_alpha(value, "rgb") else {
                 return RgbColor::default();
-            }
-            let rgb = rgb.unwrap();
-            return RgbColor::new(rgb.get_red(), rgb.get_green(), rgb.get_blue(), rgb.get_alpha());
+            };
+            let Ok(rgb) = Rgb::from_str(&rgb) else {
+                return RgbColor::default();
+            };
+            return RgbColor::new(rgb.get_red(), rgb.get_green(), rgb.get_blue(), alpha * 255.0);
         }
         if value.starts_with("hsl(") {
             let hsl = Hsl::from_str(value);
@@ -87,14 +91,15 @@ impl From<&str> for RgbColor {
             return RgbColor::new(rgb.get_red(), rgb.get_green(), rgb.get_blue(), 255.0);
         }
         if value.starts_with("hsla(") {
-            // @TODO: hsla() does not work properly
             // HSLA function
-            let hsl = Hsl::from_str(value);
-            if hsl.is_err() {
+            let Some((hsl, alpha)) = split_alpha(value, "hsl") else {
                 return RgbColor::default();
-            }
-            let rgb: Rgb = hsl.unwrap().to_rgb();
-            return RgbColor::new(rgb.get_red(), rgb.get_green(), rgb.get_blue(), rgb.get_alpha());
+            };
+            let Ok(hsl) = Hsl::from_str(&hsl) else {
+                return RgbColor::default();
+            };
+            let rgb: Rgb = hsl.to_rgb();
+            return RgbColor::new(rgb.get_red(), rgb.get_green(), rgb.get_blue(), alpha * 255.0);
         }
 
         get_hex_color_from_name(value).map_or(RgbColor::default(), parse_hex)
@@ -120,6 +125,21 @@ fn is_hex(value: &str) -> bool {
     value.chars().skip(1).all(|c| c.is_ascii_hexdigit())
 }
 
+/// Splits `rgba(r, g, b, a)` or `hsla(h, s, l, a)` into the three component function `name(r, g, b)` and the
+/// alpha in the range 0.0 to 1.0. The alpha can be a number or a percentage.
+fn split_alpha(value: &str, name: &str) -> Option<(String, f32)> {
+    let args = value.split_once('(')?.1.strip_suffix(')')?;
+    let (components, alpha) = args.rsplit_once(',')?;
+
+    let alpha = alpha.trim();
+    let alpha = match alpha.strip_suffix('%') {
+        Some(percentage) => percentage.trim().parse::<f32>().ok()? / 100.0,
+        None => alpha.parse::<f32>().ok()?,
+    };
+
+    Some((format!("{name}({components})"), alpha.clamp(0.0, 1.0)))
+}
+
 fn parse_hex(value: &str) -> RgbColor {
     const R: usize = 0;
     const G: usize = 1;
@@ -1058,6 +1078,20 @@ mod tests {
         assert_eq!(color.a, 255.0);
     }
 
+    #[test]
+    fn color_functions() {
+        let color = super::RgbColor::from("rgba(0, 255, 0, 0.5)");
+        assert_eq!(color.g, 255.0);
+        assert_eq!(color.a, 127.5);
+
+        let color = super::RgbColor::from("hsla(120, 100%, 50%, 25%)");
+        assert_eq!(color.g, 255.0);
+        assert_eq!(color.a, 63.75);
+
+        let color = super::RgbColor::from("transparent");
+        assert_eq!(color.a, 0.0);
+    }
+
     #[test]
     fn color_names() {
         let color = super::RgbColor::from("red");
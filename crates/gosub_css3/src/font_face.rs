@@ -0,0 +1,283 @@
+//! Conversion of `@font-face` rules into font face descriptors
+//! (<https://www.w3.org/TR/css-fonts-4/#font-face-rule>).
+
+use crate::stylesheet::{CssDeclaration, CssValue};
+use gosub_interface::font::{FontDisplay, FontFaceDescriptor, FontFaceSource, FontStyle};
+
+/// Converts the declarations of a `@font-face` rule into a descriptor. Returns None when the rule is invalid,
+/// which is the case when either the `font-family` or the `src` descriptor is missing.
+#[must_use]
+pub fn parse_font_face(declarations: &[CssDeclaration]) -> Option<FontFaceDescriptor> {
+    let mut family = None;
+    let mut sources = Vec::new();
+    let mut descriptor = FontFaceDescriptor::new("");
+
+    for declaration in declarations {
+        let value = &declaration.value;
+
+        match declaration.property.as_str() {
+            "font-family" => family = parse_family(value),
+            "src" => sources = parse_sources(value),
+            "font-style" => descriptor.style = parse_style(value),
+            "font-weight" => {
+                if let Some(weight) = parse_range(value, parse_weight) {
+                    descriptor.weight = (weight.0 as i32, weight.1 as i32);
+                }
+            }
+            "font-stretch" => {
+                if let Some(stretch) = parse_range(value, parse_stretch) {
+                    descriptor.stretch = stretch;
+                }
+            }
+            "unicode-range" => {
+                if let CssValue::String(ranges) = value {
+                    descriptor.unicode_range = parse_unicode_range(ranges);
+                }
+            }
+            "font-display" => descriptor.display = parse_display(value),
+            _ => {}
+        }
+    }
+
+    if sources.is_empty() {
+        return None;
+    }
+
+    descriptor.family = family?;
+    descriptor.sources = sources;
+
+    Some(descriptor)
+}
+
+/// Family names can be a string, or a sequence of identifiers that are joined by spaces
+fn parse_family(value: &CssValue) -> Option<String> {
+    join_strings(value.to_slice())
+}
+
+/// Joins all string values by spaces. Returns None when there are no strings.
+fn join_strings(values: &[CssValue]) -> Option<String> {
+    let joined = values
+        .iter()
+        .filter_map(|v| match v {
+            CssValue::String(s) => Some(s.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if joined.is_empty() {
+        None
+    } else {
+        Some(joined)
+    }
+}
+
+/// Parses the comma separated list of `url() format()` and `local()` sources
+fn parse_sources(value: &CssValue) -> Vec<FontFaceSource> {
+    let mut sources = Vec::new();
+
+    for source in value.to_slice().split(|v| *v == CssValue::Comma) {
+        let mut parts = source.iter();
+
+        match parts.next() {
+            Some(CssValue::Function(name, args)) if name.eq_ignore_ascii_case("url") => {
+                let Some(url) = join_strings(args) else {
+                    continue;
+                };
+
+                let format = parts.find_map(|v| match v {
+                    CssValue::Function(name, args) if name.eq_ignore_ascii_case("format") => join_strings(args),
+                    _ => None,
+                });
+
+                sources.push(FontFaceSource::Url { url, format });
+            }
+            Some(CssValue::Function(name, args)) if name.eq_ignore_ascii_case("local") => {
+                if let Some(name) = join_strings(args) {
+                    sources.push(FontFaceSource::Local(name));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    sources
+}
+
+fn parse_style(value: &CssValue) -> FontStyle {
+    match value.to_slice().first() {
+        Some(CssValue::String(s)) if s.eq_ignore_ascii_case("italic") => FontStyle::Italic,
+        Some(CssValue::String(s)) if s.eq_ignore_ascii_case("oblique") => FontStyle::Oblique,
+        _ => FontStyle::Normal,
+    }
+}
+
+fn parse_display(value: &CssValue) -> FontDisplay {
+    let CssValue::String(s) = value else {
+        return FontDisplay::Auto;
+    };
+
+    match s.as_str() {
+        "block" => FontDisplay::Block,
+        "swap" => FontDisplay::Swap,
+        "fallback" => FontDisplay::Fallback,
+        "optional" => FontDisplay::Optional,
+        _ => FontDisplay::Auto,
+    }
+}
+
+/// Parses a single value or a `<min> <max>` range. A reversed range is swapped as defined by the spec.
+fn parse_range(value: &CssValue, parse: fn(&CssValue) -> Option<f32>) -> Option<(f32, f32)> {
+    let values: Vec<f32> = value.to_slice().iter().filter_map(parse).collect();
+
+    match values.as_slice() {
+        [v] => Some((*v, *v)),
+        [min, max] => Some((min.min(*max), min.max(*max))),
+        _ => None,
+    }
+}
+
+fn parse_weight(value: &CssValue) -> Option<f32> {
+    match value {
+        CssValue::Number(n) => Some(n.clamp(1.0, 1000.0)),
+        CssValue::String(s) if s == "normal" => Some(400.0),
+        CssValue::String(s) if s == "bold" => Some(700.0),
+        _ => None,
+    }
+}
+
+fn parse_stretch(value: &CssValue) -> Option<f32> {
+    match value {
+        CssValue::Percentage(p) => Some(p / 100.0),
+        CssValue::String(s) => match s.as_str() {
+            "ultra-condensed" => Some(0.5),
+            "extra-condensed" => Some(0.625),
+            "condensed" => Some(0.75),
+            "semi-condensed" => Some(0.875),
+            "normal" => Some(1.0),
+            "semi-expanded" => Some(1.125),
+            "expanded" => Some(1.25),
+            "extra-expanded" => Some(1.5),
+            "ultra-expanded" => Some(2.0),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Parses a comma separated list of unicode ranges. Each range can be a single codepoint (`U+26`), a
+/// range (`U+0-7F`) or a wildcard range (`U+4??`). Invalid ranges are skipped.
+#[must_use]
+pub fn parse_unicode_range(value: &str) -> Vec<(u32, u32)> {
+    value
+        .split(',')
+        .filter_map(|range| parse_single_range(range.trim()))
+        .collect()
+}
+
+fn parse_single_range(range: &str) -> Option<(u32, u32)> {
+    let range = range.strip_prefix("U+").or_else(|| range.strip_prefix("u+"))?;
+
+    let (start, end) = if let Some((start, end)) = range.split_once('-') {
+        (parse_hex(start)?, parse_hex(end)?)
+    } else if range.contains('?') {
+        let digits = range.trim_end_matches('?');
+        if digits.contains('?') {
+            return None;
+        }
+        let wildcards = range.len() - digits.len();
+
+        let start: String = digits.chars().chain(std::iter::repeat_n('0', wildcards)).collect();
+        let end: String = digits.chars().chain(std::iter::repeat_n('F', wildcards)).collect();
+
+        (parse_hex(&start)?, parse_hex(&end)?)
+    } else {
+        let cp = parse_hex(range)?;
+        (cp, cp)
+    };
+
+    if start > end || start > 0x10FFFF {
+        return None;
+    }
+
+    Some((start, end.min(0x10FFFF)))
+}
+
+fn parse_hex(value: &str) -> Option<u32> {
+    if value.is_empty() || value.len() > 6 {
+        return None;
+    }
+
+    u32::from_str_radix(value, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Css3;
+    use gosub_interface::css3::CssOrigin;
+    use gosub_shared::config::ParserConfig;
+
+    #[test]
+    fn unicode_ranges() {
+        assert_eq!(parse_unicode_range("U+26"), vec![(0x26, 0x26)]);
+        assert_eq!(
+            parse_unicode_range("U+0-7F, u+0025-00FF"),
+            vec![(0, 0x7F), (0x25, 0xFF)]
+        );
+        assert_eq!(parse_unicode_range("U+4??"), vec![(0x400, 0x4FF)]);
+        assert_eq!(
+            parse_unicode_range("U+FF-00, U+?4?, foo, U+1F600"),
+            vec![(0x1F600, 0x1F600)]
+        );
+    }
+
+    #[test]
+    fn font_face_rule() {
+        let stylesheet = Css3::parse_str(
+            r#"
+            @font-face {
+                font-family: "Open Sans";
+                src: url(/fonts/open-sans.woff2) format("woff2"), local(Open Sans Regular), url('open-sans.ttf');
+                font-weight: 700 300;
+                font-style: italic;
+                font-stretch: 75% 100%;
+                unicode-range: U+0000-00FF, U+0131;
+                font-display: swap;
+            }
+            @font-face { font-family: Missing Source; }
+            "#,
+            ParserConfig::default(),
+            CssOrigin::Author,
+            "test.css",
+        )
+        .unwrap();
+
+        assert_eq!(stylesheet.font_faces.len(), 1);
+        assert!(stylesheet.rules.is_empty());
+
+        let face = &stylesheet.font_faces[0];
+        assert_eq!(face.family, "Open Sans");
+        assert_eq!(
+            face.sources,
+            vec![
+                FontFaceSource::Url {
+                    url: "/fonts/open-sans.woff2".into(),
+                    format: Some("woff2".into()),
+                },
+                FontFaceSource::Local("Open Sans Regular".into()),
+                FontFaceSource::Url {
+                    url: "open-sans.ttf".into(),
+                    format: None,
+                },
+            ]
+        );
+        assert_eq!(face.weight, (300, 700));
+        assert_eq!(face.style, FontStyle::Italic);
+        assert_eq!(face.stretch, (0.75, 1.0));
+        assert_eq!(face.unicode_range, vec![(0, 0xFF), (0x131, 0x131)]);
+        assert_eq!(face.display, FontDisplay::Swap);
+        assert!(face.covers('a'));
+        assert!(!face.covers('\u{2603}'));
+    }
+}
@@ -1,7 +1,7 @@
 extern crate core;
 
-use crate::ast::convert_ast_to_stylesheet;
-use crate::stylesheet::CssStylesheet;
+use crate::ast::{convert_ast_to_stylesheet, convert_declarations};
+use crate::stylesheet::{CssLog, CssRule, CssStylesheet};
 use crate::tokenizer::Tokenizer;
 
 use gosub_interface::css3::CssOrigin;
@@ -17,6 +17,7 @@ pub mod ast;
 /// The original version can be found at <https://github.com/csstree/csstree>
 pub mod colors;
 pub mod errors;
+pub mod font_face;
 mod functions;
 #[allow(dead_code)]
 pub mod matcher;
@@ -77,6 +78,41 @@ impl<'stream> Css3<'stream> {
         Css3::new(stream, config, origin, source_url).parse()
     }
 
+    /// Parses the value of a `style` attribute. The declarations are returned as a stylesheet with a single rule
+    /// without any selectors, as they apply to the element itself. Errors are added to the parse log of the
+    /// stylesheet, so an invalid style attribute results in an empty rule.
+    #[must_use]
+    pub fn parse_inline_style(data: &str, config: ParserConfig, source_url: &str) -> CssStylesheet {
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.read_from_str(data, Some(Encoding::UTF8));
+        stream.close();
+
+        let mut sheet = CssStylesheet {
+            rules: vec![],
+            origin: CssOrigin::Author,
+            url: source_url.to_string(),
+            parse_log: vec![],
+            font_faces: vec![],
+        };
+
+        let mut parser = Css3::new(&mut stream, config, CssOrigin::Author, source_url);
+        let declarations = match parser.parse_declaration_list() {
+            Ok(block) => convert_declarations(&block),
+            Err(e) => {
+                let location = e.location.unwrap_or_default();
+                sheet.parse_log.push(CssLog::error(&e.message, location));
+                vec![]
+            }
+        };
+
+        sheet.rules.push(CssRule {
+            selectors: vec![],
+            declarations,
+        });
+
+        sheet
+    }
+
     fn parse(&mut self) -> CssResult<CssStylesheet> {
         if self.config.context != Context::Stylesheet {
             return Err(CssError::new("Expected a stylesheet context"));
@@ -124,6 +160,7 @@ pub fn load_default_useragent_stylesheet() -> CssStylesheet {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::stylesheet::CssValue;
     // use crate::walker::Walker;
     use simple_logger::SimpleLogger;
 
@@ -150,4 +187,28 @@ mod tests {
         // let w = Walker::new(&binding);
         // w.walk_stdout();
     }
+
+    #[test]
+    fn inline_style() {
+        let config = ParserConfig {
+            ignore_errors: true,
+            ..Default::default()
+        };
+
+        let sheet = Css3::parse_inline_style("color: green; margin: 1px 2px !important;; foo", config, "inline");
+        assert_eq!(sheet.rules.len(), 1);
+        assert!(sheet.rules[0].selectors().is_empty());
+
+        let declarations = sheet.rules[0].declarations();
+        assert_eq!(declarations.len(), 2);
+        assert_eq!(declarations[0].property, "color");
+        assert_eq!(declarations[0].value, CssValue::String("green".into()));
+        assert!(!declarations[0].important);
+        assert_eq!(declarations[1].property, "margin");
+        assert!(declarations[1].important);
+
+        let sheet = Css3::parse_inline_style("color: green", ParserConfig::default(), "inline");
+        assert_eq!(sheet.rules[0].declarations().len(), 1);
+        assert!(sheet.parse_log.is_empty());
+    }
 }
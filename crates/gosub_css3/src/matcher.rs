@@ -1,3 +1,5 @@
+pub(crate) mod background;
+pub(crate) mod border_radius;
 pub mod property_definitions;
 pub mod shorthands;
 pub mod styling;
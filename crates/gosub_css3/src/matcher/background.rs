@@ -0,0 +1,291 @@
+//! Expansion of the `background` shorthand. The components of a layer can be given in any order and most of them
+//! are optional, which is more than the generic shorthand resolver can handle, so the layers are split up here.
+
+use crate::stylesheet::CssValue;
+use gosub_interface::css3::CssValue as _;
+
+/// Longhands of the `background` shorthand with their initial values
+const LONGHANDS: [(&str, &[&str]); 8] = [
+    ("background-image", &["none"]),
+    ("background-position", &["0%", "0%"]),
+    ("background-size", &["auto"]),
+    ("background-repeat", &["repeat"]),
+    ("background-attachment", &["scroll"]),
+    ("background-origin", &["padding-box"]),
+    ("background-clip", &["border-box"]),
+    ("background-color", &["transparent"]),
+];
+
+const IMAGE: usize = 0;
+const POSITION: usize = 1;
+const SIZE: usize = 2;
+const REPEAT: usize = 3;
+const ATTACHMENT: usize = 4;
+const ORIGIN: usize = 5;
+const CLIP: usize = 6;
+const COLOR: usize = 7;
+
+/// Splits the value of the `background` shorthand into its longhands. Layers are comma separated in the longhands as
+/// well. Returns None when the value is not a valid `background`.
+pub(crate) fn expand_background(values: &[CssValue]) -> Option<Vec<(&'static str, CssValue)>> {
+    // Resolved functions are wrapped in a list of their own
+    let values = values
+        .iter()
+        .map(|value| match value {
+            CssValue::List(list) if list.len() == 1 => list[0].clone(),
+            value => value.clone(),
+        })
+        .collect::<Vec<_>>();
+    let layers = values
+        .split(|value| matches!(value, CssValue::Comma))
+        .collect::<Vec<_>>();
+
+    let mut longhands: [Vec<CssValue>; 8] = Default::default();
+
+    for (idx, layer) in layers.iter().enumerate() {
+        let is_final = idx == layers.len() - 1;
+        let components = expand_layer(layer, is_final)?;
+
+        for (longhand, component) in components.into_iter().enumerate() {
+            // The background color is only given once, for the final layer
+            if longhand == COLOR && !is_final {
+                continue;
+            }
+            if longhand != COLOR && idx > 0 {
+                longhands[longhand].push(CssValue::Comma);
+            }
+
+            let component = component.unwrap_or_else(|| initial_value(longhand));
+            longhands[longhand].extend(component);
+        }
+    }
+
+    Some(
+        LONGHANDS
+            .iter()
+            .zip(longhands)
+            .map(|((name, _), values)| (*name, CssValue::from_vec(values)))
+            .collect(),
+    )
+}
+
+/// Splits a single layer into its components, indexed like `LONGHANDS`. Components that are not given are None.
+fn expand_layer(layer: &[CssValue], is_final: bool) -> Option<[Option<Vec<CssValue>>; 8]> {
+    let mut components: [Option<Vec<CssValue>>; 8] = Default::default();
+    let mut boxes = Vec::new();
+
+    let mut idx = 0;
+    while idx < layer.len() {
+        let value = &layer[idx];
+
+        if is_position(value) {
+            if components[POSITION].is_some() {
+                return None;
+            }
+            let len = layer[idx..]
+                .iter()
+                .take(4)
+                .take_while(|value| is_position(value))
+                .count();
+            components[POSITION] = Some(layer[idx..idx + len].to_vec());
+            idx += len;
+
+            // The slash is parsed as `none`. It can only be followed by a size after the position.
+            if matches!(layer.get(idx), Some(CssValue::None)) && layer.get(idx + 1).is_some_and(is_size) {
+                let len = layer[idx + 1..]
+                    .iter()
+                    .take(2)
+                    .take_while(|value| is_size(value))
+                    .count();
+                components[SIZE] = Some(layer[idx + 1..idx + 1 + len].to_vec());
+                idx += len + 1;
+            }
+            continue;
+        }
+
+        let longhand = match value {
+            CssValue::None => IMAGE,
+            CssValue::Function(name, _) if name == "url" || name.ends_with("gradient") => IMAGE,
+            CssValue::String(keyword) => match keyword.as_str() {
+                "repeat-x" | "repeat-y" | "repeat" | "space" | "round" | "no-repeat" => REPEAT,
+                "scroll" | "fixed" | "local" => ATTACHMENT,
+                "border-box" | "padding-box" | "content-box" | "text" => {
+                    boxes.push(value.clone());
+                    idx += 1;
+                    continue;
+                }
+                _ if is_final && is_color(value) => COLOR,
+                _ => return None,
+            },
+            _ if is_final && is_color(value) => COLOR,
+            _ => return None,
+        };
+
+        if components[longhand].is_some() {
+            return None;
+        }
+
+        // The repeat style can be given for both axes
+        let len = match value {
+            CssValue::String(keyword) if longhand == REPEAT && !keyword.starts_with("repeat-") => layer[idx..]
+                .iter()
+                .take(2)
+                .take_while(|value| matches!(value, CssValue::String(v) if matches!(v.as_str(), "repeat" | "space" | "round" | "no-repeat")))
+                .count(),
+            _ => 1,
+        };
+
+        components[longhand] = Some(layer[idx..idx + len].to_vec());
+        idx += len;
+    }
+
+    // One box sets both the origin and the clip, with two boxes the first is the origin
+    match boxes.as_slice() {
+        [] => {}
+        [CssValue::String(keyword), ..] if keyword == "text" && boxes.len() == 1 => {
+            components[CLIP] = Some(boxes);
+        }
+        [single] => {
+            components[ORIGIN] = Some(vec![single.clone()]);
+            components[CLIP] = Some(vec![single.clone()]);
+        }
+        [origin, clip] if !matches!(origin, CssValue::String(keyword) if keyword == "text") => {
+            components[ORIGIN] = Some(vec![origin.clone()]);
+            components[CLIP] = Some(vec![clip.clone()]);
+        }
+        _ => return None,
+    }
+
+    Some(components)
+}
+
+fn initial_value(longhand: usize) -> Vec<CssValue> {
+    LONGHANDS[longhand]
+        .1
+        .iter()
+        .map(|value| match *value {
+            "none" => CssValue::None,
+            "0%" => CssValue::Percentage(0.0),
+            keyword => CssValue::String(keyword.to_string()),
+        })
+        .collect()
+}
+
+fn is_color(value: &CssValue) -> bool {
+    value.parse_color().is_some()
+}
+
+fn is_position(value: &CssValue) -> bool {
+    match value {
+        CssValue::String(keyword) => matches!(keyword.as_str(), "left" | "right" | "top" | "bottom" | "center"),
+        CssValue::Unit(..) | CssValue::Percentage(_) | CssValue::Zero => true,
+        CssValue::Number(number) => *number == 0.0,
+        _ => false,
+    }
+}
+
+fn is_size(value: &CssValue) -> bool {
+    match value {
+        CssValue::String(keyword) => matches!(keyword.as_str(), "auto" | "cover" | "contain"),
+        CssValue::Unit(..) | CssValue::Percentage(_) | CssValue::Zero => true,
+        CssValue::Number(number) => *number == 0.0,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyword(value: &str) -> CssValue {
+        CssValue::String(value.to_string())
+    }
+
+    fn longhand(longhands: &[(&'static str, CssValue)], name: &str) -> CssValue {
+        longhands
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value.clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn single_layer() {
+        let url = CssValue::Function("url".into(), vec![keyword("a.png")]);
+        let longhands = expand_background(&[
+            keyword("red"),
+            url.clone(),
+            keyword("right"),
+            CssValue::Unit(10.0, "px".into()),
+            keyword("top"),
+            CssValue::None,
+            CssValue::Percentage(50.0),
+            keyword("auto"),
+            keyword("no-repeat"),
+            keyword("fixed"),
+            keyword("content-box"),
+        ])
+        .unwrap();
+
+        assert_eq!(longhand(&longhands, "background-image"), url);
+        assert_eq!(longhand(&longhands, "background-color"), keyword("red"));
+        assert_eq!(
+            longhand(&longhands, "background-position"),
+            CssValue::List(vec![
+                keyword("right"),
+                CssValue::Unit(10.0, "px".into()),
+                keyword("top")
+            ])
+        );
+        assert_eq!(
+            longhand(&longhands, "background-size"),
+            CssValue::List(vec![CssValue::Percentage(50.0), keyword("auto")])
+        );
+        assert_eq!(longhand(&longhands, "background-repeat"), keyword("no-repeat"));
+        assert_eq!(longhand(&longhands, "background-attachment"), keyword("fixed"));
+        assert_eq!(longhand(&longhands, "background-origin"), keyword("content-box"));
+        assert_eq!(longhand(&longhands, "background-clip"), keyword("content-box"));
+    }
+
+    #[test]
+    fn multiple_layers() {
+        let gradient = CssValue::Function(
+            "linear-gradient".into(),
+            vec![keyword("red"), CssValue::Comma, keyword("blue")],
+        );
+        let longhands = expand_background(&[
+            gradient.clone(),
+            keyword("space"),
+            keyword("round"),
+            CssValue::Comma,
+            keyword("center"),
+            keyword("padding-box"),
+            keyword("text"),
+            keyword("blue"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            longhand(&longhands, "background-image"),
+            CssValue::List(vec![gradient, CssValue::Comma, CssValue::None])
+        );
+        assert_eq!(
+            longhand(&longhands, "background-repeat"),
+            CssValue::List(vec![
+                keyword("space"),
+                keyword("round"),
+                CssValue::Comma,
+                keyword("repeat")
+            ])
+        );
+        assert_eq!(
+            longhand(&longhands, "background-clip"),
+            CssValue::List(vec![keyword("border-box"), CssValue::Comma, keyword("text")])
+        );
+        assert_eq!(longhand(&longhands, "background-color"), keyword("blue"));
+
+        // A color is only allowed in the final layer
+        assert!(expand_background(&[keyword("red"), CssValue::Comma, keyword("blue")]).is_none());
+        assert!(expand_background(&[keyword("bogus")]).is_none());
+    }
+}
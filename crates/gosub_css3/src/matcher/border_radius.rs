@@ -0,0 +1,142 @@
+//! Expansion of the `border-radius` shorthand. The horizontal and vertical radii are given as two lists separated by
+//! a `/`, which both follow the order of the `margin` shorthand, so each corner combines a value of both lists.
+
+use crate::stylesheet::CssValue;
+
+/// Longhands of the `border-radius` shorthand, in the order of the `margin` shorthand
+const LONGHANDS: [&str; 4] = [
+    "border-top-left-radius",
+    "border-top-right-radius",
+    "border-bottom-right-radius",
+    "border-bottom-left-radius",
+];
+
+/// Splits the value of the `border-radius` shorthand into its longhands. A corner with different horizontal and
+/// vertical radii gets a list of both. Returns None when the value is not a valid `border-radius`.
+pub(crate) fn expand_border_radius(values: &[CssValue]) -> Option<Vec<(&'static str, CssValue)>> {
+    // The `/` is parsed as `CssValue::None`
+    let mut lists = values.split(|value| matches!(value, CssValue::None));
+
+    let horizontal = corners(lists.next()?)?;
+    let vertical = match lists.next() {
+        Some(list) => corners(list)?,
+        None => horizontal.clone(),
+    };
+    if lists.next().is_some() {
+        return None;
+    }
+
+    let longhands = LONGHANDS
+        .iter()
+        .zip(horizontal.into_iter().zip(vertical))
+        .map(|(name, (x, y))| {
+            let value = if x == y { x } else { CssValue::List(vec![x, y]) };
+            (*name, value)
+        })
+        .collect();
+
+    Some(longhands)
+}
+
+/// Returns the radii of the four corners of a list with one to four radii
+fn corners(values: &[CssValue]) -> Option<[CssValue; 4]> {
+    if !values.iter().all(is_radius) {
+        return None;
+    }
+
+    let corners = match values {
+        [all] => [all, all, all, all],
+        [top_left_bottom_right, top_right_bottom_left] => [
+            top_left_bottom_right,
+            top_right_bottom_left,
+            top_left_bottom_right,
+            top_right_bottom_left,
+        ],
+        [top_left, top_right_bottom_left, bottom_right] => {
+            [top_left, top_right_bottom_left, bottom_right, top_right_bottom_left]
+        }
+        [top_left, top_right, bottom_right, bottom_left] => [top_left, top_right, bottom_right, bottom_left],
+        _ => return None,
+    };
+
+    Some(corners.map(Clone::clone))
+}
+
+fn is_radius(value: &CssValue) -> bool {
+    match value {
+        CssValue::Unit(value, _) | CssValue::Percentage(value) | CssValue::Number(value) => *value >= 0.0,
+        CssValue::Zero => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn px(value: f32) -> CssValue {
+        CssValue::Unit(value, "px".into())
+    }
+
+    fn longhand(longhands: &[(&'static str, CssValue)], name: &str) -> CssValue {
+        longhands
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value.clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn single_list() {
+        let longhands = expand_border_radius(&[px(10.0)]).unwrap();
+        assert_eq!(longhands.len(), 4);
+        assert!(longhands.iter().all(|(_, value)| *value == px(10.0)));
+
+        let longhands = expand_border_radius(&[px(1.0), CssValue::Percentage(2.0), px(3.0)]).unwrap();
+        assert_eq!(longhand(&longhands, "border-top-left-radius"), px(1.0));
+        assert_eq!(
+            longhand(&longhands, "border-top-right-radius"),
+            CssValue::Percentage(2.0)
+        );
+        assert_eq!(longhand(&longhands, "border-bottom-right-radius"), px(3.0));
+        assert_eq!(
+            longhand(&longhands, "border-bottom-left-radius"),
+            CssValue::Percentage(2.0)
+        );
+    }
+
+    #[test]
+    fn horizontal_and_vertical() {
+        let longhands = expand_border_radius(&[
+            px(1.0),
+            px(2.0),
+            px(3.0),
+            px(4.0),
+            CssValue::None,
+            px(5.0),
+            CssValue::Zero,
+        ])
+        .unwrap();
+
+        assert_eq!(
+            longhand(&longhands, "border-top-left-radius"),
+            CssValue::List(vec![px(1.0), px(5.0)])
+        );
+        assert_eq!(
+            longhand(&longhands, "border-top-right-radius"),
+            CssValue::List(vec![px(2.0), CssValue::Zero])
+        );
+        assert_eq!(
+            longhand(&longhands, "border-bottom-right-radius"),
+            CssValue::List(vec![px(3.0), px(5.0)])
+        );
+        assert_eq!(
+            longhand(&longhands, "border-bottom-left-radius"),
+            CssValue::List(vec![px(4.0), CssValue::Zero])
+        );
+
+        assert!(expand_border_radius(&[px(1.0), CssValue::None]).is_none());
+        assert!(expand_border_radius(&[px(-1.0)]).is_none());
+        assert!(expand_border_radius(&[px(1.0), px(2.0), px(3.0), px(4.0), px(5.0)]).is_none());
+    }
+}
@@ -6,13 +6,13 @@ use log::warn;
 
 use crate::matcher::shorthands::{FixList, Shorthands};
 use crate::matcher::syntax::GroupCombinators::Juxtaposition;
-use crate::matcher::syntax::{CssSyntax, SyntaxComponent};
+use crate::matcher::syntax::{CssSyntax, SyntaxComponent, SyntaxComponentMultiplier};
 use crate::matcher::syntax_matcher::CssSyntaxTree;
 use crate::stylesheet::CssValue;
 
 /// List of elements that are built-in data types in the CSS specification. These will be handled
 /// by the syntax matcher as built-in types.
-const BUILTIN_DATA_TYPES: [&str; 43] = [
+const BUILTIN_DATA_TYPES: [&str; 48] = [
     "absolute-size",
     "age",
     "angle",
@@ -20,9 +20,12 @@ const BUILTIN_DATA_TYPES: [&str; 43] = [
     "calc-size()",
     "counter-name",
     "counter-style-name",
+    "custom-property-name",
     "custom-ident",
     "dashed-ident",
     "decibel",
+    "declaration-value",
+    "dynamic-range-limit",
     "feature-tag-value",
     "flex",
     "frequency",
@@ -47,10 +50,12 @@ const BUILTIN_DATA_TYPES: [&str; 43] = [
     "timeline-range-name",
     "transform-function",
     "uri",
+    "url-modifier",
     "url-set",
     "url-token",
     "x",
     "y",
+    "zero",
     "color()",
     "attr()",    //TODO: this is not a builtin!
     "element()", //TODO: this is not a builtin!
@@ -175,6 +180,9 @@ pub struct CssDefinitions {
     pub properties: HashMap<String, PropertyDefinition>,
     /// List of syntax elements for resolving the properties
     pub syntax: HashMap<String, SyntaxDefinition>,
+    /// Datatypes that are being resolved. Definitions can refer to themselves through function arguments (like
+    /// `<calc-sum>` in `calc()`), which are not resolved again.
+    resolving: Vec<String>,
 }
 
 impl Default for CssDefinitions {
@@ -190,6 +198,7 @@ impl CssDefinitions {
             resolved_properties: HashMap::new(),
             properties: HashMap::new(),
             syntax: HashMap::new(),
+            resolving: Vec::new(),
         }
     }
 
@@ -267,63 +276,19 @@ impl CssDefinitions {
             SyntaxComponent::Definition {
                 datatype, multipliers, ..
             } => {
-                // First step: Resolve by looking the definition up in the syntax defintions.
-                if let Some(syntax_element) = self.syntax.get(datatype) {
-                    let mut syntax_element = syntax_element.clone();
-                    if !syntax_element.resolved {
-                        syntax_element.syntax = self.resolve_syntax(&syntax_element.syntax, prop_name);
-                        syntax_element.resolved = true;
-                        self.syntax.insert(datatype.clone(), syntax_element.clone());
-                    }
-
-                    return SyntaxComponent::Group {
-                        components: syntax_element.syntax.components.clone(),
-                        combinator: Juxtaposition,
-                        multipliers: multipliers.clone(),
-                    };
-                }
-
-                // Second step: Resolve by looking the definition up in the properties
-
-                // Don't resolve in properties when the datatype is the same as the
-                // property name (for instance: inset-area)
-                if datatype != prop_name {
-                    // This datatype is not resolved yet.
-                    if !self.resolved_properties.contains_key(datatype) {
-                        if let Some(property_element) = self.properties.get(datatype) {
-                            let name = property_element.name.clone();
-                            self.resolve_property(name.as_str());
-                        }
-                    }
-
-                    if let Some(resolved_prop) = self.resolved_properties.get(datatype) {
-                        // If the resolved syntax is just a single element (be it a group, or a single element),
-                        // return that component.
-                        if resolved_prop.syntax.components.len() == 1 {
-                            let mut component = resolved_prop.syntax.components[0].clone();
-
-                            component.update_multipliers(multipliers.clone());
-
-                            return component;
-                        }
-                        // Otherwise, we return a group with the components
-                        return SyntaxComponent::Group {
-                            components: resolved_prop.syntax.components.clone(),
-                            combinator: Juxtaposition,
-                            multipliers: multipliers.clone(),
-                        };
-                    }
-                }
-
-                // Last step: check if the data type is a built-in datatype
-                if BUILTIN_DATA_TYPES.contains(&datatype.as_str()) {
+                // A definition within itself matches any value
+                if self.resolving.contains(datatype) {
                     return SyntaxComponent::Builtin {
                         datatype: datatype.clone(),
                         multipliers: multipliers.clone(),
                     };
                 }
 
-                panic!("Unknown datatype encountered: {datatype:?}");
+                self.resolving.push(datatype.clone());
+                let component = self.resolve_definition(datatype, multipliers, prop_name);
+                self.resolving.pop();
+
+                component
             }
             SyntaxComponent::Group {
                 components,
@@ -342,6 +307,17 @@ impl CssDefinitions {
                     multipliers: multipliers.clone(),
                 }
             }
+            SyntaxComponent::Function {
+                name,
+                arguments,
+                multipliers,
+            } => SyntaxComponent::Function {
+                name: name.clone(),
+                arguments: arguments
+                    .as_ref()
+                    .map(|arguments| Box::new(self.resolve_component(arguments, prop_name))),
+                multipliers: multipliers.clone(),
+            },
             _ => {
                 // This component does not need any resolving
                 component.clone()
@@ -349,6 +325,72 @@ impl CssDefinitions {
         }
     }
 
+    /// Resolves a definition like `<length>` or `<'border-radius'>`
+    fn resolve_definition(
+        &mut self,
+        datatype: &str,
+        multipliers: &[SyntaxComponentMultiplier],
+        prop_name: &str,
+    ) -> SyntaxComponent {
+        // First step: Resolve by looking the definition up in the syntax defintions.
+        if let Some(syntax_element) = self.syntax.get(datatype) {
+            let mut syntax_element = syntax_element.clone();
+            if !syntax_element.resolved {
+                syntax_element.syntax = self.resolve_syntax(&syntax_element.syntax, prop_name);
+                syntax_element.resolved = true;
+                self.syntax.insert(datatype.to_string(), syntax_element.clone());
+            }
+
+            return SyntaxComponent::Group {
+                components: syntax_element.syntax.components.clone(),
+                combinator: Juxtaposition,
+                multipliers: multipliers.to_vec(),
+            };
+        }
+
+        // Second step: Resolve by looking the definition up in the properties
+
+        // Don't resolve in properties when the datatype is the same as the
+        // property name (for instance: inset-area)
+        if datatype != prop_name {
+            // This datatype is not resolved yet.
+            if !self.resolved_properties.contains_key(datatype) {
+                if let Some(property_element) = self.properties.get(datatype) {
+                    let name = property_element.name.clone();
+                    self.resolve_property(name.as_str());
+                }
+            }
+
+            if let Some(resolved_prop) = self.resolved_properties.get(datatype) {
+                // If the resolved syntax is just a single element (be it a group, or a single element),
+                // return that component.
+                if resolved_prop.syntax.components.len() == 1 {
+                    let mut component = resolved_prop.syntax.components[0].clone();
+
+                    component.update_multipliers(multipliers.to_vec());
+
+                    return component;
+                }
+                // Otherwise, we return a group with the components
+                return SyntaxComponent::Group {
+                    components: resolved_prop.syntax.components.clone(),
+                    combinator: Juxtaposition,
+                    multipliers: multipliers.to_vec(),
+                };
+            }
+        }
+
+        // Last step: check if the data type is a built-in datatype
+        if BUILTIN_DATA_TYPES.contains(&datatype) {
+            return SyntaxComponent::Builtin {
+                datatype: datatype.to_string(),
+                multipliers: multipliers.to_vec(),
+            };
+        }
+
+        panic!("Unknown datatype encountered: {datatype:?}");
+    }
+
     // Resolve all the components from a given syntax tree
     fn resolve_syntax(&mut self, syntax: &CssSyntaxTree, prop_name: &str) -> CssSyntaxTree {
         let mut resolved_components = vec![];
@@ -396,6 +438,7 @@ fn parse_definition_files() -> CssDefinitions {
         resolved_properties: HashMap::new(),
         properties,
         syntax,
+        resolving: Vec::new(),
     };
 
     definitions.index_shorthands();
@@ -605,7 +648,7 @@ mod tests {
             .with_level(log::LevelFilter::Warn)
             .init()
             .unwrap();
-        assert_eq!(CSS_DEFINITIONS.len(), 656);
+        assert_eq!(CSS_DEFINITIONS.len(), 661);
     }
 
     #[test]
@@ -961,4 +1004,111 @@ mod tests {
             CssValue::Number(100.0)
         ]));
     }
+
+    #[test]
+    fn test_color_functions() {
+        let definitions = get_css_definitions();
+        let def = definitions.find_property("color").unwrap();
+
+        let func = |name: &str, args: Vec<CssValue>| [CssValue::Function(name.into(), args)];
+        let num = CssValue::Number;
+
+        assert_true!(def.matches(&func("rgb", vec![num(1.0), num(2.0), num(3.0)])));
+        assert_true!(def.matches(&func(
+            "rgb",
+            vec![num(1.0), CssValue::Comma, num(2.0), CssValue::Comma, num(3.0)]
+        )));
+        assert_true!(def.matches(&func(
+            "rgba",
+            vec![
+                CssValue::Zero,
+                CssValue::Comma,
+                CssValue::Zero,
+                CssValue::Comma,
+                CssValue::Zero,
+                CssValue::Comma,
+                num(0.5)
+            ]
+        )));
+        // The `/` operator is parsed into a `CssValue::None`
+        assert_true!(def.matches(&func(
+            "rgb",
+            vec![num(1.0), num(2.0), num(3.0), CssValue::None, num(0.5)]
+        )));
+
+        assert_false!(def.matches(&func("rgb", vec![num(1.0), num(2.0)])));
+        assert_false!(def.matches(&func("rgb", vec![num(1.0), CssValue::Comma, num(2.0)])));
+        assert_false!(def.matches(&func("nocolor", vec![num(1.0), num(2.0), num(3.0)])));
+    }
+
+    #[test]
+    fn test_transform_functions() {
+        let definitions = get_css_definitions();
+        let def = definitions.find_property("transform").unwrap();
+
+        assert_true!(def.matches(&[
+            CssValue::Function("rotate".into(), vec![unit!(45.0, "deg")]),
+            CssValue::Function(
+                "translate".into(),
+                vec![unit!(10.0, "px"), CssValue::Comma, unit!(5.0, "px")]
+            ),
+        ]));
+        assert_true!(def.matches(&[CssValue::Function("scale".into(), vec![CssValue::Number(2.0)])]));
+        assert_true!(def.matches(&[str!("none")]));
+    }
+
+    #[test]
+    fn test_box_shadow() {
+        let definitions = get_css_definitions();
+        let def = definitions.find_property("box-shadow").unwrap();
+
+        assert_true!(def.matches(&[unit!(1.0, "px"), unit!(2.0, "px")]));
+        assert_true!(def.matches(&[unit!(1.0, "px"), unit!(2.0, "px"), str!("red")]));
+        assert_true!(def.matches(&[
+            CssValue::Zero,
+            CssValue::Zero,
+            unit!(4.0, "px"),
+            unit!(2.0, "px"),
+            str!("red")
+        ]));
+        assert_true!(def.matches(&[
+            str!("red"),
+            unit!(1.0, "px"),
+            unit!(2.0, "px"),
+            CssValue::Comma,
+            str!("inset"),
+            unit!(1.0, "px"),
+            unit!(1.0, "px"),
+            str!("blue")
+        ]));
+
+        assert_false!(def.matches(&[unit!(1.0, "px")]));
+        assert_false!(def.matches(&[str!("red"), str!("blue")]));
+        assert_false!(def.matches(&[unit!(1.0, "px"), unit!(2.0, "px"), CssValue::Comma]));
+    }
+
+    #[test]
+    fn test_text_shadow() {
+        let definitions = get_css_definitions();
+        let def = definitions.find_property("text-shadow").unwrap();
+
+        assert_true!(def.matches(&[str!("none")]));
+        assert_true!(def.matches(&[unit!(1.0, "px"), unit!(1.0, "px"), str!("red")]));
+        assert_true!(def.matches(&[unit!(1.0, "px"), unit!(1.0, "px"), unit!(2.0, "px"), str!("black")]));
+        assert_true!(def.matches(&[str!("red"), CssValue::Zero, CssValue::Zero, unit!(2.0, "px")]));
+
+        assert_false!(def.matches(&[unit!(1.0, "px")]));
+        assert_false!(def.matches(&[unit!(1.0, "px"), unit!(1.0, "px"), unit!(1.0, "px"), unit!(1.0, "px")]));
+        assert_false!(def.matches(&[str!("red")]));
+    }
+
+    #[test]
+    fn test_font() {
+        let definitions = get_css_definitions();
+        let def = definitions.find_property("font").unwrap();
+
+        assert_true!(def.matches(&[unit!(12.0, "px"), str!("serif")]));
+        assert_true!(def.matches(&[str!("bold"), unit!(12.0, "px"), str!("serif")]));
+        assert_true!(def.matches(&[unit!(12.0, "px"), CssValue::None, CssValue::Number(1.5), str!("serif")]));
+    }
 }
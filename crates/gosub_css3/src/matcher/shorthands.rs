@@ -121,6 +121,19 @@ pub struct FixListInfo {
     specificity: Specificity,
 }
 
+impl FixListInfo {
+    /// Information about the declaration that is being expanded, which is copied to all its longhand properties
+    #[must_use]
+    pub fn new(origin: CssOrigin, important: bool, location: &str, specificity: Specificity) -> Self {
+        Self {
+            origin,
+            important,
+            location: location.to_string(),
+            specificity,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Shorthand {
     name: String,
@@ -366,6 +379,11 @@ impl FixList {
 
         for (k, v) in &mut self.list {
             if *k == name {
+                // A longhand that was set by a declaration with a higher priority (like !important) is kept
+                if v.iter().any(|existing| *existing > value) {
+                    return;
+                }
+
                 v.clear(); //TODO: This is a hack, we should not have to clear the vec
                 v.push(value);
                 return;
@@ -393,6 +411,13 @@ impl FixList {
 
             had_shorthands = true;
 
+            fix_list.set_info(FixListInfo::new(
+                decl.origin,
+                decl.important,
+                &decl.location,
+                decl.specificity,
+            ));
+
             prop.matches_and_shorthands(decl.value.to_slice(), &mut fix_list);
         }
 
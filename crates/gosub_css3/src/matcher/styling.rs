@@ -9,6 +9,8 @@ use gosub_interface::config::HasDocument;
 use gosub_interface::css3;
 use gosub_interface::css3::{CssOrigin, CssPropertyMap};
 use gosub_interface::document::Document;
+use gosub_shared::config::ParserConfig;
+use gosub_shared::errors::{CssError, CssResult};
 
 use gosub_interface::node::ClassList;
 use gosub_interface::node::ElementDataType;
@@ -18,6 +20,11 @@ use gosub_shared::node::NodeId;
 use crate::matcher::property_definitions::get_css_definitions;
 use crate::stylesheet::{Combinator, CssSelector, CssSelectorPart, CssValue, MatcherType, Specificity};
 use crate::system::Css3System;
+use crate::Css3;
+
+/// Properties whose actual value is not rounded to whole numbers, as fractions matter for them (like `opacity: 0.5`,
+/// `scale: 1.5` or `rotate: 0.25turn`)
+const UNROUNDED_PROPERTIES: [&str; 5] = ["opacity", "rotate", "scale", "translate", "transform-origin"];
 
 // Matches a complete selector (all parts) against the given node(id)
 pub(crate) fn match_selector<C: HasDocument>(
@@ -34,6 +41,57 @@ pub(crate) fn match_selector<C: HasDocument>(
     (false, Specificity::new(0, 0, 0))
 }
 
+/// Matches a selector of a pseudo-element (like `p::selection` for `selection`) against the element the pseudo-element
+/// belongs to
+pub(crate) fn match_pseudo_element_selector<C: HasDocument>(
+    document: &C::Document,
+    node_id: NodeId,
+    selector: &CssSelector,
+    pseudo: &str,
+) -> (bool, Specificity) {
+    for part in &selector.parts {
+        let Some((CssSelectorPart::PseudoElement(name), element_parts)) = part.split_last() else {
+            continue;
+        };
+
+        if name.eq_ignore_ascii_case(pseudo) && match_selector_parts::<C>(document, node_id, element_parts) {
+            return (true, Specificity::from(part.as_slice()));
+        }
+    }
+
+    (false, Specificity::new(0, 0, 0))
+}
+
+/// Returns the ids of all elements in the document that match the given selector list, in tree order
+pub fn query_selector_all<C: HasDocument>(document: &C::Document, selectors: &str) -> CssResult<Vec<NodeId>> {
+    let config = ParserConfig {
+        ignore_errors: true,
+        ..Default::default()
+    };
+    let sheet = Css3::parse_str(&format!("{selectors} {{}}"), config, CssOrigin::Author, "")?;
+
+    let Some(rule) = sheet.rules.first() else {
+        return Err(CssError::new(&format!("Invalid selector: {selectors}")));
+    };
+
+    let mut matches = Vec::new();
+    let mut stack = vec![NodeId::root()];
+
+    while let Some(id) = stack.pop() {
+        let Some(node) = document.node_by_id(id) else {
+            continue;
+        };
+
+        if node.is_element_node() && rule.selectors().iter().any(|s| match_selector::<C>(document, id, s).0) {
+            matches.push(id);
+        }
+
+        stack.extend(node.children().iter().rev());
+    }
+
+    Ok(matches)
+}
+
 fn consume<'a, T>(this: &mut &'a [T]) -> Option<&'a T> {
     let last = this.last()?;
 
@@ -166,9 +224,16 @@ fn match_selector_part<'a, C: HasDocument>(
                 }
             }
         }
-        CssSelectorPart::PseudoClass(_name) => {
-            // @Todo: implement pseudo classes
-            false
+        CssSelectorPart::PseudoClass(name) => {
+            // @Todo: implement the pseudo classes besides the focus
+            let focus = doc.focus().filter(|(id, _)| *id == current_node.id());
+            if name.eq_ignore_ascii_case("focus") {
+                focus.is_some()
+            } else if name.eq_ignore_ascii_case("focus-visible") {
+                focus.is_some_and(|(_, visible)| visible)
+            } else {
+                false
+            }
         }
         CssSelectorPart::PseudoElement(_name) => {
             // @Todo: implement pseudo elements
@@ -468,6 +533,10 @@ impl CssProperty {
 
     fn find_actual_value(&self) -> CssValue {
         // @TODO: stuff like clipping and such should occur as well
+        if UNROUNDED_PROPERTIES.contains(&self.name.as_str()) {
+            return self.used.clone();
+        }
+
         match &self.used {
             CssValue::Number(len) => CssValue::Number(len.round()),
             CssValue::Percentage(perc) => CssValue::Percentage(perc.round()),
@@ -755,6 +824,33 @@ mod tests {
         assert!(prop_is_inherit(&prop.name));
     }
 
+    #[test]
+    fn actual_value_rounding() {
+        let declared = |value| DeclarationProperty {
+            value,
+            origin: CssOrigin::Author,
+            important: false,
+            location: String::new(),
+            specificity: Specificity::new(1, 0, 0),
+        };
+
+        let mut prop = CssProperty::new("width");
+        prop.declared.push(declared(CssValue::Unit(10.6, "px".into())));
+        assert_eq!(prop.compute_value(), &CssValue::Unit(11.0, "px".into()));
+
+        let mut prop = CssProperty::new("z-index");
+        prop.declared.push(declared(CssValue::Number(2.4)));
+        assert_eq!(prop.compute_value(), &CssValue::Number(2.0));
+
+        let mut prop = CssProperty::new("opacity");
+        prop.declared.push(declared(CssValue::Number(0.5)));
+        assert_eq!(prop.compute_value(), &CssValue::Number(0.5));
+
+        let mut prop = CssProperty::new("rotate");
+        prop.declared.push(declared(CssValue::Unit(0.25, "turn".into())));
+        assert_eq!(prop.compute_value(), &CssValue::Unit(0.25, "turn".into()));
+    }
+
     #[test]
     fn compare_declared() {
         let a = DeclarationProperty {
@@ -821,6 +917,29 @@ mod tests {
         assert_eq!(d, d);
     }
 
+    #[test]
+    fn pseudo_element_selector_parts() {
+        let sheet = Css3::parse_str(
+            "p.note::selection, ::selection {}",
+            ParserConfig::default(),
+            CssOrigin::Author,
+            "",
+        )
+        .unwrap();
+        let parts = &sheet.rules[0].selectors()[0].parts;
+
+        assert_eq!(
+            parts[0],
+            vec![
+                CssSelectorPart::Type("p".into()),
+                CssSelectorPart::Class("note".into()),
+                CssSelectorPart::PseudoElement("selection".into()),
+            ]
+        );
+        assert_eq!(parts[1], vec![CssSelectorPart::PseudoElement("selection".into())]);
+        assert_eq!(Specificity::from(parts[0].as_slice()), Specificity::new(0, 1, 1));
+    }
+
     #[test]
     fn is_inheritable() {
         let prop = CssProperty::new("border");
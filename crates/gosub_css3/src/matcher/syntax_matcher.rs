@@ -103,6 +103,7 @@ fn match_component_inner<'a>(
             multiplier_count += 1;
 
             let remainder = res.remainder;
+            let matched_len = matched_values.len();
             matched_values.append(&mut res.matched_values.clone());
 
             // Check if we fulfilled the multiplier for this component
@@ -128,6 +129,17 @@ fn match_component_inner<'a>(
                     return res;
                 }
                 Fulfillment::NotFulfilled => {
+                    // One value too many (ie: a fourth value for 'foo{1,3}'). When we were fulfilled without
+                    // it, we leave that value for the next component.
+                    if multiplier_fulfilled(component, multiplier_count - 1) == Fulfillment::FulfilledButMoreAllowed {
+                        matched_values.truncate(matched_len);
+                        return MatchResult {
+                            remainder: input,
+                            matched: true,
+                            matched_values,
+                        };
+                    }
+
                     // The multiplier is not fulfilled.
                     return no_match(raw_input);
                 }
@@ -199,18 +211,23 @@ fn match_component<'a>(
             break;
         }
 
-        // Make sure the next value from input is a comma
-        if input.first() != Some(&CssValue::Comma) {
-            return no_match(raw_input);
+        // A list of at most `csv_max` values leaves the next comma for the component after it (e.g. the
+        // alpha value in `rgba(<number>#{3}, <alpha-value>)`)
+        if csv_cnt == csv_max {
+            break;
         }
 
-        // Remove the comma, and continue matching
-        input.clone_from(&&input[1..input.len()]);
+        // The list ends when the next value is not a comma (ie: 'foo# bar')
+        if input.first() != Some(&CssValue::Comma) {
+            break;
+        }
 
-        if input.is_empty() {
-            // We have a comma at the end of the input. This is not allowed.
-            return no_match(raw_input);
+        // Only consume the comma when another value of the list follows it
+        let next = &input[1..];
+        if next.is_empty() || !match_component_inner(next, component, None).matched {
+            break;
         }
+        input = next;
     }
 
     // If we are in a comma separated list, we need to check if we have the correct amount of values
@@ -288,6 +305,15 @@ fn match_component_single<'a>(input: &'a [CssValue], component: &SyntaxComponent
                 CssValue::Unit(_, u) if u.eq_ignore_ascii_case("turn") => return first_match(input),
                 _ => {}
             },
+            "number" | "integer" => match value {
+                CssValue::Zero | CssValue::Number(_) => return first_match(input),
+                _ => {}
+            },
+            "zero" => match value {
+                CssValue::Zero => return first_match(input),
+                CssValue::Number(n) if *n == 0.0 => return first_match(input),
+                _ => {}
+            },
             "length" => match value {
                 CssValue::Zero => return first_match(input),
                 CssValue::Unit(_, u) if LENGTH_UNITS.contains(&u.as_str()) => return first_match(input),
@@ -321,7 +347,10 @@ fn match_component_single<'a>(input: &'a [CssValue], component: &SyntaxComponent
             _ => {
                 // println!("unknown datatype: {datatype:?}");
 
-                return first_match(input);
+                // A comma separates values, but is never a value by itself
+                if *value != CssValue::Comma {
+                    return first_match(input);
+                }
             } // _ => panic!("Unknown built-in datatype: {:?}", datatype),
         },
         SyntaxComponent::Inherit { .. } => match value {
@@ -353,6 +382,9 @@ fn match_component_single<'a>(input: &'a [CssValue], component: &SyntaxComponent
             }
         }
         SyntaxComponent::Literal { literal, .. } => match value {
+            CssValue::Comma if literal == "," => return first_match(input),
+            // Operators like the `/` in `rgb(0 0 0 / 50%)` are converted into a `CssValue::None`
+            CssValue::None if literal == "/" => return first_match(input),
             CssValue::String(v) if v.eq(literal) => return first_match(input),
             CssValue::String(v) if v.eq_ignore_ascii_case(literal) => {
                 log::warn!("Case insensitive literal matched");
@@ -360,7 +392,7 @@ fn match_component_single<'a>(input: &'a [CssValue], component: &SyntaxComponent
             }
             _ => {}
         },
-        SyntaxComponent::Function { name, .. } => {
+        SyntaxComponent::Function { name, arguments, .. } => {
             let CssValue::Function(c_name, c_args) = value else {
                 return no_match(input);
             };
@@ -369,13 +401,17 @@ fn match_component_single<'a>(input: &'a [CssValue], component: &SyntaxComponent
                 return no_match(input);
             }
 
-            if c_args.is_empty() {
+            // All arguments must match the syntax of the arguments
+            let matched = match arguments {
+                Some(arguments) => {
+                    let res = match_component(c_args, arguments, None);
+                    res.matched && res.remainder.is_empty()
+                }
+                None => c_args.is_empty(),
+            };
+            if matched {
                 return first_match(input);
             }
-
-            // todo!("Function not implemented yet. We must match the arguments");
-            // let list = CssValue::List(c_args.clone());
-            // return match_internal(&list, arguments);
         }
         SyntaxComponent::Value { value: css_value, .. } => {
             if value == css_value {
@@ -598,7 +634,8 @@ fn match_group_all_any_order<'a>(
             let component = &components[c_idx];
 
             let res = match_component(input, component, resolver);
-            if res.matched {
+            // Optional components that match without taking any values are left for later values
+            if res.matched && res.remainder.len() < input.len() {
                 matched_values.append(&mut res.matched_values.clone());
                 components_matched.push(c_idx);
 
@@ -624,7 +661,7 @@ fn match_group_all_any_order<'a>(
             let component = &components[c_idx];
 
             let res = match_component(input, component, None);
-            if res.matched {
+            if res.matched && res.remainder.len() < input.len() {
                 matched_values.append(&mut res.matched_values.clone());
                 components_matched.push(c_idx);
 
@@ -645,7 +682,12 @@ fn match_group_all_any_order<'a>(
         }
     }
 
-    if components_matched.len() != components.len() {
+    // All components must be found, unless they allow for zero values (ie: 'foo? && bar')
+    let all_found = components
+        .iter()
+        .enumerate()
+        .all(|(idx, component)| components_matched.contains(&idx) || match_component(&[], component, None).matched);
+    if !all_found {
         return no_match(input);
     }
 
@@ -687,7 +729,7 @@ fn match_group_juxtaposition<'a>(
                 if let Some(complete) = complete {
                     complete.complete(res.matched_values);
                 }
-            } else {
+            } else if !comma_elided(input, components, c_idx) {
                 break;
             }
         } else {
@@ -697,7 +739,7 @@ fn match_group_juxtaposition<'a>(
             if res.matched {
                 matched_values.append(&mut res.matched_values.clone());
                 input = res.remainder;
-            } else {
+            } else if !comma_elided(input, components, c_idx) {
                 break;
             }
         }
@@ -716,6 +758,28 @@ fn match_group_juxtaposition<'a>(
     }
 }
 
+/// Returns true when the component at `c_idx` is a comma that may be left out because the optional component
+/// after it is omitted, like in `rgb( <number>#{3} , <alpha-value>? )`
+fn comma_elided(input: &[CssValue], components: &[SyntaxComponent], c_idx: usize) -> bool {
+    let SyntaxComponent::Literal { literal, .. } = &components[c_idx] else {
+        return false;
+    };
+    if literal != "," {
+        return false;
+    }
+
+    let Some(next) = components.get(c_idx + 1) else {
+        return false;
+    };
+
+    // The next component must be optional and must not take any values from the input
+    if !match_component(&[], next, None).matched {
+        return false;
+    }
+    let res = match_component(input, next, None);
+    !res.matched || res.remainder.len() == input.len()
+}
+
 /// Fulfillment is a result returned by the `multiplier_fulfilled` function. This is used to determine
 /// if a multiplier is fulfilled or not and how.
 #[derive(Debug, PartialEq)]
@@ -1509,4 +1573,112 @@ mod tests {
         assert_false!(tree.matches(&[str!("foo"), CssValue::Comma, str!("bar"), CssValue::Comma]));
         assert_false!(tree.matches(&[str!("foo"), CssValue::Comma, CssValue::Comma, str!("bar")]));
     }
+
+    #[test]
+    fn test_comma_separated_range() {
+        let tree = CssSyntax::new("foo#{2} , bar").compile().unwrap();
+        assert_true!(tree.matches(&[str!("foo"), CssValue::Comma, str!("foo"), CssValue::Comma, str!("bar")]));
+
+        assert_false!(tree.matches(&[str!("foo"), CssValue::Comma, str!("bar")]));
+        assert_false!(tree.matches(&[
+            str!("foo"),
+            CssValue::Comma,
+            str!("foo"),
+            CssValue::Comma,
+            str!("foo"),
+            CssValue::Comma,
+            str!("bar")
+        ]));
+    }
+
+    #[test]
+    fn test_multipliers_between_leaves_last_value() {
+        let tree = CssSyntax::new("foo{1,2} [ foo | bar ]").compile().unwrap();
+        assert_true!(tree.matches(&[str!("foo"), str!("bar")]));
+        assert_true!(tree.matches(&[str!("foo"), str!("foo"), str!("foo")]));
+
+        assert_false!(tree.matches(&[str!("foo"), str!("foo"), str!("foo"), str!("foo")]));
+    }
+
+    #[test]
+    fn test_function_arguments() {
+        let mut definitions = get_css_definitions().clone();
+        definitions.add_property(
+            "testprop",
+            PropertyDefinition {
+                name: "testprop".to_string(),
+                computed: vec![],
+                syntax: CssSyntax::new("shift( <length> , <length>? ) | none")
+                    .compile()
+                    .unwrap(),
+                inherited: false,
+                initial_value: None,
+                resolved: false,
+                shorthands: None,
+            },
+        );
+        definitions.resolve();
+
+        let prop = definitions.find_property("testprop").unwrap();
+
+        let shift = |args: Vec<CssValue>| [CssValue::Function("shift".into(), args)];
+
+        assert_true!(prop.matches(&shift(vec![
+            CssValue::Unit(1.0, "px".into()),
+            CssValue::Comma,
+            CssValue::Unit(2.0, "px".into()),
+        ])));
+        // The comma before an omitted optional argument is left out
+        assert_true!(prop.matches(&shift(vec![CssValue::Unit(1.0, "px".into())])));
+        assert_true!(prop.matches(&[str!("none")]));
+
+        assert_false!(prop.matches(&shift(vec![])));
+        assert_false!(prop.matches(&shift(vec![str!("red")])));
+        assert_false!(prop.matches(&shift(vec![
+            CssValue::Unit(1.0, "px".into()),
+            CssValue::Unit(2.0, "px".into()),
+        ])));
+        assert_false!(prop.matches(&shift(vec![
+            CssValue::Unit(1.0, "px".into()),
+            CssValue::Comma,
+            CssValue::Unit(2.0, "px".into()),
+            CssValue::Comma,
+            CssValue::Unit(3.0, "px".into()),
+        ])));
+        assert_false!(prop.matches(&[CssValue::Function(
+            "other".into(),
+            vec![CssValue::Unit(1.0, "px".into())]
+        )]));
+    }
+
+    #[test]
+    fn test_all_any_order_optional() {
+        let tree = CssSyntax::new("foo? && bar").compile().unwrap();
+        assert_true!(tree.matches(&[str!("bar")]));
+        assert_true!(tree.matches(&[str!("foo"), str!("bar")]));
+        assert_true!(tree.matches(&[str!("bar"), str!("foo")]));
+
+        assert_false!(tree.matches(&[str!("foo")]));
+        assert_false!(tree.matches(&[str!("bar"), str!("bar")]));
+    }
+
+    #[test]
+    fn test_multipliers_with_optional_tail() {
+        let tree = CssSyntax::new("foo{2} bar? foo?").compile().unwrap();
+        assert_true!(tree.matches(&[str!("foo"), str!("foo")]));
+        assert_true!(tree.matches(&[str!("foo"), str!("foo"), str!("foo")]));
+        assert_true!(tree.matches(&[str!("foo"), str!("foo"), str!("bar"), str!("foo")]));
+
+        assert_false!(tree.matches(&[str!("foo")]));
+        assert_false!(tree.matches(&[str!("foo"), str!("foo"), str!("foo"), str!("foo")]));
+    }
+
+    #[test]
+    fn test_comma_separated_followed_by_value() {
+        let tree = CssSyntax::new("foo# bar").compile().unwrap();
+        assert_true!(tree.matches(&[str!("foo"), str!("bar")]));
+        assert_true!(tree.matches(&[str!("foo"), CssValue::Comma, str!("foo"), str!("bar")]));
+
+        assert_false!(tree.matches(&[str!("foo"), CssValue::Comma, str!("bar")]));
+    }
 }
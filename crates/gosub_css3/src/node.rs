@@ -210,6 +210,19 @@ impl Node {
         }
     }
 
+    #[must_use]
+    pub fn is_at_rule(&self) -> bool {
+        matches!(&*self.node_type, NodeType::AtRule { .. })
+    }
+
+    #[must_use]
+    pub fn as_at_rule(&self) -> (&String, &Option<Node>, &Option<Node>) {
+        match &&*self.node_type {
+            &NodeType::AtRule { name, prelude, block } => (name, prelude, block),
+            _ => panic!("Node is not an at-rule"),
+        }
+    }
+
     #[must_use]
     pub fn as_rule(&self) -> (&Option<Node>, &Option<Node>) {
         match &&*self.node_type {
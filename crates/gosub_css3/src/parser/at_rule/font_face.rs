@@ -1,11 +1,105 @@
-use crate::node::Node;
+use crate::node::{Node, NodeType};
+use crate::tokenizer::TokenType;
 use crate::Css3;
 use gosub_shared::errors::CssResult;
 
 impl Css3<'_> {
+    /// Parses the declarations inside a `@font-face` block. This is a regular style block, except for the
+    /// `unicode-range` descriptor. Its values (`U+0025-00FF`, `U+4??`) do not tokenize into anything
+    /// meaningful, so they are kept as a raw string instead.
     pub fn parse_at_rule_font_face_block(&mut self) -> CssResult<Node> {
         log::trace!("parse_at_rule_font_face_block");
 
-        todo!();
+        let loc = self.tokenizer.current_location();
+        let mut children: Vec<Node> = Vec::new();
+
+        while !self.tokenizer.eof() {
+            let t = self.consume_any()?;
+            match t.token_type {
+                TokenType::RCurly | TokenType::Eof => {
+                    self.tokenizer.reconsume();
+                    break;
+                }
+                TokenType::Whitespace(_) | TokenType::Comment(_) | TokenType::Semicolon => {
+                    // just eat the token
+                }
+                TokenType::Ident(ref name) if name.eq_ignore_ascii_case("unicode-range") => {
+                    self.tokenizer.reconsume();
+                    children.push(self.parse_unicode_range_descriptor()?);
+                }
+                _ => {
+                    self.tokenizer.reconsume();
+                    if let Some(declaration) = self.parse_declaration()? {
+                        children.push(declaration);
+                    }
+                }
+            }
+        }
+
+        Ok(Node::new(NodeType::Block { children }, loc))
+    }
+
+    fn parse_unicode_range_descriptor(&mut self) -> CssResult<Node> {
+        log::trace!("parse_unicode_range_descriptor");
+
+        let loc = self.tokenizer.current_location();
+
+        let property = self.consume_any_ident()?;
+        self.consume_whitespace_comments();
+        self.consume(TokenType::Colon)?;
+        self.consume_whitespace_comments();
+
+        let value_loc = self.tokenizer.current_location();
+        let mut start = None;
+        let end = loop {
+            let t = self.consume_any()?;
+            match t.token_type {
+                TokenType::Semicolon | TokenType::RCurly | TokenType::Eof => {
+                    self.tokenizer.reconsume();
+                    break t.location.offset;
+                }
+                _ => {
+                    start.get_or_insert(t.location.offset);
+                }
+            }
+        };
+        let start = start.unwrap_or(end);
+
+        let value = self.tokenizer.slice(start, end).trim().to_string();
+
+        Ok(Node::new(
+            NodeType::Declaration {
+                property,
+                value: vec![Node::new(NodeType::Raw { value }, value_loc)],
+                important: false,
+            },
+            loc,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::walker::Walker;
+    use crate::{CssOrigin, ParserConfig};
+    use gosub_shared::byte_stream::{ByteStream, Encoding};
+
+    #[test]
+    fn test_parse_at_rule_font_face_block() {
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.read_from_str(
+            "font-family: Foo; src: url(foo.woff2) format(\"woff2\"), local(Foo); unicode-range: U+0000-00FF, U+4??; font-display: swap }",
+            Some(Encoding::UTF8),
+        );
+        stream.close();
+
+        let mut parser = crate::Css3::new(&mut stream, ParserConfig::default(), CssOrigin::Author, "");
+        let node = parser.parse_at_rule_font_face_block().unwrap();
+
+        let w = Walker::new(&node);
+        assert_eq!(
+            w.walk_to_string(),
+            "[Block]\n  [Declaration] property: font-family important: false\n    [Ident] Foo\n  [Declaration] property: src important: false\n    [Url] foo.woff2\n    [Function] format\n      [String] woff2\n    [Comma]\n    [Function] local\n      [Ident] Foo\n  [Declaration] property: unicode-range important: false\n    [Raw] U+0000-00FF, U+4??\n  [Declaration] property: font-display important: false\n    [Ident] swap\n"
+        );
     }
 }
@@ -49,6 +49,11 @@ impl Css3<'_> {
         }
     }
 
+    /// Parses declarations that are not surrounded by curly braces, like the value of a `style` attribute
+    pub(crate) fn parse_declaration_list(&mut self) -> CssResult<Node> {
+        self.parse_block(BlockParseMode::StyleBlock)
+    }
+
     pub fn parse_block(&mut self, mode: BlockParseMode) -> CssResult<Node> {
         log::trace!("parse_block with parse mode: {mode:?}");
 
@@ -69,6 +74,11 @@ impl Css3<'_> {
                 TokenType::Whitespace(_) | TokenType::Comment(_) => {
                     // just eat the token
                 }
+                TokenType::Eof => {
+                    // An unclosed block (or a declaration list like a style attribute) ends at the end of the input
+                    self.tokenizer.reconsume();
+                    break;
+                }
 
                 TokenType::AtKeyword(_) => {
                     self.tokenizer.reconsume();
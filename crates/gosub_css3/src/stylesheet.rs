@@ -1,13 +1,14 @@
 use core::fmt::Debug;
 use core::slice;
 use gosub_interface::css3::CssOrigin;
+use gosub_interface::font::FontFaceDescriptor;
 use gosub_shared::byte_stream::Location;
 use gosub_shared::errors::CssError;
 use gosub_shared::errors::CssResult;
 use std::cmp::Ordering;
 use std::fmt::Display;
 
-use crate::colors::RgbColor;
+use crate::colors::{is_named_color, is_system_color, RgbColor};
 
 /// Severity of a CSS error
 #[derive(Debug, PartialEq)]
@@ -110,6 +111,8 @@ pub struct CssStylesheet {
     pub url: String,
     /// Any issues during parsing of the stylesheet
     pub parse_log: Vec<CssLog>,
+    /// Font faces defined by `@font-face` rules
+    pub font_faces: Vec<FontFaceDescriptor>,
 }
 
 impl gosub_interface::css3::CssStylesheet for CssStylesheet {
@@ -120,6 +123,30 @@ impl gosub_interface::css3::CssStylesheet for CssStylesheet {
     fn url(&self) -> &str {
         &self.url
     }
+
+    fn font_faces(&self) -> &[FontFaceDescriptor] {
+        &self.font_faces
+    }
+
+    fn matches_sibling_attribute(&self, name: &str) -> bool {
+        self.rules
+            .iter()
+            .flat_map(|rule| &rule.selectors)
+            .flat_map(|selector| &selector.parts)
+            .any(|parts| {
+                // The compound selector before a sibling combinator matches on the previous sibling
+                let mut compound = 0;
+                parts.iter().enumerate().any(|(idx, part)| {
+                    let CssSelectorPart::Combinator(combinator) = part else {
+                        return false;
+                    };
+                    let start = std::mem::replace(&mut compound, idx + 1);
+
+                    matches!(combinator, Combinator::NextSibling | Combinator::SubsequentSibling)
+                        && parts[start..idx].iter().any(|part| part.matches_attribute(name))
+                })
+            })
+    }
 }
 
 /// A CSS rule, which contains a list of selectors and a list of declarations
@@ -217,6 +244,20 @@ impl Display for Combinator {
     }
 }
 
+impl CssSelectorPart {
+    /// Returns true when the part can match differently after the given attribute of the element changed
+    fn matches_attribute(&self, name: &str) -> bool {
+        match self {
+            CssSelectorPart::Attribute(attribute) => attribute.name.eq_ignore_ascii_case(name),
+            CssSelectorPart::Class(_) => name == "class",
+            CssSelectorPart::Id(_) => name == "id",
+            // Pseudo-classes like `:checked`, `:disabled` and `:link` match on attributes
+            CssSelectorPart::PseudoClass(_) => true,
+            _ => false,
+        }
+    }
+}
+
 impl Debug for CssSelectorPart {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -293,14 +334,21 @@ impl Display for MatcherType {
     }
 }
 
-/// Defines the specificity for a selector
+/// Defines the specificity for a selector. The first component is only set for declarations from a `style`
+/// attribute, so they win over declarations from any selector.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub struct Specificity(u32, u32, u32);
+pub struct Specificity(u32, u32, u32, u32);
 
 impl Specificity {
     #[must_use]
     pub fn new(a: u32, b: u32, c: u32) -> Self {
-        Self(a, b, c)
+        Self(0, a, b, c)
+    }
+
+    /// Specificity of declarations in the `style` attribute of an element
+    #[must_use]
+    pub fn inline() -> Self {
+        Self(1, 0, 0, 0)
     }
 }
 
@@ -344,7 +392,7 @@ impl Ord for Specificity {
                 Ordering::Equal => match self.2.cmp(&other.2) {
                     Ordering::Greater => Ordering::Greater,
                     Ordering::Less => Ordering::Less,
-                    Ordering::Equal => Ordering::Equal,
+                    Ordering::Equal => self.3.cmp(&other.3),
                 },
             },
         }
@@ -418,6 +466,15 @@ impl CssValue {
         match self {
             CssValue::Color(col) => Some(*col),
             CssValue::String(s) => Some(RgbColor::from(s.as_str())),
+            CssValue::Function(name, args) if matches!(name.as_str(), "rgb" | "rgba" | "hsl" | "hsla") => {
+                let args = args
+                    .iter()
+                    .filter(|arg| !matches!(arg, CssValue::Comma))
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+
+                Some(RgbColor::from(format!("{name}({})", args.join(", ")).as_str()))
+            }
             _ => None,
         }
     }
@@ -512,6 +569,7 @@ impl CssValue {
             }
 
             crate::node::NodeType::Comma => Ok(CssValue::Comma),
+            crate::node::NodeType::Raw { value } => Ok(CssValue::String(value)),
 
             _ => Err(CssError::new(
                 format!("Cannot convert node to CssValue: {node:?}").as_str(),
@@ -655,6 +713,21 @@ impl gosub_interface::css3::CssValue for CssValue {
         matches!(self, CssValue::Comma)
     }
 
+    fn parse_color(&self) -> Option<(f32, f32, f32, f32)> {
+        // Keywords that are not colors should not turn into black
+        if let CssValue::String(s) = self {
+            let is_color = s.starts_with('#')
+                || matches!(s.as_str(), "transparent" | "currentcolor")
+                || is_named_color(s)
+                || is_system_color(s);
+            if !is_color {
+                return None;
+            }
+        }
+
+        self.to_color().map(|color| (color.r, color.g, color.b, color.a))
+    }
+
     fn is_none(&self) -> bool {
         matches!(self, CssValue::None)
     }
@@ -665,6 +738,9 @@ mod test {
     use std::vec;
 
     use super::*;
+    use crate::Css3;
+    use gosub_interface::css3::CssStylesheet as _;
+    use gosub_shared::config::ParserConfig;
 
     // #[test]
     // fn test_css_value_to_color() {
@@ -769,4 +845,20 @@ mod test {
         assert!(specificity6 > specificity7);
         assert!(specificity7 < specificity8);
     }
+
+    #[test]
+    fn test_matches_sibling_attribute() {
+        let stylesheet = Css3::parse_str(
+            "div.open + p { color: red } [data-x] ~ span { color: blue } .a .b > p, #c p { color: green }",
+            ParserConfig::default(),
+            CssOrigin::Author,
+            "test.css",
+        )
+        .unwrap();
+
+        assert!(stylesheet.matches_sibling_attribute("class"));
+        assert!(stylesheet.matches_sibling_attribute("data-x"));
+        assert!(!stylesheet.matches_sibling_attribute("id"));
+        assert!(!stylesheet.matches_sibling_attribute("title"));
+    }
 }
@@ -1,9 +1,13 @@
 use crate::functions::attr::resolve_attr;
 use crate::functions::calc::resolve_calc;
 use crate::functions::var::resolve_var;
+use crate::matcher::background::expand_background;
+use crate::matcher::border_radius::expand_border_radius;
 use crate::matcher::property_definitions::get_css_definitions;
-use crate::matcher::shorthands::FixList;
-use crate::matcher::styling::{match_selector, CssProperties, CssProperty, DeclarationProperty};
+use crate::matcher::shorthands::{FixList, FixListInfo};
+use crate::matcher::styling::{
+    match_pseudo_element_selector, match_selector, CssProperties, CssProperty, DeclarationProperty,
+};
 use crate::stylesheet::{CssDeclaration, CssValue, Specificity};
 use crate::{load_default_useragent_stylesheet, Css3};
 use gosub_interface::config::{HasDocument, HasRenderTree};
@@ -17,6 +21,9 @@ use gosub_shared::node::NodeId;
 use log::warn;
 use std::slice;
 
+/// Splits the value of a shorthand into its longhands, or returns None when the value is not valid
+type ExpandShorthand = fn(&[CssValue]) -> Option<Vec<(&'static str, CssValue)>>;
+
 #[derive(Debug, Clone)]
 pub struct Css3System;
 
@@ -32,6 +39,15 @@ impl CssSystem for Css3System {
         Css3::parse_str(str, config, origin, url)
     }
 
+    fn parse_inline_style(style: &str) -> Self::Stylesheet {
+        let config = ParserConfig {
+            ignore_errors: true,
+            ..Default::default()
+        };
+
+        Css3::parse_inline_style(style, config, "inline-style")
+    }
+
     fn properties_from_node<C: HasDocument<CssSystem = Self>>(
         node: &C::Node,
         sheets: &[Self::Stylesheet],
@@ -59,52 +75,38 @@ impl CssSystem for Css3System {
 
                     // Selector matched, so we add all declared values to the map
                     for declaration in rule.declarations() {
-                        // Step 1: find the property in our CSS definition list
-                        let Some(definition) = definitions.find_property(&declaration.property) else {
-                            // If not found, we skip this declaration
-                            warn!("Definition is not found for property {:?}", declaration.property);
-                            continue;
-                        };
-
-                        let value = resolve_functions::<C>(&declaration.value, node, doc);
-
-                        let match_value = if let CssValue::List(value) = &value {
-                            &**value
-                        } else {
-                            slice::from_ref(&value)
-                        };
-
-                        // Check if the declaration matches the definition and return the "expanded" order
-                        let res = definition.matches_and_shorthands(match_value, &mut fix_list);
-                        if !res {
-                            warn!("Declaration does not match definition: {declaration:?}");
-                            continue;
-                        }
-
-                        let value = if let CssValue::List(mut value) = value {
-                            if value.len() == 1 {
-                                value.pop().expect("unreachable")
-                            } else {
-                                CssValue::List(value)
-                            }
-                        } else {
-                            value
-                        };
-
-                        // create property for the given values
-                        let property_name = declaration.property.clone();
-                        let decl = CssDeclaration {
-                            property: property_name.to_string(),
-                            value,
-                            important: declaration.important,
-                        };
-
-                        add_property_to_map(&mut css_map_entry, sheet, specificity, &decl);
+                        add_declaration::<C>(
+                            &mut css_map_entry,
+                            &mut fix_list,
+                            sheet,
+                            specificity,
+                            declaration,
+                            node,
+                            doc,
+                        );
                     }
                 }
             }
         }
 
+        // Declarations from the style attribute have the inline specificity, so they win from all author declarations
+        // with the same importance
+        if let Some(sheet) = node.get_element_data().and_then(|data| data.inline_style()) {
+            for rule in &sheet.rules {
+                for declaration in rule.declarations() {
+                    add_declaration::<C>(
+                        &mut css_map_entry,
+                        &mut fix_list,
+                        sheet,
+                        Specificity::inline(),
+                        declaration,
+                        node,
+                        doc,
+                    );
+                }
+            }
+        }
+
         fix_list.resolve_nested(definitions);
 
         fix_list.apply(&mut css_map_entry);
@@ -112,10 +114,86 @@ impl CssSystem for Css3System {
         Some(css_map_entry)
     }
 
+    fn pseudo_element_properties_from_node<C: HasDocument<CssSystem = Self>>(
+        node: &C::Node,
+        sheets: &[Self::Stylesheet],
+        doc: &C::Document,
+        id: NodeId,
+        pseudo: &str,
+    ) -> Option<Self::PropertyMap> {
+        if !node.is_element_node() {
+            return None;
+        }
+
+        let mut css_map_entry = CssProperties::new();
+        let mut fix_list = FixList::new();
+        let mut matched_any = false;
+
+        for sheet in sheets {
+            for rule in &sheet.rules {
+                for selector in rule.selectors() {
+                    let (matched, specificity) = match_pseudo_element_selector::<C>(doc, id, selector, pseudo);
+
+                    if !matched {
+                        continue;
+                    }
+
+                    matched_any = true;
+
+                    for declaration in rule.declarations() {
+                        add_declaration::<C>(
+                            &mut css_map_entry,
+                            &mut fix_list,
+                            sheet,
+                            specificity,
+                            declaration,
+                            node,
+                            doc,
+                        );
+                    }
+                }
+            }
+        }
+
+        if !matched_any {
+            return None;
+        }
+
+        fix_list.resolve_nested(get_css_definitions());
+
+        fix_list.apply(&mut css_map_entry);
+
+        // Pseudo-elements are not part of the render tree, so their values are not resolved with the inheritance
+        for (_, prop) in css_map_entry.iter_mut() {
+            prop.compute_value();
+        }
+
+        Some(css_map_entry)
+    }
+
     fn inheritance<C: HasRenderTree<CssSystem = Self>>(tree: &mut C::RenderTree) {
         Self::resolve_inheritance::<C>(tree, tree.root(), &Vec::new());
     }
 
+    fn inheritance_from<C: HasRenderTree<CssSystem = Self>>(
+        tree: &mut C::RenderTree,
+        node_id: <C::RenderTree as RenderTree<C>>::NodeId,
+        parent_id: <C::RenderTree as RenderTree<C>>::NodeId,
+    ) {
+        let Some(parent) = tree.get_node(parent_id) else {
+            return;
+        };
+
+        let inherit_props = parent
+            .props()
+            .iter()
+            .filter(|(name, _)| prop_is_inherit(name))
+            .map(|(name, prop)| (name.to_owned(), prop.actual.clone()))
+            .collect();
+
+        Self::resolve_inheritance::<C>(tree, node_id, &inherit_props);
+    }
+
     fn load_default_useragent_stylesheet() -> Self::Stylesheet {
         load_default_useragent_stylesheet()
     }
@@ -175,6 +253,92 @@ pub fn prop_is_inherit(name: &str) -> bool {
         .is_some_and(|def| def.inherited)
 }
 
+/// Checks a declaration against its property definition and adds the (expanded) values to the map
+fn add_declaration<C: HasDocument<CssSystem = Css3System>>(
+    css_map_entry: &mut CssProperties,
+    fix_list: &mut FixList,
+    sheet: &crate::stylesheet::CssStylesheet,
+    specificity: Specificity,
+    declaration: &CssDeclaration,
+    node: &C::Node,
+    doc: &C::Document,
+) {
+    let definitions = get_css_definitions();
+
+    // Step 1: find the property in our CSS definition list
+    let Some(definition) = definitions.find_property(&declaration.property) else {
+        // If not found, we skip this declaration
+        warn!("Definition is not found for property {:?}", declaration.property);
+        return;
+    };
+
+    let value = resolve_functions::<C>(&declaration.value, node, doc);
+
+    let match_value = if let CssValue::List(value) = &value {
+        &**value
+    } else {
+        slice::from_ref(&value)
+    };
+
+    // The longhands of `background` and `border-radius` are added in place, so a longhand that is declared later
+    // with the same specificity still wins from the shorthand
+    let expand: Option<ExpandShorthand> = match declaration.property.as_str() {
+        "background" => Some(expand_background),
+        "border-radius" => Some(expand_border_radius),
+        _ => None,
+    };
+    if let Some(expand) = expand {
+        let Some(longhands) = expand(match_value) else {
+            warn!("Declaration does not match definition: {declaration:?}");
+            return;
+        };
+
+        for (name, value) in longhands {
+            let decl = CssDeclaration {
+                property: name.to_string(),
+                value,
+                important: declaration.important,
+            };
+            add_property_to_map(css_map_entry, sheet, specificity, &decl);
+        }
+        return;
+    }
+
+    // Check if the declaration matches the definition and return the "expanded" order. The longhands of a
+    // shorthand property get the same origin, importance and specificity as the declaration itself.
+    fix_list.set_info(FixListInfo::new(
+        sheet.origin,
+        declaration.important,
+        &sheet.url,
+        specificity,
+    ));
+
+    let res = definition.matches_and_shorthands(match_value, fix_list);
+    if !res {
+        warn!("Declaration does not match definition: {declaration:?}");
+        return;
+    }
+
+    let value = if let CssValue::List(mut value) = value {
+        if value.len() == 1 {
+            value.pop().expect("unreachable")
+        } else {
+            CssValue::List(value)
+        }
+    } else {
+        value
+    };
+
+    // create property for the given values
+    let decl = CssDeclaration {
+        property: declaration.property.clone(),
+        value,
+        important: declaration.important,
+    };
+
+    add_property_to_map(css_map_entry, sheet, specificity, &decl);
+}
+
 pub fn add_property_to_map(
     css_map_entry: &mut CssProperties,
     sheet: &crate::stylesheet::CssStylesheet,
@@ -222,7 +386,7 @@ pub fn add_property_to_map(
 pub fn node_is_unrenderable<C: HasDocument>(node: &C::Node) -> bool {
     // There are more elements that are not renderable, but for now we only remove the most common ones
 
-    const REMOVABLE_ELEMENTS: [&str; 6] = ["head", "script", "style", "svg", "noscript", "title"];
+    const REMOVABLE_ELEMENTS: [&str; 5] = ["head", "script", "style", "noscript", "title"];
 
     if let Some(element_data) = node.get_element_data() {
         if REMOVABLE_ELEMENTS.contains(&element_data.name()) {
@@ -247,7 +411,8 @@ pub fn resolve_functions<C: HasDocument>(value: &CssValue, node: &C::Node, doc:
                     "calc" => resolve_calc(values),
                     "attr" => resolve_attr::<C>(values, node),
                     "var" => resolve_var::<C>(values, doc, node),
-                    _ => vec![val.clone()],
+                    // Other functions (ie: `rgb()` or `translate()`) are matched against the property syntax as is
+                    _ => return val.clone(),
                 };
 
                 CssValue::List(resolved)
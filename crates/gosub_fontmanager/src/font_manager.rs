@@ -1,3 +1,6 @@
 mod cache;
+pub mod coverage;
 pub mod font_info;
 pub mod manager;
+pub mod matching;
+pub mod web_font;
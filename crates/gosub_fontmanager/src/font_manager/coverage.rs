@@ -0,0 +1,92 @@
+//! Glyph coverage of a font face: the characters its character map has a glyph for. The coverage is read once per
+//! face, so finding a font for a character does not read and parse font files over and over again.
+
+use std::path::Path;
+
+/// Characters a font face has glyphs for, as sorted, disjoint ranges of code points
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// Inclusive ranges of code points
+    ranges: Vec<(u32, u32)>,
+}
+
+impl Coverage {
+    /// Returns the coverage of the face at `index` in the font data, or None when the data is not a font
+    #[must_use]
+    pub fn from_data(data: &[u8], index: usize) -> Option<Self> {
+        let font = swash::FontRef::from_index(data, index)?;
+
+        let mut code_points = Vec::new();
+        font.charmap().enumerate(|code_point, glyph| {
+            if glyph != 0 {
+                code_points.push(code_point);
+            }
+        });
+
+        Some(Self::from_code_points(code_points))
+    }
+
+    /// Returns the coverage of the face at `index` in a font file, which is empty when the file can not be read
+    #[must_use]
+    pub fn from_file(path: &Path, index: usize) -> Self {
+        std::fs::read(path)
+            .ok()
+            .and_then(|data| Self::from_data(&data, index))
+            .unwrap_or_default()
+    }
+
+    /// Returns the coverage of the code points, in any order
+    #[must_use]
+    pub fn from_code_points(mut code_points: Vec<u32>) -> Self {
+        code_points.sort_unstable();
+
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for code_point in code_points {
+            match ranges.last_mut() {
+                Some((_, end)) if code_point <= *end + 1 => *end = (*end).max(code_point),
+                _ => ranges.push((code_point, code_point)),
+            }
+        }
+
+        Self { ranges }
+    }
+
+    /// Returns true when the face has a glyph for the character
+    #[must_use]
+    pub fn contains(&self, ch: char) -> bool {
+        let code_point = ch as u32;
+        let idx = self.ranges.partition_point(|(_, end)| *end < code_point);
+
+        self.ranges.get(idx).is_some_and(|(start, _)| *start <= code_point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_point_ranges() {
+        let coverage = Coverage::from_code_points(vec![0x43, 0x41, 0x42, 0x42, 0x61, 0x1F600]);
+        assert_eq!(coverage.ranges, vec![(0x41, 0x43), (0x61, 0x61), (0x1F600, 0x1F600)]);
+
+        assert!(coverage.contains('A'));
+        assert!(coverage.contains('C'));
+        assert!(!coverage.contains('D'));
+        assert!(coverage.contains('a'));
+        assert!(!coverage.contains('@'));
+        assert!(coverage.contains('\u{1F600}'));
+        assert!(!Coverage::default().contains('A'));
+    }
+
+    #[test]
+    fn font_coverage() {
+        let data = include_bytes!("../../../gosub_shared/resources/fonts/Roboto-Regular.ttf");
+        let coverage = Coverage::from_data(data, 0).unwrap();
+
+        assert!(coverage.contains('A'));
+        assert!(coverage.contains('é'));
+        assert!(!coverage.contains('\u{4E2D}'));
+        assert!(Coverage::from_data(b"<html></html>", 0).is_none());
+    }
+}
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use gosub_interface::font::{FontError, FontInfo as TFontInfo, FontStyle};
+use gosub_interface::font::{FontBlob, FontError, FontInfo as TFontInfo, FontStyle};
 
 #[derive(Clone, Debug)]
 pub struct FontInfo {
@@ -18,6 +18,8 @@ pub struct FontInfo {
     pub path: Option<PathBuf>,
     /// Index of the face in the font-file
     pub index: Option<i32>,
+    /// Font data for fonts that are not loaded from a file (web fonts)
+    pub data: Option<FontBlob>,
 }
 
 impl TFontInfo for FontInfo {
@@ -58,6 +60,7 @@ impl TFontInfo for FontInfo {
             monospaced: false,
             path: None,
             index: None,
+            data: None,
         })
     }
 
@@ -70,6 +73,7 @@ impl TFontInfo for FontInfo {
             monospaced: self.monospaced,
             path: self.path.clone(),
             index: self.index,
+            data: self.data.clone(),
         }
     }
 
@@ -82,6 +86,7 @@ impl TFontInfo for FontInfo {
             monospaced: self.monospaced,
             path: self.path.clone(),
             index: self.index,
+            data: self.data.clone(),
         }
     }
 
@@ -94,6 +99,7 @@ impl TFontInfo for FontInfo {
             monospaced: self.monospaced,
             path: self.path.clone(),
             index: self.index,
+            data: self.data.clone(),
         }
     }
 
@@ -106,6 +112,7 @@ impl TFontInfo for FontInfo {
             monospaced: self.monospaced,
             path: self.path.clone(),
             index: self.index,
+            data: self.data.clone(),
         }
     }
 
@@ -118,6 +125,7 @@ impl TFontInfo for FontInfo {
             monospaced,
             path: self.path.clone(),
             index: self.index,
+            data: self.data.clone(),
         }
     }
 
@@ -130,9 +138,14 @@ impl TFontInfo for FontInfo {
             monospaced: self.monospaced,
             path: Some(path),
             index,
+            data: None,
         }
     }
 
+    fn data(&self) -> Option<FontBlob> {
+        self.data.clone()
+    }
+
     fn to_description(&self, size: f32) -> String {
         format!("{} {} {}", self.family, self.style, size)
     }
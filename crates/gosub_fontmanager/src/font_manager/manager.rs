@@ -1,12 +1,17 @@
+use crate::font_manager::coverage::Coverage;
 use crate::font_manager::font_info::FontInfo;
+use crate::font_manager::matching::{match_faces, GenericFamily};
+use crate::font_manager::web_font::WebFont;
 use anyhow::anyhow;
+use cow_utils::CowUtils;
 use font_kit::handle::Handle;
+use gosub_config::{config, config_store};
 use gosub_interface::font::FontManager as TFontManager;
-use gosub_interface::font::FontStyle;
-use log::error;
-use std::collections::HashSet;
+use gosub_interface::font::{FontError, FontFaceDescriptor, FontStyle};
+use log::{error, info, warn};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 #[allow(dead_code)]
 pub const LOG_TARGET: &str = "font-manager";
@@ -18,6 +23,20 @@ thread_local! {
 pub struct FontManager {
     /// Vec of all font-info structures found
     available_fonts: Vec<FontInfo>,
+    /// Installed families that each generic family resolves to, in order of preference
+    generic_families: HashMap<GenericFamily, Vec<String>>,
+    /// Glyph coverage of the faces that were checked for characters, per index in the available fonts. A face is
+    /// read once, the first time it is checked.
+    coverage: Mutex<HashMap<usize, Arc<Coverage>>>,
+    /// Indices of the regular faces of all families in the order they are tried as a fallback: the sans-serif
+    /// families first, then all available families
+    fallback_faces: Vec<usize>,
+    /// Cache of the family that is used as a fallback for a given character
+    fallbacks: Mutex<HashMap<char, Option<String>>>,
+    /// Web fonts registered through `@font-face` rules, per document url
+    web_fonts: HashMap<String, Vec<WebFont>>,
+    /// Document whose web fonts are used for matching
+    active_document: Option<String>,
 }
 
 impl Default for FontManager {
@@ -43,9 +62,20 @@ impl FontManager {
 
         font_info_list.sort_by_key(|fi| fi.family.clone());
 
-        Self {
+        let generic_families = resolve_generic_families(&font_info_list);
+
+        let mut manager = Self {
             available_fonts: font_info_list,
-        }
+            generic_families,
+            coverage: Mutex::new(HashMap::new()),
+            fallback_faces: Vec::new(),
+            fallbacks: Mutex::new(HashMap::new()),
+            web_fonts: HashMap::new(),
+            active_document: None,
+        };
+        manager.fallback_faces = manager.resolve_fallback_faces();
+
+        manager
     }
 
     /// Returns all available fonts for given source-type
@@ -54,17 +84,147 @@ impl FontManager {
         &self.available_fonts
     }
 
+    /// Finds a font for the first family in the list that is available with a normal weight and stretch
     #[must_use]
     pub fn find(&self, families: &[&str], style: FontStyle) -> Option<FontInfo> {
-        for &fam in families {
-            for fi in self.available_fonts() {
-                if fi.family.eq_ignore_ascii_case(fam) && fi.style == style {
-                    return Some(fi.clone());
-                }
+        self.match_font(families, style, 400, 1.0)
+    }
+
+    /// Returns the installed families that the given generic family resolves to
+    #[must_use]
+    pub fn generic_family(&self, generic: GenericFamily) -> &[String] {
+        self.generic_families.get(&generic).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the web fonts of the active document for the given family (case-insensitive)
+    fn web_faces<'a>(&'a self, family: &str) -> Vec<&'a WebFont> {
+        let Some(fonts) = self.active_document.as_ref().and_then(|doc| self.web_fonts.get(doc)) else {
+            return Vec::new();
+        };
+
+        fonts
+            .iter()
+            .filter(|wf| wf.descriptor.family.eq_ignore_ascii_case(family))
+            .collect()
+    }
+
+    /// Finds the best matching web font face of the active document for the given family
+    fn match_web_family(&self, family: &str, style: FontStyle, weight: i32, stretch: f32) -> Option<FontInfo> {
+        let faces: Vec<FontInfo> = self
+            .web_faces(family)
+            .iter()
+            .map(|wf| wf.face_for(weight, stretch))
+            .collect();
+        let refs: Vec<&FontInfo> = faces.iter().collect();
+
+        match_faces(&refs, style, weight, stretch).cloned()
+    }
+
+    /// Returns the web font that the given font info belongs to
+    fn web_font_of(&self, info: &FontInfo) -> Option<&WebFont> {
+        info.data.as_ref()?;
+
+        self.web_fonts.values().flatten().find(|wf| wf.is_face_of(info))
+    }
+
+    /// Returns true when the given font has a glyph for the character
+    fn font_has_char(&self, info: &FontInfo, ch: char) -> bool {
+        if info.data.is_some() {
+            return self.web_font_of(info).is_some_and(|wf| wf.has_glyph(ch));
+        }
+
+        self.index_of(info).is_some_and(|idx| self.has_glyph(idx, ch))
+    }
+
+    /// Registers a decoded web font for the given document
+    fn register_web_font(&mut self, document: &str, font: WebFont) {
+        info!(target: LOG_TARGET, "Registered web font {} for {document}", font.descriptor.family);
+
+        self.web_fonts.entry(document.to_string()).or_default().push(font);
+    }
+
+    /// Returns all faces of the given family (case-insensitive)
+    fn faces<'a>(&'a self, family: &str) -> Vec<&'a FontInfo> {
+        self.available_fonts
+            .iter()
+            .filter(|fi| fi.family.eq_ignore_ascii_case(family))
+            .collect()
+    }
+
+    /// Finds the best matching face within a single (non-generic) family
+    fn match_family(&self, family: &str, style: FontStyle, weight: i32, stretch: f32) -> Option<FontInfo> {
+        match_faces(&self.faces(family), style, weight, stretch).cloned()
+    }
+
+    /// Returns true when the font at index `idx` in the available fonts has a glyph for the given character
+    fn has_glyph(&self, idx: usize, ch: char) -> bool {
+        self.coverage(idx).contains(ch)
+    }
+
+    /// Returns the glyph coverage of the font at index `idx` in the available fonts, which is read from its file the
+    /// first time
+    fn coverage(&self, idx: usize) -> Arc<Coverage> {
+        if let Some(coverage) = self.coverage.lock().unwrap().get(&idx) {
+            return coverage.clone();
+        }
+
+        let coverage = Arc::new(self.available_fonts.get(idx).map_or_else(Coverage::default, |fi| {
+            fi.path.as_ref().map_or_else(Coverage::default, |path| {
+                Coverage::from_file(path, fi.index.unwrap_or(0).max(0) as usize)
+            })
+        }));
+        self.coverage.lock().unwrap().insert(idx, coverage.clone());
+
+        coverage
+    }
+
+    /// Returns the index of the given font in the list of available fonts
+    fn index_of(&self, info: &FontInfo) -> Option<usize> {
+        self.available_fonts
+            .iter()
+            .position(|fi| fi.path == info.path && fi.index == info.index)
+    }
+
+    /// Returns the regular faces of all families in the order they are tried as a fallback. Only the regular face
+    /// of a family is checked; the other faces of a family normally have the same coverage.
+    fn resolve_fallback_faces(&self) -> Vec<usize> {
+        let preferred = self.generic_family(GenericFamily::SansSerif).iter();
+        let all = self.available_fonts.iter().map(|fi| &fi.family);
+
+        let mut tried = HashSet::new();
+        let mut faces = Vec::new();
+        for family in preferred.chain(all) {
+            if !tried.insert(family.cow_to_ascii_lowercase().into_owned()) {
+                continue;
+            }
+
+            let idx = self
+                .match_family(family, FontStyle::Normal, 400, 1.0)
+                .and_then(|face| self.index_of(&face));
+            if let Some(idx) = idx {
+                faces.push(idx);
             }
         }
 
-        None
+        faces
+    }
+
+    /// Finds a family that has a glyph for the given character. The sans-serif families are tried first,
+    /// after which all available families are tried.
+    fn fallback_family(&self, ch: char) -> Option<String> {
+        if let Some(family) = self.fallbacks.lock().unwrap().get(&ch) {
+            return family.clone();
+        }
+
+        let found = self
+            .fallback_faces
+            .iter()
+            .find(|idx| self.has_glyph(**idx, ch))
+            .and_then(|idx| self.available_fonts.get(*idx))
+            .map(|fi| fi.family.clone());
+
+        self.fallbacks.lock().unwrap().insert(ch, found.clone());
+        found
     }
 }
 
@@ -76,15 +236,160 @@ impl TFontManager for FontManager {
     }
 
     fn find_font(&self, families: &[&str], style: FontStyle) -> Option<Self::FontInfo> {
-        for &fam in families {
-            for fi in &self.available_fonts {
-                if fi.family.eq_ignore_ascii_case(fam) && fi.style == style {
-                    return Some(fi.clone());
+        self.find(families, style)
+    }
+
+    fn match_font(&self, families: &[&str], style: FontStyle, weight: i32, stretch: f32) -> Option<Self::FontInfo> {
+        for &family in families {
+            let family = family.trim().trim_matches(|c| c == '"' || c == '\'');
+
+            if let Some(generic) = GenericFamily::from_name(family) {
+                for resolved in self.generic_family(generic) {
+                    if let Some(fi) = self.match_family(resolved, style, weight, stretch) {
+                        return Some(fi);
+                    }
                 }
+                continue;
+            }
+
+            // Fonts defined by @font-face take precedence over installed fonts with the same name
+            if let Some(fi) = self.match_web_family(family, style, weight, stretch) {
+                return Some(fi);
+            }
+
+            if let Some(fi) = self.match_family(family, style, weight, stretch) {
+                return Some(fi);
             }
         }
+
         None
     }
+
+    fn fallback_fonts(&self, text: &str, base: &Self::FontInfo) -> Vec<Self::FontInfo> {
+        let base_web_font = self.web_font_of(base);
+        if base_web_font.is_none() && self.index_of(base).is_none() {
+            return Vec::new();
+        }
+
+        let mut seen = HashSet::new();
+        let mut fonts: Vec<FontInfo> = Vec::new();
+
+        for ch in text.chars() {
+            if ch.is_whitespace() || ch.is_control() || !seen.insert(ch) {
+                continue;
+            }
+
+            if self.font_has_char(base, ch) {
+                continue;
+            }
+
+            // Characters may already be covered by one of the fallbacks we found earlier
+            if fonts.iter().any(|fi| self.font_has_char(fi, ch)) {
+                continue;
+            }
+
+            // Web fonts are often split into multiple faces with different unicode-ranges
+            if let Some(base_web_font) = base_web_font {
+                let sibling = self
+                    .web_faces(&base_web_font.descriptor.family)
+                    .into_iter()
+                    .find(|wf| wf.descriptor.style == base_web_font.descriptor.style && wf.has_glyph(ch));
+
+                if let Some(sibling) = sibling {
+                    fonts.push(sibling.face_for(base.weight, base.stretch));
+                    continue;
+                }
+            }
+
+            let Some(family) = self.fallback_family(ch) else {
+                continue;
+            };
+
+            if let Some(fi) = self.match_family(&family, base.style, base.weight, base.stretch) {
+                fonts.push(fi);
+            }
+        }
+
+        fonts
+    }
+
+    fn add_web_font(&mut self, document: &str, descriptor: &FontFaceDescriptor, data: &[u8]) -> Result<(), FontError> {
+        let font = WebFont::load(descriptor, data, 0)?;
+        self.register_web_font(document, font);
+
+        Ok(())
+    }
+
+    fn add_local_font(&mut self, document: &str, descriptor: &FontFaceDescriptor, name: &str) -> Result<(), FontError> {
+        let info = font_kit::source::SystemSource::new()
+            .select_by_postscript_name(name)
+            .ok()
+            .and_then(|handle| match handle {
+                Handle::Path { path, font_index } => Some((resolve_symlink(path), font_index)),
+                Handle::Memory { .. } => None,
+            })
+            .or_else(|| {
+                let (weight, _) = descriptor.weight;
+                let (stretch, _) = descriptor.stretch;
+                let fi = self.match_family(name, descriptor.style, weight, stretch)?;
+                Some((fi.path?, fi.index.unwrap_or(0) as u32))
+            });
+
+        let Some((path, index)) = info else {
+            return Err(FontError::FontNotFound(name.to_string()));
+        };
+
+        let data = std::fs::read(&path).map_err(|e| FontError::InvalidFont(format!("{}: {e}", path.display())))?;
+
+        let font = WebFont::load(descriptor, &data, index)?;
+        self.register_web_font(document, font);
+
+        Ok(())
+    }
+
+    fn remove_web_fonts(&mut self, document: &str) {
+        self.web_fonts.remove(document);
+    }
+
+    fn set_active_document(&mut self, document: Option<&str>) {
+        self.active_document = document.map(ToString::to_string);
+    }
+}
+
+/// Resolves each generic family to the installed families. The families can be configured with the
+/// `renderer.font.<generic>` settings as a comma separated list. When no configured family is installed,
+/// the platform defaults are used.
+fn resolve_generic_families(fonts: &[FontInfo]) -> HashMap<GenericFamily, Vec<String>> {
+    let installed = |family: &str| fonts.iter().any(|fi| fi.family.eq_ignore_ascii_case(family));
+
+    let mut generic_families = HashMap::new();
+    for generic in GenericFamily::ALL {
+        let configured = config!(string & generic.config_key());
+
+        let mut families: Vec<String> = configured
+            .split(',')
+            .map(str::trim)
+            .filter(|family| !family.is_empty() && installed(family))
+            .map(ToString::to_string)
+            .collect();
+
+        if families.is_empty() {
+            families = generic
+                .default_families()
+                .iter()
+                .filter(|family| installed(family))
+                .map(ToString::to_string)
+                .collect();
+        }
+
+        if families.is_empty() {
+            warn!(target: LOG_TARGET, "No installed font found for generic family {}", generic.name());
+        }
+
+        generic_families.insert(generic, families);
+    }
+
+    generic_families
 }
 
 fn handle_to_info(seen_paths: &mut HashSet<PathBuf>, handle: &Handle) -> Result<FontInfo, anyhow::Error> {
@@ -119,6 +424,7 @@ fn handle_to_info(seen_paths: &mut HashSet<PathBuf>, handle: &Handle) -> Result<
         monospaced: font.is_monospace(),
         path: Some(resolved_path.clone()),
         index: Some(*font_index as i32),
+        data: None,
     })
 }
 
@@ -0,0 +1,272 @@
+//! Font matching as described in the CSS Fonts Module Level 4, section 5.2
+//! (<https://www.w3.org/TR/css-fonts-4/#font-matching-algorithm>).
+//!
+//! Given a set of faces from a single family, the faces are narrowed down in three steps: first by
+//! `font-stretch`, then by `font-style` and finally by `font-weight`. Each step keeps only the faces
+//! that are nearest to the desired value, so the result is always a single best face.
+
+use crate::font_manager::font_info::FontInfo;
+use gosub_interface::font::FontStyle;
+
+/// Generic font families as defined by CSS. These do not map onto an actual font family, but are
+/// resolved to one or more configured system fonts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GenericFamily {
+    Serif,
+    SansSerif,
+    Monospace,
+    Cursive,
+    Fantasy,
+    SystemUi,
+}
+
+impl GenericFamily {
+    pub const ALL: [GenericFamily; 6] = [
+        GenericFamily::Serif,
+        GenericFamily::SansSerif,
+        GenericFamily::Monospace,
+        GenericFamily::Cursive,
+        GenericFamily::Fantasy,
+        GenericFamily::SystemUi,
+    ];
+
+    /// Returns the generic family for the given family name, or None when the name is not a generic family
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+
+        if name.eq_ignore_ascii_case("serif") {
+            Some(Self::Serif)
+        } else if name.eq_ignore_ascii_case("sans-serif") {
+            Some(Self::SansSerif)
+        } else if name.eq_ignore_ascii_case("monospace") {
+            Some(Self::Monospace)
+        } else if name.eq_ignore_ascii_case("cursive") {
+            Some(Self::Cursive)
+        } else if name.eq_ignore_ascii_case("fantasy") {
+            Some(Self::Fantasy)
+        } else if name.eq_ignore_ascii_case("system-ui") {
+            Some(Self::SystemUi)
+        } else {
+            None
+        }
+    }
+
+    /// Name of the generic family as used in CSS
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Serif => "serif",
+            Self::SansSerif => "sans-serif",
+            Self::Monospace => "monospace",
+            Self::Cursive => "cursive",
+            Self::Fantasy => "fantasy",
+            Self::SystemUi => "system-ui",
+        }
+    }
+
+    /// Name of the configuration setting that can override the fonts for this generic family
+    #[must_use]
+    pub fn config_key(&self) -> String {
+        format!("renderer.font.{}", self.name())
+    }
+
+    /// Returns the built-in list of candidate families for this generic family on the current platform.
+    /// The first family that is installed on the system will be used.
+    #[must_use]
+    pub fn default_families(&self) -> &'static [&'static str] {
+        #[cfg(target_os = "windows")]
+        return match self {
+            Self::Serif => &["Times New Roman", "Georgia"],
+            Self::SansSerif => &["Arial", "Segoe UI", "Verdana"],
+            Self::Monospace => &["Consolas", "Courier New"],
+            Self::Cursive => &["Comic Sans MS", "Segoe Script"],
+            Self::Fantasy => &["Impact", "Arial"],
+            Self::SystemUi => &["Segoe UI", "Arial"],
+        };
+
+        #[cfg(target_os = "macos")]
+        return match self {
+            Self::Serif => &["Times", "Times New Roman", "Georgia"],
+            Self::SansSerif => &["Helvetica", "Helvetica Neue", "Arial"],
+            Self::Monospace => &["Menlo", "Monaco", "Courier New"],
+            Self::Cursive => &["Apple Chancery", "Snell Roundhand"],
+            Self::Fantasy => &["Papyrus", "Impact"],
+            Self::SystemUi => &["SF Pro Text", "Helvetica Neue", "Helvetica"],
+        };
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        match self {
+            Self::Serif => &["DejaVu Serif", "Liberation Serif", "Noto Serif", "Times New Roman"],
+            Self::SansSerif => &["DejaVu Sans", "Liberation Sans", "Noto Sans", "Arial"],
+            Self::Monospace => &["DejaVu Sans Mono", "Liberation Mono", "Noto Sans Mono", "Courier New"],
+            Self::Cursive => &["Comic Neue", "Comic Sans MS", "URW Chancery L"],
+            Self::Fantasy => &["Impact", "DejaVu Sans"],
+            Self::SystemUi => &["Cantarell", "Ubuntu", "Noto Sans", "DejaVu Sans"],
+        }
+    }
+}
+
+/// Selects the best matching face from the faces of a single family. Returns None when `faces` is empty.
+#[must_use]
+pub fn match_faces<'a>(faces: &[&'a FontInfo], style: FontStyle, weight: i32, stretch: f32) -> Option<&'a FontInfo> {
+    let faces = narrow_by_stretch(faces, stretch);
+    let faces = narrow_by_style(&faces, style);
+    let faces = narrow_by_weight(&faces, weight);
+
+    faces.first().copied()
+}
+
+/// Returns the faces with the nearest stretch. When the desired stretch is normal or condensed, narrower
+/// faces are preferred over wider ones, otherwise wider faces are preferred.
+fn narrow_by_stretch<'a>(faces: &[&'a FontInfo], desired: f32) -> Vec<&'a FontInfo> {
+    let narrower = faces
+        .iter()
+        .map(|f| f.stretch)
+        .filter(|s| *s <= desired)
+        .reduce(f32::max);
+    let wider = faces
+        .iter()
+        .map(|f| f.stretch)
+        .filter(|s| *s > desired)
+        .reduce(f32::min);
+
+    let selected = if desired <= 1.0 {
+        narrower.or(wider)
+    } else {
+        wider.or(narrower)
+    };
+
+    match selected {
+        Some(stretch) => faces.iter().copied().filter(|f| f.stretch == stretch).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Returns the faces with the best matching style. Italic falls back to oblique and oblique falls back
+/// to italic, before falling back to normal.
+fn narrow_by_style<'a>(faces: &[&'a FontInfo], desired: FontStyle) -> Vec<&'a FontInfo> {
+    let order: [FontStyle; 3] = match desired {
+        FontStyle::Italic => [FontStyle::Italic, FontStyle::Oblique, FontStyle::Normal],
+        FontStyle::Oblique => [FontStyle::Oblique, FontStyle::Italic, FontStyle::Normal],
+        FontStyle::Normal => [FontStyle::Normal, FontStyle::Oblique, FontStyle::Italic],
+    };
+
+    for style in order {
+        let matched: Vec<_> = faces.iter().copied().filter(|f| f.style == style).collect();
+        if !matched.is_empty() {
+            return matched;
+        }
+    }
+
+    Vec::new()
+}
+
+/// Returns the faces with the nearest weight, according to the weight matching rules of CSS:
+///
+/// - desired weight between 400 and 500: weights up to 500 ascending, then lighter weights descending,
+///   then heavier weights ascending.
+/// - desired weight below 400: lighter weights descending, then heavier weights ascending.
+/// - desired weight above 500: heavier weights ascending, then lighter weights descending.
+fn narrow_by_weight<'a>(faces: &[&'a FontInfo], desired: i32) -> Vec<&'a FontInfo> {
+    let weights = || faces.iter().map(|f| f.weight);
+
+    let lighter_desc = || weights().filter(|w| *w <= desired).max();
+    let heavier_asc = || weights().filter(|w| *w >= desired).min();
+
+    let selected = if (400..=500).contains(&desired) {
+        weights()
+            .filter(|w| *w >= desired && *w <= 500)
+            .min()
+            .or_else(lighter_desc)
+            .or_else(heavier_asc)
+    } else if desired < 400 {
+        lighter_desc().or_else(heavier_asc)
+    } else {
+        heavier_asc().or_else(lighter_desc)
+    };
+
+    match selected {
+        Some(weight) => faces.iter().copied().filter(|f| f.weight == weight).collect(),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn face(style: FontStyle, weight: i32, stretch: f32) -> FontInfo {
+        FontInfo {
+            family: "Test".into(),
+            style,
+            weight,
+            stretch,
+            monospaced: false,
+            path: None,
+            index: None,
+            data: None,
+        }
+    }
+
+    #[test]
+    fn weight_matching() {
+        let faces = [
+            face(FontStyle::Normal, 300, 1.0),
+            face(FontStyle::Normal, 400, 1.0),
+            face(FontStyle::Normal, 700, 1.0),
+        ];
+        let refs: Vec<_> = faces.iter().collect();
+
+        assert_eq!(match_faces(&refs, FontStyle::Normal, 400, 1.0).unwrap().weight, 400);
+        assert_eq!(match_faces(&refs, FontStyle::Normal, 500, 1.0).unwrap().weight, 400);
+        assert_eq!(match_faces(&refs, FontStyle::Normal, 600, 1.0).unwrap().weight, 700);
+        assert_eq!(match_faces(&refs, FontStyle::Normal, 900, 1.0).unwrap().weight, 700);
+        assert_eq!(match_faces(&refs, FontStyle::Normal, 350, 1.0).unwrap().weight, 300);
+        assert_eq!(match_faces(&refs, FontStyle::Normal, 100, 1.0).unwrap().weight, 300);
+    }
+
+    #[test]
+    fn weight_matching_prefers_up_to_500() {
+        let faces = [face(FontStyle::Normal, 300, 1.0), face(FontStyle::Normal, 500, 1.0)];
+        let refs: Vec<_> = faces.iter().collect();
+
+        assert_eq!(match_faces(&refs, FontStyle::Normal, 400, 1.0).unwrap().weight, 500);
+    }
+
+    #[test]
+    fn style_matching() {
+        let faces = [face(FontStyle::Normal, 400, 1.0), face(FontStyle::Oblique, 400, 1.0)];
+        let refs: Vec<_> = faces.iter().collect();
+
+        assert_eq!(
+            match_faces(&refs, FontStyle::Italic, 400, 1.0).unwrap().style,
+            FontStyle::Oblique
+        );
+        assert_eq!(
+            match_faces(&refs, FontStyle::Normal, 400, 1.0).unwrap().style,
+            FontStyle::Normal
+        );
+    }
+
+    #[test]
+    fn stretch_is_matched_before_weight() {
+        let faces = [
+            face(FontStyle::Normal, 700, 0.75),
+            face(FontStyle::Normal, 400, 1.0),
+            face(FontStyle::Normal, 400, 1.25),
+        ];
+        let refs: Vec<_> = faces.iter().collect();
+
+        assert_eq!(match_faces(&refs, FontStyle::Normal, 400, 0.8).unwrap().stretch, 0.75);
+        assert_eq!(match_faces(&refs, FontStyle::Normal, 700, 1.0).unwrap().stretch, 1.0);
+        assert_eq!(match_faces(&refs, FontStyle::Normal, 400, 1.1).unwrap().stretch, 1.25);
+    }
+
+    #[test]
+    fn generic_families() {
+        assert_eq!(GenericFamily::from_name("Sans-Serif"), Some(GenericFamily::SansSerif));
+        assert_eq!(GenericFamily::from_name("system-ui"), Some(GenericFamily::SystemUi));
+        assert_eq!(GenericFamily::from_name("Arial"), None);
+    }
+}
@@ -0,0 +1,165 @@
+//! Web fonts that are loaded through `@font-face` rules. Font data is decoded from TTF, OTF, WOFF or WOFF2
+//! and kept in memory, as these fonts are not installed on the system.
+
+use crate::font_manager::coverage::Coverage;
+use crate::font_manager::font_info::FontInfo;
+use gosub_interface::font::{FontBlob, FontError, FontFaceDescriptor};
+use std::sync::Arc;
+
+/// Container format of a font file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FontFormat {
+    /// Plain TrueType or OpenType font (or collection)
+    Sfnt,
+    Woff,
+    Woff2,
+}
+
+impl FontFormat {
+    /// Detects the format of the font data by looking at its signature
+    #[must_use]
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        match data.get(0..4)? {
+            b"wOFF" => Some(Self::Woff),
+            b"wOF2" => Some(Self::Woff2),
+            [0x00, 0x01, 0x00, 0x00] | b"OTTO" | b"true" | b"ttcf" => Some(Self::Sfnt),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes the font data into a plain TrueType/OpenType font
+pub fn decode(data: &[u8]) -> Result<Vec<u8>, FontError> {
+    match FontFormat::detect(data) {
+        Some(FontFormat::Sfnt) => Ok(data.to_vec()),
+        Some(FontFormat::Woff) => {
+            wuff::decompress_woff1(data).map_err(|e| FontError::InvalidFont(format!("Cannot decode WOFF: {e:?}")))
+        }
+        Some(FontFormat::Woff2) => {
+            wuff::decompress_woff2(data).map_err(|e| FontError::InvalidFont(format!("Cannot decode WOFF2: {e:?}")))
+        }
+        None => Err(FontError::UnsupportedFeature("Unknown font format".into())),
+    }
+}
+
+/// A font face that is registered through a `@font-face` rule
+#[derive(Clone, Debug)]
+pub struct WebFont {
+    /// Descriptors of the `@font-face` rule
+    pub descriptor: FontFaceDescriptor,
+    /// Font info of the face, with the (decoded) font data
+    pub info: FontInfo,
+    /// Characters the face has glyphs for
+    coverage: Arc<Coverage>,
+}
+
+impl WebFont {
+    /// Creates a web font from (encoded) font data. The face at `index` is used for font collections.
+    pub fn load(descriptor: &FontFaceDescriptor, data: &[u8], index: u32) -> Result<Self, FontError> {
+        let data = decode(data)?;
+
+        // Fonts without a character map are of no use to us, and this catches most corrupted files
+        let font = swash::FontRef::from_index(&data, index as usize)
+            .filter(|font| font.table(swash::tag_from_bytes(b"cmap")).is_some());
+        let Some(font) = font else {
+            return Err(FontError::InvalidFont(format!(
+                "Cannot read font data for {}",
+                descriptor.family
+            )));
+        };
+        let monospaced = font.metrics(&[]).is_monospace;
+        let coverage = Coverage::from_data(&data, index as usize).unwrap_or_default();
+
+        let info = FontInfo {
+            family: descriptor.family.clone(),
+            style: descriptor.style,
+            weight: descriptor.weight.0,
+            stretch: descriptor.stretch.0,
+            monospaced,
+            path: None,
+            index: Some(index as i32),
+            data: Some(FontBlob::new(Arc::new(data), index)),
+        };
+
+        Ok(Self {
+            descriptor: descriptor.clone(),
+            info,
+            coverage: Arc::new(coverage),
+        })
+    }
+
+    /// Returns the font info of this face for the desired weight and stretch. Faces with a weight or
+    /// stretch range (variable fonts) match any value in that range.
+    #[must_use]
+    pub fn face_for(&self, weight: i32, stretch: f32) -> FontInfo {
+        let (min_weight, max_weight) = self.descriptor.weight;
+        let (min_stretch, max_stretch) = self.descriptor.stretch;
+
+        let mut info = self.info.clone();
+        info.weight = weight.clamp(min_weight, max_weight);
+        info.stretch = stretch.clamp(min_stretch, max_stretch);
+        info
+    }
+
+    /// Returns true when the given font info refers to this face
+    #[must_use]
+    pub fn is_face_of(&self, info: &FontInfo) -> bool {
+        match (&self.info.data, &info.data) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a.data(), b.data()),
+            _ => false,
+        }
+    }
+
+    /// Returns true when the face may be used for the character and has a glyph for it
+    #[must_use]
+    pub fn has_glyph(&self, ch: char) -> bool {
+        self.descriptor.covers(ch) && self.coverage.contains(ch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_format() {
+        assert_eq!(FontFormat::detect(b"wOF2\x00\x01"), Some(FontFormat::Woff2));
+        assert_eq!(FontFormat::detect(b"wOFF\x00\x01"), Some(FontFormat::Woff));
+        assert_eq!(FontFormat::detect(b"OTTO\x00\x01"), Some(FontFormat::Sfnt));
+        assert_eq!(FontFormat::detect(&[0, 1, 0, 0, 0, 12]), Some(FontFormat::Sfnt));
+        assert_eq!(FontFormat::detect(b"<html>"), None);
+        assert_eq!(FontFormat::detect(b"wO"), None);
+    }
+
+    #[test]
+    fn load_truetype_font() {
+        let data = include_bytes!("../../../gosub_shared/resources/fonts/Roboto-Regular.ttf");
+
+        let mut descriptor = FontFaceDescriptor::new("Web Roboto");
+        descriptor.weight = (100, 900);
+        descriptor.unicode_range = vec![(0x41, 0x5A)];
+
+        let font = WebFont::load(&descriptor, data, 0).unwrap();
+        assert_eq!(font.info.family, "Web Roboto");
+        assert!(font.is_face_of(&font.face_for(700, 1.0)));
+        assert_eq!(font.face_for(950, 1.5).weight, 900);
+        assert_eq!(font.face_for(950, 1.5).stretch, 1.0);
+
+        assert!(font.has_glyph('A'));
+        assert!(!font.has_glyph('a'));
+    }
+
+    #[test]
+    fn invalid_font_data() {
+        let descriptor = FontFaceDescriptor::new("Test");
+
+        assert!(matches!(
+            WebFont::load(&descriptor, b"<html></html>", 0),
+            Err(FontError::UnsupportedFeature(_))
+        ));
+        assert!(matches!(
+            WebFont::load(&descriptor, b"OTTO", 0),
+            Err(FontError::InvalidFont(_))
+        ));
+    }
+}
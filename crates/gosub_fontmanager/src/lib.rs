@@ -3,3 +3,4 @@ pub mod font_manager;
 
 pub use font_manager::font_info::FontInfo;
 pub use font_manager::manager::FontManager;
+pub use font_manager::matching::GenericFamily;
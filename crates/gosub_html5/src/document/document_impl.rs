@@ -1,9 +1,10 @@
 use core::fmt::Debug;
-use gosub_interface::document::{Document as OtherDocument, Document, DocumentType};
+use gosub_interface::document::{Document as OtherDocument, Document, DocumentType, MutationRecord};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::sync::Arc;
 use url::Url;
 
 use crate::document::task_queue::is_valid_id_attribute_value;
@@ -15,10 +16,15 @@ use crate::node::data::element::{ClassListImpl, ElementData};
 use crate::node::data::text::TextData;
 use crate::node::node_impl::{NodeDataTypeInternal, NodeImpl};
 use crate::node::visitor::Visitor;
+use crate::writer::DocumentWriter;
 use gosub_interface::config::HasDocument;
+use gosub_interface::css3::{CssOrigin, CssSystem};
+use gosub_interface::node::ElementDataType;
 use gosub_interface::node::Node;
 use gosub_interface::node::QuirksMode;
+use gosub_interface::resource::{ResourceKind, ResourceLoader, ResourceRequest, ResourceResponse};
 use gosub_shared::byte_stream::Location;
+use gosub_shared::config::ParserConfig;
 use gosub_shared::node::NodeId;
 
 /// Defines a document
@@ -37,6 +43,22 @@ pub struct DocumentImpl<C: HasDocument> {
     pub quirks_mode: QuirksMode,
     /// Loaded stylesheets as extracted from the document
     pub stylesheets: Vec<C::Stylesheet>,
+    /// Loader that fetches external resources (if any)
+    resource_loader: Option<Arc<dyn ResourceLoader>>,
+    /// Requested resources that are not loaded yet, in request order
+    pending_resources: Vec<PendingResource>,
+    /// Mutations that are not taken yet, or None when mutations are not observed
+    mutations: Option<Vec<MutationRecord>>,
+    /// Element that has the keyboard focus, and whether it shows that it has it
+    focus: Option<(NodeId, bool)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PendingResource {
+    request: ResourceRequest,
+    /// Position in the stylesheet list the stylesheet is inserted at, so stylesheets keep their document order
+    /// regardless of the order in which they are loaded
+    stylesheet_index: usize,
 }
 
 impl<C: HasDocument> PartialEq for DocumentImpl<C> {
@@ -47,6 +69,7 @@ impl<C: HasDocument> PartialEq for DocumentImpl<C> {
             && self.doctype == other.doctype
             && self.quirks_mode == other.quirks_mode
             && self.stylesheets == other.stylesheets
+            && self.pending_resources == other.pending_resources
     }
 }
 
@@ -62,6 +85,10 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
             doctype: document_type,
             quirks_mode: QuirksMode::NoQuirks,
             stylesheets: Vec::new(),
+            resource_loader: None,
+            pending_resources: Vec::new(),
+            mutations: None,
+            focus: None,
         };
 
         if let Some(node) = root_node {
@@ -126,6 +153,109 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
         self.stylesheets.push(stylesheet);
     }
 
+    fn set_resource_loader(&mut self, loader: Arc<dyn ResourceLoader>) {
+        self.resource_loader = Some(loader);
+    }
+
+    fn resource_loader(&self) -> Option<Arc<dyn ResourceLoader>> {
+        self.resource_loader.clone()
+    }
+
+    fn request_resource(&mut self, request: ResourceRequest) {
+        let Some(loader) = &self.resource_loader else {
+            log::warn!("No resource loader available, not loading {}", request.url);
+            return;
+        };
+
+        self.pending_resources.push(PendingResource {
+            request: request.clone(),
+            stylesheet_index: self.stylesheets.len(),
+        });
+
+        loader.request(request);
+    }
+
+    fn resource_loaded(&mut self, response: ResourceResponse) {
+        let Some(idx) = self.pending_resources.iter().position(|p| {
+            p.request.url == response.request.url
+                && p.request.kind == response.request.kind
+                && p.request.initiator == response.request.initiator
+        }) else {
+            return;
+        };
+
+        let pending = self.pending_resources.remove(idx);
+
+        let body = match response.body {
+            Ok(body) => body,
+            Err(err) => {
+                log::warn!("Could not load {}: {err}", pending.request.url);
+                return;
+            }
+        };
+
+        if pending.request.kind != ResourceKind::Stylesheet {
+            return;
+        }
+
+        let url = pending.request.url.as_str();
+        let config = ParserConfig {
+            source: Some(url.to_string()),
+            ignore_errors: true,
+            ..Default::default()
+        };
+
+        match C::CssSystem::parse_str(&String::from_utf8_lossy(&body), config, CssOrigin::Author, url) {
+            Ok(stylesheet) => {
+                let index = pending.stylesheet_index.min(self.stylesheets.len());
+                self.stylesheets.insert(index, stylesheet);
+
+                // Stylesheets requested later are inserted after this one
+                for later in &mut self.pending_resources[idx..] {
+                    later.stylesheet_index += 1;
+                }
+            }
+            Err(err) => log::warn!("Error while parsing CSS stylesheet {url}: {err}"),
+        }
+    }
+
+    fn is_render_blocked(&self) -> bool {
+        self.pending_resources.iter().any(|p| p.request.render_blocking)
+    }
+
+    fn observe_mutations(&mut self, observe: bool) {
+        if !observe {
+            self.mutations = None;
+        } else if self.mutations.is_none() {
+            self.mutations = Some(Vec::new());
+        }
+    }
+
+    fn take_mutations(&mut self) -> Vec<MutationRecord> {
+        self.mutations.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn focus(&self) -> Option<(NodeId, bool)> {
+        self.focus
+    }
+
+    fn set_focus(&mut self, focus: Option<(NodeId, bool)>) {
+        let previous = std::mem::replace(&mut self.focus, focus);
+        if previous == focus {
+            return;
+        }
+
+        // The elements that gain and lose the focus are styled again
+        let previous = previous.map(|(node, _)| node);
+        let node = focus.map(|(node, _)| node);
+        if let Some(previous) = previous {
+            self.record(MutationRecord::Focus { node: previous });
+        }
+        if let Some(node) = node.filter(|node| Some(*node) != previous) {
+            self.record(MutationRecord::Focus { node });
+        }
+    }
+
     /// returns the root node
     fn get_root(&self) -> &Self::Node {
         self.arena.node_ref(NodeId::root()).expect("Root node not found !?")
@@ -156,6 +286,14 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
             }
 
             self.update_node(parent_node);
+
+            let (previous_sibling, next_sibling) = self.siblings(parent_id, node_id);
+            self.record(MutationRecord::Insert {
+                node: node_id,
+                parent: parent_id,
+                previous_sibling,
+                next_sibling,
+            });
         }
 
         let mut node = self.arena.node(node_id).unwrap();
@@ -172,6 +310,14 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
         let parent = self.node_by_id(node_id).expect("node not found").parent_id();
 
         if let Some(parent_id) = parent {
+            let (previous_sibling, next_sibling) = self.siblings(parent_id, node_id);
+            self.record(MutationRecord::Remove {
+                node: node_id,
+                parent: parent_id,
+                previous_sibling,
+                next_sibling,
+            });
+
             let mut parent_node = self.node_by_id(parent_id).expect("parent node not found").clone();
             parent_node.remove(node_id);
             self.update_node(parent_node);
@@ -203,6 +349,7 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
         }
 
         self.on_document_node_mutation(&node);
+        self.record_changes(&node);
         self.arena.update_node(node);
     }
 
@@ -213,6 +360,7 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
         }
 
         self.on_document_node_mutation(node);
+        self.record_changes(node);
         self.arena.update_node(node.clone());
     }
 
@@ -223,6 +371,14 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
         let parent_id = node.parent_id();
 
         if let Some(parent_id) = parent_id {
+            let (previous_sibling, next_sibling) = self.siblings(parent_id, node_id);
+            self.record(MutationRecord::Remove {
+                node: node_id,
+                parent: parent_id,
+                previous_sibling,
+                next_sibling,
+            });
+
             let mut parent = self.node_by_id(parent_id).unwrap().clone();
             parent.remove(node_id);
             self.update_node(parent);
@@ -347,8 +503,8 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
         self.write_from_node(NodeId::root())
     }
 
-    fn write_from_node(&self, _node_id: NodeId) -> String {
-        todo!(); //This should definitely be implemented
+    fn write_from_node(&self, node_id: NodeId) -> String {
+        DocumentWriter::write_from_node::<C>(node_id, self)
     }
 
     fn cloned_node_by_id(&self, node_id: NodeId) -> Option<Self::Node> {
@@ -370,7 +526,7 @@ impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
         }
 
         let element_data = node.get_element_data().unwrap();
-        if let Some(id_value) = element_data.attributes.get("id") {
+        if let Some(id_value) = element_data.attributes().get("id") {
             // When we have an ID attribute: update the named ID element map.
             if is_valid_id_attribute_value(id_value) {
                 match self.named_id_elements.entry(id_value.clone()) {
@@ -386,6 +542,66 @@ impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
         }
     }
 
+    /// Adds a mutation record when mutations are observed
+    fn record(&mut self, record: MutationRecord) {
+        if let Some(mutations) = &mut self.mutations {
+            mutations.push(record);
+        }
+    }
+
+    /// Records the attributes and text that differ between the node in the arena and its updated version
+    fn record_changes(&mut self, node: &NodeImpl<C>) {
+        let Some(mutations) = &mut self.mutations else {
+            return;
+        };
+        let Some(old) = self.arena.node_ref(node.id) else {
+            return;
+        };
+
+        match (&old.data, &node.data) {
+            (NodeDataTypeInternal::Element(old), NodeDataTypeInternal::Element(new)) => {
+                let mut names = new
+                    .attributes()
+                    .iter()
+                    .filter(|(name, value)| old.attributes().get(*name) != Some(value))
+                    .map(|(name, _)| name)
+                    .chain(
+                        old.attributes()
+                            .keys()
+                            .filter(|name| !new.attributes().contains_key(*name)),
+                    )
+                    .collect::<Vec<_>>();
+                names.sort();
+
+                for name in names {
+                    mutations.push(MutationRecord::Attribute {
+                        node: node.id,
+                        name: name.clone(),
+                    });
+                }
+            }
+            (NodeDataTypeInternal::Text(old), NodeDataTypeInternal::Text(new)) if old.value != new.value => {
+                mutations.push(MutationRecord::Text { node: node.id });
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the siblings before and after the given child of the parent
+    fn siblings(&self, parent_id: NodeId, node_id: NodeId) -> (Option<NodeId>, Option<NodeId>) {
+        let Some(parent) = self.arena.node_ref(parent_id) else {
+            return (None, None);
+        };
+        let Some(idx) = parent.children.iter().position(|id| *id == node_id) else {
+            return (None, None);
+        };
+
+        (
+            idx.checked_sub(1).map(|idx| parent.children[idx]),
+            parent.children.get(idx + 1).copied(),
+        )
+    }
+
     /// Print a node and all its children in a tree-like structure
     pub fn print_tree(&self, node: &C::Node, prefix: String, last: bool, f: &mut Formatter) {
         let mut buffer = prefix.clone();
@@ -415,7 +631,7 @@ impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
             }
             NodeDataTypeInternal::Element(element) => {
                 _ = write!(f, "{}<{}", buffer, element.name);
-                for (key, value) in &element.attributes {
+                for (key, value) in element.attributes() {
                     _ = write!(f, " {key}={value}");
                 }
 
@@ -579,13 +795,16 @@ mod tests {
     use crate::node::HTML_NAMESPACE;
     use crate::parser::query::Query;
     use crate::parser::tree_builder::TreeBuilder;
+    use gosub_css3::stylesheet::CssValue;
     use gosub_css3::system::Css3System;
     use gosub_interface::config::HasCssSystem;
+    use gosub_interface::css3::{CssOrigin, CssSystem};
     use gosub_interface::document::DocumentBuilder;
     use gosub_interface::node::ClassList;
     use gosub_interface::node::ElementDataType;
     use gosub_interface::node::NodeType;
     use gosub_shared::byte_stream::Location;
+    use gosub_shared::config::ParserConfig;
     use std::collections::HashMap;
 
     #[derive(Clone, Debug, PartialEq)]
@@ -601,6 +820,80 @@ mod tests {
     }
     type Document = DocumentImpl<Config>;
 
+    #[test]
+    fn mutation_records() {
+        let mut doc = <DocumentBuilderImpl as DocumentBuilder<Config>>::new_document(None);
+
+        let parent_node =
+            Document::new_element_node("parent", Some(HTML_NAMESPACE), HashMap::new(), Location::default());
+        let parent_id = doc.register_node_at(parent_node, NodeId::root(), None);
+        let div1 = Document::new_element_node("div1", Some(HTML_NAMESPACE), HashMap::new(), Location::default());
+        let div1_id = doc.register_node_at(div1, parent_id, None);
+
+        // Nothing is recorded until the mutations are observed
+        assert!(doc.take_mutations().is_empty());
+        doc.observe_mutations(true);
+
+        let div2 = Document::new_element_node("div2", Some(HTML_NAMESPACE), HashMap::new(), Location::default());
+        let div2_id = doc.register_node_at(div2, parent_id, Some(0));
+        let text = Document::new_text_node("foo", Location::default());
+        let text_id = doc.register_node_at(text, div1_id, None);
+
+        let mut node = doc.node_by_id(div1_id).unwrap().clone();
+        node.get_element_data_mut().unwrap().add_attribute("class", "bar");
+        doc.update_node(node);
+
+        let mut node = doc.node_by_id(text_id).unwrap().clone();
+        node.get_text_data_mut().unwrap().value = "baz".into();
+        doc.update_node(node);
+
+        doc.detach_node(div2_id);
+
+        assert_eq!(
+            doc.take_mutations(),
+            vec![
+                MutationRecord::Insert {
+                    node: div2_id,
+                    parent: parent_id,
+                    previous_sibling: None,
+                    next_sibling: Some(div1_id),
+                },
+                MutationRecord::Insert {
+                    node: text_id,
+                    parent: div1_id,
+                    previous_sibling: None,
+                    next_sibling: None,
+                },
+                MutationRecord::Attribute {
+                    node: div1_id,
+                    name: "class".into(),
+                },
+                MutationRecord::Text { node: text_id },
+                MutationRecord::Remove {
+                    node: div2_id,
+                    parent: parent_id,
+                    previous_sibling: None,
+                    next_sibling: Some(div1_id),
+                },
+            ]
+        );
+        assert!(doc.take_mutations().is_empty());
+
+        // Moving the focus records the element that loses it and the element that gains it
+        doc.set_focus(Some((div1_id, false)));
+        doc.set_focus(Some((div1_id, false)));
+        doc.set_focus(Some((parent_id, true)));
+        assert_eq!(doc.focus(), Some((parent_id, true)));
+        assert_eq!(
+            doc.take_mutations(),
+            vec![
+                MutationRecord::Focus { node: div1_id },
+                MutationRecord::Focus { node: div1_id },
+                MutationRecord::Focus { node: parent_id },
+            ]
+        );
+    }
+
     #[test]
     fn relocate() {
         let mut doc = <DocumentBuilderImpl as DocumentBuilder<Config>>::new_document(None);
@@ -1588,6 +1881,78 @@ mod tests {
         assert_eq!(expected_order, traversed_nodes);
     }
 
+    #[test]
+    fn write_from_node() {
+        let mut doc = <DocumentBuilderImpl as DocumentBuilder<Config>>::new_document(None);
+
+        let div_node = Document::new_element_node("div", Some(HTML_NAMESPACE), HashMap::new(), Location::default());
+        let div_id = doc.register_node_at(div_node, NodeId::root(), None);
+
+        let mut attributes = HashMap::new();
+        attributes.insert("title".to_string(), "say \"a & b\"".to_string());
+        let p_node = Document::new_element_node("p", Some(HTML_NAMESPACE), attributes, Location::default());
+        let p_id = doc.register_node_at(p_node, div_id, None);
+
+        let text_node = Document::new_text_node("1 < 2 & 3 > 2", Location::default());
+        doc.register_node_at(text_node, p_id, None);
+
+        assert_eq!(
+            doc.write_from_node(p_id),
+            "<p title=\"say &quot;a &amp; b&quot;\">1 &lt; 2 &amp; 3 &gt; 2</p>"
+        );
+        assert_eq!(
+            doc.write(),
+            "<div><p title=\"say &quot;a &amp; b&quot;\">1 &lt; 2 &amp; 3 &gt; 2</p></div>"
+        );
+    }
+
+    #[test]
+    fn inline_style_cascade() {
+        let mut doc = <DocumentBuilderImpl as DocumentBuilder<Config>>::new_document(None);
+
+        let css = "#a { color: red; margin-top: 5px; } p { background-color: blue !important; }";
+        let sheet = Css3System::parse_str(css, ParserConfig::default(), CssOrigin::Author, "test.css").unwrap();
+        doc.add_stylesheet(sheet);
+
+        let mut attributes = HashMap::new();
+        attributes.insert("id".to_string(), "a".to_string());
+        attributes.insert(
+            "style".to_string(),
+            "color: green; margin: 1px; background-color: red".to_string(),
+        );
+        let p_node = Document::new_element_node("p", Some(HTML_NAMESPACE), attributes, Location::default());
+        let p_id = doc.register_node_at(p_node, NodeId::root(), None);
+
+        let computed = |doc: &Document, name: &str| {
+            let node = doc.node_by_id(p_id).unwrap();
+            let mut props = Css3System::properties_from_node::<Config>(node, doc.stylesheets(), doc, p_id).unwrap();
+            props.properties.get_mut(name).map(|prop| prop.compute_value().clone())
+        };
+
+        // Inline declarations win from the id selector, also for longhands of an inline shorthand
+        assert_eq!(computed(&doc, "color"), Some(CssValue::String("green".into())));
+        assert_eq!(computed(&doc, "margin-top"), Some(CssValue::Unit(1.0, "px".into())));
+        // ... but not from an !important declaration
+        assert_eq!(
+            computed(&doc, "background-color"),
+            Some(CssValue::String("blue".into()))
+        );
+
+        // Changing the attribute parses the style again
+        let mut node = doc.cloned_node_by_id(p_id).unwrap();
+        node.get_element_data_mut()
+            .unwrap()
+            .add_attribute("style", "color: yellow");
+        doc.update_node(node);
+        assert_eq!(computed(&doc, "color"), Some(CssValue::String("yellow".into())));
+        assert_eq!(computed(&doc, "margin-top"), Some(CssValue::Unit(5.0, "px".into())));
+
+        let mut node = doc.cloned_node_by_id(p_id).unwrap();
+        node.get_element_data_mut().unwrap().remove_attribute("style");
+        doc.update_node(node);
+        assert_eq!(computed(&doc, "color"), Some(CssValue::String("red".into())));
+    }
+
     /*    #[test] //TODO: currently, this is not possible anymore
     fn tree_iterator_mutation() {
         let mut doc = <DocumentBuilderImpl as DocumentBuilder<Config>>::new_document(None);
@@ -0,0 +1,165 @@
+//! Resource loaders that hand their responses back to the caller instead of a channel: an in-memory loader, which
+//! serves resources from a map instead of the network so documents with external resources can be parsed in tests,
+//! and a blocking loader for callers that parse a document synchronously.
+
+use gosub_interface::config::HasDocument;
+use gosub_interface::document::Document;
+use gosub_interface::resource::{ResourceCallback, ResourceLoader, ResourceRequest, ResourceResponse};
+#[cfg(not(target_arch = "wasm32"))]
+use gosub_net::http::fetcher::Fetcher;
+#[cfg(not(target_arch = "wasm32"))]
+use std::cmp::Reverse;
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fmt;
+use std::sync::Mutex;
+use url::Url;
+
+/// Resource loader that serves resources from memory. Responses are not handed to the document automatically, but
+/// are collected until they are taken with `take_responses`.
+#[derive(Debug, Default)]
+pub struct MemoryResourceLoader {
+    resources: HashMap<Url, Vec<u8>>,
+    requests: Mutex<Vec<ResourceRequest>>,
+    responses: Mutex<Vec<ResourceResponse>>,
+}
+
+impl MemoryResourceLoader {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a resource that is served for the given url
+    pub fn add_resource(&mut self, url: Url, body: impl Into<Vec<u8>>) {
+        self.resources.insert(url, body.into());
+    }
+
+    /// Returns all requests made so far, in request order
+    pub fn requests(&self) -> Vec<ResourceRequest> {
+        self.requests.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    /// Takes the responses to all requests made so far
+    pub fn take_responses(&self) -> Vec<ResourceResponse> {
+        self.responses
+            .lock()
+            .map(|mut r| r.drain(..).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl MemoryResourceLoader {
+    /// Records the request and returns its response
+    fn serve(&self, request: ResourceRequest) -> ResourceResponse {
+        let body = self
+            .resources
+            .get(&request.url)
+            .cloned()
+            .ok_or_else(|| format!("{} not found", request.url));
+
+        if let Ok(mut requests) = self.requests.lock() {
+            requests.push(request.clone());
+        }
+
+        ResourceResponse { request, body }
+    }
+}
+
+impl ResourceLoader for MemoryResourceLoader {
+    fn request(&self, request: ResourceRequest) {
+        let response = self.serve(request);
+
+        if let Ok(mut responses) = self.responses.lock() {
+            responses.push(response);
+        }
+    }
+
+    fn fetch(&self, request: ResourceRequest, reply: ResourceCallback) {
+        reply(self.serve(request));
+    }
+}
+
+/// Resource loader for callers that parse a document synchronously and can not wait for the responses of an
+/// asynchronous loader. Requests are only queued while the document is parsed; `load_into` fetches them all at once
+/// through the `Fetcher`, blocking until they are loaded, and hands the responses to the document.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct BlockingResourceLoader {
+    fetcher: Fetcher,
+    queue: Mutex<Vec<(ResourceRequest, Option<ResourceCallback>)>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl fmt::Debug for BlockingResourceLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockingResourceLoader")
+            .field("fetcher", &self.fetcher)
+            .field("queued", &self.queue.lock().map(|q| q.len()).unwrap_or_default())
+            .finish()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl BlockingResourceLoader {
+    /// Creates a loader for the document at the given url
+    #[must_use]
+    pub fn new(base: Url) -> Self {
+        Self {
+            fetcher: Fetcher::new(base),
+            queue: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Fetches all requests made so far and hands their responses to the document, or to the callback they were
+    /// fetched with. Requests that are made by the callbacks are fetched as well before this returns.
+    pub fn load_into<C: HasDocument>(&self, doc: &mut C::Document) {
+        loop {
+            let mut queued = self
+                .queue
+                .lock()
+                .map(|mut q| q.drain(..).collect::<Vec<_>>())
+                .unwrap_or_default();
+            if queued.is_empty() {
+                return;
+            }
+
+            // The sort is stable, so requests with the same priority stay in request order
+            queued.sort_by_key(|(request, _)| Reverse(request.priority));
+
+            let bodies = futures::executor::block_on(futures::future::join_all(
+                queued.iter().map(|(request, _)| self.fetcher.get_url(&request.url)),
+            ));
+
+            for ((request, reply), body) in queued.into_iter().zip(bodies) {
+                let body = match body {
+                    Ok(response) if response.is_ok() => Ok(response.body),
+                    Ok(response) => Err(format!("status code {}", response.status)),
+                    Err(e) => Err(e.to_string()),
+                };
+
+                let response = ResourceResponse { request, body };
+                match reply {
+                    Some(reply) => reply(response),
+                    None => doc.resource_loaded(response),
+                }
+            }
+        }
+    }
+
+    fn queue(&self, request: ResourceRequest, reply: Option<ResourceCallback>) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.push((request, reply));
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ResourceLoader for BlockingResourceLoader {
+    fn request(&self, request: ResourceRequest) {
+        self.queue(request, None);
+    }
+
+    fn fetch(&self, request: ResourceRequest, reply: ResourceCallback) {
+        self.queue(request, Some(reply));
+    }
+}
@@ -4,12 +4,14 @@ use crate::node::elements::{
 use crate::node::{HTML_NAMESPACE, MATHML_NAMESPACE, SVG_NAMESPACE};
 use core::fmt::{Debug, Formatter};
 use gosub_interface::config::HasDocument;
+use gosub_interface::css3::CssSystem;
 
 use gosub_interface::node::{ClassList, ElementDataType};
 use gosub_shared::node::NodeId;
 use std::collections::hash_map::IntoIter;
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct ClassListImpl {
@@ -148,26 +150,42 @@ impl From<&str> for ClassListImpl {
 }
 
 /// Data structure for element nodes
-#[derive(PartialEq, Clone)]
+#[derive(PartialEq)]
 pub struct ElementData<C: HasDocument> {
     pub node_id: Option<NodeId>,
     /// Name of the element (e.g., div)
     pub name: String,
     /// Namespace of the element
     pub namespace: Option<String>,
-    /// Element's attributes stored as key-value pairs.
-    /// Note that it is NOT RECOMMENDED to modify this
-    /// attribute map directly and instead use `TreeBuilder.insert_attribute`
-    /// to keep attributes in sync with the DOM.
-    pub attributes: HashMap<String, String>,
+    /// Element's attributes stored as key-value pairs. They are only changed through `add_attribute` and
+    /// `remove_attribute`, which keep the class list and the inline style in sync with them.
+    attributes: HashMap<String, String>,
     /// CSS list of classes
     pub class_list: ClassListImpl,
+    /// Parsed declarations of the style attribute. Like the class list, this is kept in sync with the attribute.
+    pub inline_style: Option<Arc<C::Stylesheet>>,
     // Only used for <script> elements
     pub force_async: bool,
     // Template contents (when it's a template element)
     pub template_contents: Option<C::DocumentFragment>,
 }
 
+// Not derived, as that would require the stylesheet of the css system to be `Clone`
+impl<C: HasDocument> Clone for ElementData<C> {
+    fn clone(&self) -> Self {
+        Self {
+            node_id: self.node_id,
+            name: self.name.clone(),
+            namespace: self.namespace.clone(),
+            attributes: self.attributes.clone(),
+            class_list: self.class_list.clone(),
+            inline_style: self.inline_style.clone(),
+            force_async: self.force_async,
+            template_contents: self.template_contents.clone(),
+        }
+    }
+}
+
 impl<C: HasDocument> Debug for ElementData<C> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let mut debug = f.debug_struct("ElementData");
@@ -234,6 +252,11 @@ impl<C: HasDocument> ElementDataType<C> for ElementData<C> {
                 self.class_list.add(class);
             }
         }
+
+        // The style attribute is parsed once, and only parsed again when it changes
+        if name == "style" {
+            self.inline_style = Some(Arc::new(C::CssSystem::parse_inline_style(value)));
+        }
     }
 
     // removes attribute from element
@@ -243,6 +266,9 @@ impl<C: HasDocument> ElementDataType<C> for ElementData<C> {
                 self.class_list.remove(value);
             }
         }
+        if name == "style" {
+            self.inline_style = None;
+        }
 
         self.attributes.remove(name);
     }
@@ -251,6 +277,10 @@ impl<C: HasDocument> ElementDataType<C> for ElementData<C> {
         self.class_list.add(class_name);
     }
 
+    fn inline_style(&self) -> Option<&C::Stylesheet> {
+        self.inline_style.as_deref()
+    }
+
     /// This will only compare against the tag, namespace and data same except element data.
     /// for element data compare against the tag, namespace and attributes without order.
     /// Both nodes could still have other parents and children.
@@ -342,12 +372,17 @@ impl<C: HasDocument> ElementData<C> {
         classlist: ClassListImpl,
     ) -> Self {
         let (force_async, template_contents) = <_>::default();
+        let inline_style = attributes
+            .get("style")
+            .map(|style| Arc::new(C::CssSystem::parse_inline_style(style)));
+
         Self {
             node_id: None, // We are not yet registered in the document, so we have no node-id
             name: name.into(),
             namespace: Some(namespace.unwrap_or(HTML_NAMESPACE).into()),
             attributes,
             class_list: classlist,
+            inline_style,
             force_async,
             template_contents,
         }
@@ -21,6 +21,7 @@ use gosub_interface::document::{Document, DocumentFragment, DocumentType};
 use gosub_interface::html5::ParserOptions;
 use gosub_interface::node::TextDataType;
 use gosub_interface::node::{ElementDataType, Node, QuirksMode};
+use gosub_interface::resource::{ResourceKind, ResourceRequest};
 use gosub_shared::byte_stream::{ByteStream, Location};
 use gosub_shared::config::{Context, ParserConfig};
 use gosub_shared::node::NodeId;
@@ -2997,15 +2998,15 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
                 attributes,
                 ..
             } if name == "base" || name == "basefont" || name == "bgsound" || name == "link" => {
-                if name == "link" {
-                    // Handle link elements, as it depends on rel/itemprop attributes and other factors
-                    self.handle_link_element(attributes.clone());
-                }
-
                 self.acknowledge_closing_tag(*is_self_closing);
 
-                self.insert_html_element(&token.clone());
+                let node_id = self.insert_html_element(&token.clone());
                 self.open_elements.pop();
+
+                if name == "link" {
+                    // Handle link elements, as it depends on rel/itemprop attributes and other factors
+                    self.handle_link_element(node_id, attributes.clone());
+                }
             }
             Token::StartTag {
                 name, is_self_closing, ..
@@ -4099,86 +4100,7 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
         None
     }
 
-    /// Load and parse an external stylesheet by URL
-    #[cfg(target_arch = "wasm32")]
-    fn load_external_stylesheet(&self, _origin: CssOrigin, _url: Url) -> Option<C::Stylesheet> {
-        None
-    }
-
-    #[cfg(not(target_arch = "wasm32"))]
-    fn load_external_stylesheet(&self, origin: CssOrigin, url: Url) -> Option<C::Stylesheet> {
-        let css = if url.scheme() == "http" || url.scheme() == "https" {
-            // Fetch the html from the url
-            let response = ureq::get(url.as_ref()).call();
-            if response.is_err() {
-                warn!(
-                    "Could not load external stylesheet from {}. Error: {}",
-                    url,
-                    response.unwrap_err()
-                );
-                return None;
-            }
-            let mut response = response.expect("result");
-
-            if response.status() != 200 {
-                warn!(
-                    "Could not load external stylesheet from {}. Status code {} ",
-                    url,
-                    response.status()
-                );
-                return None;
-            }
-            let content_type = response.headers().get("Content-Type");
-            match content_type {
-                Some(content_type) => {
-                    let content_type = content_type.to_str().unwrap_or("");
-                    if !content_type.starts_with("text/css") {
-                        warn!("External stylesheet has no text/css content type: {content_type} ");
-                    }
-                }
-                None => {
-                    warn!("External stylesheet has no content type: {url} ");
-                }
-            }
-
-            match response.body_mut().read_to_string() {
-                Ok(css) => css,
-                Err(err) => {
-                    warn!("Could not load external stylesheet from {url}. Error: {err}");
-                    return None;
-                }
-            }
-        } else if url.scheme() == "file" {
-            let path = &url.as_str()[7..];
-
-            match std::fs::read_to_string(path) {
-                Ok(css) => css,
-                Err(err) => {
-                    warn!("Could not load external stylesheet from {url}. Error: {err}");
-                    return None;
-                }
-            }
-        } else {
-            warn!("Unsupported URL scheme for external stylesheet: {}", url.scheme());
-            return None;
-        };
-
-        let config = ParserConfig {
-            source: Some(url.to_string()),
-            ignore_errors: true,
-            ..Default::default()
-        };
-
-        match C::CssSystem::parse_str(css.as_str(), config, origin, url.as_str()) {
-            Ok(stylesheet) => Some(stylesheet),
-            Err(err) => {
-                warn!("Error while parsing CSS stylesheet: {err}");
-                None
-            }
-        }
-    }
-
-    fn handle_link_element(&mut self, attributes: HashMap<String, String>) {
+    fn handle_link_element(&mut self, node_id: NodeId, attributes: HashMap<String, String>) {
         if attributes.contains_key("rel") && attributes.contains_key("itemprop") {
             // cannot have them both
             self.parse_error("link element cannot have both 'rel' and 'itemprop' attributes");
@@ -4217,37 +4139,59 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
         match rel.as_str() {
             "stylesheet" => {
                 // Stylesheet link may not have a href link (for instance, it could be data-href for optional loading through JS)
-                let Some(href) = attributes.get("href") else {
+                let Some(url) = self.link_url(&attributes) else {
                     return;
                 };
 
-                let css_url = match Url::parse(href) {
-                    Ok(url) => url,
-                    Err(_err) => {
-                        // Relative URL
-                        if self.document.url().is_some() {
-                            let url = self.document.url();
+                let mut request = ResourceRequest::new(url, ResourceKind::Stylesheet);
+                request.render_blocking = true;
+                request.initiator = Some(node_id);
 
-                            let base_url = url.as_ref().unwrap();
-                            base_url.join(href).unwrap()
-                        } else {
-                            self.parse_error("link element without base url not supported yet");
-                            return;
-                        }
-                    }
+                self.document.request_resource(request);
+            }
+            "preload" => {
+                let Some(kind) = attributes.get("as").and_then(|d| ResourceKind::from_destination(d)) else {
+                    self.parse_error("link element with rel 'preload' needs a supported 'as' attribute");
+                    return;
                 };
-                if let Some(stylesheet) = self.load_external_stylesheet(CssOrigin::Author, css_url) {
-                    println!("success: loaded external stylesheet");
-                    self.document.add_stylesheet(stylesheet);
-                } else {
-                    println!("failed loading stylesheet");
-                }
+                let Some(url) = self.link_url(&attributes) else {
+                    return;
+                };
+
+                let mut request = ResourceRequest::new(url, kind);
+                request.initiator = Some(node_id);
+
+                self.document.request_resource(request);
             }
             _ => {
                 self.parse_error(format!("link element with rel attribute '{rel}' is not supported").as_str());
             }
         }
     }
+
+    /// Resolves the `href` of a link element against the url of the document
+    fn link_url(&mut self, attributes: &HashMap<String, String>) -> Option<Url> {
+        let href = attributes.get("href")?;
+
+        match Url::parse(href) {
+            Ok(url) => Some(url),
+            Err(_) => {
+                // Relative URL
+                let joined = self.document.url().map(|base| base.join(href));
+                match joined {
+                    Some(Ok(url)) => Some(url),
+                    Some(Err(_)) => {
+                        self.parse_error("link element with invalid href");
+                        None
+                    }
+                    None => {
+                        self.parse_error("link element without base url not supported yet");
+                        None
+                    }
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -4256,6 +4200,7 @@ mod test {
     use crate::document::builder::DocumentBuilderImpl;
     use crate::document::document_impl::DocumentImpl;
     use crate::document::fragment::DocumentFragmentImpl;
+    use crate::document::loader::{BlockingResourceLoader, MemoryResourceLoader};
     use crate::node::data::element::ElementData;
     use crate::node::node_impl::NodeDataTypeInternal;
     use crate::node::node_impl::NodeImpl;
@@ -4263,7 +4208,9 @@ mod test {
     use gosub_css3::system::Css3System;
     use gosub_interface::config::HasCssSystem;
     use gosub_interface::node::ClassList;
+    use gosub_interface::resource::ResourcePriority;
     use gosub_shared::byte_stream::Encoding;
+    use std::sync::Arc;
 
     #[derive(Clone, Debug, PartialEq)]
     struct Config;
@@ -4616,4 +4563,96 @@ mod test {
         assert_eq!(div.id, NodeId::from(4usize));
         assert_eq!(div.get_element_data().unwrap().name(), "div");
     }
+
+    #[test]
+    fn external_stylesheets() {
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.read_from_str(
+            "<html><head>\
+             <link rel=\"stylesheet\" href=\"a.css\">\
+             <style>p { color: red; }</style>\
+             <link rel=\"stylesheet\" href=\"https://cdn.example.com/b.css\">\
+             <link rel=\"preload\" as=\"image\" href=\"img.png\">\
+             </head><body></body></html>",
+            Some(Encoding::UTF8),
+        );
+        stream.close();
+
+        let base = Url::parse("https://example.com/page/index.html").unwrap();
+        let a_url = base.join("a.css").unwrap();
+        let b_url = Url::parse("https://cdn.example.com/b.css").unwrap();
+
+        let mut loader = MemoryResourceLoader::new();
+        loader.add_resource(a_url.clone(), "p { color: green; }");
+        loader.add_resource(b_url.clone(), "p { color: blue; }");
+        let loader = Arc::new(loader);
+
+        let mut doc = <DocumentBuilderImpl as DocumentBuilder<Config>>::new_document(Some(base.clone()));
+        doc.set_resource_loader(loader.clone());
+        let _ = Parser::parse_document(&mut stream, &mut doc, None);
+
+        let requests = loader.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].url, a_url);
+        assert_eq!(requests[0].kind, ResourceKind::Stylesheet);
+        assert_eq!(requests[0].priority, ResourcePriority::High);
+        assert!(requests[0].render_blocking);
+        assert!(requests[0].initiator.is_some());
+        assert_eq!(requests[1].url, b_url);
+        assert_eq!(requests[2].url, base.join("img.png").unwrap());
+        assert_eq!(requests[2].kind, ResourceKind::Image);
+        assert_eq!(requests[2].priority, ResourcePriority::Low);
+        assert!(!requests[2].render_blocking);
+
+        // Only the <style> element is parsed while the external stylesheets are loading
+        assert!(doc.is_render_blocked());
+        assert_eq!(doc.stylesheets().len(), 1);
+
+        // Stylesheets keep their document order, even when loaded in reverse order
+        for response in loader.take_responses().into_iter().rev() {
+            doc.resource_loaded(response);
+        }
+
+        assert!(!doc.is_render_blocked());
+        let urls = doc.stylesheets().iter().map(|s| s.url.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            vec![
+                a_url.as_str(),
+                "https://example.com/page/index.html#inline",
+                b_url.as_str()
+            ]
+        );
+    }
+
+    #[test]
+    fn blocking_external_stylesheets() {
+        let dir = std::env::temp_dir().join(format!("gosub-blocking-loader-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.css"), "p { color: green; }").unwrap();
+
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.read_from_str(
+            "<html><head>\
+             <link rel=\"stylesheet\" href=\"a.css\">\
+             <link rel=\"stylesheet\" href=\"missing.css\">\
+             </head><body></body></html>",
+            Some(Encoding::UTF8),
+        );
+        stream.close();
+
+        let base = Url::from_file_path(dir.join("index.html")).unwrap();
+        let loader = Arc::new(BlockingResourceLoader::new(base.clone()));
+
+        let mut doc = <DocumentBuilderImpl as DocumentBuilder<Config>>::new_document(Some(base.clone()));
+        doc.set_resource_loader(loader.clone());
+        let _ = Parser::parse_document(&mut stream, &mut doc, None);
+        loader.load_into::<Config>(&mut doc);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!doc.is_render_blocked());
+        let urls = doc.stylesheets().iter().map(|s| s.url.as_str()).collect::<Vec<_>>();
+        assert_eq!(urls, vec![base.join("a.css").unwrap().as_str()]);
+    }
 }
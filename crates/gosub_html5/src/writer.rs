@@ -86,7 +86,7 @@ impl<C: HasDocument> Visitor<C> for DocumentWriter {
 
     fn text_enter(&mut self, node: &C::Node) {
         if let Some(data) = node.get_text_data() {
-            self.buffer.push_str(data.value());
+            escape_into(&mut self.buffer, data.value(), false);
         }
     }
 
@@ -111,7 +111,7 @@ impl<C: HasDocument> Visitor<C> for DocumentWriter {
                 self.buffer.push(' ');
                 self.buffer.push_str(name);
                 self.buffer.push_str("=\"");
-                self.buffer.push_str(value);
+                escape_into(&mut self.buffer, value, true);
                 self.buffer.push('"');
             }
 
@@ -127,3 +127,16 @@ impl<C: HasDocument> Visitor<C> for DocumentWriter {
         }
     }
 }
+
+/// Escapes the characters that cannot appear as-is in text or in double-quoted attribute values
+fn escape_into(buffer: &mut String, value: &str, attribute: bool) {
+    for c in value.chars() {
+        match c {
+            '&' => buffer.push_str("&amp;"),
+            '"' if attribute => buffer.push_str("&quot;"),
+            '<' if !attribute => buffer.push_str("&lt;"),
+            '>' if !attribute => buffer.push_str("&gt;"),
+            _ => buffer.push(c),
+        }
+    }
+}
@@ -1,20 +1,25 @@
+use gosub_interface::accessibility::{AccessibilityProvider, AccessibilityTree};
 use gosub_interface::chrome::ChromeHandle;
 use gosub_interface::config::{HasTreeDrawer, ModuleConfiguration};
+use gosub_interface::document::Document;
 use gosub_interface::draw::TreeDrawer;
 use gosub_interface::eventloop::EventLoopHandle;
+use gosub_interface::font::FontFaceDescriptor;
 use gosub_interface::input::InputEvent;
 use gosub_interface::instance::{Handles, InstanceId};
 use gosub_interface::layout::LayoutTree;
-use gosub_interface::render_backend::{ImageBuffer, NodeDesc};
+use gosub_interface::render_backend::{ImageBuffer, ImgCache, NodeDesc};
 use gosub_net::http::fetcher::Fetcher;
-use gosub_shared::geo::SizeU32;
+use gosub_shared::geo::{Point, SizeU32};
 use gosub_shared::types::Result;
 use gosub_web_platform::{WebEventLoop, WebEventLoopHandle, WebEventLoopMessage};
 use log::warn;
+use std::future::Future;
 use std::sync::mpsc::Sender as SyncSender;
 use std::sync::Arc;
 use tokio::runtime::{Builder, Handle, Runtime};
 use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::oneshot;
 use tokio::task;
 use tokio::task::LocalSet;
 use url::Url;
@@ -24,6 +29,12 @@ pub struct EngineInstance<C: ModuleConfiguration> {
     pub title: String,
     pub url: Url,
     pub data: C::TreeDrawer,
+    /// Document of the page that is shown. The changes to it are applied to the page after every message.
+    document: C::Document,
+    /// Documents of the pages that finished loading with how they change the history, or None for a page that failed
+    /// to load. They are sent by the tasks that load them.
+    dtx: Sender<Option<(C::Document, Loaded)>>,
+    drx: Receiver<Option<(C::Document, Loaded)>>,
     web: WebEventLoopHandle,
     rx: Receiver<InstanceMessage>,
     irx: Receiver<InternalInstanceMessage<C>>,
@@ -33,6 +44,40 @@ pub struct EngineInstance<C: ModuleConfiguration> {
     #[allow(unused)]
     fetcher: Arc<Fetcher>,
     size: SizeU32,
+    /// Pages visited in this instance, which the user can go back and forward through
+    history: Vec<HistoryEntry>,
+    history_index: usize,
+    /// The page that is shown is the response to a posted form. It is not in the history, so it can not be loaded
+    /// again by accident. Going back shows the page the form was on.
+    posted: bool,
+    /// Scroll position of the page that is loading, to restore once it is shown
+    restore_scroll: Option<Point>,
+}
+
+/// Page in the history of an instance, with where it was scrolled to when the user left it
+#[derive(Debug, Clone)]
+struct HistoryEntry {
+    url: Url,
+    scroll: Point,
+}
+
+/// How a page that is shown changes the history. The history only changes once the page is loaded, so a page that
+/// fails to load leaves no entry behind.
+#[derive(Debug, Clone)]
+enum Loaded {
+    /// The user navigated to the url, which is added to the history after the current page
+    Navigated { url: Url, scroll: Point },
+    /// The response to a posted form, which is not added to the history
+    Posted { url: Url, scroll: Point },
+    /// The user went back or forward to the page at the index in the history
+    Traversed { index: usize, scroll: Point },
+    /// The current page is loaded again
+    Reloaded,
+}
+
+/// Returns true when two urls point to the same document, which they do when they differ only in their fragment
+fn same_document(a: &Url, b: &Url) -> bool {
+    a[..url::Position::AfterQuery] == b[..url::Position::AfterQuery]
 }
 
 impl<C: ModuleConfiguration> EngineInstance<C> {
@@ -59,17 +104,29 @@ impl<C: ModuleConfiguration> EngineInstance<C> {
         handles: Handles<C>,
     ) -> Result<Self> {
         let fetcher = Arc::new(Fetcher::new(url.clone()));
-        let (data, _handle) = C::TreeDrawer::with_fetcher(url.clone(), fetcher.clone(), layouter, false).await?;
+        let (data, mut document) = C::TreeDrawer::with_fetcher(url.clone(), fetcher.clone(), layouter, false).await?;
+        document.observe_mutations(true);
 
         let (itx, irx) = tokio::sync::mpsc::channel(128);
+        let (dtx, drx) = tokio::sync::mpsc::channel(8);
 
         let web = WebEventLoop::new_on_thread(handles.clone());
 
         Ok(EngineInstance {
             title: "Gosub".to_string(),
             web,
+            history: vec![HistoryEntry {
+                url: url.clone(),
+                scroll: Point::ZERO,
+            }],
+            history_index: 0,
+            posted: false,
+            restore_scroll: None,
             url,
             data,
+            document,
+            dtx,
+            drx,
             rx,
             el: El(itx),
             irx,
@@ -109,14 +166,59 @@ impl<C: ModuleConfiguration> EngineInstance<C> {
         let set = LocalSet::new();
 
         set.block_on(rt, async move {
-            while let Some(message) = self.rx.recv().await {
-                if let Err(e) = self.handle_message(message).await {
-                    warn!("Error: {e:?}");
+            loop {
+                tokio::select! {
+                    message = self.rx.recv() => {
+                        let Some(message) = message else {
+                            break;
+                        };
+
+                        if let Err(e) = self.handle_message(message).await {
+                            warn!("Error: {e:?}");
+                        }
+                    }
+                    Some(message) = self.irx.recv() => {
+                        self.handle_internal_message(message);
+                    }
+                    Some(loaded) = self.drx.recv() => match loaded {
+                        Some((mut document, loaded)) => {
+                            document.observe_mutations(true);
+                            self.document = document;
+                            self.update_history(loaded);
+                        }
+                        // The page that failed to load is not shown, so there is no scroll position to restore
+                        None => self.restore_scroll = None,
+                    }
                 }
+
+                self.apply_mutations();
             }
         });
     }
 
+    /// Handles a message sent from within the instance, like resources that finished loading
+    fn handle_internal_message(&mut self, message: InternalInstanceMessage<C>) {
+        match message {
+            InternalInstanceMessage::Image(url, buf, size) => {
+                self.data.get_img_cache().add(url.to_string(), buf, size);
+                self.data.delete_scene();
+            }
+            InternalInstanceMessage::Redraw => {}
+            InternalInstanceMessage::ReloadFrom(rt) => {
+                self.data.reload_from(rt);
+
+                if let Some(position) = self.restore_scroll.take() {
+                    self.data.restore_scroll_position(position);
+                }
+            }
+            InternalInstanceMessage::WebFont(document, descriptor, data) => {
+                self.data.add_web_font(&document, &descriptor, &data);
+            }
+        }
+
+        self.redraw();
+    }
+
     /// Handles a message sent to the instance
     async fn handle_message(&mut self, message: InstanceMessage) -> Result<()> {
         match message {
@@ -128,23 +230,45 @@ impl<C: ModuleConfiguration> EngineInstance<C> {
             }
 
             InstanceMessage::Navigate(url) => {
-                let el = self.el.clone();
+                let navigated = Loaded::Navigated {
+                    url: url.clone(),
+                    scroll: self.data.scroll_position(),
+                };
+
+                if self.data.navigate_to_fragment(&url) {
+                    self.update_history(navigated);
+                    self.redraw();
+                } else {
+                    self.restore_scroll = None;
+
+                    let el = self.el.clone();
 
-                task::spawn_local(self.data.navigate(url, el));
+                    let load = self.data.navigate(url, el);
+                    self.load(load, navigated);
+                }
             }
 
             InstanceMessage::Back => {
-                //TODO
+                if self.posted {
+                    self.traverse_history(self.history_index);
+                } else if let Some(index) = self.history_index.checked_sub(1) {
+                    self.traverse_history(index);
+                }
             }
 
             InstanceMessage::Forward => {
-                //TODO
+                if self.history_index + 1 < self.history.len() {
+                    self.traverse_history(self.history_index + 1);
+                }
             }
 
             InstanceMessage::Reload => {
+                self.restore_scroll = Some(self.data.scroll_position());
+
                 let el = self.el.clone();
 
-                task::spawn_local(self.data.reload(el));
+                let load = self.data.reload(el);
+                self.load(load, Loaded::Reloaded);
             }
 
             InstanceMessage::Close => {
@@ -168,6 +292,10 @@ impl<C: ModuleConfiguration> EngineInstance<C> {
                             .info(<C::LayoutTree as LayoutTree<C>>::NodeId::from(id), sender);
                     }
 
+                    DebugEvent::SendAccessibilityTree(sender) => {
+                        let _ = sender.send(self.data.accessibility_tree());
+                    }
+
                     DebugEvent::Deselect => {
                         self.data.unselect_element();
                     }
@@ -190,21 +318,64 @@ impl<C: ModuleConfiguration> EngineInstance<C> {
                 }
             }
             InstanceMessage::Input(event) => {
-                match event {
+                let redraw = match event {
                     InputEvent::MouseScroll(delta) => {
                         self.data.scroll(delta);
-                        self.redraw();
+                        true
                     }
-                    InputEvent::MouseMove(point) => {
-                        if self.data.mouse_move(point.x, point.y) {
-                            self.redraw();
-                        }
+                    InputEvent::MouseMove(point) => self.data.mouse_move(point.x, point.y),
+                    InputEvent::MouseDown(button) => self.data.mouse_down(button),
+                    InputEvent::MouseUp(button) => self.data.mouse_up(button),
+                    InputEvent::KeyboardDown(key) => self.data.key_down(key),
+                    InputEvent::KeyboardUp(key) => self.data.key_up(key),
+                };
+
+                if redraw {
+                    self.redraw();
+                }
+
+                let navigation = self.data.take_navigation();
+                if let Some((request, url)) =
+                    navigation.and_then(|request| Url::parse(&request.uri).ok().map(|url| (request, url)))
+                {
+                    let scroll = self.data.scroll_position();
+
+                    if request.method == "GET" && self.data.navigate_to_fragment(&url) {
+                        self.update_history(Loaded::Navigated { url, scroll });
+                        self.redraw();
+                    } else {
+                        let loaded = if request.method == "GET" {
+                            Loaded::Navigated { url, scroll }
+                        } else {
+                            Loaded::Posted { url, scroll }
+                        };
+                        self.restore_scroll = None;
+
+                        let el = self.el.clone();
+
+                        let load = self.data.load_request(request, el);
+                        self.load(load, loaded);
                     }
-                    _ => {}
                 }
 
                 self.web.tx.send(WebEventLoopMessage::InputEvent(event)).await?;
             }
+
+            InstanceMessage::Find {
+                query,
+                case_sensitive,
+                forward,
+            } => {
+                let result = self.data.find(&query, case_sensitive, forward);
+                self.handles.chrome.find_result(result, self.id);
+
+                self.redraw();
+            }
+
+            InstanceMessage::SelectedText(sender) => {
+                // The receiver may have been dropped, in which case nobody is waiting for the text anymore
+                let _ = sender.send(self.data.selected_text());
+            }
         }
 
         Ok(())
@@ -215,12 +386,143 @@ impl<C: ModuleConfiguration> EngineInstance<C> {
 
         self.handles.chrome.draw_scene(scene, self.size, self.id);
     }
+
+    /// Updates the history for a page that is shown
+    fn update_history(&mut self, loaded: Loaded) {
+        match loaded {
+            Loaded::Navigated { url, scroll } => self.push_history(url, scroll),
+            Loaded::Posted { url, scroll } => self.push_posted(url, scroll),
+            Loaded::Traversed { index, scroll } => {
+                // The history may have changed while the page was loading
+                let Some(entry) = self.history.get(index) else {
+                    return;
+                };
+                self.url = entry.url.clone();
+
+                self.remember_scroll(scroll);
+                self.history_index = index;
+                self.posted = false;
+            }
+            Loaded::Reloaded => {}
+        }
+    }
+
+    /// Adds the page the user navigated to to the history, after the current page, and remembers where the current
+    /// page was scrolled to. The pages the user went back from are dropped. Navigating to the current url again does
+    /// not add a page.
+    fn push_history(&mut self, url: Url, scroll: Point) {
+        self.remember_scroll(scroll);
+        self.posted = false;
+
+        if url != self.history[self.history_index].url {
+            self.history.truncate(self.history_index + 1);
+            self.history.push(HistoryEntry {
+                url: url.clone(),
+                scroll: Point::ZERO,
+            });
+            self.history_index += 1;
+        }
+
+        self.url = url;
+    }
+
+    /// Shows the response to a posted form after the current page, without adding it to the history. The pages the
+    /// user went back from are dropped.
+    fn push_posted(&mut self, url: Url, scroll: Point) {
+        self.remember_scroll(scroll);
+        self.posted = true;
+
+        self.history.truncate(self.history_index + 1);
+        self.url = url;
+    }
+
+    /// Remembers where the current page was scrolled to in its history entry. The response to a posted form has no
+    /// entry of its own.
+    fn remember_scroll(&mut self, scroll: Point) {
+        if !self.posted {
+            self.history[self.history_index].scroll = scroll;
+        }
+    }
+
+    /// Goes back or forward to a page in the history, which is shown scrolled to where the user left it
+    fn traverse_history(&mut self, index: usize) {
+        let entry = self.history[index].clone();
+        let traversed = Loaded::Traversed {
+            index,
+            scroll: self.data.scroll_position(),
+        };
+
+        if !self.posted && same_document(&self.url, &entry.url) {
+            self.update_history(traversed);
+            self.restore_scroll = None;
+            self.data.restore_scroll_position(entry.scroll);
+            self.redraw();
+            return;
+        }
+
+        self.restore_scroll = Some(entry.scroll);
+
+        let el = self.el.clone();
+
+        let load = self.data.navigate(entry.url, el);
+        self.load(load, traversed);
+    }
+
+    /// Loads a page in the background. Its document is kept once it is loaded, so its changes can be applied, and
+    /// the history is updated for it then.
+    fn load(&self, load: impl Future<Output = Result<C::Document>> + 'static, loaded: Loaded) {
+        let dtx = self.dtx.clone();
+
+        task::spawn_local(async move {
+            let document = load.await.ok().map(|document| (document, loaded));
+            let _ = dtx.send(document).await;
+        });
+    }
+
+    /// Updates the page with the changes that were made to its document since the last update, so only the changed
+    /// nodes are styled and laid out again. The focus of the page is kept in the document, so `:focus` and
+    /// `:focus-visible` match the focused element.
+    fn apply_mutations(&mut self) {
+        let focus = self.data.focus();
+        if self.document.focus() != focus {
+            self.document.set_focus(focus);
+        }
+
+        let mutations = self.document.take_mutations();
+        if mutations.is_empty() {
+            return;
+        }
+
+        self.data.apply_mutations(&self.document, &mutations);
+        self.redraw();
+    }
 }
 
 pub struct InstanceHandle {
     pub tx: Sender<InstanceMessage>,
 }
 
+impl InstanceHandle {
+    /// Returns the text that is selected on the page, for instance to copy it to the clipboard
+    pub async fn get_selected_text(&self) -> Result<String> {
+        let (tx, rx) = oneshot::channel();
+
+        self.tx.send(InstanceMessage::SelectedText(tx)).await?;
+
+        Ok(rx.await?)
+    }
+
+    /// Returns the text that is selected on the page, blocking the current thread until the instance has answered.
+    /// This must not be called from within an async runtime.
+    pub fn blocking_get_selected_text(&self) -> Result<String> {
+        let (tx, rx) = oneshot::channel();
+
+        self.tx.blocking_send(InstanceMessage::SelectedText(tx))?;
+
+        Ok(rx.blocking_recv()?)
+    }
+}
+
 pub enum InstanceMessage {
     /// Redraw the instance with the given size
     Redraw(SizeU32),
@@ -240,6 +542,16 @@ pub enum InstanceMessage {
     Input(InputEvent),
     /// Debug event (send nodes, select element, etc.)
     Debug(DebugEvent),
+    /// Search the text of the page, highlight the matches and scroll the active match into view. The number of
+    /// matches and the active one are reported to the chrome. Searching for the same query again moves to the next
+    /// (or previous) match, and an empty query removes the highlights.
+    Find {
+        query: String,
+        case_sensitive: bool,
+        forward: bool,
+    },
+    /// Send the text that is selected on the page to the given sender
+    SelectedText(oneshot::Sender<String>),
 }
 
 #[derive(Clone)]
@@ -257,6 +569,10 @@ impl<C: ModuleConfiguration> EventLoopHandle<C> for El<C> {
     fn reload_from(&self, rt: C::RenderTree) {
         self.send(InternalInstanceMessage::ReloadFrom(rt));
     }
+
+    fn add_web_font(&self, document: String, descriptor: FontFaceDescriptor, data: Vec<u8>) {
+        self.send(InternalInstanceMessage::WebFont(document, descriptor, data));
+    }
 }
 
 impl<C: ModuleConfiguration> El<C> {
@@ -280,6 +596,8 @@ pub enum InternalInstanceMessage<C: HasTreeDrawer> {
     Redraw,
     /// Reload the instance from the given tree
     ReloadFrom(C::RenderTree),
+    /// Add a downloaded web font for the given document
+    WebFont(String, FontFaceDescriptor, Vec<u8>),
 }
 
 pub enum DebugEvent {
@@ -289,6 +607,8 @@ pub enum DebugEvent {
     SelectElement(u64),
     /// Send a `NodeDescription` of the element with the given ID to the given sender
     Info(u64, SyncSender<NodeDesc>),
+    /// Send the accessibility tree of the page to the given sender
+    SendAccessibilityTree(SyncSender<AccessibilityTree>),
     /// Deselect the currently selected element (visually)
     Deselect,
     /// Toggle the debug mode
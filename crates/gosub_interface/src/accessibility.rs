@@ -0,0 +1,181 @@
+//! Accessibility tree of a page, for assistive technology. The tree is shaped like an AccessKit tree update: a flat
+//! list of nodes that refer to their children by id, the id of the root and the id of the focused node. It
+//! serializes to JSON, so an embedder can bridge it to the accessibility layer of the platform.
+
+use gosub_shared::geo::FP;
+use serde::Serialize;
+
+/// Role of a node in the accessibility tree: what kind of thing it is to the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Role {
+    Document,
+    GenericContainer,
+    /// Static text, with the text as its label
+    Label,
+    Paragraph,
+    Heading,
+    Link,
+    Button,
+    CheckBox,
+    RadioButton,
+    Switch,
+    TextInput,
+    MultilineTextInput,
+    SearchInput,
+    EmailInput,
+    NumberInput,
+    PasswordInput,
+    PhoneNumberInput,
+    UrlInput,
+    ComboBox,
+    ListBox,
+    ListBoxOption,
+    List,
+    ListItem,
+    Image,
+    Table,
+    RowGroup,
+    Row,
+    Cell,
+    ColumnHeader,
+    RowHeader,
+    Navigation,
+    Main,
+    Banner,
+    ContentInfo,
+    Complementary,
+    Form,
+    Region,
+    Search,
+    Article,
+    Figure,
+    Group,
+    Dialog,
+    AlertDialog,
+    Alert,
+    Status,
+    Blockquote,
+    Separator,
+    Details,
+    DisclosureTriangle,
+    Tab,
+    TabList,
+    TabPanel,
+    Menu,
+    MenuBar,
+    MenuItem,
+    ProgressIndicator,
+    Meter,
+    Slider,
+    Tooltip,
+    Tree,
+    TreeItem,
+    Iframe,
+}
+
+/// State of a node that can be checked or pressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Toggled {
+    True,
+    False,
+    Mixed,
+}
+
+/// Box of a node on the page, from its top left corner (`x0`, `y0`) to its bottom right corner (`x1`, `y1`)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Bounds {
+    pub x0: FP,
+    pub y0: FP,
+    pub x1: FP,
+    pub y1: FP,
+}
+
+/// Node of the accessibility tree. The id is the id of the node in the render tree it was computed from.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessNode {
+    pub id: u64,
+    pub role: Role,
+    /// Accessible name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Accessible description
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Current value of a form control
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Url a link points to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<Bounds>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toggled: Option<Toggled>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expanded: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected: Option<bool>,
+    /// Level of a heading, or of an item in a tree
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<usize>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub focusable: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub disabled: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
+}
+
+impl AccessNode {
+    /// Returns a node with the role and no name, value or states
+    #[must_use]
+    pub fn new(id: u64, role: Role) -> Self {
+        Self {
+            id,
+            role,
+            label: None,
+            description: None,
+            value: None,
+            url: None,
+            bounds: None,
+            children: Vec::new(),
+            toggled: None,
+            expanded: None,
+            selected: None,
+            level: None,
+            focusable: false,
+            disabled: false,
+            required: false,
+            read_only: false,
+        }
+    }
+}
+
+/// Accessibility tree of a page
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AccessibilityTree {
+    /// Nodes of the tree, in tree order
+    pub nodes: Vec<AccessNode>,
+    pub root: u64,
+    /// Node that has the keyboard focus, or the root when no element has it
+    pub focus: u64,
+}
+
+impl AccessibilityTree {
+    /// Returns the node with the id
+    #[must_use]
+    pub fn node(&self, id: u64) -> Option<&AccessNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+}
+
+/// Provides the accessibility tree of the page it shows
+pub trait AccessibilityProvider {
+    /// Computes the accessibility tree of the page as it is now
+    fn accessibility_tree(&self) -> AccessibilityTree;
+}
@@ -1,4 +1,5 @@
 use crate::config::HasRenderBackend;
+use crate::draw::FindResult;
 use crate::instance::InstanceId;
 use crate::render_backend::RenderBackend;
 use gosub_shared::geo::SizeU32;
@@ -7,4 +8,6 @@ use gosub_shared::geo::SizeU32;
 /// this can include drawing the scene
 pub trait ChromeHandle<C: HasRenderBackend>: Send + Clone {
     fn draw_scene(&self, scene: <C::RenderBackend as RenderBackend>::Scene, size: SizeU32, instance: InstanceId);
+    /// Reports the outcome of a search in the page, for instance to show the number of matches in a find bar
+    fn find_result(&self, result: FindResult, instance: InstanceId);
 }
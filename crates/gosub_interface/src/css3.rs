@@ -1,4 +1,6 @@
 use crate::config::{HasDocument, HasRenderTree};
+use crate::font::FontFaceDescriptor;
+use crate::render_tree::RenderTree;
 use gosub_shared::async_executor::WasmNotSend;
 use gosub_shared::config::ParserConfig;
 use gosub_shared::errors::CssResult;
@@ -29,6 +31,9 @@ pub trait CssSystem: Clone + Debug + 'static {
     /// Parses a string into a CSS3 stylesheet
     fn parse_str(str: &str, config: ParserConfig, origin: CssOrigin, source_url: &str) -> CssResult<Self::Stylesheet>;
 
+    /// Parses the value of a `style` attribute into a stylesheet that applies to the element itself
+    fn parse_inline_style(style: &str) -> Self::Stylesheet;
+
     /// Returns the properties of a node
     /// If `None` is returned, the node is not renderable
     fn properties_from_node<C: HasDocument<CssSystem = Self>>(
@@ -38,8 +43,25 @@ pub trait CssSystem: Clone + Debug + 'static {
         id: NodeId,
     ) -> Option<Self::PropertyMap>;
 
+    /// Returns the properties of a pseudo-element of a node, like `selection` for `::selection`. Returns `None` when
+    /// no rule applies to the pseudo-element of the node.
+    fn pseudo_element_properties_from_node<C: HasDocument<CssSystem = Self>>(
+        node: &C::Node,
+        sheets: &[Self::Stylesheet],
+        doc: &C::Document,
+        id: NodeId,
+        pseudo: &str,
+    ) -> Option<Self::PropertyMap>;
+
     fn inheritance<C: HasRenderTree<CssSystem = Self>>(tree: &mut C::RenderTree);
 
+    /// Resolves the inherited values in the subtree of `node_id`, which inherits the values of its (resolved) parent
+    fn inheritance_from<C: HasRenderTree<CssSystem = Self>>(
+        tree: &mut C::RenderTree,
+        node_id: <C::RenderTree as RenderTree<C>>::NodeId,
+        parent_id: <C::RenderTree as RenderTree<C>>::NodeId,
+    );
+
     fn load_default_useragent_stylesheet() -> Self::Stylesheet;
 }
 
@@ -49,6 +71,13 @@ pub trait CssStylesheet: PartialEq + Debug {
 
     /// Returns the source URL of the stylesheet
     fn url(&self) -> &str;
+
+    /// Returns the `@font-face` rules found in the stylesheet
+    fn font_faces(&self) -> &[FontFaceDescriptor];
+
+    /// Returns true when a selector matches on the given attribute of a previous sibling, so changing the attribute
+    /// of an element can change the style of the siblings after it
+    fn matches_sibling_attribute(&self, name: &str) -> bool;
 }
 
 pub trait CssPropertyMap<S: CssSystem>: Default + Debug + WasmNotSend {
@@ -111,5 +140,8 @@ pub trait CssValue: Sized {
 
     fn is_comma(&self) -> bool;
 
+    /// Parses the value as a color. Returns None when the value is not a color (for instance, a keyword).
+    fn parse_color(&self) -> Option<(f32, f32, f32, f32)>;
+
     fn is_none(&self) -> bool;
 }
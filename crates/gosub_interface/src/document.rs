@@ -1,9 +1,11 @@
 use crate::config::HasDocument;
 use crate::node::{Node, QuirksMode};
+use crate::resource::{ResourceLoader, ResourceRequest, ResourceResponse};
 use gosub_shared::byte_stream::Location;
 use gosub_shared::node::NodeId;
 use std::collections::HashMap;
 use std::fmt::{Debug, Display};
+use std::sync::Arc;
 use url::Url;
 
 /// Type of the given document
@@ -15,6 +17,31 @@ pub enum DocumentType {
     IframeSrcDoc,
 }
 
+/// Change to the tree of a document, like a DOM `MutationRecord`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MutationRecord {
+    /// A node is inserted into `parent`, between `previous_sibling` and `next_sibling`
+    Insert {
+        node: NodeId,
+        parent: NodeId,
+        previous_sibling: Option<NodeId>,
+        next_sibling: Option<NodeId>,
+    },
+    /// A node is removed from `parent`, where it was between `previous_sibling` and `next_sibling`
+    Remove {
+        node: NodeId,
+        parent: NodeId,
+        previous_sibling: Option<NodeId>,
+        next_sibling: Option<NodeId>,
+    },
+    /// An attribute of an element is added, changed or removed
+    Attribute { node: NodeId, name: String },
+    /// The value of a text node changed
+    Text { node: NodeId },
+    /// An element gains or loses the keyboard focus
+    Focus { node: NodeId },
+}
+
 pub trait DocumentBuilder<C: HasDocument> {
     fn new_document(url: Option<Url>) -> C::Document;
     fn new_document_fragment(context_node: &<C::Document as Document<C>>::Node, quirks_mode: QuirksMode)
@@ -49,6 +76,30 @@ pub trait Document<C: HasDocument<Document = Self>>: Sized + Display + Debug + P
     fn stylesheets(&self) -> &Vec<C::Stylesheet>;
     fn add_stylesheet(&mut self, stylesheet: C::Stylesheet);
 
+    /// Sets the loader that fetches the external resources of the document
+    fn set_resource_loader(&mut self, loader: Arc<dyn ResourceLoader>);
+    /// Returns the loader that fetches the external resources of the document, if it has one
+    fn resource_loader(&self) -> Option<Arc<dyn ResourceLoader>>;
+    /// Hands a request for an external resource to the resource loader. Without a loader, the request is dropped.
+    fn request_resource(&mut self, request: ResourceRequest);
+    /// Processes a finished request. Loaded stylesheets are added to the document, other kinds of resources are not
+    /// used by the document and are ignored.
+    fn resource_loaded(&mut self, response: ResourceResponse);
+    /// Returns true while render-blocking resources are still loading
+    fn is_render_blocked(&self) -> bool;
+
+    /// Starts or stops recording the mutations of the document. Nothing is recorded until this is turned on, so
+    /// parsing a document does not record anything.
+    fn observe_mutations(&mut self, observe: bool);
+    /// Returns the mutations recorded since the last call, in the order they were made
+    fn take_mutations(&mut self) -> Vec<MutationRecord>;
+
+    /// Returns the element that has the keyboard focus, and whether it shows that it has it (it matches
+    /// `:focus-visible`)
+    fn focus(&self) -> Option<(NodeId, bool)>;
+    /// Gives the keyboard focus to an element, or takes it off the page when None
+    fn set_focus(&mut self, focus: Option<(NodeId, bool)>);
+
     /// Return the root node of the document
     fn get_root(&self) -> &Self::Node;
 
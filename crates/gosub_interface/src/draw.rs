@@ -1,22 +1,77 @@
+use crate::accessibility::AccessibilityProvider;
 use crate::config::{HasDocument, HasDrawComponents, HasHtmlParser};
+use crate::document::MutationRecord;
 use crate::eventloop::EventLoopHandle;
+use crate::font::FontFaceDescriptor;
+use crate::input::{Key, MouseButton};
 use crate::layout::LayoutTree;
 use crate::render_backend::{ImgCache, NodeDesc, RenderBackend};
 use gosub_net::http::fetcher::Fetcher;
+use gosub_net::http::request::Request;
 use gosub_shared::geo::{Point, SizeU32, FP};
+use gosub_shared::node::NodeId;
 use gosub_shared::types::Result;
 use std::future::Future;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use url::Url;
 
-pub trait TreeDrawer<C: HasDrawComponents> {
+/// Outcome of a search in the page
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FindResult {
+    /// Number of matches on the page
+    pub matches: usize,
+    /// Index of the active match, which is `None` when there are no matches
+    pub active: Option<usize>,
+}
+
+pub trait TreeDrawer<C: HasDrawComponents>: AccessibilityProvider {
     type ImgCache: ImgCache<C::RenderBackend>;
 
     fn draw(&mut self, size: SizeU32, el: &impl EventLoopHandle<C>) -> <C::RenderBackend as RenderBackend>::Scene;
     fn mouse_move(&mut self, x: FP, y: FP) -> bool;
+    /// Handles a press of a mouse button at the position the mouse moved to last. Returns true when the page needs to
+    /// be redrawn.
+    fn mouse_down(&mut self, button: MouseButton) -> bool;
+    /// Handles a release of a mouse button. Returns true when the page needs to be redrawn.
+    fn mouse_up(&mut self, button: MouseButton) -> bool;
+    /// Handles a press of a key, which goes to the focused element. Tab and Shift+Tab move the focus. Returns true
+    /// when the page needs to be redrawn.
+    fn key_down(&mut self, key: Key) -> bool;
+    /// Handles a release of a key. Returns true when the page needs to be redrawn.
+    fn key_up(&mut self, key: Key) -> bool;
+
+    /// Returns the element that has the keyboard focus, and whether it shows a focus ring because it was focused with
+    /// the keyboard
+    fn focus(&self) -> Option<(NodeId, bool)>;
+
+    /// Returns the request of the page the last input event navigates to, if any: a submitted form or a followed
+    /// link. The request is taken, so it is returned only once.
+    fn take_navigation(&mut self) -> Option<Request>;
+
+    /// Returns the text that is selected on the page, which is empty when nothing is selected
+    fn selected_text(&self) -> String;
 
+    /// Highlights the matches of the query in the text of the page and scrolls the active match into view. Searching
+    /// for the same query again moves to the next match, or the previous one when `forward` is false. An empty query
+    /// removes the highlights.
+    fn find(&mut self, query: &str, case_sensitive: bool, forward: bool) -> FindResult;
+
+    /// Scrolls by a wheel delta, the distance the content moves. The innermost scroll container under the mouse
+    /// scrolls first; what it can not scroll goes to the containers around it and finally to the page.
     fn scroll(&mut self, point: Point);
+
+    /// Returns how far the page is scrolled
+    fn scroll_position(&self) -> Point;
+
+    /// Scrolls the page to a position it was scrolled to before, once the page is laid out. This takes the place of
+    /// scrolling to the fragment of the url, so a page that is reloaded or returned to shows what it showed before.
+    fn restore_scroll_position(&mut self, position: Point);
+
+    /// Navigates to a url that differs from the url of the page only in its fragment, by scrolling to the element the
+    /// fragment points to instead of loading the page again. Returns false when the url points to another document.
+    fn navigate_to_fragment(&mut self, url: &Url) -> bool;
+
     fn from_url(
         url: Url,
         layouter: C::Layouter,
@@ -83,5 +138,24 @@ pub trait TreeDrawer<C: HasDrawComponents> {
     where
         C: HasDocument + HasHtmlParser;
 
+    /// Sends the request of a submitted form or a followed link and loads the response as the new page
+    fn load_request(
+        &mut self,
+        request: Request,
+        el: impl EventLoopHandle<C>,
+    ) -> impl Future<Output = Result<C::Document>> + 'static
+    where
+        C: HasDocument + HasHtmlParser;
+
     fn reload_from(&mut self, tree: C::RenderTree);
+
+    /// Updates the render tree with the mutations of the document, so only the changed nodes are styled and laid out
+    /// again instead of reloading the whole tree
+    fn apply_mutations(&mut self, document: &C::Document, mutations: &[MutationRecord])
+    where
+        C: HasDocument;
+
+    /// Registers a downloaded web font for the given document and re-lays out the text when the font
+    /// arrived in time to be used (see `font-display`)
+    fn add_web_font(&mut self, document: &str, descriptor: &FontFaceDescriptor, data: &[u8]);
 }
@@ -1,4 +1,5 @@
 use crate::config::HasDrawComponents;
+use crate::font::FontFaceDescriptor;
 use crate::render_backend::ImageBuffer;
 use gosub_shared::async_executor::WasmNotSendSync;
 use gosub_shared::geo::SizeU32;
@@ -13,4 +14,7 @@ pub trait EventLoopHandle<C: HasDrawComponents>: WasmNotSendSync + Clone + 'stat
 
     /// Reload the instance from the given render tree
     fn reload_from(&self, rt: C::RenderTree);
+
+    /// Add a downloaded web font for the document with the given url
+    fn add_web_font(&self, document: String, descriptor: FontFaceDescriptor, data: Vec<u8>);
 }
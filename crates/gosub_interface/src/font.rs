@@ -1,16 +1,32 @@
 use std::fmt::{Debug, Formatter};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 
+/// Id of the next font data that is wrapped in a `FontBlob`
+static NEXT_FONT_ID: AtomicU64 = AtomicU64::new(1);
+
 #[derive(Clone)]
 pub struct FontBlob {
     pub data: Arc<dyn AsRef<[u8]> + Send + Sync>,
     pub index: u32,
+    id: u64,
 }
 
 impl FontBlob {
     pub fn new(data: Arc<dyn AsRef<[u8]> + Send + Sync>, index: u32) -> Self {
-        Self { data, index }
+        Self {
+            data,
+            index,
+            id: NEXT_FONT_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the id of the font data. Clones of the blob share the id, which is never given to other font data,
+    /// not even after this data is dropped.
+    #[must_use]
+    pub fn id(&self) -> u64 {
+        self.id
     }
 
     #[must_use]
@@ -54,6 +70,67 @@ impl std::fmt::Display for FontStyle {
     }
 }
 
+/// A single source from the `src` descriptor of a `@font-face` rule
+#[derive(Clone, Debug, PartialEq)]
+pub enum FontFaceSource {
+    /// Font file that must be fetched from the given url. The format is the (optional) `format()` hint
+    Url { url: String, format: Option<String> },
+    /// Font that is installed on the system, referenced by its full or postscript name
+    Local(String),
+}
+
+/// The `font-display` descriptor, which defines how text is rendered while a web font is loading
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FontDisplay {
+    #[default]
+    Auto,
+    Block,
+    Swap,
+    Fallback,
+    Optional,
+}
+
+/// Descriptors of a single `@font-face` rule
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontFaceDescriptor {
+    /// Family name under which the font is made available
+    pub family: String,
+    /// Sources of the font in order of preference
+    pub sources: Vec<FontFaceSource>,
+    /// Style of the face
+    pub style: FontStyle,
+    /// Weight range of the face (min, max). Non-variable fonts have min == max.
+    pub weight: (i32, i32),
+    /// Stretch range of the face (min, max), where 1.0 is normal
+    pub stretch: (f32, f32),
+    /// Codepoint ranges (inclusive) the face may be used for. Empty means all codepoints.
+    pub unicode_range: Vec<(u32, u32)>,
+    /// How text is displayed while the font is loading
+    pub display: FontDisplay,
+}
+
+impl FontFaceDescriptor {
+    #[must_use]
+    pub fn new(family: &str) -> Self {
+        Self {
+            family: family.to_string(),
+            sources: Vec::new(),
+            style: FontStyle::Normal,
+            weight: (400, 400),
+            stretch: (1.0, 1.0),
+            unicode_range: Vec::new(),
+            display: FontDisplay::Auto,
+        }
+    }
+
+    /// Returns true when the given character falls within the unicode-range of this face
+    #[must_use]
+    pub fn covers(&self, ch: char) -> bool {
+        let cp = ch as u32;
+        self.unicode_range.is_empty() || self.unicode_range.iter().any(|(start, end)| cp >= *start && cp <= *end)
+    }
+}
+
 pub trait HasFontManager: Sized + Debug {
     type FontManager: FontManager;
 }
@@ -75,6 +152,9 @@ pub trait FontInfo: Sized + Clone + Debug + Send {
     fn with_monospaced(&self, monospaced: bool) -> Self;
     fn with_path(&self, path: PathBuf, index: Option<i32>) -> Self;
 
+    /// Returns the font data for fonts that are not backed by a file (ie: web fonts)
+    fn data(&self) -> Option<FontBlob>;
+
     /// Converts this font info to a font description usable by Pango
     fn to_description(&self, size: f32) -> String;
 }
@@ -84,4 +164,28 @@ pub trait FontManager: Sized + 'static {
 
     fn instance() -> Arc<RwLock<Self>>;
     fn find_font(&self, families: &[&str], style: FontStyle) -> Option<Self::FontInfo>;
+
+    /// Finds the best matching font for the given list of families by using the CSS font matching
+    /// algorithm. Families are tried in order, and generic families (`serif`, `sans-serif` etc.) are
+    /// mapped onto the configured system fonts. Within a family the nearest stretch, style and weight
+    /// is selected.
+    fn match_font(&self, families: &[&str], style: FontStyle, weight: i32, stretch: f32) -> Option<Self::FontInfo>;
+
+    /// Returns the fonts that are needed to render the characters in `text` that are not present in
+    /// the `base` font. The fonts are returned in order of first use and are matched as closely as
+    /// possible to the style, weight and stretch of `base`.
+    fn fallback_fonts(&self, text: &str, base: &Self::FontInfo) -> Vec<Self::FontInfo>;
+
+    /// Registers a downloaded web font for the given document. The data can be a TTF, OTF, WOFF or
+    /// WOFF2 file and is decoded before it is registered.
+    fn add_web_font(&mut self, document: &str, descriptor: &FontFaceDescriptor, data: &[u8]) -> Result<(), FontError>;
+
+    /// Registers an installed font that is referenced by a `local()` source for the given document
+    fn add_local_font(&mut self, document: &str, descriptor: &FontFaceDescriptor, name: &str) -> Result<(), FontError>;
+
+    /// Removes all web fonts that were registered for the given document
+    fn remove_web_fonts(&mut self, document: &str);
+
+    /// Sets the document whose web fonts are taken into account when matching fonts
+    fn set_active_document(&mut self, document: Option<&str>);
 }
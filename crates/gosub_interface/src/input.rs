@@ -11,9 +11,29 @@ pub enum InputEvent {
     /// A mouse button was released
     MouseUp(MouseButton),
     /// A key was pressed
-    KeyboardDown(char),
+    KeyboardDown(Key),
     /// A key was released
-    KeyboardUp(char),
+    KeyboardUp(Key),
+}
+
+/// Key on the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// Key that types the character
+    Character(char),
+    Backspace,
+    Delete,
+    Enter,
+    Tab,
+    Escape,
+    ArrowLeft,
+    ArrowRight,
+    ArrowUp,
+    ArrowDown,
+    Home,
+    End,
+    Shift,
+    Control,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
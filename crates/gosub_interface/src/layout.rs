@@ -4,6 +4,7 @@ use gosub_shared::font::Glyph;
 use gosub_shared::geo::{Point, Rect, Size, SizeU32};
 use gosub_shared::types::Result;
 use std::fmt::Debug;
+use std::ops::Range;
 
 #[derive(Clone, Debug)]
 pub struct FontData {
@@ -172,6 +173,36 @@ pub trait LayoutNode<C: HasLayouter>: HasTextLayout<C> {
     fn text_size(&self) -> Option<Size>;
     /// This can only return true if the `Layout::COLLAPSE_INLINE` is set true for the layouter
     fn is_anon_inline_parent(&self) -> bool;
+    /// Natural size of a replaced element (like an inline `<svg>`). Returns None for all other nodes.
+    fn intrinsic_size(&self) -> Option<IntrinsicSize>;
+    /// Text that is shown in a replaced element, like the value of a form control. It is laid out in the content box
+    /// of the element with the style of the element.
+    fn replaced_text(&self) -> Option<ReplacedText>;
+}
+
+/// Text in the content box of a replaced element
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplacedText {
+    pub text: String,
+    /// The text is wrapped at the width of the content box, otherwise it stays on a single line that is centered
+    /// vertically
+    pub multiline: bool,
+    /// Room that is left after the text when the natural width of the element is taken from its text, like the
+    /// arrow of a `<select>`
+    pub end_space: f32,
+    /// Rows of a list that is shown below the element, like the options of an open `<select>`. They are laid out one
+    /// per line, starting at the bottom of the border box, and do not take part in the size of the element.
+    pub list: Vec<String>,
+}
+
+/// Natural dimensions of a replaced element. Any of the dimensions can be missing, in which case it is derived
+/// from the other dimension and the ratio, or from the space that is available.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct IntrinsicSize {
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    /// Ratio of width / height
+    pub ratio: Option<f32>,
 }
 
 pub trait HasTextLayout<C: HasLayouter> {
@@ -197,6 +228,22 @@ pub trait TextLayout {
     fn coords(&self) -> &[i16];
     /// Size of the text
     fn size(&self) -> Size;
+    /// Text the glyphs are shaped from
+    fn text(&self) -> &str;
+    /// Clusters of the glyphs, in the order they are painted from left to right
+    fn clusters(&self) -> &[TextCluster];
+}
+
+/// Glyphs that are shaped from a part of the text together. This is the smallest part of the text that can be
+/// selected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextCluster {
+    /// Byte range of the part of `TextLayout::text` the glyphs are shaped from
+    pub text: Range<usize>,
+    /// Start of the cluster, relative to the offset of the text layout
+    pub x: f32,
+    /// Width of the cluster
+    pub advance: f32,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -223,3 +270,35 @@ pub enum DecorationStyle {
     Dashed,
     Wavy,
 }
+
+/// Value of the `position` property. The layouter places absolute and fixed elements in their containing block, while
+/// fixed and sticky elements also depend on the scroll offset of the page, which is applied when painting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Position {
+    #[default]
+    Static,
+    Relative,
+    Absolute,
+    Fixed,
+    Sticky,
+}
+
+impl Position {
+    /// Returns the position of a `position` keyword. Unknown keywords are `static`.
+    #[must_use]
+    pub fn from_keyword(keyword: Option<&str>) -> Self {
+        match keyword {
+            Some("relative") => Self::Relative,
+            Some("absolute") => Self::Absolute,
+            Some("fixed") => Self::Fixed,
+            Some("sticky") => Self::Sticky,
+            _ => Self::Static,
+        }
+    }
+
+    /// Returns true when the element is positioned, which makes it the containing block of absolute descendants
+    #[must_use]
+    pub fn is_positioned(self) -> bool {
+        self != Self::Static
+    }
+}
@@ -1,3 +1,4 @@
+pub mod accessibility;
 pub mod chrome;
 pub mod config;
 pub mod css3;
@@ -13,4 +14,5 @@ pub mod node;
 pub mod render_backend;
 pub mod render_tree;
 pub mod request;
+pub mod resource;
 pub mod svg;
@@ -115,6 +115,8 @@ pub trait ElementDataType<C: HasDocument> {
     fn remove_attribute(&mut self, name: &str);
     /// Add a class to the element
     fn add_class(&mut self, class: &str);
+    /// Returns the parsed `style` attribute of the element (or None when the element has no style attribute)
+    fn inline_style(&self) -> Option<&C::Stylesheet>;
 
     fn matches_tag_and_attrs_without_order(&self, other_data: &Self) -> bool;
     fn is_mathml_integration_point(&self) -> bool;
@@ -8,6 +8,7 @@ use smallvec::SmallVec;
 use std::fmt::{Debug, Display, Write};
 use std::io;
 use std::ops::{Div, Mul, MulAssign};
+use std::str::FromStr;
 
 pub trait WindowHandle: HasDisplayHandle + HasWindowHandle + Send + Sync + Clone {}
 
@@ -72,11 +73,21 @@ pub trait RenderBackend: Sized + Debug {
 pub trait Scene<B: RenderBackend>: Clone + Debug + Send {
     fn draw_rect(&mut self, rect: &RenderRect<B>);
     fn draw_text(&mut self, text: &RenderText<B>);
+    /// Draws a rectangle with (rounded) corners that is blurred with a gaussian blur, like the shadow of a box
+    fn draw_blurred_rect(&mut self, rect: &RenderBlurredRect<B>);
+    /// Draws text that is blurred with a gaussian blur with the given standard deviation, like the shadow of text
+    fn draw_blurred_text(&mut self, text: &RenderText<B>, std_dev: FP);
 
     fn debug_draw_simple_text(&mut self, text: &str, pos: Point, size: FP);
     fn apply_scene(&mut self, scene: &B::Scene, transform: Option<B::Transform>);
     fn reset(&mut self);
 
+    /// Starts a new layer. Everything drawn until the matching `pop_layer` is clipped to the shape, and the layer
+    /// is composited onto the content below with the given opacity and blend mode.
+    fn push_layer(&mut self, clip: &ClipShape<B>, opacity: FP, blend_mode: BlendMode);
+    /// Ends the layer that was started last
+    fn pop_layer(&mut self);
+
     fn new() -> Self;
 }
 
@@ -163,6 +174,27 @@ impl<B: RenderBackend> RenderText<B> {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct RenderBlurredRect<B: RenderBackend> {
+    pub rect: B::Rect,
+    pub radius: Option<B::BorderRadius>,
+    pub color: B::Color,
+    /// Standard deviation of the blur. With zero the rectangle has sharp edges.
+    pub std_dev: FP,
+    pub clip: ShadowClip<B>,
+}
+
+/// How a blurred rectangle is clipped to the box that casts the shadow
+#[derive(Clone, Debug)]
+pub enum ShadowClip<B: RenderBackend> {
+    /// The rectangle is painted everywhere
+    None,
+    /// The rectangle is only painted outside of the box (outer `box-shadow`)
+    Outside(B::Rect, Option<B::BorderRadius>),
+    /// The box is painted, except for the rectangle that is cut out of it (inset `box-shadow`)
+    Inset(B::Rect, Option<B::BorderRadius>),
+}
+
 #[derive(Clone, Debug)]
 pub struct RenderBorder<B: RenderBackend> {
     pub border: B::Border,
@@ -182,6 +214,66 @@ impl<B: RenderBackend> RenderBorder<B> {
     }
 }
 
+/// Shape the content of a layer is clipped to
+#[derive(Clone, Debug)]
+pub enum ClipShape<B: RenderBackend> {
+    /// The content is not clipped, the layer is only used for its opacity or blend mode
+    None,
+    /// Rectangle, optionally with rounded corners
+    Rect(B::Rect, Option<B::BorderRadius>),
+    /// Ellipse around the center with the given radii
+    Ellipse { center: Point, radius_x: FP, radius_y: FP },
+}
+
+/// Mode in which a layer is blended with the content below it (`mix-blend-mode`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+
+impl FromStr for BlendMode {
+    type Err = ();
+
+    /// Parses a keyword of `mix-blend-mode`
+    fn from_str(mode: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match mode {
+            "normal" => Self::Normal,
+            "multiply" => Self::Multiply,
+            "screen" => Self::Screen,
+            "overlay" => Self::Overlay,
+            "darken" => Self::Darken,
+            "lighten" => Self::Lighten,
+            "color-dodge" => Self::ColorDodge,
+            "color-burn" => Self::ColorBurn,
+            "hard-light" => Self::HardLight,
+            "soft-light" => Self::SoftLight,
+            "difference" => Self::Difference,
+            "exclusion" => Self::Exclusion,
+            "hue" => Self::Hue,
+            "saturation" => Self::Saturation,
+            "color" => Self::Color,
+            "luminosity" => Self::Luminosity,
+            _ => return Err(()),
+        })
+    }
+}
+
 pub trait Rect {
     fn new(x: FP, y: FP, width: FP, height: FP) -> Self;
 
@@ -222,11 +314,12 @@ pub enum BorderStyle {
     Hidden,
 }
 
-impl BorderStyle {
-    #[allow(clippy::should_implement_trait)]
-    #[must_use]
-    pub fn from_str(style: &str) -> Self {
-        match style {
+impl FromStr for BorderStyle {
+    type Err = ();
+
+    /// Parses a keyword of `border-style`
+    fn from_str(style: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match style {
             "none" => Self::None,
             "hidden" => Self::Hidden,
             "dotted" => Self::Dotted,
@@ -237,8 +330,8 @@ impl BorderStyle {
             "ridge" => Self::Ridge,
             "inset" => Self::Inset,
             "outset" => Self::Outset,
-            _ => Self::None,
-        }
+            _ => return Err(()),
+        })
     }
 }
 
@@ -523,6 +616,10 @@ pub trait Gradient<B: RenderBackend> {
     }
 
     fn new_sweep(center: Point, start_angle: FP, end_angle: FP, stops: ColorStops<B>) -> Self;
+
+    /// Repeats the color stops beyond the start and end of the gradient, instead of extending the first and last color
+    #[must_use]
+    fn repeat(self) -> Self;
 }
 
 pub trait Color {
@@ -38,5 +38,8 @@ pub trait RenderTreeNode<C: HasLayouter>: Debug {
 
     fn text_data(&self) -> Option<(&str, TextLayoutRef<'_, C>)>;
 
+    /// Returns the markup of an inline `<svg>` element, including all of its descendants
+    fn svg_source(&self) -> Option<&str>;
+
     fn name(&self) -> &str;
 }
@@ -0,0 +1,109 @@
+//! External resources (stylesheets, scripts, images and fonts) of a document. The parser does not fetch anything
+//! itself: it hands requests to the `ResourceLoader` of the document, and the responses are given back to the
+//! document once they are loaded.
+//!
+//! The parser requests the stylesheets of `<link rel="stylesheet">` elements, which all block rendering, and the
+//! resources of `<link rel="preload">` elements. Images and web fonts are fetched by the renderer through the same
+//! loader, so they are queued behind the stylesheets. Scripts are not loaded at all.
+
+use gosub_shared::async_executor::WasmNotSendSync;
+use gosub_shared::node::NodeId;
+use std::fmt::Debug;
+use url::Url;
+
+/// Kind of resource that is requested
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    Stylesheet,
+    Script,
+    Image,
+    Font,
+}
+
+impl ResourceKind {
+    /// Returns the kind for the `as` attribute of a `<link rel="preload">` element
+    #[must_use]
+    pub fn from_destination(destination: &str) -> Option<Self> {
+        match destination {
+            "style" => Some(Self::Stylesheet),
+            "script" => Some(Self::Script),
+            "image" => Some(Self::Image),
+            "font" => Some(Self::Font),
+            _ => None,
+        }
+    }
+
+    /// Default priority of the kind. Stylesheets can block rendering so they are fetched first, images are fetched
+    /// last as the page can be rendered without them.
+    #[must_use]
+    pub fn priority(self) -> ResourcePriority {
+        match self {
+            Self::Stylesheet => ResourcePriority::High,
+            Self::Script | Self::Font => ResourcePriority::Normal,
+            Self::Image => ResourcePriority::Low,
+        }
+    }
+}
+
+/// Priority of a request. Queued requests with a higher priority are fetched first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ResourcePriority {
+    Low,
+    Normal,
+    High,
+}
+
+/// Request for an external resource
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceRequest {
+    /// Absolute url of the resource
+    pub url: Url,
+    pub kind: ResourceKind,
+    pub priority: ResourcePriority,
+    /// Render-blocking resources must be loaded before the document is rendered for the first time
+    pub render_blocking: bool,
+    /// Element that requested the resource (for instance, the `<link>` element)
+    pub initiator: Option<NodeId>,
+}
+
+impl ResourceRequest {
+    /// Creates a non-blocking request with the default priority of the kind
+    #[must_use]
+    pub fn new(url: Url, kind: ResourceKind) -> Self {
+        Self {
+            url,
+            kind,
+            priority: kind.priority(),
+            render_blocking: false,
+            initiator: None,
+        }
+    }
+}
+
+/// Response to a resource request
+#[derive(Debug)]
+pub struct ResourceResponse {
+    pub request: ResourceRequest,
+    /// Body of the resource, or the reason why it could not be loaded
+    pub body: Result<Vec<u8>, String>,
+}
+
+/// Called with the response of a request that is fetched with `ResourceLoader::fetch`
+#[cfg(not(target_arch = "wasm32"))]
+pub type ResourceCallback = Box<dyn FnOnce(ResourceResponse) + Send>;
+
+/// Called with the response of a request that is fetched with `ResourceLoader::fetch`
+#[cfg(target_arch = "wasm32")]
+pub type ResourceCallback = Box<dyn FnOnce(ResourceResponse)>;
+
+/// Fetches the external resources of a document in the background. How the responses get back to the document is
+/// up to the loader: `FetchResourceLoader` in `gosub_renderer` sends them over a channel that is drained by the
+/// owner of the document, while the loaders in `gosub_html5` hand them back to the caller.
+pub trait ResourceLoader: WasmNotSendSync + Debug {
+    /// Queues a request, of which the response is given back to the document
+    fn request(&self, request: ResourceRequest);
+
+    /// Queues a request, of which the response is given to `reply` instead of the document. The renderer fetches
+    /// images and web fonts this way, so they are ordered with the other requests of the document.
+    fn fetch(&self, request: ResourceRequest, reply: ResourceCallback);
+}
@@ -1,6 +1,7 @@
 use crate::http::fetcher::RequestAgent;
 
 pub mod fetcher;
+pub mod form;
 pub mod headers;
 pub mod request;
 mod request_impl;
@@ -5,6 +5,7 @@ use std::future::Future;
 use url::{ParseError, Url};
 
 use gosub_shared::types::Result;
+use gosub_shared::{timing_start_async, timing_stop};
 
 use crate::http::request::Request;
 use crate::http::request_impl::RequestImpl;
@@ -40,6 +41,16 @@ impl Fetcher {
     }
 
     pub async fn get_url(&self, url: &Url) -> Result<Response> {
+        let t_id = timing_start_async!("net.fetch", url.as_str());
+
+        let resp = self.fetch_url(url).await;
+
+        timing_stop!(t_id);
+
+        resp
+    }
+
+    async fn fetch_url(&self, url: &Url) -> Result<Response> {
         let scheme = url.scheme();
 
         let resp = if scheme == "http" || scheme == "https" {
@@ -63,8 +74,25 @@ impl Fetcher {
         self.get_url(&url).await
     }
 
-    pub fn get_req(&self, _url: &Request) {
-        todo!()
+    /// Sends the request, for instance the submission of a form. Relative urls are resolved against the base url.
+    /// Requests for file urls read the file, whatever their method.
+    pub async fn get_req(&self, req: &Request) -> Result<Response> {
+        let url = self.parse_url(&req.uri)?;
+
+        let t_id = timing_start_async!("net.fetch", url.as_str());
+
+        let resp = if url.scheme() == "http" || url.scheme() == "https" {
+            let mut req = req.clone();
+            req.uri = url.to_string();
+
+            self.client.get_req(&req).await
+        } else {
+            self.fetch_url(&url).await
+        };
+
+        timing_stop!(t_id);
+
+        resp
     }
 
     pub fn parse_url(&self, url: &str) -> Result<Url> {
@@ -0,0 +1,273 @@
+//! Form submission. The entries of a form data set are encoded as `application/x-www-form-urlencoded`,
+//! `multipart/form-data` or `text/plain`, and sent in the query of a GET request or in the body of a POST request.
+
+use crate::http::request::Request;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use url::form_urlencoded;
+use url::Url;
+
+/// Method of a form submission
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FormMethod {
+    #[default]
+    Get,
+    Post,
+    /// Closes the dialog the form is in, without sending a request
+    Dialog,
+}
+
+impl FormMethod {
+    /// Parses a `method` or `formmethod` attribute. Invalid values are GET.
+    #[must_use]
+    pub fn from_attribute(value: &str) -> Self {
+        let value = value.trim();
+
+        if value.eq_ignore_ascii_case("post") {
+            Self::Post
+        } else if value.eq_ignore_ascii_case("dialog") {
+            Self::Dialog
+        } else {
+            Self::Get
+        }
+    }
+}
+
+/// Encoding of the body of a POST form submission
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FormEncoding {
+    #[default]
+    UrlEncoded,
+    Multipart,
+    TextPlain,
+}
+
+impl FormEncoding {
+    /// Parses an `enctype` or `formenctype` attribute. Invalid values are `application/x-www-form-urlencoded`.
+    #[must_use]
+    pub fn from_attribute(value: &str) -> Self {
+        let value = value.trim();
+
+        if value.eq_ignore_ascii_case("multipart/form-data") {
+            Self::Multipart
+        } else if value.eq_ignore_ascii_case("text/plain") {
+            Self::TextPlain
+        } else {
+            Self::UrlEncoded
+        }
+    }
+}
+
+/// Submission of a form: where it goes, how, and the names and values of its controls in tree order
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormSubmission {
+    pub action: Url,
+    pub method: FormMethod,
+    pub encoding: FormEncoding,
+    pub entries: Vec<(String, String)>,
+}
+
+impl FormSubmission {
+    /// Returns the request that submits the form, or None for a dialog form. A GET submission replaces the query of
+    /// the action with the url encoded entries. A POST submission to an http(s) url sends the entries in the body,
+    /// in the encoding of the form. Other urls are only navigated to.
+    #[must_use]
+    pub fn request(&self) -> Option<Request> {
+        let http = matches!(self.action.scheme(), "http" | "https");
+
+        match self.method {
+            FormMethod::Dialog => None,
+            FormMethod::Get => {
+                let mut url = self.action.clone();
+                if http || url.scheme() == "file" {
+                    url.set_query(Some(&urlencoded(&self.entries)));
+                }
+
+                Some(Request::new("GET", url.as_str(), "HTTP/1.1"))
+            }
+            FormMethod::Post if !http => Some(Request::new("GET", self.action.as_str(), "HTTP/1.1")),
+            FormMethod::Post => {
+                let mut request = Request::new("POST", self.action.as_str(), "HTTP/1.1");
+
+                let (content_type, body) = match self.encoding {
+                    FormEncoding::UrlEncoded => (
+                        "application/x-www-form-urlencoded".to_owned(),
+                        urlencoded(&self.entries).into_bytes(),
+                    ),
+                    FormEncoding::Multipart => {
+                        let boundary = boundary();
+                        (
+                            format!("multipart/form-data; boundary={boundary}"),
+                            multipart(&self.entries, &boundary),
+                        )
+                    }
+                    FormEncoding::TextPlain => ("text/plain".to_owned(), text_plain(&self.entries).into_bytes()),
+                };
+
+                request.add_header("Content-Type", &content_type);
+                request.body = body;
+
+                Some(request)
+            }
+        }
+    }
+}
+
+/// Encodes the entries as `application/x-www-form-urlencoded`
+#[must_use]
+pub fn urlencoded(entries: &[(String, String)]) -> String {
+    form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(
+            entries
+                .iter()
+                .map(|(name, value)| (normalize_newlines(name), normalize_newlines(value))),
+        )
+        .finish()
+}
+
+/// Encodes the entries as `multipart/form-data`, with parts separated by the boundary
+#[must_use]
+pub fn multipart(entries: &[(String, String)], boundary: &str) -> Vec<u8> {
+    let mut body = String::new();
+
+    for (name, value) in entries {
+        body.push_str(&format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+            escape_part_name(&normalize_newlines(name)),
+            normalize_newlines(value)
+        ));
+    }
+    body.push_str(&format!("--{boundary}--\r\n"));
+
+    body.into_bytes()
+}
+
+/// Encodes the entries as `text/plain`: a `name=value` line for every entry
+#[must_use]
+pub fn text_plain(entries: &[(String, String)]) -> String {
+    entries
+        .iter()
+        .map(|(name, value)| format!("{}={}\r\n", normalize_newlines(name), normalize_newlines(value)))
+        .collect()
+}
+
+/// Returns a boundary for a multipart body. It is random, so it is very unlikely to occur in the values.
+#[must_use]
+pub fn boundary() -> String {
+    let random = RandomState::new().hash_one(std::time::SystemTime::now());
+
+    format!("----GosubFormBoundary{random:016x}")
+}
+
+/// Replaces every line break (a CR, a LF or a CR LF pair) with a CR LF pair
+fn normalize_newlines(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => {
+                chars.next_if_eq(&'\n');
+                normalized.push_str("\r\n");
+            }
+            '\n' => normalized.push_str("\r\n"),
+            c => normalized.push(c),
+        }
+    }
+
+    normalized
+}
+
+/// Escapes the quotes and line breaks in the name of a multipart part
+fn escape_part_name(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        match c {
+            '\n' => escaped.push_str("%0A"),
+            '\r' => escaped.push_str("%0D"),
+            '"' => escaped.push_str("%22"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_attributes() {
+        assert_eq!(FormMethod::from_attribute(" POST "), FormMethod::Post);
+        assert_eq!(FormMethod::from_attribute("put"), FormMethod::Get);
+        assert_eq!(FormMethod::from_attribute("dialog"), FormMethod::Dialog);
+
+        assert_eq!(
+            FormEncoding::from_attribute("Multipart/Form-Data"),
+            FormEncoding::Multipart
+        );
+        assert_eq!(FormEncoding::from_attribute("text/plain"), FormEncoding::TextPlain);
+        assert_eq!(
+            FormEncoding::from_attribute("application/json"),
+            FormEncoding::UrlEncoded
+        );
+    }
+
+    #[test]
+    fn encode_entries() {
+        let entries = entries(&[("q", "gosub browser"), ("note", "a\nb&c"), ("é", "\r")]);
+
+        assert_eq!(urlencoded(&entries), "q=gosub+browser&note=a%0D%0Ab%26c&%C3%A9=%0D%0A");
+        assert_eq!(text_plain(&entries), "q=gosub browser\r\nnote=a\r\nb&c\r\né=\r\n\r\n");
+        assert_eq!(
+            String::from_utf8(multipart(&entries[..2], "XYZ")).unwrap(),
+            "--XYZ\r\nContent-Disposition: form-data; name=\"q\"\r\n\r\ngosub browser\r\n\
+             --XYZ\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\na\r\nb&c\r\n--XYZ--\r\n"
+        );
+        assert_eq!(escape_part_name("a\"b\nc"), "a%22b%0Ac");
+    }
+
+    #[test]
+    fn submission_requests() {
+        let mut submission = FormSubmission {
+            action: Url::parse("https://example.com/search?old=1#top").unwrap(),
+            method: FormMethod::Get,
+            encoding: FormEncoding::Multipart,
+            entries: entries(&[("q", "rust"), ("lang", "en")]),
+        };
+
+        let request = submission.request().unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.uri, "https://example.com/search?q=rust&lang=en#top");
+        assert!(request.body.is_empty());
+
+        submission.method = FormMethod::Post;
+        submission.encoding = FormEncoding::UrlEncoded;
+        let request = submission.request().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.uri, "https://example.com/search?old=1#top");
+        assert_eq!(
+            request.headers.get("Content-Type").map(String::as_str),
+            Some("application/x-www-form-urlencoded")
+        );
+        assert_eq!(request.body, b"q=rust&lang=en");
+
+        submission.encoding = FormEncoding::Multipart;
+        let request = submission.request().unwrap();
+        let content_type = request.headers.get("Content-Type").unwrap();
+        let boundary = content_type.strip_prefix("multipart/form-data; boundary=").unwrap();
+        assert_eq!(request.body, multipart(&submission.entries, boundary));
+
+        submission.method = FormMethod::Dialog;
+        assert!(submission.request().is_none());
+    }
+}
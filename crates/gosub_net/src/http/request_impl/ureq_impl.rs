@@ -28,8 +28,19 @@ impl RequestAgent for UreqAgent {
         response.try_into()
     }
 
-    async fn get_req(&self, _req: &Request) -> gosub_shared::types::Result<Response> {
-        todo!()
+    async fn get_req(&self, req: &Request) -> gosub_shared::types::Result<Response> {
+        let mut builder = http::Request::builder().method(req.method.as_str()).uri(&req.uri);
+        for (key, value) in req.headers.all() {
+            builder = builder.header(key, value);
+        }
+
+        let response = if req.body.is_empty() && matches!(req.method.as_str(), "GET" | "HEAD") {
+            self.agent.run(builder.body(())?)?
+        } else {
+            self.agent.run(builder.body(req.body.clone())?)?
+        };
+
+        response.try_into()
     }
 }
 
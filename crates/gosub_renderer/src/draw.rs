@@ -1,42 +1,87 @@
 use crate::debug::scale::px_scale;
-use crate::draw::img::request_img;
+use crate::draw::find::render_find_matches;
+use crate::draw::font::{request_web_fonts, swap_period};
+use crate::draw::form::{dropdown_area, render_control, render_dropdown};
+use crate::draw::gradient::{gradient_brush, render_gradient};
+use crate::draw::img::{render_inline_svg, request_img};
 use crate::draw::img_cache::ImageCache;
+use crate::draw::layer::node_layers;
+use crate::draw::scroll::{render_scrollbars, scroll_box, scrolls_viewport};
+use crate::draw::selection::render_selection;
 use crate::draw::testing::{test_add_element, test_restyle_element};
-use crate::render_tree::{load_html_rendertree, load_html_rendertree_fetcher, load_html_rendertree_source};
+use crate::render_tree::{
+    load_html_rendertree, load_html_rendertree_fetcher, load_html_rendertree_request, load_html_rendertree_source_sync,
+};
 use anyhow::anyhow;
+use gosub_interface::accessibility::{AccessibilityProvider, AccessibilityTree};
 use gosub_interface::config::{HasDocument, HasDrawComponents, HasHtmlParser};
 use gosub_interface::css3::{CssProperty, CssPropertyMap, CssValue};
 
-use gosub_interface::draw::TreeDrawer;
+use gosub_interface::document::MutationRecord;
+use gosub_interface::draw::{FindResult, TreeDrawer};
 use gosub_interface::eventloop::EventLoopHandle;
+use gosub_interface::font::{FontFaceDescriptor, FontManager};
+use gosub_interface::input::{Key, MouseButton};
 use gosub_interface::layout::{Layout, LayoutTree, Layouter};
 use gosub_interface::render_backend::{
-    Border, BorderSide, BorderStyle, Brush, Color, ImageBuffer, ImgCache, NodeDesc, Rect, RenderBackend, RenderBorder,
-    RenderRect, RenderText, Scene as TScene, Text, Transform,
+    BlendMode, Border, BorderRadius as _, BorderSide, BorderStyle, Brush, ClipShape, Color, ImageBuffer, ImgCache,
+    NodeDesc, Radius, Rect, RenderBackend, RenderBlurredRect, RenderBorder, RenderRect, RenderText, Scene as TScene,
+    ShadowClip, Text, Transform,
 };
 use gosub_interface::render_tree;
 use gosub_interface::render_tree::RenderTreeNode as _;
+use gosub_interface::resource::ResourceLoader;
 use gosub_interface::svg::SvgRenderer;
 use gosub_net::http::fetcher::Fetcher;
-use gosub_rendering::position::PositionTree;
+use gosub_net::http::request::Request;
+use gosub_rendering::background::{self, background_layers, Area, BackgroundBox, Edges, ElementBoxes, Tiles};
+use gosub_rendering::border_radius::{border_radii, BorderRadii};
+use gosub_rendering::find::FindState;
+use gosub_rendering::outline::{outline, outline_box};
+use gosub_rendering::position::{HitShape, PositionTree};
+use gosub_rendering::positioning::{is_stacking_context, paint_layer, position, sticky_offset, PaintLayer, Position};
+use gosub_rendering::render_tree::form::{ControlData, ControlKind, ControlState};
+use gosub_rendering::render_tree::fragment::FragmentTarget;
 use gosub_rendering::render_tree::RenderTree;
+use gosub_rendering::scroll::{
+    is_smooth, scroll_into_view, scroll_margin, scroll_padding, Align, Axis, ScrollAnimation, ScrollBox, Scrollbar,
+};
+use gosub_rendering::selection::{caret_at, node_offset_at, text_rects, word_at, Granularity, Selection};
+use gosub_rendering::shadow::{box_shadows, text_shadows, Shadow};
+use gosub_rendering::transform::transform;
 use gosub_shared::geo::{Size, SizeU32, FP};
 use gosub_shared::node::NodeId;
 use gosub_shared::types::Result;
-use log::{error, info};
+use gosub_shared::{timing_start, timing_stop};
+use log::{error, info, warn};
+use std::collections::HashMap;
 use std::future::Future;
+use std::ops::Range;
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use url::Url;
 
+mod find;
+mod font;
+mod form;
+mod gradient;
 mod img;
 pub mod img_cache;
+mod layer;
+mod scroll;
+mod selection;
 mod testing;
 
 const DEBUG_CONTENT_COLOR: (u8, u8, u8) = (0, 192, 255);
 const DEBUG_PADDING_COLOR: (u8, u8, u8) = (0, 255, 192);
 const DEBUG_BORDER_COLOR: (u8, u8, u8) = (255, 72, 72);
 
+/// Longest time between two clicks that count as a double (or triple) click
+const MULTI_CLICK_TIME: Duration = Duration::from_millis(500);
+/// Farthest the mouse can move between two clicks that count as a double (or triple) click
+const MULTI_CLICK_DISTANCE: FP = 4.0;
+
 type Point = gosub_shared::types::Point<FP>;
 
 #[derive(Debug)]
@@ -54,6 +99,60 @@ pub struct TreeDrawerImpl<C: HasDrawComponents> {
     pub(crate) selected_element: Option<NodeId>,
     pub(crate) scene_transform: Option<<C::RenderBackend as RenderBackend>::Transform>,
     pub(crate) img_cache: ImageCache<C::RenderBackend>,
+    pub(crate) web_fonts_requested: Option<Instant>,
+    /// The page has fixed or sticky elements, which move when the page is scrolled
+    pub(crate) has_pinned_elements: bool,
+    /// Nodes whose layout is invalidated by mutations of the document. Only the position tree around them is built
+    /// again, unless it is empty.
+    pub(crate) layout_dirty: Vec<NodeId>,
+    /// Position of the mouse on the page
+    pub(crate) mouse: Point,
+    /// Time, position and number of the last clicks in a row, which tells double and triple clicks apart
+    pub(crate) last_click: Option<(Instant, Point, u32)>,
+    /// Text that is selected on the page
+    pub(crate) text_selection: Option<Selection<NodeId>>,
+    /// Matches of the last search in the page
+    pub(crate) find_state: Option<FindState<NodeId>>,
+    /// The selection follows the mouse while the left button is down
+    pub(crate) selecting: bool,
+    /// Element that has the keyboard focus
+    pub(crate) focused: Option<NodeId>,
+    /// The focused element shows a focus ring (it matches `:focus-visible`), because it was focused with the keyboard
+    /// or is a text control
+    pub(crate) focus_visible: bool,
+    /// Button or link that is held down
+    pub(crate) pressed: Option<NodeId>,
+    /// Shift key is held down
+    pub(crate) shift: bool,
+    /// Control key is held down
+    pub(crate) control: bool,
+    /// Request of the page to navigate to, from a submitted form or a followed link, which the instance sends
+    pub(crate) navigation: Option<Request>,
+    /// Scroll offsets of the scroll containers on the page that were scrolled
+    pub(crate) scroll_offsets: HashMap<NodeId, Point>,
+    /// Scrollbar whose thumb is dragged: its scroll container, its axis and where the thumb was grabbed, from the
+    /// start of the thumb
+    pub(crate) scroll_drag: Option<(NodeId, Axis, FP)>,
+    /// Scroll of the page to make once it is laid out
+    pub(crate) pending_scroll: Option<PendingScroll>,
+    /// Smooth scrolls that are running, of scroll containers or of the page when the id is None
+    pub(crate) scroll_animations: Vec<(Option<NodeId>, ScrollAnimation)>,
+}
+
+/// Scroll of a page that waits for the page to be laid out
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PendingScroll {
+    /// To the element the fragment of the url of the page points to
+    Fragment(String),
+    /// Back to where the page was scrolled to before it was reloaded or left
+    Position(Point),
+}
+
+impl PendingScroll {
+    /// Returns the scroll to the fragment of a url, if it has one
+    fn from_url(url: &Url) -> Option<Self> {
+        url.fragment().map(|fragment| Self::Fragment(fragment.to_string()))
+    }
 }
 
 impl<C: HasDrawComponents> TreeDrawerImpl<C> {
@@ -72,46 +171,74 @@ impl<C: HasDrawComponents> TreeDrawerImpl<C> {
             selected_element: None,
             scene_transform: None,
             img_cache: ImageCache::new(),
+            web_fonts_requested: None,
+            has_pinned_elements: false,
+            layout_dirty: Vec::new(),
+            mouse: Point::ZERO,
+            last_click: None,
+            text_selection: None,
+            find_state: None,
+            selecting: false,
+            focused: None,
+            focus_visible: false,
+            pressed: None,
+            shift: false,
+            control: false,
+            navigation: None,
+            scroll_offsets: HashMap::new(),
+            scroll_drag: None,
+            pending_scroll: None,
+            scroll_animations: Vec::new(),
         }
     }
 }
 
+impl<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>>> AccessibilityProvider
+    for TreeDrawerImpl<C>
+{
+    fn accessibility_tree(&self) -> AccessibilityTree {
+        self.tree.accessibility_tree(self.focused)
+    }
+}
+
 impl<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>> + HasHtmlParser + HasDocument>
     TreeDrawer<C> for TreeDrawerImpl<C>
 {
     type ImgCache = ImageCache<C::RenderBackend>;
 
     fn draw(&mut self, size: SizeU32, el: &impl EventLoopHandle<C>) -> <C::RenderBackend as RenderBackend>::Scene {
-        if self.tree_scene.is_none() || self.size != Some(size) || !self.dirty {
-            self.size = Some(size);
-
-            let mut scene = <C::RenderBackend as RenderBackend>::Scene::new();
-
-            // Apply new maximums to the scene transform
-            if let Some(scene_transform) = self.scene_transform.as_mut() {
-                let root_size = self.tree.get_root().layout.content();
-                // Calculate max_x and max_y, ensuring they are not negative cause if the root size is smaller than the size, max_x/max_y should be 0
-                let max_x = (root_size.width - size.width as f32).max(0.0);
-                let max_y = (root_size.height - size.height as f32).max(0.0);
+        if self.web_fonts_requested.is_none() {
+            self.web_fonts_requested = Some(Instant::now());
 
-                let x = scene_transform.tx().min(0.0).max(-max_x);
-                let y = scene_transform.ty().min(0.0).max(-max_y);
-
-                scene_transform.set_xy(x, y);
+            if let Some(document) = &self.tree.document_url {
+                request_web_fonts::<C>(self.tree.resource_loader.as_ref(), document, &self.tree.font_faces, el);
             }
+        }
 
-            let mut drawer = Drawer {
-                scene: &mut scene,
-                drawer: self,
-                svg: Arc::new(Mutex::new(<C::RenderBackend as RenderBackend>::SVGRenderer::new())),
-                el,
-            };
+        if self.tree_scene.is_none() || self.size != Some(size) || !self.dirty {
+            self.render_scene(size, el);
+        }
 
-            drawer.render(size);
+        // The page is laid out now, so the element the fragment of its url points to can be scrolled to
+        if size.width > 0 && size.height > 0 {
+            match self.pending_scroll.take() {
+                Some(PendingScroll::Fragment(fragment)) => {
+                    self.scroll_to_fragment(&fragment, false);
+                }
+                Some(PendingScroll::Position(position)) => self.set_page_offset(position),
+                None => {}
+            }
+        }
 
-            self.tree_scene = Some(scene);
+        if !self.scroll_animations.is_empty() {
+            self.step_scroll_animations(Instant::now());
+            if !self.scroll_animations.is_empty() {
+                el.redraw();
+            }
+        }
 
-            self.size = Some(size);
+        if self.tree_scene.is_none() {
+            self.render_scene(size, el);
         }
 
         let bg = Rect::new(0.0, 0.0, size.width as FP, size.height as FP);
@@ -169,6 +296,35 @@ impl<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>
         let x = x - self.scene_transform.clone().unwrap_or(Transform::IDENTITY).tx();
         let y = y - self.scene_transform.clone().unwrap_or(Transform::IDENTITY).ty();
 
+        self.mouse = Point::new(x, y);
+
+        if let Some((id, axis, grab)) = self.scroll_drag {
+            return self.drag_scrollbar(id, axis, grab);
+        }
+
+        if self.selecting {
+            // Dragging in a text control selects its value instead of the text of the page
+            if let Some(id) = self.focused.filter(|id| self.is_text_control(*id)) {
+                let offset = self.control_offset_at(id);
+                return self.update_control(id, |_, state| state.move_to(offset, true));
+            }
+
+            let Some(caret) = caret_at::<C>(&self.tree, &self.position, self.mouse) else {
+                return false;
+            };
+            let Some(selection) = &mut self.text_selection else {
+                return false;
+            };
+
+            if !selection.extend::<C>(&self.tree, caret) {
+                return false;
+            }
+
+            self.tree_scene = None;
+            self.dirty = true;
+            return true;
+        }
+
         if let Some(e) = self.position.find(x, y) {
             if self.last_hover != Some(e) {
                 self.last_hover = Some(e);
@@ -181,39 +337,365 @@ impl<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>
         false
     }
 
+    fn mouse_down(&mut self, button: MouseButton) -> bool {
+        if button != MouseButton::Left {
+            return false;
+        }
+
+        let now = Instant::now();
+        let clicks = match self.last_click {
+            Some((time, point, clicks))
+                if now.duration_since(time) <= MULTI_CLICK_TIME
+                    && (point.x - self.mouse.x).abs() <= MULTI_CLICK_DISTANCE
+                    && (point.y - self.mouse.y).abs() <= MULTI_CLICK_DISTANCE =>
+            {
+                clicks + 1
+            }
+            _ => 1,
+        };
+        self.last_click = Some((now, self.mouse, clicks));
+
+        // An open dropdown takes the click when it is on one of its options, any other click closes it
+        let mut closed = None;
+        if let Some(select) = self.open_select() {
+            let option = self.dropdown_option_at(select);
+            self.update_control(select, |control, state| {
+                state.open = false;
+                if let Some(idx) = option {
+                    state.select_option(control, idx, true);
+                }
+            });
+            if option.is_some() {
+                return true;
+            }
+            closed = Some(select);
+        }
+
+        // A press on a scrollbar grabs its thumb
+        if let Some((id, scrollbar, mouse)) = self.scrollbar_at() {
+            let grab = scrollbar.grab(mouse);
+            self.scroll_drag = Some((id, scrollbar.axis, grab));
+            self.scroll_animations.retain(|(container, _)| *container != Some(id));
+            self.drag_scrollbar(id, scrollbar.axis, grab);
+            return true;
+        }
+
+        let hit = self.position.find(self.mouse.x, self.mouse.y);
+        let target = hit
+            .and_then(|id| self.tree.activation_target(id))
+            .filter(|id| !self.tree.is_disabled(*id));
+
+        // A click focuses the control it activates, or else the closest focusable element. Only text controls show a
+        // focus ring when they are clicked.
+        let focus = target.or_else(|| hit.and_then(|id| self.tree.focus_target(id)));
+        let focus_changed = self.focused != focus;
+        self.focused = focus;
+        self.focus_visible = focus.is_some_and(|id| self.is_text_control(id));
+
+        if let Some(id) = target {
+            self.text_selection = None;
+            self.activate(id, hit == Some(id), clicks, closed == Some(id));
+            self.tree_scene = None;
+            self.dirty = true;
+            return true;
+        }
+
+        // A link is followed when the mouse is released over it
+        self.pressed = hit.and_then(|id| self.tree.link_target(id));
+        self.selecting = true;
+
+        let had_selection = self.text_selection.take().is_some();
+
+        self.text_selection = caret_at::<C>(&self.tree, &self.position, self.mouse)
+            .map(|caret| Selection::start::<C>(&self.tree, caret, Granularity::from_clicks(clicks)));
+
+        if !had_selection && self.text_selection.is_none() && !focus_changed && closed.is_none() {
+            return false;
+        }
+
+        self.tree_scene = None;
+        self.dirty = true;
+        true
+    }
+
+    fn mouse_up(&mut self, button: MouseButton) -> bool {
+        if button != MouseButton::Left {
+            return false;
+        }
+
+        if self.scroll_drag.take().is_some() {
+            return false;
+        }
+
+        self.selecting = false;
+
+        let Some(pressed) = self.pressed.take() else {
+            return false;
+        };
+
+        let hit = self.position.find(self.mouse.x, self.mouse.y);
+
+        // A link is followed when the mouse is released over it, unless text was selected by dragging over it
+        if self.tree.link_target(pressed) == Some(pressed) && !self.tree.is_button(pressed) {
+            let nothing_selected = self
+                .text_selection
+                .as_ref()
+                .is_none_or(|selection| selection.text::<C>(&self.tree).is_empty());
+
+            if nothing_selected && hit.and_then(|id| self.tree.link_target(id)) == Some(pressed) {
+                self.follow_link(pressed);
+            }
+            return false;
+        }
+
+        // A button is only activated when the mouse is released over it
+        let target = hit.and_then(|id| self.tree.activation_target(id));
+        if target == Some(pressed) {
+            self.submit(pressed);
+        }
+
+        self.tree_scene = None;
+        self.dirty = true;
+        true
+    }
+
+    fn key_down(&mut self, key: Key) -> bool {
+        match key {
+            Key::Shift => self.shift = true,
+            Key::Control => self.control = true,
+            _ => {}
+        }
+
+        if key == Key::Tab {
+            return self.move_focus(self.shift);
+        }
+
+        let Some(id) = self.focused else {
+            return false;
+        };
+        let Some(kind) = self.tree.control(id).map(|control| control.kind) else {
+            // A focused `<button>` element is pressed with the keyboard, and a focused link is followed with enter
+            if self.tree.is_button(id) && matches!(key, Key::Character(' ') | Key::Enter) {
+                self.pressed = Some(id);
+                self.tree_scene = None;
+                self.dirty = true;
+                return true;
+            }
+            if key == Key::Enter && self.tree.link_target(id) == Some(id) {
+                self.follow_link(id);
+            }
+            return false;
+        };
+
+        let shift = self.shift;
+        let control = self.control;
+
+        match kind {
+            ControlKind::Text { .. } | ControlKind::TextArea => {
+                let multiline = kind == ControlKind::TextArea;
+
+                // Pressing enter in a text field submits its form
+                if key == Key::Enter && !multiline {
+                    if let Some((form, submitter)) = self.tree.implicit_submission(id) {
+                        self.queue_submission(form, submitter);
+                    }
+                    return false;
+                }
+
+                self.update_control(id, |data, state| match key {
+                    Key::Character('a' | 'A') if control => state.select_all(),
+                    Key::Character(_) | Key::Enter | Key::Backspace | Key::Delete if data.is_readonly() => {}
+                    Key::Character(c) if !control => state.insert_within(c.encode_utf8(&mut [0; 4]), data.max_length()),
+                    Key::Enter if multiline => state.insert_within("\n", data.max_length()),
+                    Key::Backspace => state.delete_backward(),
+                    Key::Delete => state.delete_forward(),
+                    Key::ArrowLeft => state.move_left(shift),
+                    Key::ArrowRight => state.move_right(shift),
+                    Key::ArrowUp => state.move_up(shift),
+                    Key::ArrowDown => state.move_down(shift),
+                    Key::Home => state.move_home(shift),
+                    Key::End => state.move_end(shift),
+                    _ => {}
+                })
+            }
+            ControlKind::Checkbox if key == Key::Character(' ') => {
+                self.update_control(id, |_, state| state.checked = !state.checked)
+            }
+            ControlKind::Radio if key == Key::Character(' ') => self.check_radio(id),
+            ControlKind::Button if matches!(key, Key::Character(' ') | Key::Enter) => {
+                self.pressed = Some(id);
+                self.tree_scene = None;
+                self.dirty = true;
+                true
+            }
+            ControlKind::Select => self.update_control(id, |control, state| match key {
+                Key::Enter | Key::Character(' ') => state.open = !state.open,
+                Key::Escape => state.open = false,
+                Key::ArrowUp | Key::ArrowDown => {
+                    // Step to the next option that can be chosen
+                    let enabled = |idx: &usize| control.options.get(*idx).is_some_and(|option| !option.disabled);
+                    let current = state.selected;
+                    let next = if key == Key::ArrowDown {
+                        let start = current.map_or(0, |idx| idx + 1);
+                        (start..control.options.len()).find(enabled)
+                    } else {
+                        let end = current.unwrap_or(control.options.len());
+                        (0..end).rev().find(enabled)
+                    };
+                    if let Some(idx) = next {
+                        state.select_option(control, idx, false);
+                    }
+                }
+                _ => {}
+            }),
+            _ => false,
+        }
+    }
+
+    fn key_up(&mut self, key: Key) -> bool {
+        match key {
+            Key::Shift => self.shift = false,
+            Key::Control => self.control = false,
+            Key::Character(' ') | Key::Enter if self.pressed.is_some() && !self.selecting => {
+                if let Some(pressed) = self.pressed.take() {
+                    self.submit(pressed);
+                }
+                self.tree_scene = None;
+                self.dirty = true;
+                return true;
+            }
+            _ => {}
+        }
+
+        false
+    }
+
+    fn focus(&self) -> Option<(NodeId, bool)> {
+        self.focused.map(|id| (id, self.focus_visible))
+    }
+
+    fn take_navigation(&mut self) -> Option<Request> {
+        self.navigation.take()
+    }
+
+    fn selected_text(&self) -> String {
+        self.text_selection
+            .as_ref()
+            .map(|selection| selection.text::<C>(&self.tree))
+            .unwrap_or_default()
+    }
+
+    fn find(&mut self, query: &str, case_sensitive: bool, forward: bool) -> FindResult {
+        let had_matches = self.find_state.as_ref().is_some_and(|find| !find.matches.is_empty());
+
+        if query.is_empty() {
+            self.find_state = None;
+        } else {
+            let find = FindState::search::<C>(&self.tree, query, case_sensitive, forward, self.find_state.as_ref());
+            self.find_state = Some(find);
+            self.scroll_to_active_match();
+        }
+
+        let result = self
+            .find_state
+            .as_ref()
+            .map_or_else(FindResult::default, |find| FindResult {
+                matches: find.matches.len(),
+                active: find.active,
+            });
+
+        if had_matches || result.matches > 0 {
+            self.tree_scene = None;
+            self.dirty = true;
+        }
+
+        result
+    }
+
     fn scroll(&mut self, point: Point) {
-        let mut transform = self.scene_transform.take().unwrap_or(Transform::IDENTITY);
+        // The delta moves the content, so it scrolls the other way. The innermost scroll container under the mouse
+        // scrolls first, and what it can not scroll goes to the containers around it and finally to the page.
+        let mut delta = Point::new(-point.x, -point.y);
+        let mut scrolled = false;
+
+        // Scrolling by the user stops the smooth scrolls that are running
+        self.scroll_animations.clear();
+
+        let mut current = self.position.find(self.mouse.x, self.mouse.y);
+        while let Some(id) = current {
+            if delta.x == 0.0 && delta.y == 0.0 {
+                break;
+            }
 
-        let x = transform.tx() + point.x;
-        let y = transform.ty() + point.y;
+            if let Some(scroll) = scroll_box::<C>(&self.tree, id) {
+                let offset = self.scroll_offset(id, &scroll);
+                let (new, rest) = scroll.scroll_by(offset, delta);
+                if new != offset {
+                    self.scroll_offsets.insert(id, new);
+                    scrolled = true;
+                }
+                delta = rest;
+            }
 
-        let root_size = self.tree.get_root().layout.content();
-        let size = self.size.unwrap_or(SizeU32::ZERO);
+            current = self.tree.parent_id(id);
+        }
+
+        if scrolled {
+            self.repaint_scrolled();
+        }
 
-        let max_x = root_size.width - size.width as f32;
-        let max_y = root_size.height - size.height as f32;
+        if delta.x != 0.0 || delta.y != 0.0 {
+            self.scroll_page(Point::new(-delta.x, -delta.y));
+        }
+    }
 
-        let x = x.min(0.0).max(-max_x);
-        let y = y.min(0.0).max(-max_y);
+    fn scroll_position(&self) -> Point {
+        self.page_offset()
+    }
 
-        transform.set_xy(x, y);
+    fn restore_scroll_position(&mut self, position: Point) {
+        self.pending_scroll = Some(PendingScroll::Position(position));
+        self.scroll_animations.clear();
+        self.dirty = true;
+    }
 
-        self.scene_transform = Some(transform);
+    fn navigate_to_fragment(&mut self, url: &Url) -> bool {
+        let Some(fragment) = url.fragment() else {
+            return false;
+        };
+        let document = self.tree.document_url.as_deref().and_then(|url| Url::parse(url).ok());
+        if !document.is_some_and(|document| document[..url::Position::AfterQuery] == url[..url::Position::AfterQuery]) {
+            return false;
+        }
 
+        if self.size.is_some() {
+            self.scroll_to_fragment(fragment, true);
+        } else {
+            // Not laid out yet
+            self.pending_scroll = Some(PendingScroll::Fragment(fragment.to_string()));
+        }
         self.dirty = true;
+        true
     }
 
     async fn from_url(url: Url, layouter: C::Layouter, debug: bool) -> Result<(Self, C::Document)> {
         let (rt, handle, fetcher) = load_html_rendertree::<C>(url.clone(), None).await?;
 
-        Ok((Self::new(rt, layouter, Arc::new(fetcher), debug), handle))
+        let mut drawer = Self::new(rt, layouter, Arc::new(fetcher), debug);
+        drawer.pending_scroll = PendingScroll::from_url(&url);
+
+        Ok((drawer, handle))
     }
 
     fn from_source(url: Url, source_html: &str, layouter: C::Layouter, debug: bool) -> Result<(Self, C::Document)> {
         let fetcher = Fetcher::new(url.clone());
-        let (rt, handle) = load_html_rendertree_source::<C>(url, source_html)?;
+        let pending_scroll = PendingScroll::from_url(&url);
+        let (rt, handle) = load_html_rendertree_source_sync::<C>(url, source_html)?;
 
-        Ok((Self::new(rt, layouter, Arc::new(fetcher), debug), handle))
+        let mut drawer = Self::new(rt, layouter, Arc::new(fetcher), debug);
+        drawer.pending_scroll = pending_scroll;
+
+        Ok((drawer, handle))
     }
 
     async fn with_fetcher(
@@ -224,7 +706,10 @@ impl<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>
     ) -> Result<(Self, C::Document)> {
         let (rt, handle) = load_html_rendertree_fetcher::<C>(url.clone(), &fetcher).await?;
 
-        Ok((Self::new(rt, layouter, fetcher, debug), handle))
+        let mut drawer = Self::new(rt, layouter, fetcher, debug);
+        drawer.pending_scroll = PendingScroll::from_url(&url);
+
+        Ok((drawer, handle))
     }
 
     fn clear_buffers(&mut self) {
@@ -289,11 +774,18 @@ impl<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>
 
     fn reload(&mut self, el: impl EventLoopHandle<C>) -> impl Future<Output = Result<C::Document>> + 'static {
         let fetcher = self.fetcher.clone();
+        // The page may have been navigated away from the url the fetcher started with
+        let url = self
+            .tree
+            .document_url
+            .as_deref()
+            .and_then(|url| Url::parse(url).ok())
+            .unwrap_or_else(|| fetcher.base().clone());
 
         async move {
             info!("Reloading tab");
 
-            let (rt, handle) = match load_html_rendertree_fetcher::<C>(fetcher.base().clone(), &fetcher).await {
+            let (rt, handle) = match load_html_rendertree_fetcher::<C>(url, &fetcher).await {
                 Ok(rt) => rt,
                 Err(e) => {
                     error!("Failed to reload tab: {e}");
@@ -331,6 +823,30 @@ impl<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>
         }
     }
 
+    fn load_request(
+        &mut self,
+        request: Request,
+        el: impl EventLoopHandle<C>,
+    ) -> impl Future<Output = Result<C::Document>> + 'static {
+        let fetcher = self.fetcher.clone();
+
+        async move {
+            info!("Loading {} {}", request.method, request.uri);
+
+            let (rt, handle) = match load_html_rendertree_request::<C>(&request, &fetcher).await {
+                Ok(rt) => rt,
+                Err(e) => {
+                    error!("Failed to load {}: {e}", request.uri);
+                    return Err(e);
+                }
+            };
+
+            el.reload_from(rt);
+
+            Ok(handle)
+        }
+    }
+
     fn reload_from(&mut self, tree: C::RenderTree) {
         self.tree = tree;
         self.size = None;
@@ -341,69 +857,494 @@ impl<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>
         self.tree_scene = None;
         self.selected_element = None;
         self.scene_transform = None;
+        self.web_fonts_requested = None;
+        self.has_pinned_elements = false;
+        self.layout_dirty.clear();
+        self.last_click = None;
+        self.text_selection = None;
+        self.find_state = None;
+        self.selecting = false;
+        self.focused = None;
+        self.focus_visible = false;
+        self.pressed = None;
+        self.navigation = None;
+        self.scroll_offsets.clear();
+        self.scroll_drag = None;
+        self.pending_scroll = self
+            .tree
+            .document_url
+            .as_deref()
+            .and_then(|url| Url::parse(url).ok())
+            .and_then(|url| PendingScroll::from_url(&url));
+        self.scroll_animations.clear();
+    }
+
+    fn apply_mutations(&mut self, document: &C::Document, mutations: &[MutationRecord]) {
+        if mutations.is_empty() {
+            return;
+        }
+
+        let dirty = self.tree.apply_mutations(document, mutations);
+        self.layout_dirty.extend(dirty);
+
+        if self
+            .selected_element
+            .is_some_and(|id| !self.tree.nodes.contains_key(&id))
+        {
+            self.selected_element = None;
+        }
+        self.last_hover = None;
+        self.tree_scene = None;
+        self.debugger_scene = None;
+        self.dirty = true;
+    }
+
+    fn add_web_font(&mut self, document: &str, descriptor: &FontFaceDescriptor, data: &[u8]) {
+        if self.tree.document_url.as_deref() != Some(document) {
+            // We navigated away from the document that requested the font
+            return;
+        }
+
+        if let (Some(period), Some(requested)) = (swap_period(descriptor.display), self.web_fonts_requested) {
+            if requested.elapsed() > period {
+                info!("Font {} arrived too late to be swapped in", descriptor.family);
+                return;
+            }
+        }
+
+        let manager = C::FontManager::instance();
+        let Ok(mut manager) = manager.write() else {
+            return;
+        };
+
+        if let Err(e) = manager.add_web_font(document, descriptor, data) {
+            warn!("Could not load font {}: {e:?}", descriptor.family);
+            return;
+        }
+        drop(manager);
+
+        // Text must be laid out again with the new font
+        self.tree.invalidate_layout();
+        self.layout_dirty.clear();
+        self.tree_scene = None;
+        self.debugger_scene = None;
+    }
+}
+
+impl<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>> + HasHtmlParser> TreeDrawerImpl<C> {
+    /// Lays out the page for the size of the viewport and paints it into the scene of the tree
+    fn render_scene(&mut self, size: SizeU32, el: &impl EventLoopHandle<C>) {
+        if self.size != Some(size) {
+            // Everything is laid out again
+            self.layout_dirty.clear();
+        }
+        self.size = Some(size);
+
+        let mut scene = <C::RenderBackend as RenderBackend>::Scene::new();
+
+        // Apply new maximums to the scene transform
+        if let Some(scene_transform) = self.scene_transform.as_mut() {
+            let root_size = self.tree.get_root().layout.content();
+            // Calculate max_x and max_y, ensuring they are not negative cause if the root size is smaller than the size, max_x/max_y should be 0
+            let max_x = (root_size.width - size.width as f32).max(0.0);
+            let max_y = (root_size.height - size.height as f32).max(0.0);
+
+            let x = scene_transform.tx().min(0.0).max(-max_x);
+            let y = scene_transform.ty().min(0.0).max(-max_y);
+
+            scene_transform.set_xy(x, y);
+        }
+
+        let mut drawer = Drawer {
+            scene: &mut scene,
+            drawer: self,
+            text_brush: None,
+            offsets: HashMap::new(),
+            scrollport: None,
+            found: HashMap::new(),
+            selected: HashMap::new(),
+            dropdown: None,
+            layered: Vec::new(),
+            clips: Vec::new(),
+            svg: Arc::new(Mutex::new(<C::RenderBackend as RenderBackend>::SVGRenderer::new())),
+            el,
+        };
+
+        drawer.render(size);
+
+        self.tree_scene = Some(scene);
+
+        self.size = Some(size);
     }
 }
 
 struct Drawer<'s, 't, C: HasDrawComponents, EL: EventLoopHandle<C>> {
     scene: &'s mut <C::RenderBackend as RenderBackend>::Scene,
     drawer: &'t mut TreeDrawerImpl<C>,
+    /// Brush of the nearest ancestor with `background-clip: text`, which its text is painted with
+    text_brush: Option<TextBrush<C::RenderBackend>>,
+    /// Offsets of the fixed and sticky elements that were painted, which hit testing needs as well
+    offsets: HashMap<NodeId, Point>,
+    /// Scrollport of the closest scroll container around the element that is painted, which sticky elements stick
+    /// in. None when it is the viewport.
+    scrollport: Option<Area>,
+    /// Matches of the search in the page in the text nodes, and whether they are the active match
+    found: HashMap<NodeId, Vec<(Range<usize>, bool)>>,
+    /// Selected parts of the text nodes, as byte ranges into their text
+    selected: HashMap<NodeId, Range<usize>>,
+    /// Open `<select>` and where it is painted. Its dropdown is painted after the page, so it goes over it.
+    dropdown: Option<(NodeId, Point)>,
+    /// Scenes of the positioned descendants of the stacking context that is painted, in tree order. They are added
+    /// to the scene of the stacking context in the order of their layer once its other descendants are painted.
+    layered: Vec<(PaintLayer, <C::RenderBackend as RenderBackend>::Scene)>,
+    /// Overflow clips of the ancestors between the stacking context that is painted and the element that is painted,
+    /// which the scenes of the positioned elements are clipped to as well
+    clips: Vec<ClipShape<C::RenderBackend>>,
     svg: Arc<Mutex<<C::RenderBackend as RenderBackend>::SVGRenderer>>,
     el: &'t EL,
 }
 
+/// Brush with the transform that is applied to it
+type TextBrush<B> = (<B as RenderBackend>::Brush, Option<<B as RenderBackend>::Transform>);
+
+/// Border radius of the element, the size of its background image when the layout has no size yet, and the brush of
+/// `background-clip: text`
+type PaintedBackground<B> = (BorderRadii, Option<SizeU32>, Option<TextBrush<B>>);
+
 impl<
         C: HasDrawComponents<LayoutTree = RenderTree<C>, RenderTree = RenderTree<C>> + HasHtmlParser,
         EL: EventLoopHandle<C>,
     > Drawer<'_, '_, C, EL>
 {
     pub(crate) fn render(&mut self, size: SizeU32) {
+        let frame_id = timing_start!("render.frame", format!("{}x{}", size.width, size.height));
+
         let root = self.drawer.tree.root();
         if let Err(e) = self.drawer.layouter.layout(&mut self.drawer.tree, root, size) {
             eprintln!("Failed to compute layout: {e:?}");
+            timing_stop!(frame_id);
             return;
         }
 
-        self.drawer.position = PositionTree::<C>::from_tree(&self.drawer.tree);
-
-        self.render_node_with_children(self.drawer.tree.root(), Point::ZERO);
-    }
-
-    fn render_node_with_children(&mut self, id: NodeId, mut pos: Point) {
-        let err = self.render_node(id, &mut pos);
-        if let Err(e) = err {
-            eprintln!("Error rendering node: {e}");
+        let paint_id = timing_start!("render.paint");
+        self.offsets.clear();
+        self.found.clear();
+        if let Some(find) = &self.drawer.find_state {
+            for (idx, found) in find.matches.iter().enumerate() {
+                self.found
+                    .entry(found.node)
+                    .or_default()
+                    .push((found.range.clone(), find.active == Some(idx)));
+            }
         }
-
-        let Some(children) = self.drawer.tree.children(id) else {
-            eprintln!("Error rendering node children");
-            return;
+        self.selected = self
+            .drawer
+            .text_selection
+            .as_ref()
+            .map(|selection| selection.ranges::<C>(&self.drawer.tree))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|selected| (selected.node, selected.range))
+            .collect();
+        self.dropdown = None;
+        self.render_node_with_children(self.drawer.tree.root(), Point::ZERO, self.viewport());
+        if let Some((id, pos)) = self.dropdown {
+            render_dropdown::<C>(&self.drawer.tree, id, &pos, self.scene);
+        }
+        self.drawer.has_pinned_elements = !self.offsets.is_empty();
+        timing_stop!(paint_id);
+
+        let tree = &self.drawer.tree;
+        let offsets = &self.offsets;
+        let scroll_offsets = &self.drawer.scroll_offsets;
+        let shape_of = |id, pos, size| {
+            let scroll = scroll_box::<C>(tree, id);
+            let offset = offsets.get(&id).copied().unwrap_or(Point::ZERO);
+            // The transform origin is in the box where it is painted, after the offset of a fixed or sticky element
+            let painted = Point::new(pos.x + offset.x, pos.y + offset.y);
+
+            tree.get_node(id).map_or_else(HitShape::default, |node| HitShape {
+                transform: transform::<C::CssSystem>(node.props(), painted, size),
+                radii: border_radii::<C::CssSystem>(node.props(), size),
+                offset,
+                scroll: scroll.map_or(Point::ZERO, |scroll| {
+                    scroll.clamp(scroll_offsets.get(&id).copied().unwrap_or(Point::ZERO))
+                }),
+                clip: scroll.map(|scroll| scroll.port),
+                layer: paint_layer::<C::CssSystem>(node.props()),
+                stacking_context: is_stacking_context::<C::CssSystem>(node.props()),
+            })
         };
 
-        for child in children {
-            self.render_node_with_children(child, pos);
+        let layout_dirty = std::mem::take(&mut self.drawer.layout_dirty);
+        if layout_dirty.is_empty() {
+            self.drawer.position = PositionTree::<C>::from_tree(tree, shape_of);
+        } else {
+            for id in layout_dirty {
+                self.drawer.position.update(tree, id, shape_of);
+            }
         }
+
+        timing_stop!(frame_id);
     }
 
-    fn render_node(&mut self, id: NodeId, pos: &mut Point) -> Result<()> {
-        let node = self.drawer.tree.get_node(id).ok_or(anyhow!("Node {id} not found"))?;
+    /// Paints an element and its descendants. `container` is the content box of the parent, which a sticky element
+    /// stays in.
+    fn render_node_with_children(&mut self, id: NodeId, mut pos: Point, container: Area) {
+        let Some(node) = self.drawer.tree.get_node(id) else {
+            eprintln!("Error rendering node: Node {id} not found");
+            return;
+        };
 
         let p = node.layout().rel_pos();
         pos.x += p.x as FP;
         pos.y += p.y as FP;
 
-        let (border_radius, new_size) = render_bg::<C>(
-            node,
-            self.scene,
-            pos,
-            self.svg.clone(),
-            self.drawer.fetcher.clone(),
-            &mut self.drawer.img_cache,
-            self.el,
-        );
+        // Fixed elements are laid out in the viewport, and sticky elements are shifted to stay in the scrollport
+        let offset = match position::<C::CssSystem>(node.props()) {
+            Position::Fixed => Some(self.viewport().pos),
+            Position::Sticky => Some(sticky_offset::<C::CssSystem>(
+                node.props(),
+                Area::new(pos, node.layout().size()),
+                container,
+                self.scrollport.unwrap_or_else(|| self.viewport()),
+            )),
+            _ => None,
+        };
+        if let Some(offset) = offset {
+            pos.x += offset.x;
+            pos.y += offset.y;
+            self.offsets.insert(id, offset);
+        }
+        let mut layers = node_layers::<C>(node, pos, border_radius::<C>(node));
+        if layers.is_invisible() {
+            return;
+        }
+        if scrolls_viewport(&self.drawer.tree, id) {
+            // The overflow is clipped by the viewport instead
+            layers.overflow = None;
+        }
 
-        let mut size_change = new_size;
+        // The content of a scroll container is moved by its scroll offset, and sticky elements in it stick to its
+        // scrollport
+        let scroll = scroll_box::<C>(&self.drawer.tree, id);
+        let scroll_offset = scroll.map_or(Point::ZERO, |scroll| self.drawer.scroll_offset(id, &scroll));
+        let child_pos = Point::new(pos.x - scroll_offset.x, pos.y - scroll_offset.y);
+        let content = content_box(child_pos, node.layout());
+
+        let scrollport = self.scrollport;
+        if let Some(scroll) = &scroll {
+            self.scrollport = Some(Area::new(
+                Point::new(pos.x + scroll.port.pos.x, pos.y + scroll.port.pos.y),
+                scroll.port.size,
+            ));
+        }
 
-        if node.name() == "img" {
+        // A transformed element and its descendants are painted into a scene of their own, which is transformed as
+        // a whole when it is added to the scene of the parent
+        let mut parent_scene = None;
+        if layers.transform.is_some() {
+            let mut scene = <C::RenderBackend as RenderBackend>::Scene::new();
+            std::mem::swap(self.scene, &mut scene);
+            parent_scene = Some(scene);
+        }
+
+        if let Some(layer) = &layers.effects {
+            self.scene.push_layer(&layer.clip, layer.opacity, layer.blend_mode);
+        }
+
+        let text_brush = self.text_brush.clone();
+
+        // The root is the root stacking context
+        let stacking_context = is_stacking_context::<C::CssSystem>(node.props()) || self.drawer.tree.root() == id;
+
+        let err = self.render_node(id, &pos);
+        if let Err(e) = err {
+            eprintln!("Error rendering node: {e}");
+        }
+
+        if let Some(clip) = &layers.overflow {
+            self.scene.push_layer(clip, 1.0, BlendMode::Normal);
+        }
+
+        // The positioned descendants of a stacking context are painted into scenes of their own, which are added
+        // below and above the other descendants in the order of their layer
+        let mut outer = None;
+        if stacking_context {
+            let mut scene = <C::RenderBackend as RenderBackend>::Scene::new();
+            std::mem::swap(self.scene, &mut scene);
+            outer = Some((
+                scene,
+                std::mem::take(&mut self.layered),
+                std::mem::take(&mut self.clips),
+            ));
+        } else if let Some(clip) = &layers.overflow {
+            self.clips.push(clip.clone());
+        }
+
+        if let Some(children) = self.drawer.tree.children(id) {
+            for child in children {
+                let layer = self
+                    .drawer
+                    .tree
+                    .get_node(child)
+                    .map(|child| paint_layer::<C::CssSystem>(child.props()))
+                    .unwrap_or_default();
+
+                if layer.is_layered() {
+                    self.render_layer(child, layer, child_pos, content);
+                } else {
+                    self.render_node_with_children(child, child_pos, content);
+                }
+            }
+        } else {
+            eprintln!("Error rendering node children");
+        }
+
+        if let Some((mut scene, layered, clips)) = outer {
+            std::mem::swap(self.scene, &mut scene);
+            let mut layered = std::mem::replace(&mut self.layered, layered);
+            self.clips = clips;
+
+            // Layers with a negative z-index go below the other descendants, the others above them
+            layered.sort_by_key(|(layer, _)| *layer);
+            let below = layered.partition_point(|(layer, _)| layer.is_below_flow());
+            for (_, layer) in &layered[..below] {
+                self.scene.apply_scene(layer, None);
+            }
+            self.scene.apply_scene(&scene, None);
+            for (_, layer) in &layered[below..] {
+                self.scene.apply_scene(layer, None);
+            }
+        } else if layers.overflow.is_some() {
+            self.clips.pop();
+        }
+        self.scrollport = scrollport;
+
+        // The scrollbars go over the content that is scrolled
+        if let Some(scroll) = &scroll {
+            render_scrollbars::<C::RenderBackend>(&scroll.scrollbars(scroll_offset), &pos, self.scene);
+        }
+
+        if layers.overflow.is_some() {
+            self.scene.pop_layer();
+        }
+
+        // The outline is not clipped by the overflow of the element, and goes over its descendants
+        self.render_outline(id, pos);
+
+        if layers.effects.is_some() {
+            self.scene.pop_layer();
+        }
+
+        if let Some(mut scene) = parent_scene {
+            std::mem::swap(self.scene, &mut scene);
+            self.scene.apply_scene(&scene, layers.transform);
+        }
+
+        self.text_brush = text_brush;
+    }
+
+    /// Paints a positioned element and its descendants into a scene of their own, in the layer of the stacking
+    /// context it is ordered in. The scene is clipped to the overflow of the ancestors in between.
+    fn render_layer(&mut self, id: NodeId, layer: PaintLayer, pos: Point, container: Area) {
+        // The slot is taken first, so the element goes before its own positioned descendants in the same layer
+        let idx = self.layered.len();
+        self.layered
+            .push((layer, <C::RenderBackend as RenderBackend>::Scene::new()));
+
+        let mut scene = <C::RenderBackend as RenderBackend>::Scene::new();
+        std::mem::swap(self.scene, &mut scene);
+
+        for clip in &self.clips {
+            self.scene.push_layer(clip, 1.0, BlendMode::Normal);
+        }
+        self.render_node_with_children(id, pos, container);
+        for _ in &self.clips {
+            self.scene.pop_layer();
+        }
+
+        std::mem::swap(self.scene, &mut scene);
+        self.layered[idx].1 = scene;
+    }
+
+    /// Paints the outline of an element. The focus ring is the outline the user agent stylesheet gives an element
+    /// that matches `:focus-visible`.
+    fn render_outline(&mut self, id: NodeId, pos: Point) {
+        let Some(node) = self.drawer.tree.get_node(id) else {
+            return;
+        };
+        if !node.is_element() {
+            return;
+        }
+
+        let Some(outline) = outline::<C::CssSystem>(node.props()) else {
+            return;
+        };
+        let Some(border_box) = outline_box::<C>(&self.drawer.tree, id) else {
+            return;
+        };
+
+        let border_box = Area::new(
+            Point::new(pos.x + border_box.pos.x, pos.y + border_box.pos.y),
+            border_box.size,
+        );
+        let area = outline.area(border_box);
+        let radii = outline.radii(border_radius::<C>(node));
+
+        let (r, g, b, a) = outline.color;
+        let brush = Brush::color(Color::rgba(r as u8, g as u8, b as u8, a as u8));
+        let style = outline.style.parse().unwrap_or(BorderStyle::None);
+
+        let mut border = <C::RenderBackend as RenderBackend>::Border::empty();
+        border.left(BorderSide::new(outline.width, style, brush.clone()));
+        border.right(BorderSide::new(outline.width, style, brush.clone()));
+        border.top(BorderSide::new(outline.width, style, brush.clone()));
+        border.bottom(BorderSide::new(outline.width, style, brush));
+
+        let rect = RenderRect {
+            rect: Rect::new(area.pos.x, area.pos.y, area.size.width, area.size.height),
+            transform: None,
+            radius: Some(backend_radius::<C::RenderBackend>(radii)),
+            brush: Brush::color(Color::TRANSPARENT),
+            brush_transform: None,
+            border: Some(RenderBorder::new(border)),
+        };
+
+        self.scene.draw_rect(&rect);
+    }
+
+    /// Returns the visible part of the page, which fixed backgrounds are positioned in
+    fn viewport(&self) -> Area {
+        let transform = self.drawer.scene_transform.clone().unwrap_or(Transform::IDENTITY);
+        let size = self.drawer.size.unwrap_or(SizeU32::ZERO).f32();
+
+        Area::new(Point::new(-transform.tx(), -transform.ty()), size)
+    }
+
+    fn render_node(&mut self, id: NodeId, pos: &Point) -> Result<()> {
+        let viewport = self.viewport();
+        let node = self.drawer.tree.get_node(id).ok_or(anyhow!("Node {id} not found"))?;
+
+        let (border_radius, new_size, text_brush) = render_bg::<C>(
+            node,
+            self.scene,
+            pos,
+            viewport,
+            self.svg.clone(),
+            self.drawer.tree.resource_loader.as_ref(),
+            &self.drawer.fetcher,
+            &mut self.drawer.img_cache,
+            self.el,
+        );
+
+        if text_brush.is_some() {
+            self.text_brush = text_brush;
+        }
+
+        let mut size_change = new_size;
+
+        if node.name() == "img" {
             if let Some(attributes) = node.element_attributes() {
                 let url: &str = attributes
                     .get("src")
@@ -412,7 +1353,8 @@ impl<
                 let size = node.layout().size_or().map(|x| x.u32());
 
                 let img = request_img::<C>(
-                    self.drawer.fetcher.clone(),
+                    self.drawer.tree.resource_loader.as_ref(),
+                    &self.drawer.fetcher,
                     self.svg.clone(),
                     url,
                     size,
@@ -436,7 +1378,35 @@ impl<
             }
         }
 
-        render_text::<C>(node, pos, self.scene);
+        if let Some(source) = node.svg_source() {
+            let size = node.layout().size();
+
+            if size.width > 0.0 && size.height > 0.0 {
+                let img = render_inline_svg::<C>(node, source, size.u32(), &self.svg, &mut self.drawer.img_cache)?;
+
+                render_image::<C::RenderBackend>(img, *pos, size, border_radius, "fill", self.scene)?;
+            }
+        }
+
+        if self.drawer.tree.control(id).is_some() || self.drawer.tree.is_button(id) {
+            let focused = self.drawer.focused == Some(id);
+            let pressed = self.drawer.pressed == Some(id);
+            render_control::<C>(&self.drawer.tree, id, pos, focused, pressed, self.scene);
+
+            if self.drawer.tree.controls.get(&id).is_some_and(|state| state.open) {
+                self.dropdown = Some((id, *pos));
+            }
+        }
+
+        if let Some(found) = self.found.get(&id) {
+            render_find_matches::<C>(&self.drawer.tree, id, found, pos, self.scene);
+        }
+
+        render_text::<C>(node, pos, self.text_brush.clone(), self.scene);
+
+        if let Some(range) = self.selected.get(&id) {
+            render_selection::<C>(&self.drawer.tree, id, range.clone(), pos, self.scene);
+        }
 
         if let Some(new) = size_change {
             let node = self
@@ -457,6 +1427,7 @@ impl<
 fn render_text<C: HasDrawComponents>(
     node: &<C::RenderTree as render_tree::RenderTree<C>>::Node,
     pos: &Point,
+    text_brush: Option<TextBrush<C::RenderBackend>>,
     scene: &mut <C::RenderBackend as RenderBackend>::Scene,
 ) {
     let color = node
@@ -480,12 +1451,32 @@ fn render_text<C: HasDrawComponents>(
 
         let rect = Rect::new(pos.x as FP, pos.y as FP, size.width as FP, size.height as FP);
 
+        // The first shadow is painted on top, and all shadows are painted below the text
+        for shadow in text_shadows::<C::CssSystem>(node.props()).iter().rev() {
+            let shadow_text = RenderText {
+                text: text.clone(),
+                rect: Rect::new(
+                    pos.x as FP + shadow.offset_x,
+                    pos.y as FP + shadow.offset_y,
+                    size.width as FP,
+                    size.height as FP,
+                ),
+                transform: None,
+                brush: Brush::color(shadow_color::<C::RenderBackend>(shadow)),
+                brush_transform: None,
+            };
+
+            scene.draw_blurred_text(&shadow_text, shadow.std_dev());
+        }
+
+        let (brush, brush_transform) = text_brush.unwrap_or_else(|| (Brush::color(color), None));
+
         let render_text = RenderText {
             text,
             rect,
             transform: None,
-            brush: Brush::color(color),
-            brush_transform: None,
+            brush,
+            brush_transform,
         };
 
         scene.draw_text(&render_text);
@@ -496,14 +1487,14 @@ fn render_image<B: RenderBackend>(
     img: ImageBuffer<B>,
     pos: Point,
     size: Size,
-    radii: (FP, FP, FP, FP),
+    radii: BorderRadii,
     fit: &str,
     scene: &mut B::Scene,
 ) -> anyhow::Result<()> {
     let width = size.width as FP;
     let height = size.height as FP;
 
-    let rect = Rect::new(pos.x, pos.y, pos.x + width, pos.y + height);
+    let rect = Rect::new(pos.x, pos.y, width, height);
 
     let img_size = img.size_tuple();
 
@@ -549,7 +1540,7 @@ fn render_image<B: RenderBackend>(
             let rect = RenderRect {
                 rect,
                 transform: None,
-                radius: Some(B::BorderRadius::from(radii)),
+                radius: Some(backend_radius::<B>(radii)),
                 brush: Brush::image(img),
                 brush_transform: Some(transform),
                 border: None,
@@ -558,7 +1549,12 @@ fn render_image<B: RenderBackend>(
             scene.draw_rect(&rect);
         }
         ImageBuffer::Scene(s, _size) => {
-            scene.apply_scene(&s, Some(transform)); //TODO we probably want to use a clip layer here
+            // The scene is not clipped by itself, so it could draw outside the box of the element
+            let clip = ClipShape::Rect(rect, Some(backend_radius::<B>(radii)));
+
+            scene.push_layer(&clip, 1.0, BlendMode::Normal);
+            scene.apply_scene(&s, Some(transform));
+            scene.pop_layer();
         }
     }
 
@@ -637,74 +1633,179 @@ pub fn print_tree<B: RenderBackend, L: Layouter>(
 }
 */
 
+#[allow(clippy::too_many_arguments)]
 fn render_bg<C: HasDrawComponents>(
     node: &<C::RenderTree as render_tree::RenderTree<C>>::Node,
     scene: &mut <C::RenderBackend as RenderBackend>::Scene,
     pos: &Point,
+    viewport: Area,
     svg: Arc<Mutex<<C::RenderBackend as RenderBackend>::SVGRenderer>>,
-    fetcher: Arc<Fetcher>,
+    loader: Option<&Arc<dyn ResourceLoader>>,
+    fetcher: &Fetcher,
     img_cache: &mut ImageCache<C::RenderBackend>,
     el: &impl EventLoopHandle<C>,
-) -> ((FP, FP, FP, FP), Option<SizeU32>) {
+) -> PaintedBackground<C::RenderBackend> {
     let bg_color = node
         .props()
         .get("background-color")
         .and_then(gosub_interface::css3::CssProperty::parse_color)
         .map(|color| Color::rgba(color.0 as u8, color.1 as u8, color.2 as u8, color.3 as u8));
 
-    let border_radius_left = node
-        .props()
-        .get("border-radius-left")
-        .map_or(0.0, |prop| f64::from(prop.unit_to_px()));
+    let border_radius = border_radius::<C>(node);
 
-    let border_radius_right = node
-        .props()
-        .get("border-radius-right")
-        .map_or(0.0, |prop| f64::from(prop.unit_to_px()));
+    let border = get_border::<C>(node).map(RenderBorder::new);
 
-    let border_radius_top = node
-        .props()
-        .get("border-radius-top")
-        .map_or(0.0, |prop| f64::from(prop.unit_to_px()));
+    let size = node.layout().size();
+    let rect = <C::RenderBackend as RenderBackend>::Rect::new(pos.x, pos.y, size.width, size.height);
 
-    let border_radius_bottom = node
-        .props()
-        .get("border-radius-bottom")
-        .map_or(0.0, |prop| f64::from(prop.unit_to_px()));
-
-    let border_radius = (
-        border_radius_top as FP,
-        border_radius_right as FP,
-        border_radius_bottom as FP,
-        border_radius_left as FP,
+    let boxes = ElementBoxes::new(
+        Area::new(*pos, size),
+        Edges::from_layout_rect(node.layout().border()),
+        Edges::from_layout_rect(node.layout().padding()),
     );
 
-    let border = get_border::<C>(node).map(RenderBorder::new);
+    let layers = background_layers::<C::CssSystem>(node.props());
+    let shadows = box_shadows::<C::CssSystem>(node.props());
+
+    // Outer shadows are painted below the background
+    render_box_shadows::<C::RenderBackend>(&shadows, false, boxes.border, border_radius, scene);
 
+    let mut text_brush = None;
+
+    // The background color is clipped like the bottom layer
     if let Some(bg_color) = bg_color {
-        let size = node.layout().size();
+        let clip = layers.last().map_or(BackgroundBox::Border, |layer| layer.clip);
 
-        let rect = Rect::new(pos.x as FP, pos.y as FP, size.width as FP, size.height as FP);
+        if clip == BackgroundBox::Text {
+            text_brush = Some((Brush::color(bg_color), None));
+        } else {
+            let area = boxes.get(clip);
 
-        let rect = RenderRect {
-            rect,
-            transform: None,
-            radius: Some(<C::RenderBackend as RenderBackend>::BorderRadius::from(border_radius)),
-            brush: Brush::color(bg_color),
-            brush_transform: None,
-            border,
+            let rect = RenderRect {
+                rect: Rect::new(area.pos.x, area.pos.y, area.size.width, area.size.height),
+                transform: None,
+                radius: Some(backend_radius::<C::RenderBackend>(
+                    border_radius.inner(boxes.border, area),
+                )),
+                brush: Brush::color(bg_color),
+                brush_transform: None,
+                border: None,
+            };
+
+            scene.draw_rect(&rect);
+        }
+    }
+
+    let mut img_size = None;
+
+    // The first layer is the top layer, so the layers are painted from the last to the first
+    for layer in layers.iter().rev() {
+        let Some((function, args)) = layer.image else {
+            continue;
         };
 
-        scene.draw_rect(&rect);
-    } else if let Some(border) = border {
-        let size = node.layout().size();
+        if function == "url" {
+            let Some(url) = args.first().and_then(|url| url.as_string()) else {
+                continue;
+            };
+            let img_box = node.layout().size_or().map(|x| x.u32());
 
-        let rect = Rect::new(pos.x as FP, pos.y as FP, size.width as FP, size.height as FP);
+            let img = match request_img::<C>(loader, fetcher, svg.clone(), url, img_box, img_cache, el) {
+                Ok(img) => img,
+                Err(e) => {
+                    eprintln!("Error loading image: {e:?}");
+                    continue;
+                }
+            };
+
+            if img_box.is_none() {
+                img_size = Some(img.size());
+            }
+
+            let intrinsic = img.size_tuple();
+            let Some(tiles) = layer.tiles(&boxes, viewport, Some(Size::new(intrinsic.0, intrinsic.1))) else {
+                continue;
+            };
+
+            if layer.clip == BackgroundBox::Text {
+                // The text is painted with the first tile, the image brush is not repeated
+                if let (ImageBuffer::Image(img), Some(tile)) = (&img, tiles.positions.first()) {
+                    let transform = <C::RenderBackend as RenderBackend>::Transform::scale_xy(
+                        tiles.size.width / intrinsic.0,
+                        tiles.size.height / intrinsic.1,
+                    )
+                    .with_translation(*tile);
+
+                    text_brush = Some((Brush::image(img.clone()), Some(transform)));
+                }
+                continue;
+            }
+
+            scene.push_layer(
+                &tiles_clip::<C::RenderBackend>(&tiles, border_radius, boxes.border),
+                1.0,
+                BlendMode::Normal,
+            );
+            for tile in &tiles.positions {
+                let _ = render_image::<C::RenderBackend>(
+                    img.clone(),
+                    *tile,
+                    tiles.size,
+                    BorderRadii::default(),
+                    "fill",
+                    scene,
+                )
+                .map_err(|e| {
+                    eprintln!("Error rendering image: {e:?}");
+                });
+            }
+            scene.pop_layer();
+        } else {
+            let Some(tiles) = layer.tiles(&boxes, viewport, None) else {
+                continue;
+            };
+
+            // Gradients are resolved against each tile
+            if layer.clip == BackgroundBox::Text {
+                if let Some(gradient) = tiles
+                    .positions
+                    .first()
+                    .and_then(|tile| background::gradient(function, args, *tile, tiles.size))
+                {
+                    text_brush = Some(gradient_brush::<C::RenderBackend>(&gradient));
+                }
+                continue;
+            }
+
+            scene.push_layer(
+                &tiles_clip::<C::RenderBackend>(&tiles, border_radius, boxes.border),
+                1.0,
+                BlendMode::Normal,
+            );
+            for tile in &tiles.positions {
+                if let Some(gradient) = background::gradient(function, args, *tile, tiles.size) {
+                    render_gradient::<C::RenderBackend>(&gradient, *tile, tiles.size, scene);
+                }
+            }
+            scene.pop_layer();
+        }
+    }
 
+    // Inset shadows are painted on top of the background, below the border
+    render_box_shadows::<C::RenderBackend>(
+        &shadows,
+        true,
+        boxes.padding,
+        border_radius.inner(boxes.border, boxes.padding),
+        scene,
+    );
+
+    // The border is painted on top of the background
+    if let Some(border) = border {
         let rect = RenderRect {
             rect,
             transform: None,
-            radius: Some(<C::RenderBackend as RenderBackend>::BorderRadius::from(border_radius)),
+            radius: Some(backend_radius::<C::RenderBackend>(border_radius)),
             brush: Brush::color(Color::TRANSPARENT),
             brush_transform: None,
             border: Some(border),
@@ -713,43 +1814,93 @@ fn render_bg<C: HasDrawComponents>(
         scene.draw_rect(&rect);
     }
 
-    let background_image = node.props().get("background-image").and_then(|prop| prop.as_function());
+    (border_radius, img_size, text_brush)
+}
 
-    let mut img_size = None;
+/// Paints the outer or the inset box shadows of an element. `area` is the border box for outer shadows and the
+/// padding box for inset shadows. The first shadow is painted on top.
+fn render_box_shadows<B: RenderBackend>(
+    shadows: &[Shadow],
+    inset: bool,
+    area: Area,
+    radius: BorderRadii,
+    scene: &mut B::Scene,
+) {
+    let clip_rect = B::Rect::new(area.pos.x, area.pos.y, area.size.width, area.size.height);
 
-    #[allow(clippy::collapsible_match)]
-    if let Some((function, args)) = background_image {
-        #[allow(clippy::single_match)]
-        match function {
-            "url" => {
-                if let Some(url) = args.first().and_then(|url| url.as_string()) {
-                    let size = node.layout().size_or().map(|x| x.u32());
-
-                    let img = match request_img::<C>(fetcher.clone(), svg.clone(), url, size, img_cache, el) {
-                        Ok(img) => img,
-                        Err(e) => {
-                            eprintln!("Error loading image: {e:?}");
-                            return (border_radius, None);
-                        }
-                    };
+    for shadow in shadows.iter().rev().filter(|shadow| shadow.inset == inset) {
+        let (shape, radii) = shadow.shape(area, radius);
 
-                    if size.is_none() {
-                        img_size = Some(img.size());
-                    }
+        let clip_radius = Some(backend_radius::<B>(radius));
+        let clip = if inset {
+            ShadowClip::Inset(clip_rect.clone(), clip_radius)
+        } else {
+            ShadowClip::Outside(clip_rect.clone(), clip_radius)
+        };
 
-                    let _ =
-                        render_image::<C::RenderBackend>(img, *pos, node.layout().size(), border_radius, "fill", scene)
-                            .map_err(|e| {
-                                eprintln!("Error rendering image: {e:?}");
-                            });
-                }
-            }
+        let rect = RenderBlurredRect {
+            rect: B::Rect::new(shape.pos.x, shape.pos.y, shape.size.width, shape.size.height),
+            radius: Some(backend_radius::<B>(radii)),
+            color: shadow_color::<B>(shadow),
+            std_dev: shadow.std_dev(),
+            clip,
+        };
 
-            _ => {}
-        }
+        scene.draw_blurred_rect(&rect);
     }
+}
+
+fn shadow_color<B: RenderBackend>(shadow: &Shadow) -> B::Color {
+    let (r, g, b, a) = shadow.color;
+    B::Color::rgba(r as u8, g as u8, b as u8, a as u8)
+}
+
+/// Returns the clip of the tiles of a background layer
+fn tiles_clip<B: RenderBackend>(tiles: &Tiles, border_radius: BorderRadii, border_box: Area) -> ClipShape<B> {
+    let rect = B::Rect::new(
+        tiles.clip.pos.x,
+        tiles.clip.pos.y,
+        tiles.clip.size.width,
+        tiles.clip.size.height,
+    );
+
+    ClipShape::Rect(
+        rect,
+        Some(backend_radius::<B>(border_radius.inner(border_box, tiles.clip))),
+    )
+}
+
+fn border_radius<C: HasDrawComponents>(node: &<C::RenderTree as render_tree::RenderTree<C>>::Node) -> BorderRadii {
+    border_radii::<C::CssSystem>(node.props(), node.layout().size())
+}
+
+/// Converts the radii of the corners to the radius of the render backend
+fn backend_radius<B: RenderBackend>(radii: BorderRadii) -> B::BorderRadius {
+    let corner = |(x, y): (FP, FP)| Radius::Elliptical(x, y);
+
+    B::BorderRadius::all_radius(
+        corner(radii.top_left),
+        corner(radii.top_right),
+        corner(radii.bottom_left),
+        corner(radii.bottom_right),
+    )
+}
 
-    (border_radius, img_size)
+/// Returns the content box of an element with its border box at `pos`. The edges are stored as (top, right, bottom,
+/// left).
+fn content_box(pos: Point, layout: &impl Layout) -> Area {
+    let (border, padding) = (layout.border(), layout.padding());
+    let (top, right) = (border.x1 + padding.x1, border.y1 + padding.y1);
+    let (bottom, left) = (border.x2 + padding.x2, border.y2 + padding.y2);
+    let size = layout.size();
+
+    Area::new(
+        Point::new(pos.x + left, pos.y + top),
+        Size::new(
+            (size.width - left - right).max(0.0),
+            (size.height - top - bottom).max(0.0),
+        ),
+    )
 }
 
 fn get_border<C: HasDrawComponents>(
@@ -805,7 +1956,7 @@ fn get_border_side<C: HasDrawComponents>(
         .and_then(|prop| prop.as_string())
         .unwrap_or("none");
 
-    let style = BorderStyle::from_str(style);
+    let style = style.parse().unwrap_or(BorderStyle::None);
 
     let brush = Brush::color(Color::rgba(color.0 as u8, color.1 as u8, color.2 as u8, color.3 as u8));
 
@@ -831,6 +1982,475 @@ impl Side {
 }
 
 impl<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>>> TreeDrawerImpl<C> {
+    /// Changes the state of a form control. The control is laid out again when the text it shows changed. Returns
+    /// true when anything changed, so the page needs to be redrawn.
+    fn update_control(&mut self, id: NodeId, update: impl FnOnce(&ControlData, &mut ControlState)) -> bool {
+        let before = self.tree.controls.get(&id).cloned();
+
+        let relayout = self.tree.update_control(id, update);
+        if relayout {
+            self.tree.layout_dirty_from(id);
+            self.layout_dirty.push(id);
+        }
+
+        if !relayout && self.tree.controls.get(&id) == before.as_ref() {
+            return false;
+        }
+
+        self.tree_scene = None;
+        self.dirty = true;
+        true
+    }
+
+    /// Submits the form of a submit button that is activated
+    fn submit(&mut self, button: NodeId) {
+        if let Some(form) = self.tree.submitted_form(button) {
+            self.queue_submission(form, Some(button));
+        }
+    }
+
+    /// Prepares the request that submits the form, which the instance takes and sends
+    fn queue_submission(&mut self, form: NodeId, submitter: Option<NodeId>) {
+        self.navigation = self
+            .tree
+            .form_submission(form, submitter)
+            .and_then(|submission| submission.request());
+    }
+
+    /// Prepares the request of the page a link points to, which the instance takes and loads. A link to a fragment of
+    /// the page itself is not loaded: the instance scrolls to the fragment instead (see `navigate_to_fragment`).
+    fn follow_link(&mut self, link: NodeId) {
+        let Some(url) = self.tree.link_url(link) else {
+            return;
+        };
+        if !matches!(url.scheme(), "http" | "https" | "file") {
+            return;
+        }
+
+        self.navigation = Some(Request::new("GET", url.as_str(), "HTTP/1.1"));
+    }
+
+    /// Moves the keyboard focus to the next focusable element, or the previous one when `backward`, and scrolls it
+    /// into view. Moving past the last element takes the focus off the page.
+    fn move_focus(&mut self, backward: bool) -> bool {
+        if let Some(select) = self.open_select() {
+            self.update_control(select, |_, state| state.open = false);
+        }
+
+        self.pressed = None;
+        self.focused = self.tree.next_focus(self.focused, backward);
+        self.focus_visible = true;
+
+        if let Some(id) = self.focused {
+            // Tabbing into a text control selects its value
+            if self.is_text_control(id) {
+                self.update_control(id, |_, state| state.select_all());
+            }
+
+            let area = self.position.position(id).zip(outline_box::<C>(&self.tree, id));
+            if let Some(((x, y), area)) = area {
+                let area = Area::new(Point::new(x + area.pos.x, y + area.pos.y), area.size);
+                self.scroll_into_view(id, area, Align::Center, false);
+            }
+        }
+
+        self.tree_scene = None;
+        self.dirty = true;
+        true
+    }
+
+    /// Checks a radio button and unchecks the others in its group
+    fn check_radio(&mut self, id: NodeId) -> bool {
+        if self.tree.controls.get(&id).is_some_and(|state| state.checked) {
+            return false;
+        }
+
+        self.tree.check_radio(id);
+        self.tree_scene = None;
+        self.dirty = true;
+        true
+    }
+
+    /// Handles a click on a form control or `<button>`. `in_control` is false when the click was on its label, which
+    /// only focuses text controls. `closed` tells that the click closed the dropdown of this select.
+    fn activate(&mut self, id: NodeId, in_control: bool, clicks: u32, closed: bool) {
+        let Some(kind) = self.tree.control(id).map(|control| control.kind) else {
+            self.pressed = Some(id);
+            return;
+        };
+
+        match kind {
+            ControlKind::Text { .. } | ControlKind::TextArea => {
+                if !in_control {
+                    return;
+                }
+
+                self.selecting = true;
+                let offset = self.control_offset_at(id);
+                let shift = self.shift;
+
+                self.update_control(id, |_, state| match (clicks - 1) % 3 {
+                    0 => state.move_to(offset, shift),
+                    1 => {
+                        let word = word_at(&state.value, offset);
+                        state.move_to(word.start, false);
+                        state.move_to(word.end, true);
+                    }
+                    _ => state.select_all(),
+                });
+            }
+            ControlKind::Checkbox => {
+                self.update_control(id, |_, state| state.checked = !state.checked);
+            }
+            ControlKind::Radio => {
+                self.check_radio(id);
+            }
+            ControlKind::Button => self.pressed = Some(id),
+            ControlKind::Select => {
+                if !closed {
+                    self.update_control(id, |_, state| state.open = true);
+                }
+            }
+        }
+    }
+
+    fn is_text_control(&self, id: NodeId) -> bool {
+        self.tree.control(id).is_some_and(|control| control.kind.is_text())
+    }
+
+    /// Select whose dropdown is open
+    fn open_select(&self) -> Option<NodeId> {
+        self.tree
+            .controls
+            .iter()
+            .find(|(_, state)| state.open)
+            .map(|(id, _)| *id)
+    }
+
+    /// Offset in the value of a text control that is closest to the mouse
+    fn control_offset_at(&self, id: NodeId) -> usize {
+        let (Some(control), Some(state)) = (self.tree.control(id), self.tree.controls.get(&id)) else {
+            return 0;
+        };
+        let Some((x, y)) = self.position.position(id) else {
+            return state.caret;
+        };
+
+        node_offset_at::<C>(&self.tree, id, Point::new(x, y), self.mouse)
+            .map(|offset| control.value_offset(state, offset))
+            .unwrap_or(state.value.len())
+    }
+
+    /// Option of an open select under the mouse, unless it is disabled
+    fn dropdown_option_at(&self, id: NodeId) -> Option<usize> {
+        let (area, row_height) = dropdown_area::<C>(&self.tree, id)?;
+        let (x, y) = self.position.position(id)?;
+
+        let x = self.mouse.x - x - area.pos.x;
+        let y = self.mouse.y - y - area.pos.y;
+        if x < 0.0 || y < 0.0 || x >= area.size.width || y >= area.size.height {
+            return None;
+        }
+
+        let idx = (y / row_height) as usize;
+        self.tree
+            .control(id)?
+            .options
+            .get(idx)
+            .is_some_and(|option| !option.disabled)
+            .then_some(idx)
+    }
+
+    /// Returns the scroll offset of a scroll container, clamped to how far it can scroll now
+    fn scroll_offset(&self, id: NodeId, scroll: &ScrollBox) -> Point {
+        scroll.clamp(self.scroll_offsets.get(&id).copied().unwrap_or(Point::ZERO))
+    }
+
+    /// Paints the page again after a scroll container scrolled, which moves its content for hit testing as well
+    fn repaint_scrolled(&mut self) {
+        self.tree_scene = None;
+        self.layout_dirty.clear();
+        self.dirty = true;
+    }
+
+    /// Returns how far the page is scrolled
+    fn page_offset(&self) -> Point {
+        self.scene_transform
+            .as_ref()
+            .map_or(Point::ZERO, |transform| Point::new(-transform.tx(), -transform.ty()))
+    }
+
+    /// Scrolls the page to an offset, as far as it can scroll
+    fn set_page_offset(&mut self, offset: Point) {
+        let mut transform = self.scene_transform.take().unwrap_or(Transform::IDENTITY);
+        let (old_x, old_y) = (transform.tx(), transform.ty());
+
+        let root_size = self.tree.get_root().layout.content();
+        let size = self.size.unwrap_or(SizeU32::ZERO);
+
+        let max_x = (root_size.width - size.width as f32).max(0.0);
+        let max_y = (root_size.height - size.height as f32).max(0.0);
+
+        let x = -offset.x.min(max_x).max(0.0);
+        let y = -offset.y.min(max_y).max(0.0);
+
+        transform.set_xy(x, y);
+
+        self.scene_transform = Some(transform);
+
+        // The mouse stays where it is in the window, so it is over another part of the page now
+        self.mouse.x -= x - old_x;
+        self.mouse.y -= y - old_y;
+
+        // Fixed and sticky elements are placed against the scrolled viewport, so the page is painted again
+        if self.has_pinned_elements {
+            self.tree_scene = None;
+            self.layout_dirty.clear();
+        }
+
+        self.dirty = true;
+    }
+
+    /// Scrolls the page by `point`, the distance its content moves
+    fn scroll_page(&mut self, point: Point) {
+        let offset = self.page_offset();
+        self.set_page_offset(Point::new(offset.x - point.x, offset.y - point.y));
+    }
+
+    /// Scrolls a scroll container, or the page when `container` is None, from one offset to another. The scroll is
+    /// animated when `smooth`.
+    fn scroll_to(&mut self, container: Option<NodeId>, from: Point, to: Point, smooth: bool) {
+        self.scroll_animations.retain(|(id, _)| *id != container);
+
+        if smooth {
+            self.scroll_animations
+                .push((container, ScrollAnimation::new(from, to, Instant::now())));
+            self.dirty = true;
+            return;
+        }
+
+        match container {
+            Some(id) => {
+                self.scroll_offsets.insert(id, to);
+                self.repaint_scrolled();
+            }
+            None => self.set_page_offset(to),
+        }
+    }
+
+    /// Moves the smooth scrolls that are running to where they are at `now`, and drops the ones that ended
+    fn step_scroll_animations(&mut self, now: Instant) {
+        for (container, animation) in std::mem::take(&mut self.scroll_animations) {
+            let (offset, done) = animation.at(now);
+
+            match container {
+                Some(id) => {
+                    self.scroll_offsets.insert(id, offset);
+                    self.repaint_scrolled();
+                }
+                None => self.set_page_offset(offset),
+            }
+
+            if !done {
+                self.scroll_animations.push((container, animation));
+            }
+        }
+    }
+
+    /// Returns the root element, whose `scroll-padding` and `scroll-behavior` apply to the viewport
+    fn root_element(&self) -> Option<NodeId> {
+        self.tree
+            .get_node(self.tree.root)?
+            .children
+            .iter()
+            .copied()
+            .find(|id| self.tree.get_node(*id).is_some_and(|node| node.name == "html"))
+    }
+
+    /// Scrolls to the element the fragment of a url points to, which ends up at the top of the viewport. Returns
+    /// false when the fragment points to nothing on the page.
+    fn scroll_to_fragment(&mut self, fragment: &str, smooth: bool) -> bool {
+        let id = match self.tree.fragment_target(fragment) {
+            Some(FragmentTarget::Element(id)) => id,
+            Some(FragmentTarget::Top) => {
+                let smooth = smooth && self.root_element().is_some_and(|id| self.scrolls_smoothly(id));
+                self.scroll_to(None, self.page_offset(), Point::ZERO, smooth);
+                return true;
+            }
+            None => return false,
+        };
+
+        // An empty anchor has no box, so it is scrolled to where it is placed, or else to the box around it
+        let mut current = Some(id);
+        while let Some(node) = current {
+            if let Some((x, y)) = self.position.position(node) {
+                let area = outline_box::<C>(&self.tree, node).unwrap_or(Area::new(Point::ZERO, Size::ZERO));
+                let area = Area::new(Point::new(x + area.pos.x, y + area.pos.y), area.size);
+                self.scroll_into_view(node, area, Align::Start, smooth);
+                return true;
+            }
+            current = self.tree.parent_id(node);
+        }
+
+        false
+    }
+
+    /// Returns true when a scrolling box scrolls smoothly
+    fn scrolls_smoothly(&self, id: NodeId) -> bool {
+        self.tree
+            .get_node(id)
+            .is_some_and(|node| is_smooth::<C::CssSystem>(node.props()))
+    }
+
+    /// Returns the scrollbar under the mouse with its scroll container, and the position of the mouse relative to
+    /// the border box of the container
+    fn scrollbar_at(&self) -> Option<(NodeId, Scrollbar, Point)> {
+        let mut current = self.position.find(self.mouse.x, self.mouse.y);
+
+        while let Some(id) = current {
+            current = self.tree.parent_id(id);
+
+            let Some(scroll) = scroll_box::<C>(&self.tree, id) else {
+                continue;
+            };
+            let Some((x, y)) = self.position.position(id) else {
+                continue;
+            };
+
+            let mouse = Point::new(self.mouse.x - x, self.mouse.y - y);
+            let scrollbar = scroll
+                .scrollbars(self.scroll_offset(id, &scroll))
+                .into_iter()
+                .find(|scrollbar| scrollbar.track.contains(mouse));
+            if let Some(scrollbar) = scrollbar {
+                return Some((id, scrollbar, mouse));
+            }
+        }
+
+        None
+    }
+
+    /// Moves the thumb of a dragged scrollbar to the mouse, where it keeps the place it was grabbed at, and scrolls
+    /// its container to match
+    fn drag_scrollbar(&mut self, id: NodeId, axis: Axis, grab: FP) -> bool {
+        let Some(scroll) = scroll_box::<C>(&self.tree, id) else {
+            return false;
+        };
+        let Some((x, y)) = self.position.position(id) else {
+            return false;
+        };
+
+        let offset = self.scroll_offset(id, &scroll);
+        let Some(scrollbar) = scroll
+            .scrollbars(offset)
+            .into_iter()
+            .find(|scrollbar| scrollbar.axis == axis)
+        else {
+            return false;
+        };
+
+        let start = scrollbar.along(Point::new(self.mouse.x - x, self.mouse.y - y)) - grab;
+        let mut new = offset;
+        match axis {
+            Axis::Horizontal => new.x = scrollbar.offset_at(start),
+            Axis::Vertical => new.y = scrollbar.offset_at(start),
+        }
+        if new == offset {
+            return false;
+        }
+
+        self.scroll_offsets.insert(id, new);
+        self.repaint_scrolled();
+        true
+    }
+
+    /// Scrolls the page so the active match of the search is in the middle of the viewport, unless it is in view
+    /// already
+    fn scroll_to_active_match(&mut self) {
+        let Some(found) = self.find_state.as_ref().and_then(FindState::active_match) else {
+            return;
+        };
+        let Some(origin) = self.position.positions().get(&found.node.into()).copied() else {
+            return;
+        };
+        let Some(rect) = text_rects::<C>(&self.tree, found.node, found.range.clone())
+            .first()
+            .copied()
+        else {
+            return;
+        };
+
+        self.scroll_into_view(
+            found.node,
+            Area::new(Point::new(origin.x + rect.pos.x, origin.y + rect.pos.y), rect.size),
+            Align::Center,
+            false,
+        );
+    }
+
+    /// Scrolls the area of a node on the page into view, unless it is in view already. The scroll containers around
+    /// the node scroll first, from the innermost one out, and the page scrolls last. The area is grown by the
+    /// `scroll-margin` of the node and kept out of the `scroll-padding` of every scrolling box. When `smooth`, the
+    /// boxes with `scroll-behavior: smooth` scroll smoothly.
+    fn scroll_into_view(&mut self, id: NodeId, mut area: Area, align: Align, smooth: bool) {
+        let Some(size) = self.size else {
+            return;
+        };
+
+        let margin = self
+            .tree
+            .get_node(id)
+            .filter(|node| node.is_element())
+            .map_or_else(Edges::default, |node| scroll_margin::<C::CssSystem>(node.props()));
+
+        let mut current = self.tree.parent_id(id);
+        while let Some(container) = current {
+            current = self.tree.parent_id(container);
+
+            let Some(scroll) = scroll_box::<C>(&self.tree, container) else {
+                continue;
+            };
+            let Some((x, y)) = self.position.position(container) else {
+                continue;
+            };
+            let Some(node) = self.tree.get_node(container) else {
+                continue;
+            };
+
+            let port = Area::new(
+                Point::new(x + scroll.port.pos.x, y + scroll.port.pos.y),
+                scroll.port.size,
+            );
+            let padding = scroll_padding::<C::CssSystem>(node.props(), port.size);
+            let offset = self.scroll_offset(container, &scroll);
+            let delta = scroll_into_view(area, margin, port, padding, align);
+            let new = scroll.clamp(Point::new(offset.x + delta.x, offset.y + delta.y));
+
+            if new != offset {
+                // The area moves with the content of the container
+                area.pos.x -= new.x - offset.x;
+                area.pos.y -= new.y - offset.y;
+                let smooth = smooth && self.scrolls_smoothly(container);
+                self.scroll_to(Some(container), offset, new, smooth);
+            }
+        }
+
+        let root = self.root_element();
+        let offset = self.page_offset();
+        let port = Area::new(offset, size.f32());
+        let padding = root
+            .and_then(|id| self.tree.get_node(id))
+            .map_or_else(Edges::default, |node| {
+                scroll_padding::<C::CssSystem>(node.props(), port.size)
+            });
+
+        let delta = scroll_into_view(area, margin, port, padding, align);
+        if delta.x != 0.0 || delta.y != 0.0 {
+            let smooth = smooth && root.is_some_and(|id| self.scrolls_smoothly(id));
+            self.scroll_to(None, offset, Point::new(offset.x + delta.x, offset.y + delta.y), smooth);
+        }
+    }
+
     fn debug_annotate(&mut self, e: NodeId) -> bool {
         let Some(node) = self.tree.get_node(e) else {
             return false;
@@ -0,0 +1,42 @@
+use gosub_interface::config::HasDrawComponents;
+use gosub_interface::render_backend::{Brush, Color, Rect, RenderBackend, RenderRect, Scene as TScene};
+use gosub_rendering::render_tree::RenderTree;
+use gosub_rendering::selection::text_rects;
+use gosub_shared::geo::Point;
+use gosub_shared::node::NodeId;
+use std::ops::Range;
+
+/// Highlight behind a match of the search in the page
+const MATCH_COLOR: (u8, u8, u8) = (255, 235, 59);
+/// Highlight behind the active match of the search in the page
+const ACTIVE_MATCH_COLOR: (u8, u8, u8) = (255, 150, 50);
+
+/// Paints the highlights of the matches of the search in a text node, which go below the text. `matches` are byte
+/// ranges into the text of all layouts of the node, and whether they are the active match.
+pub fn render_find_matches<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>>>(
+    tree: &RenderTree<C>,
+    id: NodeId,
+    matches: &[(Range<usize>, bool)],
+    pos: &Point,
+    scene: &mut <C::RenderBackend as RenderBackend>::Scene,
+) {
+    for (range, active) in matches {
+        let color = if *active { ACTIVE_MATCH_COLOR } else { MATCH_COLOR };
+
+        for rect in text_rects::<C>(tree, id, range.clone()) {
+            scene.draw_rect(&RenderRect {
+                rect: Rect::new(
+                    pos.x + rect.pos.x,
+                    pos.y + rect.pos.y,
+                    rect.size.width,
+                    rect.size.height,
+                ),
+                transform: None,
+                radius: None,
+                brush: Brush::color(Color::rgb(color.0, color.1, color.2)),
+                brush_transform: None,
+                border: None,
+            });
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use gosub_interface::config::HasDrawComponents;
+use gosub_interface::eventloop::EventLoopHandle;
+use gosub_interface::font::{FontDisplay, FontFaceDescriptor, FontFaceSource, FontManager};
+use gosub_interface::resource::{ResourceKind, ResourceLoader, ResourceRequest};
+use log::warn;
+use url::Url;
+
+/// Values of `format()` hints of font formats we are able to decode
+const SUPPORTED_FORMATS: [&str; 5] = ["woff", "woff2", "truetype", "opentype", "collection"];
+
+/// Registers the `@font-face` rules of a document with the font manager. Local fonts are registered
+/// directly. Web fonts are fetched through the resource loader of the document and are handed to the event loop
+/// once downloaded.
+pub fn request_web_fonts<C: HasDrawComponents>(
+    loader: Option<&Arc<dyn ResourceLoader>>,
+    document: &str,
+    faces: &[FontFaceDescriptor],
+    el: &impl EventLoopHandle<C>,
+) {
+    let manager = C::FontManager::instance();
+    let Ok(mut manager) = manager.write() else {
+        return;
+    };
+
+    manager.remove_web_fonts(document);
+    manager.set_active_document(Some(document));
+
+    for face in faces {
+        for (idx, source) in face.sources.iter().enumerate() {
+            match source {
+                FontFaceSource::Local(name) => {
+                    if manager.add_local_font(document, face, name).is_ok() {
+                        break;
+                    }
+                }
+                FontFaceSource::Url { .. } => {
+                    let Some(loader) = loader else {
+                        warn!(
+                            "No resource loader available, not loading the web fonts of {}",
+                            face.family
+                        );
+                        break;
+                    };
+
+                    // The remaining sources are tried in order until one of them can be fetched. They are collected
+                    // in reverse order, so the next one to try is at the end.
+                    let urls = face.sources[idx..]
+                        .iter()
+                        .rev()
+                        .filter_map(|source| match source {
+                            FontFaceSource::Url { url, format } if is_supported(format.as_deref()) => Some(url.clone()),
+                            _ => None,
+                        })
+                        .collect();
+
+                    fetch_web_font(loader.clone(), document.to_string(), face.clone(), urls, el.clone());
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Returns how long after the fonts are requested a web font may still replace the fallback font, or None
+/// when the font is always swapped in once it arrives.
+#[must_use]
+pub fn swap_period(display: FontDisplay) -> Option<Duration> {
+    match display {
+        FontDisplay::Optional => Some(Duration::from_millis(100)),
+        FontDisplay::Fallback => Some(Duration::from_secs(3)),
+        FontDisplay::Auto | FontDisplay::Block | FontDisplay::Swap => None,
+    }
+}
+
+fn is_supported(format: Option<&str>) -> bool {
+    format.is_none_or(|format| SUPPORTED_FORMATS.iter().any(|f| f.eq_ignore_ascii_case(format)))
+}
+
+/// Fetches the last of the urls, and the one before it when it can not be loaded. The urls are in reverse order of
+/// preference.
+fn fetch_web_font<C: HasDrawComponents>(
+    loader: Arc<dyn ResourceLoader>,
+    document: String,
+    face: FontFaceDescriptor,
+    mut urls: Vec<String>,
+    el: impl EventLoopHandle<C>,
+) {
+    let Some(url) = urls.pop() else {
+        return;
+    };
+
+    // The urls of font faces are absolute
+    let url = match Url::parse(&url) {
+        Ok(url) => url,
+        Err(e) => {
+            warn!("Could not load font {url} for {}: {e}", face.family);
+            fetch_web_font(loader, document, face, urls, el);
+            return;
+        }
+    };
+
+    let next = loader.clone();
+    loader.fetch(
+        ResourceRequest::new(url, ResourceKind::Font),
+        Box::new(move |response| match response.body {
+            Ok(data) => el.add_web_font(document, face, data),
+            Err(e) => {
+                warn!("Could not load font {} for {}: {e}", response.request.url, face.family);
+                fetch_web_font(next, document, face, urls, el);
+            }
+        }),
+    );
+}
@@ -0,0 +1,353 @@
+use crate::draw::content_box;
+use gosub_interface::config::HasDrawComponents;
+use gosub_interface::css3::{CssProperty, CssPropertyMap};
+use gosub_interface::layout::{Layout, LayoutTree, Layouter, TextLayout};
+use gosub_interface::render_backend::{
+    BlendMode, Border as _, BorderSide as _, BorderStyle, Brush, ClipShape, Color, Rect, RenderBackend, RenderBorder,
+    RenderRect, RenderText, Scene as TScene, Text, Transform,
+};
+use gosub_interface::render_tree::RenderTreeNode as _;
+use gosub_rendering::background::{Area, Edges};
+use gosub_rendering::render_tree::form::ControlKind;
+use gosub_rendering::render_tree::RenderTree;
+use gosub_rendering::selection::{caret_rect, text_layouts, text_rects};
+use gosub_shared::geo::{Point, Size, FP};
+use gosub_shared::node::NodeId;
+
+/// Border of fields, buttons and checkboxes
+const BORDER_COLOR: (u8, u8, u8) = (118, 118, 118);
+/// Background of text fields, checkboxes and the dropdown of a `<select>`
+const FIELD_COLOR: (u8, u8, u8) = (255, 255, 255);
+const BUTTON_COLOR: (u8, u8, u8) = (239, 239, 239);
+const BUTTON_PRESSED_COLOR: (u8, u8, u8) = (209, 209, 209);
+/// Fill of checked checkboxes and radio buttons
+const ACCENT_COLOR: (u8, u8, u8) = (0, 117, 255);
+const PLACEHOLDER_COLOR: (u8, u8, u8) = (117, 117, 117);
+/// Background of the selected text in a text field, and of the selected option in a dropdown
+const SELECTION_COLOR: (u8, u8, u8) = (179, 215, 255);
+/// Width of the caret in a text field
+const CARET_WIDTH: FP = 1.0;
+
+type Layouts<'a, C> = Vec<&'a <<C as gosub_interface::config::HasLayouter>::Layouter as Layouter<C>>::TextLayout>;
+
+/// Paints a form control or a `<button>`. Unless its `appearance` is `none`, the control gets its native look over
+/// its CSS background. The text of the control is painted on top, and a focused text control shows its selection and
+/// caret. `pressed` tells whether a button is held down.
+pub fn render_control<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>>>(
+    tree: &RenderTree<C>,
+    id: NodeId,
+    pos: &Point,
+    focused: bool,
+    pressed: bool,
+    scene: &mut <C::RenderBackend as RenderBackend>::Scene,
+) {
+    let Some(node) = tree.get_node(id) else {
+        return;
+    };
+    let border_box = Area::new(*pos, node.layout().size());
+    let content = content_box(*pos, node.layout());
+    let native = node
+        .props()
+        .get("appearance")
+        .and_then(CssProperty::as_string)
+        .is_none_or(|appearance| appearance != "none");
+
+    let Some(control) = tree.control(id) else {
+        // A `<button>` element paints its children itself
+        if native && tree.is_button(id) {
+            render_button::<C::RenderBackend>(border_box, pressed, scene);
+        }
+        return;
+    };
+    let state = tree
+        .controls
+        .get(&id)
+        .cloned()
+        .unwrap_or_else(|| control.initial_state());
+
+    if native {
+        match control.kind {
+            ControlKind::Text { .. } | ControlKind::TextArea => render_field::<C::RenderBackend>(border_box, scene),
+            ControlKind::Checkbox => render_checkbox::<C::RenderBackend>(content, state.checked, scene),
+            ControlKind::Radio => render_radio::<C::RenderBackend>(content, state.checked, scene),
+            ControlKind::Button => render_button::<C::RenderBackend>(border_box, pressed, scene),
+            ControlKind::Select => {
+                render_button::<C::RenderBackend>(border_box, false, scene);
+                render_arrow::<C::RenderBackend>(content, scene);
+            }
+        }
+    }
+
+    let (layouts, _) = split_layouts::<C>(tree, id, border_box.size.height);
+    if layouts.is_empty() && !(focused && control.kind.is_text()) {
+        return;
+    }
+
+    // Text does not leave the padding box, like the value of a field that is longer than the field
+    let padding = border_box.inset(Edges::from_layout_rect(node.layout().border()));
+    let clip = Rect::new(padding.pos.x, padding.pos.y, padding.size.width, padding.size.height);
+    scene.push_layer(&ClipShape::Rect(clip, None), 1.0, BlendMode::Normal);
+
+    let focused_text = focused && control.kind.is_text();
+    if focused_text && !control.shows_placeholder(&state) {
+        let selection = state.selection();
+        let range = control.shown_offset(&state, selection.start)..control.shown_offset(&state, selection.end);
+
+        for rect in text_rects::<C>(tree, id, range) {
+            draw_box::<C::RenderBackend>(
+                Area::new(Point::new(pos.x + rect.pos.x, pos.y + rect.pos.y), rect.size),
+                SELECTION_COLOR,
+                None,
+                scene,
+            );
+        }
+    }
+
+    let color = if control.shows_placeholder(&state) {
+        Color::rgb(PLACEHOLDER_COLOR.0, PLACEHOLDER_COLOR.1, PLACEHOLDER_COLOR.2)
+    } else {
+        node.props()
+            .get("color")
+            .and_then(CssProperty::parse_color)
+            .map_or(Color::BLACK, |color| {
+                Color::rgba(color.0 as u8, color.1 as u8, color.2 as u8, color.3 as u8)
+            })
+    };
+    draw_layouts::<C>(&layouts, border_box, color, scene);
+
+    if focused_text {
+        let offset = control.shown_offset(&state, state.caret);
+        let caret = caret_rect::<C>(tree, id, offset).unwrap_or_else(|| {
+            // An empty control has no text layouts, so the caret is at the start of the content box
+            let height = node.props().get("font-size").map_or(16.0, CssProperty::unit_to_px) * 1.2;
+            let top = if control.kind == ControlKind::TextArea {
+                content.pos.y
+            } else {
+                content.pos.y + (content.size.height - height) / 2.0
+            };
+            Area::new(Point::new(content.pos.x - pos.x, top - pos.y), Size::new(0.0, height))
+        });
+
+        draw_box::<C::RenderBackend>(
+            Area::new(
+                Point::new(pos.x + caret.pos.x, pos.y + caret.pos.y),
+                Size::new(CARET_WIDTH, caret.size.height),
+            ),
+            (0, 0, 0),
+            None,
+            scene,
+        );
+    }
+
+    scene.pop_layer();
+}
+
+/// Paints the options of an open `<select>` in a list below it, which goes over the rest of the page. The selected
+/// options are highlighted.
+pub fn render_dropdown<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>>>(
+    tree: &RenderTree<C>,
+    id: NodeId,
+    pos: &Point,
+    scene: &mut <C::RenderBackend as RenderBackend>::Scene,
+) {
+    let Some((area, row_height)) = dropdown_area::<C>(tree, id) else {
+        return;
+    };
+    let Some(node) = tree.get_node(id) else {
+        return;
+    };
+    let area = Area::new(Point::new(pos.x + area.pos.x, pos.y + area.pos.y), area.size);
+
+    draw_box::<C::RenderBackend>(area, FIELD_COLOR, Some(BORDER_COLOR), scene);
+
+    if let (Some(control), Some(state)) = (tree.control(id), tree.controls.get(&id)) {
+        for selected in (0..control.options.len()).filter(|idx| state.is_selected(control, *idx)) {
+            draw_box::<C::RenderBackend>(
+                Area::new(
+                    Point::new(area.pos.x, area.pos.y + selected as FP * row_height),
+                    Size::new(area.size.width, row_height),
+                ),
+                SELECTION_COLOR,
+                None,
+                scene,
+            );
+        }
+    }
+
+    let (_, rows) = split_layouts::<C>(tree, id, node.layout().size().height);
+    draw_layouts::<C>(&rows, Area::new(*pos, node.layout().size()), Color::BLACK, scene);
+}
+
+/// Returns the area of the list of options of an open `<select>` relative to the select, and the height of a row
+/// in the list
+pub fn dropdown_area<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>>>(
+    tree: &RenderTree<C>,
+    id: NodeId,
+) -> Option<(Area, FP)> {
+    let control = tree.control(id)?;
+    if !tree.controls.get(&id).is_some_and(|state| state.open) {
+        return None;
+    }
+
+    let size = tree.get_node(id)?.layout().size();
+    let (_, rows) = split_layouts::<C>(tree, id, size.height);
+    let row_height = rows.first()?.size().height;
+
+    // The list is as wide as the select, or wider when an option does not fit
+    let width = rows
+        .iter()
+        .map(|row| row.offset().x * 2.0 + row.size().width)
+        .fold(size.width, FP::max);
+
+    Some((
+        Area::new(
+            Point::new(0.0, size.height),
+            Size::new(width, row_height * control.options.len() as FP),
+        ),
+        row_height,
+    ))
+}
+
+/// Splits the text layouts of a control into the layouts of its text and the rows of its dropdown list, which start
+/// below its border box
+fn split_layouts<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>>>(
+    tree: &RenderTree<C>,
+    id: NodeId,
+    height: FP,
+) -> (Layouts<'_, C>, Layouts<'_, C>) {
+    text_layouts::<C>(tree, id)
+        .iter()
+        .partition(|layout| layout.offset().y - layout.size().height < height - 0.5)
+}
+
+fn draw_layouts<C: HasDrawComponents>(
+    layouts: &Layouts<'_, C>,
+    area: Area,
+    color: <C::RenderBackend as RenderBackend>::Color,
+    scene: &mut <C::RenderBackend as RenderBackend>::Scene,
+) {
+    if layouts.is_empty() {
+        return;
+    }
+
+    scene.draw_text(&RenderText {
+        text: layouts
+            .iter()
+            .map(|layout| <C::RenderBackend as RenderBackend>::Text::new(*layout))
+            .collect(),
+        rect: Rect::new(area.pos.x, area.pos.y, area.size.width, area.size.height),
+        transform: None,
+        brush: Brush::color(color),
+        brush_transform: None,
+    });
+}
+
+fn render_field<B: RenderBackend>(area: Area, scene: &mut B::Scene) {
+    draw_box::<B>(area, FIELD_COLOR, Some(BORDER_COLOR), scene);
+}
+
+fn render_button<B: RenderBackend>(area: Area, pressed: bool, scene: &mut B::Scene) {
+    let face = if pressed { BUTTON_PRESSED_COLOR } else { BUTTON_COLOR };
+
+    draw_box::<B>(area, face, Some(BORDER_COLOR), scene);
+}
+
+/// Paints the box of a checkbox in the content box, with a check mark when it is checked
+fn render_checkbox<B: RenderBackend>(area: Area, checked: bool, scene: &mut B::Scene) {
+    if !checked {
+        draw_box::<B>(area, FIELD_COLOR, Some(BORDER_COLOR), scene);
+        return;
+    }
+
+    draw_box::<B>(area, ACCENT_COLOR, None, scene);
+
+    let point = |x: FP, y: FP| Point::new(area.pos.x + area.size.width * x, area.pos.y + area.size.height * y);
+    let width = area.size.width / 7.0;
+    draw_line::<B>(point(0.22, 0.5), point(0.42, 0.7), width, FIELD_COLOR, scene);
+    draw_line::<B>(point(0.42, 0.7), point(0.78, 0.3), width, FIELD_COLOR, scene);
+}
+
+/// Paints the circle of a radio button in the content box, with a dot when it is checked
+fn render_radio<B: RenderBackend>(area: Area, checked: bool, scene: &mut B::Scene) {
+    let radius = area.size.width.min(area.size.height) / 2.0;
+    let circle = |inset: FP| {
+        Area::new(
+            Point::new(area.pos.x + inset, area.pos.y + inset),
+            Size::new(2.0 * (radius - inset), 2.0 * (radius - inset)),
+        )
+    };
+
+    if checked {
+        draw_round::<B>(circle(0.0), radius, ACCENT_COLOR, None, scene);
+        draw_round::<B>(circle(radius / 4.0), radius * 3.0 / 4.0, FIELD_COLOR, None, scene);
+        draw_round::<B>(circle(radius / 2.0), radius / 2.0, ACCENT_COLOR, None, scene);
+    } else {
+        draw_round::<B>(circle(0.0), radius, FIELD_COLOR, Some(BORDER_COLOR), scene);
+    }
+}
+
+/// Paints the arrow of a `<select>` at the end of its content box
+fn render_arrow<B: RenderBackend>(content: Area, scene: &mut B::Scene) {
+    let x = content.pos.x + content.size.width - 8.0;
+    let y = content.pos.y + content.size.height / 2.0;
+
+    draw_line::<B>(
+        Point::new(x - 4.0, y - 2.0),
+        Point::new(x, y + 2.0),
+        1.5,
+        (0, 0, 0),
+        scene,
+    );
+    draw_line::<B>(
+        Point::new(x, y + 2.0),
+        Point::new(x + 4.0, y - 2.0),
+        1.5,
+        (0, 0, 0),
+        scene,
+    );
+}
+
+fn draw_box<B: RenderBackend>(area: Area, fill: (u8, u8, u8), border: Option<(u8, u8, u8)>, scene: &mut B::Scene) {
+    draw_round::<B>(area, 2.0, fill, border, scene);
+}
+
+fn draw_round<B: RenderBackend>(
+    area: Area,
+    radius: FP,
+    fill: (u8, u8, u8),
+    border: Option<(u8, u8, u8)>,
+    scene: &mut B::Scene,
+) {
+    let border = border.map(|color| {
+        RenderBorder::new(B::Border::new(B::BorderSide::new(
+            1.0,
+            BorderStyle::Solid,
+            Brush::color(Color::rgb(color.0, color.1, color.2)),
+        )))
+    });
+
+    scene.draw_rect(&RenderRect {
+        rect: Rect::new(area.pos.x, area.pos.y, area.size.width, area.size.height),
+        transform: None,
+        radius: Some(B::BorderRadius::from(radius)),
+        brush: Brush::color(Color::rgb(fill.0, fill.1, fill.2)),
+        brush_transform: None,
+        border,
+    });
+}
+
+/// Paints a straight line as a rotated rectangle
+fn draw_line<B: RenderBackend>(from: Point, to: Point, width: FP, color: (u8, u8, u8), scene: &mut B::Scene) {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let length = dx.hypot(dy);
+    let center = Point::new((from.x + to.x) / 2.0, (from.y + to.y) / 2.0);
+
+    scene.draw_rect(&RenderRect {
+        rect: Rect::new(center.x - length / 2.0, center.y - width / 2.0, length, width),
+        transform: Some(B::Transform::rotate_around(dy.atan2(dx), center)),
+        radius: Some(B::BorderRadius::from(width / 2.0)),
+        brush: Brush::color(Color::rgb(color.0, color.1, color.2)),
+        brush_transform: None,
+        border: None,
+    });
+}
@@ -0,0 +1,71 @@
+use gosub_interface::render_backend::{
+    Brush, Color, ColorStop, ColorStops, Gradient as TGradient, Rect, RenderBackend, RenderRect, Scene as TScene,
+    Transform,
+};
+use gosub_rendering::background::{Gradient, GradientShape};
+use gosub_shared::geo::{Point, Size};
+
+/// Creates the brush of a resolved gradient, with the transform that is applied to the brush
+pub fn gradient_brush<B: RenderBackend>(gradient: &Gradient) -> (B::Brush, Option<B::Transform>) {
+    let stops = gradient
+        .stops
+        .iter()
+        .map(|stop| ColorStop {
+            offset: stop.offset,
+            color: B::Color::rgba(
+                stop.color.0 as u8,
+                stop.color.1 as u8,
+                stop.color.2 as u8,
+                stop.color.3 as u8,
+            ),
+        })
+        .collect::<ColorStops<B>>();
+
+    let mut brush_transform = None;
+
+    let brush = match gradient.shape {
+        GradientShape::Linear { start, end } => B::Gradient::new_linear(start, end, stops),
+        GradientShape::Radial {
+            center,
+            start_radius,
+            end_radius,
+            scale_y,
+        } => {
+            // Elliptical gradients are circles that are scaled vertically around the center
+            if scale_y != 1.0 {
+                brush_transform = Some(
+                    B::Transform::translate(-center.x, -center.y)
+                        .then_scale_xy(1.0, scale_y)
+                        .then_translate(center.x, center.y),
+                );
+            }
+
+            B::Gradient::new_radial_two_point(center, start_radius, center, end_radius, stops)
+        }
+        GradientShape::Conic {
+            center,
+            start_angle,
+            end_angle,
+        } => B::Gradient::new_sweep(center, start_angle, end_angle, stops),
+    };
+
+    let brush = if gradient.repeating { brush.repeat() } else { brush };
+
+    (B::Brush::gradient(brush), brush_transform)
+}
+
+/// Paints a resolved gradient into the tile at `pos`. The tile is clipped by the caller.
+pub fn render_gradient<B: RenderBackend>(gradient: &Gradient, pos: Point, size: Size, scene: &mut B::Scene) {
+    let (brush, brush_transform) = gradient_brush::<B>(gradient);
+
+    let rect = RenderRect {
+        rect: B::Rect::new(pos.x, pos.y, size.width, size.height),
+        transform: None,
+        radius: None,
+        brush,
+        brush_transform,
+        border: None,
+    };
+
+    scene.draw_rect(&rect);
+}
@@ -1,19 +1,28 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io::Cursor;
 use std::sync::{Arc, LazyLock, Mutex};
 
 use crate::draw::img_cache::ImageCache;
 use anyhow::anyhow;
 use gosub_interface::config::HasDrawComponents;
+use gosub_interface::css3::{CssProperty, CssPropertyMap};
 use gosub_interface::eventloop::EventLoopHandle;
 use gosub_interface::render_backend::{Image as _, ImageBuffer, ImageCacheEntry, ImgCache, RenderBackend, SizeU32};
+use gosub_interface::render_tree;
+use gosub_interface::render_tree::RenderTreeNode as _;
+use gosub_interface::resource::{ResourceKind, ResourceLoader, ResourceRequest};
 use gosub_interface::svg::SvgRenderer;
 use gosub_net::http::fetcher::Fetcher;
+use gosub_rendering::render_tree::svg::{css_color, resolve_paint, style_svg};
 use gosub_shared::types::Result;
 use image::DynamicImage;
-use url::Url;
+use log::warn;
 
+/// Returns the image at the given url from the cache. Images that are not cached yet are fetched through the
+/// resource loader of the document and handed to the event loop once decoded, an empty image is returned meanwhile.
 pub fn request_img<C: HasDrawComponents>(
-    fetcher: Arc<Fetcher>,
+    loader: Option<&Arc<dyn ResourceLoader>>,
+    fetcher: &Fetcher,
     svg_renderer: Arc<Mutex<<C::RenderBackend as RenderBackend>::SVGRenderer>>,
     url: &str,
     size: Option<SizeU32>,
@@ -30,29 +39,34 @@ pub fn request_img<C: HasDrawComponents>(
         ImageCacheEntry::None => {
             img_cache.add_pending(url.to_string());
 
-            let url = url.to_string();
-
-            let el = el.clone();
-
-            let Ok(url) = fetcher.parse_url(&url) else {
+            let (Some(loader), Ok(url)) = (loader, fetcher.parse_url(url)) else {
                 return Ok(ImageBuffer::Image(
                     <C::RenderBackend as RenderBackend>::Image::from_img(INVALID_IMG.clone()),
                 ));
             };
 
-            gosub_shared::async_executor::spawn(async move {
-                if let Ok(img) = load_img::<C::RenderBackend>(&url, fetcher, svg_renderer, size).await {
-                    el.add_img_cache(url, img, size);
-                } else {
-                    el.add_img_cache(
-                        url,
-                        ImageBuffer::Image(<C::RenderBackend as RenderBackend>::Image::from_img(
-                            INVALID_IMG.clone(),
-                        )),
-                        size,
-                    );
-                }
-            });
+            let el = el.clone();
+
+            loader.fetch(
+                ResourceRequest::new(url, ResourceKind::Image),
+                Box::new(move |response| {
+                    let url = response.request.url.clone();
+
+                    match decode_img::<C::RenderBackend>(response.body, svg_renderer, size) {
+                        Ok(img) => el.add_img_cache(url, img, size),
+                        Err(e) => {
+                            warn!("Could not load image {url}: {e}");
+                            el.add_img_cache(
+                                url,
+                                ImageBuffer::Image(<C::RenderBackend as RenderBackend>::Image::from_img(
+                                    INVALID_IMG.clone(),
+                                )),
+                                size,
+                            );
+                        }
+                    }
+                }),
+            );
 
             ImageBuffer::Image(<C::RenderBackend as RenderBackend>::Image::from_img(
                 DynamicImage::new_rgba8(0, 0),
@@ -61,18 +75,12 @@ pub fn request_img<C: HasDrawComponents>(
     })
 }
 
-async fn load_img<B: RenderBackend>(
-    url: &Url,
-    fetcher: Arc<Fetcher>,
+fn decode_img<B: RenderBackend>(
+    body: std::result::Result<Vec<u8>, String>,
     svg_renderer: Arc<Mutex<B::SVGRenderer>>,
     size: Option<SizeU32>,
 ) -> Result<ImageBuffer<B>> {
-    let res = fetcher.get_url(url).await?;
-    if !res.is_ok() {
-        return Err(anyhow!("Could not get url. Status code {}", res.status));
-    }
-
-    let img = res.body;
+    let img = body.map_err(|e| anyhow!(e))?;
 
     let is_svg = img.starts_with(b"<?xml") || img.starts_with(b"<svg");
 
@@ -100,6 +108,50 @@ async fn load_img<B: RenderBackend>(
     })
 }
 
+/// Renders an inline `<svg>` element at the given size. `currentColor` and the `fill` and `stroke` of the svg
+/// element resolve from its computed style. Rendered images are cached by their markup and size.
+pub fn render_inline_svg<C: HasDrawComponents>(
+    node: &<C::RenderTree as render_tree::RenderTree<C>>::Node,
+    source: &str,
+    size: SizeU32,
+    svg_renderer: &Mutex<<C::RenderBackend as RenderBackend>::SVGRenderer>,
+    img_cache: &mut ImageCache<C::RenderBackend>,
+) -> Result<ImageBuffer<C::RenderBackend>> {
+    let props = node.props();
+
+    let color = props
+        .get("color")
+        .and_then(CssProperty::parse_color)
+        .map_or_else(|| "black".to_string(), css_color);
+    let fill = props
+        .get("fill")
+        .and_then(|prop| resolve_paint::<C::CssSystem>(prop, &color));
+    let stroke = props
+        .get("stroke")
+        .and_then(|prop| resolve_paint::<C::CssSystem>(prop, &color));
+
+    let svg = style_svg(source, &color, fill.as_deref(), stroke.as_deref());
+
+    let mut hasher = DefaultHasher::new();
+    svg.hash(&mut hasher);
+    let key = format!("inline-svg:{:x}:{}x{}", hasher.finish(), size.width, size.height);
+
+    if let ImageCacheEntry::Image(img) = img_cache.get(&key) {
+        return Ok(img.clone());
+    }
+
+    let doc = <<C::RenderBackend as RenderBackend>::SVGRenderer as SvgRenderer<C::RenderBackend>>::parse_external(svg)?;
+
+    let img = svg_renderer
+        .lock()
+        .map_err(|_| anyhow!("Could not lock svg renderer"))?
+        .render_with_size(&doc, size)?;
+
+    img_cache.add(key, img.clone(), Some(size));
+
+    Ok(img)
+}
+
 const INVALID_IMG_BYTES: &[u8] = include_bytes!("../../resources/test_img.png");
 
 static INVALID_IMG: LazyLock<DynamicImage> =
@@ -0,0 +1,115 @@
+use crate::draw::backend_radius;
+use gosub_interface::config::HasDrawComponents;
+use gosub_interface::css3::{CssProperty, CssPropertyMap};
+use gosub_interface::layout::Layout;
+use gosub_interface::render_backend::{BlendMode, ClipShape, Rect, RenderBackend, Transform};
+use gosub_interface::render_tree::{RenderTree, RenderTreeNode};
+use gosub_rendering::background::{Area, Edges};
+use gosub_rendering::border_radius::BorderRadii;
+use gosub_rendering::effects::{clip_path, clips_overflow, opacity, ClipPath};
+use gosub_rendering::transform::{invert, transform};
+use gosub_shared::geo::{Point, FP};
+
+/// Layer that is pushed onto the scene
+pub struct Layer<B: RenderBackend> {
+    pub clip: ClipShape<B>,
+    pub opacity: FP,
+    pub blend_mode: BlendMode,
+}
+
+/// Layers an element paints into
+pub struct NodeLayers<B: RenderBackend> {
+    /// Transform of the element and all of its descendants, in page coordinates. A transformed element creates a
+    /// stacking context: it is painted into a scene of its own, which is transformed as a whole.
+    pub transform: Option<B::Transform>,
+    /// Layer for `opacity`, `mix-blend-mode` and `clip-path`. It contains the element and all of its descendants.
+    pub effects: Option<Layer<B>>,
+    /// Clip of the overflowing content. It contains the descendants, but not the background and border of the
+    /// element itself.
+    pub overflow: Option<ClipShape<B>>,
+}
+
+impl<B: RenderBackend> NodeLayers<B> {
+    /// Returns true when nothing of the element and its descendants is visible
+    pub fn is_invisible(&self) -> bool {
+        self.effects.as_ref().is_some_and(|layer| layer.opacity <= 0.0)
+            || self
+                .transform
+                .as_ref()
+                .is_some_and(|transform| invert(transform.as_matrix()).is_none())
+    }
+}
+
+/// Resolves the layers of the element that is painted at `pos`
+pub fn node_layers<C: HasDrawComponents>(
+    node: &<C::RenderTree as RenderTree<C>>::Node,
+    pos: Point,
+    border_radius: BorderRadii,
+) -> NodeLayers<C::RenderBackend> {
+    let props = node.props();
+    let size = node.layout().size();
+
+    let transform =
+        transform::<C::CssSystem>(props, pos, size).map(<C::RenderBackend as RenderBackend>::Transform::from_matrix);
+
+    let opacity = opacity::<C::CssSystem>(props);
+    let blend_mode = props
+        .get("mix-blend-mode")
+        .and_then(|prop| prop.as_string())
+        .and_then(|mode| mode.parse().ok())
+        .unwrap_or_default();
+    let clip = props
+        .get("clip-path")
+        .and_then(|prop| clip_path::<C::CssSystem>(prop, pos, size))
+        .map_or(ClipShape::None, clip_shape::<C::RenderBackend>);
+
+    let effects =
+        (opacity < 1.0 || blend_mode != BlendMode::Normal || !matches!(clip, ClipShape::None)).then_some(Layer {
+            clip,
+            opacity,
+            blend_mode,
+        });
+
+    let overflow = clips_overflow::<C::CssSystem>(props).then(|| {
+        // Content is clipped to the padding box
+        let padding = Area::new(pos, size).inset(Edges::from_layout_rect(node.layout().border()));
+        let rect = <C::RenderBackend as RenderBackend>::Rect::new(
+            padding.pos.x,
+            padding.pos.y,
+            padding.size.width,
+            padding.size.height,
+        );
+
+        // The corners of the padding box follow the inner edge of the border
+        let radius = border_radius.inner(Area::new(pos, size), padding);
+
+        ClipShape::Rect(rect, Some(backend_radius::<C::RenderBackend>(radius)))
+    });
+
+    NodeLayers {
+        transform,
+        effects,
+        overflow,
+    }
+}
+
+fn clip_shape<B: RenderBackend>(clip: ClipPath) -> ClipShape<B> {
+    match clip {
+        ClipPath::Rect {
+            x,
+            y,
+            width,
+            height,
+            radius,
+        } => ClipShape::Rect(B::Rect::new(x, y, width, height), Some(radius.into())),
+        ClipPath::Ellipse {
+            center,
+            radius_x,
+            radius_y,
+        } => ClipShape::Ellipse {
+            center,
+            radius_x,
+            radius_y,
+        },
+    }
+}
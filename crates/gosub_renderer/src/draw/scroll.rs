@@ -0,0 +1,110 @@
+use gosub_interface::config::HasDrawComponents;
+use gosub_interface::layout::LayoutTree;
+use gosub_interface::render_backend::{Brush, Color, Rect, RenderBackend, RenderRect, Scene as TScene};
+use gosub_interface::render_tree::RenderTreeNode as _;
+use gosub_rendering::background::Area;
+use gosub_rendering::render_tree::RenderTree;
+use gosub_rendering::scroll::{overflow, scroll_box as resolve_scroll_box, Overflow, ScrollBox, Scrollbar};
+use gosub_shared::geo::{Point, Size, FP};
+use gosub_shared::node::NodeId;
+
+/// Background of the track of a scrollbar in its gutter
+const TRACK_COLOR: (u8, u8, u8) = (241, 241, 241);
+const THUMB_COLOR: (u8, u8, u8) = (193, 193, 193);
+/// Thumb of a scrollbar that is drawn over the content, which has no track
+const OVERLAY_THUMB_COLOR: (u8, u8, u8, u8) = (0, 0, 0, 112);
+/// Space between the thumb and the edges of its track
+const THUMB_INSET: FP = 2.0;
+
+/// Returns the scroll container of an element, or None when it is not one. The overflow of the root element, and of
+/// the `<body>` when the root element leaves its overflow visible, applies to the viewport instead, which the page
+/// scrolls in.
+pub fn scroll_box<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>>>(
+    tree: &RenderTree<C>,
+    id: NodeId,
+) -> Option<ScrollBox> {
+    if scrolls_viewport(tree, id) {
+        return None;
+    }
+
+    let node = tree.get_node(id)?;
+    if !node.is_element() {
+        return None;
+    }
+
+    resolve_scroll_box::<C::CssSystem>(node.props(), node.layout())
+}
+
+/// Returns true when the overflow of the element applies to the viewport
+pub fn scrolls_viewport<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>>>(
+    tree: &RenderTree<C>,
+    id: NodeId,
+) -> bool {
+    let is_root_element = |id: NodeId| {
+        tree.get_node(id)
+            .is_some_and(|node| node.name == "html" && node.parent == Some(tree.root))
+    };
+
+    let Some(node) = tree.get_node(id) else {
+        return false;
+    };
+
+    match node.name.as_str() {
+        "html" => is_root_element(id),
+        "body" => node.parent.is_some_and(|parent| {
+            is_root_element(parent)
+                && tree.get_node(parent).is_some_and(|html| {
+                    overflow::<C::CssSystem>(html.props()) == (Overflow::Visible, Overflow::Visible)
+                })
+        }),
+        _ => false,
+    }
+}
+
+/// Paints the scrollbars of a scroll container with its border box at `pos`
+pub fn render_scrollbars<B: RenderBackend>(scrollbars: &[Scrollbar], pos: &Point, scene: &mut B::Scene) {
+    let rect = |area: Area| {
+        Rect::new(
+            pos.x + area.pos.x,
+            pos.y + area.pos.y,
+            area.size.width,
+            area.size.height,
+        )
+    };
+
+    for scrollbar in scrollbars {
+        let thumb = Area::new(
+            Point::new(scrollbar.thumb.pos.x + THUMB_INSET, scrollbar.thumb.pos.y + THUMB_INSET),
+            Size::new(
+                (scrollbar.thumb.size.width - 2.0 * THUMB_INSET).max(0.0),
+                (scrollbar.thumb.size.height - 2.0 * THUMB_INSET).max(0.0),
+            ),
+        );
+        let radius = thumb.size.width.min(thumb.size.height) / 2.0;
+
+        let brush = if scrollbar.overlay {
+            let (r, g, b, a) = OVERLAY_THUMB_COLOR;
+            Brush::color(Color::rgba(r, g, b, a))
+        } else {
+            scene.draw_rect(&RenderRect {
+                rect: rect(scrollbar.track),
+                transform: None,
+                radius: None,
+                brush: Brush::color(Color::rgb(TRACK_COLOR.0, TRACK_COLOR.1, TRACK_COLOR.2)),
+                brush_transform: None,
+                border: None,
+            });
+
+            Brush::color(Color::rgb(THUMB_COLOR.0, THUMB_COLOR.1, THUMB_COLOR.2))
+        };
+
+        scene.draw_rect(&RenderRect {
+            rect: rect(thumb),
+            transform: None,
+            radius: Some(B::BorderRadius::from(radius)),
+            brush,
+            brush_transform: None,
+            border: None,
+        });
+    }
+}
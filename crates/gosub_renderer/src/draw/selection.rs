@@ -0,0 +1,80 @@
+use gosub_interface::config::HasDrawComponents;
+use gosub_interface::css3::{CssProperty, CssPropertyMap};
+use gosub_interface::layout::{Layout, LayoutTree};
+use gosub_interface::render_backend::{
+    BlendMode, Brush, ClipShape, Color, Rect, RenderBackend, RenderRect, RenderText, Scene as TScene, Text,
+};
+use gosub_interface::render_tree::RenderTreeNode;
+use gosub_rendering::render_tree::RenderTree;
+use gosub_rendering::selection::text_rects;
+use gosub_shared::geo::Point;
+use gosub_shared::node::NodeId;
+use std::ops::Range;
+
+/// Background of selected text when no `::selection` rule sets one
+const SELECTION_BACKGROUND: (u8, u8, u8, u8) = (179, 215, 255, 255);
+
+/// Paints the selected part of a text node over the text: a background behind it and the text again in the
+/// `::selection` colour. `range` is a byte range into the text of all layouts of the node.
+pub fn render_selection<C: HasDrawComponents<RenderTree = RenderTree<C>, LayoutTree = RenderTree<C>>>(
+    tree: &RenderTree<C>,
+    id: NodeId,
+    range: Range<usize>,
+    pos: &Point,
+    scene: &mut <C::RenderBackend as RenderBackend>::Scene,
+) {
+    let Some(node) = tree.get_node(id) else {
+        return;
+    };
+    let Some((_, layouts)) = node.text_data() else {
+        return;
+    };
+
+    let style = tree.selection_style(id);
+    let color_of = |props: Option<&C::CssPropertyMap>, name: &str| {
+        props
+            .and_then(|props| props.get(name))
+            .and_then(CssProperty::parse_color)
+            .map(|color| (color.0 as u8, color.1 as u8, color.2 as u8, color.3 as u8))
+    };
+
+    let background = color_of(style, "background-color").unwrap_or(SELECTION_BACKGROUND);
+    let color = color_of(style, "color")
+        .or_else(|| color_of(Some(node.props()), "color"))
+        .unwrap_or((0, 0, 0, 255));
+
+    let text = layouts
+        .iter()
+        .map(<C::RenderBackend as RenderBackend>::Text::new)
+        .collect::<Vec<_>>();
+    let size = node.layout().size();
+
+    for rect in text_rects::<C>(tree, id, range) {
+        let x = pos.x + rect.pos.x;
+        let y = pos.y + rect.pos.y;
+
+        scene.draw_rect(&RenderRect {
+            rect: Rect::new(x, y, rect.size.width, rect.size.height),
+            transform: None,
+            radius: None,
+            brush: Brush::color(Color::rgba(background.0, background.1, background.2, background.3)),
+            brush_transform: None,
+            border: None,
+        });
+
+        // The text is painted again over the background, clipped to the selected part. The clip reaches below the
+        // line, so descenders are not cut off.
+        let clip = Rect::new(x, y, rect.size.width, rect.size.height * 2.0);
+        scene.push_layer(&ClipShape::Rect(clip, None), 1.0, BlendMode::Normal);
+
+        scene.draw_text(&RenderText {
+            text: text.clone(),
+            rect: Rect::new(pos.x, pos.y, size.width, size.height),
+            transform: None,
+            brush: Brush::color(Color::rgba(color.0, color.1, color.2, color.3)),
+            brush_transform: None,
+        });
+
+        scene.pop_layer();
+    }
+}
@@ -1,3 +1,4 @@
 mod debug;
 pub mod draw;
 pub mod render_tree;
+pub mod resource;
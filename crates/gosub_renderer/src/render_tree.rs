@@ -1,13 +1,17 @@
+use crate::resource::{wait_for_render_blocking, FetchResourceLoader};
 use anyhow::bail;
 use gosub_interface::config::{HasHtmlParser, HasRenderTree};
 use gosub_interface::css3::CssSystem;
 use gosub_interface::document::{Document, DocumentBuilder};
 
 use gosub_interface::html5::Html5Parser;
+use gosub_interface::resource::ResourceLoader;
 use gosub_net::http::fetcher::Fetcher;
+use gosub_net::http::request::Request;
 use gosub_rendering::render_tree::generate_render_tree;
 use gosub_shared::byte_stream::{ByteStream, Encoding};
 use std::fs;
+use std::sync::Arc;
 use url::Url;
 
 /// Generates a render tree from the given URL... if the source is given, the URL is not loaded, but the source HTML is used instead
@@ -18,7 +22,7 @@ pub async fn load_html_rendertree<C: HasRenderTree + HasHtmlParser>(
     let fetcher = Fetcher::new(url.clone());
 
     let (rt, handle) = match source {
-        Some(source) => load_html_rendertree_source::<C>(url, source)?,
+        Some(source) => load_html_rendertree_source::<C>(url, source).await?,
         None => load_html_rendertree_fetcher::<C>(url, &fetcher).await?,
     };
 
@@ -26,27 +30,78 @@ pub async fn load_html_rendertree<C: HasRenderTree + HasHtmlParser>(
 }
 
 // Generate a render tree from the given source HTML. THe URL is needed to resolve relative URLs
-// and also to set the base URL for the document.
-pub fn load_html_rendertree_source<C: HasRenderTree + HasHtmlParser>(
+// and also to set the base URL for the document. External resources are loaded in the background, and the render
+// tree is generated once all render-blocking resources (like stylesheets) are loaded.
+pub async fn load_html_rendertree_source<C: HasRenderTree + HasHtmlParser>(
     url: Url,
     source_html: &str,
 ) -> gosub_shared::types::Result<(C::RenderTree, C::Document)> {
+    let (loader, mut responses) = FetchResourceLoader::new(url.clone());
+
+    let mut doc = parse_document::<C>(url, source_html, Arc::new(loader))?;
+    wait_for_render_blocking::<C>(&mut doc, &mut responses).await;
+
+    finish_rendertree::<C>(doc)
+}
+
+/// Generates a render tree from the given source HTML, blocking until all render-blocking resources are loaded
+pub fn load_html_rendertree_source_sync<C: HasRenderTree + HasHtmlParser>(
+    url: Url,
+    source_html: &str,
+) -> gosub_shared::types::Result<(C::RenderTree, C::Document)> {
+    futures::executor::block_on(load_html_rendertree_source::<C>(url, source_html))
+}
+
+fn parse_document<C: HasRenderTree + HasHtmlParser>(
+    url: Url,
+    source_html: &str,
+    loader: Arc<dyn ResourceLoader>,
+) -> gosub_shared::types::Result<C::Document> {
     let mut stream = ByteStream::new(Encoding::UTF8, None);
     stream.read_from_str(source_html, Some(Encoding::UTF8));
     stream.close();
 
     let mut doc = C::DocumentBuilder::new_document(Some(url));
+    doc.set_resource_loader(loader);
+
     let parse_errors = C::HtmlParser::parse(&mut stream, &mut doc, None)?;
 
     for error in parse_errors {
         eprintln!("Parse error: {error:?}");
     }
 
+    Ok(doc)
+}
+
+fn finish_rendertree<C: HasRenderTree + HasHtmlParser>(
+    mut doc: C::Document,
+) -> gosub_shared::types::Result<(C::RenderTree, C::Document)> {
     doc.add_stylesheet(C::CssSystem::load_default_useragent_stylesheet());
 
     Ok((generate_render_tree::<C>(&doc)?, doc))
 }
 
+/// Generates a render tree from the response to the request, like the submission of a form. Requests that are not
+/// POST requests are loaded like any other URL.
+pub async fn load_html_rendertree_request<C: HasRenderTree + HasHtmlParser>(
+    request: &Request,
+    fetcher: &Fetcher,
+) -> gosub_shared::types::Result<(C::RenderTree, C::Document)> {
+    let url = fetcher.parse_url(&request.uri)?;
+    if request.method != "POST" {
+        return load_html_rendertree_fetcher::<C>(url, fetcher).await;
+    }
+
+    let response = fetcher.get_req(request).await?;
+    if !response.is_ok() {
+        bail!(format!("Could not submit form. Status code {}", response.status));
+    }
+
+    let html = String::from_utf8(response.body)?;
+
+    load_html_rendertree_source::<C>(url, &html).await
+}
+
 /// Generates a render tree from the given URL. The complete HTML source is fetched from the URL async.
 pub async fn load_html_rendertree_fetcher<C: HasRenderTree + HasHtmlParser>(
     url: Url,
@@ -61,10 +116,13 @@ pub async fn load_html_rendertree_fetcher<C: HasRenderTree + HasHtmlParser>(
 
         String::from_utf8(response.body.clone())?
     } else if url.scheme() == "file" {
-        fs::read_to_string(url.as_str().trim_start_matches("file://"))?
+        // The fragment points into the page, it is not part of the path of the file
+        let mut file = url.clone();
+        file.set_fragment(None);
+        fs::read_to_string(file.as_str().trim_start_matches("file://"))?
     } else {
         bail!("Unsupported url scheme: {}", url.scheme());
     };
 
-    load_html_rendertree_source::<C>(url, &html)
+    load_html_rendertree_source::<C>(url, &html).await
 }
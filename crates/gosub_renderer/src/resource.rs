@@ -0,0 +1,180 @@
+//! Loads the external resources of a document through the `Fetcher`
+
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::StreamExt;
+use gosub_interface::config::HasDocument;
+use gosub_interface::document::Document;
+use gosub_interface::resource::{
+    ResourceCallback, ResourceLoader, ResourcePriority, ResourceRequest, ResourceResponse,
+};
+use gosub_net::http::fetcher::Fetcher;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// Maximum number of resources that are fetched at the same time
+const MAX_CONCURRENT_FETCHES: usize = 6;
+
+/// Resource loader that fetches resources in the background. Queued requests are fetched in order of priority and
+/// the responses are sent to the receiver returned by `new`, or to the callback they were fetched with.
+#[derive(Debug)]
+pub struct FetchResourceLoader {
+    fetcher: Arc<Fetcher>,
+    state: Arc<Mutex<LoaderState>>,
+    sender: UnboundedSender<ResourceResponse>,
+}
+
+#[derive(Debug, Default)]
+struct LoaderState {
+    queue: BinaryHeap<QueuedRequest>,
+    /// Number of fetches in progress
+    active: usize,
+    /// Sequence number of the next request, so requests with the same priority are fetched in request order
+    next_seq: u64,
+}
+
+struct QueuedRequest {
+    priority: ResourcePriority,
+    seq: u64,
+    request: ResourceRequest,
+    /// Callback of a request that is fetched with `ResourceLoader::fetch`
+    reply: Option<ResourceCallback>,
+}
+
+impl fmt::Debug for QueuedRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueuedRequest")
+            .field("priority", &self.priority)
+            .field("seq", &self.seq)
+            .field("request", &self.request)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for QueuedRequest {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedRequest {}
+
+impl PartialOrd for QueuedRequest {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedRequest {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl FetchResourceLoader {
+    /// Creates a loader for the document at the given url
+    #[must_use]
+    pub fn new(base: Url) -> (Self, UnboundedReceiver<ResourceResponse>) {
+        let (sender, receiver) = unbounded();
+
+        let loader = Self {
+            fetcher: Arc::new(Fetcher::new(base)),
+            state: Arc::new(Mutex::new(LoaderState::default())),
+            sender,
+        };
+
+        (loader, receiver)
+    }
+
+    fn queue(&self, request: ResourceRequest, reply: Option<ResourceCallback>) {
+        if let Ok(mut state) = self.state.lock() {
+            let seq = state.next_seq;
+            state.next_seq += 1;
+
+            state.queue.push(QueuedRequest {
+                priority: request.priority,
+                seq,
+                request,
+                reply,
+            });
+        }
+
+        pump(&self.fetcher, &self.state, &self.sender);
+    }
+}
+
+impl ResourceLoader for FetchResourceLoader {
+    fn request(&self, request: ResourceRequest) {
+        self.queue(request, None);
+    }
+
+    fn fetch(&self, request: ResourceRequest, reply: ResourceCallback) {
+        self.queue(request, Some(reply));
+    }
+}
+
+/// Starts fetching queued requests until the maximum number of concurrent fetches is reached
+fn pump(fetcher: &Arc<Fetcher>, state: &Arc<Mutex<LoaderState>>, sender: &UnboundedSender<ResourceResponse>) {
+    loop {
+        let queued = {
+            let Ok(mut state) = state.lock() else {
+                return;
+            };
+            if state.active >= MAX_CONCURRENT_FETCHES {
+                return;
+            }
+            let Some(queued) = state.queue.pop() else {
+                return;
+            };
+
+            state.active += 1;
+            queued
+        };
+        let QueuedRequest { request, reply, .. } = queued;
+
+        let fetcher = fetcher.clone();
+        let state = state.clone();
+        let sender = sender.clone();
+
+        gosub_shared::async_executor::spawn(async move {
+            let body = match fetcher.get_url(&request.url).await {
+                Ok(response) if response.is_ok() => Ok(response.body),
+                Ok(response) => Err(format!("status code {}", response.status)),
+                Err(e) => Err(e.to_string()),
+            };
+
+            let response = ResourceResponse { request, body };
+            match reply {
+                Some(reply) => reply(response),
+                // The receiver is gone when the document has been dropped in the meantime
+                None => _ = sender.unbounded_send(response),
+            }
+
+            if let Ok(mut state) = state.lock() {
+                state.active -= 1;
+            }
+
+            pump(&fetcher, &state, &sender);
+        });
+    }
+}
+
+/// Hands loaded resources to the document until all render-blocking resources are loaded. Responses that arrive
+/// after this returns (for instance, of preloaded resources) are not used by the document, so the receiver can be
+/// dropped.
+pub async fn wait_for_render_blocking<C: HasDocument>(
+    doc: &mut C::Document,
+    responses: &mut UnboundedReceiver<ResourceResponse>,
+) {
+    while doc.is_render_blocked() {
+        let Some(response) = responses.next().await else {
+            break;
+        };
+
+        doc.resource_loaded(response);
+    }
+}
@@ -0,0 +1,696 @@
+//! Background layers of an element. `background-image` holds a comma separated list of images, which are either
+//! `url()`s or gradients. The other `background-*` longhands hold a value per layer, and are repeated when they have
+//! fewer values than there are images. The size, position and repetition of the tiles of a layer are resolved here
+//! against the boxes of the element, the images and brushes are painted by the renderer.
+
+mod gradient;
+
+pub use gradient::{gradient, Gradient, GradientShape, GradientStop, Rgba};
+
+use gosub_interface::css3::{CssProperty, CssPropertyMap, CssSystem, CssValue};
+use gosub_shared::geo::{Point, Rect, Size, FP};
+
+/// Maximum number of tiles that are painted for a single layer
+pub const MAX_TILES: usize = 4096;
+
+/// Box of the element that a layer is positioned in (`background-origin`) or painted in (`background-clip`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundBox {
+    Border,
+    Padding,
+    Content,
+    /// Only the text of the element and its descendants is painted with the background
+    Text,
+}
+
+impl BackgroundBox {
+    fn from_component(component: Option<&Component>, default: Self) -> Self {
+        match component {
+            Some(Component::Keyword("border-box")) => Self::Border,
+            Some(Component::Keyword("padding-box")) => Self::Padding,
+            Some(Component::Keyword("content-box")) => Self::Content,
+            Some(Component::Keyword("text")) => Self::Text,
+            _ => default,
+        }
+    }
+}
+
+/// How the tiles of a layer are repeated along an axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    Repeat,
+    /// Repeated as often as it fits without clipping, the remaining space is distributed between the tiles
+    Space,
+    /// Repeated as often as it fits, the tiles are scaled so a whole number of tiles fits
+    Round,
+    NoRepeat,
+}
+
+impl Repeat {
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "repeat" => Some(Self::Repeat),
+            "space" => Some(Self::Space),
+            "round" => Some(Self::Round),
+            "no-repeat" => Some(Self::NoRepeat),
+            _ => None,
+        }
+    }
+}
+
+/// Rectangle on the page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Area {
+    pub pos: Point,
+    pub size: Size,
+}
+
+impl Area {
+    #[must_use]
+    pub fn new(pos: Point, size: Size) -> Self {
+        Self { pos, size }
+    }
+
+    /// Returns true when the point is inside the area
+    #[must_use]
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.pos.x
+            && point.y >= self.pos.y
+            && point.x < self.pos.x + self.size.width
+            && point.y < self.pos.y + self.size.height
+    }
+
+    /// Returns the area shrunk by the edges
+    #[must_use]
+    pub fn inset(self, edges: Edges) -> Self {
+        Self {
+            pos: Point::new(self.pos.x + edges.left, self.pos.y + edges.top),
+            size: Size::new(
+                (self.size.width - edges.left - edges.right).max(0.0),
+                (self.size.height - edges.top - edges.bottom).max(0.0),
+            ),
+        }
+    }
+}
+
+/// Widths of the four edges of a box
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Edges {
+    pub top: FP,
+    pub right: FP,
+    pub bottom: FP,
+    pub left: FP,
+}
+
+impl Edges {
+    /// Returns the edges of the border or padding of a layout, which stores them as (top, right, bottom, left)
+    #[must_use]
+    pub fn from_layout_rect(rect: Rect) -> Self {
+        Self {
+            top: rect.x1,
+            right: rect.y1,
+            bottom: rect.x2,
+            left: rect.y2,
+        }
+    }
+}
+
+/// Border, padding and content box of an element
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElementBoxes {
+    pub border: Area,
+    pub padding: Area,
+    pub content: Area,
+}
+
+impl ElementBoxes {
+    #[must_use]
+    pub fn new(border_box: Area, border: Edges, padding: Edges) -> Self {
+        let padding_box = border_box.inset(border);
+
+        Self {
+            border: border_box,
+            padding: padding_box,
+            content: padding_box.inset(padding),
+        }
+    }
+
+    /// Returns the area of a box. Text is clipped to the border box.
+    #[must_use]
+    pub fn get(&self, kind: BackgroundBox) -> Area {
+        match kind {
+            BackgroundBox::Border | BackgroundBox::Text => self.border,
+            BackgroundBox::Padding => self.padding,
+            BackgroundBox::Content => self.content,
+        }
+    }
+}
+
+/// Value of a background longhand for a single layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Component<'a> {
+    Keyword(&'a str),
+    Length(FP),
+    Percentage(FP),
+    Other,
+}
+
+/// Background layer of an element
+pub struct BackgroundLayer<'a, V> {
+    /// Image of the layer as `(function, arguments)`, or None for `none`
+    pub image: Option<(&'a str, &'a [V])>,
+    pub origin: BackgroundBox,
+    pub clip: BackgroundBox,
+    /// The layer is positioned relative to the viewport instead of the element (`background-attachment: fixed`)
+    pub fixed: bool,
+    pub repeat: (Repeat, Repeat),
+    size: Vec<Component<'a>>,
+    position: Vec<Component<'a>>,
+}
+
+/// Tiles that a layer is painted with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tiles {
+    /// Area the layer is clipped to
+    pub clip: Area,
+    pub size: Size,
+    /// Top left corner of each tile
+    pub positions: Vec<Point>,
+}
+
+/// Returns the background layers of the element, the top layer first. There is always at least one layer, which
+/// holds the clip of the background color.
+pub fn background_layers<S: CssSystem>(props: &S::PropertyMap) -> Vec<BackgroundLayer<'_, S::Value>> {
+    let images = match props.get("background-image") {
+        Some(prop) => match (prop.as_function(), prop.as_list()) {
+            (Some(function), _) => vec![Some(function)],
+            (None, Some(list)) => split_layers(list)
+                .into_iter()
+                .map(|layer| layer.first().and_then(|value| (*value).as_function()))
+                .collect(),
+            (None, None) => vec![None],
+        },
+        None => vec![None],
+    };
+
+    let sizes = longhand::<S>(props, "background-size");
+    let positions = longhand::<S>(props, "background-position");
+    let repeats = longhand::<S>(props, "background-repeat");
+    let attachments = longhand::<S>(props, "background-attachment");
+    let origins = longhand::<S>(props, "background-origin");
+    let clips = longhand::<S>(props, "background-clip");
+
+    images
+        .into_iter()
+        .enumerate()
+        .map(|(idx, image)| {
+            let repeat = match cycle(&repeats, idx).as_slice() {
+                [Component::Keyword("repeat-x")] => (Repeat::Repeat, Repeat::NoRepeat),
+                [Component::Keyword("repeat-y")] => (Repeat::NoRepeat, Repeat::Repeat),
+                [Component::Keyword(both)] => {
+                    let repeat = Repeat::from_keyword(both).unwrap_or(Repeat::Repeat);
+                    (repeat, repeat)
+                }
+                [Component::Keyword(x), Component::Keyword(y)] => (
+                    Repeat::from_keyword(x).unwrap_or(Repeat::Repeat),
+                    Repeat::from_keyword(y).unwrap_or(Repeat::Repeat),
+                ),
+                _ => (Repeat::Repeat, Repeat::Repeat),
+            };
+
+            BackgroundLayer {
+                image,
+                origin: BackgroundBox::from_component(cycle(&origins, idx).first(), BackgroundBox::Padding),
+                clip: BackgroundBox::from_component(cycle(&clips, idx).first(), BackgroundBox::Border),
+                fixed: cycle(&attachments, idx).first() == Some(&Component::Keyword("fixed")),
+                repeat,
+                size: cycle(&sizes, idx),
+                position: cycle(&positions, idx),
+            }
+        })
+        .collect()
+}
+
+impl<V> BackgroundLayer<'_, V> {
+    /// Resolves the tiles of the layer. `intrinsic` is the size of a raster image, gradients have no intrinsic size.
+    /// Fixed layers are positioned in the `viewport`, the visible part of the page. Returns None when nothing of the
+    /// layer is visible.
+    #[must_use]
+    pub fn tiles(&self, boxes: &ElementBoxes, viewport: Area, intrinsic: Option<Size>) -> Option<Tiles> {
+        let area = if self.fixed { viewport } else { boxes.get(self.origin) };
+        let clip = boxes.get(self.clip);
+        if clip.size.width <= 0.0 || clip.size.height <= 0.0 {
+            return None;
+        }
+
+        let size = self.tile_size(area.size, intrinsic)?;
+
+        let (x, y) = position(&self.position);
+        let x = area.pos.x + x.resolve(area.size.width - size.width);
+        let y = area.pos.y + y.resolve(area.size.height - size.height);
+
+        let xs = axis_tiles(
+            self.repeat.0,
+            x,
+            size.width,
+            (area.pos.x, area.size.width),
+            (clip.pos.x, clip.size.width),
+        );
+        let ys = axis_tiles(
+            self.repeat.1,
+            y,
+            size.height,
+            (area.pos.y, area.size.height),
+            (clip.pos.y, clip.size.height),
+        );
+
+        let positions = ys
+            .iter()
+            .flat_map(|y| xs.iter().map(move |x| Point::new(*x, *y)))
+            .take(MAX_TILES)
+            .collect();
+
+        Some(Tiles { clip, size, positions })
+    }
+
+    /// Resolves `background-size` against the positioning area. Rounded tiles are scaled so a whole number of tiles
+    /// fits in the area.
+    fn tile_size(&self, area: Size, intrinsic: Option<Size>) -> Option<Size> {
+        let intrinsic = intrinsic.filter(|size| size.width > 0.0 && size.height > 0.0);
+
+        let resolve = |component: Option<&Component>, basis: FP| match component {
+            Some(Component::Length(length)) => Some(*length),
+            Some(Component::Percentage(percentage)) => Some(basis * percentage / 100.0),
+            _ => None,
+        };
+
+        let (mut width, mut height, width_auto, height_auto) = match self.size.as_slice() {
+            [Component::Keyword(keyword @ ("cover" | "contain"))] => {
+                let size = match intrinsic {
+                    Some(intrinsic) => {
+                        let scale_x = area.width / intrinsic.width;
+                        let scale_y = area.height / intrinsic.height;
+                        let scale = if *keyword == "cover" {
+                            scale_x.max(scale_y)
+                        } else {
+                            scale_x.min(scale_y)
+                        };
+                        Size::new(intrinsic.width * scale, intrinsic.height * scale)
+                    }
+                    None => area,
+                };
+                (size.width, size.height, false, false)
+            }
+            components => {
+                let width = resolve(components.first(), area.width);
+                let height = resolve(components.get(1), area.height);
+
+                // A missing dimension follows from the aspect ratio of the image. Gradients have no aspect ratio, so
+                // they fill the positioning area.
+                let (w, h) = match (width, height, intrinsic) {
+                    (Some(w), Some(h), _) => (w, h),
+                    (Some(w), None, Some(i)) => (w, w * i.height / i.width),
+                    (None, Some(h), Some(i)) => (h * i.width / i.height, h),
+                    (None, None, Some(i)) => (i.width, i.height),
+                    (w, h, None) => (w.unwrap_or(area.width), h.unwrap_or(area.height)),
+                };
+                (w, h, width.is_none(), height.is_none())
+            }
+        };
+
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+
+        let rounded = |tile: FP, area: FP| area / (area / tile).round().max(1.0);
+
+        if self.repeat.0 == Repeat::Round && area.width > 0.0 {
+            let rounded = rounded(width, area.width);
+            if self.repeat.1 != Repeat::Round && height_auto {
+                height *= rounded / width;
+            }
+            width = rounded;
+        }
+        if self.repeat.1 == Repeat::Round && area.height > 0.0 {
+            let rounded = rounded(height, area.height);
+            if self.repeat.0 != Repeat::Round && width_auto {
+                width *= rounded / height;
+            }
+            height = rounded;
+        }
+
+        Some(Size::new(width, height))
+    }
+}
+
+/// Position of the tile along one axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AxisPosition<'a> {
+    /// The offset is from the right or bottom edge instead of the left or top edge
+    from_end: bool,
+    offset: Component<'a>,
+}
+
+impl AxisPosition<'_> {
+    const START: Self = Self {
+        from_end: false,
+        offset: Component::Percentage(0.0),
+    };
+    const CENTER: Self = Self {
+        from_end: false,
+        offset: Component::Percentage(50.0),
+    };
+    const END: Self = Self {
+        from_end: true,
+        offset: Component::Percentage(0.0),
+    };
+
+    /// Resolves the offset of the tile, `free` is the space that is left in the positioning area
+    fn resolve(self, free: FP) -> FP {
+        let offset = match self.offset {
+            Component::Length(length) => length,
+            Component::Percentage(percentage) => free * percentage / 100.0,
+            _ => 0.0,
+        };
+
+        if self.from_end {
+            free - offset
+        } else {
+            offset
+        }
+    }
+}
+
+/// Parses `background-position`. With one or two values the keywords are positions themselves, with three or four
+/// values the keywords are edges that are followed by an offset (`right 10px top`).
+fn position<'a>(components: &[Component<'a>]) -> (AxisPosition<'a>, AxisPosition<'a>) {
+    let keyword = |component: Option<&Component<'a>>, start: &str, end: &str| match component {
+        None | Some(Component::Keyword("center")) => AxisPosition::CENTER,
+        Some(Component::Keyword(keyword)) if *keyword == start => AxisPosition::START,
+        Some(Component::Keyword(keyword)) if *keyword == end => AxisPosition::END,
+        Some(offset) => AxisPosition {
+            from_end: false,
+            offset: *offset,
+        },
+    };
+
+    match components {
+        [] => (AxisPosition::START, AxisPosition::START),
+        [_] | [_, _] => {
+            // A vertical keyword first swaps the axes, like `top left`
+            let swapped = matches!(components[0], Component::Keyword("top" | "bottom"))
+                || matches!(components.get(1), Some(Component::Keyword("left" | "right")));
+            let (x, y) = if swapped {
+                (components.get(1), components.first())
+            } else {
+                (components.first(), components.get(1))
+            };
+
+            (keyword(x, "left", "right"), keyword(y, "top", "bottom"))
+        }
+        _ => {
+            let mut x = AxisPosition::CENTER;
+            let mut y = AxisPosition::CENTER;
+
+            let mut idx = 0;
+            while let Some(component) = components.get(idx) {
+                idx += 1;
+                let Component::Keyword(edge) = component else {
+                    continue;
+                };
+
+                let offset = match components.get(idx) {
+                    Some(offset @ (Component::Length(_) | Component::Percentage(_))) => {
+                        idx += 1;
+                        *offset
+                    }
+                    _ => Component::Percentage(0.0),
+                };
+
+                match *edge {
+                    "left" | "right" => {
+                        x = AxisPosition {
+                            from_end: *edge == "right",
+                            offset,
+                        };
+                    }
+                    "top" | "bottom" => {
+                        y = AxisPosition {
+                            from_end: *edge == "bottom",
+                            offset,
+                        };
+                    }
+                    _ => {}
+                }
+            }
+
+            (x, y)
+        }
+    }
+}
+
+/// Returns the start of the tiles along one axis that are visible in the clip area. `area` and `clip` are given as
+/// `(start, length)`.
+fn axis_tiles(repeat: Repeat, start: FP, tile: FP, area: (FP, FP), clip: (FP, FP)) -> Vec<FP> {
+    let (first, period) = match repeat {
+        Repeat::NoRepeat => return vec![start],
+        Repeat::Repeat | Repeat::Round => (start, tile),
+        Repeat::Space => {
+            let count = (area.1 / tile).floor();
+            if count < 2.0 {
+                return vec![start];
+            }
+            let spacing = (area.1 - count * tile) / (count - 1.0);
+            (area.0, tile + spacing)
+        }
+    };
+
+    // Move back to the first tile that could overlap the clip area
+    let first = first - ((first - clip.0) / period).ceil() * period;
+    let end = clip.0 + clip.1;
+
+    let mut tiles = Vec::new();
+    let mut pos = first;
+    while pos < end && tiles.len() < MAX_TILES {
+        if pos + tile > clip.0 {
+            tiles.push(pos);
+        }
+        pos += period;
+    }
+
+    tiles
+}
+
+/// Returns the values of a longhand per layer
+fn longhand<'a, S: CssSystem>(props: &'a S::PropertyMap, name: &str) -> Vec<Vec<Component<'a>>> {
+    let Some(prop) = props.get(name) else {
+        return Vec::new();
+    };
+
+    if let Some(list) = prop.as_list() {
+        return split_layers(list)
+            .into_iter()
+            .map(|layer| layer.into_iter().map(component).collect())
+            .collect();
+    }
+
+    let component = if let Some(keyword) = prop.as_string() {
+        Component::Keyword(keyword)
+    } else if let Some(percentage) = prop.as_percentage() {
+        Component::Percentage(percentage)
+    } else if prop.as_unit().is_some() || prop.as_number().is_some() {
+        Component::Length(prop.unit_to_px())
+    } else {
+        Component::Other
+    };
+
+    vec![vec![component]]
+}
+
+fn component<V: CssValue>(value: &V) -> Component<'_> {
+    if let Some(keyword) = value.as_string() {
+        return Component::Keyword(keyword);
+    }
+    if let Some(percentage) = value.as_percentage() {
+        return Component::Percentage(percentage);
+    }
+    if value.as_unit().is_some() || value.as_number().is_some() {
+        return Component::Length(value.unit_to_px());
+    }
+
+    Component::Other
+}
+
+/// Splits a comma separated list into layers. A value of a layer can be a list itself.
+fn split_layers<V: CssValue>(list: &[V]) -> Vec<Vec<&V>> {
+    list.split(|value| value.is_comma())
+        .map(|layer| {
+            layer
+                .iter()
+                .flat_map(|value| match value.as_list() {
+                    Some(values) => values.iter().collect(),
+                    None => vec![value],
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the value for a layer. A longhand with fewer values than there are layers repeats its values.
+fn cycle<'a>(layers: &[Vec<Component<'a>>], idx: usize) -> Vec<Component<'a>> {
+    if layers.is_empty() {
+        return Vec::new();
+    }
+
+    layers[idx % layers.len()].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::props;
+    use gosub_css3::system::Css3System;
+
+    type PropertyMap = <Css3System as CssSystem>::PropertyMap;
+
+    fn rounded(x: FP, y: FP) -> (FP, FP) {
+        ((x * 1000.0).round() / 1000.0, (y * 1000.0).round() / 1000.0)
+    }
+
+    const VIEWPORT: Area = Area {
+        pos: Point { x: 0.0, y: 500.0 },
+        size: Size {
+            width: 800.0,
+            height: 600.0,
+        },
+    };
+
+    /// Border box of 220x120 at (10, 20) with 5px borders and padding
+    fn boxes() -> ElementBoxes {
+        let edges = Edges {
+            top: 5.0,
+            right: 5.0,
+            bottom: 5.0,
+            left: 5.0,
+        };
+
+        ElementBoxes::new(Area::new(Point::new(10.0, 20.0), Size::new(220.0, 120.0)), edges, edges)
+    }
+
+    #[test]
+    fn background_layers_repeat_longhands() {
+        let map = props(&[
+            ("background-image", "linear-gradient(red, blue), none, url(image.png)"),
+            ("background-repeat", "repeat-x, space round"),
+            ("background-clip", "content-box"),
+            ("background-attachment", "fixed"),
+        ]);
+
+        let layers = background_layers::<Css3System>(&map);
+        assert_eq!(layers.len(), 3);
+        assert_eq!(layers[0].image.map(|(name, _)| name), Some("linear-gradient"));
+        assert!(layers[1].image.is_none());
+        assert_eq!(layers[2].image.map(|(name, _)| name), Some("url"));
+
+        assert_eq!(layers[0].repeat, (Repeat::Repeat, Repeat::NoRepeat));
+        assert_eq!(layers[1].repeat, (Repeat::Space, Repeat::Round));
+        assert_eq!(layers[2].repeat, (Repeat::Repeat, Repeat::NoRepeat));
+        assert!(layers.iter().all(|layer| layer.clip == BackgroundBox::Content
+            && layer.origin == BackgroundBox::Padding
+            && layer.fixed));
+
+        let map = props(&[("background-image", "linear-gradient(red, blue)")]);
+        assert_eq!(background_layers::<Css3System>(&map).len(), 1);
+
+        // Without images there is still a layer for the background color
+        let map = PropertyMap::default();
+        let layers = background_layers::<Css3System>(&map);
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].clip, BackgroundBox::Border);
+    }
+
+    #[test]
+    fn background_size_and_position() {
+        let map = props(&[
+            ("background-image", "url(image.png)"),
+            ("background-size", "contain"),
+            ("background-repeat", "no-repeat"),
+            ("background-position", "right 10px bottom"),
+        ]);
+        let layers = background_layers::<Css3System>(&map);
+
+        // The padding box is 210x110 at (15, 25)
+        let tiles = layers[0]
+            .tiles(&boxes(), VIEWPORT, Some(Size::new(100.0, 50.0)))
+            .unwrap();
+        assert_eq!(rounded(tiles.size.width, tiles.size.height), (210.0, 105.0));
+        assert_eq!(rounded(tiles.positions[0].x, tiles.positions[0].y), (5.0, 30.0));
+        assert_eq!(tiles.clip, boxes().border);
+
+        let map = props(&[
+            ("background-image", "url(image.png)"),
+            ("background-size", "50px auto"),
+            ("background-repeat", "no-repeat"),
+            ("background-position", "top right"),
+        ]);
+        let layers = background_layers::<Css3System>(&map);
+        let tiles = layers[0]
+            .tiles(&boxes(), VIEWPORT, Some(Size::new(100.0, 50.0)))
+            .unwrap();
+        assert_eq!(tiles.size, Size::new(50.0, 25.0));
+        assert_eq!(tiles.positions, [Point::new(175.0, 25.0)]);
+
+        // Gradients fill the positioning area in the missing dimension
+        let tiles = layers[0].tiles(&boxes(), VIEWPORT, None).unwrap();
+        assert_eq!(tiles.size, Size::new(50.0, 110.0));
+    }
+
+    #[test]
+    fn background_repeat_space_and_round() {
+        // 210 / 50 rounds to 4 tiles of 52.5, the auto height keeps the aspect ratio
+        let map = props(&[
+            ("background-image", "url(image.png)"),
+            ("background-repeat", "round no-repeat"),
+        ]);
+        let layers = background_layers::<Css3System>(&map);
+        let tiles = layers[0]
+            .tiles(&boxes(), VIEWPORT, Some(Size::new(50.0, 20.0)))
+            .unwrap();
+        assert_eq!(tiles.size, Size::new(52.5, 21.0));
+
+        // The tiles start at the padding box and cover the whole border box
+        let xs = tiles.positions.iter().map(|pos| pos.x).collect::<Vec<_>>();
+        assert_eq!(xs, [-37.5, 15.0, 67.5, 120.0, 172.5, 225.0]);
+
+        // 4 tiles of 50 fit in 210, with 10/3 between them
+        let map = props(&[
+            ("background-image", "url(image.png)"),
+            ("background-repeat", "space no-repeat"),
+            ("background-clip", "padding-box"),
+        ]);
+        let layers = background_layers::<Css3System>(&map);
+        let tiles = layers[0]
+            .tiles(&boxes(), VIEWPORT, Some(Size::new(50.0, 20.0)))
+            .unwrap();
+        assert_eq!(tiles.positions.len(), 4);
+        assert_eq!(tiles.positions[0].x, 15.0);
+        assert!((tiles.positions[3].x - 175.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn background_attachment_fixed() {
+        let map = props(&[
+            ("background-image", "url(image.png)"),
+            ("background-attachment", "fixed"),
+            ("background-repeat", "no-repeat"),
+            ("background-position", "center"),
+        ]);
+        let layers = background_layers::<Css3System>(&map);
+        let tiles = layers[0]
+            .tiles(&boxes(), VIEWPORT, Some(Size::new(100.0, 100.0)))
+            .unwrap();
+
+        // Centered in the viewport, even when that is outside the element
+        assert_eq!(tiles.positions, [Point::new(350.0, 750.0)]);
+    }
+}
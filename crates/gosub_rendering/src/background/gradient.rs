@@ -0,0 +1,704 @@
+//! CSS gradients: `linear-gradient()`, `radial-gradient()`, `conic-gradient()` and their `repeating-` variants
+
+use crate::effects::{length, position};
+use gosub_interface::css3::CssValue;
+use gosub_shared::geo::{Point, Size, FP};
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+/// Color with the components in the range 0.0 to 255.0
+pub type Rgba = (f32, f32, f32, f32);
+
+/// Geometry of a resolved gradient
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradientShape {
+    /// Gradient along the line from `start` to `end`
+    Linear { start: Point, end: Point },
+    /// Gradient between two circles around `center`. Elliptical gradients are circles that are scaled vertically
+    /// by `scale_y` around the center.
+    Radial {
+        center: Point,
+        start_radius: FP,
+        end_radius: FP,
+        scale_y: FP,
+    },
+    /// Gradient around `center`. The angles are in radians, clockwise from the positive x-axis.
+    Conic {
+        center: Point,
+        start_angle: FP,
+        end_angle: FP,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientStop {
+    /// Offset in the range 0.0 to 1.0
+    pub offset: FP,
+    pub color: Rgba,
+}
+
+/// Gradient that is resolved against the box it is painted in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub shape: GradientShape,
+    pub stops: Vec<GradientStop>,
+    /// The stops repeat beyond the start and end of the gradient, instead of extending the first and last color
+    pub repeating: bool,
+}
+
+/// Entry of a color stop list before the positions are fixed up
+enum StopEntry {
+    Stop(Rgba, Option<FP>),
+    Hint(FP),
+}
+
+/// Resolves a gradient function against the box at `pos`. Returns None when the function is not a gradient, or when
+/// its arguments are invalid.
+pub fn gradient<V: CssValue>(function: &str, args: &[V], pos: Point, size: Size) -> Option<Gradient> {
+    let (repeating, function) = match function.strip_prefix("repeating-") {
+        Some(function) => (true, function),
+        None => (false, function),
+    };
+
+    let groups = args.split(|value| value.is_comma()).collect::<Vec<_>>();
+    let (config, stops) = match groups.first() {
+        Some(first) if first.first().and_then(CssValue::parse_color).is_none() => (*first, &groups[1..]),
+        _ => (&[][..], &groups[..]),
+    };
+
+    // Positions of the stops are resolved as a fraction of `basis`
+    let (shape, basis) = match function {
+        "linear-gradient" => linear(config, pos, size)?,
+        "radial-gradient" => radial(config, pos, size)?,
+        "conic-gradient" => conic(config, pos, size)?,
+        _ => return None,
+    };
+    let is_conic = matches!(shape, GradientShape::Conic { .. });
+
+    let offset = |value: &V| match is_conic {
+        true => angle(value)
+            .map(|angle| angle / TAU)
+            .or_else(|| value.as_percentage().map(|p| p / 100.0)),
+        false => value
+            .as_percentage()
+            .map(|p| p / 100.0)
+            .or_else(|| length(value, basis).map(|length| length / basis.max(0.01))),
+    };
+
+    let mut entries = Vec::new();
+    for stop in stops {
+        match stop {
+            [hint] if hint.parse_color().is_none() => entries.push(StopEntry::Hint(offset(hint)?)),
+            [color, positions @ ..] if positions.len() <= 2 => {
+                let color = color.parse_color()?;
+                if positions.is_empty() {
+                    entries.push(StopEntry::Stop(color, None));
+                }
+                for position in positions {
+                    entries.push(StopEntry::Stop(color, Some(offset(position)?)));
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    let stops = fixup_stops(entries)?;
+
+    Some(match repeating {
+        true => repeat(shape, stops),
+        false => Gradient {
+            shape,
+            stops: clamp_stops(&stops),
+            repeating: false,
+        },
+    })
+}
+
+/// Resolves the gradient line of a linear gradient. The angle defaults to `to bottom`.
+fn linear<V: CssValue>(config: &[V], pos: Point, size: Size) -> Option<(GradientShape, FP)> {
+    let direction = match config {
+        [] => (0.0, 1.0),
+        [value] if value.as_string().is_none() => {
+            let angle = angle(value)?;
+            (angle.sin(), -angle.cos())
+        }
+        [to, sides @ ..] if to.as_string() == Some("to") && !sides.is_empty() && sides.len() <= 2 => {
+            let mut x = 0.0;
+            let mut y = 0.0;
+            for side in sides {
+                match side.as_string()? {
+                    "left" => x = -1.0,
+                    "right" => x = 1.0,
+                    "top" => y = -1.0,
+                    "bottom" => y = 1.0,
+                    _ => return None,
+                }
+            }
+
+            // Towards a corner, the gradient line is perpendicular to the diagonal between the two neighbouring
+            // corners, so those corners get the color at 50%
+            if x != 0.0 && y != 0.0 {
+                let (dx, dy) = (x * size.height, y * size.width);
+                let length = dx.hypot(dy);
+                if length == 0.0 {
+                    (0.0, y)
+                } else {
+                    (dx / length, dy / length)
+                }
+            } else {
+                (x, y)
+            }
+        }
+        _ => return None,
+    };
+
+    // The gradient line passes through the center and its ends are on the perpendicular lines through the corners
+    let length = (size.width * direction.0).abs() + (size.height * direction.1).abs();
+    let center = Point::new(pos.x + size.width / 2.0, pos.y + size.height / 2.0);
+    let half = (direction.0 * length / 2.0, direction.1 * length / 2.0);
+
+    let shape = GradientShape::Linear {
+        start: Point::new(center.x - half.0, center.y - half.1),
+        end: Point::new(center.x + half.0, center.y + half.1),
+    };
+
+    Some((shape, length))
+}
+
+/// Resolves the ending shape of a radial gradient. It defaults to an ellipse through the farthest corner.
+fn radial<V: CssValue>(config: &[V], pos: Point, size: Size) -> Option<(GradientShape, FP)> {
+    let (ending_shape, at) = match config.iter().position(|v| v.as_string() == Some("at")) {
+        Some(idx) => (&config[..idx], &config[idx + 1..]),
+        None => (config, &[][..]),
+    };
+    let center = position(at, pos, size)?;
+
+    let mut circle = None;
+    let mut extent = None;
+    let mut lengths = Vec::new();
+    for value in ending_shape {
+        match value.as_string() {
+            Some("circle") => circle = Some(true),
+            Some("ellipse") => circle = Some(false),
+            Some(keyword @ ("closest-side" | "farthest-side" | "closest-corner" | "farthest-corner")) => {
+                extent = Some(keyword);
+            }
+            Some(_) => return None,
+            None => lengths.push(value),
+        }
+    }
+    let circle = circle.unwrap_or(lengths.len() == 1);
+
+    // Distances from the center to the nearest and farthest sides of the box
+    let (cx, cy) = (center.x - pos.x, center.y - pos.y);
+    let (near_x, far_x) = (
+        cx.abs().min((size.width - cx).abs()),
+        cx.abs().max((size.width - cx).abs()),
+    );
+    let (near_y, far_y) = (
+        cy.abs().min((size.height - cy).abs()),
+        cy.abs().max((size.height - cy).abs()),
+    );
+
+    let (radius_x, radius_y) = match (lengths.as_slice(), circle) {
+        ([], true) => {
+            let radius = match extent.unwrap_or("farthest-corner") {
+                "closest-side" => near_x.min(near_y),
+                "farthest-side" => far_x.max(far_y),
+                "closest-corner" => near_x.hypot(near_y),
+                _ => far_x.hypot(far_y),
+            };
+            (radius, radius)
+        }
+        ([], false) => match extent.unwrap_or("farthest-corner") {
+            "closest-side" => (near_x, near_y),
+            "farthest-side" => (far_x, far_y),
+            // The ellipse through a corner has the aspect ratio of the closest-side ellipse
+            corner => {
+                let (x, y) = match corner {
+                    "closest-corner" => (near_x, near_y),
+                    _ => (far_x, far_y),
+                };
+                if near_x == 0.0 || near_y == 0.0 {
+                    (x.hypot(y), x.hypot(y))
+                } else {
+                    let ratio = near_y / near_x;
+                    let radius_x = (x.powi(2) + (y / ratio).powi(2)).sqrt();
+                    (radius_x, radius_x * ratio)
+                }
+            }
+        },
+        // Circles can not be sized with a percentage
+        ([radius], true) if extent.is_none() && radius.as_percentage().is_none() => {
+            let radius = length(*radius, 0.0)?;
+            (radius, radius)
+        }
+        ([x, y], false) if extent.is_none() => (length(*x, size.width)?, length(*y, size.height)?),
+        _ => return None,
+    };
+
+    // A degenerate ending shape is painted as a very small one
+    let radius_x = radius_x.max(0.01);
+    let radius_y = radius_y.max(0.01);
+
+    let shape = GradientShape::Radial {
+        center,
+        start_radius: 0.0,
+        end_radius: radius_x,
+        scale_y: radius_y / radius_x,
+    };
+
+    Some((shape, radius_x))
+}
+
+/// Resolves the center and starting angle of a conic gradient. Stop positions are fractions of a full turn.
+fn conic<V: CssValue>(config: &[V], pos: Point, size: Size) -> Option<(GradientShape, FP)> {
+    let mut from = 0.0;
+    let mut center = Point::new(pos.x + size.width / 2.0, pos.y + size.height / 2.0);
+
+    let mut idx = 0;
+    while idx < config.len() {
+        match config[idx].as_string() {
+            Some("from") => {
+                from = angle(config.get(idx + 1)?)?;
+                idx += 2;
+            }
+            Some("at") => {
+                center = position(&config[idx + 1..], pos, size)?;
+                break;
+            }
+            _ => return None,
+        }
+    }
+
+    // Conic gradients start at the top, the backends start at the positive x-axis
+    let start_angle = from - FRAC_PI_2;
+    let shape = GradientShape::Conic {
+        center,
+        start_angle,
+        end_angle: start_angle + TAU,
+    };
+
+    Some((shape, 1.0))
+}
+
+/// Resolves an angle in radians. A zero without unit is allowed as well.
+fn angle<V: CssValue>(value: &V) -> Option<FP> {
+    if let Some((value, unit)) = value.as_unit() {
+        return match unit {
+            "deg" => Some(value.to_radians()),
+            "rad" => Some(value),
+            "grad" => Some(value * TAU / 400.0),
+            "turn" => Some(value * TAU),
+            _ => None,
+        };
+    }
+
+    let is_zero = value.as_string().is_none()
+        && value.as_percentage().is_none()
+        && value.as_function().is_none()
+        && value.as_number().is_none_or(|number| number == 0.0)
+        && value.unit_to_px() == 0.0;
+
+    is_zero.then_some(0.0)
+}
+
+/// Fixes up the positions of the color stops: the first and last stop default to 0% and 100%, a stop can not be
+/// positioned before an earlier stop, and stops without position are spread evenly between their neighbours.
+/// Color hints are replaced with a stop that has the color halfway between its neighbours.
+fn fixup_stops(entries: Vec<StopEntry>) -> Option<Vec<GradientStop>> {
+    let count = entries
+        .iter()
+        .filter(|entry| matches!(entry, StopEntry::Stop(..)))
+        .count();
+    if count < 2 {
+        return None;
+    }
+    // A hint must be between two color stops
+    let is_hint = |entry: Option<&StopEntry>| matches!(entry, Some(StopEntry::Hint(_)) | None);
+    for (idx, entry) in entries.iter().enumerate() {
+        if matches!(entry, StopEntry::Hint(_))
+            && (idx == 0 || is_hint(entries.get(idx - 1)) || is_hint(entries.get(idx + 1)))
+        {
+            return None;
+        }
+    }
+
+    let mut positions = entries
+        .iter()
+        .map(|entry| match entry {
+            StopEntry::Stop(_, position) => *position,
+            StopEntry::Hint(position) => Some(*position),
+        })
+        .collect::<Vec<_>>();
+
+    let last = positions.len() - 1;
+    positions[0].get_or_insert(0.0);
+    positions[last].get_or_insert(1.0);
+
+    let mut max = FP::MIN;
+    for position in positions.iter_mut().flatten() {
+        *position = position.max(max);
+        max = *position;
+    }
+
+    let mut idx = 0;
+    while idx < positions.len() {
+        if positions[idx].is_some() {
+            idx += 1;
+            continue;
+        }
+
+        let start = idx - 1;
+        let end = (idx..positions.len()).find(|&idx| positions[idx].is_some())?;
+        let (from, to) = (positions[start]?, positions[end]?);
+        for (step, position) in positions[idx..end].iter_mut().enumerate() {
+            *position = Some(from + (to - from) * (step + 1) as FP / (end - start) as FP);
+        }
+        idx = end;
+    }
+
+    let mut stops = Vec::with_capacity(entries.len());
+    for (idx, entry) in entries.iter().enumerate() {
+        let offset = positions[idx]?;
+        match entry {
+            StopEntry::Stop(color, _) => stops.push(GradientStop { offset, color: *color }),
+            StopEntry::Hint(_) => {
+                let (StopEntry::Stop(before, _), StopEntry::Stop(after, _)) = (&entries[idx - 1], &entries[idx + 1])
+                else {
+                    return None;
+                };
+                stops.push(GradientStop {
+                    offset,
+                    color: mix(*before, *after, 0.5),
+                });
+            }
+        }
+    }
+
+    Some(stops)
+}
+
+/// Maps the stops of a repeating gradient to the range 0.0 to 1.0 by moving the start and end of the gradient to the
+/// first and last stop
+fn repeat(shape: GradientShape, stops: Vec<GradientStop>) -> Gradient {
+    let mut first = stops[0].offset;
+    let mut last = stops[stops.len() - 1].offset;
+    let span = last - first;
+
+    // Without a span, the gradient is painted with the average color. The last color is close enough.
+    if span <= 0.0 {
+        let color = stops[stops.len() - 1].color;
+        return Gradient {
+            shape,
+            stops: vec![GradientStop { offset: 0.0, color }, GradientStop { offset: 1.0, color }],
+            repeating: false,
+        };
+    }
+
+    let shape = match shape {
+        GradientShape::Linear { start, end } => {
+            let at = |offset: FP| {
+                Point::new(
+                    start.x + (end.x - start.x) * offset,
+                    start.y + (end.y - start.y) * offset,
+                )
+            };
+            GradientShape::Linear {
+                start: at(first),
+                end: at(last),
+            }
+        }
+        GradientShape::Radial {
+            center,
+            end_radius,
+            scale_y,
+            ..
+        } => {
+            // A radius can not be negative, moving the range by whole repetitions does not change the gradient
+            if first < 0.0 {
+                let shift = (-first / span).ceil() * span;
+                first += shift;
+                last += shift;
+            }
+            GradientShape::Radial {
+                center,
+                start_radius: end_radius * first,
+                end_radius: end_radius * last,
+                scale_y,
+            }
+        }
+        GradientShape::Conic {
+            center, start_angle, ..
+        } => GradientShape::Conic {
+            center,
+            start_angle: start_angle + TAU * first,
+            end_angle: start_angle + TAU * last,
+        },
+    };
+
+    let offset = stops[0].offset;
+    let stops = stops
+        .into_iter()
+        .map(|stop| GradientStop {
+            offset: (stop.offset - offset) / span,
+            color: stop.color,
+        })
+        .collect();
+
+    Gradient {
+        shape,
+        stops,
+        repeating: true,
+    }
+}
+
+/// Limits the stops to the range 0.0 to 1.0. Stops outside the range are replaced with a stop at the edge of the
+/// range with the color at that edge.
+fn clamp_stops(stops: &[GradientStop]) -> Vec<GradientStop> {
+    let first = stops[0].offset;
+    let last = stops[stops.len() - 1].offset;
+
+    let mut clamped = Vec::with_capacity(stops.len());
+    if first < 0.0 {
+        clamped.push(GradientStop {
+            offset: 0.0,
+            color: color_at(stops, 0.0),
+        });
+    }
+    clamped.extend(stops.iter().filter(|stop| (0.0..=1.0).contains(&stop.offset)));
+    if last > 1.0 {
+        clamped.push(GradientStop {
+            offset: 1.0,
+            color: color_at(stops, 1.0),
+        });
+    }
+
+    clamped
+}
+
+/// Returns the color at `offset`
+fn color_at(stops: &[GradientStop], offset: FP) -> Rgba {
+    for window in stops.windows(2) {
+        let (before, after) = (window[0], window[1]);
+        if offset <= before.offset {
+            return before.color;
+        }
+        if offset < after.offset {
+            let t = (offset - before.offset) / (after.offset - before.offset);
+            return mix(before.color, after.color, t);
+        }
+    }
+
+    stops[stops.len() - 1].color
+}
+
+fn mix(a: Rgba, b: Rgba, t: FP) -> Rgba {
+    (
+        a.0 + (b.0 - a.0) * t,
+        a.1 + (b.1 - a.1) * t,
+        a.2 + (b.2 - a.2) * t,
+        a.3 + (b.3 - a.3) * t,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{keyword, px, POS, SIZE};
+    use gosub_css3::stylesheet::CssValue as Value;
+    use std::f32::consts::SQRT_2;
+
+    fn resolve(function: &str, args: Vec<Value>) -> Option<Gradient> {
+        gradient(function, &args, POS, SIZE)
+    }
+
+    fn offsets(gradient: &Gradient) -> Vec<FP> {
+        gradient.stops.iter().map(|stop| stop.offset).collect()
+    }
+
+    const RED: Rgba = (255.0, 0.0, 0.0, 255.0);
+    const BLUE: Rgba = (0.0, 0.0, 255.0, 255.0);
+
+    #[test]
+    fn linear_gradient_direction() {
+        // Defaults to `to bottom`
+        let gradient = resolve("linear-gradient", vec![keyword("red"), Value::Comma, keyword("blue")]).unwrap();
+        assert_eq!(
+            gradient.shape,
+            GradientShape::Linear {
+                start: Point::new(110.0, 20.0),
+                end: Point::new(110.0, 120.0),
+            }
+        );
+        assert_eq!(
+            gradient.stops[0],
+            GradientStop {
+                offset: 0.0,
+                color: RED
+            }
+        );
+        assert_eq!(
+            gradient.stops[1],
+            GradientStop {
+                offset: 1.0,
+                color: BLUE
+            }
+        );
+
+        let gradient = resolve(
+            "linear-gradient",
+            vec![
+                Value::Unit(90.0, "deg".into()),
+                Value::Comma,
+                keyword("red"),
+                Value::Comma,
+                keyword("blue"),
+            ],
+        )
+        .unwrap();
+        let GradientShape::Linear { start, end } = gradient.shape else {
+            panic!("expected a linear gradient");
+        };
+        assert!((start.x - 10.0).abs() < 0.001 && (start.y - 70.0).abs() < 0.001);
+        assert!((end.x - 210.0).abs() < 0.001 && (end.y - 70.0).abs() < 0.001);
+
+        // Towards a corner, the neighbouring corners are on the 50% line
+        let gradient = resolve(
+            "linear-gradient",
+            vec![
+                keyword("to"),
+                keyword("top"),
+                keyword("right"),
+                Value::Comma,
+                keyword("red"),
+                Value::Comma,
+                keyword("blue"),
+            ],
+        )
+        .unwrap();
+        let GradientShape::Linear { start, end } = gradient.shape else {
+            panic!("expected a linear gradient");
+        };
+        let direction = (end.x - start.x, end.y - start.y);
+        let diagonal = (SIZE.width, SIZE.height);
+        assert!((direction.0 * diagonal.0 + direction.1 * diagonal.1).abs() < 0.001);
+        assert!(direction.0 > 0.0 && direction.1 < 0.0);
+    }
+
+    #[test]
+    fn color_stop_positions() {
+        let gradient = resolve(
+            "linear-gradient",
+            vec![
+                keyword("red"),
+                Value::Comma,
+                keyword("red"),
+                Value::Comma,
+                Value::Percentage(20.0),
+                Value::Comma,
+                keyword("blue"),
+                px(50.0),
+                Value::Percentage(80.0),
+                Value::Comma,
+                keyword("red"),
+                Value::Percentage(70.0),
+            ],
+        )
+        .unwrap();
+
+        // The hint becomes a stop with the color halfway, a stop can not be before an earlier stop
+        assert_eq!(offsets(&gradient), [0.0, 0.1, 0.2, 0.5, 0.8, 0.8]);
+        assert_eq!(gradient.stops[2].color, (127.5, 0.0, 127.5, 255.0));
+
+        // A hint must be between two color stops
+        assert!(resolve(
+            "linear-gradient",
+            vec![Value::Percentage(20.0), Value::Comma, keyword("red")]
+        )
+        .is_none());
+        assert!(resolve("linear-gradient", vec![keyword("red")]).is_none());
+    }
+
+    #[test]
+    fn radial_gradient_ending_shape() {
+        let gradient = resolve(
+            "radial-gradient",
+            vec![
+                keyword("circle"),
+                keyword("closest-side"),
+                keyword("at"),
+                px(50.0),
+                Value::Percentage(50.0),
+                Value::Comma,
+                keyword("red"),
+                Value::Comma,
+                keyword("blue"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            gradient.shape,
+            GradientShape::Radial {
+                center: Point::new(60.0, 70.0),
+                start_radius: 0.0,
+                end_radius: 50.0,
+                scale_y: 1.0,
+            }
+        );
+
+        // Ellipse through the farthest corner with the aspect ratio of the box
+        let gradient = resolve("radial-gradient", vec![keyword("red"), Value::Comma, keyword("blue")]).unwrap();
+        let GradientShape::Radial {
+            end_radius, scale_y, ..
+        } = gradient.shape
+        else {
+            panic!("expected a radial gradient");
+        };
+        assert!((end_radius - 100.0 * SQRT_2).abs() < 0.001);
+        assert!((scale_y - 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn repeating_and_conic_gradients() {
+        let gradient = resolve(
+            "repeating-linear-gradient",
+            vec![keyword("red"), px(10.0), Value::Comma, keyword("blue"), px(30.0)],
+        )
+        .unwrap();
+        assert!(gradient.repeating);
+        assert_eq!(offsets(&gradient), [0.0, 1.0]);
+        assert_eq!(
+            gradient.shape,
+            GradientShape::Linear {
+                start: Point::new(110.0, 30.0),
+                end: Point::new(110.0, 50.0),
+            }
+        );
+
+        let gradient = resolve(
+            "conic-gradient",
+            vec![
+                keyword("from"),
+                Value::Unit(0.25, "turn".into()),
+                Value::Comma,
+                keyword("red"),
+                Value::Comma,
+                keyword("blue"),
+                Value::Unit(180.0, "deg".into()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(offsets(&gradient), [0.0, 0.5]);
+        let GradientShape::Conic {
+            center,
+            start_angle,
+            end_angle,
+        } = gradient.shape
+        else {
+            panic!("expected a conic gradient");
+        };
+        assert_eq!(center, Point::new(110.0, 70.0));
+        assert!(start_angle.abs() < 0.001);
+        assert!((end_angle - TAU).abs() < 0.001);
+    }
+}
@@ -0,0 +1,229 @@
+//! Rounded corners of a box. The `border-*-radius` longhands are resolved against the border box and scaled down
+//! when the curves of neighbouring corners would overlap. The same radii clip the background, the border, images and
+//! the descendants of the box, and are used by hit testing.
+
+use crate::background::Area;
+use gosub_interface::css3::{CssProperty, CssPropertyMap, CssSystem, CssValue};
+use gosub_shared::geo::{Point, Size, FP};
+
+/// Horizontal and vertical radius of a corner
+pub type Corner = (FP, FP);
+
+/// Radii of the four corners of a box
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BorderRadii {
+    pub top_left: Corner,
+    pub top_right: Corner,
+    pub bottom_right: Corner,
+    pub bottom_left: Corner,
+}
+
+impl BorderRadii {
+    /// Returns true when none of the corners is rounded
+    #[must_use]
+    pub fn is_zero(&self) -> bool {
+        [self.top_left, self.top_right, self.bottom_right, self.bottom_left]
+            .iter()
+            .all(|corner| corner.0 <= 0.0 || corner.1 <= 0.0)
+    }
+
+    /// Returns the radii with `f` applied to both radii of every corner
+    #[must_use]
+    pub fn map(self, f: impl Fn(FP) -> FP) -> Self {
+        let corner = |corner: Corner| (f(corner.0), f(corner.1));
+
+        Self {
+            top_left: corner(self.top_left),
+            top_right: corner(self.top_right),
+            bottom_right: corner(self.bottom_right),
+            bottom_left: corner(self.bottom_left),
+        }
+    }
+
+    /// Returns the radii of a box inside the box with these radii, like the padding box inside the border box. The
+    /// inner curves follow the outer curves, so each radius shrinks by the distance between the edges of the boxes.
+    #[must_use]
+    pub fn inner(&self, outer: Area, inner: Area) -> Self {
+        let left = inner.pos.x - outer.pos.x;
+        let top = inner.pos.y - outer.pos.y;
+        let right = (outer.pos.x + outer.size.width) - (inner.pos.x + inner.size.width);
+        let bottom = (outer.pos.y + outer.size.height) - (inner.pos.y + inner.size.height);
+
+        let corner = |corner: Corner, x: FP, y: FP| ((corner.0 - x).max(0.0), (corner.1 - y).max(0.0));
+
+        Self {
+            top_left: corner(self.top_left, left, top),
+            top_right: corner(self.top_right, right, top),
+            bottom_right: corner(self.bottom_right, right, bottom),
+            bottom_left: corner(self.bottom_left, left, bottom),
+        }
+    }
+
+    /// Returns true when the point is inside the box with these rounded corners
+    #[must_use]
+    pub fn contains(&self, area: Area, point: Point) -> bool {
+        let (left, top) = (area.pos.x, area.pos.y);
+        let (right, bottom) = (left + area.size.width, top + area.size.height);
+
+        if point.x < left || point.x > right || point.y < top || point.y > bottom {
+            return false;
+        }
+
+        // The center of the ellipse of each corner, and the quadrant of the ellipse that forms the corner
+        let corners = [
+            (self.top_left, left + self.top_left.0, top + self.top_left.1, -1.0, -1.0),
+            (
+                self.top_right,
+                right - self.top_right.0,
+                top + self.top_right.1,
+                1.0,
+                -1.0,
+            ),
+            (
+                self.bottom_right,
+                right - self.bottom_right.0,
+                bottom - self.bottom_right.1,
+                1.0,
+                1.0,
+            ),
+            (
+                self.bottom_left,
+                left + self.bottom_left.0,
+                bottom - self.bottom_left.1,
+                -1.0,
+                1.0,
+            ),
+        ];
+
+        corners.iter().all(|&((rx, ry), cx, cy, dir_x, dir_y)| {
+            let dx = point.x - cx;
+            let dy = point.y - cy;
+
+            // Only points beyond the center of the corner, in its quadrant, can be outside of the curve
+            if rx <= 0.0 || ry <= 0.0 || dx * dir_x <= 0.0 || dy * dir_y <= 0.0 {
+                return true;
+            }
+
+            (dx / rx).powi(2) + (dy / ry).powi(2) <= 1.0
+        })
+    }
+}
+
+/// Returns the radii of the corners of an element with a border box of `size`. Percentages resolve against the
+/// width for horizontal radii and against the height for vertical radii.
+pub fn border_radii<S: CssSystem>(props: &S::PropertyMap, size: Size) -> BorderRadii {
+    let corner = |name: &str| -> Corner {
+        let Some(prop) = props.get(name) else {
+            return (0.0, 0.0);
+        };
+
+        if let Some(list) = prop.as_list() {
+            let x = list.first().map_or(0.0, |x| length(x, size.width));
+            let y = list.get(1).map_or(x, |y| length(y, size.height));
+            return (x, y);
+        }
+
+        match prop.as_percentage() {
+            Some(percentage) => (size.width * percentage / 100.0, size.height * percentage / 100.0),
+            None => (prop.unit_to_px().max(0.0), prop.unit_to_px().max(0.0)),
+        }
+    };
+
+    let radii = BorderRadii {
+        top_left: corner("border-top-left-radius"),
+        top_right: corner("border-top-right-radius"),
+        bottom_right: corner("border-bottom-right-radius"),
+        bottom_left: corner("border-bottom-left-radius"),
+    };
+
+    // When the radii of two corners on the same side are larger than the side, all radii are scaled down by the
+    // same factor
+    let factor = [
+        (size.width, radii.top_left.0 + radii.top_right.0),
+        (size.width, radii.bottom_left.0 + radii.bottom_right.0),
+        (size.height, radii.top_left.1 + radii.bottom_left.1),
+        (size.height, radii.top_right.1 + radii.bottom_right.1),
+    ]
+    .iter()
+    .filter(|(_, sum)| *sum > 0.0)
+    .fold(1.0, |factor: FP, (side, sum)| factor.min(side / sum));
+
+    if factor < 1.0 {
+        radii.map(|radius| radius * factor.max(0.0))
+    } else {
+        radii
+    }
+}
+
+fn length<V: CssValue>(value: &V, basis: FP) -> FP {
+    match value.as_percentage() {
+        Some(percentage) => basis * percentage / 100.0,
+        None => value.unit_to_px().max(0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::props;
+    use gosub_css3::system::Css3System;
+
+    #[test]
+    fn longhands_and_percentages() {
+        let map = props(&[
+            ("border-top-left-radius", "10px 20px"),
+            ("border-top-right-radius", "10%"),
+            ("border-bottom-right-radius", "5% 4px"),
+        ]);
+
+        let radii = border_radii::<Css3System>(&map, Size::new(200.0, 100.0));
+        assert_eq!(
+            radii,
+            BorderRadii {
+                top_left: (10.0, 20.0),
+                top_right: (20.0, 10.0),
+                bottom_right: (10.0, 4.0),
+                bottom_left: (0.0, 0.0),
+            }
+        );
+    }
+
+    #[test]
+    fn overlapping_radii_scale_down() {
+        // The left side is 100px high, but the corners on it add up to 200px
+        let map = props(&[
+            ("border-top-left-radius", "150px"),
+            ("border-bottom-left-radius", "50px"),
+            ("border-top-right-radius", "20px"),
+        ]);
+
+        let radii = border_radii::<Css3System>(&map, Size::new(400.0, 100.0));
+        assert_eq!(radii.top_left, (75.0, 75.0));
+        assert_eq!(radii.bottom_left, (25.0, 25.0));
+        assert_eq!(radii.top_right, (10.0, 10.0));
+    }
+
+    #[test]
+    fn inner_radii_and_contains() {
+        let radii = BorderRadii {
+            top_left: (20.0, 10.0),
+            top_right: (5.0, 5.0),
+            bottom_right: (0.0, 0.0),
+            bottom_left: (10.0, 10.0),
+        };
+        let outer = Area::new(Point::new(0.0, 0.0), Size::new(100.0, 50.0));
+        let inner = Area::new(Point::new(8.0, 4.0), Size::new(88.0, 42.0));
+
+        let inner_radii = radii.inner(outer, inner);
+        assert_eq!(inner_radii.top_left, (12.0, 6.0));
+        assert_eq!(inner_radii.top_right, (1.0, 1.0));
+        assert_eq!(inner_radii.bottom_left, (2.0, 6.0));
+
+        assert!(radii.contains(outer, Point::new(50.0, 25.0)));
+        assert!(radii.contains(outer, Point::new(99.0, 49.0)));
+        // Outside of the curve of the top left corner, but inside its bounding box
+        assert!(!radii.contains(outer, Point::new(2.0, 2.0)));
+        assert!(radii.contains(outer, Point::new(20.0, 1.0)));
+        assert!(!radii.contains(outer, Point::new(101.0, 25.0)));
+    }
+}
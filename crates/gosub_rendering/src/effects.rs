@@ -0,0 +1,259 @@
+//! Visual effects of an element that are painted through a layer: `opacity`, `mix-blend-mode`, `clip-path` and
+//! clipping of overflowing content. The values are resolved here against the boxes of the element, the backend
+//! specific layers are created by the renderer.
+
+use gosub_interface::css3::{CssProperty, CssPropertyMap, CssSystem, CssValue};
+use gosub_shared::geo::{Point, Size, FP};
+
+/// Area that remains visible of an element with a `clip-path` basic shape
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipPath {
+    /// Rectangle with uniformly rounded corners (`inset()`)
+    Rect {
+        x: FP,
+        y: FP,
+        width: FP,
+        height: FP,
+        radius: FP,
+    },
+    /// Ellipse (`circle()` and `ellipse()`)
+    Ellipse { center: Point, radius_x: FP, radius_y: FP },
+}
+
+/// Returns the opacity of the element in the range 0.0 to 1.0
+pub fn opacity<S: CssSystem>(props: &S::PropertyMap) -> FP {
+    let Some(prop) = props.get("opacity") else {
+        return 1.0;
+    };
+
+    let opacity = prop
+        .as_percentage()
+        .map(|p| p / 100.0)
+        .or_else(|| prop.as_number())
+        .unwrap_or(1.0);
+
+    opacity.clamp(0.0, 1.0)
+}
+
+/// Returns true when content overflowing the padding box of the element is clipped. When one axis is not
+/// `visible`, the other axis can not be visible either, so both axes are clipped.
+pub fn clips_overflow<S: CssSystem>(props: &S::PropertyMap) -> bool {
+    ["overflow-x", "overflow-y"].iter().any(|name| {
+        props
+            .get(name)
+            .and_then(|prop| prop.as_string())
+            .is_some_and(|value| value != "visible")
+    })
+}
+
+/// Resolves a `clip-path` basic shape against the border box of the element at `pos`. Returns None when the
+/// element is not clipped, or when the shape is not supported.
+pub fn clip_path<S: CssSystem>(prop: &S::Property, pos: Point, size: Size) -> Option<ClipPath> {
+    let (function, args) = prop.as_function()?;
+
+    match function {
+        "inset" => inset(args, pos, size),
+        "circle" => {
+            let (radii, center) = split_at(args);
+            let center = position(center, pos, size)?;
+
+            // A percentage resolves against the normalized diagonal of the box
+            let diagonal = (size.width.powi(2) + size.height.powi(2)).sqrt() / FP::sqrt(2.0);
+            let radius = match radii.first() {
+                None => shape_radius(None, center.x - pos.x, size.width).min(shape_radius(
+                    None,
+                    center.y - pos.y,
+                    size.height,
+                )),
+                Some(value) => match value.as_string() {
+                    Some(keyword) => {
+                        let x = shape_radius(Some(keyword), center.x - pos.x, size.width);
+                        let y = shape_radius(Some(keyword), center.y - pos.y, size.height);
+                        if keyword == "farthest-side" {
+                            x.max(y)
+                        } else {
+                            x.min(y)
+                        }
+                    }
+                    None => length(value, diagonal)?,
+                },
+            };
+
+            Some(ClipPath::Ellipse {
+                center,
+                radius_x: radius,
+                radius_y: radius,
+            })
+        }
+        "ellipse" => {
+            let (radii, center) = split_at(args);
+            let center = position(center, pos, size)?;
+
+            let radius = |value: Option<&S::Value>, offset: FP, basis: FP| match value {
+                None => Some(shape_radius(None, offset, basis)),
+                Some(value) => match value.as_string() {
+                    Some(keyword) => Some(shape_radius(Some(keyword), offset, basis)),
+                    None => length(value, basis),
+                },
+            };
+
+            Some(ClipPath::Ellipse {
+                center,
+                radius_x: radius(radii.first(), center.x - pos.x, size.width)?,
+                radius_y: radius(radii.get(1), center.y - pos.y, size.height)?,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn inset<V: CssValue>(args: &[V], pos: Point, size: Size) -> Option<ClipPath> {
+    let round = args.iter().position(|v| v.as_string() == Some("round"));
+    let (offsets, radius) = match round {
+        Some(idx) => (&args[..idx], args.get(idx + 1)),
+        None => (args, None),
+    };
+
+    // Offsets follow the order of the `margin` shorthand
+    let offset = |idx: usize, basis: FP| offsets.get(idx).and_then(|v| length(v, basis));
+    let top = offset(0, size.height)?;
+    let right = offset(1, size.width).unwrap_or(top);
+    let bottom = offset(2, size.height).unwrap_or(top);
+    let left = offset(3, size.width).unwrap_or(right);
+
+    let width = (size.width - left - right).max(0.0);
+    let height = (size.height - top - bottom).max(0.0);
+
+    Some(ClipPath::Rect {
+        x: pos.x + left,
+        y: pos.y + top,
+        width,
+        height,
+        radius: radius.and_then(|r| length(r, width)).unwrap_or(0.0),
+    })
+}
+
+/// Splits the arguments of a shape function into the radii and the position after `at`
+fn split_at<V: CssValue>(args: &[V]) -> (&[V], &[V]) {
+    match args.iter().position(|v| v.as_string() == Some("at")) {
+        Some(idx) => (&args[..idx], &args[idx + 1..]),
+        None => (args, &[]),
+    }
+}
+
+/// Resolves the center of a shape. Without a position, the shape is centered in the box.
+pub(crate) fn position<V: CssValue>(args: &[V], pos: Point, size: Size) -> Option<Point> {
+    let mut x = None;
+    let mut y = None;
+
+    for (idx, value) in args.iter().take(2).enumerate() {
+        match value.as_string() {
+            Some("left") => x = Some(0.0),
+            Some("right") => x = Some(size.width),
+            Some("top") => y = Some(0.0),
+            Some("bottom") => y = Some(size.height),
+            Some("center") => {}
+            Some(_) => return None,
+            None if idx == 0 => x = Some(length(value, size.width)?),
+            None => y = Some(length(value, size.height)?),
+        }
+    }
+
+    Some(Point::new(
+        pos.x + x.unwrap_or(size.width / 2.0),
+        pos.y + y.unwrap_or(size.height / 2.0),
+    ))
+}
+
+/// Resolves `closest-side` (the default) and `farthest-side` along one axis
+fn shape_radius(keyword: Option<&str>, offset: FP, basis: FP) -> FP {
+    let near = offset.abs().min((basis - offset).abs());
+    let far = offset.abs().max((basis - offset).abs());
+
+    match keyword {
+        Some("farthest-side") => far,
+        _ => near,
+    }
+}
+
+/// Resolves a length or a percentage of `basis`. Keywords are not lengths.
+pub(crate) fn length<V: CssValue>(value: &V, basis: FP) -> Option<FP> {
+    if let Some(percentage) = value.as_percentage() {
+        return Some(basis * percentage / 100.0);
+    }
+    if value.as_string().is_some() {
+        return None;
+    }
+
+    Some(value.unit_to_px())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{keyword, px, POS, SIZE};
+    use gosub_css3::stylesheet::CssValue as Value;
+    use gosub_css3::system::Css3System;
+
+    type Property = <Css3System as CssSystem>::Property;
+
+    fn function(name: &str, args: Vec<Value>) -> Property {
+        Property::from(Value::Function(name.to_string(), args))
+    }
+
+    #[test]
+    fn clip_path_inset() {
+        let prop = function(
+            "inset",
+            vec![px(10.0), Value::Percentage(10.0), keyword("round"), px(4.0)],
+        );
+
+        assert_eq!(
+            clip_path::<Css3System>(&prop, POS, SIZE),
+            Some(ClipPath::Rect {
+                x: 30.0,
+                y: 30.0,
+                width: 160.0,
+                height: 80.0,
+                radius: 4.0,
+            })
+        );
+    }
+
+    #[test]
+    fn clip_path_circle_and_ellipse() {
+        let prop = function("circle", vec![px(20.0), keyword("at"), keyword("left"), px(10.0)]);
+        assert_eq!(
+            clip_path::<Css3System>(&prop, POS, SIZE),
+            Some(ClipPath::Ellipse {
+                center: Point::new(10.0, 30.0),
+                radius_x: 20.0,
+                radius_y: 20.0,
+            })
+        );
+
+        // Closest side of a centered circle
+        let prop = function("circle", vec![]);
+        assert_eq!(
+            clip_path::<Css3System>(&prop, POS, SIZE),
+            Some(ClipPath::Ellipse {
+                center: Point::new(110.0, 70.0),
+                radius_x: 50.0,
+                radius_y: 50.0,
+            })
+        );
+
+        let prop = function("ellipse", vec![Value::Percentage(50.0), keyword("farthest-side")]);
+        assert_eq!(
+            clip_path::<Css3System>(&prop, POS, SIZE),
+            Some(ClipPath::Ellipse {
+                center: Point::new(110.0, 70.0),
+                radius_x: 100.0,
+                radius_y: 50.0,
+            })
+        );
+
+        let prop = function("polygon", vec![]);
+        assert_eq!(clip_path::<Css3System>(&prop, POS, SIZE), None);
+    }
+}
@@ -0,0 +1,152 @@
+//! Find in page. The query is searched in the text of every text node, which is the same text a selection is made
+//! in, so a match does not run across the boundary of a text node.
+
+use std::ops::Range;
+
+use gosub_interface::config::HasLayouter;
+use gosub_interface::layout::LayoutTree;
+
+use crate::selection::{node_text, text_nodes};
+
+/// Occurrence of the query in the text of a text node
+#[derive(Debug, Clone, PartialEq)]
+pub struct FindMatch<Id> {
+    pub node: Id,
+    /// Byte range in the text of the node
+    pub range: Range<usize>,
+}
+
+/// Matches of a query on the page, of which one is active
+#[derive(Debug, Clone)]
+pub struct FindState<Id> {
+    pub query: String,
+    pub case_sensitive: bool,
+    /// Matches in document order
+    pub matches: Vec<FindMatch<Id>>,
+    /// Index of the active match, which is `None` when there are no matches
+    pub active: Option<usize>,
+}
+
+impl<Id: Copy + PartialEq + Into<u64>> FindState<Id> {
+    /// Searches the page for the query. When the query is the one that was searched for before, the next (or
+    /// previous) match becomes active, otherwise the first (or last) one.
+    pub fn search<C: HasLayouter<LayoutTree: LayoutTree<C, NodeId = Id>>>(
+        tree: &C::LayoutTree,
+        query: &str,
+        case_sensitive: bool,
+        forward: bool,
+        previous: Option<&Self>,
+    ) -> Self {
+        let matches = text_nodes::<C>(tree)
+            .into_iter()
+            .flat_map(|node| {
+                find_in_text(&node_text::<C>(tree, node), query, case_sensitive)
+                    .into_iter()
+                    .map(move |range| FindMatch { node, range })
+            })
+            .collect::<Vec<_>>();
+
+        let active = previous
+            .filter(|previous| previous.query == query && previous.case_sensitive == case_sensitive)
+            .and_then(|previous| previous.active);
+
+        Self {
+            query: query.to_owned(),
+            case_sensitive,
+            active: next_active(active, matches.len(), forward),
+            matches,
+        }
+    }
+
+    /// Returns the active match
+    #[must_use]
+    pub fn active_match(&self) -> Option<&FindMatch<Id>> {
+        self.active.and_then(|idx| self.matches.get(idx))
+    }
+}
+
+/// Returns the byte ranges of the occurrences of the query in the text. Occurrences do not overlap.
+#[must_use]
+pub fn find_in_text(text: &str, query: &str, case_sensitive: bool) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    if query.is_empty() {
+        return ranges;
+    }
+
+    let mut from = 0;
+    while from < text.len() {
+        if let Some(end) = match_at(&text[from..], query, case_sensitive) {
+            ranges.push(from..from + end);
+            from += end;
+        } else {
+            from += text[from..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+
+    ranges
+}
+
+/// Returns the index after the match to become active, which wraps around at the ends. When nothing was active, the
+/// first match is taken going forward and the last going backward.
+#[must_use]
+pub fn next_active(active: Option<usize>, count: usize, forward: bool) -> Option<usize> {
+    if count == 0 {
+        return None;
+    }
+
+    Some(match (active, forward) {
+        (None, true) => 0,
+        (None, false) => count - 1,
+        (Some(idx), true) => (idx + 1) % count,
+        (Some(idx), false) => (idx.min(count) + count - 1) % count,
+    })
+}
+
+/// Returns the length in bytes of the text the query matches at the start of `text`
+fn match_at(text: &str, query: &str, case_sensitive: bool) -> Option<usize> {
+    let mut chars = text.char_indices();
+
+    for q in query.chars() {
+        let (_, c) = chars.next()?;
+
+        let equal = if case_sensitive {
+            c == q
+        } else {
+            c == q || c.to_lowercase().eq(q.to_lowercase())
+        };
+        if !equal {
+            return None;
+        }
+    }
+
+    Some(chars.next().map_or(text.len(), |(idx, _)| idx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn occurrences_in_text() {
+        assert_eq!(find_in_text("Foo foo FOO", "foo", true), vec![4..7]);
+        assert_eq!(find_in_text("Foo foo FOO", "foo", false), vec![0..3, 4..7, 8..11]);
+        assert_eq!(find_in_text("aaaa", "aa", true), vec![0..2, 2..4]);
+        assert_eq!(find_in_text("Straße STRASSE", "straße", false), vec![0..7]);
+        assert_eq!(find_in_text("ÉCOLE école", "école", false), vec![0..6, 7..13]);
+        assert!(find_in_text("foo", "", false).is_empty());
+        assert!(find_in_text("fo", "foo", false).is_empty());
+    }
+
+    #[test]
+    fn active_match_wraps_around() {
+        assert_eq!(next_active(None, 0, true), None);
+        assert_eq!(next_active(None, 3, true), Some(0));
+        assert_eq!(next_active(None, 3, false), Some(2));
+        assert_eq!(next_active(Some(2), 3, true), Some(0));
+        assert_eq!(next_active(Some(0), 3, false), Some(2));
+        assert_eq!(next_active(Some(1), 3, false), Some(0));
+        // Matches have disappeared since the last search
+        assert_eq!(next_active(Some(5), 3, true), Some(0));
+        assert_eq!(next_active(Some(5), 3, false), Some(2));
+    }
+}
@@ -3,6 +3,18 @@
 //! This crate supplies functionality to render CSSOM and DOM trees into a viewable display.
 //!
 
+pub mod background;
+pub mod border_radius;
+pub mod effects;
+pub mod find;
+pub mod outline;
 pub mod position;
+pub mod positioning;
 // pub mod macos_render_tree;
 pub mod render_tree;
+pub mod scroll;
+pub mod selection;
+pub mod shadow;
+#[cfg(test)]
+mod testing;
+pub mod transform;
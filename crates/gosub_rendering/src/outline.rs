@@ -0,0 +1,175 @@
+//! Outlines of elements: `outline` and the focus ring. An outline is drawn around the border box, `outline-offset`
+//! away from it, and takes up no space. The renderer paints it on top of the content of the page.
+
+use crate::background::{Area, Rgba};
+use crate::border_radius::BorderRadii;
+use crate::selection::NodeId;
+use gosub_interface::config::HasLayouter;
+use gosub_interface::css3::{CssProperty, CssPropertyMap, CssSystem};
+use gosub_interface::layout::{Layout, LayoutTree};
+use gosub_shared::geo::{Point, Size, FP};
+
+const BLACK: Rgba = (0.0, 0.0, 0.0, 255.0);
+/// Color of the focus ring (`outline-style: auto`)
+pub const FOCUS_RING_COLOR: Rgba = (16.0, 95.0, 204.0, 255.0);
+/// Width of the focus ring (`outline-style: auto`)
+const FOCUS_RING_WIDTH: FP = 2.0;
+
+/// Outline of an element
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outline {
+    pub width: FP,
+    /// Line style, like the style of a border. The focus ring is drawn solid.
+    pub style: String,
+    pub color: Rgba,
+    pub offset: FP,
+}
+
+impl Outline {
+    /// Returns the area the outline is drawn in: the border box grown by the offset and the width
+    #[must_use]
+    pub fn area(&self, border_box: Area) -> Area {
+        let grow = self.offset + self.width;
+
+        Area::new(
+            Point::new(border_box.pos.x - grow, border_box.pos.y - grow),
+            Size::new(
+                (border_box.size.width + 2.0 * grow).max(0.0),
+                (border_box.size.height + 2.0 * grow).max(0.0),
+            ),
+        )
+    }
+
+    /// Returns the radii of the corners of the outline, which follow the rounded corners of the border box
+    #[must_use]
+    pub fn radii(&self, radii: BorderRadii) -> BorderRadii {
+        let grow = self.offset + self.width;
+
+        radii.map(|radius| if radius > 0.0 { (radius + grow).max(0.0) } else { 0.0 })
+    }
+}
+
+/// Returns the outline of an element, or None when it has no visible outline
+pub fn outline<S: CssSystem>(props: &S::PropertyMap) -> Option<Outline> {
+    let style = props.get("outline-style")?.as_string()?;
+    if style == "none" || style == "hidden" {
+        return None;
+    }
+
+    let auto = style == "auto";
+    let color = props
+        .get("outline-color")
+        .and_then(CssProperty::parse_color)
+        .or(auto.then_some(FOCUS_RING_COLOR))
+        .or_else(|| props.get("color").and_then(CssProperty::parse_color))
+        .unwrap_or(BLACK);
+
+    let width = match props.get("outline-width") {
+        Some(prop) => match prop.as_string() {
+            Some("thin") => 1.0,
+            Some("medium") => 3.0,
+            Some("thick") => 5.0,
+            _ => prop.unit_to_px(),
+        },
+        None if auto => FOCUS_RING_WIDTH,
+        None => 3.0,
+    };
+    if width <= 0.0 {
+        return None;
+    }
+
+    Some(Outline {
+        // The focus ring is never thinner than its default width
+        width: if auto { width.max(FOCUS_RING_WIDTH) } else { width },
+        style: if auto { "solid".to_owned() } else { style.to_owned() },
+        color,
+        offset: props.get("outline-offset").map_or(0.0, CssProperty::unit_to_px),
+    })
+}
+
+/// Returns the box the outline of an element goes around, relative to the position of the element. An inline element
+/// that is laid out as part of the text around it has no box of its own, so its outline goes around the boxes of its
+/// descendants instead.
+pub fn outline_box<C: HasLayouter>(tree: &C::LayoutTree, id: NodeId<C>) -> Option<Area> {
+    let size = tree.get_layout(id)?.size();
+    if size.width > 0.0 && size.height > 0.0 {
+        return Some(Area::new(Point::ZERO, size));
+    }
+
+    let mut bounds: Option<(Point, Point)> = None;
+    let mut stack = tree
+        .children(id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|child| (child, Point::ZERO))
+        .collect::<Vec<_>>();
+
+    while let Some((id, parent)) = stack.pop() {
+        let Some(layout) = tree.get_layout(id) else {
+            continue;
+        };
+        let rel = layout.rel_pos();
+        let pos = Point::new(parent.x + rel.x, parent.y + rel.y);
+        let size = layout.size();
+
+        if size.width > 0.0 && size.height > 0.0 {
+            let (min, max) = bounds.get_or_insert((pos, pos));
+            min.x = min.x.min(pos.x);
+            min.y = min.y.min(pos.y);
+            max.x = max.x.max(pos.x + size.width);
+            max.y = max.y.max(pos.y + size.height);
+        }
+
+        stack.extend(
+            tree.children(id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|child| (child, pos)),
+        );
+    }
+
+    bounds.map(|(min, max)| Area::new(min, Size::new(max.x - min.x, max.y - min.y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::props;
+    use gosub_css3::system::Css3System;
+
+    #[test]
+    fn outline_properties() {
+        assert_eq!(outline::<Css3System>(&props(&[])), None);
+        assert_eq!(outline::<Css3System>(&props(&[("outline-style", "none")])), None);
+
+        let map = props(&[
+            ("outline-style", "dashed"),
+            ("outline-width", "4px"),
+            ("outline-offset", "2px"),
+            ("color", "red"),
+        ]);
+        let dashed = outline::<Css3System>(&map).unwrap();
+        assert_eq!(
+            dashed,
+            Outline {
+                width: 4.0,
+                style: "dashed".into(),
+                color: (255.0, 0.0, 0.0, 255.0),
+                offset: 2.0,
+            }
+        );
+
+        let border_box = Area::new(Point::new(10.0, 10.0), Size::new(100.0, 20.0));
+        assert_eq!(
+            dashed.area(border_box),
+            Area::new(Point::new(4.0, 4.0), Size::new(112.0, 32.0))
+        );
+
+        let auto = props(&[("outline-style", "auto"), ("outline-width", "thin")]);
+        let ring = outline::<Css3System>(&auto).unwrap();
+        assert_eq!(
+            (ring.width, ring.style.as_str(), ring.color),
+            (2.0, "solid", FOCUS_RING_COLOR)
+        );
+    }
+}
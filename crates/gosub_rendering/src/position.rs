@@ -1,9 +1,58 @@
-use std::cmp::Ordering;
-
-use rstar::{RTree, RTreeObject, AABB};
+use rstar::{RTree, RTreeObject, SelectionFunction, AABB};
+use std::collections::{HashMap, HashSet};
 
 use gosub_interface::config::HasLayouter;
 use gosub_interface::layout::{Layout, LayoutTree};
+use gosub_shared::geo::{Point, Size};
+
+use crate::background::Area;
+use crate::border_radius::BorderRadii;
+use crate::positioning::PaintLayer;
+use crate::transform::{apply, invert, multiply, Matrix, IDENTITY};
+
+/// Placement of a node: its border box on the page, its shape, the transform of its ancestors and the clips of their
+/// overflow
+type Placement = ((Point, Size, HitShape), Option<Matrix>, Vec<Clip>);
+
+/// Area an ancestor clips its descendants to, on the page, and the inverse of the transform it is painted with
+type Clip = (Area, Option<Matrix>);
+
+/// Elements of the positioned descendants of a stacking context and their layer, in tree order
+type Layered<C> = Vec<(PaintLayer, Vec<Element<C>>)>;
+
+/// Shape of a node that hit testing needs besides its layout
+#[derive(Debug, Clone, Copy)]
+pub struct HitShape {
+    /// Transform of the node in page coordinates (see `transform::transform`)
+    pub transform: Option<Matrix>,
+    /// Radii of the rounded corners of the border box
+    pub radii: BorderRadii,
+    /// Offset of the node and its descendants from their place in the layout, like the scroll offset of a fixed
+    /// element
+    pub offset: Point,
+    /// Scroll offset of a scroll container, which moves its descendants but not the node itself
+    pub scroll: Point,
+    /// Area the node clips its descendants to, relative to its border box
+    pub clip: Option<Area>,
+    /// Layer of the node in the painting order of its stacking context
+    pub layer: PaintLayer,
+    /// Whether the node starts a stacking context, which its positioned descendants are ordered in
+    pub stacking_context: bool,
+}
+
+impl Default for HitShape {
+    fn default() -> Self {
+        Self {
+            transform: None,
+            radii: BorderRadii::default(),
+            offset: Point::ZERO,
+            scroll: Point::ZERO,
+            clip: None,
+            layer: PaintLayer::default(),
+            stacking_context: false,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Element<C: HasLayouter> {
@@ -12,16 +61,55 @@ pub struct Element<C: HasLayouter> {
     y: f32,
     width: f32,
     height: f32,
-    radius: Option<(f32, f32, f32, f32)>,
-    z_index: i32,
+    radii: BorderRadii,
+    /// Position of the element in the painting order. Elements that are painted later are on top.
+    order: usize,
+    /// Maps page coordinates into the untransformed box of a transformed element
+    inverse: Option<Matrix>,
+    /// Bounding box of the element on the page, after it is transformed
+    bounds: AABB<(f32, f32)>,
+    /// Clips of the ancestors that clip their overflow. The element can only be hit inside all of them.
+    clips: Vec<Clip>,
+    /// Whether the element is painted in a layer of its stacking context, away from the elements around it
+    layered: bool,
+}
+
+impl<C: HasLayouter> Element<C> {
+    /// Returns true when the point on the page is inside the (rounded) border box of the element
+    fn contains(&self, x: f32, y: f32) -> bool {
+        let clipped = self.clips.iter().any(|(area, inverse)| {
+            !area.contains(inverse.map_or(Point::new(x, y), |inverse| apply(inverse, Point::new(x, y))))
+        });
+        if clipped {
+            return false;
+        }
+
+        let point = self
+            .inverse
+            .map_or(Point::new(x, y), |inverse| apply(inverse, Point::new(x, y)));
+        let area = Area::new(Point::new(self.x, self.y), Size::new(self.width, self.height));
+
+        self.radii.contains(area, point)
+    }
 }
 
 impl<C: HasLayouter> RTreeObject for Element<C> {
     type Envelope = AABB<(f32, f32)>;
     fn envelope(&self) -> Self::Envelope {
-        let lower = (self.x, self.y);
-        let upper = (self.x + self.width, self.y + self.height);
-        AABB::from_corners(lower, upper)
+        self.bounds
+    }
+}
+
+/// Selects the elements that match a predicate, wherever they are in the tree
+struct SelectElements<F>(F);
+
+impl<C: HasLayouter, F: Fn(&Element<C>) -> bool> SelectionFunction<Element<C>> for SelectElements<F> {
+    fn should_unpack_parent(&self, _envelope: &AABB<(f32, f32)>) -> bool {
+        true
+    }
+
+    fn should_unpack_leaf(&self, leaf: &Element<C>) -> bool {
+        (self.0)(leaf)
     }
 }
 
@@ -37,47 +125,292 @@ impl<C: HasLayouter> Default for PositionTree<C> {
 }
 
 impl<C: HasLayouter> PositionTree<C> {
-    pub fn from_tree(from_tree: &C::LayoutTree) -> Self {
-        let mut tree = RTree::new();
+    /// Creates the position tree of a layout tree. `shape_of` returns the shape of the node with its border box at
+    /// the given position in the layout.
+    pub fn from_tree(
+        from_tree: &C::LayoutTree,
+        shape_of: impl Fn(<C::LayoutTree as LayoutTree<C>>::NodeId, Point, Size) -> HitShape,
+    ) -> Self {
+        let mut elements = Vec::new();
+
+        let root = from_tree.root();
+        if let Some(layout) = from_tree.get_layout(root) {
+            let pos = layout.rel_pos();
+            let size = layout.size();
+            let shape = shape_of(root, pos, size);
 
-        //TODO: we somehow need to get the border radius and a potential stacking context of the element here
+            Self::add_node_to_tree(
+                from_tree,
+                root,
+                (pos, size, shape),
+                (&mut elements, &mut Vec::new()),
+                (None, &[]),
+                &shape_of,
+            );
+        }
 
-        Self::add_node_to_tree(from_tree, from_tree.root(), 0, &mut tree, (0.0, 0.0));
+        for (order, element) in elements.iter_mut().enumerate() {
+            element.order = order;
+        }
 
-        Self { tree }
+        Self {
+            tree: RTree::bulk_load(elements),
+        }
     }
 
-    fn add_node_to_tree(
+    /// Updates the elements after the layout of the node changed. Its descendants and following siblings may have
+    /// moved as well, so the subtree of the closest ancestor that kept its size is added again. A subtree with
+    /// positioned descendants is not in one piece in the painting order, so then the whole tree is added again.
+    pub fn update(
+        &mut self,
         from_tree: &C::LayoutTree,
         id: <C::LayoutTree as LayoutTree<C>>::NodeId,
-        z_index: i32,
-        tree: &mut RTree<Element<C>>,
-        mut pos: (f32, f32),
+        shape_of: impl Fn(<C::LayoutTree as LayoutTree<C>>::NodeId, Point, Size) -> HitShape,
     ) {
-        let Some(layout) = from_tree.get_layout(id) else {
+        let mut root = id;
+        loop {
+            let size = from_tree.get_layout(root).map(|layout| layout.size());
+            let unchanged = self
+                .get_node(root)
+                .zip(size)
+                .is_some_and(|(e, size)| e.width == size.width && e.height == size.height);
+            if unchanged {
+                break;
+            }
+
+            match from_tree.parent_id(root) {
+                Some(parent) => root = parent,
+                None => break,
+            }
+        }
+
+        let placement = Self::placement(from_tree, root, &shape_of);
+        let Some((start, placement)) = self.get_node(root).map(|e| e.order).zip(placement) else {
+            *self = Self::from_tree(from_tree, shape_of);
             return;
         };
 
-        let p = layout.rel_pos();
+        // Elements of the subtree, and of nodes that are not in the tree anymore
+        let mut subtree = HashSet::new();
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            subtree.insert(id.into());
+            stack.extend(from_tree.children(id).unwrap_or_default());
+        }
+        let removed = self
+            .tree
+            .drain_with_selection_function(SelectElements(|e: &Element<C>| {
+                subtree.contains(&e.id.into()) || !from_tree.contains(&e.id)
+            }))
+            .collect::<Vec<_>>();
+        let was_layered = removed.iter().any(|e| e.layered && e.id != root);
+        let removed = removed.len();
+
+        let mut elements = Vec::new();
+        let mut layered = Vec::new();
+        let ((pos, size, shape), transform, clips) = placement;
+        Self::add_node_to_tree(
+            from_tree,
+            root,
+            (pos, size, shape),
+            (&mut elements, &mut layered),
+            (transform, &clips),
+            &shape_of,
+        );
+        if was_layered || !layered.is_empty() || elements.iter().any(|e| e.layered && e.id != root) {
+            *self = Self::from_tree(from_tree, shape_of);
+            return;
+        }
+
+        for (idx, element) in elements.iter_mut().enumerate() {
+            element.order = start + idx;
+        }
+
+        // The elements that are painted later keep their order
+        for e in self.tree.iter_mut() {
+            if e.order >= start + removed {
+                e.order = e.order + elements.len() - removed;
+            }
+        }
 
-        pos.0 += p.x;
-        pos.1 += p.y;
+        for element in elements {
+            self.tree.insert(element);
+        }
+    }
+
+    /// Returns the placement of a node, in the same way as it is found when the tree is added from the root
+    fn placement(
+        from_tree: &C::LayoutTree,
+        id: <C::LayoutTree as LayoutTree<C>>::NodeId,
+        shape_of: &impl Fn(<C::LayoutTree as LayoutTree<C>>::NodeId, Point, Size) -> HitShape,
+    ) -> Option<Placement> {
+        let mut path = vec![id];
+        while let Some(parent) = from_tree.parent_id(path[path.len() - 1]) {
+            path.push(parent);
+        }
+
+        // Position of the content of the parent after its offsets, and the transform and clips that apply to its
+        // children
+        let mut parent: Option<(Point, Option<Matrix>, Vec<Clip>)> = None;
+        let mut placement = None;
+
+        for node in path.into_iter().rev() {
+            let layout = from_tree.get_layout(node)?;
+            let (origin, transform, mut clips) = parent.take().unwrap_or((Point::ZERO, None, Vec::new()));
+
+            let p = layout.rel_pos();
+            let pos = Point::new(origin.x + p.x, origin.y + p.y);
+            let size = layout.size();
+            let shape = shape_of(node, pos, size);
+            placement = Some(((pos, size, shape), transform, clips.clone()));
+
+            let transform = match shape.transform {
+                Some(matrix) => Some(multiply(transform.unwrap_or(IDENTITY), matrix)),
+                None => transform,
+            };
+            let pos = Point::new(pos.x + shape.offset.x, pos.y + shape.offset.y);
+            if let Some(clip) = shape.clip {
+                clips.push(page_clip(pos, clip, transform));
+            }
+            parent = Some((
+                Point::new(pos.x - shape.scroll.x, pos.y - shape.scroll.y),
+                transform,
+                clips,
+            ));
+        }
+
+        placement
+    }
+
+    /// Adds the element of a node and its descendants in the order they are painted in. The elements of positioned
+    /// descendants are added to `layered`, the list of the stacking context they are ordered in. The order of the
+    /// elements is their index once the whole tree is added.
+    fn add_node_to_tree(
+        from_tree: &C::LayoutTree,
+        id: <C::LayoutTree as LayoutTree<C>>::NodeId,
+        (mut pos, size, shape): (Point, Size, HitShape),
+        (tree, layered): (&mut Vec<Element<C>>, &mut Layered<C>),
+        (transform, clips): (Option<Matrix>, &[Clip]),
+        shape_of: &impl Fn(<C::LayoutTree as LayoutTree<C>>::NodeId, Point, Size) -> HitShape,
+    ) {
+        pos.x += shape.offset.x;
+        pos.y += shape.offset.y;
+
+        // The transforms of the ancestors apply to the element as well
+        let transform = match shape.transform {
+            Some(matrix) => Some(multiply(transform.unwrap_or(IDENTITY), matrix)),
+            None => transform,
+        };
+
+        let inverse = match transform {
+            Some(matrix) => match invert(matrix) {
+                Some(inverse) => Some(inverse),
+                // An element that can not be inverted is not painted, and neither are its descendants
+                None => return,
+            },
+            None => None,
+        };
+
+        let corners = [
+            Point::new(pos.x, pos.y),
+            Point::new(pos.x + size.width, pos.y),
+            Point::new(pos.x, pos.y + size.height),
+            Point::new(pos.x + size.width, pos.y + size.height),
+        ]
+        .map(|corner| transform.map_or(corner, |matrix| apply(matrix, corner)));
+
+        let lower = corners.iter().fold((f32::INFINITY, f32::INFINITY), |acc, c| {
+            (acc.0.min(c.x), acc.1.min(c.y))
+        });
+        let upper = corners.iter().fold((f32::NEG_INFINITY, f32::NEG_INFINITY), |acc, c| {
+            (acc.0.max(c.x), acc.1.max(c.y))
+        });
 
-        let size = layout.size();
         let element = Element {
             id,
-            x: pos.0,
-            y: pos.1,
+            x: pos.x,
+            y: pos.y,
             width: size.width,
             height: size.height,
-            radius: None, //TODO: border radius
-            z_index,
+            radii: shape.radii,
+            order: 0,
+            inverse,
+            bounds: AABB::from_corners(lower, upper),
+            clips: clips.to_vec(),
+            layered: shape.layer.is_layered(),
         };
 
-        tree.insert(element);
+        tree.push(element);
 
+        // The descendants are moved by the scroll offset and clipped to the scrollport
+        let mut clips = clips.to_vec();
+        if let Some(clip) = shape.clip {
+            clips.push(page_clip(pos, clip, transform));
+        }
+        let pos = Point::new(pos.x - shape.scroll.x, pos.y - shape.scroll.y);
+
+        // The root of the page is the root stacking context
+        let stacking_context = shape.stacking_context || from_tree.root() == id;
+        let mut context_layered = Vec::new();
+        let layered = if stacking_context {
+            &mut context_layered
+        } else {
+            layered
+        };
+
+        let mut flow = Vec::new();
         for child in from_tree.children(id).unwrap_or_default() {
-            Self::add_node_to_tree(from_tree, child, z_index + 1, tree, pos);
+            let Some(layout) = from_tree.get_layout(child) else {
+                continue;
+            };
+            let p = layout.rel_pos();
+            let child_pos = Point::new(pos.x + p.x, pos.y + p.y);
+            let size = layout.size();
+            let shape = shape_of(child, child_pos, size);
+
+            if shape.layer.is_layered() {
+                // The slot is taken first, so the element goes before its own positioned descendants
+                let idx = layered.len();
+                layered.push((shape.layer, Vec::new()));
+
+                let mut elements = Vec::new();
+                Self::add_node_to_tree(
+                    from_tree,
+                    child,
+                    (child_pos, size, shape),
+                    (&mut elements, layered),
+                    (transform, &clips),
+                    shape_of,
+                );
+                layered[idx].1 = elements;
+            } else {
+                Self::add_node_to_tree(
+                    from_tree,
+                    child,
+                    (child_pos, size, shape),
+                    (&mut flow, layered),
+                    (transform, &clips),
+                    shape_of,
+                );
+            }
+        }
+
+        if !stacking_context {
+            tree.append(&mut flow);
+            return;
+        }
+
+        // Positioned descendants with a negative z-index go below the flow, the others above it
+        context_layered.sort_by_key(|(layer, _)| *layer);
+        let below = context_layered.partition_point(|(layer, _)| layer.is_below_flow());
+        let mut above = context_layered.split_off(below);
+
+        for (_, elements) in &mut context_layered {
+            tree.append(elements);
+        }
+        tree.append(&mut flow);
+        for (_, elements) in &mut above {
+            tree.append(elements);
         }
     }
 
@@ -87,57 +420,8 @@ impl<C: HasLayouter> PositionTree<C> {
 
         self.tree
             .locate_in_envelope_intersecting(&envelope)
-            .filter(|e| {
-                let Some(radi) = e.radius else {
-                    return true;
-                };
-
-                let middle = (e.x + e.width / 2.0, e.y + e.height / 2.0);
-
-                match middle.0.total_cmp(&x) {
-                    Ordering::Equal => true,
-                    Ordering::Less => {
-                        match middle.1.total_cmp(&y) {
-                            Ordering::Equal => true,
-                            // top left
-                            Ordering::Less => {
-                                if (e.x + radi.0) > x && (e.y + radi.0) > y {
-                                    return is_point_in_circle((e.x + radi.0, e.y + radi.0), radi.0, (x, y));
-                                }
-                                false
-                            }
-                            // top right
-                            Ordering::Greater => {
-                                if (e.x + e.width - radi.1) < x && (e.y + radi.1) < y {
-                                    return is_point_in_circle((e.x + radi.1, e.y + radi.1), radi.1, (x, y));
-                                }
-
-                                false
-                            }
-                        }
-                    }
-                    Ordering::Greater => {
-                        match middle.1.total_cmp(&y) {
-                            Ordering::Equal => true,
-                            // bottom left
-                            Ordering::Less => {
-                                if (e.x + radi.2) > x && (e.y + e.height - radi.2) < y {
-                                    return is_point_in_circle((e.x + radi.2, e.y + e.height - radi.2), radi.2, (x, y));
-                                }
-                                false
-                            }
-                            // bottom right
-                            Ordering::Greater => {
-                                if (e.x + e.width - radi.3) < x && (e.y + e.height - radi.3) < y {
-                                    return is_point_in_circle((e.x + radi.3, e.y + radi.3), radi.3, (x, y));
-                                }
-                                false
-                            }
-                        }
-                    }
-                }
-            })
-            .reduce(|a, b| if a.z_index >= b.z_index { a } else { b }) // >= because we just hope that the last-drawn element is last in the list
+            .filter(|e| e.contains(x, y))
+            .max_by_key(|e| e.order)
             .map(|e| e.id)
     }
 
@@ -148,12 +432,141 @@ impl<C: HasLayouter> PositionTree<C> {
     pub fn position(&self, id: <C::LayoutTree as LayoutTree<C>>::NodeId) -> Option<(f32, f32)> {
         self.get_node(id).map(|e| (e.x, e.y))
     }
+
+    /// Returns the position of the border box of every element on the page, by the id of its node
+    #[must_use]
+    pub fn positions(&self) -> HashMap<u64, Point> {
+        self.tree.iter().map(|e| (e.id.into(), Point::new(e.x, e.y))).collect()
+    }
 }
 
-fn is_point_in_circle(circle_center: (f32, f32), circle_radius: f32, point: (f32, f32)) -> bool {
-    let dx = circle_center.0 - point.0;
-    let dy = circle_center.1 - point.1;
-    let distance = (dx * dx + dy * dy).sqrt();
+/// Returns the clip of a node with its border box at `pos` on the page
+fn page_clip(pos: Point, clip: Area, transform: Option<Matrix>) -> Clip {
+    (
+        Area::new(Point::new(pos.x + clip.pos.x, pos.y + clip.pos.y), clip.size),
+        transform.and_then(invert),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::positioning::{is_stacking_context, paint_layer};
+    use crate::render_tree::fragment::FragmentTarget;
+    use crate::render_tree::RenderTree;
+    use crate::testing::{document, laid_out, layout, Config};
+    use gosub_css3::system::Css3System;
+    use gosub_interface::document::Document;
+    use gosub_interface::node::{ElementDataType, Node};
+    use gosub_interface::render_tree::RenderTreeNode;
+    use gosub_shared::node::NodeId;
+
+    fn position_tree(tree: &RenderTree<Config>) -> PositionTree<Config> {
+        PositionTree::from_tree(tree, |id, _, _| {
+            tree.get_node(id).map_or_else(HitShape::default, |node| HitShape {
+                layer: paint_layer::<Css3System>(node.props()),
+                stacking_context: is_stacking_context::<Css3System>(node.props()),
+                ..HitShape::default()
+            })
+        })
+    }
+
+    fn element(tree: &RenderTree<Config>, id: &str) -> NodeId {
+        match tree.fragment_target(id) {
+            Some(FragmentTarget::Element(id)) => id,
+            _ => panic!("no element with id {id}"),
+        }
+    }
+
+    #[test]
+    fn painting_order() {
+        let tree = laid_out(
+            r#"<body style="margin: 0">
+                <div id="flow" style="height: 100px">
+                    <div id="below" style="position: absolute; z-index: -1; width: 50px; height: 50px"></div>
+                    <div style="position: relative">
+                        <div id="nested" style="position: absolute; z-index: 3; top: 0; width: 50px; height: 50px"></div>
+                    </div>
+                </div>
+                <div id="context" style="position: relative; z-index: 1; margin-top: -100px; height: 100px">
+                    <div id="confined" style="position: absolute; z-index: 9; left: 100px; width: 50px; height: 50px"></div>
+                </div>
+                <div id="above" style="position: relative; z-index: 2; margin-top: -100px; margin-left: 100px; width: 50px; height: 100px"></div>
+            </body>"#,
+        );
+        let position = position_tree(&tree);
 
-    distance <= circle_radius
+        // The nested element is ordered in the root stacking context, above the elements with a lower z-index
+        assert_eq!(position.find(10.0, 10.0), Some(element(&tree, "nested")));
+        // Elements in a stacking context can't go above the elements that are above the context
+        assert_eq!(position.find(110.0, 10.0), Some(element(&tree, "above")));
+        // A negative z-index goes below the flow of its stacking context
+        assert_eq!(position.find(10.0, 60.0), Some(element(&tree, "context")));
+        assert!(position.find(10.0, 60.0) != Some(element(&tree, "below")));
+    }
+
+    /// Changes the style of an element, and updates the position tree with the nodes whose layout changed
+    fn update(html: &str, id: &str, style: &str) {
+        let (mut doc, mut tree) = document(html);
+        layout(&mut tree);
+        let mut position = position_tree(&tree);
+
+        doc.observe_mutations(true);
+        let mut node = doc.node_by_named_id(id).unwrap().clone();
+        node.get_element_data_mut().unwrap().add_attribute("style", style);
+        doc.update_node(node);
+
+        let mutations = doc.take_mutations();
+        let invalidated = tree.apply_mutations(&doc, &mutations);
+        assert!(!invalidated.is_empty());
+        layout(&mut tree);
+        for id in invalidated {
+            position.update(&tree, id, |id, _, _| {
+                tree.get_node(id).map_or_else(HitShape::default, |node| HitShape {
+                    layer: paint_layer::<Css3System>(node.props()),
+                    stacking_context: is_stacking_context::<Css3System>(node.props()),
+                    ..HitShape::default()
+                })
+            });
+        }
+
+        // The elements are the same as in a tree that is added from the root
+        let expected = position_tree(&tree);
+        let elements = |position: &PositionTree<Config>| {
+            let mut elements = position
+                .tree
+                .iter()
+                .map(|e| (e.order, e.id, e.x, e.y, e.width, e.height))
+                .collect::<Vec<_>>();
+            elements.sort_by_key(|e| e.0);
+            elements
+        };
+        assert_eq!(elements(&position), elements(&expected));
+    }
+
+    #[test]
+    fn update_after_layout_change() {
+        update(
+            r#"<body style="margin: 0">
+                <div id="first" style="height: 10px"><div style="height: 5px"></div></div>
+                <div><div style="height: 20px"></div></div>
+                <div style="height: 30px"></div>
+            </body>"#,
+            "first",
+            "height: 50px",
+        );
+    }
+
+    #[test]
+    fn update_with_positioned_elements() {
+        update(
+            r#"<body style="margin: 0">
+                <div id="first" style="height: 10px"></div>
+                <div style="position: relative; z-index: 1; height: 20px"></div>
+                <div style="height: 30px"><div style="position: absolute; z-index: -1; height: 5px"></div></div>
+            </body>"#,
+            "first",
+            "height: 50px; position: relative",
+        );
+    }
 }
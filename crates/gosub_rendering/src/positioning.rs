@@ -0,0 +1,224 @@
+//! Positioned elements at paint time. The layouter places absolute and fixed elements in their containing block, but
+//! fixed and sticky elements also depend on the scroll offset of the page, which is only known when painting. The
+//! positioned descendants of a stacking context are painted in the order of their `z-index`.
+
+use crate::background::Area;
+use gosub_interface::css3::{CssProperty, CssPropertyMap, CssSystem};
+use gosub_shared::geo::{Point, FP};
+
+pub use gosub_interface::layout::Position;
+
+/// Returns the `position` of an element
+pub fn position<S: CssSystem>(props: &S::PropertyMap) -> Position {
+    Position::from_keyword(props.get("position").and_then(|prop| prop.as_string()))
+}
+
+/// Layer of an element in the painting order of its stacking context. Elements with a negative `z-index` are painted
+/// first, then the elements in the normal flow, then positioned elements and finally elements with a positive
+/// `z-index`. Elements in the same layer are painted in tree order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct PaintLayer {
+    z_index: i32,
+    positioned: bool,
+}
+
+impl PaintLayer {
+    /// Returns true when the element is painted in a layer of its stacking context, instead of in the normal flow of
+    /// its parent
+    #[must_use]
+    pub fn is_layered(self) -> bool {
+        self.positioned
+    }
+
+    /// Returns true when the layer is painted below the normal flow of its stacking context
+    #[must_use]
+    pub fn is_below_flow(self) -> bool {
+        self.z_index < 0
+    }
+}
+
+/// Returns the layer an element is painted in. `z-index` only applies to positioned elements, and other stacking
+/// contexts (ie: an element with an opacity) are painted with the positioned elements.
+pub fn paint_layer<S: CssSystem>(props: &S::PropertyMap) -> PaintLayer {
+    let positioned = position::<S>(props).is_positioned();
+    if !positioned && !is_stacking_context::<S>(props) {
+        return PaintLayer::default();
+    }
+
+    let z_index = props
+        .get("z-index")
+        .and_then(|prop| prop.as_number())
+        .filter(|_| positioned)
+        .map_or(0, |z_index| z_index as i32);
+
+    PaintLayer {
+        z_index,
+        positioned: true,
+    }
+}
+
+/// Returns true when the element starts a stacking context. The positioned descendants of a stacking context are
+/// painted in its layers, and never go below or above the stacking context itself.
+pub fn is_stacking_context<S: CssSystem>(props: &S::PropertyMap) -> bool {
+    let keyword = |name: &str| props.get(name).map(|prop| prop.as_string());
+    // Properties that are set to anything else than their initial keyword
+    let is_set = |name: &str, initial: &str| keyword(name).is_some_and(|value| value != Some(initial));
+
+    let position = position::<S>(props);
+
+    matches!(position, Position::Fixed | Position::Sticky)
+        || (position.is_positioned() && is_set("z-index", "auto"))
+        || props
+            .get("opacity")
+            .and_then(|prop| prop.as_number())
+            .is_some_and(|opacity| opacity < 1.0)
+        || is_set("transform", "none")
+        || is_set("filter", "none")
+        || is_set("mix-blend-mode", "normal")
+        || keyword("isolation") == Some(Some("isolate"))
+}
+
+/// Returns how far a sticky element is shifted from its position in the flow. The element sticks to the edges of the
+/// `scrollport` that have an inset, but it never leaves the content box of its parent (`container`). `rect` is the
+/// border box of the element in the flow.
+pub fn sticky_offset<S: CssSystem>(props: &S::PropertyMap, rect: Area, container: Area, scrollport: Area) -> Point {
+    let inset = |name: &str, basis: FP| -> Option<FP> {
+        let prop = props.get(name)?;
+        if prop.as_string().is_some() {
+            // auto
+            return None;
+        }
+
+        Some(
+            prop.as_percentage()
+                .map_or_else(|| prop.unit_to_px(), |p| basis * p / 100.0),
+        )
+    };
+
+    let x = sticky_axis(
+        (rect.pos.x, rect.size.width),
+        (container.pos.x, container.size.width),
+        (scrollport.pos.x, scrollport.size.width),
+        inset("left", scrollport.size.width),
+        inset("right", scrollport.size.width),
+    );
+    let y = sticky_axis(
+        (rect.pos.y, rect.size.height),
+        (container.pos.y, container.size.height),
+        (scrollport.pos.y, scrollport.size.height),
+        inset("top", scrollport.size.height),
+        inset("bottom", scrollport.size.height),
+    );
+
+    Point::new(x, y)
+}
+
+/// Resolves the offset on one axis. Each range is given as (start, length).
+fn sticky_axis(rect: (FP, FP), container: (FP, FP), scrollport: (FP, FP), start: Option<FP>, end: Option<FP>) -> FP {
+    let rect_end = rect.0 + rect.1;
+    let container_end = container.0 + container.1;
+    let mut offset: FP = 0.0;
+
+    if let Some(start) = start {
+        // Moves forward until the end of the element reaches the end of its container
+        let limit = (container_end - rect_end).max(0.0);
+        offset = (scrollport.0 + start - rect.0).clamp(0.0, limit);
+    }
+
+    if let Some(end) = end {
+        // Moves back until the start of the element reaches the start of its container
+        let limit = (container.0 - rect.0).min(0.0);
+        let back = (scrollport.0 + scrollport.1 - end - rect_end).clamp(limit, 0.0);
+        if back < 0.0 {
+            offset = back;
+        }
+    }
+
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::props;
+    use gosub_css3::system::Css3System;
+    use gosub_shared::geo::Size;
+
+    fn area(x: FP, y: FP, width: FP, height: FP) -> Area {
+        Area::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn paint_layers() {
+        let layer = |values: &[(&str, &str)]| paint_layer::<Css3System>(&props(values));
+
+        let in_flow = layer(&[("z-index", "5")]);
+        let positioned = layer(&[("position", "relative")]);
+        let above = layer(&[("position", "absolute"), ("z-index", "2")]);
+        let below = layer(&[("position", "fixed"), ("z-index", "-1")]);
+
+        assert_eq!(in_flow, PaintLayer::default());
+        assert!(below < in_flow);
+        assert!(in_flow < positioned);
+        assert!(positioned < above);
+
+        assert!(below.is_below_flow());
+        assert!(!in_flow.is_layered());
+        assert!(positioned.is_layered() && !positioned.is_below_flow());
+
+        // Other stacking contexts are painted with the positioned elements, where z-index does not apply
+        let translucent = layer(&[("opacity", "0.5"), ("z-index", "3")]);
+        assert_eq!(translucent, positioned);
+    }
+
+    #[test]
+    fn stacking_contexts() {
+        let context = |values: &[(&str, &str)]| is_stacking_context::<Css3System>(&props(values));
+
+        assert!(!context(&[]));
+        assert!(!context(&[("position", "relative")]));
+        assert!(!context(&[("z-index", "1")]));
+        assert!(!context(&[("transform", "none")]));
+        assert!(!context(&[("opacity", "1")]));
+
+        assert!(context(&[("position", "relative"), ("z-index", "0")]));
+        assert!(context(&[("position", "fixed")]));
+        assert!(context(&[("position", "sticky")]));
+        assert!(context(&[("opacity", "0.5")]));
+        assert!(context(&[("transform", "rotate(45deg)")]));
+        assert!(context(&[("mix-blend-mode", "multiply")]));
+        assert!(context(&[("isolation", "isolate")]));
+    }
+
+    #[test]
+    fn sticks_to_the_top_within_its_container() {
+        let map = props(&[("top", "10px")]);
+        let rect = area(0.0, 100.0, 200.0, 20.0);
+        let container = area(0.0, 50.0, 200.0, 300.0);
+
+        // Not scrolled far enough for the element to stick
+        let offset = sticky_offset::<Css3System>(&map, rect, container, area(0.0, 50.0, 800.0, 600.0));
+        assert_eq!(offset, Point::new(0.0, 0.0));
+
+        let offset = sticky_offset::<Css3System>(&map, rect, container, area(0.0, 150.0, 800.0, 600.0));
+        assert_eq!(offset, Point::new(0.0, 60.0));
+
+        // The element stops at the end of its container
+        let offset = sticky_offset::<Css3System>(&map, rect, container, area(0.0, 500.0, 800.0, 600.0));
+        assert_eq!(offset, Point::new(0.0, 230.0));
+    }
+
+    #[test]
+    fn sticks_to_the_bottom() {
+        let map = props(&[("bottom", "0"), ("left", "auto")]);
+        let rect = area(0.0, 1000.0, 200.0, 20.0);
+        let container = area(0.0, 900.0, 200.0, 200.0);
+
+        let offset = sticky_offset::<Css3System>(&map, rect, container, area(0.0, 0.0, 800.0, 960.0));
+        assert_eq!(offset, Point::new(0.0, -60.0));
+
+        // The element does not move above the start of its container
+        let offset = sticky_offset::<Css3System>(&map, rect, container, area(0.0, 0.0, 800.0, 500.0));
+        assert_eq!(offset, Point::new(0.0, -100.0));
+    }
+}
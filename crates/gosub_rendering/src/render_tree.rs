@@ -1,28 +1,44 @@
 use cow_utils::CowUtils;
 use gosub_html5::document::document_impl::TreeIterator;
 use gosub_interface::config::{HasDocument, HasLayouter, HasRenderTree};
-use gosub_interface::css3::{CssProperty, CssPropertyMap, CssSystem};
+use gosub_interface::css3::{CssProperty, CssPropertyMap, CssStylesheet, CssSystem};
 use gosub_interface::document::Document;
 
-use gosub_interface::font::HasFontManager;
-use gosub_interface::layout::{HasTextLayout, Layout, LayoutCache, LayoutNode, LayoutTree, Layouter, TextLayout};
+use gosub_interface::font::{FontFaceDescriptor, FontFaceSource, HasFontManager};
+use gosub_interface::layout::{
+    HasTextLayout, IntrinsicSize, Layout, LayoutCache, LayoutNode, LayoutTree, Layouter, ReplacedText, TextLayout,
+};
 use gosub_interface::node::NodeData;
 use gosub_interface::node::{ElementDataType, Node as DocumentNode, TextDataType};
 use gosub_interface::render_tree;
 use gosub_interface::render_tree::TextLayoutRef;
+use gosub_interface::resource::ResourceLoader;
 use gosub_shared::geo::Size;
 use gosub_shared::node::NodeId;
 use gosub_shared::types::Result;
+use gosub_shared::{timing_start, timing_stop};
 use log::info;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
 
+pub mod accessibility;
 mod desc;
-
-const INLINE_ELEMENTS: [&str; 31] = [
+pub mod focus;
+pub mod form;
+pub mod fragment;
+mod mutation;
+pub mod submission;
+pub mod svg;
+
+use form::{ControlData, ControlState};
+use submission::Forms;
+use svg::SvgData;
+
+const INLINE_ELEMENTS: [&str; 32] = [
     "a", "abbr", "acronym", "b", "bdo", "big", "br", "button", "cite", "code", "dfn", "em", "i", "img", "input", "kbd",
-    "label", "map", "object", "q", "samp", "script", "select", "small", "span", "strong", "sub", "sup", "textarea",
-    "tt", "var",
+    "label", "map", "object", "q", "samp", "script", "select", "small", "span", "strong", "sub", "sup", "svg",
+    "textarea", "tt", "var",
 ];
 
 /// Map of all declared values for all nodes in the document
@@ -32,6 +48,18 @@ pub struct RenderTree<C: HasLayouter + HasFontManager> {
     pub root: NodeId,
     pub dirty: bool,
     next_id: NodeId,
+    /// Url of the document the tree is generated from
+    pub document_url: Option<String>,
+    /// Font faces declared by `@font-face` rules in the stylesheets of the document. Urls are absolute.
+    pub font_faces: Vec<FontFaceDescriptor>,
+    /// Properties of the `::selection` pseudo-element of the elements that are styled by a `::selection` rule
+    pub selection_styles: HashMap<NodeId, C::CssPropertyMap>,
+    /// State of the form controls, which is kept when their nodes are styled again
+    pub controls: HashMap<NodeId, ControlState>,
+    /// Forms of the document and their fields, including the ones that are not rendered
+    pub forms: Forms,
+    /// Loader of the document, which also fetches the images and web fonts of the tree
+    pub resource_loader: Option<Arc<dyn ResourceLoader>>,
 }
 
 #[allow(unused)]
@@ -119,6 +147,12 @@ impl<C: HasLayouter<LayoutTree = Self>> RenderTree<C> {
             root: NodeId::root(),
             dirty: false,
             next_id: NodeId::from(1u64),
+            document_url: None,
+            font_faces: Vec::new(),
+            selection_styles: HashMap::new(),
+            controls: HashMap::new(),
+            forms: Forms::default(),
+            resource_loader: None,
         };
 
         tree.insert_node(
@@ -299,12 +333,12 @@ impl<C: HasLayouter<LayoutTree = Self>> RenderTree<C> {
         self.nodes.get(&node_id).map(|props| &props.properties)
     }
 
-    /// Removes all unrenderable nodes from the render tree
-    fn remove_unrenderable_nodes(&mut self) {
+    /// Removes the given nodes from the render tree when they are not renderable
+    fn remove_unrenderable_nodes(&mut self, ids: &[NodeId]) {
         // There are more elements that are not renderable, but for now we only remove the most common ones
         let mut delete_list = Vec::new();
 
-        for id in self.nodes.keys() {
+        for id in ids {
             // Check CSS styles and remove if not renderable
             if let Some(prop) = self.get_property(*id, "display") {
                 if prop.as_string() == Some("none") {
@@ -322,14 +356,25 @@ impl<C: HasLayouter<LayoutTree = Self>> RenderTree<C> {
 
     /// Collapse all inline elements / wrap inline elements with anonymous boxes
     fn collapse_inline(&mut self, node_id: NodeId) {
+        for child_id in self.wrap_inline_children(node_id) {
+            self.collapse_inline(child_id);
+        }
+    }
+
+    /// Wraps the runs of inline children of the node with anonymous boxes, replacing the anonymous boxes the node
+    /// already had. Returns the children of the node without the anonymous boxes.
+    fn wrap_inline_children(&mut self, node_id: NodeId) -> Vec<NodeId> {
+        self.unwrap_inline_children(node_id);
+
         let Some(node) = self.nodes.get(&node_id) else {
             eprintln!("Node not found: {node_id}");
-            return;
+            return Vec::new();
         };
 
+        let children = node.children.clone();
         let mut inline_wrapper = None;
 
-        for child_id in node.children.clone() {
+        for child_id in children.clone() {
             let Some(child) = self.nodes.get_mut(&child_id) else {
                 eprintln!("Child not found: {child_id}");
                 continue;
@@ -391,8 +436,41 @@ impl<C: HasLayouter<LayoutTree = Self>> RenderTree<C> {
             } else {
                 inline_wrapper = None;
             }
+        }
 
-            self.collapse_inline(child_id);
+        children
+    }
+
+    /// Removes the anonymous boxes from the children of the node and puts their children back in their place
+    fn unwrap_inline_children(&mut self, node_id: NodeId) {
+        let Some(node) = self.nodes.get(&node_id) else {
+            return;
+        };
+
+        let mut children = Vec::with_capacity(node.children.len());
+        for child_id in node.children.clone() {
+            let is_wrapper = self
+                .nodes
+                .get(&child_id)
+                .is_some_and(|child| matches!(child.data, RenderNodeData::AnonymousInline));
+            if !is_wrapper {
+                children.push(child_id);
+                continue;
+            }
+
+            let Some(wrapper) = self.nodes.remove(&child_id) else {
+                continue;
+            };
+            for id in wrapper.children {
+                if let Some(child) = self.nodes.get_mut(&id) {
+                    child.parent = Some(node_id);
+                }
+                children.push(id);
+            }
+        }
+
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            node.children = children;
         }
     }
 
@@ -421,6 +499,29 @@ impl<C: HasLayouter<LayoutTree = Self>> RenderTree<C> {
         }
     }
 
+    /// Returns the properties of the `::selection` pseudo-element that applies to the node. Like other highlights, it
+    /// is inherited from the closest ancestor that has one.
+    pub fn selection_style(&self, id: NodeId) -> Option<&C::CssPropertyMap> {
+        let mut next = Some(id);
+
+        while let Some(id) = next {
+            if let Some(style) = self.selection_styles.get(&id) {
+                return Some(style);
+            }
+
+            next = self.nodes.get(&id).and_then(|node| node.parent);
+        }
+
+        None
+    }
+
+    /// Invalidates the layout of all nodes, for instance when fonts have changed
+    pub fn invalidate_layout(&mut self) {
+        for node in self.nodes.values_mut() {
+            node.cache.invalidate();
+        }
+    }
+
     pub fn layout_dirty_from(&mut self, from: NodeId) {
         let mut next_node = Some(from);
 
@@ -457,80 +558,157 @@ impl<C: HasRenderTree<LayoutTree = Self, RenderTree = Self> + HasDocument> Rende
     }
 
     fn generate_from(&mut self, doc: &C::Document) {
+        let t_id = timing_start!(
+            "style.compute",
+            doc.url().map(|url| url.to_string()).unwrap_or_default()
+        );
+
         // Iterate the complete document tree
 
+        // Replaced elements (inline svg elements and form controls) and their descendants. The descendants are
+        // rendered as part of the replaced element.
+        let mut replaced_nodes = HashSet::new();
+
         for current_node_id in TreeIterator::<C>::new(doc) {
-            let node = doc.node_by_id(current_node_id).unwrap();
+            self.insert_from_document(doc, current_node_id, &mut replaced_nodes);
+        }
 
-            let Some(properties) =
-                <C::CssSystem as CssSystem>::properties_from_node::<C>(node, doc.stylesheets(), doc, current_node_id)
-            else {
-                if let Some(parent) = node.parent_id() {
-                    if let Some(parent) = self.get_node_mut(parent) {
-                        parent.children.retain(|id| *id != current_node_id);
-                    }
-                }
+        self.next_id = doc.peek_next_id();
 
-                // doc.detach_node(current_node_id);
-                continue;
-            };
+        self.document_url = doc.url().map(|url| url.to_string());
+        self.font_faces = collect_font_faces::<C>(doc);
+        self.forms = Forms::from_document::<C>(doc);
+        self.resource_loader = doc.resource_loader();
 
-            let data = node.data();
+        self.remove_unrenderable_nodes(&self.nodes.keys().copied().collect::<Vec<_>>());
 
-            let render_data = match RenderNodeData::from_node_data(&data) {
-                ControlFlow::Ok(data) => data,
-                ControlFlow::Drop => {
-                    if let Some(parent) = node.parent_id() {
-                        if let Some(parent) = self.get_node_mut(parent) {
-                            parent.children.retain(|id| *id != current_node_id);
-                        }
-                    }
+        <C::CssSystem as CssSystem>::inheritance::<C>(self);
 
-                    // doc.detach_node(current_node_id);
-                    continue;
-                }
-                ControlFlow::Error(e) => {
-                    log::error!("Failed to create node data for node: {current_node_id:?} ({e}");
-                    continue;
-                }
-            };
+        if <C::Layouter as Layouter<C>>::COLLAPSE_INLINE {
+            self.collapse_inline(self.root);
+        }
 
-            let mut namespace: Option<String> = None;
+        timing_stop!(t_id);
+    }
 
-            let name = match data {
-                NodeData::Element(data) => {
-                    namespace = Some(data.namespace().to_string());
-                    data.name().to_string()
-                }
-                NodeData::Text(_) => "#text".to_owned(),
-                NodeData::Document(_) => "#document".to_owned(),
-                _ => String::new(),
-            };
+    /// Adds the node of the document to the tree, unless it is not rendered. `replaced_nodes` holds the replaced
+    /// elements and their descendants that are added so far.
+    fn insert_from_document(
+        &mut self,
+        doc: &C::Document,
+        current_node_id: NodeId,
+        replaced_nodes: &mut HashSet<NodeId>,
+    ) {
+        let node = doc.node_by_id(current_node_id).unwrap();
 
-            let render_tree_node = RenderTreeNode {
-                id: current_node_id,
-                properties,
-                children: node.children().to_vec(),
-                parent: node.parent_id(),
-                name, // We might be able to move node into render_tree_node
-                namespace,
-                data: render_data,
-                cache: <C::Layouter as Layouter<C>>::Cache::default(),
-                layout: <C::Layouter as Layouter<C>>::Layout::default(),
-            };
+        self.selection_styles.remove(&current_node_id);
 
-            self.nodes.insert(current_node_id, render_tree_node);
+        if node.parent_id().is_some_and(|parent| replaced_nodes.contains(&parent)) {
+            replaced_nodes.insert(current_node_id);
+            return;
         }
 
-        self.next_id = doc.peek_next_id();
+        let Some(properties) =
+            <C::CssSystem as CssSystem>::properties_from_node::<C>(node, doc.stylesheets(), doc, current_node_id)
+        else {
+            if let Some(parent) = node.parent_id() {
+                if let Some(parent) = self.get_node_mut(parent) {
+                    parent.children.retain(|id| *id != current_node_id);
+                }
+            }
 
-        self.remove_unrenderable_nodes();
+            // doc.detach_node(current_node_id);
+            return;
+        };
 
-        <C::CssSystem as CssSystem>::inheritance::<C>(self);
+        let data = node.data();
 
-        if <C::Layouter as Layouter<C>>::COLLAPSE_INLINE {
-            self.collapse_inline(self.root);
+        let render_data = match RenderNodeData::from_node_data(&data) {
+            ControlFlow::Ok(RenderNodeData::Element { attributes }) if is_svg_root(&data) => {
+                replaced_nodes.insert(current_node_id);
+
+                RenderNodeData::Svg(Box::new(SvgData {
+                    attributes,
+                    source: doc.write_from_node(current_node_id),
+                }))
+            }
+            ControlFlow::Ok(RenderNodeData::Element { attributes }) => match form::control_kind(&data) {
+                Some(kind) => {
+                    replaced_nodes.insert(current_node_id);
+
+                    let mut control = ControlData::from_document::<C>(doc, current_node_id, kind, attributes);
+                    let state = self
+                        .controls
+                        .entry(current_node_id)
+                        .or_insert_with(|| control.initial_state());
+                    state.fit(&control);
+                    control.shown = control.shown_text(state);
+
+                    RenderNodeData::Control {
+                        control: Box::new(control),
+                        layout: Vec::new(),
+                    }
+                }
+                None => RenderNodeData::Element { attributes },
+            },
+            ControlFlow::Ok(data) => data,
+            ControlFlow::Drop => {
+                if let Some(parent) = node.parent_id() {
+                    if let Some(parent) = self.get_node_mut(parent) {
+                        parent.children.retain(|id| *id != current_node_id);
+                    }
+                }
+
+                // doc.detach_node(current_node_id);
+                return;
+            }
+            ControlFlow::Error(e) => {
+                log::error!("Failed to create node data for node: {current_node_id:?} ({e}");
+                return;
+            }
+        };
+
+        let mut namespace: Option<String> = None;
+
+        let name = match data {
+            NodeData::Element(data) => {
+                namespace = Some(data.namespace().to_string());
+                data.name().to_string()
+            }
+            NodeData::Text(_) => "#text".to_owned(),
+            NodeData::Document(_) => "#document".to_owned(),
+            _ => String::new(),
+        };
+
+        let children = if replaced_nodes.contains(&current_node_id) {
+            Vec::new()
+        } else {
+            node.children().to_vec()
+        };
+
+        if let Some(selection) = <C::CssSystem as CssSystem>::pseudo_element_properties_from_node::<C>(
+            node,
+            doc.stylesheets(),
+            doc,
+            current_node_id,
+            "selection",
+        ) {
+            self.selection_styles.insert(current_node_id, selection);
         }
+
+        let render_tree_node = RenderTreeNode {
+            id: current_node_id,
+            properties,
+            children,
+            parent: node.parent_id(),
+            name, // We might be able to move node into render_tree_node
+            namespace,
+            data: render_data,
+            cache: <C::Layouter as Layouter<C>>::Cache::default(),
+            layout: <C::Layouter as Layouter<C>>::Layout::default(),
+        };
+
+        self.nodes.insert(current_node_id, render_tree_node);
     }
 }
 
@@ -584,11 +762,12 @@ impl<C: HasLayouter> render_tree::RenderTreeNode<C> for RenderTreeNode<C> {
     }
 
     fn element_attributes(&self) -> Option<&HashMap<String, String>> {
-        if let RenderNodeData::Element { attributes } = &self.data {
-            return Some(attributes);
+        match &self.data {
+            RenderNodeData::Element { attributes } => Some(attributes),
+            RenderNodeData::Svg(svg) => Some(&svg.attributes),
+            RenderNodeData::Control { control, .. } => Some(&control.attributes),
+            _ => None,
         }
-
-        None
     }
 
     fn text_data(&self) -> Option<(&str, TextLayoutRef<'_, C>)> {
@@ -599,6 +778,14 @@ impl<C: HasLayouter> render_tree::RenderTreeNode<C> for RenderTreeNode<C> {
         None
     }
 
+    fn svg_source(&self) -> Option<&str> {
+        if let RenderNodeData::Svg(svg) = &self.data {
+            return Some(&svg.source);
+        }
+
+        None
+    }
+
     fn name(&self) -> &str {
         &self.name
     }
@@ -608,9 +795,19 @@ impl<C: HasLayouter> render_tree::RenderTreeNode<C> for RenderTreeNode<C> {
 
 pub enum RenderNodeData<C: HasLayouter> {
     Document,
-    Element { attributes: HashMap<String, String> },
+    Element {
+        attributes: HashMap<String, String>,
+    },
     Text(Box<TextData<C>>),
     AnonymousInline,
+    /// Inline `<svg>` element, which is laid out and painted as a replaced element
+    Svg(Box<SvgData>),
+    /// Form control, which is laid out as a replaced element and painted by the renderer. The layout is the layout
+    /// of the text it shows.
+    Control {
+        control: Box<ControlData>,
+        layout: Vec<<C::Layouter as Layouter<C>>::TextLayout>,
+    },
 }
 
 impl<C: HasLayouter> Debug for RenderNodeData<C> {
@@ -620,6 +817,12 @@ impl<C: HasLayouter> Debug for RenderNodeData<C> {
             Self::Element { attributes } => f.debug_struct("Element").field("attributes", attributes).finish(),
             Self::Text(data) => f.debug_struct("TextData").field("data", data).finish(),
             Self::AnonymousInline => f.write_str("AnonymousInline"),
+            Self::Svg(data) => f.debug_tuple("Svg").field(data).finish(),
+            Self::Control { control, layout } => f
+                .debug_struct("Control")
+                .field("control", control)
+                .field("layout", layout)
+                .finish(),
         }
     }
 }
@@ -665,39 +868,17 @@ impl<C: HasLayouter + HasDocument> RenderNodeData<C> {
             NodeData::Element(d) => RenderNodeData::Element {
                 attributes: d.attributes().clone(),
             },
-            NodeData::Text(data) => {
-                let text = pre_transform_text(data.string_value());
-
-                RenderNodeData::Text(Box::new(TextData {
-                    text,
-                    layout: Vec::new(),
-                }))
-            }
+            // White space is processed by the layouter, which knows the `white-space` of the text
+            NodeData::Text(data) => RenderNodeData::Text(Box::new(TextData {
+                text: data.string_value(),
+                layout: Vec::new(),
+            })),
             NodeData::Document(_) => RenderNodeData::Document,
             _ => return ControlFlow::Drop,
         })
     }
 }
 
-fn pre_transform_text(text: String) -> String {
-    let mut new_text = String::with_capacity(text.len());
-
-    let mut last_was_ws = false;
-    for c in text.chars() {
-        if c.is_whitespace() {
-            if !last_was_ws {
-                new_text.push(' ');
-                last_was_ws = true;
-            }
-        } else {
-            new_text.push(c);
-            last_was_ws = false;
-        }
-    }
-
-    new_text
-}
-
 pub struct RenderTreeNode<C: HasLayouter + HasFontManager> {
     pub id: NodeId,
     pub properties: C::CssPropertyMap,
@@ -727,7 +908,10 @@ impl<C: HasLayouter> Debug for RenderTreeNode<C> {
 impl<C: HasLayouter> RenderTreeNode<C> {
     /// Returns true if the node is an element node
     pub fn is_element(&self) -> bool {
-        matches!(self.data, RenderNodeData::Element { .. })
+        matches!(
+            self.data,
+            RenderNodeData::Element { .. } | RenderNodeData::Svg(_) | RenderNodeData::Control { .. }
+        )
     }
 
     /// Returns true if the node is a text node
@@ -746,6 +930,13 @@ impl<C: HasLayouter> RenderTreeNode<C> {
             return true;
         }
 
+        // Floated elements are block-level, whatever their display is
+        if let Some(float) = self.properties.get("float").and_then(|prop| prop.as_string()) {
+            if float == "left" || float == "right" {
+                return false;
+            }
+        }
+
         if let Some(d) = self.properties.get("display").and_then(|prop| {
             let val = prop.as_string()?;
 
@@ -771,30 +962,30 @@ impl<C: HasLayouter> RenderTreeNode<C> {
 
 impl<C: HasLayouter> HasTextLayout<C> for RenderTreeNode<C> {
     fn clear_text_layout(&mut self) {
-        if let RenderNodeData::Text(text) = &mut self.data {
-            text.layout.clear();
+        if let Some(layout) = self.get_text_layouts_mut() {
+            layout.clear();
         }
     }
 
     fn add_text_layout(&mut self, layout: <C::Layouter as Layouter<C>>::TextLayout) {
-        if let RenderNodeData::Text(text) = &mut self.data {
-            text.layout.push(layout);
+        if let Some(layouts) = self.get_text_layouts_mut() {
+            layouts.push(layout);
         }
     }
 
     fn get_text_layouts(&self) -> Option<&[<C::Layouter as Layouter<C>>::TextLayout]> {
-        if let RenderNodeData::Text(text) = &self.data {
-            Some(&text.layout)
-        } else {
-            None
+        match &self.data {
+            RenderNodeData::Text(text) => Some(&text.layout),
+            RenderNodeData::Control { layout, .. } => Some(layout),
+            _ => None,
         }
     }
 
     fn get_text_layouts_mut(&mut self) -> Option<&mut Vec<<C::Layouter as Layouter<C>>::TextLayout>> {
-        if let RenderNodeData::Text(text) = &mut self.data {
-            Some(&mut text.layout)
-        } else {
-            None
+        match &mut self.data {
+            RenderNodeData::Text(text) => Some(&mut text.layout),
+            RenderNodeData::Control { layout, .. } => Some(layout),
+            _ => None,
         }
     }
 }
@@ -830,6 +1021,64 @@ impl<C: HasLayouter> LayoutNode<C> for RenderTreeNode<C> {
     fn is_anon_inline_parent(&self) -> bool {
         matches!(self.data, RenderNodeData::<C>::AnonymousInline)
     }
+
+    fn intrinsic_size(&self) -> Option<IntrinsicSize> {
+        match &self.data {
+            RenderNodeData::Svg(svg) => Some(svg::intrinsic_size(&svg.attributes)),
+            RenderNodeData::Control { control, .. } => {
+                let font_size = self.properties.get("font-size").map_or(16.0, CssProperty::unit_to_px);
+
+                Some(control.intrinsic_size(font_size))
+            }
+            _ => None,
+        }
+    }
+
+    fn replaced_text(&self) -> Option<ReplacedText> {
+        match &self.data {
+            RenderNodeData::Control { control, .. } => control.shown.clone(),
+            _ => None,
+        }
+    }
+}
+
+/// Returns true for `<svg>` elements that are not nested in another svg element
+fn is_svg_root<C: HasDocument>(data: &NodeData<C>) -> bool {
+    if let NodeData::Element(element) = data {
+        return element.name() == "svg" && element.namespace() == svg::SVG_NAMESPACE;
+    }
+
+    false
+}
+
+/// Returns the font faces of all stylesheets in the document. Relative source urls are resolved against
+/// the url of the stylesheet they are declared in.
+fn collect_font_faces<C: HasDocument>(doc: &C::Document) -> Vec<FontFaceDescriptor> {
+    let doc_url = doc.url();
+
+    let mut font_faces = Vec::new();
+    for sheet in doc.stylesheets() {
+        let base = doc_url
+            .as_ref()
+            .and_then(|url| url.join(sheet.url()).ok())
+            .or(doc_url.clone());
+
+        for face in sheet.font_faces() {
+            let mut face = face.clone();
+
+            for source in &mut face.sources {
+                if let FontFaceSource::Url { url, .. } = source {
+                    if let Some(resolved) = base.as_ref().and_then(|base| base.join(url).ok()) {
+                        *url = resolved.to_string();
+                    }
+                }
+            }
+
+            font_faces.push(face);
+        }
+    }
+
+    font_faces
 }
 
 /// Generates a render tree for the given document based on its loaded stylesheets
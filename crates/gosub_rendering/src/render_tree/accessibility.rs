@@ -0,0 +1,691 @@
+//! Accessibility tree. The tree is computed from the render tree, so elements that are not rendered are left out of
+//! it. Every element gets the role of its `role` attribute, or else the implicit role of its HTML semantics, and a
+//! name following the accessible name computation: `aria-labelledby`, `aria-label`, the native label of the element
+//! (like the `alt` of an image or the `<label>` of a control), its content for roles that are named by their
+//! content, and its `title`. Elements without a role or a name, like `<div>` and `<span>`, are left out and their
+//! children take their place.
+
+use crate::outline::outline_box;
+use crate::render_tree::form::{collapse_white_space, ControlKind};
+use crate::render_tree::{RenderNodeData, RenderTree};
+use cow_utils::CowUtils;
+use gosub_interface::accessibility::{AccessNode, AccessibilityTree, Bounds, Role, Toggled};
+use gosub_interface::config::HasLayouter;
+use gosub_interface::css3::CssProperty;
+use gosub_interface::layout::Layout;
+use gosub_interface::render_tree::RenderTreeNode as _;
+use gosub_shared::geo::Point;
+use gosub_shared::node::NodeId;
+use std::collections::HashMap;
+
+/// Parses a `role` attribute: the first role in the list that is known
+#[must_use]
+pub fn parse_role(value: &str) -> Option<Role> {
+    value.split_ascii_whitespace().find_map(|role| {
+        Some(match role.cow_to_ascii_lowercase().as_ref() {
+            "alert" => Role::Alert,
+            "alertdialog" => Role::AlertDialog,
+            "article" => Role::Article,
+            "banner" => Role::Banner,
+            "blockquote" => Role::Blockquote,
+            "button" => Role::Button,
+            "cell" | "gridcell" => Role::Cell,
+            "checkbox" => Role::CheckBox,
+            "columnheader" => Role::ColumnHeader,
+            "combobox" => Role::ComboBox,
+            "complementary" => Role::Complementary,
+            "contentinfo" => Role::ContentInfo,
+            "dialog" => Role::Dialog,
+            "document" => Role::Document,
+            "figure" => Role::Figure,
+            "form" => Role::Form,
+            "generic" | "none" | "presentation" => Role::GenericContainer,
+            "group" => Role::Group,
+            "heading" => Role::Heading,
+            "img" | "image" => Role::Image,
+            "link" => Role::Link,
+            "list" => Role::List,
+            "listbox" => Role::ListBox,
+            "listitem" => Role::ListItem,
+            "main" => Role::Main,
+            "menu" => Role::Menu,
+            "menubar" => Role::MenuBar,
+            "menuitem" | "menuitemcheckbox" | "menuitemradio" => Role::MenuItem,
+            "meter" => Role::Meter,
+            "navigation" => Role::Navigation,
+            "option" => Role::ListBoxOption,
+            "paragraph" => Role::Paragraph,
+            "progressbar" => Role::ProgressIndicator,
+            "radio" => Role::RadioButton,
+            "region" => Role::Region,
+            "row" => Role::Row,
+            "rowgroup" => Role::RowGroup,
+            "rowheader" => Role::RowHeader,
+            "search" => Role::Search,
+            "searchbox" => Role::SearchInput,
+            "separator" => Role::Separator,
+            "slider" => Role::Slider,
+            "status" => Role::Status,
+            "switch" => Role::Switch,
+            "tab" => Role::Tab,
+            "table" | "grid" => Role::Table,
+            "tablist" => Role::TabList,
+            "tabpanel" => Role::TabPanel,
+            "textbox" => Role::TextInput,
+            "tooltip" => Role::Tooltip,
+            "tree" => Role::Tree,
+            "treeitem" => Role::TreeItem,
+            _ => return None,
+        })
+    })
+}
+
+/// Returns the role an element has by its HTML semantics, or None for elements that have no meaning of their own.
+/// `in_section` tells that the element is in an `<article>`, `<aside>`, `<main>`, `<nav>` or `<section>`, where a
+/// `<header>` or `<footer>` is not about the whole page.
+#[must_use]
+pub fn implicit_role(name: &str, attributes: &HashMap<String, String>, in_section: bool) -> Option<Role> {
+    let has = |name: &str| attributes.contains_key(name);
+    let is = |name: &str, value: &str| {
+        attributes
+            .get(name)
+            .is_some_and(|attribute| attribute.trim().eq_ignore_ascii_case(value))
+    };
+    // A select that shows more than one option is a list box
+    let list_box = has("multiple")
+        || attributes
+            .get("size")
+            .is_some_and(|size| size.trim().parse::<u32>().is_ok_and(|size| size > 1));
+
+    Some(match name {
+        "a" | "area" if has("href") => Role::Link,
+        "article" => Role::Article,
+        "aside" => Role::Complementary,
+        "blockquote" => Role::Blockquote,
+        "button" => Role::Button,
+        "details" => Role::Details,
+        "dialog" => Role::Dialog,
+        "fieldset" | "optgroup" => Role::Group,
+        "figure" => Role::Figure,
+        "footer" if !in_section => Role::ContentInfo,
+        "form" => Role::Form,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => Role::Heading,
+        "header" if !in_section => Role::Banner,
+        "hr" => Role::Separator,
+        "iframe" => Role::Iframe,
+        // An image with an empty `alt` is decoration
+        "img" if attributes.get("alt").is_some_and(String::is_empty) => return None,
+        "img" | "svg" => Role::Image,
+        "input" => {
+            let ty = attributes.get("type").map(|ty| ty.trim().cow_to_ascii_lowercase());
+            match ty.as_deref() {
+                Some("hidden") => return None,
+                Some("button" | "submit" | "reset" | "image") => Role::Button,
+                Some("checkbox") => Role::CheckBox,
+                Some("radio") => Role::RadioButton,
+                Some("range") => Role::Slider,
+                Some("search") => Role::SearchInput,
+                Some("email") => Role::EmailInput,
+                Some("number") => Role::NumberInput,
+                Some("password") => Role::PasswordInput,
+                Some("tel") => Role::PhoneNumberInput,
+                Some("url") => Role::UrlInput,
+                _ => Role::TextInput,
+            }
+        }
+        "li" => Role::ListItem,
+        "main" => Role::Main,
+        "menu" | "ol" | "ul" => Role::List,
+        "meter" => Role::Meter,
+        "nav" => Role::Navigation,
+        "option" => Role::ListBoxOption,
+        "output" => Role::Status,
+        "p" => Role::Paragraph,
+        "progress" => Role::ProgressIndicator,
+        "search" => Role::Search,
+        // A section is only a landmark when it has a name, which the caller checks
+        "section" => Role::Region,
+        "select" if list_box => Role::ListBox,
+        "select" => Role::ComboBox,
+        "summary" => Role::DisclosureTriangle,
+        "table" => Role::Table,
+        "tbody" | "tfoot" | "thead" => Role::RowGroup,
+        "td" => Role::Cell,
+        "textarea" => Role::MultilineTextInput,
+        "th" if is("scope", "row") => Role::RowHeader,
+        "th" => Role::ColumnHeader,
+        "tr" => Role::Row,
+        _ => return None,
+    })
+}
+
+/// Returns true for roles that take their name from their content when nothing else names them
+#[must_use]
+pub fn named_by_content(role: Role) -> bool {
+    matches!(
+        role,
+        Role::Button
+            | Role::Cell
+            | Role::CheckBox
+            | Role::ColumnHeader
+            | Role::DisclosureTriangle
+            | Role::Heading
+            | Role::Link
+            | Role::ListBoxOption
+            | Role::MenuItem
+            | Role::RadioButton
+            | Role::RowHeader
+            | Role::Switch
+            | Role::Tab
+            | Role::Tooltip
+            | Role::TreeItem
+    )
+}
+
+/// Elements by their `id` attribute and the `<label>` elements of the controls, in tree order
+#[derive(Default)]
+struct References {
+    ids: HashMap<String, NodeId>,
+    labels: HashMap<NodeId, Vec<NodeId>>,
+}
+
+impl<C: HasLayouter<LayoutTree = Self>> RenderTree<C> {
+    /// Computes the accessibility tree. `focused` is the element that has the keyboard focus.
+    #[must_use]
+    pub fn accessibility_tree(&self, focused: Option<NodeId>) -> AccessibilityTree {
+        let mut references = References::default();
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            let Some(node) = self.nodes.get(&id) else {
+                continue;
+            };
+            if let Some(name) = node.element_attributes().and_then(|attributes| attributes.get("id")) {
+                references.ids.entry(name.clone()).or_insert(id);
+            }
+            if node.name == "label" {
+                if let Some(control) = self.labeled_control(id) {
+                    references.labels.entry(control).or_default().push(id);
+                }
+            }
+            stack.extend(node.children.iter().rev());
+        }
+
+        let mut nodes = Vec::new();
+        let mut root = AccessNode::new(self.root.into(), Role::Document);
+        root.bounds = self.nodes.get(&self.root).map(|node| {
+            let size = node.layout.size();
+            Bounds {
+                x0: 0.0,
+                y0: 0.0,
+                x1: size.width,
+                y1: size.height,
+            }
+        });
+        nodes.push(root);
+
+        let children = self.access_children(self.root, Point::ZERO, false, &references, &mut nodes);
+        nodes[0].children = children;
+
+        let focus = focused
+            .map(u64::from)
+            .filter(|id| nodes.iter().any(|node| node.id == *id))
+            .unwrap_or(self.root.into());
+
+        AccessibilityTree {
+            nodes,
+            root: self.root.into(),
+            focus,
+        }
+    }
+
+    /// Adds the accessibility nodes of the children of a node, and returns their ids
+    fn access_children(
+        &self,
+        id: NodeId,
+        pos: Point,
+        in_section: bool,
+        references: &References,
+        nodes: &mut Vec<AccessNode>,
+    ) -> Vec<u64> {
+        let Some(node) = self.nodes.get(&id) else {
+            return Vec::new();
+        };
+        let in_section = in_section || matches!(node.name.as_str(), "article" | "aside" | "main" | "nav" | "section");
+
+        node.children
+            .iter()
+            .flat_map(|child| self.access_node(*child, pos, in_section, references, nodes))
+            .collect()
+    }
+
+    /// Adds the accessibility node of a node and its descendants. Returns the id of the node, or the ids of its
+    /// children when the node is left out of the tree.
+    fn access_node(
+        &self,
+        id: NodeId,
+        parent: Point,
+        in_section: bool,
+        references: &References,
+        nodes: &mut Vec<AccessNode>,
+    ) -> Vec<u64> {
+        let Some(node) = self.nodes.get(&id) else {
+            return Vec::new();
+        };
+        let rel = node.layout.rel_pos();
+        let pos = Point::new(parent.x + rel.x, parent.y + rel.y);
+        let bounds = outline_box::<C>(self, id).map(|area| Bounds {
+            x0: pos.x + area.pos.x,
+            y0: pos.y + area.pos.y,
+            x1: pos.x + area.pos.x + area.size.width,
+            y1: pos.y + area.pos.y + area.size.height,
+        });
+
+        if let RenderNodeData::Text(text) = &node.data {
+            let text = collapse_white_space(&text.text);
+            if text.is_empty() {
+                return Vec::new();
+            }
+
+            let mut access = AccessNode::new(id.into(), Role::Label);
+            access.label = Some(text);
+            access.bounds = bounds;
+            nodes.push(access);
+            return vec![id.into()];
+        }
+
+        let Some(attributes) = node.element_attributes() else {
+            return self.access_children(id, pos, in_section, references, nodes);
+        };
+        if attribute_is_true(attributes, "aria-hidden") {
+            return Vec::new();
+        }
+
+        let focusable = self.is_focusable(id);
+        let explicit = attributes.get("role").and_then(|role| parse_role(role));
+        let mut role = explicit.or_else(|| implicit_role(&node.name, attributes, in_section));
+
+        let label = self.accessible_name(id, role.unwrap_or(Role::GenericContainer), references);
+
+        // A generic element, or a section without a name, is left out unless it can be focused or is labelled
+        let generic = match role {
+            None | Some(Role::GenericContainer) => true,
+            Some(Role::Region) if explicit.is_none() => label.is_none(),
+            _ => false,
+        };
+        let labelled = attributes.contains_key("aria-label") || attributes.contains_key("aria-labelledby");
+        if generic && !focusable && !labelled {
+            return self.access_children(id, pos, in_section, references, nodes);
+        }
+        if generic {
+            role = Some(Role::GenericContainer);
+        }
+        let role = role.unwrap_or(Role::GenericContainer);
+
+        let idx = nodes.len();
+        let mut access = AccessNode::new(id.into(), role);
+        access.label = label;
+        access.description = self.accessible_description(id, access.label.as_deref(), references);
+        access.bounds = bounds;
+        access.focusable = focusable;
+        self.access_states(id, role, attributes, &mut access);
+        nodes.push(access);
+
+        let children = self.access_children(id, pos, in_section, references, nodes);
+        nodes[idx].children = children;
+
+        vec![id.into()]
+    }
+
+    /// Sets the value, url and states of the node of an element
+    fn access_states(&self, id: NodeId, role: Role, attributes: &HashMap<String, String>, access: &mut AccessNode) {
+        let aria = |name: &str| attributes.get(name).map(|value| value.trim().cow_to_ascii_lowercase());
+
+        let control = self.control(id);
+        let state = control.map(|control| {
+            self.controls
+                .get(&id)
+                .cloned()
+                .unwrap_or_else(|| control.initial_state())
+        });
+
+        if let (Some(control), Some(state)) = (control, &state) {
+            match control.kind {
+                // The bullets that are shown instead of the password, but not the placeholder of an empty field
+                ControlKind::Text { password: true } if control.shows_placeholder(state) => {
+                    access.value = Some(String::new());
+                }
+                ControlKind::Text { password: true } => {
+                    access.value = control.shown_text(state).map(|shown| shown.text);
+                }
+                ControlKind::Text { .. } | ControlKind::TextArea => access.value = Some(state.value.clone()),
+                ControlKind::Checkbox | ControlKind::Radio => {
+                    access.toggled = Some(if state.checked { Toggled::True } else { Toggled::False });
+                }
+                ControlKind::Select => {
+                    access.value = state
+                        .selected
+                        .and_then(|idx| control.options.get(idx))
+                        .map(|option| collapse_white_space(&option.label));
+                    access.expanded = Some(state.open);
+                }
+                ControlKind::Button => {}
+            }
+        }
+
+        let toggled = match aria("aria-checked").or_else(|| aria("aria-pressed")).as_deref() {
+            Some("true") => Some(Toggled::True),
+            Some("false") => Some(Toggled::False),
+            Some("mixed") => Some(Toggled::Mixed),
+            _ => None,
+        };
+        if toggled.is_some() && control.is_none() {
+            access.toggled = toggled;
+        }
+
+        if let Some(expanded) = aria("aria-expanded") {
+            access.expanded = Some(expanded == "true");
+        }
+        if let Some(selected) = aria("aria-selected") {
+            access.selected = Some(selected == "true");
+        }
+
+        // A heading without a level of its own is on level 2
+        let heading_level = || {
+            let name = self.nodes.get(&id).map_or("", |node| node.name.as_str());
+            (role == Role::Heading).then(|| node_level(name).unwrap_or(2))
+        };
+        access.level = aria("aria-level")
+            .and_then(|level| level.parse::<usize>().ok())
+            .filter(|level| *level > 0)
+            .or_else(heading_level);
+
+        if role == Role::Link {
+            access.url = self.link_url(id).map(String::from);
+        }
+
+        access.disabled = self.is_disabled(id) || attribute_is_true(attributes, "aria-disabled");
+        access.required = attributes.contains_key("required") || attribute_is_true(attributes, "aria-required");
+        access.read_only = (control.is_some_and(|control| control.kind.is_text())
+            && attributes.contains_key("readonly"))
+            || attribute_is_true(attributes, "aria-readonly");
+    }
+
+    /// Computes the accessible name of an element with the role
+    fn accessible_name(&self, id: NodeId, role: Role, references: &References) -> Option<String> {
+        let node = self.nodes.get(&id)?;
+        let attributes = node.element_attributes()?;
+        let attribute = |name: &str| {
+            attributes
+                .get(name)
+                .map(|value| collapse_white_space(value))
+                .filter(|value| !value.is_empty())
+        };
+
+        if let Some(ids) = attributes.get("aria-labelledby") {
+            let name = self.referenced_text(ids, references);
+            if !name.is_empty() {
+                return Some(name);
+            }
+        }
+        if let Some(label) = attribute("aria-label") {
+            return Some(label);
+        }
+
+        let ty = attributes.get("type").map(|ty| ty.trim().cow_to_ascii_lowercase());
+        let native = match node.name.as_str() {
+            "img" | "area" => attribute("alt"),
+            "input" if ty.as_deref() == Some("image") => attribute("alt"),
+            "input" if matches!(ty.as_deref(), Some("button" | "submit" | "reset")) => attributes
+                .get("value")
+                .map(|value| collapse_white_space(value))
+                .or_else(|| match ty.as_deref() {
+                    Some("submit") => Some("Submit".to_owned()),
+                    Some("reset") => Some("Reset".to_owned()),
+                    _ => None,
+                }),
+            "fieldset" => self.child_text(id, "legend"),
+            "figure" => self.child_text(id, "figcaption"),
+            "table" => self.child_text(id, "caption"),
+            _ => None,
+        };
+        if native.is_some() {
+            return native;
+        }
+
+        if let Some(labels) = references.labels.get(&id) {
+            let name = labels
+                .iter()
+                .map(|label| self.text_alternative(*label, Some(id)))
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            if !name.is_empty() {
+                return Some(name);
+            }
+        }
+
+        if named_by_content(role) {
+            let name = self.text_alternative(id, None);
+            if !name.is_empty() {
+                return Some(name);
+            }
+        }
+
+        attribute("title").or_else(|| {
+            self.control(id)
+                .filter(|control| control.kind.is_text())
+                .and_then(|_| attribute("placeholder"))
+        })
+    }
+
+    /// Computes the accessible description of an element: the text of the elements in its `aria-describedby`, or
+    /// else its `title` when that is not its name already
+    fn accessible_description(&self, id: NodeId, label: Option<&str>, references: &References) -> Option<String> {
+        let attributes = self.nodes.get(&id)?.element_attributes()?;
+
+        if let Some(ids) = attributes.get("aria-describedby") {
+            let description = self.referenced_text(ids, references);
+            if !description.is_empty() {
+                return Some(description);
+            }
+        }
+
+        attributes
+            .get("title")
+            .map(|title| collapse_white_space(title))
+            .filter(|title| !title.is_empty() && Some(title.as_str()) != label)
+    }
+
+    /// Returns the text of the elements in a list of ids, joined by spaces
+    fn referenced_text(&self, ids: &str, references: &References) -> String {
+        ids.split_ascii_whitespace()
+            .filter_map(|name| references.ids.get(name))
+            .map(|id| self.text_alternative(*id, None))
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Returns the text of the first child element with the name
+    fn child_text(&self, id: NodeId, name: &str) -> Option<String> {
+        let child = self
+            .nodes
+            .get(&id)?
+            .children
+            .iter()
+            .find(|child| self.nodes.get(child).is_some_and(|node| node.name == name))?;
+
+        Some(self.text_alternative(*child, None)).filter(|text| !text.is_empty())
+    }
+
+    /// Returns the text a node contributes to the name of an ancestor: its text, the label of an element that has one,
+    /// the alternative text of an image and the value of a control. `skip` is the control that is being named, which
+    /// does not contribute its own value.
+    fn text_alternative(&self, id: NodeId, skip: Option<NodeId>) -> String {
+        let mut text = String::new();
+        self.append_text_alternative(id, skip, true, &mut text);
+        collapse_white_space(&text)
+    }
+
+    fn append_text_alternative(&self, id: NodeId, skip: Option<NodeId>, root: bool, text: &mut String) {
+        let Some(node) = self.nodes.get(&id) else {
+            return;
+        };
+
+        if let RenderNodeData::Text(data) = &node.data {
+            text.push_str(&data.text);
+            return;
+        }
+
+        let attributes = node.element_attributes();
+        if attributes.is_some_and(|attributes| attribute_is_true(attributes, "aria-hidden")) {
+            return;
+        }
+
+        // Block boxes are separated from the text around them
+        let block = !root
+            && node.is_element()
+            && !self
+                .get_property(id, "display")
+                .and_then(|display| display.as_string())
+                .is_some_and(|display| display.starts_with("inline"));
+        if block {
+            text.push(' ');
+        }
+
+        let label = attributes
+            .and_then(|attributes| attributes.get("aria-label"))
+            .filter(|label| !root && !label.trim().is_empty());
+
+        if let Some(label) = label {
+            text.push_str(label);
+        } else if node.name == "img" {
+            if let Some(alt) = attributes.and_then(|attributes| attributes.get("alt")) {
+                text.push_str(alt);
+            }
+        } else if let Some(control) = self.control(id).filter(|_| Some(id) != skip) {
+            let state = self
+                .controls
+                .get(&id)
+                .cloned()
+                .unwrap_or_else(|| control.initial_state());
+            match control.kind {
+                ControlKind::Text { password: false } | ControlKind::TextArea => text.push_str(&state.value),
+                ControlKind::Select => {
+                    if let Some(option) = state.selected.and_then(|idx| control.options.get(idx)) {
+                        text.push_str(&option.label);
+                    }
+                }
+                ControlKind::Button => {
+                    if let Some(value) = control.attributes.get("value") {
+                        text.push_str(value);
+                    }
+                }
+                _ => {}
+            }
+        } else {
+            for child in &node.children {
+                self.append_text_alternative(*child, skip, false, text);
+            }
+        }
+
+        if block {
+            text.push(' ');
+        }
+    }
+}
+
+/// Returns the level of a heading element
+fn node_level(name: &str) -> Option<usize> {
+    let level = name.strip_prefix('h')?.parse::<usize>().ok()?;
+    (1..=6).contains(&level).then_some(level)
+}
+
+/// Returns true when an ARIA attribute is `true`
+fn attribute_is_true(attributes: &HashMap<String, String>, name: &str) -> bool {
+    attributes
+        .get(name)
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::attributes;
+
+    #[test]
+    fn explicit_roles() {
+        assert_eq!(parse_role("button"), Some(Role::Button));
+        assert_eq!(parse_role(" Switch checkbox"), Some(Role::Switch));
+        assert_eq!(parse_role("fancy-widget tab"), Some(Role::Tab));
+        assert_eq!(parse_role("presentation"), Some(Role::GenericContainer));
+        assert_eq!(parse_role("unknown"), None);
+        assert_eq!(parse_role(""), None);
+    }
+
+    #[test]
+    fn implicit_roles() {
+        let none = attributes(&[]);
+
+        assert_eq!(
+            implicit_role("a", &attributes(&[("href", "/")]), false),
+            Some(Role::Link)
+        );
+        assert_eq!(implicit_role("a", &none, false), None);
+        assert_eq!(implicit_role("h3", &none, false), Some(Role::Heading));
+        assert_eq!(implicit_role("div", &none, false), None);
+        assert_eq!(implicit_role("header", &none, false), Some(Role::Banner));
+        assert_eq!(implicit_role("header", &none, true), None);
+        assert_eq!(implicit_role("img", &attributes(&[("alt", "")]), false), None);
+        assert_eq!(
+            implicit_role("img", &attributes(&[("alt", "Logo")]), false),
+            Some(Role::Image)
+        );
+        assert_eq!(
+            implicit_role("input", &attributes(&[("type", "CHECKBOX")]), false),
+            Some(Role::CheckBox)
+        );
+        assert_eq!(implicit_role("input", &none, false), Some(Role::TextInput));
+        assert_eq!(implicit_role("input", &attributes(&[("type", "hidden")]), false), None);
+        assert_eq!(implicit_role("select", &none, false), Some(Role::ComboBox));
+        assert_eq!(
+            implicit_role("select", &attributes(&[("multiple", "")]), false),
+            Some(Role::ListBox)
+        );
+        assert_eq!(
+            implicit_role("th", &attributes(&[("scope", "row")]), false),
+            Some(Role::RowHeader)
+        );
+    }
+
+    #[test]
+    fn names_and_levels() {
+        assert!(named_by_content(Role::Link));
+        assert!(!named_by_content(Role::TextInput));
+        assert_eq!(collapse_white_space("  Sign \n  in "), "Sign in");
+        assert_eq!(node_level("h4"), Some(4));
+        assert_eq!(node_level("h7"), None);
+        assert_eq!(node_level("header"), None);
+    }
+
+    #[test]
+    fn serialize_tree() {
+        let mut button = AccessNode::new(2, Role::Button);
+        button.label = Some("Save".to_owned());
+        button.focusable = true;
+
+        let mut root = AccessNode::new(1, Role::Document);
+        root.children = vec![2];
+
+        let tree = AccessibilityTree {
+            nodes: vec![root, button],
+            root: 1,
+            focus: 2,
+        };
+
+        assert_eq!(
+            serde_json::to_string(&tree).unwrap(),
+            r#"{"nodes":[{"id":1,"role":"Document","children":[2]},{"id":2,"role":"Button","label":"Save","focusable":true}],"root":1,"focus":2}"#
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Keyboard focus. Which elements can be focused and the order in which Tab moves the focus between them follow the
+//! `tabindex` attributes: elements with a positive index come first, in increasing order, followed by the elements
+//! with an index of zero and the elements that are focusable by default, in tree order. Elements that are not
+//! rendered can not be focused, so the order is read from the render tree.
+
+use crate::render_tree::{RenderNodeData, RenderTree};
+use gosub_interface::config::HasLayouter;
+use gosub_interface::css3::CssProperty;
+use gosub_interface::render_tree::RenderTreeNode as _;
+use gosub_shared::node::NodeId;
+use std::collections::HashMap;
+use url::Url;
+
+/// Parses a `tabindex` attribute: an optionally signed integer, surrounded by white space
+#[must_use]
+pub fn parse_tab_index(value: &str) -> Option<i32> {
+    let value = value.trim_matches(|c: char| c.is_ascii_whitespace());
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    // Indices that are too large are clamped, which keeps their order
+    Some(value.parse::<i64>().map_or_else(
+        |_| if value.starts_with('-') { i32::MIN } else { i32::MAX },
+        |index| index.clamp(i32::MIN.into(), i32::MAX.into()) as i32,
+    ))
+}
+
+/// Returns true for elements that are focusable without a `tabindex` attribute
+#[must_use]
+pub fn is_focusable_by_default(name: &str, attributes: &HashMap<String, String>) -> bool {
+    match name {
+        "a" | "area" => attributes.contains_key("href"),
+        "input" => !attributes
+            .get("type")
+            .is_some_and(|ty| ty.trim().eq_ignore_ascii_case("hidden")),
+        "button" | "select" | "textarea" | "iframe" | "summary" => true,
+        _ => attributes
+            .get("contenteditable")
+            .is_some_and(|value| !value.eq_ignore_ascii_case("false")),
+    }
+}
+
+/// Orders focusable elements, given in tree order with their tab index, for sequential focus navigation. Elements
+/// with a negative index are left out.
+#[must_use]
+pub fn sequential_order(elements: &[(NodeId, i32)]) -> Vec<NodeId> {
+    let mut positive = elements
+        .iter()
+        .filter(|(_, index)| *index > 0)
+        .copied()
+        .collect::<Vec<_>>();
+    // The sort is stable, so elements with the same index stay in tree order
+    positive.sort_by_key(|(_, index)| *index);
+
+    positive
+        .into_iter()
+        .chain(elements.iter().filter(|(_, index)| *index == 0).copied())
+        .map(|(id, _)| id)
+        .collect()
+}
+
+impl<C: HasLayouter<LayoutTree = Self>> RenderTree<C> {
+    /// Returns the tab index of an element: its valid `tabindex` attribute, or else 0 when it is focusable by
+    /// default. None when the element can not be focused.
+    #[must_use]
+    pub fn tab_index(&self, id: NodeId) -> Option<i32> {
+        let node = self.nodes.get(&id)?;
+        let attributes = node.element_attributes()?;
+
+        attributes
+            .get("tabindex")
+            .and_then(|value| parse_tab_index(value))
+            .or_else(|| is_focusable_by_default(&node.name, attributes).then_some(0))
+    }
+
+    /// Returns true when the element can be focused: it has a tab index, is not disabled and is visible
+    #[must_use]
+    pub fn is_focusable(&self, id: NodeId) -> bool {
+        if self.tab_index(id).is_none() || self.is_disabled(id) {
+            return false;
+        }
+
+        let hidden = self
+            .get_property(id, "visibility")
+            .and_then(|prop| prop.as_string())
+            .is_some_and(|visibility| visibility == "hidden" || visibility == "collapse");
+
+        !hidden
+    }
+
+    /// Returns true for disabled form fields, including the fields in a disabled `<fieldset>`
+    #[must_use]
+    pub fn is_disabled(&self, id: NodeId) -> bool {
+        if let Some(field) = self.forms.field(id) {
+            return field.disabled;
+        }
+
+        self.nodes.get(&id).is_some_and(|node| {
+            matches!(node.name.as_str(), "button" | "input" | "select" | "textarea")
+                && node
+                    .element_attributes()
+                    .is_some_and(|attrs| attrs.contains_key("disabled"))
+        })
+    }
+
+    /// Returns the element that gets the focus when the node is clicked: the closest focusable element that
+    /// contains the node
+    #[must_use]
+    pub fn focus_target(&self, id: NodeId) -> Option<NodeId> {
+        let mut current = Some(id);
+
+        while let Some(id) = current {
+            if self.is_focusable(id) {
+                return Some(id);
+            }
+            current = self.nodes.get(&id)?.parent;
+        }
+
+        None
+    }
+
+    /// Returns the elements that Tab moves the focus between, in the order it visits them
+    #[must_use]
+    pub fn sequential_focus_order(&self) -> Vec<NodeId> {
+        let mut elements = Vec::new();
+        let mut stack = vec![self.root];
+
+        while let Some(id) = stack.pop() {
+            let Some(node) = self.nodes.get(&id) else {
+                continue;
+            };
+            if self.is_focusable(id) {
+                if let Some(index) = self.tab_index(id) {
+                    elements.push((id, index));
+                }
+            }
+            stack.extend(node.children.iter().rev());
+        }
+
+        sequential_order(&elements)
+    }
+
+    /// Returns the element that gets the focus when Tab (or Shift+Tab when `backward`) is pressed while `current`
+    /// has it. Without a focused element, the focus moves to the first (or last) element. None when the focus moves
+    /// past the end of the order, so it leaves the page.
+    #[must_use]
+    pub fn next_focus(&self, current: Option<NodeId>, backward: bool) -> Option<NodeId> {
+        let order = self.sequential_focus_order();
+
+        let Some(pos) = current.and_then(|current| order.iter().position(|id| *id == current)) else {
+            return if backward { order.last() } else { order.first() }.copied();
+        };
+
+        if backward {
+            pos.checked_sub(1).map(|pos| order[pos])
+        } else {
+            order.get(pos + 1).copied()
+        }
+    }
+
+    /// Returns the link the node is in: the closest `<a>` or `<area>` element with an `href` attribute
+    #[must_use]
+    pub fn link_target(&self, id: NodeId) -> Option<NodeId> {
+        let mut current = Some(id);
+
+        while let Some(id) = current {
+            let node = self.nodes.get(&id)?;
+            if matches!(node.data, RenderNodeData::Element { .. })
+                && matches!(node.name.as_str(), "a" | "area")
+                && node
+                    .element_attributes()
+                    .is_some_and(|attrs| attrs.contains_key("href"))
+            {
+                return Some(id);
+            }
+            current = node.parent;
+        }
+
+        None
+    }
+
+    /// Returns the url a link points to, resolved against the url of the document
+    #[must_use]
+    pub fn link_url(&self, link: NodeId) -> Option<Url> {
+        let href = self.nodes.get(&link)?.element_attributes()?.get("href")?;
+        let base = Url::parse(self.document_url.as_deref()?).ok()?;
+
+        base.join(href.trim()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::attributes;
+
+    #[test]
+    fn parse_tab_indices() {
+        assert_eq!(parse_tab_index("0"), Some(0));
+        assert_eq!(parse_tab_index(" 3 "), Some(3));
+        assert_eq!(parse_tab_index("-1"), Some(-1));
+        assert_eq!(parse_tab_index("+2"), Some(2));
+        assert_eq!(parse_tab_index("99999999999"), Some(i32::MAX));
+        assert_eq!(parse_tab_index(""), None);
+        assert_eq!(parse_tab_index("1.5"), None);
+        assert_eq!(parse_tab_index("-"), None);
+        assert_eq!(parse_tab_index("abc"), None);
+    }
+
+    #[test]
+    fn focusable_elements() {
+        assert!(is_focusable_by_default("a", &attributes(&[("href", "/")])));
+        assert!(!is_focusable_by_default("a", &attributes(&[])));
+        assert!(is_focusable_by_default("input", &attributes(&[("type", "checkbox")])));
+        assert!(!is_focusable_by_default("input", &attributes(&[("type", "Hidden")])));
+        assert!(is_focusable_by_default("button", &attributes(&[])));
+        assert!(is_focusable_by_default("div", &attributes(&[("contenteditable", "")])));
+        assert!(!is_focusable_by_default(
+            "div",
+            &attributes(&[("contenteditable", "false")])
+        ));
+        assert!(!is_focusable_by_default("span", &attributes(&[])));
+    }
+
+    #[test]
+    fn sequential_navigation_order() {
+        let elements = [
+            (NodeId::from(1_usize), 0),
+            (NodeId::from(2_usize), 2),
+            (NodeId::from(3_usize), -1),
+            (NodeId::from(4_usize), 1),
+            (NodeId::from(5_usize), 0),
+            (NodeId::from(6_usize), 2),
+        ];
+
+        assert_eq!(
+            sequential_order(&elements),
+            [4_usize, 2, 6, 1, 5].map(NodeId::from).to_vec()
+        );
+    }
+}
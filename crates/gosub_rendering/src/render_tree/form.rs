@@ -0,0 +1,950 @@
+//! Form controls. `<input>`, `<select>` and `<textarea>` elements are replaced elements: the renderer paints them,
+//! and the text they show is laid out in their content box. `<button>` elements stay ordinary elements with children,
+//! but can be pressed like the buttons of an `<input>`. The state of the controls is kept in the tree apart from the
+//! nodes, so it survives restyling the nodes.
+
+use crate::render_tree::{RenderNodeData, RenderTree, RenderTreeNode};
+use cow_utils::CowUtils;
+use gosub_html5::node::HTML_NAMESPACE;
+use gosub_interface::config::{HasDocument, HasLayouter};
+use gosub_interface::document::Document;
+use gosub_interface::layout::{IntrinsicSize, ReplacedText};
+use gosub_interface::node::{ElementDataType, Node as DocumentNode, NodeData, TextDataType};
+use gosub_interface::render_tree::RenderTreeNode as _;
+use gosub_shared::node::NodeId;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+
+/// Width of a character in a text field relative to the font size, for sizing a field by its `size` or `cols`
+const AVERAGE_CHAR_WIDTH: f32 = 0.55;
+/// Height of a line in a `<textarea>` relative to the font size, for sizing it by its `rows`
+const LINE_HEIGHT: f32 = 1.2;
+/// Size of the box of a checkbox or radio button
+pub const CHECK_SIZE: f32 = 13.0;
+/// Room for the arrow after the text of a `<select>`
+pub const SELECT_ARROW_SPACE: f32 = 20.0;
+/// Character that is shown for every character of a password
+const PASSWORD_BULLET: char = '•';
+
+/// Kind of a form control
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlKind {
+    /// Single line text field. A password field shows bullets instead of its value.
+    Text {
+        password: bool,
+    },
+    Checkbox,
+    Radio,
+    /// `<input type="button|submit|reset">`
+    Button,
+    Select,
+    TextArea,
+}
+
+impl ControlKind {
+    /// Returns the kind of control of an element, or None when the element is not a replaced form control. Hidden
+    /// inputs are not rendered, and image and file inputs are not supported.
+    #[must_use]
+    pub fn of(name: &str, attributes: &HashMap<String, String>) -> Option<Self> {
+        match name {
+            "input" => {
+                let ty = attributes.get("type").map(|ty| ty.trim().cow_to_ascii_lowercase());
+
+                Some(match ty.as_deref() {
+                    Some("hidden" | "image" | "file") => return None,
+                    Some("checkbox") => Self::Checkbox,
+                    Some("radio") => Self::Radio,
+                    Some("button" | "submit" | "reset") => Self::Button,
+                    Some("password") => Self::Text { password: true },
+                    _ => Self::Text { password: false },
+                })
+            }
+            "select" => Some(Self::Select),
+            "textarea" => Some(Self::TextArea),
+            _ => None,
+        }
+    }
+
+    /// Returns true for controls whose value is edited as text
+    #[must_use]
+    pub fn is_text(self) -> bool {
+        matches!(self, Self::Text { .. } | Self::TextArea)
+    }
+}
+
+/// Option of a `<select>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectOption {
+    pub label: String,
+    pub value: String,
+    /// The option is selected in the markup
+    pub selected: bool,
+    pub disabled: bool,
+}
+
+/// Data of a form control element
+#[derive(Debug, Clone)]
+pub struct ControlData {
+    pub kind: ControlKind,
+    pub attributes: HashMap<String, String>,
+    /// Options of a `<select>`, including the ones in an `<optgroup>`
+    pub options: Vec<SelectOption>,
+    /// Text content of a `<textarea>`, which is its initial value
+    pub text: String,
+    /// Text that is shown in the control for its current state
+    pub shown: Option<ReplacedText>,
+}
+
+impl ControlData {
+    /// Reads the control from the document. The options of a `<select>` and the text of a `<textarea>` come from
+    /// the descendants of the element, which are not part of the render tree.
+    pub fn from_document<C: HasDocument>(
+        doc: &C::Document,
+        id: NodeId,
+        kind: ControlKind,
+        attributes: HashMap<String, String>,
+    ) -> Self {
+        let mut options = Vec::new();
+        let mut text = String::new();
+
+        match kind {
+            ControlKind::Select => collect_options::<C>(doc, id, &mut options),
+            ControlKind::TextArea => {
+                text = text_content::<C>(doc, id);
+                // A newline directly after the start tag is not part of the value
+                if text.starts_with('\n') {
+                    text.remove(0);
+                }
+            }
+            _ => {}
+        }
+
+        Self {
+            kind,
+            attributes,
+            options,
+            text,
+            shown: None,
+        }
+    }
+
+    /// Returns the state of the control as it is in the markup
+    #[must_use]
+    pub fn initial_state(&self) -> ControlState {
+        let value = match self.kind {
+            ControlKind::Text { .. } => self
+                .attributes
+                .get("value")
+                .map(|value| value.chars().filter(|c| !matches!(c, '\n' | '\r')).collect())
+                .unwrap_or_default(),
+            ControlKind::TextArea => self.text.clone(),
+            _ => String::new(),
+        };
+
+        // A select that allows choosing several options starts with the options selected in the markup, and may
+        // have none. Any other select without a selected option shows its first option.
+        let chosen: BTreeSet<usize> = if self.is_multiple() {
+            (0..self.options.len())
+                .filter(|idx| self.options[*idx].selected)
+                .collect()
+        } else {
+            BTreeSet::new()
+        };
+        let selected = if self.is_multiple() {
+            chosen.first().copied()
+        } else {
+            self.options
+                .iter()
+                .rposition(|option| option.selected)
+                .or_else(|| self.options.iter().position(|option| !option.disabled))
+        };
+
+        ControlState {
+            caret: value.len(),
+            anchor: value.len(),
+            value,
+            checked: self.attributes.contains_key("checked"),
+            selected,
+            chosen,
+            open: false,
+        }
+    }
+
+    /// Returns the text the control shows in the given state, which is None for checkboxes and radio buttons
+    #[must_use]
+    pub fn shown_text(&self, state: &ControlState) -> Option<ReplacedText> {
+        let text = match self.kind {
+            ControlKind::Checkbox | ControlKind::Radio => return None,
+            _ if self.shows_placeholder(state) => self.attributes.get("placeholder").cloned().unwrap_or_default(),
+            ControlKind::Text { password: true } => PASSWORD_BULLET.to_string().repeat(state.value.chars().count()),
+            ControlKind::Text { .. } | ControlKind::TextArea => state.value.clone(),
+            ControlKind::Button => self.attributes.get("value").cloned().unwrap_or_else(|| {
+                match self
+                    .attributes
+                    .get("type")
+                    .map(|ty| ty.trim().cow_to_ascii_lowercase())
+                    .as_deref()
+                {
+                    Some("submit") => "Submit".to_owned(),
+                    Some("reset") => "Reset".to_owned(),
+                    _ => String::new(),
+                }
+            }),
+            ControlKind::Select => state
+                .selected
+                .and_then(|idx| self.options.get(idx))
+                .map(|option| option.label.clone())
+                .unwrap_or_default(),
+        };
+
+        Some(ReplacedText {
+            text,
+            multiline: self.kind == ControlKind::TextArea,
+            end_space: if self.kind == ControlKind::Select {
+                SELECT_ARROW_SPACE
+            } else {
+                0.0
+            },
+            list: if state.open {
+                self.options.iter().map(|option| option.label.clone()).collect()
+            } else {
+                Vec::new()
+            },
+        })
+    }
+
+    /// Returns true when a text control is empty and shows its placeholder instead
+    #[must_use]
+    pub fn shows_placeholder(&self, state: &ControlState) -> bool {
+        self.kind.is_text()
+            && state.value.is_empty()
+            && self.attributes.get("placeholder").is_some_and(|text| !text.is_empty())
+    }
+
+    /// Returns true for a `<select>` that allows choosing several options
+    #[must_use]
+    pub fn is_multiple(&self) -> bool {
+        self.kind == ControlKind::Select && self.attributes.contains_key("multiple")
+    }
+
+    /// Returns true when the value of a text control can not be edited by the user
+    #[must_use]
+    pub fn is_readonly(&self) -> bool {
+        self.kind.is_text() && self.attributes.contains_key("readonly")
+    }
+
+    /// Returns the maximum length of the value of a text control that the user can enter, in UTF-16 code units
+    #[must_use]
+    pub fn max_length(&self) -> Option<usize> {
+        if !self.kind.is_text() {
+            return None;
+        }
+
+        self.attributes
+            .get("maxlength")
+            .and_then(|value| value.trim().parse().ok())
+    }
+
+    /// Returns the byte offset in the shown text for a byte offset in the value
+    #[must_use]
+    pub fn shown_offset(&self, state: &ControlState, offset: usize) -> usize {
+        if self.shows_placeholder(state) {
+            return 0;
+        }
+
+        match self.kind {
+            ControlKind::Text { password: true } => {
+                state.value[..offset.min(state.value.len())].chars().count() * PASSWORD_BULLET.len_utf8()
+            }
+            _ => offset,
+        }
+    }
+
+    /// Returns the byte offset in the value for a byte offset in the shown text
+    #[must_use]
+    pub fn value_offset(&self, state: &ControlState, offset: usize) -> usize {
+        if self.shows_placeholder(state) {
+            return 0;
+        }
+
+        match self.kind {
+            ControlKind::Text { password: true } => state
+                .value
+                .char_indices()
+                .nth(offset / PASSWORD_BULLET.len_utf8())
+                .map_or(state.value.len(), |(idx, _)| idx),
+            _ => floor_boundary(&state.value, offset),
+        }
+    }
+
+    /// Returns the natural size of the control. Dimensions that are missing are taken from the shown text.
+    #[must_use]
+    pub fn intrinsic_size(&self, font_size: f32) -> IntrinsicSize {
+        let count = |name: &str, default: usize| {
+            self.attributes
+                .get(name)
+                .and_then(|value| value.trim().parse::<usize>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(default) as f32
+        };
+        let char_width = font_size * AVERAGE_CHAR_WIDTH;
+
+        let (width, height) = match self.kind {
+            ControlKind::Text { .. } => (Some(count("size", 20) * char_width), None),
+            ControlKind::Checkbox | ControlKind::Radio => (Some(CHECK_SIZE), Some(CHECK_SIZE)),
+            ControlKind::Button | ControlKind::Select => (None, None),
+            ControlKind::TextArea => (
+                Some(count("cols", 20) * char_width),
+                Some(count("rows", 2) * font_size * LINE_HEIGHT),
+            ),
+        };
+
+        IntrinsicSize {
+            width,
+            height,
+            ratio: None,
+        }
+    }
+}
+
+/// State of a form control
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ControlState {
+    /// Value of a text control
+    pub value: String,
+    /// Byte offset of the caret in the value
+    pub caret: usize,
+    /// Byte offset where the selection in the value starts. The selection runs to the caret, so it is empty when the
+    /// anchor is at the caret.
+    pub anchor: usize,
+    /// Checkbox or radio button is checked
+    pub checked: bool,
+    /// Index of the selected option of a `<select>`. For a `<select multiple>` it is the first of the chosen options.
+    pub selected: Option<usize>,
+    /// Indices of the selected options of a `<select multiple>`
+    pub chosen: BTreeSet<usize>,
+    /// The options of a `<select>` are shown in a dropdown
+    pub open: bool,
+}
+
+impl ControlState {
+    /// Returns the selected part of the value
+    #[must_use]
+    pub fn selection(&self) -> Range<usize> {
+        self.caret.min(self.anchor)..self.caret.max(self.anchor)
+    }
+
+    /// Replaces the selection with the text, and puts the caret after it
+    pub fn insert(&mut self, text: &str) {
+        let range = self.selection();
+        self.value.replace_range(range.clone(), text);
+
+        self.caret = range.start + text.len();
+        self.anchor = self.caret;
+    }
+
+    /// Replaces the selection with as much of the text as fits in the maximum length of the value. The length is
+    /// counted in UTF-16 code units, like `maxlength`.
+    pub fn insert_within(&mut self, text: &str, max_length: Option<usize>) {
+        let Some(max_length) = max_length else {
+            self.insert(text);
+            return;
+        };
+
+        let range = self.selection();
+        let kept = utf16_len(&self.value[..range.start]) + utf16_len(&self.value[range.end..]);
+        let mut room = max_length.saturating_sub(kept);
+
+        let end = text
+            .char_indices()
+            .find(|(_, c)| {
+                let fits = c.len_utf16() <= room;
+                room = room.saturating_sub(c.len_utf16());
+                !fits
+            })
+            .map_or(text.len(), |(idx, _)| idx);
+        self.insert(&text[..end]);
+    }
+
+    /// Removes the selection, or the character before the caret when nothing is selected
+    pub fn delete_backward(&mut self) {
+        if self.caret == self.anchor {
+            self.anchor = prev_boundary(&self.value, self.caret);
+        }
+        self.insert("");
+    }
+
+    /// Removes the selection, or the character after the caret when nothing is selected
+    pub fn delete_forward(&mut self) {
+        if self.caret == self.anchor {
+            self.anchor = next_boundary(&self.value, self.caret);
+        }
+        self.insert("");
+    }
+
+    /// Moves the caret to the byte offset. The selection is extended to it, or cleared.
+    pub fn move_to(&mut self, offset: usize, extend: bool) {
+        self.caret = floor_boundary(&self.value, offset);
+        if !extend {
+            self.anchor = self.caret;
+        }
+    }
+
+    /// Moves the caret a character to the left. Without extending, a selection collapses to its start.
+    pub fn move_left(&mut self, extend: bool) {
+        let to = if !extend && self.caret != self.anchor {
+            self.selection().start
+        } else {
+            prev_boundary(&self.value, self.caret)
+        };
+        self.move_to(to, extend);
+    }
+
+    /// Moves the caret a character to the right. Without extending, a selection collapses to its end.
+    pub fn move_right(&mut self, extend: bool) {
+        let to = if !extend && self.caret != self.anchor {
+            self.selection().end
+        } else {
+            next_boundary(&self.value, self.caret)
+        };
+        self.move_to(to, extend);
+    }
+
+    /// Moves the caret to the start of its line
+    pub fn move_home(&mut self, extend: bool) {
+        self.move_to(self.line_start(self.caret), extend);
+    }
+
+    /// Moves the caret to the end of its line
+    pub fn move_end(&mut self, extend: bool) {
+        self.move_to(self.line_end(self.caret), extend);
+    }
+
+    /// Moves the caret to the same column on the previous line, or to the start of the value on the first line
+    pub fn move_up(&mut self, extend: bool) {
+        let start = self.line_start(self.caret);
+        if start == 0 {
+            self.move_to(0, extend);
+            return;
+        }
+
+        let column = self.value[start..self.caret].chars().count();
+        let to = self.column_offset(self.line_start(start - 1), column);
+        self.move_to(to, extend);
+    }
+
+    /// Moves the caret to the same column on the next line, or to the end of the value on the last line
+    pub fn move_down(&mut self, extend: bool) {
+        let end = self.line_end(self.caret);
+        if end == self.value.len() {
+            self.move_to(end, extend);
+            return;
+        }
+
+        let column = self.value[self.line_start(self.caret)..self.caret].chars().count();
+        let to = self.column_offset(end + 1, column);
+        self.move_to(to, extend);
+    }
+
+    /// Returns true when the option at the index of a `<select>` is selected
+    #[must_use]
+    pub fn is_selected(&self, control: &ControlData, idx: usize) -> bool {
+        if control.is_multiple() {
+            self.chosen.contains(&idx)
+        } else {
+            self.selected == Some(idx)
+        }
+    }
+
+    /// Selects the option at the index of a `<select>`. A `<select multiple>` toggles the option when `toggle` is
+    /// set, and otherwise selects only that option.
+    pub fn select_option(&mut self, control: &ControlData, idx: usize, toggle: bool) {
+        if !control.is_multiple() {
+            self.selected = Some(idx);
+            return;
+        }
+
+        if !toggle {
+            self.chosen.clear();
+        }
+        if !self.chosen.remove(&idx) {
+            self.chosen.insert(idx);
+        }
+        self.selected = self.chosen.first().copied();
+    }
+
+    /// Selects the complete value
+    pub fn select_all(&mut self) {
+        self.anchor = 0;
+        self.caret = self.value.len();
+    }
+
+    /// Keeps the state within the bounds of the control, after the control is read from the document again
+    pub fn fit(&mut self, control: &ControlData) {
+        self.caret = floor_boundary(&self.value, self.caret);
+        self.anchor = floor_boundary(&self.value, self.anchor);
+
+        if control.is_multiple() {
+            self.chosen.retain(|idx| *idx < control.options.len());
+            self.selected = self.chosen.first().copied();
+        } else if self.selected.is_none_or(|idx| idx >= control.options.len()) {
+            self.selected = control.initial_state().selected;
+        }
+    }
+
+    fn line_start(&self, offset: usize) -> usize {
+        self.value[..offset].rfind('\n').map_or(0, |idx| idx + 1)
+    }
+
+    fn line_end(&self, offset: usize) -> usize {
+        self.value[offset..]
+            .find('\n')
+            .map_or(self.value.len(), |idx| offset + idx)
+    }
+
+    /// Returns the byte offset of the column in the line that starts at `start`, or the end of the line when the
+    /// line is shorter
+    fn column_offset(&self, start: usize, column: usize) -> usize {
+        let end = self.line_end(start);
+
+        self.value[start..end]
+            .char_indices()
+            .nth(column)
+            .map_or(end, |(idx, _)| start + idx)
+    }
+}
+
+impl<C: HasLayouter<LayoutTree = Self>> RenderTree<C> {
+    /// Returns the data of the form control
+    #[must_use]
+    pub fn control(&self, id: NodeId) -> Option<&ControlData> {
+        match &self.nodes.get(&id)?.data {
+            RenderNodeData::Control { control, .. } => Some(control),
+            _ => None,
+        }
+    }
+
+    /// Changes the state of a form control and updates the text it shows. Returns true when the shown text changed,
+    /// so the control needs to be laid out again.
+    pub fn update_control(&mut self, id: NodeId, update: impl FnOnce(&ControlData, &mut ControlState)) -> bool {
+        let Some(RenderNodeData::Control { control, .. }) = self.nodes.get_mut(&id).map(|node| &mut node.data) else {
+            return false;
+        };
+        let state = self.controls.entry(id).or_insert_with(|| control.initial_state());
+
+        update(control, state);
+
+        let shown = control.shown_text(state);
+        if shown == control.shown {
+            return false;
+        }
+
+        control.shown = shown;
+        true
+    }
+
+    /// Returns true for buttons: `<button>` elements and inputs of a button type
+    #[must_use]
+    pub fn is_button(&self, id: NodeId) -> bool {
+        match self.nodes.get(&id).map(|node| (&node.data, node.name.as_str())) {
+            Some((RenderNodeData::Control { control, .. }, _)) => control.kind == ControlKind::Button,
+            Some((RenderNodeData::Element { .. }, "button")) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the form control or button that is activated by clicking the node: the control the node is in, or
+    /// the control of the `<label>` the node is in
+    #[must_use]
+    pub fn activation_target(&self, id: NodeId) -> Option<NodeId> {
+        let mut current = Some(id);
+
+        while let Some(id) = current {
+            let node = self.nodes.get(&id)?;
+
+            if self.control(id).is_some() || self.is_button(id) {
+                return Some(id);
+            }
+            if node.name == "label" {
+                return self.labeled_control(id);
+            }
+
+            current = node.parent;
+        }
+
+        None
+    }
+
+    /// Returns the control of a `<label>`: the element its `for` attribute refers to, or else the first control in
+    /// the label
+    #[must_use]
+    pub fn labeled_control(&self, label: NodeId) -> Option<NodeId> {
+        let node = self.nodes.get(&label)?;
+
+        if let Some(target) = node_attribute(node, "for") {
+            return self
+                .nodes
+                .values()
+                .find(|node| node_attribute(node, "id") == Some(target))
+                .map(|node| node.id)
+                .filter(|id| self.control(*id).is_some() || self.is_button(*id));
+        }
+
+        let mut stack = node.children.iter().rev().copied().collect::<Vec<_>>();
+        while let Some(id) = stack.pop() {
+            if self.control(id).is_some() || self.is_button(id) {
+                return Some(id);
+            }
+            if let Some(node) = self.nodes.get(&id) {
+                stack.extend(node.children.iter().rev());
+            }
+        }
+
+        None
+    }
+
+    /// Checks a radio button and unchecks the other radio buttons in its group: the radio buttons with the same name
+    /// in the same form
+    pub fn check_radio(&mut self, id: NodeId) -> Vec<NodeId> {
+        let Some(node) = self.nodes.get(&id) else {
+            return Vec::new();
+        };
+        let name = node_attribute(node, "name").filter(|name| !name.is_empty());
+        let form = self.form_owner(id);
+
+        let group = match name {
+            Some(name) => self
+                .nodes
+                .values()
+                .filter(|node| node.id != id && node_attribute(node, "name") == Some(name))
+                .filter(|node| self.control(node.id).is_some_and(|c| c.kind == ControlKind::Radio))
+                .map(|node| node.id)
+                .filter(|other| self.form_owner(*other) == form)
+                .collect::<Vec<_>>(),
+            None => Vec::new(),
+        };
+
+        self.update_control(id, |_, state| state.checked = true);
+        for other in &group {
+            self.update_control(*other, |_, state| state.checked = false);
+        }
+
+        group
+    }
+
+    /// Returns the `<form>` element the control belongs to: the form its `form` attribute refers to, or else the
+    /// form it is in
+    #[must_use]
+    pub fn form_owner(&self, id: NodeId) -> Option<NodeId> {
+        if let Some(field) = self.forms.field(id) {
+            return field.form;
+        }
+
+        let mut current = self.nodes.get(&id)?.parent;
+
+        while let Some(id) = current {
+            let node = self.nodes.get(&id)?;
+            if node.name == "form" {
+                return Some(id);
+            }
+            current = node.parent;
+        }
+
+        None
+    }
+}
+
+/// Returns the kind of form control of an element in the html namespace
+pub fn control_kind<C: HasDocument>(data: &NodeData<C>) -> Option<ControlKind> {
+    match data {
+        NodeData::Element(element) if element.namespace() == HTML_NAMESPACE => {
+            ControlKind::of(element.name(), element.attributes())
+        }
+        _ => None,
+    }
+}
+
+fn node_attribute<'a, C: HasLayouter>(node: &'a RenderTreeNode<C>, name: &str) -> Option<&'a str> {
+    node.element_attributes()?.get(name).map(String::as_str)
+}
+
+/// Collects the `<option>` elements of a `<select>`, also the ones in an `<optgroup>`
+fn collect_options<C: HasDocument>(doc: &C::Document, id: NodeId, options: &mut Vec<SelectOption>) {
+    let Some(node) = doc.node_by_id(id) else {
+        return;
+    };
+
+    for child in node.children() {
+        let Some(NodeData::Element(element)) = doc.node_by_id(*child).map(|node| node.data()) else {
+            continue;
+        };
+
+        match element.name() {
+            "option" => {
+                let attributes = element.attributes();
+                let label = attributes
+                    .get("label")
+                    .cloned()
+                    .unwrap_or_else(|| collapse_white_space(&text_content::<C>(doc, *child)));
+
+                options.push(SelectOption {
+                    value: attributes.get("value").cloned().unwrap_or_else(|| label.clone()),
+                    label,
+                    selected: attributes.contains_key("selected"),
+                    disabled: attributes.contains_key("disabled"),
+                });
+            }
+            "optgroup" => collect_options::<C>(doc, *child, options),
+            _ => {}
+        }
+    }
+}
+
+/// Returns the text of all text nodes in the node
+fn text_content<C: HasDocument>(doc: &C::Document, id: NodeId) -> String {
+    let mut text = String::new();
+    let mut stack = vec![id];
+
+    while let Some(id) = stack.pop() {
+        let Some(node) = doc.node_by_id(id) else {
+            continue;
+        };
+        if let NodeData::Text(data) = node.data() {
+            text.push_str(&data.string_value());
+        }
+        stack.extend(node.children().iter().rev());
+    }
+
+    text
+}
+
+/// Replaces every run of white space with a single space and trims the text
+#[must_use]
+pub fn collapse_white_space(text: &str) -> String {
+    text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn prev_boundary(text: &str, offset: usize) -> usize {
+    text[..offset].chars().next_back().map_or(0, |c| offset - c.len_utf8())
+}
+
+fn next_boundary(text: &str, offset: usize) -> usize {
+    text[offset..]
+        .chars()
+        .next()
+        .map_or(text.len(), |c| offset + c.len_utf8())
+}
+
+/// Returns the length of the text in UTF-16 code units
+fn utf16_len(text: &str) -> usize {
+    text.chars().map(char::len_utf16).sum()
+}
+
+/// Returns the char boundary at or before the offset
+fn floor_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::attributes;
+
+    fn control(kind: ControlKind, attrs: &[(&str, &str)]) -> ControlData {
+        ControlData {
+            kind,
+            attributes: attributes(attrs),
+            options: Vec::new(),
+            text: String::new(),
+            shown: None,
+        }
+    }
+
+    fn text_state(value: &str, caret: usize) -> ControlState {
+        ControlState {
+            value: value.to_owned(),
+            caret,
+            anchor: caret,
+            ..ControlState::default()
+        }
+    }
+
+    #[test]
+    fn control_kinds() {
+        assert_eq!(
+            ControlKind::of("input", &attributes(&[])),
+            Some(ControlKind::Text { password: false })
+        );
+        assert_eq!(
+            ControlKind::of("input", &attributes(&[("type", "Password")])),
+            Some(ControlKind::Text { password: true })
+        );
+        assert_eq!(
+            ControlKind::of("input", &attributes(&[("type", "email")])),
+            Some(ControlKind::Text { password: false })
+        );
+        assert_eq!(
+            ControlKind::of("input", &attributes(&[("type", "checkbox")])),
+            Some(ControlKind::Checkbox)
+        );
+        assert_eq!(
+            ControlKind::of("input", &attributes(&[("type", "submit")])),
+            Some(ControlKind::Button)
+        );
+        assert_eq!(ControlKind::of("input", &attributes(&[("type", "hidden")])), None);
+        assert_eq!(
+            ControlKind::of("textarea", &attributes(&[])),
+            Some(ControlKind::TextArea)
+        );
+        assert_eq!(ControlKind::of("button", &attributes(&[])), None);
+    }
+
+    #[test]
+    fn edit_text() {
+        let mut state = text_state("héllo", 3);
+
+        state.insert("X");
+        assert_eq!((state.value.as_str(), state.caret), ("héXllo", 4));
+
+        state.delete_backward();
+        state.delete_backward();
+        assert_eq!((state.value.as_str(), state.caret), ("hllo", 1));
+
+        state.move_right(true);
+        state.move_right(true);
+        assert_eq!(state.selection(), 1..3);
+        state.insert("e");
+        assert_eq!(state.value, "heo");
+
+        state.move_home(false);
+        state.delete_forward();
+        assert_eq!((state.value.as_str(), state.caret), ("eo", 0));
+
+        state.select_all();
+        state.move_left(false);
+        assert_eq!((state.caret, state.anchor), (0, 0));
+    }
+
+    #[test]
+    fn limit_text_length() {
+        let field = control(ControlKind::Text { password: false }, &[("maxlength", " 4 ")]);
+        assert_eq!(field.max_length(), Some(4));
+        assert!(!field.is_readonly());
+
+        let mut state = text_state("ab", 2);
+        state.insert_within("cdef", field.max_length());
+        assert_eq!((state.value.as_str(), state.caret), ("abcd", 4));
+        state.insert_within("x", field.max_length());
+        assert_eq!(state.value, "abcd");
+
+        // A selection makes room for what replaces it
+        state.move_left(true);
+        state.move_left(true);
+        state.insert_within("xyz", field.max_length());
+        assert_eq!((state.value.as_str(), state.caret), ("abxy", 4));
+
+        // A character outside the basic multilingual plane takes two code units
+        let mut state = text_state("abc", 3);
+        state.insert_within("😀", field.max_length());
+        assert_eq!(state.value, "abc");
+        state.delete_backward();
+        state.insert_within("😀", field.max_length());
+        assert_eq!(state.value, "ab😀");
+
+        assert_eq!(
+            control(ControlKind::TextArea, &[("maxlength", "-1")]).max_length(),
+            None
+        );
+        assert_eq!(control(ControlKind::Checkbox, &[("maxlength", "1")]).max_length(), None);
+        assert!(control(ControlKind::TextArea, &[("readonly", "")]).is_readonly());
+        assert!(!control(ControlKind::Checkbox, &[("readonly", "")]).is_readonly());
+    }
+
+    #[test]
+    fn move_between_lines() {
+        let mut state = text_state("abcd\nx\nefgh", 3);
+
+        state.move_down(false);
+        assert_eq!(state.caret, 6);
+        state.move_down(false);
+        assert_eq!(state.caret, 8);
+        state.move_end(false);
+        assert_eq!(state.caret, 11);
+        state.move_up(true);
+        assert_eq!((state.caret, state.anchor), (6, 11));
+        state.move_up(false);
+        state.move_up(false);
+        assert_eq!(state.caret, 0);
+    }
+
+    #[test]
+    fn shown_text() {
+        let password = control(ControlKind::Text { password: true }, &[("placeholder", "Password")]);
+        let mut state = password.initial_state();
+
+        let shown = password.shown_text(&state).unwrap();
+        assert_eq!(shown.text, "Password");
+        assert!(password.shows_placeholder(&state));
+
+        state.insert("aé");
+        assert_eq!(password.shown_text(&state).unwrap().text, "••");
+        assert_eq!(password.shown_offset(&state, 1), 3);
+        assert_eq!(password.value_offset(&state, 3), 1);
+        assert_eq!(password.value_offset(&state, 6), 3);
+
+        let submit = control(ControlKind::Button, &[("type", "submit")]);
+        assert_eq!(submit.shown_text(&submit.initial_state()).unwrap().text, "Submit");
+
+        let checkbox = control(ControlKind::Checkbox, &[("checked", "")]);
+        assert!(checkbox.initial_state().checked);
+        assert_eq!(checkbox.shown_text(&checkbox.initial_state()), None);
+    }
+
+    #[test]
+    fn select_initial_option() {
+        let option = |label: &str, selected, disabled| SelectOption {
+            label: label.to_owned(),
+            value: label.to_owned(),
+            selected,
+            disabled,
+        };
+
+        let mut select = control(ControlKind::Select, &[]);
+        select.options = vec![
+            option("a", false, true),
+            option("b", false, false),
+            option("c", false, false),
+        ];
+        assert_eq!(select.initial_state().selected, Some(1));
+
+        select.options[2].selected = true;
+        let state = select.initial_state();
+        assert_eq!(state.selected, Some(2));
+
+        let shown = select.shown_text(&state).unwrap();
+        assert_eq!(shown.text, "c");
+        assert!(shown.end_space > 0.0);
+
+        // A select that allows choosing several options has no default option
+        select.options[2].selected = false;
+        select.attributes.insert("multiple".to_owned(), String::new());
+        let mut state = select.initial_state();
+        assert_eq!((state.selected, state.chosen.len()), (None, 0));
+        assert_eq!(select.shown_text(&state).unwrap().text, "");
+
+        state.select_option(&select, 2, true);
+        state.select_option(&select, 1, true);
+        assert_eq!(state.chosen, BTreeSet::from([1, 2]));
+        assert_eq!(state.selected, Some(1));
+        assert!(state.is_selected(&select, 2));
+
+        select.options.truncate(2);
+        state.fit(&select);
+        assert_eq!(state.chosen, BTreeSet::from([1]));
+    }
+}
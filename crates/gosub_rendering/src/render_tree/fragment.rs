@@ -0,0 +1,116 @@
+//! Fragment navigation. The fragment of a url points to an element of the page: the first element with that `id`, or
+//! else the first `<a>` element with that `name`. The fragment `top` points to the top of the page when no element
+//! is named after it.
+
+use crate::render_tree::RenderTree;
+use gosub_interface::config::HasLayouter;
+use gosub_interface::render_tree::RenderTreeNode as _;
+use gosub_shared::node::NodeId;
+use percent_encoding::percent_decode_str;
+use std::collections::HashMap;
+
+/// Place on the page a fragment points to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentTarget {
+    Element(NodeId),
+    /// The top of the page
+    Top,
+}
+
+/// Returns the fragment of a url as text: percent-decoded, with invalid UTF-8 replaced
+#[must_use]
+pub fn decode_fragment(fragment: &str) -> String {
+    percent_decode_str(fragment).decode_utf8_lossy().into_owned()
+}
+
+impl<C: HasLayouter<LayoutTree = Self>> RenderTree<C> {
+    /// Returns what the fragment of a url points to, or None when it points to nothing on the page. An empty
+    /// fragment points to the top of the page.
+    #[must_use]
+    pub fn fragment_target(&self, fragment: &str) -> Option<FragmentTarget> {
+        if fragment.is_empty() {
+            return Some(FragmentTarget::Top);
+        }
+
+        // The fragment is looked up as it is first, and decoded when nothing matches it
+        let decoded = decode_fragment(fragment);
+
+        for name in [fragment, decoded.as_str()] {
+            let target = self
+                .find_element(|_, attrs| attrs.get("id").is_some_and(|id| id == name))
+                .or_else(|| self.find_element(|tag, attrs| tag == "a" && attrs.get("name").is_some_and(|a| a == name)));
+            if let Some(id) = target {
+                return Some(FragmentTarget::Element(id));
+            }
+        }
+
+        decoded.eq_ignore_ascii_case("top").then_some(FragmentTarget::Top)
+    }
+
+    /// Returns the first element in tree order that matches a predicate on its name and attributes
+    fn find_element(&self, matches: impl Fn(&str, &HashMap<String, String>) -> bool) -> Option<NodeId> {
+        let mut stack = vec![self.root];
+
+        while let Some(id) = stack.pop() {
+            let Some(node) = self.nodes.get(&id) else {
+                continue;
+            };
+            if let Some(attributes) = node.element_attributes() {
+                if matches(&node.name, attributes) {
+                    return Some(id);
+                }
+            }
+            stack.extend(node.children.iter().rev());
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::document;
+    use gosub_interface::document::Document;
+    use gosub_interface::node::Node;
+
+    #[test]
+    fn decode_fragments() {
+        assert_eq!(decode_fragment("section-2"), "section-2");
+        assert_eq!(decode_fragment("caf%C3%A9"), "café");
+        assert_eq!(decode_fragment("a%20b"), "a b");
+        assert_eq!(decode_fragment("bad%FF"), "bad\u{FFFD}");
+    }
+
+    #[test]
+    fn fragment_targets() {
+        let (doc, tree) = document(
+            r#"<body><a name="intro">a</a><p id="intro">b</p><a name="only-named">c</a>
+            <p id="caf%C3%A9">d</p><p id="café">e</p><p id="naïve">f</p></body>"#,
+        );
+        let element = |id: &str| {
+            let id = doc.node_by_named_id(id).map(|node| node.id()).unwrap();
+            Some(FragmentTarget::Element(id))
+        };
+
+        // An id takes precedence over the name of an `<a>` element that comes before it
+        assert_eq!(tree.fragment_target("intro"), element("intro"));
+        assert!(matches!(
+            tree.fragment_target("only-named"),
+            Some(FragmentTarget::Element(_))
+        ));
+
+        // The fragment is looked up as it is before it is decoded
+        assert_eq!(tree.fragment_target("caf%C3%A9"), element("caf%C3%A9"));
+        assert_eq!(tree.fragment_target("na%C3%AFve"), element("naïve"));
+
+        assert_eq!(tree.fragment_target(""), Some(FragmentTarget::Top));
+        assert_eq!(tree.fragment_target("TOP"), Some(FragmentTarget::Top));
+        assert_eq!(tree.fragment_target("missing"), None);
+
+        // An element named `top` takes precedence over the top of the page
+        let (doc, tree) = document(r#"<body><p>a</p><div id="top">b</div></body>"#);
+        let top = doc.node_by_named_id("top").map(|node| node.id()).unwrap();
+        assert_eq!(tree.fragment_target("top"), Some(FragmentTarget::Element(top)));
+    }
+}
@@ -0,0 +1,461 @@
+//! Updates the render tree from the mutations of its document. Only the nodes whose style can depend on a mutation
+//! are styled again, and only the nodes around them are laid out again.
+
+use crate::render_tree::submission::Forms;
+use crate::render_tree::{RenderNodeData, RenderTree};
+use gosub_interface::config::{HasDocument, HasRenderTree};
+use gosub_interface::css3::{CssStylesheet, CssSystem};
+use gosub_interface::document::{Document, MutationRecord};
+use gosub_interface::layout::{HasTextLayout, Layouter};
+use gosub_interface::node::{Node as DocumentNode, NodeData, TextDataType};
+use gosub_shared::node::NodeId;
+use gosub_shared::{timing_start, timing_stop};
+use std::collections::HashSet;
+
+impl<C: HasRenderTree<LayoutTree = Self, RenderTree = Self> + HasDocument> RenderTree<C> {
+    /// Applies the mutations of the document to the tree. Returns the nodes whose layout is invalidated, so their
+    /// surroundings can be laid out (and hit tested) again.
+    pub fn apply_mutations(&mut self, doc: &C::Document, mutations: &[MutationRecord]) -> Vec<NodeId> {
+        let t_id = timing_start!(
+            "style.compute",
+            doc.url().map(|url| url.to_string()).unwrap_or_default()
+        );
+
+        // Anonymous boxes get ids after the ids of the document
+        if self.next_id < doc.peek_next_id() {
+            self.next_id = doc.peek_next_id();
+        }
+
+        let mut restyle = Vec::new();
+        let mut dirty = Vec::new();
+
+        for mutation in mutations {
+            match mutation {
+                MutationRecord::Text { node } => {
+                    if self.update_text(doc, *node) {
+                        dirty.push(*node);
+                    } else {
+                        restyle.push(*node);
+                    }
+                }
+                // Selectors match on the attributes of ancestors, and of previous siblings when they have a sibling
+                // combinator
+                MutationRecord::Attribute { node, name } => {
+                    restyle.push(*node);
+                    if doc
+                        .stylesheets()
+                        .iter()
+                        .any(|sheet| sheet.matches_sibling_attribute(name))
+                    {
+                        restyle.extend(following_siblings::<C>(doc, *node));
+                    }
+                }
+                // `:focus` and `:focus-visible` can be followed by a sibling combinator as well
+                MutationRecord::Focus { node } => {
+                    restyle.push(*node);
+                    restyle.extend(following_siblings::<C>(doc, *node));
+                }
+                // Structural pseudo-classes of the siblings can change as well
+                MutationRecord::Insert {
+                    node, previous_sibling, ..
+                } => {
+                    restyle.push(*node);
+                    restyle.extend(previous_sibling);
+                    restyle.extend(following_siblings::<C>(doc, *node));
+                }
+                MutationRecord::Remove {
+                    node,
+                    parent,
+                    previous_sibling,
+                    next_sibling,
+                } => {
+                    restyle.push(*node);
+                    restyle.extend(previous_sibling);
+                    if let Some(next) = next_sibling {
+                        restyle.push(*next);
+                        restyle.extend(following_siblings::<C>(doc, *next));
+                    }
+
+                    // The source of an inline svg element or the options of a form control change
+                    restyle.extend(self.replaced_root(doc, *parent));
+                }
+            }
+        }
+
+        // Styling a node styles its descendants as well
+        let roots = restyle.iter().copied().collect::<HashSet<_>>();
+        let mut seen = HashSet::new();
+        for id in restyle {
+            if !seen.insert(id) || ancestors::<C>(doc, id).any(|ancestor| roots.contains(&ancestor)) {
+                continue;
+            }
+
+            self.restyle(doc, id, &mut dirty);
+        }
+
+        // Any mutation can add, remove or change the fields of a form
+        self.forms = Forms::from_document::<C>(doc);
+
+        let mut invalidated = Vec::new();
+        for id in dirty {
+            if !self.nodes.contains_key(&id) || invalidated.contains(&id) {
+                continue;
+            }
+
+            if <C::Layouter as Layouter<C>>::COLLAPSE_INLINE && !self.is_text(id) {
+                self.wrap_inline_children(id);
+            }
+            self.layout_dirty_from(id);
+
+            invalidated.push(id);
+        }
+
+        timing_stop!(t_id);
+
+        invalidated
+    }
+
+    /// Replaces the text of a text node. Returns false when the node is not in the tree.
+    fn update_text(&mut self, doc: &C::Document, id: NodeId) -> bool {
+        let Some(NodeData::Text(data)) = doc.node_by_id(id).map(|node| node.data()) else {
+            return false;
+        };
+        let Some(node) = self.nodes.get_mut(&id) else {
+            return false;
+        };
+        let RenderNodeData::Text(text) = &mut node.data else {
+            return false;
+        };
+
+        text.text = data.string_value();
+        node.clear_text_layout();
+
+        true
+    }
+
+    /// Styles the node and its descendants again, replacing the nodes in the tree. The parents whose children
+    /// changed are added to `dirty`.
+    fn restyle(&mut self, doc: &C::Document, id: NodeId, dirty: &mut Vec<NodeId>) {
+        // The descendants of a replaced element are part of the replaced element
+        let id = self.replaced_root(doc, id).unwrap_or(id);
+
+        dirty.extend(self.remove_subtree(id));
+
+        if !is_connected::<C>(doc, id) {
+            return;
+        }
+        let Some(parent) = doc.node_by_id(id).and_then(|node| node.parent_id()) else {
+            return;
+        };
+        if !self.nodes.contains_key(&parent) {
+            // The parent is not rendered, so neither is the node
+            return;
+        }
+
+        self.unwrap_inline_children(parent);
+
+        let mut ids = Vec::new();
+        let mut replaced_nodes = HashSet::new();
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let Some(node) = doc.node_by_id(id) else {
+                continue;
+            };
+            stack.extend(node.children().iter().rev());
+
+            dirty.extend(self.free_id(id));
+            self.insert_from_document(doc, id, &mut replaced_nodes);
+            ids.push(id);
+        }
+
+        // Puts the node between its siblings
+        let order = doc
+            .node_by_id(parent)
+            .map(|node| node.children().to_vec())
+            .unwrap_or_default();
+        let children = order
+            .into_iter()
+            .filter(|child| self.nodes.get(child).is_some_and(|node| node.parent == Some(parent)))
+            .collect();
+        if let Some(parent) = self.nodes.get_mut(&parent) {
+            parent.children = children;
+        }
+
+        self.remove_unrenderable_nodes(&ids);
+
+        if self.nodes.contains_key(&id) {
+            <C::CssSystem as CssSystem>::inheritance_from::<C>(self, id, parent);
+
+            if <C::Layouter as Layouter<C>>::COLLAPSE_INLINE {
+                self.collapse_inline(id);
+            }
+        }
+
+        dirty.push(parent);
+    }
+
+    /// Removes the node and its descendants from the tree. Returns the parent it is removed from.
+    fn remove_subtree(&mut self, id: NodeId) -> Option<NodeId> {
+        let node = self.nodes.get(&id)?;
+        if matches!(node.data, RenderNodeData::AnonymousInline) {
+            // An anonymous box that got the id of a new node
+            return None;
+        }
+
+        let parent = self.block_parent(node.parent);
+
+        for id in self.get_child_node_ids(id) {
+            self.delete_node(&id);
+        }
+
+        parent
+    }
+
+    /// Makes room for a node of the document with the given id. An anonymous box that has the id gets another id,
+    /// and a node that is moved to another parent is removed from its previous parent, which is returned.
+    fn free_id(&mut self, id: NodeId) -> Option<NodeId> {
+        let node = self.nodes.get(&id)?;
+
+        if !matches!(node.data, RenderNodeData::AnonymousInline) {
+            let parent = self.block_parent(node.parent);
+            self.delete_node(&id);
+
+            return parent;
+        }
+
+        let mut new_id = self.reserve_id();
+        while self.nodes.contains_key(&new_id) {
+            new_id = self.reserve_id();
+        }
+
+        let mut wrapper = self.nodes.remove(&id)?;
+        wrapper.id = new_id;
+
+        for child in &wrapper.children {
+            if let Some(child) = self.nodes.get_mut(child) {
+                child.parent = Some(new_id);
+            }
+        }
+        if let Some(parent) = wrapper.parent.and_then(|parent| self.nodes.get_mut(&parent)) {
+            for child in &mut parent.children {
+                if *child == id {
+                    *child = new_id;
+                }
+            }
+        }
+
+        self.nodes.insert(new_id, wrapper);
+
+        None
+    }
+
+    /// Returns the parent, or the parent of the anonymous box when the parent is an anonymous box
+    fn block_parent(&self, parent: Option<NodeId>) -> Option<NodeId> {
+        let parent = parent?;
+
+        match self.nodes.get(&parent) {
+            Some(node) if matches!(node.data, RenderNodeData::AnonymousInline) => node.parent,
+            _ => Some(parent),
+        }
+    }
+
+    /// Returns the replaced element (an inline svg element or a form control) the node (or one of its ancestors) is in
+    fn replaced_root(&self, doc: &C::Document, id: NodeId) -> Option<NodeId> {
+        std::iter::once(id).chain(ancestors::<C>(doc, id)).find(|id| {
+            matches!(
+                self.nodes.get(id).map(|node| &node.data),
+                Some(RenderNodeData::Svg(_) | RenderNodeData::Control { .. })
+            )
+        })
+    }
+
+    /// Returns true when the node is a text node
+    fn is_text(&self, id: NodeId) -> bool {
+        self.nodes.get(&id).is_some_and(|node| node.is_text())
+    }
+}
+
+/// Returns the ancestors of the node in the document, from its parent up to the root
+fn ancestors<C: HasDocument>(doc: &C::Document, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+    std::iter::successors(doc.node_by_id(id).and_then(|node| node.parent_id()), |id| {
+        doc.node_by_id(*id).and_then(|node| node.parent_id())
+    })
+}
+
+/// Returns true when the node is in the tree of the document
+fn is_connected<C: HasDocument>(doc: &C::Document, id: NodeId) -> bool {
+    id.is_root() || ancestors::<C>(doc, id).any(|id| id.is_root())
+}
+
+/// Returns the siblings after the node in the document
+fn following_siblings<C: HasDocument>(doc: &C::Document, id: NodeId) -> Vec<NodeId> {
+    let Some(parent) = doc
+        .node_by_id(id)
+        .and_then(|node| node.parent_id())
+        .and_then(|parent| doc.node_by_id(parent))
+    else {
+        return Vec::new();
+    };
+
+    parent
+        .children()
+        .iter()
+        .skip_while(|child| **child != id)
+        .skip(1)
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{document, Config};
+    use gosub_html5::node::HTML_NAMESPACE;
+    use gosub_interface::css3::CssProperty;
+    use gosub_interface::node::ElementDataType;
+    use gosub_shared::byte_stream::Location;
+    use std::collections::HashMap;
+
+    type Doc = <Config as HasDocument>::Document;
+
+    /// Returns the tree as text: the names, texts and colors of the nodes, and their children
+    fn dump(tree: &RenderTree<Config>, id: NodeId, depth: usize, out: &mut String) {
+        let Some(node) = tree.nodes.get(&id) else {
+            return;
+        };
+
+        let text = match &node.data {
+            RenderNodeData::Text(text) => text.text.as_str(),
+            _ => "",
+        };
+        let color = tree
+            .get_property(id, "color")
+            .map(|color| color.to_string())
+            .unwrap_or_default();
+        out.push_str(&format!("{}{} {text:?} {color}\n", "  ".repeat(depth), node.name));
+
+        for child in &node.children {
+            dump(tree, *child, depth + 1, out);
+        }
+    }
+
+    fn assert_same_tree(tree: &RenderTree<Config>, doc: &Doc) {
+        let expected = RenderTree::<Config>::from_document(doc);
+
+        let (mut a, mut b) = (String::new(), String::new());
+        dump(tree, tree.root, 0, &mut a);
+        dump(&expected, expected.root, 0, &mut b);
+        assert_eq!(a, b);
+    }
+
+    fn element(doc: &Doc, id: &str) -> NodeId {
+        doc.node_by_named_id(id).map(|node| node.id()).unwrap()
+    }
+
+    fn set_attribute(doc: &mut Doc, id: NodeId, name: &str, value: Option<&str>) {
+        let mut node = doc.node_by_id(id).unwrap().clone();
+        let data = node.get_element_data_mut().unwrap();
+        match value {
+            Some(value) => data.add_attribute(name, value),
+            None => data.remove_attribute(name),
+        }
+        doc.update_node(node);
+    }
+
+    /// Applies the mutations that are recorded in the document to the tree
+    fn apply(tree: &mut RenderTree<Config>, doc: &mut Doc) -> Vec<NodeId> {
+        let mutations = doc.take_mutations();
+        tree.apply_mutations(doc, &mutations)
+    }
+
+    const PAGE: &str = r#"<style>.hidden { display: none } .red + span { color: red }</style>
+        <body><div id="list"><p id="first">a</p><p id="second" class="hidden">b</p><span id="after">c</span></div></body>"#;
+
+    #[test]
+    fn attribute_mutations() {
+        let (mut doc, mut tree) = document(PAGE);
+        doc.observe_mutations(true);
+
+        let second = element(&doc, "second");
+
+        // The hidden element is shown, and the sibling selector matches the span after it
+        set_attribute(&mut doc, second, "class", Some("red"));
+        let invalidated = apply(&mut tree, &mut doc);
+
+        assert!(!invalidated.is_empty());
+        assert_same_tree(&tree, &doc);
+        assert!(tree.nodes.contains_key(&second));
+
+        set_attribute(&mut doc, second, "class", None);
+        apply(&mut tree, &mut doc);
+        assert_same_tree(&tree, &doc);
+    }
+
+    #[test]
+    fn tree_mutations() {
+        let (mut doc, mut tree) = document(PAGE);
+        doc.observe_mutations(true);
+
+        let first = element(&doc, "first");
+        let list = element(&doc, "list");
+
+        let node = Doc::new_element_node("span", Some(HTML_NAMESPACE), HashMap::new(), Location::default());
+        let span = doc.register_node_at(node, list, Some(0));
+        let text = Doc::new_text_node("new", Location::default());
+        doc.register_node_at(text, span, None);
+        doc.detach_node(first);
+
+        apply(&mut tree, &mut doc);
+        assert_same_tree(&tree, &doc);
+        assert!(!tree.nodes.contains_key(&first));
+    }
+
+    #[test]
+    fn text_mutations() {
+        let (mut doc, mut tree) = document(PAGE);
+        doc.observe_mutations(true);
+
+        let first = element(&doc, "first");
+        let text = doc.node_by_id(first).unwrap().children()[0];
+        let mut node = doc.node_by_id(text).unwrap().clone();
+        node.get_text_data_mut().unwrap().value = "changed".into();
+        doc.update_node(node);
+
+        let invalidated = apply(&mut tree, &mut doc);
+        assert_eq!(invalidated, vec![text]);
+        assert_same_tree(&tree, &doc);
+    }
+
+    #[test]
+    fn focus_mutations() {
+        let (mut doc, mut tree) = document(
+            r#"<style>a:focus { color: blue } a:focus-visible + span { color: green }</style>
+            <body><a id="link" href="x">a</a><span>b</span><button id="button">c</button></body>"#,
+        );
+        doc.observe_mutations(true);
+
+        let link = element(&doc, "link");
+        let button = element(&doc, "button");
+        let outline = |tree: &RenderTree<Config>, id| {
+            tree.get_property(id, "outline-style")
+                .and_then(|style| style.as_string().map(ToOwned::to_owned))
+        };
+
+        // The focus ring of the user agent stylesheet only shows with `:focus-visible`
+        doc.set_focus(Some((link, false)));
+        apply(&mut tree, &mut doc);
+        assert_same_tree(&tree, &doc);
+        assert_eq!(outline(&tree, link), None);
+
+        doc.set_focus(Some((link, true)));
+        apply(&mut tree, &mut doc);
+        assert_same_tree(&tree, &doc);
+        assert_eq!(outline(&tree, link).as_deref(), Some("auto"));
+
+        doc.set_focus(Some((button, true)));
+        apply(&mut tree, &mut doc);
+        assert_same_tree(&tree, &doc);
+        assert_eq!(outline(&tree, link), None);
+        assert_eq!(outline(&tree, button).as_deref(), Some("auto"));
+    }
+}
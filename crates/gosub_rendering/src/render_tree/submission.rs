@@ -0,0 +1,474 @@
+//! Form submission. The fields of the forms are read from the document, so controls that are not rendered (like
+//! hidden inputs) are submitted as well. Their values come from the state of the controls in the tree, or from the
+//! markup for controls that have no state.
+
+use crate::render_tree::form::{control_kind, ControlData, ControlKind, ControlState};
+use crate::render_tree::RenderTree;
+use cow_utils::CowUtils;
+use gosub_html5::document::document_impl::TreeIterator;
+use gosub_html5::node::HTML_NAMESPACE;
+use gosub_interface::config::{HasDocument, HasLayouter};
+use gosub_interface::document::Document;
+use gosub_interface::node::{ElementDataType, Node as DocumentNode, NodeData};
+use gosub_net::http::form::{FormEncoding, FormMethod, FormSubmission};
+use gosub_shared::node::NodeId;
+use std::collections::HashMap;
+use url::Url;
+
+/// Element that takes part in form submission: an `<input>`, `<button>`, `<select>` or `<textarea>`
+#[derive(Debug, Clone)]
+pub struct FormField {
+    pub id: NodeId,
+    /// Local name of the element
+    pub element: String,
+    pub attributes: HashMap<String, String>,
+    /// `<form>` element the field belongs to
+    pub form: Option<NodeId>,
+    /// The field is disabled, or is in a disabled `<fieldset>`
+    pub disabled: bool,
+    /// Control of the field, which gives its state in the markup. None for hidden, image and file inputs and for
+    /// `<button>` elements.
+    pub control: Option<ControlData>,
+}
+
+impl FormField {
+    /// Returns the lowercased `type` attribute of an `<input>` or `<button>`
+    fn ty(&self) -> String {
+        let default = if self.element == "button" { "submit" } else { "text" };
+
+        self.attributes
+            .get("type")
+            .map(|ty| ty.trim().cow_to_ascii_lowercase().into_owned())
+            .unwrap_or_else(|| default.to_owned())
+    }
+
+    /// Returns true for buttons that submit their form
+    #[must_use]
+    pub fn is_submit_button(&self) -> bool {
+        match self.element.as_str() {
+            // Invalid types of a `<button>` are submit buttons as well
+            "button" => !matches!(self.ty().as_str(), "reset" | "button"),
+            "input" => self.ty() == "submit",
+            _ => false,
+        }
+    }
+
+    /// Returns true for all buttons, which are only submitted when they submit the form
+    fn is_button(&self) -> bool {
+        match self.element.as_str() {
+            "button" => true,
+            "input" => matches!(self.ty().as_str(), "submit" | "reset" | "button" | "image"),
+            _ => false,
+        }
+    }
+
+    /// Appends the entries of the field to the entry list of its form, given the state of its control
+    pub fn append_entries(&self, state: Option<&ControlState>, submitter: bool, entries: &mut Vec<(String, String)>) {
+        if self.disabled || (self.is_button() && !submitter) {
+            return;
+        }
+        let Some(name) = self.attributes.get("name").filter(|name| !name.is_empty()) else {
+            return;
+        };
+
+        let initial;
+        let state = match (state, &self.control) {
+            (Some(state), _) => Some(state),
+            (None, Some(control)) => {
+                initial = control.initial_state();
+                Some(&initial)
+            }
+            (None, None) => None,
+        };
+        let attribute = |name: &str| self.attributes.get(name).cloned().unwrap_or_default();
+
+        match (self.control.as_ref().map(|control| control.kind), state) {
+            (Some(ControlKind::Checkbox | ControlKind::Radio), Some(state)) => {
+                if state.checked {
+                    let value = self.attributes.get("value").cloned().unwrap_or_else(|| "on".to_owned());
+                    entries.push((name.clone(), value));
+                }
+            }
+            (Some(ControlKind::Select), Some(state)) => {
+                let Some(control) = &self.control else {
+                    return;
+                };
+                for (idx, option) in control.options.iter().enumerate() {
+                    if state.is_selected(control, idx) && !option.disabled {
+                        entries.push((name.clone(), option.value.clone()));
+                    }
+                }
+            }
+            (Some(ControlKind::Text { .. } | ControlKind::TextArea), Some(state)) => {
+                entries.push((name.clone(), state.value.clone()));
+
+                // The direction of the text is submitted under the name in the `dirname` attribute
+                if let Some(dirname) = self.attributes.get("dirname").filter(|dirname| !dirname.is_empty()) {
+                    entries.push((dirname.clone(), "ltr".to_owned()));
+                }
+            }
+            _ => match self.ty().as_str() {
+                // Files can not be chosen, and image buttons are not supported
+                "file" | "image" if self.element == "input" => {}
+                "hidden" if name.eq_ignore_ascii_case("_charset_") => {
+                    entries.push((name.clone(), "UTF-8".to_owned()));
+                }
+                _ => entries.push((name.clone(), attribute("value"))),
+            },
+        }
+    }
+
+    /// Returns true for text fields, which prevent submitting their form by pressing enter when the form has more
+    /// than one of them but no submit button
+    fn blocks_implicit_submission(&self) -> bool {
+        self.element == "input"
+            && self
+                .control
+                .as_ref()
+                .is_some_and(|control| matches!(control.kind, ControlKind::Text { .. }))
+    }
+}
+
+/// Forms of a document and the fields that are submitted with them
+#[derive(Debug, Clone, Default)]
+pub struct Forms {
+    /// Attributes of the `<form>` elements
+    pub forms: HashMap<NodeId, HashMap<String, String>>,
+    /// Fields of all forms in tree order
+    pub fields: Vec<FormField>,
+}
+
+impl Forms {
+    /// Collects the forms and their fields from the document
+    pub fn from_document<C: HasDocument>(doc: &C::Document) -> Self {
+        let mut forms = Self::default();
+
+        for id in TreeIterator::<C>::new(doc) {
+            let Some(node) = doc.node_by_id(id) else {
+                continue;
+            };
+            let data = node.data();
+            let NodeData::Element(element) = &data else {
+                continue;
+            };
+            if element.namespace() != HTML_NAMESPACE {
+                continue;
+            }
+
+            let attributes = element.attributes();
+            match element.name() {
+                "form" => {
+                    forms.forms.insert(id, attributes.clone());
+                }
+                name @ ("input" | "button" | "select" | "textarea") => {
+                    let control = control_kind(&data)
+                        .map(|kind| ControlData::from_document::<C>(doc, id, kind, attributes.clone()));
+
+                    forms.fields.push(FormField {
+                        id,
+                        element: name.to_owned(),
+                        attributes: attributes.clone(),
+                        form: form_owner::<C>(doc, id, attributes),
+                        disabled: attributes.contains_key("disabled") || in_disabled_fieldset::<C>(doc, id),
+                        control,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        forms
+    }
+
+    /// Returns the field of the element
+    #[must_use]
+    pub fn field(&self, id: NodeId) -> Option<&FormField> {
+        self.fields.iter().find(|field| field.id == id)
+    }
+
+    /// Returns the fields of the form in tree order
+    pub fn fields_of(&self, form: NodeId) -> impl Iterator<Item = &FormField> {
+        self.fields.iter().filter(move |field| field.form == Some(form))
+    }
+}
+
+impl<C: HasLayouter<LayoutTree = Self>> RenderTree<C> {
+    /// Returns the form that is submitted by activating the element, which is a submit button
+    #[must_use]
+    pub fn submitted_form(&self, id: NodeId) -> Option<NodeId> {
+        let field = self.forms.field(id)?;
+        if !field.is_submit_button() || field.disabled {
+            return None;
+        }
+
+        field.form
+    }
+
+    /// Returns the form that is submitted by pressing enter in a text field, and the button that submits it. That is
+    /// the first submit button of the form, or none when the form has no submit button but only one text field.
+    #[must_use]
+    pub fn implicit_submission(&self, id: NodeId) -> Option<(NodeId, Option<NodeId>)> {
+        let form = self.forms.field(id)?.form?;
+
+        if let Some(button) = self.forms.fields_of(form).find(|field| field.is_submit_button()) {
+            // A disabled default button prevents implicit submission
+            return (!button.disabled).then_some((form, Some(button.id)));
+        }
+
+        let blocking = self
+            .forms
+            .fields_of(form)
+            .filter(|field| field.blocks_implicit_submission())
+            .count();
+
+        (blocking <= 1).then_some((form, None))
+    }
+
+    /// Returns the names and values of the fields of the form in tree order. The submitter is the button that
+    /// submits the form, which is the only button that is part of the entries.
+    #[must_use]
+    pub fn form_entries(&self, form: NodeId, submitter: Option<NodeId>) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+
+        for field in self.forms.fields_of(form) {
+            field.append_entries(self.controls.get(&field.id), submitter == Some(field.id), &mut entries);
+        }
+
+        entries
+    }
+
+    /// Returns the submission of the form. The `formaction`, `formmethod` and `formenctype` attributes of the
+    /// submitter override the attributes of the form. The action is resolved against the url of the document.
+    #[must_use]
+    pub fn form_submission(&self, form: NodeId, submitter: Option<NodeId>) -> Option<FormSubmission> {
+        let form_attributes = self.forms.forms.get(&form)?;
+        let submitter_attributes = submitter
+            .and_then(|id| self.forms.field(id))
+            .map(|field| &field.attributes);
+
+        let attribute = |submitter_name: &str, form_name: &str| {
+            submitter_attributes
+                .and_then(|attributes| attributes.get(submitter_name))
+                .or_else(|| form_attributes.get(form_name))
+                .map(String::as_str)
+        };
+
+        let base = Url::parse(self.document_url.as_deref()?).ok()?;
+        let action = match attribute("formaction", "action").map(str::trim) {
+            Some(action) if !action.is_empty() => base.join(action).ok()?,
+            _ => base,
+        };
+
+        Some(FormSubmission {
+            action,
+            method: attribute("formmethod", "method").map_or(FormMethod::Get, FormMethod::from_attribute),
+            encoding: attribute("formenctype", "enctype")
+                .map_or(FormEncoding::UrlEncoded, FormEncoding::from_attribute),
+            entries: self.form_entries(form, submitter),
+        })
+    }
+}
+
+/// Returns the form the field belongs to: the form its `form` attribute refers to, or else the form it is in
+fn form_owner<C: HasDocument>(doc: &C::Document, id: NodeId, attributes: &HashMap<String, String>) -> Option<NodeId> {
+    if let Some(form) = attributes.get("form") {
+        let node = doc.node_by_named_id(form)?;
+        return is_form::<C>(node).then(|| node.id());
+    }
+
+    let mut current = doc.node_by_id(id)?.parent_id();
+    while let Some(id) = current {
+        let node = doc.node_by_id(id)?;
+        if is_form::<C>(node) {
+            return Some(id);
+        }
+        current = node.parent_id();
+    }
+
+    None
+}
+
+fn is_form<C: HasDocument>(node: &<C::Document as Document<C>>::Node) -> bool {
+    node.get_element_data()
+        .is_some_and(|element| element.name() == "form" && element.namespace() == HTML_NAMESPACE)
+}
+
+/// Returns true when the node is in a disabled `<fieldset>`, but not in the first `<legend>` of that fieldset
+fn in_disabled_fieldset<C: HasDocument>(doc: &C::Document, id: NodeId) -> bool {
+    let mut child = id;
+    let mut current = doc.node_by_id(id).and_then(|node| node.parent_id());
+
+    while let Some(id) = current {
+        let Some(node) = doc.node_by_id(id) else {
+            return false;
+        };
+
+        if let Some(element) = node.get_element_data() {
+            if element.name() == "fieldset" && element.attributes().contains_key("disabled") {
+                let first_legend = node.children().iter().copied().find(|child| {
+                    doc.node_by_id(*child)
+                        .and_then(|node| node.get_element_data())
+                        .is_some_and(|element| element.name() == "legend")
+                });
+
+                if first_legend != Some(child) {
+                    return true;
+                }
+            }
+        }
+
+        child = id;
+        current = node.parent_id();
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_tree::form::SelectOption;
+
+    fn field(element: &str, attrs: &[(&str, &str)]) -> FormField {
+        let attributes = attrs
+            .iter()
+            .map(|(k, v)| ((*k).to_owned(), (*v).to_owned()))
+            .collect::<HashMap<_, _>>();
+
+        FormField {
+            id: NodeId::from(1u64),
+            element: element.to_owned(),
+            control: ControlKind::of(element, &attributes).map(|kind| ControlData {
+                kind,
+                attributes: attributes.clone(),
+                options: Vec::new(),
+                text: String::new(),
+                shown: None,
+            }),
+            attributes,
+            form: None,
+            disabled: false,
+        }
+    }
+
+    fn entries(field: &FormField, state: Option<&ControlState>, submitter: bool) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        field.append_entries(state, submitter, &mut entries);
+        entries
+    }
+
+    fn pair(name: &str, value: &str) -> (String, String) {
+        (name.to_owned(), value.to_owned())
+    }
+
+    #[test]
+    fn text_and_hidden_entries() {
+        let text = field("input", &[("name", "q"), ("value", "initial"), ("dirname", "q.dir")]);
+        assert_eq!(
+            entries(&text, None, false),
+            vec![pair("q", "initial"), pair("q.dir", "ltr")]
+        );
+
+        let state = ControlState {
+            value: "typed".to_owned(),
+            ..ControlState::default()
+        };
+        assert_eq!(entries(&text, Some(&state), false)[0], pair("q", "typed"));
+
+        let hidden = field("input", &[("type", "hidden"), ("name", "token"), ("value", "abc")]);
+        assert_eq!(entries(&hidden, None, false), vec![pair("token", "abc")]);
+
+        let charset = field("input", &[("type", "hidden"), ("name", "_charset_")]);
+        assert_eq!(entries(&charset, None, false), vec![pair("_charset_", "UTF-8")]);
+
+        let unnamed = field("input", &[("value", "x")]);
+        assert!(entries(&unnamed, None, false).is_empty());
+
+        let mut disabled = field("input", &[("name", "q")]);
+        disabled.disabled = true;
+        assert!(entries(&disabled, None, false).is_empty());
+    }
+
+    #[test]
+    fn checked_and_selected_entries() {
+        let checkbox = field("input", &[("type", "checkbox"), ("name", "agree")]);
+        assert!(entries(&checkbox, None, false).is_empty());
+
+        let checked = ControlState {
+            checked: true,
+            ..ControlState::default()
+        };
+        assert_eq!(entries(&checkbox, Some(&checked), false), vec![pair("agree", "on")]);
+
+        let radio = field(
+            "input",
+            &[("type", "radio"), ("name", "size"), ("value", "l"), ("checked", "")],
+        );
+        assert_eq!(entries(&radio, None, false), vec![pair("size", "l")]);
+
+        let mut select = field("select", &[("name", "tags"), ("multiple", "")]);
+        let option = |value: &str, selected: bool, disabled: bool| SelectOption {
+            label: value.to_owned(),
+            value: value.to_owned(),
+            selected,
+            disabled,
+        };
+        if let Some(control) = &mut select.control {
+            control.options = vec![
+                option("a", true, false),
+                option("b", false, false),
+                option("c", true, true),
+                option("d", true, false),
+            ];
+        }
+        assert_eq!(
+            entries(&select, None, false),
+            vec![pair("tags", "a"), pair("tags", "d")]
+        );
+
+        // Choosing an option of a multiple select keeps the other chosen options
+        let control = select.control.clone().unwrap();
+        let mut state = control.initial_state();
+        state.select_option(&control, 1, true);
+        state.select_option(&control, 0, true);
+        assert_eq!(
+            entries(&select, Some(&state), false),
+            vec![pair("tags", "b"), pair("tags", "d")]
+        );
+
+        // A multiple select may submit nothing
+        let mut state = control.initial_state();
+        state.select_option(&control, 3, false);
+        state.select_option(&control, 3, true);
+        assert!(entries(&select, Some(&state), false).is_empty());
+
+        select.attributes.remove("multiple");
+        if let Some(control) = &mut select.control {
+            control.attributes.remove("multiple");
+        }
+        let state = ControlState {
+            selected: Some(1),
+            ..ControlState::default()
+        };
+        assert_eq!(entries(&select, Some(&state), false), vec![pair("tags", "b")]);
+    }
+
+    #[test]
+    fn button_entries() {
+        let submit = field("input", &[("type", "submit"), ("name", "go"), ("value", "Search")]);
+        assert!(submit.is_submit_button());
+        assert!(entries(&submit, None, false).is_empty());
+        assert_eq!(entries(&submit, None, true), vec![pair("go", "Search")]);
+
+        let button = field("button", &[("name", "action"), ("value", "save")]);
+        assert!(button.is_submit_button());
+        assert_eq!(entries(&button, None, true), vec![pair("action", "save")]);
+
+        assert!(!field("button", &[("type", "reset")]).is_submit_button());
+        assert!(field("button", &[("type", "bogus")]).is_submit_button());
+        assert!(!field("input", &[("type", "button")]).is_submit_button());
+
+        assert!(field("input", &[("type", "email")]).blocks_implicit_submission());
+        assert!(!field("input", &[("type", "checkbox")]).blocks_implicit_submission());
+    }
+}
@@ -0,0 +1,178 @@
+//! Inline `<svg>` elements. These are replaced elements: their descendants are not part of the render tree, but
+//! the markup of the complete element is kept, so it can be handed to the svg renderer of the backend.
+
+use gosub_interface::css3::{CssProperty, CssSystem};
+use gosub_interface::layout::IntrinsicSize;
+use std::collections::HashMap;
+
+pub const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+/// Data of an inline `<svg>` element
+#[derive(Debug, Clone)]
+pub struct SvgData {
+    pub attributes: HashMap<String, String>,
+    /// Markup of the element and all of its descendants
+    pub source: String,
+}
+
+/// Returns the natural size of an `<svg>` element from its `width`, `height` and `viewBox` attributes.
+/// Percentages and other relative lengths do not give a natural size.
+#[must_use]
+pub fn intrinsic_size(attributes: &HashMap<String, String>) -> IntrinsicSize {
+    let width = attributes.get("width").and_then(|w| parse_length(w));
+    let height = attributes.get("height").and_then(|h| parse_length(h));
+
+    let ratio = match (width, height) {
+        (Some(w), Some(h)) if w > 0.0 && h > 0.0 => Some(w / h),
+        _ => attributes.get("viewBox").and_then(|v| parse_view_box_ratio(v)),
+    };
+
+    IntrinsicSize { width, height, ratio }
+}
+
+fn parse_length(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let value = value.strip_suffix("px").unwrap_or(value);
+
+    value.parse::<f32>().ok().filter(|v| v.is_finite() && *v >= 0.0)
+}
+
+fn parse_view_box_ratio(value: &str) -> Option<f32> {
+    let values = value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|v| !v.is_empty())
+        .map(str::parse::<f32>)
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+
+    match values.as_slice() {
+        [_, _, w, h] if *w > 0.0 && *h > 0.0 => Some(w / h),
+        _ => None,
+    }
+}
+
+/// Formats a color as returned by `CssProperty::parse_color` (all components in the 0-255 range)
+#[must_use]
+pub fn css_color(color: (f32, f32, f32, f32)) -> String {
+    format!("rgba({}, {}, {}, {})", color.0, color.1, color.2, color.3 / 255.0)
+}
+
+/// Resolves a computed `fill` or `stroke` property of the host element into an svg paint. `currentcolor`
+/// resolves to the given color.
+pub fn resolve_paint<S: CssSystem>(prop: &S::Property, current_color: &str) -> Option<String> {
+    if prop.is_none() {
+        return Some("none".to_string());
+    }
+
+    match prop.as_string() {
+        Some(s) if s.eq_ignore_ascii_case("currentcolor") => return Some(current_color.to_string()),
+        Some(s) if s.eq_ignore_ascii_case("none") => return Some("none".to_string()),
+        _ => {}
+    }
+
+    prop.parse_color().map(css_color)
+}
+
+/// Prepares the markup of an inline `<svg>` for the svg renderer. The `color`, `fill` and `stroke` of the host
+/// element are set on the root element, from where they are inherited by all shapes that do not specify them. The
+/// svg renderer resolves `currentColor` in paints to the inherited `color`.
+#[must_use]
+pub fn style_svg(source: &str, color: &str, fill: Option<&str>, stroke: Option<&str>) -> String {
+    let mut source = source.to_string();
+
+    let Some(start) = source.find("<svg") else {
+        return source;
+    };
+    let Some(end) = tag_end(&source, start) else {
+        return source;
+    };
+
+    let tag = &source[start..end];
+    let mut extra = String::new();
+
+    if !has_attribute(tag, "xmlns") {
+        extra.push_str(&format!(" xmlns=\"{SVG_NAMESPACE}\""));
+    }
+    for (name, value) in [("color", Some(color)), ("fill", fill), ("stroke", stroke)] {
+        if let Some(value) = value {
+            if !has_attribute(tag, name) {
+                extra.push_str(&format!(" {name}=\"{value}\""));
+            }
+        }
+    }
+
+    let insert_at = if tag.ends_with('/') { end - 1 } else { end };
+    source.insert_str(insert_at, &extra);
+
+    source
+}
+
+/// Returns the offset of the closing `>` of the tag starting at `start`, skipping over quoted attribute values
+fn tag_end(source: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+
+    for (idx, c) in source[start..].char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('>', None) => return Some(start + idx),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn has_attribute(tag: &str, name: &str) -> bool {
+    tag.split(|c: char| c.is_whitespace())
+        .any(|part| part.split('=').next() == Some(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::attributes;
+
+    #[test]
+    fn svg_intrinsic_size() {
+        let size = intrinsic_size(&attributes(&[("width", "100"), ("height", "50px")]));
+        assert_eq!(
+            size,
+            IntrinsicSize {
+                width: Some(100.0),
+                height: Some(50.0),
+                ratio: Some(2.0),
+            }
+        );
+
+        let size = intrinsic_size(&attributes(&[("width", "40"), ("viewBox", "0 0 24,12")]));
+        assert_eq!(
+            size,
+            IntrinsicSize {
+                width: Some(40.0),
+                height: None,
+                ratio: Some(2.0),
+            }
+        );
+
+        let size = intrinsic_size(&attributes(&[("width", "50%"), ("viewBox", "0 0 0 10")]));
+        assert_eq!(size, IntrinsicSize::default());
+    }
+
+    #[test]
+    fn svg_host_style() {
+        // currentColor is left to the svg renderer, so text and other attributes that mention it are kept as they are
+        let source = r#"<svg width="10" fill="red"><path stroke="currentColor" d="M0 0" data-x="currentColor"></path><text>currentColor</text></svg>"#;
+
+        assert_eq!(
+            style_svg(source, "rgba(0, 0, 255, 1)", Some("green"), Some("none")),
+            r#"<svg width="10" fill="red" xmlns="http://www.w3.org/2000/svg" color="rgba(0, 0, 255, 1)" stroke="none"><path stroke="currentColor" d="M0 0" data-x="currentColor"></path><text>currentColor</text></svg>"#
+        );
+
+        let source = r#"<svg xmlns="http://www.w3.org/2000/svg" color="red" data-x="a>b"/>"#;
+        assert_eq!(
+            style_svg(source, "black", Some("black"), None),
+            r#"<svg xmlns="http://www.w3.org/2000/svg" color="red" data-x="a>b" fill="black"/>"#
+        );
+    }
+}
@@ -0,0 +1,652 @@
+//! Scroll containers. An element with `overflow: auto` or `overflow: scroll` clips its content to its scrollport and
+//! lets the user scroll the content that overflows it. The renderer keeps the scroll offset of every container; this
+//! module resolves how far a container can scroll, where its scrollbars are and how wheel scrolling chains to the
+//! parents of a container that reached its limits. Scrolling an element into view follows its `scroll-margin` and
+//! the `scroll-padding` of the container, and is animated when the container has `scroll-behavior: smooth`.
+
+use crate::background::{Area, Edges};
+use gosub_interface::css3::{CssProperty, CssPropertyMap, CssSystem};
+use gosub_interface::layout::Layout;
+use gosub_shared::geo::{Point, Size, FP};
+use std::time::{Duration, Instant};
+
+/// Thickness of the scrollbars that are drawn over the content, when the layout reserved no space for them
+pub const OVERLAY_SCROLLBAR_SIZE: FP = 8.0;
+/// Shortest length of the thumb of a scrollbar, which keeps it easy to grab
+const MIN_THUMB_LENGTH: FP = 20.0;
+/// Time a smooth scroll takes
+pub const SMOOTH_SCROLL_DURATION: Duration = Duration::from_millis(300);
+
+/// Value of `overflow-x` or `overflow-y`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    #[default]
+    Visible,
+    Hidden,
+    Clip,
+    Scroll,
+    Auto,
+}
+
+impl Overflow {
+    fn parse(value: &str) -> Self {
+        match value {
+            "hidden" => Self::Hidden,
+            "clip" => Self::Clip,
+            "scroll" => Self::Scroll,
+            "auto" | "overlay" => Self::Auto,
+            _ => Self::Visible,
+        }
+    }
+
+    /// Returns true when the user can scroll along the axis. A `hidden` axis can only be scrolled by the page itself.
+    #[must_use]
+    pub fn is_user_scrollable(self) -> bool {
+        matches!(self, Self::Scroll | Self::Auto)
+    }
+
+    /// Returns true when the axis makes the element a scroll container
+    #[must_use]
+    pub fn is_scroll_container(self) -> bool {
+        matches!(self, Self::Hidden | Self::Scroll | Self::Auto)
+    }
+}
+
+/// Returns the computed `overflow-x` and `overflow-y` of an element. When one axis scrolls, the other can not be
+/// `visible` or `clip`: they compute to `auto` and `hidden`.
+pub fn overflow<S: CssSystem>(props: &S::PropertyMap) -> (Overflow, Overflow) {
+    let axis = |name: &str| {
+        props
+            .get(name)
+            .and_then(|prop| prop.as_string())
+            .map_or(Overflow::Visible, Overflow::parse)
+    };
+
+    let fixup = |overflow: Overflow, other: Overflow| match overflow {
+        Overflow::Visible if other.is_scroll_container() => Overflow::Auto,
+        Overflow::Clip if other.is_scroll_container() => Overflow::Hidden,
+        _ => overflow,
+    };
+
+    let (x, y) = (axis("overflow-x"), axis("overflow-y"));
+    (fixup(x, y), fixup(y, x))
+}
+
+/// Axis of a scrollbar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// Scrollbar of a scroll container, relative to its border box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scrollbar {
+    pub axis: Axis,
+    /// Area the thumb moves in
+    pub track: Area,
+    /// Part of the track that stands for the visible part of the content
+    pub thumb: Area,
+    /// The scrollbar is drawn over the content, because the layout reserved no space for it
+    pub overlay: bool,
+    /// Largest scroll offset along the axis
+    max: FP,
+}
+
+impl Scrollbar {
+    /// Returns the scroll offset that puts the start of the thumb at `start`, along the axis of the scrollbar
+    #[must_use]
+    pub fn offset_at(&self, start: FP) -> FP {
+        let (track_start, track, thumb) = match self.axis {
+            Axis::Horizontal => (self.track.pos.x, self.track.size.width, self.thumb.size.width),
+            Axis::Vertical => (self.track.pos.y, self.track.size.height, self.thumb.size.height),
+        };
+        if track <= thumb {
+            return 0.0;
+        }
+
+        ((start - track_start) / (track - thumb) * self.max).clamp(0.0, self.max)
+    }
+
+    /// Returns where the thumb is grabbed when the mouse is pressed at the point: the distance from the start of the
+    /// thumb. A press on the track next to the thumb grabs the middle of the thumb, which moves it under the mouse.
+    #[must_use]
+    pub fn grab(&self, point: Point) -> FP {
+        let (start, length) = match self.axis {
+            Axis::Horizontal => (self.thumb.pos.x, self.thumb.size.width),
+            Axis::Vertical => (self.thumb.pos.y, self.thumb.size.height),
+        };
+        let along = self.along(point);
+
+        if along >= start && along <= start + length {
+            along - start
+        } else {
+            length / 2.0
+        }
+    }
+
+    /// Returns the position of a point along the axis of the scrollbar
+    #[must_use]
+    pub fn along(&self, point: Point) -> FP {
+        match self.axis {
+            Axis::Horizontal => point.x,
+            Axis::Vertical => point.y,
+        }
+    }
+}
+
+/// Track of a scrollbar when it is shown, and whether it is drawn over the content
+type Track = Option<(Area, bool)>;
+
+/// Scroll container, relative to its border box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollBox {
+    /// Area the content is scrolled in: the padding box without the scrollbar gutters
+    pub port: Area,
+    /// Largest scroll offset along each axis
+    pub max: Point,
+    /// Whether the user can scroll along the horizontal and the vertical axis
+    pub scrollable: (bool, bool),
+    /// Tracks of the horizontal and the vertical scrollbar
+    tracks: (Track, Track),
+}
+
+impl ScrollBox {
+    /// Resolves a scroll container with a border box of `size`. `content` is the size of its content, measured from
+    /// the top left corner of the border box, and `gutter` the space the layout reserved for the vertical (width) and
+    /// horizontal (height) scrollbar. None when the element is not a scroll container.
+    #[must_use]
+    pub fn new(
+        (x, y): (Overflow, Overflow),
+        size: Size,
+        border: Edges,
+        padding: Edges,
+        content: Size,
+        gutter: Size,
+    ) -> Option<Self> {
+        if !x.is_scroll_container() && !y.is_scroll_container() {
+            return None;
+        }
+
+        let port = Area::new(
+            Point::new(border.left, border.top),
+            Size::new(
+                (size.width - border.left - border.right - gutter.width).max(0.0),
+                (size.height - border.top - border.bottom - gutter.height).max(0.0),
+            ),
+        );
+
+        // The padding at the end of the content scrolls into view as well
+        let max = Point::new(
+            (content.width + padding.right - port.pos.x - port.size.width).max(0.0),
+            (content.height + padding.bottom - port.pos.y - port.size.height).max(0.0),
+        );
+
+        // A scrollbar goes in its gutter, or else over the content when the content overflows
+        let horizontal = if gutter.height > 0.0 {
+            Some((
+                Area::new(
+                    Point::new(port.pos.x, port.pos.y + port.size.height),
+                    Size::new(port.size.width, gutter.height),
+                ),
+                false,
+            ))
+        } else {
+            (x.is_user_scrollable() && max.x > 0.0).then(|| {
+                (
+                    Area::new(
+                        Point::new(port.pos.x, port.pos.y + port.size.height - OVERLAY_SCROLLBAR_SIZE),
+                        Size::new(port.size.width, OVERLAY_SCROLLBAR_SIZE),
+                    ),
+                    true,
+                )
+            })
+        };
+        let vertical = if gutter.width > 0.0 {
+            Some((
+                Area::new(
+                    Point::new(port.pos.x + port.size.width, port.pos.y),
+                    Size::new(gutter.width, port.size.height),
+                ),
+                false,
+            ))
+        } else {
+            (y.is_user_scrollable() && max.y > 0.0).then(|| {
+                // The vertical scrollbar leaves the corner to a horizontal scrollbar over the content
+                let corner = if gutter.height > 0.0 {
+                    0.0
+                } else {
+                    horizontal.map_or(0.0, |(h, _)| h.size.height)
+                };
+                (
+                    Area::new(
+                        Point::new(port.pos.x + port.size.width - OVERLAY_SCROLLBAR_SIZE, port.pos.y),
+                        Size::new(OVERLAY_SCROLLBAR_SIZE, (port.size.height - corner).max(0.0)),
+                    ),
+                    true,
+                )
+            })
+        };
+
+        Some(Self {
+            port,
+            max,
+            scrollable: (x.is_user_scrollable(), y.is_user_scrollable()),
+            tracks: (horizontal, vertical),
+        })
+    }
+
+    /// Returns true when the user can scroll the container in some direction
+    #[must_use]
+    pub fn can_scroll(&self) -> bool {
+        (self.scrollable.0 && self.max.x > 0.0) || (self.scrollable.1 && self.max.y > 0.0)
+    }
+
+    /// Returns the offset clamped to the scrollable range
+    #[must_use]
+    pub fn clamp(&self, offset: Point) -> Point {
+        Point::new(offset.x.clamp(0.0, self.max.x), offset.y.clamp(0.0, self.max.y))
+    }
+
+    /// Scrolls the container at `offset` by `delta`, where a positive delta scrolls further into the content. Returns
+    /// the new offset and the part of the delta that is left when the container reaches its limits, which scrolls
+    /// its parent.
+    #[must_use]
+    pub fn scroll_by(&self, offset: Point, delta: Point) -> (Point, Point) {
+        let axis = |scrollable: bool, offset: FP, delta: FP, max: FP| {
+            if !scrollable {
+                return (offset, delta);
+            }
+            let new = (offset + delta).clamp(0.0, max.max(0.0));
+            (new, delta - (new - offset))
+        };
+
+        let (x, rest_x) = axis(self.scrollable.0, offset.x, delta.x, self.max.x);
+        let (y, rest_y) = axis(self.scrollable.1, offset.y, delta.y, self.max.y);
+
+        (Point::new(x, y), Point::new(rest_x, rest_y))
+    }
+
+    /// Returns the scrollbars of the container scrolled to `offset`
+    #[must_use]
+    pub fn scrollbars(&self, offset: Point) -> Vec<Scrollbar> {
+        let offset = self.clamp(offset);
+        let mut scrollbars = Vec::new();
+
+        if let Some((track, overlay)) = self.tracks.0 {
+            let (start, length) = thumb(track.size.width, self.port.size.width, self.max.x, offset.x);
+            scrollbars.push(Scrollbar {
+                axis: Axis::Horizontal,
+                track,
+                thumb: Area::new(
+                    Point::new(track.pos.x + start, track.pos.y),
+                    Size::new(length, track.size.height),
+                ),
+                overlay,
+                max: self.max.x,
+            });
+        }
+
+        if let Some((track, overlay)) = self.tracks.1 {
+            let (start, length) = thumb(track.size.height, self.port.size.height, self.max.y, offset.y);
+            scrollbars.push(Scrollbar {
+                axis: Axis::Vertical,
+                track,
+                thumb: Area::new(
+                    Point::new(track.pos.x, track.pos.y + start),
+                    Size::new(track.size.width, length),
+                ),
+                overlay,
+                max: self.max.y,
+            });
+        }
+
+        scrollbars
+    }
+}
+
+/// Returns the start and the length of the thumb in a track of `track` long, for a scrollport of `port` long that is
+/// scrolled to `offset` of at most `max`
+fn thumb(track: FP, port: FP, max: FP, offset: FP) -> (FP, FP) {
+    if max <= 0.0 || port <= 0.0 {
+        return (0.0, track);
+    }
+
+    let length = (track * port / (port + max)).max(MIN_THUMB_LENGTH).min(track);
+    (((track - length) * offset / max).max(0.0), length)
+}
+
+/// Returns the scroll container of an element, or None when it is not one
+pub fn scroll_box<S: CssSystem>(props: &S::PropertyMap, layout: &impl Layout) -> Option<ScrollBox> {
+    ScrollBox::new(
+        overflow::<S>(props),
+        layout.size(),
+        Edges::from_layout_rect(layout.border()),
+        Edges::from_layout_rect(layout.padding()),
+        layout.content(),
+        layout.scrollbar(),
+    )
+}
+
+/// Returns true when `scroll-behavior` of a scrolling box asks for the scrolls of the page to be animated. Scrolling
+/// by the user is never animated.
+pub fn is_smooth<S: CssSystem>(props: &S::PropertyMap) -> bool {
+    props.get("scroll-behavior").and_then(|prop| prop.as_string()) == Some("smooth")
+}
+
+/// Returns `scroll-margin` of an element: how far its box is grown when it is scrolled into view
+pub fn scroll_margin<S: CssSystem>(props: &S::PropertyMap) -> Edges {
+    let side = |name: &str| props.get(name).map_or(0.0, |prop| prop.unit_to_px());
+
+    Edges {
+        top: side("scroll-margin-top"),
+        right: side("scroll-margin-right"),
+        bottom: side("scroll-margin-bottom"),
+        left: side("scroll-margin-left"),
+    }
+}
+
+/// Returns `scroll-padding` of a scrolling box: the edges of its scrollport that an element scrolled into view stays
+/// out of, like the space under a fixed header. Percentages resolve against the scrollport.
+pub fn scroll_padding<S: CssSystem>(props: &S::PropertyMap, port: Size) -> Edges {
+    let side = |name: &str, basis: FP| {
+        let Some(prop) = props.get(name) else {
+            return 0.0;
+        };
+        if prop.as_string().is_some() {
+            // auto
+            return 0.0;
+        }
+
+        prop.as_percentage()
+            .map_or_else(|| prop.unit_to_px(), |p| basis * p / 100.0)
+    };
+
+    Edges {
+        top: side("scroll-padding-top", port.height),
+        right: side("scroll-padding-right", port.width),
+        bottom: side("scroll-padding-bottom", port.height),
+        left: side("scroll-padding-left", port.width),
+    }
+}
+
+/// Where an element ends up in the scrollport when it is scrolled into view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    /// In the middle of the scrollport, unless it is in view already
+    Center,
+    /// At the top of the scrollport, and horizontally as little as it takes to be in view, like the target of a
+    /// fragment
+    Start,
+}
+
+/// Returns how far to scroll so the area, grown by its scroll margin, is in view in the scrollport, shrunk by its
+/// scroll padding. The area and the scrollport are in the same coordinates, and a positive distance scrolls further
+/// into the content.
+#[must_use]
+pub fn scroll_into_view(area: Area, margin: Edges, port: Area, padding: Edges, align: Align) -> Point {
+    let area = Area::new(
+        Point::new(area.pos.x - margin.left, area.pos.y - margin.top),
+        Size::new(
+            area.size.width + margin.left + margin.right,
+            area.size.height + margin.top + margin.bottom,
+        ),
+    );
+    let port = port.inset(padding);
+
+    let in_view =
+        |start: FP, len: FP, port_start: FP, port_len: FP| start >= port_start && start + len <= port_start + port_len;
+    let center = |start: FP, len: FP, port_start: FP, port_len: FP| {
+        if in_view(start, len, port_start, port_len) {
+            0.0
+        } else {
+            start + len / 2.0 - (port_start + port_len / 2.0)
+        }
+    };
+    let nearest = |start: FP, len: FP, port_start: FP, port_len: FP| {
+        if in_view(start, len, port_start, port_len) {
+            0.0
+        } else if start < port_start || len > port_len {
+            start - port_start
+        } else {
+            start + len - (port_start + port_len)
+        }
+    };
+
+    match align {
+        Align::Center => Point::new(
+            center(area.pos.x, area.size.width, port.pos.x, port.size.width),
+            center(area.pos.y, area.size.height, port.pos.y, port.size.height),
+        ),
+        Align::Start => Point::new(
+            nearest(area.pos.x, area.size.width, port.pos.x, port.size.width),
+            area.pos.y - port.pos.y,
+        ),
+    }
+}
+
+/// Animation of a smooth scroll from one scroll offset to another
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollAnimation {
+    pub from: Point,
+    pub to: Point,
+    start: Instant,
+}
+
+impl ScrollAnimation {
+    #[must_use]
+    pub fn new(from: Point, to: Point, start: Instant) -> Self {
+        Self { from, to, start }
+    }
+
+    /// Returns the scroll offset at `now`, and whether the animation has ended
+    #[must_use]
+    pub fn at(&self, now: Instant) -> (Point, bool) {
+        let t =
+            (now.saturating_duration_since(self.start).as_secs_f32() / SMOOTH_SCROLL_DURATION.as_secs_f32()).min(1.0);
+
+        // The scroll starts and stops gently
+        let eased = if t < 0.5 {
+            4.0 * t * t * t
+        } else {
+            1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+        };
+
+        let offset = Point::new(
+            self.from.x + (self.to.x - self.from.x) * eased,
+            self.from.y + (self.to.y - self.from.y) * eased,
+        );
+        (offset, t >= 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::props;
+    use gosub_css3::system::Css3System;
+
+    fn uniform(width: FP) -> Edges {
+        Edges {
+            top: width,
+            right: width,
+            bottom: width,
+            left: width,
+        }
+    }
+
+    #[test]
+    fn computed_overflow() {
+        assert_eq!(
+            overflow::<Css3System>(&props(&[])),
+            (Overflow::Visible, Overflow::Visible)
+        );
+        assert_eq!(
+            overflow::<Css3System>(&props(&[("overflow-y", "auto")])),
+            (Overflow::Auto, Overflow::Auto)
+        );
+        assert_eq!(
+            overflow::<Css3System>(&props(&[("overflow-x", "clip"), ("overflow-y", "scroll")])),
+            (Overflow::Hidden, Overflow::Scroll)
+        );
+        assert_eq!(
+            overflow::<Css3System>(&props(&[("overflow-x", "clip")])),
+            (Overflow::Clip, Overflow::Visible)
+        );
+    }
+
+    #[test]
+    fn scroll_range_and_chaining() {
+        // 200x100 with a border of 2, padding of 10 and a reserved vertical gutter of 16
+        let scroll = ScrollBox::new(
+            (Overflow::Hidden, Overflow::Scroll),
+            Size::new(200.0, 100.0),
+            uniform(2.0),
+            uniform(10.0),
+            Size::new(150.0, 400.0),
+            Size::new(16.0, 0.0),
+        )
+        .unwrap();
+
+        assert_eq!(scroll.port, Area::new(Point::new(2.0, 2.0), Size::new(180.0, 96.0)));
+        assert_eq!(scroll.max, Point::new(0.0, 312.0));
+        assert!(scroll.can_scroll());
+
+        let (offset, rest) = scroll.scroll_by(Point::ZERO, Point::new(30.0, 100.0));
+        assert_eq!(offset, Point::new(0.0, 100.0));
+        // The hidden axis can not be scrolled by the user, so all of it goes to the parent
+        assert_eq!(rest, Point::new(30.0, 0.0));
+
+        let (offset, rest) = scroll.scroll_by(Point::new(0.0, 300.0), Point::new(0.0, 50.0));
+        assert_eq!(offset, Point::new(0.0, 312.0));
+        assert_eq!(rest, Point::new(0.0, 38.0));
+
+        let (offset, rest) = scroll.scroll_by(Point::new(0.0, 20.0), Point::new(0.0, -50.0));
+        assert_eq!(offset, Point::ZERO);
+        assert_eq!(rest, Point::new(0.0, -30.0));
+
+        assert_eq!(
+            ScrollBox::new(
+                (Overflow::Visible, Overflow::Visible),
+                Size::new(200.0, 100.0),
+                uniform(0.0),
+                uniform(0.0),
+                Size::new(400.0, 400.0),
+                Size::ZERO,
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn scrollbar_thumbs() {
+        let scroll = ScrollBox::new(
+            (Overflow::Auto, Overflow::Auto),
+            Size::new(100.0, 100.0),
+            uniform(0.0),
+            uniform(0.0),
+            Size::new(100.0, 400.0),
+            Size::ZERO,
+        )
+        .unwrap();
+
+        // Only the overflowing axis gets a scrollbar, which goes over the content
+        let bars = scroll.scrollbars(Point::new(0.0, 150.0));
+        assert_eq!(bars.len(), 1);
+        let bar = bars[0];
+        assert_eq!(bar.axis, Axis::Vertical);
+        assert!(bar.overlay);
+        assert_eq!(
+            bar.track,
+            Area::new(
+                Point::new(100.0 - OVERLAY_SCROLLBAR_SIZE, 0.0),
+                Size::new(OVERLAY_SCROLLBAR_SIZE, 100.0)
+            )
+        );
+        // The thumb is a quarter of the track, halfway down
+        assert_eq!(bar.thumb.size.height, 25.0);
+        assert_eq!(bar.thumb.pos.y, 37.5);
+
+        // Dragging the thumb to a place in the track scrolls to the matching offset
+        assert_eq!(bar.offset_at(bar.thumb.pos.y), 150.0);
+        assert_eq!(bar.offset_at(75.0), 300.0);
+        assert_eq!(bar.offset_at(-10.0), 0.0);
+
+        // A press on the thumb keeps the place it was grabbed at, a press next to it grabs its middle
+        assert_eq!(bar.grab(Point::new(95.0, 40.0)), 2.5);
+        assert_eq!(bar.grab(Point::new(95.0, 90.0)), 12.5);
+    }
+
+    #[test]
+    fn scroll_into_view_alignment() {
+        let port = Area::new(Point::ZERO, Size::new(100.0, 100.0));
+        let none = uniform(0.0);
+
+        // In view already
+        let area = Area::new(Point::new(10.0, 20.0), Size::new(20.0, 20.0));
+        assert_eq!(scroll_into_view(area, none, port, none, Align::Center), Point::ZERO);
+
+        // Below the scrollport: centered, or at the top
+        let area = Area::new(Point::new(10.0, 300.0), Size::new(20.0, 20.0));
+        assert_eq!(
+            scroll_into_view(area, none, port, none, Align::Center),
+            Point::new(0.0, 260.0)
+        );
+        assert_eq!(
+            scroll_into_view(area, none, port, none, Align::Start),
+            Point::new(0.0, 300.0)
+        );
+
+        // A fixed header of 30 in the scroll padding, and a scroll margin of 10 above the target
+        let padding = Edges {
+            top: 30.0,
+            ..Edges::default()
+        };
+        let margin = Edges {
+            top: 10.0,
+            ..Edges::default()
+        };
+        assert_eq!(
+            scroll_into_view(area, margin, port, padding, Align::Start),
+            Point::new(0.0, 260.0)
+        );
+
+        // Horizontally only as far as it takes
+        let area = Area::new(Point::new(150.0, 0.0), Size::new(20.0, 20.0));
+        assert_eq!(
+            scroll_into_view(area, none, port, none, Align::Start),
+            Point::new(70.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn scroll_padding_percentages() {
+        let map = props(&[
+            ("scroll-padding-left", "auto"),
+            ("scroll-padding-top", "10%"),
+            ("scroll-margin-top", "8px"),
+        ]);
+
+        let padding = scroll_padding::<Css3System>(&map, Size::new(300.0, 200.0));
+        assert_eq!((padding.top, padding.left, padding.bottom), (20.0, 0.0, 0.0));
+        assert_eq!(scroll_margin::<Css3System>(&map).top, 8.0);
+        assert!(is_smooth::<Css3System>(&props(&[("scroll-behavior", "smooth")])));
+        assert!(!is_smooth::<Css3System>(&props(&[])));
+    }
+
+    #[test]
+    fn smooth_scroll_animation() {
+        let start = Instant::now();
+        let animation = ScrollAnimation::new(Point::ZERO, Point::new(0.0, 200.0), start);
+
+        assert_eq!(animation.at(start), (Point::ZERO, false));
+        assert_eq!(
+            animation.at(start + SMOOTH_SCROLL_DURATION / 2),
+            (Point::new(0.0, 100.0), false)
+        );
+        assert_eq!(
+            animation.at(start + SMOOTH_SCROLL_DURATION * 2),
+            (Point::new(0.0, 200.0), true)
+        );
+    }
+}
@@ -0,0 +1,581 @@
+//! Text selection. A selection runs between two carets in the text of the text nodes, in document order, so it can
+//! span inline and block boundaries. The text of a text node is the text of its text layouts after white space
+//! processing, which is also the text that is copied.
+
+use std::ops::Range;
+
+use gosub_interface::config::HasLayouter;
+use gosub_interface::css3::CssProperty;
+use gosub_interface::layout::{HasTextLayout, LayoutNode, LayoutTree, Layouter, TextCluster, TextLayout};
+use gosub_shared::geo::{Point, Size};
+
+use crate::background::Area;
+use crate::position::PositionTree;
+
+pub(crate) type NodeId<C> = <<C as HasLayouter>::LayoutTree as LayoutTree<C>>::NodeId;
+
+/// Place between two characters in the text of a text node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Caret<Id> {
+    /// Text node the caret is in
+    pub node: Id,
+    /// Byte offset in the text of the node
+    pub offset: usize,
+}
+
+/// Unit of text a selection grows in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Granularity {
+    #[default]
+    Character,
+    Word,
+    Paragraph,
+}
+
+impl Granularity {
+    /// Returns the granularity of a click that is the `count`th click in a row. A double click selects words and a
+    /// triple click selects paragraphs, after which it starts over.
+    #[must_use]
+    pub fn from_clicks(count: u32) -> Self {
+        match count.saturating_sub(1) % 3 {
+            1 => Self::Word,
+            2 => Self::Paragraph,
+            _ => Self::Character,
+        }
+    }
+}
+
+/// Selected part of the text of a text node
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectedText<Id> {
+    pub node: Id,
+    /// Byte range in the text of the node
+    pub range: Range<usize>,
+}
+
+/// Text that is selected on the page
+#[derive(Debug, Clone, Copy)]
+pub struct Selection<Id> {
+    /// Unit of text around where the selection started. It stays selected while the selection is extended.
+    anchor: (Caret<Id>, Caret<Id>),
+    /// Unit of text around where the selection ends
+    focus: (Caret<Id>, Caret<Id>),
+    granularity: Granularity,
+}
+
+impl<Id: Copy + PartialEq + Into<u64>> Selection<Id> {
+    /// Starts a selection at the caret, which selects the unit of text around it
+    pub fn start<C: HasLayouter<LayoutTree: LayoutTree<C, NodeId = Id>>>(
+        tree: &C::LayoutTree,
+        caret: Caret<Id>,
+        granularity: Granularity,
+    ) -> Self {
+        let unit = unit_at::<C>(tree, caret, granularity);
+
+        Self {
+            anchor: unit,
+            focus: unit,
+            granularity,
+        }
+    }
+
+    /// Moves the end of the selection to the caret. Returns true when the selection changed.
+    pub fn extend<C: HasLayouter<LayoutTree: LayoutTree<C, NodeId = Id>>>(
+        &mut self,
+        tree: &C::LayoutTree,
+        caret: Caret<Id>,
+    ) -> bool {
+        let focus = unit_at::<C>(tree, caret, self.granularity);
+        if focus == self.focus {
+            return false;
+        }
+
+        self.focus = focus;
+        true
+    }
+
+    /// Returns the selected parts of the text nodes, in document order. Nothing is selected when the selection is
+    /// collapsed, or when a node it starts or ends in is gone.
+    pub fn ranges<C: HasLayouter<LayoutTree: LayoutTree<C, NodeId = Id>>>(
+        &self,
+        tree: &C::LayoutTree,
+    ) -> Vec<SelectedText<Id>> {
+        let nodes = text_nodes::<C>(tree);
+        let index = |caret: Caret<Id>| {
+            nodes
+                .iter()
+                .position(|id| *id == caret.node)
+                .map(|idx| (idx, caret.offset))
+        };
+
+        let (Some(anchor_start), Some(anchor_end), Some(focus_start), Some(focus_end)) = (
+            index(self.anchor.0),
+            index(self.anchor.1),
+            index(self.focus.0),
+            index(self.focus.1),
+        ) else {
+            return Vec::new();
+        };
+
+        let start = anchor_start.min(focus_start);
+        let end = anchor_end.max(focus_end);
+
+        let mut ranges = Vec::new();
+        for (idx, id) in nodes.iter().enumerate().take(end.0 + 1).skip(start.0) {
+            let len = node_text::<C>(tree, *id).len();
+            let from = if idx == start.0 { start.1.min(len) } else { 0 };
+            let to = if idx == end.0 { end.1.min(len) } else { len };
+
+            if from < to {
+                ranges.push(SelectedText {
+                    node: *id,
+                    range: from..to,
+                });
+            }
+        }
+
+        ranges
+    }
+
+    /// Returns the selected text. Text of different blocks is put on lines of its own.
+    pub fn text<C: HasLayouter<LayoutTree: LayoutTree<C, NodeId = Id>>>(&self, tree: &C::LayoutTree) -> String {
+        let mut text = String::new();
+        let mut previous_block = None;
+
+        for selected in self.ranges::<C>(tree) {
+            let block = block_of::<C>(tree, selected.node);
+            if previous_block.is_some_and(|previous| previous != block) && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            previous_block = Some(block);
+
+            if let Some(part) = node_text::<C>(tree, selected.node).get(selected.range) {
+                text.push_str(part);
+            }
+        }
+
+        text
+    }
+}
+
+/// Returns the caret closest to the point on the page. The text layout the point is in is preferred, otherwise the
+/// closest line is taken, so dragging past the end of a line or between blocks still selects text.
+pub fn caret_at<C: HasLayouter>(
+    tree: &C::LayoutTree,
+    positions: &PositionTree<C>,
+    point: Point,
+) -> Option<Caret<NodeId<C>>> {
+    let origins = positions.positions();
+
+    let mut closest = None;
+    // Vertical and horizontal distance to the closest text layout
+    let mut closest_distance = (f32::INFINITY, f32::INFINITY);
+
+    for id in text_nodes::<C>(tree) {
+        let Some(origin) = origins.get(&id.into()) else {
+            continue;
+        };
+
+        if let Some((offset, distance)) = closest_offset::<C>(tree, id, *origin, point) {
+            if closest.is_none() || distance < closest_distance {
+                closest = Some(Caret { node: id, offset });
+                closest_distance = distance;
+            }
+        }
+    }
+
+    closest
+}
+
+/// Returns the text offset in the node closest to the point on the page, given the origin of the node. This is how
+/// carets are placed in the text of form controls, which are not part of the selectable text of the page.
+pub fn node_offset_at<C: HasLayouter>(
+    tree: &C::LayoutTree,
+    id: NodeId<C>,
+    origin: Point,
+    point: Point,
+) -> Option<usize> {
+    closest_offset::<C>(tree, id, origin, point).map(|(offset, _)| offset)
+}
+
+/// Returns the closest offset in the text layouts of a node, and its vertical and horizontal distance to the point
+fn closest_offset<C: HasLayouter>(
+    tree: &C::LayoutTree,
+    id: NodeId<C>,
+    origin: Point,
+    point: Point,
+) -> Option<(usize, (f32, f32))> {
+    let mut closest = None;
+    let mut closest_distance = (f32::INFINITY, f32::INFINITY);
+
+    let mut start = 0;
+    for layout in text_layouts::<C>(tree, id) {
+        let offset = layout.offset();
+        let size = layout.size();
+
+        // The box of a text layout is the line above its baseline, like the box of its node
+        let left = origin.x + offset.x;
+        let top = origin.y + offset.y - size.height;
+        let distance = (
+            distance_to(point.y, top, top + size.height),
+            distance_to(point.x, left, left + size.width),
+        );
+
+        if closest.is_none() || distance < closest_distance {
+            closest = Some(start + offset_at(layout.clusters(), point.x - left));
+            closest_distance = distance;
+        }
+
+        start += layout.text().len();
+    }
+
+    closest.map(|offset| (offset, closest_distance))
+}
+
+/// Returns the text of a text node, which is the text of all its text layouts
+pub fn node_text<C: HasLayouter>(tree: &C::LayoutTree, id: NodeId<C>) -> String {
+    text_layouts::<C>(tree, id).iter().map(TextLayout::text).collect()
+}
+
+/// Returns the text layouts of a node, or an empty list when the node has no text
+pub fn text_layouts<C: HasLayouter>(
+    tree: &C::LayoutTree,
+    id: NodeId<C>,
+) -> &[<C::Layouter as Layouter<C>>::TextLayout] {
+    tree.get_node(id)
+        .and_then(|node| node.get_text_layouts())
+        .unwrap_or_default()
+}
+
+/// Returns the boxes of a byte range in the text of a node, relative to the node. There is a box for every text
+/// layout the range is on, which spans the line above its baseline.
+pub fn text_rects<C: HasLayouter>(tree: &C::LayoutTree, id: NodeId<C>, range: Range<usize>) -> Vec<Area> {
+    let mut rects = Vec::new();
+
+    let mut start = 0;
+    for layout in text_layouts::<C>(tree, id) {
+        let text = start..start + layout.text().len();
+        start = text.end;
+
+        let selected = range.start.max(text.start) - text.start..range.end.min(text.end).saturating_sub(text.start);
+        if selected.is_empty() {
+            continue;
+        }
+
+        let Some((from, to)) = cluster_span(layout.clusters(), selected) else {
+            continue;
+        };
+
+        let offset = layout.offset();
+        let height = layout.size().height;
+        rects.push(Area::new(
+            Point::new(offset.x + from, offset.y - height),
+            Size::new(to - from, height),
+        ));
+    }
+
+    rects
+}
+
+/// Returns the box of a caret at a byte offset in the text of a node, relative to the node. The caret has no width
+/// and spans the line above the baseline of the text layout the offset is in.
+pub fn caret_rect<C: HasLayouter>(tree: &C::LayoutTree, id: NodeId<C>, offset: usize) -> Option<Area> {
+    let mut start = 0;
+
+    for layout in text_layouts::<C>(tree, id) {
+        let end = start + layout.text().len();
+
+        if offset <= end {
+            let x = cluster_x(layout.clusters(), offset.saturating_sub(start));
+            let position = layout.offset();
+            let height = layout.size().height;
+
+            return Some(Area::new(
+                Point::new(position.x + x, position.y - height),
+                Size::new(0.0, height),
+            ));
+        }
+
+        start = end;
+    }
+
+    None
+}
+
+/// Returns the distance from the start of the clusters to the place of a byte offset in their text. An offset in
+/// a cluster is at the start of the cluster.
+#[must_use]
+pub fn cluster_x(clusters: &[TextCluster], offset: usize) -> f32 {
+    clusters.iter().find(|cluster| offset < cluster.text.end).map_or_else(
+        || clusters.last().map_or(0.0, |cluster| cluster.x + cluster.advance),
+        |cluster| cluster.x,
+    )
+}
+
+/// Returns the nodes with text in document order
+pub(crate) fn text_nodes<C: HasLayouter>(tree: &C::LayoutTree) -> Vec<NodeId<C>> {
+    let mut nodes = Vec::new();
+    let mut stack = vec![tree.root()];
+
+    while let Some(id) = stack.pop() {
+        // The text of a replaced element, like the value of a form control, is not part of the text of the page
+        let replaced = tree.get_node(id).is_some_and(|node| node.intrinsic_size().is_some());
+        if !replaced && !text_layouts::<C>(tree, id).is_empty() {
+            nodes.push(id);
+        }
+
+        let mut children = tree.children(id).unwrap_or_default();
+        children.reverse();
+        stack.extend(children);
+    }
+
+    nodes
+}
+
+/// Returns the closest ancestor of the node that is not inline, which the text of the node is a paragraph of
+fn block_of<C: HasLayouter>(tree: &C::LayoutTree, id: NodeId<C>) -> Option<u64> {
+    let mut next = tree.parent_id(id);
+
+    while let Some(id) = next {
+        let display = tree
+            .get_node(id)
+            .and_then(|node| node.get_property("display"))
+            .and_then(|prop| prop.as_string().map(ToOwned::to_owned));
+
+        if !matches!(display.as_deref(), None | Some("inline" | "contents")) {
+            return Some(id.into());
+        }
+
+        next = tree.parent_id(id);
+    }
+
+    None
+}
+
+/// Returns the start and end of the unit of text around the caret
+fn unit_at<C: HasLayouter>(
+    tree: &C::LayoutTree,
+    caret: Caret<NodeId<C>>,
+    granularity: Granularity,
+) -> (Caret<NodeId<C>>, Caret<NodeId<C>>) {
+    match granularity {
+        Granularity::Character => (caret, caret),
+        Granularity::Word => {
+            let range = word_at(&node_text::<C>(tree, caret.node), caret.offset);
+
+            (
+                Caret {
+                    node: caret.node,
+                    offset: range.start,
+                },
+                Caret {
+                    node: caret.node,
+                    offset: range.end,
+                },
+            )
+        }
+        Granularity::Paragraph => {
+            let nodes = text_nodes::<C>(tree);
+            let Some(idx) = nodes.iter().position(|id| *id == caret.node) else {
+                return (caret, caret);
+            };
+
+            // The text nodes around the caret in the same block
+            let block = block_of::<C>(tree, caret.node);
+            let first = nodes[..idx]
+                .iter()
+                .rposition(|id| block_of::<C>(tree, *id) != block)
+                .map_or(0, |idx| idx + 1);
+            let last = nodes[idx..]
+                .iter()
+                .position(|id| block_of::<C>(tree, *id) != block)
+                .map_or(nodes.len(), |len| idx + len)
+                - 1;
+
+            (
+                Caret {
+                    node: nodes[first],
+                    offset: 0,
+                },
+                Caret {
+                    node: nodes[last],
+                    offset: node_text::<C>(tree, nodes[last]).len(),
+                },
+            )
+        }
+    }
+}
+
+/// Returns the byte offset of the caret closest to `x` in a line of clusters. `x` is relative to the start of the
+/// line.
+#[must_use]
+pub fn offset_at(clusters: &[TextCluster], x: f32) -> usize {
+    for cluster in clusters {
+        if x < cluster.x + cluster.advance / 2.0 {
+            return cluster.text.start;
+        }
+        if x < cluster.x + cluster.advance {
+            return cluster.text.end;
+        }
+    }
+
+    clusters.last().map_or(0, |cluster| cluster.text.end)
+}
+
+/// Returns the horizontal extent of the clusters that are in the byte range, relative to the start of the line
+#[must_use]
+pub fn cluster_span(clusters: &[TextCluster], range: Range<usize>) -> Option<(f32, f32)> {
+    clusters
+        .iter()
+        .filter(|cluster| cluster.text.start < range.end && cluster.text.end > range.start)
+        .fold(None, |span, cluster| {
+            let (start, end) = span.unwrap_or((f32::INFINITY, f32::NEG_INFINITY));
+            Some((start.min(cluster.x), end.max(cluster.x + cluster.advance)))
+        })
+}
+
+/// Returns the byte range of the word, run of white space or punctuation character at the offset
+#[must_use]
+pub fn word_at(text: &str, offset: usize) -> Range<usize> {
+    let offset = offset.min(text.len());
+    let Some((start, c)) = text[offset..]
+        .chars()
+        .next()
+        .map(|c| (offset, c))
+        .or_else(|| text[..offset].char_indices().next_back())
+    else {
+        return offset..offset;
+    };
+
+    let class = CharClass::of(c);
+    if class == CharClass::Punctuation {
+        return start..start + c.len_utf8();
+    }
+
+    let from = text[..start]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| CharClass::of(*c) == class)
+        .last()
+        .map_or(start, |(idx, _)| idx);
+    let to = text[start..]
+        .char_indices()
+        .find(|(_, c)| CharClass::of(*c) != class)
+        .map_or(text.len(), |(idx, _)| start + idx);
+
+    from..to
+}
+
+/// Kind of character that a word is made of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Word,
+    Space,
+    Punctuation,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        if c.is_alphanumeric() || c == '_' {
+            Self::Word
+        } else if c.is_whitespace() {
+            Self::Space
+        } else {
+            Self::Punctuation
+        }
+    }
+}
+
+/// Returns the distance from the value to the range, which is 0 when the value is in the range
+fn distance_to(value: f32, start: f32, end: f32) -> f32 {
+    if value < start {
+        start - value
+    } else if value > end {
+        value - end
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clusters(widths: &[(usize, f32)]) -> Vec<TextCluster> {
+        let mut x = 0.0;
+        let mut start = 0;
+
+        widths
+            .iter()
+            .map(|(len, advance)| {
+                let cluster = TextCluster {
+                    text: start..start + len,
+                    x,
+                    advance: *advance,
+                };
+                x += advance;
+                start += len;
+                cluster
+            })
+            .collect()
+    }
+
+    #[test]
+    fn granularity_of_clicks() {
+        assert_eq!(Granularity::from_clicks(1), Granularity::Character);
+        assert_eq!(Granularity::from_clicks(2), Granularity::Word);
+        assert_eq!(Granularity::from_clicks(3), Granularity::Paragraph);
+        assert_eq!(Granularity::from_clicks(4), Granularity::Character);
+    }
+
+    #[test]
+    fn offset_in_clusters() {
+        // "ab" followed by a ligature of two bytes
+        let clusters = clusters(&[(1, 10.0), (1, 10.0), (2, 20.0)]);
+
+        assert_eq!(offset_at(&clusters, -5.0), 0);
+        assert_eq!(offset_at(&clusters, 4.0), 0);
+        assert_eq!(offset_at(&clusters, 6.0), 1);
+        assert_eq!(offset_at(&clusters, 28.0), 2);
+        assert_eq!(offset_at(&clusters, 31.0), 4);
+        assert_eq!(offset_at(&clusters, 100.0), 4);
+        assert_eq!(offset_at(&[], 10.0), 0);
+    }
+
+    #[test]
+    fn place_of_offset() {
+        let clusters = clusters(&[(1, 10.0), (1, 10.0), (2, 20.0)]);
+
+        assert_eq!(cluster_x(&clusters, 0), 0.0);
+        assert_eq!(cluster_x(&clusters, 2), 20.0);
+        assert_eq!(cluster_x(&clusters, 3), 20.0);
+        assert_eq!(cluster_x(&clusters, 4), 40.0);
+        assert_eq!(cluster_x(&[], 2), 0.0);
+    }
+
+    #[test]
+    fn span_of_range() {
+        let clusters = clusters(&[(1, 10.0), (1, 10.0), (2, 20.0)]);
+
+        assert_eq!(cluster_span(&clusters, 1..2), Some((10.0, 20.0)));
+        // A part of a cluster selects the whole cluster
+        assert_eq!(cluster_span(&clusters, 1..3), Some((10.0, 40.0)));
+        assert_eq!(cluster_span(&clusters, 4..6), None);
+    }
+
+    #[test]
+    fn words() {
+        let text = "Hello, wörld  foo_bar!";
+
+        assert_eq!(word_at(text, 0), 0..5);
+        assert_eq!(word_at(text, 3), 0..5);
+        assert_eq!(word_at(text, 5), 5..6);
+        assert_eq!(word_at(text, 6), 6..7);
+        assert_eq!(word_at(text, 8), 7..13);
+        assert_eq!(word_at(text, 13), 13..15);
+        assert_eq!(word_at(text, 16), 15..22);
+        // At the end of the text, the last character is taken
+        assert_eq!(word_at(text, text.len()), 22..23);
+        assert_eq!(word_at("", 0), 0..0);
+    }
+}
@@ -0,0 +1,244 @@
+//! Shadows of an element: `box-shadow` and `text-shadow`. The shadows are resolved here, the renderer paints them
+//! through the blur primitives of the backend.
+
+use crate::background::{Area, Rgba};
+use crate::border_radius::BorderRadii;
+use gosub_interface::css3::{CssProperty, CssPropertyMap, CssSystem, CssValue};
+use gosub_shared::geo::{Point, Size, FP};
+
+const BLACK: Rgba = (0.0, 0.0, 0.0, 255.0);
+
+/// A single shadow of `box-shadow` or `text-shadow`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shadow {
+    pub offset_x: FP,
+    pub offset_y: FP,
+    pub blur: FP,
+    /// Always 0 for text shadows
+    pub spread: FP,
+    pub color: Rgba,
+    /// Always false for text shadows
+    pub inset: bool,
+}
+
+impl Shadow {
+    /// Standard deviation of the gaussian blur, which is half the blur radius
+    #[must_use]
+    pub fn std_dev(&self) -> FP {
+        (self.blur / 2.0).max(0.0)
+    }
+
+    /// Returns the shape of a box shadow before it is blurred, with the radii of its corners. `area` is the border
+    /// box for outer shadows and the padding box for inset shadows, `radii` are the radii of the corners of that box.
+    #[must_use]
+    pub fn shape(&self, area: Area, radii: BorderRadii) -> (Area, BorderRadii) {
+        let spread = if self.inset { -self.spread } else { self.spread };
+
+        let width = area.size.width + 2.0 * spread;
+        let height = area.size.height + 2.0 * spread;
+        let shape = Area::new(
+            Point::new(area.pos.x + self.offset_x - spread, area.pos.y + self.offset_y - spread),
+            Size::new(width.max(0.0), height.max(0.0)),
+        );
+
+        (shape, radii.map(|radius| spread_radius(radius, spread)))
+    }
+}
+
+/// Returns the radius of a corner of a box that is grown (or shrunk with a negative spread) by `spread`. Small
+/// radii of outer shadows grow less than the spread, so sharp corners stay sharp.
+fn spread_radius(radius: FP, spread: FP) -> FP {
+    if radius <= 0.0 {
+        return 0.0;
+    }
+
+    if spread > 0.0 && radius < spread {
+        let ratio = radius / spread - 1.0;
+        return radius + spread * (1.0 + ratio * ratio * ratio);
+    }
+
+    (radius + spread).max(0.0)
+}
+
+/// Returns the box shadows of an element in the order they are specified. The first shadow is painted on top.
+pub fn box_shadows<S: CssSystem>(props: &S::PropertyMap) -> Vec<Shadow> {
+    shadows::<S>(props, "box-shadow", true)
+}
+
+/// Returns the text shadows of an element in the order they are specified. The first shadow is painted on top.
+pub fn text_shadows<S: CssSystem>(props: &S::PropertyMap) -> Vec<Shadow> {
+    shadows::<S>(props, "text-shadow", false)
+}
+
+fn shadows<S: CssSystem>(props: &S::PropertyMap, name: &str, box_shadow: bool) -> Vec<Shadow> {
+    let Some(prop) = props.get(name) else {
+        return Vec::new();
+    };
+    let Some(list) = prop.as_list() else {
+        return Vec::new();
+    };
+
+    // `currentcolor` and shadows without a color use the color of the element
+    let current_color = props.get("color").and_then(CssProperty::parse_color).unwrap_or(BLACK);
+
+    list.split(CssValue::is_comma)
+        .filter_map(|values| {
+            let values = values
+                .iter()
+                .flat_map(|value| match value.as_list() {
+                    Some(values) => values.iter().collect(),
+                    None => vec![value],
+                })
+                .collect::<Vec<_>>();
+
+            shadow(&values, current_color, box_shadow)
+        })
+        .collect()
+}
+
+fn shadow<V: CssValue>(values: &[&V], current_color: Rgba, box_shadow: bool) -> Option<Shadow> {
+    let mut lengths = Vec::new();
+    let mut color = None;
+    let mut inset = false;
+
+    for value in values {
+        if let Some(length) = length(*value) {
+            lengths.push(length);
+        } else if box_shadow && value.as_string() == Some("inset") {
+            inset = true;
+        } else if value.as_string() == Some("currentcolor") {
+            color = Some(current_color);
+        } else {
+            color = Some(value.parse_color()?);
+        }
+    }
+
+    let max_lengths = if box_shadow { 4 } else { 3 };
+    if lengths.len() < 2 || lengths.len() > max_lengths {
+        return None;
+    }
+
+    Some(Shadow {
+        offset_x: lengths[0],
+        offset_y: lengths[1],
+        blur: lengths.get(2).copied().unwrap_or(0.0).max(0.0),
+        spread: lengths.get(3).copied().unwrap_or(0.0),
+        color: color.unwrap_or(current_color),
+        inset,
+    })
+}
+
+/// Resolves a length in pixels. A zero without unit is allowed as well.
+fn length<V: CssValue>(value: &V) -> Option<FP> {
+    if value.as_unit().is_some() {
+        return Some(value.unit_to_px());
+    }
+    if let Some(number) = value.as_number() {
+        return (number == 0.0).then_some(0.0);
+    }
+
+    let is_zero = value.as_string().is_none()
+        && value.as_percentage().is_none()
+        && value.as_function().is_none()
+        && value.as_color().is_none()
+        && value.as_list().is_none()
+        && !value.is_comma()
+        && value.unit_to_px() == 0.0;
+
+    is_zero.then_some(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::props;
+    use gosub_css3::system::Css3System;
+
+    #[test]
+    fn box_shadow_list() {
+        let map = props(&[
+            ("color", "red"),
+            (
+                "box-shadow",
+                "2px 3px 4px 1px blue, inset 0 0 10px rgba(0, 0, 255, 0.5), 5px -5px",
+            ),
+        ]);
+
+        let shadows = box_shadows::<Css3System>(&map);
+        assert_eq!(shadows.len(), 3);
+
+        assert_eq!(
+            shadows[0],
+            Shadow {
+                offset_x: 2.0,
+                offset_y: 3.0,
+                blur: 4.0,
+                spread: 1.0,
+                color: (0.0, 0.0, 255.0, 255.0),
+                inset: false,
+            }
+        );
+        assert_eq!(shadows[0].std_dev(), 2.0);
+
+        assert!(shadows[1].inset);
+        assert_eq!((shadows[1].offset_x, shadows[1].blur), (0.0, 10.0));
+        assert_eq!(shadows[1].color.2, 255.0);
+
+        // Without a color, the shadow has the color of the element
+        assert_eq!(shadows[2].color, (255.0, 0.0, 0.0, 255.0));
+        assert_eq!((shadows[2].offset_x, shadows[2].offset_y), (5.0, -5.0));
+
+        let map = props(&[("box-shadow", "none")]);
+        assert!(box_shadows::<Css3System>(&map).is_empty());
+    }
+
+    #[test]
+    fn text_shadow_list() {
+        let map = props(&[("text-shadow", "1px 1px 2px black, blue 0 0 3px")]);
+
+        let shadows = text_shadows::<Css3System>(&map);
+        assert_eq!(shadows.len(), 2);
+        assert_eq!(shadows[0].color, BLACK);
+        assert_eq!(shadows[0].blur, 2.0);
+        assert_eq!(shadows[1].color, (0.0, 0.0, 255.0, 255.0));
+        assert_eq!((shadows[1].offset_x, shadows[1].blur), (0.0, 3.0));
+
+        // Text shadows have no spread
+        let map = props(&[("text-shadow", "1px 1px 2px black, 1px 1px 1px 1px")]);
+        assert!(text_shadows::<Css3System>(&map).is_empty());
+    }
+
+    #[test]
+    fn box_shadow_shape() {
+        let area = Area::new(Point::new(10.0, 20.0), Size::new(100.0, 50.0));
+        let shadow = Shadow {
+            offset_x: 5.0,
+            offset_y: 5.0,
+            blur: 0.0,
+            spread: 10.0,
+            color: BLACK,
+            inset: false,
+        };
+
+        let radii = BorderRadii {
+            top_left: (0.0, 0.0),
+            top_right: (5.0, 5.0),
+            bottom_right: (10.0, 20.0),
+            bottom_left: (20.0, 20.0),
+        };
+
+        let (shape, spread) = shadow.shape(area, radii);
+        assert_eq!(shape, Area::new(Point::new(5.0, 15.0), Size::new(120.0, 70.0)));
+        // Small radii grow less than the spread, sharp corners stay sharp
+        assert_eq!(spread.top_left, (0.0, 0.0));
+        assert_eq!(spread.top_right, (13.75, 13.75));
+        assert_eq!(spread.bottom_right, (20.0, 30.0));
+        assert_eq!(spread.bottom_left, (30.0, 30.0));
+
+        let inset = Shadow { inset: true, ..shadow };
+        let (shape, spread) = inset.shape(area, radii);
+        assert_eq!(shape, Area::new(Point::new(25.0, 35.0), Size::new(80.0, 30.0)));
+        assert_eq!(spread.top_right, (0.0, 0.0));
+        assert_eq!(spread.bottom_right, (0.0, 10.0));
+    }
+}
@@ -0,0 +1,110 @@
+//! Configuration of the modules for the tests that need a render tree
+
+use crate::render_tree::RenderTree;
+use gosub_css3::stylesheet::CssValue;
+use gosub_css3::system::Css3System;
+use gosub_fontmanager::FontManager;
+use gosub_html5::document::builder::DocumentBuilderImpl;
+use gosub_html5::document::document_impl::DocumentImpl;
+use gosub_html5::document::fragment::DocumentFragmentImpl;
+use gosub_interface::config::{HasCssSystem, HasDocument, HasLayouter, HasRenderTree};
+use gosub_interface::css3::CssSystem;
+use gosub_interface::document::Document;
+use gosub_interface::font::HasFontManager;
+use gosub_interface::layout::Layouter;
+use gosub_shared::geo::{Point, Size, SizeU32};
+use gosub_taffy::TaffyLayouter;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config;
+
+impl HasCssSystem for Config {
+    type CssSystem = Css3System;
+}
+
+impl HasDocument for Config {
+    type Document = DocumentImpl<Self>;
+    type DocumentFragment = DocumentFragmentImpl<Self>;
+    type DocumentBuilder = DocumentBuilderImpl;
+}
+
+impl HasFontManager for Config {
+    type FontManager = FontManager;
+}
+
+impl HasLayouter for Config {
+    type Layouter = TaffyLayouter;
+    type LayoutTree = RenderTree<Self>;
+}
+
+impl HasRenderTree for Config {
+    type RenderTree = RenderTree<Self>;
+}
+
+/// Parses the page with the user agent stylesheet, and generates its render tree
+pub fn document(html: &str) -> (DocumentImpl<Config>, RenderTree<Config>) {
+    let mut doc = gosub_html5::html_compile::<Config>(html);
+    doc.add_stylesheet(Css3System::load_default_useragent_stylesheet());
+    let tree = RenderTree::from_document(&doc);
+
+    (doc, tree)
+}
+
+/// Lays out the tree in a viewport of 800x600
+pub fn layout(tree: &mut RenderTree<Config>) {
+    let root = tree.root;
+    <TaffyLayouter as Layouter<Config>>::layout(&TaffyLayouter, tree, root, SizeU32::new(800, 600))
+        .expect("layout failed");
+}
+
+/// Generates the render tree of the page, and lays it out
+pub fn laid_out(html: &str) -> RenderTree<Config> {
+    let (_, mut tree) = document(html);
+    layout(&mut tree);
+
+    tree
+}
+
+/// Returns the computed properties of a `<div>` that declares the given properties in its style attribute, so the
+/// values are parsed and shorthands are expanded like the ones of a real page
+pub fn props(values: &[(&str, &str)]) -> <Css3System as CssSystem>::PropertyMap {
+    let style = values
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}"))
+        .collect::<Vec<_>>()
+        .join("; ");
+    let (_, mut tree) = document(&format!("<div style=\"{style}\"></div>"));
+
+    let id = tree
+        .nodes
+        .values()
+        .find(|node| node.name == "div")
+        .map(|node| node.id)
+        .expect("div is not rendered");
+
+    tree.nodes.remove(&id).expect("div is not rendered").properties
+}
+
+/// Returns the attributes of an element
+pub fn attributes(attrs: &[(&str, &str)]) -> HashMap<String, String> {
+    attrs
+        .iter()
+        .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+        .collect()
+}
+
+pub fn keyword(value: &str) -> CssValue {
+    CssValue::String(value.to_string())
+}
+
+pub fn px(value: f32) -> CssValue {
+    CssValue::Unit(value, "px".into())
+}
+
+/// Position and size of the box that is painted in the tests
+pub const POS: Point = Point { x: 10.0, y: 20.0 };
+pub const SIZE: Size = Size {
+    width: 200.0,
+    height: 100.0,
+};
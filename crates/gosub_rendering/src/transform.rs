@@ -0,0 +1,422 @@
+//! CSS transforms: `transform`, `transform-origin` and the individual `translate`, `rotate` and `scale` properties.
+//! They are resolved into a single 2D affine matrix in page coordinates, which the renderer applies to the element and
+//! its descendants, and which hit testing inverts to map points back into the untransformed boxes.
+
+use cow_utils::CowUtils;
+use gosub_interface::css3::{CssProperty, CssPropertyMap, CssSystem, CssValue};
+use gosub_shared::geo::{Point, Size, FP};
+use std::f32::consts::TAU;
+
+/// 2D affine matrix `[a, b, c, d, e, f]`, which maps a point to `(a * x + c * y + e, b * x + d * y + f)`. This is the
+/// same layout as `Transform::as_matrix` of the render backends.
+pub type Matrix = [FP; 6];
+
+pub const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Value of a transform function argument or of an individual transform property
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Component<'a> {
+    Keyword(&'a str),
+    Length(FP),
+    Percentage(FP),
+    Number(FP),
+    /// Angle in radians
+    Angle(FP),
+    Other,
+}
+
+impl Component<'_> {
+    fn length(self, basis: FP) -> Option<FP> {
+        match self {
+            Component::Length(length) => Some(length),
+            Component::Percentage(percentage) => Some(basis * percentage / 100.0),
+            Component::Number(0.0) => Some(0.0),
+            _ => None,
+        }
+    }
+
+    fn number(self) -> Option<FP> {
+        match self {
+            Component::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    fn angle(self) -> Option<FP> {
+        match self {
+            Component::Angle(angle) => Some(angle),
+            Component::Number(0.0) => Some(0.0),
+            _ => None,
+        }
+    }
+
+    /// Scale factor, which is either a number or a percentage
+    fn factor(self) -> Option<FP> {
+        match self {
+            Component::Number(number) => Some(number),
+            Component::Percentage(percentage) => Some(percentage / 100.0),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the transform of an element with its border box at `pos`, in page coordinates. Returns None when the
+/// element is not transformed. An element with a transform that is not `none` is transformed, even when the matrix
+/// is the identity.
+pub fn transform<S: CssSystem>(props: &S::PropertyMap, pos: Point, size: Size) -> Option<Matrix> {
+    let mut matrix = IDENTITY;
+    let mut transformed = false;
+
+    // The individual properties are applied before the `transform` property, in this order
+    if let Some([x, rest @ ..]) = property::<S>(props, "translate").as_deref() {
+        let x = x.length(size.width)?;
+        let y = match rest.first() {
+            Some(y) => y.length(size.height)?,
+            None => 0.0,
+        };
+
+        matrix = multiply(matrix, translation(x, y));
+        transformed = true;
+    }
+
+    if let Some(components) = property::<S>(props, "rotate") {
+        let (angle, axis) = components.split_last()?;
+        let angle = angle.angle()?;
+
+        // Only rotations around the z-axis are visible in 2D
+        let direction = match axis {
+            [] | [Component::Keyword("z")] => 1.0,
+            [x, y, z] if x.number() == Some(0.0) && y.number() == Some(0.0) => z.number()?.signum(),
+            _ => 0.0,
+        };
+
+        if direction != 0.0 {
+            matrix = multiply(matrix, rotation(angle * direction));
+        }
+        transformed = true;
+    }
+
+    if let Some([x, rest @ ..]) = property::<S>(props, "scale").as_deref() {
+        let x = x.factor()?;
+        let y = match rest.first() {
+            Some(y) => y.factor()?,
+            None => x,
+        };
+
+        matrix = multiply(matrix, scaling(x, y));
+        transformed = true;
+    }
+
+    if let Some(prop) = props.get("transform") {
+        if prop.as_string() != Some("none") {
+            let mut functions = Vec::new();
+            if let Some((name, args)) = prop.as_function() {
+                functions.push((name, args));
+            } else if let Some(list) = prop.as_list() {
+                collect_functions(list, &mut functions);
+            }
+
+            for (name, args) in functions {
+                let args = args
+                    .iter()
+                    .filter(|arg| !arg.is_comma())
+                    .map(component)
+                    .collect::<Vec<_>>();
+
+                matrix = multiply(matrix, function(name, &args, size)?);
+            }
+            transformed = true;
+        }
+    }
+
+    if !transformed {
+        return None;
+    }
+
+    // The transform is applied around the origin, which is relative to the border box
+    let origin = transform_origin::<S>(props, size);
+    let (x, y) = (pos.x + origin.x, pos.y + origin.y);
+
+    Some(multiply(multiply(translation(x, y), matrix), translation(-x, -y)))
+}
+
+/// Returns the matrix that applies `n` first and then `m`
+#[must_use]
+pub fn multiply(m: Matrix, n: Matrix) -> Matrix {
+    [
+        m[0] * n[0] + m[2] * n[1],
+        m[1] * n[0] + m[3] * n[1],
+        m[0] * n[2] + m[2] * n[3],
+        m[1] * n[2] + m[3] * n[3],
+        m[0] * n[4] + m[2] * n[5] + m[4],
+        m[1] * n[4] + m[3] * n[5] + m[5],
+    ]
+}
+
+/// Returns the inverse of the matrix, or None when the matrix is not invertible (like `scale(0)`)
+#[must_use]
+pub fn invert(m: Matrix) -> Option<Matrix> {
+    let det = m[0] * m[3] - m[1] * m[2];
+    if det.abs() < FP::EPSILON {
+        return None;
+    }
+
+    Some([
+        m[3] / det,
+        -m[1] / det,
+        -m[2] / det,
+        m[0] / det,
+        (m[2] * m[5] - m[3] * m[4]) / det,
+        (m[1] * m[4] - m[0] * m[5]) / det,
+    ])
+}
+
+/// Maps a point through the matrix
+#[must_use]
+pub fn apply(m: Matrix, point: Point) -> Point {
+    Point::new(
+        m[0] * point.x + m[2] * point.y + m[4],
+        m[1] * point.x + m[3] * point.y + m[5],
+    )
+}
+
+fn translation(x: FP, y: FP) -> Matrix {
+    [1.0, 0.0, 0.0, 1.0, x, y]
+}
+
+fn scaling(x: FP, y: FP) -> Matrix {
+    [x, 0.0, 0.0, y, 0.0, 0.0]
+}
+
+/// Rotation clockwise on the screen, as the y-axis points down
+fn rotation(angle: FP) -> Matrix {
+    let (sin, cos) = angle.sin_cos();
+    [cos, sin, -sin, cos, 0.0, 0.0]
+}
+
+fn skewing(x: FP, y: FP) -> Matrix {
+    [1.0, y.tan(), x.tan(), 1.0, 0.0, 0.0]
+}
+
+/// Returns the matrix of a transform function. Functions that only have an effect in 3D are the identity, invalid
+/// arguments return None.
+fn function(name: &str, args: &[Component], size: Size) -> Option<Matrix> {
+    let arg = |idx: usize| args.get(idx).copied().unwrap_or(Component::Other);
+
+    let matrix = match name.cow_to_ascii_lowercase().as_ref() {
+        "matrix" if args.len() == 6 => {
+            let mut matrix = IDENTITY;
+            for (value, arg) in matrix.iter_mut().zip(args) {
+                *value = arg.number()?;
+            }
+            matrix
+        }
+        // Only the 2D part of the matrix is used
+        "matrix3d" if args.len() == 16 => {
+            let number = |idx: usize| arg(idx).number();
+            [number(0)?, number(1)?, number(4)?, number(5)?, number(12)?, number(13)?]
+        }
+        "translate" | "translate3d" => {
+            let y = if args.len() > 1 {
+                arg(1).length(size.height)?
+            } else {
+                0.0
+            };
+            translation(arg(0).length(size.width)?, y)
+        }
+        "translatex" => translation(arg(0).length(size.width)?, 0.0),
+        "translatey" => translation(0.0, arg(0).length(size.height)?),
+        "scale" | "scale3d" => {
+            let x = arg(0).factor()?;
+            let y = if args.len() > 1 { arg(1).factor()? } else { x };
+            scaling(x, y)
+        }
+        "scalex" => scaling(arg(0).factor()?, 1.0),
+        "scaley" => scaling(1.0, arg(0).factor()?),
+        "rotate" | "rotatez" => rotation(arg(0).angle()?),
+        "skew" => {
+            let y = if args.len() > 1 { arg(1).angle()? } else { 0.0 };
+            skewing(arg(0).angle()?, y)
+        }
+        "skewx" => skewing(arg(0).angle()?, 0.0),
+        "skewy" => skewing(0.0, arg(0).angle()?),
+        "translatez" | "scalez" | "rotatex" | "rotatey" | "rotate3d" | "perspective" => IDENTITY,
+        _ => return None,
+    };
+
+    Some(matrix)
+}
+
+/// Returns the origin of the transform relative to the border box. Defaults to the center of the box.
+fn transform_origin<S: CssSystem>(props: &S::PropertyMap, size: Size) -> Point {
+    let center = Point::new(size.width / 2.0, size.height / 2.0);
+    let Some(components) = property::<S>(props, "transform-origin") else {
+        return center;
+    };
+
+    let keyword = |component: Component, basis: FP| match component {
+        Component::Keyword("left" | "top") => Some(0.0),
+        Component::Keyword("center") => Some(basis / 2.0),
+        Component::Keyword("right" | "bottom") => Some(basis),
+        component => component.length(basis),
+    };
+    let is_vertical = |component: &Component| matches!(component, Component::Keyword("top" | "bottom"));
+    let is_horizontal = |component: &Component| matches!(component, Component::Keyword("left" | "right"));
+
+    let (x, y) = match components.as_slice() {
+        [] => return center,
+        [single] if is_vertical(single) => (None, Some(*single)),
+        [single] => (Some(*single), None),
+        // Keywords can be given in any order
+        [first, second, ..] if is_vertical(first) || is_horizontal(second) => (Some(*second), Some(*first)),
+        [first, second, ..] => (Some(*first), Some(*second)),
+    };
+
+    Point::new(
+        x.and_then(|x| keyword(x, size.width)).unwrap_or(center.x),
+        y.and_then(|y| keyword(y, size.height)).unwrap_or(center.y),
+    )
+}
+
+/// Returns the values of a property, or None when it is not set or `none`
+fn property<'a, S: CssSystem>(props: &'a S::PropertyMap, name: &str) -> Option<Vec<Component<'a>>> {
+    let prop = props.get(name)?;
+
+    if let Some(list) = prop.as_list() {
+        return Some(list.iter().map(component).collect());
+    }
+
+    let component = if let Some(keyword) = prop.as_string() {
+        if keyword == "none" {
+            return None;
+        }
+        Component::Keyword(keyword)
+    } else if let Some(percentage) = prop.as_percentage() {
+        Component::Percentage(percentage)
+    } else if let Some(number) = prop.as_number() {
+        Component::Number(number)
+    } else if let Some((value, unit)) = prop.as_unit() {
+        angle(value, unit).map_or_else(|| Component::Length(prop.unit_to_px()), Component::Angle)
+    } else if prop.as_function().is_some() || prop.as_color().is_some() || prop.is_none() {
+        Component::Other
+    } else {
+        // A zero without unit
+        Component::Number(0.0)
+    };
+
+    Some(vec![component])
+}
+
+fn component<V: CssValue>(value: &V) -> Component<'_> {
+    if let Some(keyword) = value.as_string() {
+        return Component::Keyword(keyword);
+    }
+    if let Some(percentage) = value.as_percentage() {
+        return Component::Percentage(percentage);
+    }
+    if let Some(number) = value.as_number() {
+        return Component::Number(number);
+    }
+    if let Some((number, unit)) = value.as_unit() {
+        return angle(number, unit).map_or_else(|| Component::Length(value.unit_to_px()), Component::Angle);
+    }
+    if value.as_function().is_some()
+        || value.as_color().is_some()
+        || value.as_list().is_some()
+        || value.is_comma()
+        || value.is_none()
+    {
+        return Component::Other;
+    }
+
+    // A zero without unit
+    Component::Number(0.0)
+}
+
+/// Resolves an angle in radians, or returns None when the unit is not an angle
+fn angle(value: FP, unit: &str) -> Option<FP> {
+    match unit {
+        "deg" => Some(value.to_radians()),
+        "rad" => Some(value),
+        "grad" => Some(value * TAU / 400.0),
+        "turn" => Some(value * TAU),
+        _ => None,
+    }
+}
+
+/// Collects the functions of a transform list. Functions can be wrapped in lists of their own.
+fn collect_functions<'a, V: CssValue>(list: &'a [V], functions: &mut Vec<(&'a str, &'a [V])>) {
+    for value in list {
+        if let Some(function) = value.as_function() {
+            functions.push(function);
+        } else if let Some(list) = value.as_list() {
+            collect_functions(list, functions);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::props;
+    use gosub_css3::system::Css3System;
+
+    fn rounded(point: Point) -> (FP, FP) {
+        ((point.x * 1000.0).round() / 1000.0, (point.y * 1000.0).round() / 1000.0)
+    }
+
+    const POS: Point = Point { x: 10.0, y: 20.0 };
+    const SIZE: Size = Size {
+        width: 100.0,
+        height: 50.0,
+    };
+
+    #[test]
+    fn transform_list() {
+        let map = props(&[("transform", "none")]);
+        assert_eq!(transform::<Css3System>(&map, POS, SIZE), None);
+
+        // Rotated by 90 degrees around the center at (60, 45)
+        let map = props(&[("transform", "translate(10px, 20%) rotate(90deg)")]);
+        let matrix = transform::<Css3System>(&map, POS, SIZE).unwrap();
+        assert_eq!(rounded(apply(matrix, Point::new(60.0, 45.0))), (70.0, 55.0));
+        assert_eq!(rounded(apply(matrix, Point::new(10.0, 20.0))), (95.0, 5.0));
+
+        let map = props(&[
+            ("transform", "matrix(2, 0, 0, 2, 5, 6)"),
+            ("transform-origin", "top left"),
+        ]);
+        let matrix = transform::<Css3System>(&map, POS, SIZE).unwrap();
+        assert_eq!(rounded(apply(matrix, POS)), (15.0, 26.0));
+        assert_eq!(rounded(apply(matrix, Point::new(20.0, 30.0))), (35.0, 46.0));
+    }
+
+    #[test]
+    fn individual_properties() {
+        // Translated first, then rotated and scaled around the center at (60, 45)
+        let map = props(&[
+            ("translate", "10px 5px"),
+            ("rotate", "0.5turn"),
+            ("scale", "200%"),
+            ("transform-origin", "50% center"),
+        ]);
+        let matrix = transform::<Css3System>(&map, POS, SIZE).unwrap();
+        assert_eq!(rounded(apply(matrix, Point::new(60.0, 45.0))), (70.0, 50.0));
+        assert_eq!(rounded(apply(matrix, Point::new(70.0, 45.0))), (50.0, 50.0));
+
+        // Rotations around the x-axis have no effect in 2D
+        let map = props(&[("rotate", "x 45deg")]);
+        assert_eq!(transform::<Css3System>(&map, POS, SIZE), Some(IDENTITY));
+    }
+
+    #[test]
+    fn inverse() {
+        let matrix = multiply(rotation(0.3), multiply(scaling(2.0, 3.0), translation(5.0, -7.0)));
+        let inverse = invert(matrix).unwrap();
+
+        let point = Point::new(12.0, 34.0);
+        assert_eq!(rounded(apply(inverse, apply(matrix, point))), (12.0, 34.0));
+
+        assert_eq!(invert(scaling(0.0, 1.0)), None);
+    }
+}
@@ -1,5 +1,10 @@
+use std::cell::Cell;
 use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
+use std::fmt::{Display, Formatter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::LazyLock;
 use std::sync::Mutex;
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::Instant;
@@ -14,6 +19,26 @@ fn new_timer_id() -> TimerId {
     uuid::Uuid::new_v4()
 }
 
+/// Moment all timer offsets are relative to. On wasm, the performance timer is already relative to the page load.
+#[cfg(not(target_arch = "wasm32"))]
+static EPOCH: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static THREAD_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Returns a small numeric id for the current thread, as the std `ThreadId` cannot be converted into a number
+fn current_thread_id() -> u64 {
+    THREAD_ID.with(|id| {
+        if id.get() == 0 {
+            id.set(NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed));
+        }
+        id.get()
+    })
+}
+
 #[derive(Debug, Clone)]
 pub enum Scale {
     MicroSecond,
@@ -39,6 +64,11 @@ impl Display for Duration {
 pub struct TimingTable {
     timers: HashMap<TimerId, Timer>,
     namespaces: HashMap<String, Vec<TimerId>>,
+    /// Synchronous timers that are running per thread, innermost last. A new synchronous timer is a child of the
+    /// innermost running one.
+    running: HashMap<u64, Vec<TimerId>>,
+    /// Names of the threads that started timers
+    threads: HashMap<u64, String>,
 }
 
 pub struct Stats {
@@ -53,6 +83,27 @@ pub struct Stats {
     p99: u64,
 }
 
+/// Returns the string as a JSON string literal
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            ch if ch < ' ' => {
+                let _ = write!(json, "\\u{:04x}", ch as u32);
+            }
+            ch => json.push(ch),
+        }
+    }
+    json.push('"');
+    json
+}
+
 fn percentage_to_index(count: u64, percentage: f64) -> usize {
     (count as f64 * percentage) as usize
 }
@@ -60,14 +111,43 @@ fn percentage_to_index(count: u64, percentage: f64) -> usize {
 impl TimingTable {
     #[must_use]
     pub fn new() -> TimingTable {
-        TimingTable {
-            timers: HashMap::new(),
-            namespaces: HashMap::new(),
-        }
+        TimingTable::default()
     }
 
+    /// Starts a timer for a synchronous scope, which is a child of the innermost synchronous timer running on this
+    /// thread. The timer must be stopped before anything is awaited: tasks that run interleaved on a thread would
+    /// otherwise nest their timers in each other. Timers that span an await are started with `start_async_timer`.
     pub fn start_timer(&mut self, namespace: &str, context: Option<String>) -> TimerId {
-        let timer = Timer::new(context);
+        let thread_id = current_thread_id();
+        let parent = self.running.get(&thread_id).and_then(|running| running.last().copied());
+
+        let id = self.add_timer(namespace, context, parent);
+        self.running.entry(thread_id).or_default().push(id);
+
+        id
+    }
+
+    /// Starts a timer that may span an await, with an explicit parent. No other timer nests in it, so concurrent
+    /// tasks on a thread, like fetches, do not become each other's children.
+    pub fn start_async_timer(&mut self, namespace: &str, context: Option<String>, parent: Option<TimerId>) -> TimerId {
+        self.add_timer(namespace, context, parent)
+    }
+
+    fn add_timer(&mut self, namespace: &str, context: Option<String>, parent: Option<TimerId>) -> TimerId {
+        let thread_id = current_thread_id();
+
+        let mut timer = Timer::new(context);
+        timer.namespace = namespace.to_string();
+        timer.parent = parent;
+        timer.thread_id = thread_id;
+
+        self.threads.entry(thread_id).or_insert_with(|| {
+            let thread = std::thread::current();
+            thread
+                .name()
+                .map_or_else(|| format!("thread-{thread_id}"), str::to_string)
+        });
+
         self.timers.insert(timer.id, timer.clone());
         self.namespaces.entry(namespace.to_string()).or_default().push(timer.id);
 
@@ -77,9 +157,76 @@ impl TimingTable {
     pub fn stop_timer(&mut self, timer_id: TimerId) {
         if let Some(timer) = self.timers.get_mut(&timer_id) {
             timer.end();
+
+            // Timers are not necessarily stopped in the reverse order they were started
+            if let Some(running) = self.running.get_mut(&timer.thread_id) {
+                running.retain(|id| *id != timer_id);
+            }
         }
     }
 
+    #[must_use]
+    pub fn timer(&self, timer_id: TimerId) -> Option<&Timer> {
+        self.timers.get(&timer_id)
+    }
+
+    /// Returns the timers that were started as children of the given timer
+    #[must_use]
+    pub fn children(&self, timer_id: TimerId) -> Vec<TimerId> {
+        let mut children: Vec<&Timer> = self
+            .timers
+            .values()
+            .filter(|timer| timer.parent == Some(timer_id))
+            .collect();
+        children.sort_by_key(|timer| timer.start_us);
+
+        children.iter().map(|timer| timer.id).collect()
+    }
+
+    /// Exports all finished timers in the Chrome Trace Event format, which can be loaded in Perfetto or
+    /// `chrome://tracing`. Timestamps are in microseconds since the first timer was created.
+    #[must_use]
+    pub fn to_chrome_trace(&self) -> String {
+        let mut events: Vec<String> = Vec::with_capacity(self.timers.len() + self.threads.len());
+
+        for (tid, name) in &self.threads {
+            events.push(format!(
+                r#"{{"name":"thread_name","ph":"M","pid":1,"tid":{tid},"args":{{"name":{}}}}}"#,
+                json_string(name)
+            ));
+        }
+
+        let mut timers: Vec<&Timer> = self.timers.values().filter(|timer| timer.has_finished()).collect();
+        // Parents start before their children, so sorting on start time keeps the events properly nested
+        timers.sort_by_key(|timer| (timer.start_us, std::cmp::Reverse(timer.duration_us)));
+
+        for timer in timers {
+            let mut args = format!(r#""id":"{}""#, timer.id);
+            if let Some(context) = &timer.context {
+                let _ = write!(args, r#","context":{}"#, json_string(context));
+            }
+            if let Some(parent) = timer.parent {
+                let _ = write!(args, r#","parent":"{parent}""#);
+            }
+
+            events.push(format!(
+                r#"{{"name":{},"cat":{},"ph":"X","ts":{},"dur":{},"pid":1,"tid":{},"args":{{{args}}}}}"#,
+                json_string(&timer.namespace),
+                json_string(timer.namespace.split('.').next().unwrap_or_default()),
+                timer.start_us,
+                timer.duration_us,
+                timer.thread_id,
+            ));
+        }
+
+        format!(r#"{{"traceEvents":[{}],"displayTimeUnit":"ms"}}"#, events.join(","))
+    }
+
+    /// Writes the Chrome trace of all finished timers to the given file
+    pub fn write_chrome_trace(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_chrome_trace())
+    }
+
     #[must_use]
     pub fn get_stats(&self, timers: &Vec<TimerId>) -> Stats {
         let mut durations: Vec<u64> = Vec::new();
@@ -201,6 +348,25 @@ macro_rules! timing_start {
     }};
 }
 
+/// Starts a timer that may span an await, optionally as a child of the given timer
+#[allow(clippy::crate_in_macro_def)]
+#[macro_export]
+macro_rules! timing_start_async {
+    ($namespace:expr, $context:expr, $parent:expr) => {{
+        $crate::timing::TIMING_TABLE
+            .lock()
+            .unwrap()
+            .start_async_timer($namespace, Some($context.to_string()), $parent)
+    }};
+
+    ($namespace:expr, $context:expr) => {{
+        $crate::timing::TIMING_TABLE
+            .lock()
+            .unwrap()
+            .start_async_timer($namespace, Some($context.to_string()), None)
+    }};
+}
+
 #[allow(clippy::crate_in_macro_def)]
 #[macro_export]
 macro_rules! timing_stop {
@@ -209,6 +375,14 @@ macro_rules! timing_stop {
     }};
 }
 
+#[allow(clippy::crate_in_macro_def)]
+#[macro_export]
+macro_rules! timing_export {
+    ($path:expr) => {{
+        $crate::timing::TIMING_TABLE.lock().unwrap().write_chrome_trace($path)
+    }};
+}
+
 #[allow(clippy::crate_in_macro_def)]
 #[macro_export]
 macro_rules! timing_display {
@@ -237,7 +411,13 @@ macro_rules! timing_display {
 #[derive(Debug, Clone)]
 pub struct Timer {
     id: TimerId,
+    namespace: String,
     context: Option<String>,
+    /// Timer this timer was started in
+    parent: Option<TimerId>,
+    thread_id: u64,
+    /// Start of the timer in microseconds since the epoch of the timing table
+    start_us: u64,
     #[cfg(not(target_arch = "wasm32"))]
     start: Instant,
     #[cfg(target_arch = "wasm32")]
@@ -263,18 +443,49 @@ impl Timer {
                 .unwrap_or(f64::NAN)
         };
 
+        #[cfg(not(target_arch = "wasm32"))]
+        let start_us = start.duration_since(*EPOCH).as_micros() as u64;
+
+        #[cfg(target_arch = "wasm32")]
+        let start_us = (start * 1000.0) as u64;
+
         Timer {
             id: new_timer_id(),
+            namespace: String::new(),
             context,
+            parent: None,
+            thread_id: current_thread_id(),
+            start_us,
             start,
             end: None,
             duration_us: 0,
         }
     }
 
+    #[must_use]
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    #[must_use]
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+
+    #[must_use]
+    pub fn parent(&self) -> Option<TimerId> {
+        self.parent
+    }
+
+    #[must_use]
+    pub fn thread_id(&self) -> u64 {
+        self.thread_id
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     pub fn start(&mut self) {
         self.start = Instant::now();
+        self.start_us = self.start.duration_since(*EPOCH).as_micros() as u64;
     }
 
     #[cfg(target_arch = "wasm32")]
@@ -283,6 +494,7 @@ impl Timer {
             .and_then(|w| w.performance())
             .map(|p| p.now())
             .unwrap_or(f64::NAN);
+        self.start_us = (self.start * 1000.0) as u64;
     }
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -361,6 +573,73 @@ mod tests {
         TIMING_TABLE.lock().unwrap().print_timings(true, Scale::Auto);
     }
 
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_timing_chrome_trace() {
+        let mut table = TimingTable::new();
+
+        let load = table.start_timer("page.load", Some("index.html".into()));
+        let parse = table.start_timer("html5.parse", None);
+        sleep(Duration::from_millis(2));
+        table.stop_timer(parse);
+        let layout = table.start_timer("layout.compute", None);
+        table.stop_timer(layout);
+        table.stop_timer(load);
+
+        let unfinished = table.start_timer("render.paint", None);
+
+        let other = std::thread::spawn(move || {
+            let fetch = table.start_timer("net.fetch", None);
+            table.stop_timer(fetch);
+            (table, fetch)
+        });
+        let (table, fetch) = other.join().unwrap();
+
+        assert_eq!(table.children(load), vec![parse, layout]);
+        assert_eq!(table.timer(unfinished).unwrap().parent(), None);
+        assert_eq!(table.timer(fetch).unwrap().parent(), None);
+        assert_ne!(
+            table.timer(fetch).unwrap().thread_id(),
+            table.timer(load).unwrap().thread_id()
+        );
+
+        let trace = table.to_chrome_trace();
+        assert!(trace.starts_with(r#"{"traceEvents":["#));
+        assert!(trace.ends_with(r#"],"displayTimeUnit":"ms"}"#));
+        assert_eq!(trace.matches(r#""ph":"X""#).count(), 4);
+        assert_eq!(trace.matches(r#""ph":"M""#).count(), 2);
+
+        let load_event = trace.find(r#"{"name":"page.load","cat":"page","ph":"X""#).unwrap();
+        let parse_event = trace.find(r#"{"name":"html5.parse","cat":"html5","ph":"X""#).unwrap();
+        assert!(load_event < parse_event);
+        assert!(trace.contains(&format!(r#""args":{{"id":"{load}","context":"index.html"}}"#)));
+        assert!(trace.contains(&format!(r#""args":{{"id":"{parse}","parent":"{load}"}}"#)));
+
+        assert_eq!(json_string("a\"b\\c\nd\u{1}"), r#""a\"b\\c\nd\u0001""#);
+    }
+
+    #[test]
+    fn test_async_timers_do_not_nest() {
+        let mut table = TimingTable::new();
+
+        let load = table.start_timer("page.load", None);
+        let first = table.start_async_timer("net.fetch", Some("a.css".into()), Some(load));
+        let second = table.start_async_timer("net.fetch", Some("b.css".into()), Some(load));
+        let parse = table.start_timer("css.parse", None);
+        table.stop_timer(parse);
+        table.stop_timer(second);
+        table.stop_timer(load);
+
+        let unfinished = table.start_async_timer("net.fetch", Some("c.png".into()), None);
+        let paint = table.start_timer("render.paint", None);
+        table.stop_timer(paint);
+
+        assert_eq!(table.children(load), vec![first, second, parse]);
+        assert!(table.children(first).is_empty());
+        assert!(table.children(unfinished).is_empty());
+        assert_eq!(table.timer(paint).unwrap().parent(), None);
+    }
+
     #[wasm_bindgen_test]
     #[cfg(target_arch = "wasm32")]
     fn test_timing_defaults_wasm() {
@@ -1 +1,5 @@
+pub mod float;
 pub mod inline;
+pub mod positioned;
+pub mod replaced;
+pub mod white_space;
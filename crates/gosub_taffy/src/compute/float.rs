@@ -0,0 +1,750 @@
+use taffy::{
+    compute_block_layout, compute_hidden_layout, AvailableSpace, BoxSizing, CollapsibleMarginSet, Dimension,
+    Display as TaffyDisplay, Layout, LayoutInput, LayoutOutput, LayoutPartialTree, Line, MaybeMath, MaybeResolve,
+    NodeId, Overflow, Point, Position, Rect, RequestedAxis, ResolveOrZero, RunMode, Size, SizingMode, Style,
+};
+
+use gosub_interface::config::HasLayouter;
+use gosub_interface::layout::{LayoutNode, LayoutTree};
+
+use crate::compute::inline::compute_inline_layout;
+use crate::compute::positioned::{layout_in_block, Block};
+use crate::style::parse_properties::{parse_clear, parse_float, CssClear, CssFloat};
+use crate::{Display, LayoutDocument, TaffyLayouter};
+
+/// Margin box of a float, relative to the border box of the element that establishes its block formatting context
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FloatBox {
+    side: CssFloat,
+    pos: Point<f32>,
+    size: Size<f32>,
+}
+
+impl FloatBox {
+    fn bottom(&self) -> f32 {
+        self.pos.y + self.size.height
+    }
+
+    /// Returns true when the float is next to the band from `y` with the given height. A band without a height is
+    /// next to the floats that contain `y`.
+    fn beside(&self, y: f32, height: f32) -> bool {
+        self.size.height > 0.0 && self.bottom() > y && (self.pos.y < y + height || self.pos.y <= y)
+    }
+}
+
+/// Floats of a block formatting context. Line boxes, and blocks that establish a new block formatting context, are
+/// shortened to the space between the floats next to them.
+#[derive(Debug, Default)]
+pub struct Floats {
+    boxes: Vec<FloatBox>,
+}
+
+impl Floats {
+    pub fn is_empty(&self) -> bool {
+        self.boxes.is_empty()
+    }
+
+    /// Returns the start and end of the space between the floats next to the band from `y` with the given height,
+    /// within the edges `left` and `right`
+    pub fn available(&self, y: f32, height: f32, left: f32, right: f32) -> (f32, f32) {
+        self.boxes
+            .iter()
+            .filter(|float| float.beside(y, height))
+            .fold((left, right), |(start, end), float| match float.side {
+                CssFloat::Right => (start, end.min(float.pos.x)),
+                _ => (start.max(float.pos.x + float.size.width), end),
+            })
+    }
+
+    /// Returns the first bottom of a float below `y`, which is where the space between the floats changes
+    fn next_bottom(&self, y: f32) -> Option<f32> {
+        self.boxes
+            .iter()
+            .map(FloatBox::bottom)
+            .filter(|bottom| *bottom > y)
+            .min_by(f32::total_cmp)
+    }
+
+    /// Finds the first band at or below `y` where a box of the given size fits between the floats, and returns its
+    /// top with the start and end of the space. A box that is too wide to fit anywhere ends up below all floats.
+    pub fn fit(&self, size: Size<f32>, mut y: f32, left: f32, right: f32) -> (f32, f32, f32) {
+        loop {
+            let (start, end) = self.available(y, size.height, left, right);
+            if end - start >= size.width {
+                return (y, start, end);
+            }
+
+            match self.next_bottom(y) {
+                Some(bottom) => y = bottom,
+                None => return (y, start, end),
+            }
+        }
+    }
+
+    /// Places a float with the given margin box size. It is placed as high as possible, but not above `y` and not
+    /// above earlier floats. Returns the position of the margin box.
+    pub fn place(&mut self, side: CssFloat, size: Size<f32>, y: f32, left: f32, right: f32) -> Point<f32> {
+        let y = self.boxes.iter().fold(y, |y, float| y.max(float.pos.y));
+        let (y, start, end) = self.fit(size, y, left, right);
+
+        let x = if side == CssFloat::Right {
+            end - size.width
+        } else {
+            start
+        };
+        let pos = Point { x, y };
+
+        self.boxes.push(FloatBox { side, pos, size });
+
+        pos
+    }
+
+    /// Returns the position below the floats that `clear` moves an element past
+    pub fn clearance(&self, clear: CssClear) -> Option<f32> {
+        self.boxes
+            .iter()
+            .filter(|float| match clear {
+                CssClear::None => false,
+                CssClear::Left => float.side == CssFloat::Left,
+                CssClear::Right => float.side == CssFloat::Right,
+                CssClear::Both => true,
+            })
+            .map(FloatBox::bottom)
+            .max_by(f32::total_cmp)
+    }
+
+    /// Returns the bottom of the lowest float
+    pub fn bottom(&self) -> Option<f32> {
+        self.clearance(CssClear::Both)
+    }
+}
+
+/// Content box of a block container inside a block formatting context, with the floats that its line boxes flow
+/// around
+#[derive(Debug, Clone, Copy)]
+pub struct FloatArea<'a> {
+    floats: &'a Floats,
+    /// Position of the content box in the block formatting context
+    origin: Point<f32>,
+    width: f32,
+}
+
+impl FloatArea<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.floats.is_empty()
+    }
+
+    /// Returns the start and end of a line box from `y` with the given height, relative to the content box
+    pub fn line(&self, y: f32, height: f32) -> (f32, f32) {
+        let (start, end) = self
+            .floats
+            .available(self.origin.y + y, height, self.origin.x, self.origin.x + self.width);
+
+        (start - self.origin.x, end - self.origin.x)
+    }
+
+    /// Returns the next position below `y` where the space between the floats changes. A line that does not fit
+    /// next to the floats is moved down to it.
+    pub fn next_line(&self, y: f32) -> Option<f32> {
+        self.floats
+            .next_bottom(self.origin.y + y)
+            .map(|bottom| bottom - self.origin.y)
+    }
+}
+
+/// Returns true when a block container has floats in its block formatting context. It is stored in the cache of the
+/// node before the layout, by `update_has_floats`.
+pub fn has_floats<C: HasLayouter<Layouter = TaffyLayouter>>(
+    tree: &LayoutDocument<C>,
+    node_id: <C::LayoutTree as LayoutTree<C>>::NodeId,
+) -> bool {
+    tree.0.get_cache(node_id).is_some_and(|cache| cache.has_floats)
+}
+
+/// Stores whether a block container has floats in its block formatting context: floated children, or floats inside
+/// children that do not establish a block formatting context of their own. The children must be updated first, so
+/// every node is only visited once.
+pub fn update_has_floats<C: HasLayouter<Layouter = TaffyLayouter>>(
+    tree: &mut LayoutDocument<C>,
+    node_id: <C::LayoutTree as LayoutTree<C>>::NodeId,
+) {
+    let children = tree.0.children(node_id).unwrap_or_default();
+
+    let floats = children.into_iter().any(|child| {
+        let Some(node) = tree.0.get_node_mut(child) else {
+            return false;
+        };
+
+        if parse_float(node) != CssFloat::None {
+            return true;
+        }
+
+        // Inline content does not contain floats, they are made block-level by the render tree
+        if node.is_anon_inline_parent() {
+            return false;
+        }
+
+        !establishes_bfc(tree, child) && has_floats(tree, child)
+    });
+
+    if let Some(cache) = tree.0.get_cache_mut(node_id) {
+        cache.has_floats = floats;
+    }
+}
+
+/// Returns true when the element establishes a new block formatting context. Its floats stay inside of it, and it
+/// does not overlap the floats around it.
+fn establishes_bfc<C: HasLayouter<Layouter = TaffyLayouter>>(
+    tree: &mut LayoutDocument<C>,
+    node_id: <C::LayoutTree as LayoutTree<C>>::NodeId,
+) -> bool {
+    let Some(node) = tree.0.get_node_mut(node_id) else {
+        return true;
+    };
+
+    if parse_float(node) != CssFloat::None || node.intrinsic_size().is_some() {
+        return true;
+    }
+
+    let style = tree.get_taffy_style(node_id);
+    if style.position == Position::Absolute
+        || style.display != TaffyDisplay::Block
+        || style.overflow.x != Overflow::Visible
+        || style.overflow.y != Overflow::Visible
+    {
+        return true;
+    }
+
+    tree.0
+        .get_cache(node_id)
+        .is_some_and(|cache| matches!(cache.display, Display::FlowRoot | Display::InlineBlock | Display::Table))
+}
+
+/// Box model of a block, resolved against the width of its containing block
+struct BoxModel {
+    margin: Rect<Option<f32>>,
+    padding: Rect<f32>,
+    border: Rect<f32>,
+    /// Border box size from the style, when it is not `auto`
+    size: Size<Option<f32>>,
+    min_size: Size<Option<f32>>,
+    max_size: Size<Option<f32>>,
+}
+
+impl BoxModel {
+    fn new(style: &Style, parent_size: Size<Option<f32>>) -> Self {
+        let padding = style.padding.resolve_or_zero(parent_size.width);
+        let border = style.border.resolve_or_zero(parent_size.width);
+
+        let box_sizing = if style.box_sizing == BoxSizing::ContentBox {
+            Size {
+                width: padding.left + padding.right + border.left + border.right,
+                height: padding.top + padding.bottom + border.top + border.bottom,
+            }
+        } else {
+            Size::ZERO
+        };
+
+        Self {
+            margin: style.margin.map(|margin| margin.maybe_resolve(parent_size.width)),
+            padding,
+            border,
+            size: style
+                .size
+                .maybe_resolve(parent_size)
+                .maybe_apply_aspect_ratio(style.aspect_ratio)
+                .maybe_add(box_sizing),
+            min_size: style.min_size.maybe_resolve(parent_size).maybe_add(box_sizing),
+            max_size: style.max_size.maybe_resolve(parent_size).maybe_add(box_sizing),
+        }
+    }
+
+    /// Size of the padding and border together
+    fn frame(&self) -> Size<f32> {
+        Size {
+            width: self.padding.left + self.padding.right + self.border.left + self.border.right,
+            height: self.padding.top + self.padding.bottom + self.border.top + self.border.bottom,
+        }
+    }
+
+    /// Sum of the horizontal margins, where `auto` margins are zero
+    fn horizontal_margins(&self) -> f32 {
+        self.margin.left.unwrap_or(0.0) + self.margin.right.unwrap_or(0.0)
+    }
+
+    /// Returns the left margin of a box of the given width in a line of the given width. `auto` margins take up the
+    /// free space, which centers a box with two of them.
+    fn margin_start(&self, width: f32, available: f32) -> f32 {
+        let free = available - width;
+
+        match (self.margin.left, self.margin.right) {
+            (None, None) => (free / 2.0).max(0.0),
+            (None, Some(right)) => free - right,
+            (Some(left), _) => left,
+        }
+    }
+
+    fn clamp_width(&self, width: f32) -> f32 {
+        width
+            .maybe_clamp(self.min_size.width, self.max_size.width)
+            .max(self.frame().width)
+    }
+
+    fn clamp_height(&self, height: f32) -> f32 {
+        height
+            .maybe_clamp(self.min_size.height, self.max_size.height)
+            .max(self.frame().height)
+    }
+}
+
+/// Lays out a block container that establishes a block formatting context with floats in it. Taffy's block layout
+/// knows nothing about floats, so the block formatting context is laid out here: in-flow blocks are stacked below
+/// each other, floats are placed at the left or right of the current line and the line boxes of the inline content
+/// next to them are shortened. Floats do not take part in the intrinsic sizes of the container.
+pub fn compute_float_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
+    tree: &mut LayoutDocument<C>,
+    node_id: <C::LayoutTree as LayoutTree<C>>::NodeId,
+    inputs: LayoutInput,
+) -> LayoutOutput {
+    let taffy_id = NodeId::from(node_id.into());
+    if inputs.run_mode == RunMode::PerformHiddenLayout {
+        return compute_hidden_layout(tree, taffy_id);
+    }
+
+    let style = tree.get_taffy_style(node_id).clone();
+    let mut model = BoxModel::new(&style, inputs.parent_size);
+    model.size = inputs.known_dimensions.or(model.size);
+
+    // Sizes that are known do not need the children to be laid out
+    if inputs.run_mode == RunMode::ComputeSize {
+        if let Size {
+            width: Some(width),
+            height: Some(height),
+        } = model.size
+        {
+            return LayoutOutput::from_outer_size(Size {
+                width: model.clamp_width(width),
+                height: model.clamp_height(height),
+            });
+        }
+    }
+
+    // A block without a definite width shrinks to fit its content, which taffy measures for us
+    let width = match model.size.width {
+        Some(width) => width,
+        None => {
+            compute_block_layout(
+                tree,
+                taffy_id,
+                LayoutInput {
+                    run_mode: RunMode::ComputeSize,
+                    ..inputs
+                },
+            )
+            .size
+            .width
+        }
+    };
+    let width = model.clamp_width(width);
+
+    if inputs.run_mode == RunMode::ComputeSize && inputs.axis == RequestedAxis::Horizontal {
+        return LayoutOutput::from_outer_size(Size { width, height: 0.0 });
+    }
+
+    let mut floats = Floats::default();
+    let content_height = layout_container(tree, node_id, &model, width, Point::ZERO, &mut floats, true);
+
+    let size = Size {
+        width,
+        height: content_height,
+    };
+
+    LayoutOutput {
+        size,
+        content_size: size,
+        first_baselines: Point::NONE,
+        top_margin: CollapsibleMarginSet::ZERO,
+        bottom_margin: CollapsibleMarginSet::ZERO,
+        margins_can_collapse_through: false,
+    }
+}
+
+/// Lays out the children of a block container with the given border box width, and returns the height of its
+/// border box. `offset` is the position of the border box in the block formatting context, which grows to contain
+/// its floats when the container establishes it.
+fn layout_container<C: HasLayouter<Layouter = TaffyLayouter>>(
+    tree: &mut LayoutDocument<C>,
+    node_id: <C::LayoutTree as LayoutTree<C>>::NodeId,
+    model: &BoxModel,
+    width: f32,
+    offset: Point<f32>,
+    floats: &mut Floats,
+    contain_floats: bool,
+) -> f32 {
+    let frame = model.frame();
+    let content = Point {
+        x: model.padding.left + model.border.left,
+        y: model.padding.top + model.border.top,
+    };
+    let content_width = (width - frame.width).max(0.0);
+
+    let (content_height, absolute) = layout_children(tree, node_id, offset, content, content_width, floats);
+
+    // The container of a block formatting context grows to contain its floats
+    let content_height = if contain_floats {
+        floats
+            .bottom()
+            .map_or(content_height, |bottom| content_height.max(bottom - content.y))
+    } else {
+        content_height
+    };
+
+    let height = model.clamp_height(model.size.height.unwrap_or(content_height + frame.height));
+
+    // Absolute children are placed in the padding box when its height is known
+    let block = Block {
+        pos: Point {
+            x: model.border.left,
+            y: model.border.top,
+        },
+        size: Size {
+            width: (width - model.border.left - model.border.right).max(0.0),
+            height: (height - model.border.top - model.border.bottom).max(0.0),
+        },
+    };
+
+    for (order, child, location) in absolute {
+        tree.set_unrounded_layout(
+            NodeId::from(child.into()),
+            &Layout {
+                location,
+                ..Layout::with_order(order)
+            },
+        );
+        layout_in_block(tree, child, block, Point::ZERO);
+    }
+
+    height
+}
+
+/// Absolutely positioned child with its paint order and static position
+type AbsoluteChild<C> = (
+    u32,
+    <<C as HasLayouter>::LayoutTree as LayoutTree<C>>::NodeId,
+    Point<f32>,
+);
+
+/// Lays out the children of a block container in the normal flow, and returns the height of its content box with
+/// the absolutely positioned children. `content` is the position of the content box in the border box of the
+/// container.
+fn layout_children<C: HasLayouter<Layouter = TaffyLayouter>>(
+    tree: &mut LayoutDocument<C>,
+    node_id: <C::LayoutTree as LayoutTree<C>>::NodeId,
+    offset: Point<f32>,
+    content: Point<f32>,
+    width: f32,
+    floats: &mut Floats,
+) -> (f32, Vec<AbsoluteChild<C>>) {
+    let Some(children) = tree.0.children(node_id) else {
+        return (0.0, Vec::new());
+    };
+
+    let parent_size = Size {
+        width: Some(width),
+        height: None,
+    };
+
+    // Edges of the content box in the block formatting context
+    let left = offset.x + content.x;
+    let right = left + width;
+    let top = offset.y + content.y;
+
+    let mut absolute = Vec::new();
+
+    // Bottom of the last in-flow child, and its bottom margin that is collapsed with the top margin of the next
+    let mut y = 0.0;
+    let mut margin = 0.0;
+
+    for (order, child) in children.into_iter().enumerate() {
+        let order = order as u32;
+        let child_id = NodeId::from(child.into());
+
+        let Some(node) = tree.0.get_node_mut(child) else {
+            continue;
+        };
+        let float = parse_float(node);
+        let clear = parse_clear(node);
+        let anonymous = node.is_anon_inline_parent();
+        let replaced = node.intrinsic_size().is_some();
+
+        let style = tree.get_taffy_style(child).clone();
+        let model = BoxModel::new(&style, parent_size);
+
+        if style.display == TaffyDisplay::None {
+            compute_hidden_layout(tree, child_id);
+            continue;
+        }
+
+        if style.position == Position::Absolute {
+            let location = Point {
+                x: content.x,
+                y: content.y + y + margin,
+            };
+            absolute.push((order, child, location));
+            continue;
+        }
+
+        let scrollbar_size = Size {
+            width: if style.overflow.y == Overflow::Scroll {
+                style.scrollbar_width
+            } else {
+                0.0
+            },
+            height: if style.overflow.x == Overflow::Scroll {
+                style.scrollbar_width
+            } else {
+                0.0
+            },
+        };
+        let set_layout = |tree: &mut LayoutDocument<C>, location: Point<f32>, size: Size<f32>| {
+            tree.set_unrounded_layout(
+                child_id,
+                &Layout {
+                    order,
+                    location,
+                    size,
+                    content_size: size,
+                    scrollbar_size,
+                    border: model.border,
+                    padding: model.padding,
+                    margin: model.margin.map(|margin| margin.unwrap_or(0.0)),
+                },
+            );
+        };
+
+        let margin_top = model.margin.top.unwrap_or(0.0);
+        let margin_bottom = model.margin.bottom.unwrap_or(0.0);
+        let margins = Size {
+            width: model.horizontal_margins(),
+            height: margin_top + margin_bottom,
+        };
+        let clearance = floats.clearance(clear).map(|clearance| clearance - top);
+
+        if float != CssFloat::None {
+            let output = tree.compute_child_layout(
+                child_id,
+                child_input(None, (width - margins.width).max(0.0), parent_size),
+            );
+
+            let size = Size {
+                width: output.size.width + margins.width,
+                height: output.size.height + margins.height,
+            };
+            let position = clearance.map_or(y + margin, |clearance| (y + margin).max(clearance));
+            let pos = floats.place(float, size, top + position, left, right);
+
+            let location = Point {
+                x: pos.x - offset.x + model.margin.left.unwrap_or(0.0),
+                y: pos.y - offset.y + margin_top,
+            };
+            set_layout(tree, location, output.size);
+            continue;
+        }
+
+        // Margins of adjacent blocks collapse, and `clear` moves the block below the floats
+        let mut position = y + collapse_margins(margin, margin_top);
+        if let Some(clearance) = clearance {
+            position = position.max(clearance);
+        }
+
+        if anonymous {
+            let area = FloatArea {
+                floats,
+                origin: Point {
+                    x: left,
+                    y: top + position,
+                },
+                width,
+            };
+            let output = compute_inline_layout(tree, child, child_input(Some(width), width, parent_size), Some(area));
+
+            let size = Size {
+                width,
+                height: output.size.height,
+            };
+            set_layout(
+                tree,
+                Point {
+                    x: content.x,
+                    y: content.y + position,
+                },
+                size,
+            );
+
+            y = position + size.height;
+            margin = 0.0;
+            continue;
+        }
+
+        let size = if replaced || establishes_bfc(tree, child) {
+            // The block is placed next to the floats, at the first position where it fits
+            let stretch = style.size.width == Dimension::Auto && !replaced;
+            let mut band_top = top + position;
+
+            loop {
+                let (start, end) = floats.available(band_top, 0.0, left, right);
+                let available = (end - start - margins.width).max(0.0);
+
+                let output = tree.compute_child_layout(
+                    child_id,
+                    child_input(stretch.then_some(available), available, parent_size),
+                );
+                let size = Size {
+                    width: output.size.width + margins.width,
+                    height: output.size.height,
+                };
+
+                let (fit_top, start, end) = floats.fit(size, band_top, left, right);
+                if fit_top <= band_top {
+                    let location = Point {
+                        x: start - offset.x + model.margin_start(output.size.width, end - start),
+                        y: band_top - offset.y,
+                    };
+                    set_layout(tree, location, output.size);
+                    break output.size;
+                }
+
+                band_top = fit_top;
+            }
+        } else {
+            let block_width = model.clamp_width(model.size.width.unwrap_or(width - margins.width));
+            let location = Point {
+                x: content.x + model.margin_start(block_width, width),
+                y: content.y + position,
+            };
+            let block_offset = Point {
+                x: offset.x + location.x,
+                y: offset.y + location.y,
+            };
+
+            let height = layout_container(tree, child, &model, block_width, block_offset, floats, false);
+
+            let size = Size {
+                width: block_width,
+                height,
+            };
+            set_layout(tree, location, size);
+            size
+        };
+
+        y = position + size.height;
+        margin = margin_bottom;
+    }
+
+    (y + margin, absolute)
+}
+
+/// Input for laying out a child of a block container with the given content width
+fn child_input(width: Option<f32>, available: f32, parent_size: Size<Option<f32>>) -> LayoutInput {
+    LayoutInput {
+        known_dimensions: Size { width, height: None },
+        parent_size,
+        available_space: Size {
+            width: AvailableSpace::Definite(available),
+            height: AvailableSpace::MaxContent,
+        },
+        sizing_mode: SizingMode::InherentSize,
+        axis: RequestedAxis::Both,
+        run_mode: RunMode::PerformLayout,
+        vertical_margins_are_collapsible: Line::FALSE,
+    }
+}
+
+/// Collapses two adjoining margins into the largest positive margin plus the most negative one
+fn collapse_margins(a: f32, b: f32) -> f32 {
+    a.max(b).max(0.0) + a.min(b).min(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn size(width: f32, height: f32) -> Size<f32> {
+        Size { width, height }
+    }
+
+    #[test]
+    fn floats_are_placed_next_to_each_other() {
+        let mut floats = Floats::default();
+
+        let first = floats.place(CssFloat::Left, size(100.0, 50.0), 0.0, 0.0, 500.0);
+        let second = floats.place(CssFloat::Left, size(100.0, 30.0), 0.0, 0.0, 500.0);
+        let right = floats.place(CssFloat::Right, size(200.0, 20.0), 0.0, 0.0, 500.0);
+
+        assert_eq!(first, Point { x: 0.0, y: 0.0 });
+        assert_eq!(second, Point { x: 100.0, y: 0.0 });
+        assert_eq!(right, Point { x: 300.0, y: 0.0 });
+
+        // There is no room left for this one until the right float ends
+        let below = floats.place(CssFloat::Left, size(150.0, 10.0), 0.0, 0.0, 500.0);
+        assert_eq!(below, Point { x: 200.0, y: 20.0 });
+
+        assert_eq!(floats.available(5.0, 10.0, 0.0, 500.0), (200.0, 300.0));
+        assert_eq!(floats.available(40.0, 10.0, 0.0, 500.0), (100.0, 500.0));
+        assert_eq!(floats.available(60.0, 10.0, 0.0, 500.0), (0.0, 500.0));
+    }
+
+    #[test]
+    fn floats_are_not_placed_above_earlier_floats() {
+        let mut floats = Floats::default();
+
+        floats.place(CssFloat::Left, size(400.0, 50.0), 0.0, 0.0, 500.0);
+        let wide = floats.place(CssFloat::Right, size(200.0, 10.0), 0.0, 0.0, 500.0);
+        let narrow = floats.place(CssFloat::Right, size(50.0, 10.0), 0.0, 0.0, 500.0);
+
+        assert_eq!(wide, Point { x: 300.0, y: 50.0 });
+        assert_eq!(narrow, Point { x: 250.0, y: 50.0 });
+    }
+
+    #[test]
+    fn boxes_fit_between_floats() {
+        let mut floats = Floats::default();
+        floats.place(CssFloat::Left, size(100.0, 50.0), 0.0, 0.0, 500.0);
+        floats.place(CssFloat::Right, size(100.0, 80.0), 0.0, 0.0, 500.0);
+
+        assert_eq!(floats.fit(size(300.0, 100.0), 0.0, 0.0, 500.0), (0.0, 100.0, 400.0));
+        assert_eq!(floats.fit(size(350.0, 100.0), 0.0, 0.0, 500.0), (50.0, 0.0, 400.0));
+        assert_eq!(floats.fit(size(600.0, 10.0), 0.0, 0.0, 500.0), (80.0, 0.0, 500.0));
+    }
+
+    #[test]
+    fn clearance() {
+        let mut floats = Floats::default();
+        assert_eq!(floats.clearance(CssClear::Both), None);
+
+        floats.place(CssFloat::Left, size(100.0, 50.0), 0.0, 0.0, 500.0);
+        floats.place(CssFloat::Right, size(100.0, 80.0), 10.0, 0.0, 500.0);
+
+        assert_eq!(floats.clearance(CssClear::None), None);
+        assert_eq!(floats.clearance(CssClear::Left), Some(50.0));
+        assert_eq!(floats.clearance(CssClear::Right), Some(90.0));
+        assert_eq!(floats.bottom(), Some(90.0));
+    }
+
+    #[test]
+    fn lines_are_relative_to_the_content_box() {
+        let mut floats = Floats::default();
+        floats.place(CssFloat::Left, size(100.0, 50.0), 20.0, 10.0, 510.0);
+
+        let area = FloatArea {
+            floats: &floats,
+            origin: Point { x: 10.0, y: 40.0 },
+            width: 500.0,
+        };
+
+        assert_eq!(area.line(0.0, 20.0), (100.0, 500.0));
+        assert_eq!(area.line(30.0, 20.0), (0.0, 500.0));
+        assert_eq!(area.next_line(0.0), Some(30.0));
+        assert_eq!(area.next_line(30.0), None);
+    }
+}
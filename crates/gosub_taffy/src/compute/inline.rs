@@ -1,6 +1,9 @@
 use parley::fontique::{FallbackKey, FontWeight, Script};
 use parley::{AlignmentOptions, FontContext};
-use std::sync::{LazyLock, Mutex};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, Mutex, Weak};
 use taffy::{
     AvailableSpace, CollapsibleMarginSet, Layout, LayoutInput, LayoutOutput, LayoutPartialTree, NodeId, Point, Rect,
     RunMode, Size,
@@ -9,15 +12,22 @@ use taffy::{
 use gosub_interface::config::HasLayouter;
 use gosub_interface::css3::{CssProperty, CssValue};
 use gosub_interface::font::{FontBlob, FontInfo, FontManager, FontStyle, HasFontManager};
-use gosub_interface::layout::{Decoration, DecorationStyle, HasTextLayout, LayoutNode, LayoutTree};
+use gosub_interface::layout::{
+    Decoration, DecorationStyle, HasTextLayout, LayoutNode, LayoutTree, ReplacedText, TextCluster,
+};
 use gosub_shared::font::Glyph;
 use gosub_shared::geo::FP;
 use gosub_shared::{geo, ROBOTO_FONT};
 
+use crate::compute::float::FloatArea;
+use crate::compute::white_space::{break_words, is_line_break_control, TextStyle, WhiteSpaceProcessor};
 use crate::text::TextLayout;
 use crate::{Display, LayoutDocument, TaffyLayouter};
 
-static FONT_CX: LazyLock<Mutex<FontContext>> = LazyLock::new(|| {
+static FONT_CX: LazyLock<Mutex<FontContext>> = LazyLock::new(|| Mutex::new(font_context()));
+
+/// Returns a font context with only the built-in fallback font
+fn font_context() -> FontContext {
     let mut ctx = FontContext::default();
 
     let fonts = ctx.collection.register_fonts(ROBOTO_FONT.to_vec());
@@ -25,14 +35,36 @@ static FONT_CX: LazyLock<Mutex<FontContext>> = LazyLock::new(|| {
     ctx.collection
         .append_fallbacks(FallbackKey::new(Script::from("Latn"), None), fonts.iter().map(|f| f.0));
 
-    Mutex::new(ctx)
-});
+    ctx
+}
+
+/// Identifies a font that is registered in `FONT_CX`
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum FontKey {
+    /// Font file with the face index
+    File(PathBuf, i32),
+    /// In-memory font data (web fonts), keyed by the id of the data and the face index
+    Memory(u64, i32),
+}
+
+/// Font from the font manager that is registered in `FONT_CX`
+struct RegisteredFont {
+    /// Family name the layouter knows the font by
+    family: String,
+    /// Data of an in-memory font, which is released from `FONT_CX` once the font manager dropped it
+    data: Option<Weak<dyn AsRef<[u8]> + Send + Sync>>,
+}
+
+/// Fonts from the font manager that are registered in `FONT_CX`
+static REGISTERED_FONTS: LazyLock<Mutex<HashMap<FontKey, RegisteredFont>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
-/// Computes the layout for inline elements.
+/// Computes the layout for inline elements. The line boxes are shortened next to the floats in `floats`.
 pub fn compute_inline_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
     tree: &mut LayoutDocument<C>,
     node_id: <C::LayoutTree as LayoutTree<C>>::NodeId,
     mut layout_input: LayoutInput,
+    floats: Option<FloatArea>,
 ) -> LayoutOutput {
     layout_input.known_dimensions = Size::NONE;
     layout_input.run_mode = RunMode::PerformLayout; //TODO: We should respect the run mode
@@ -53,6 +85,8 @@ pub fn compute_inline_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
     let mut text_node_data: Vec<TextNodeData<C>> = Vec::new();
     // List of any inline boxes that are inside the node
     let mut inline_boxes = Vec::new();
+    // White space is processed across the text nodes
+    let mut white_space = WhiteSpaceProcessor::default();
 
     // Generate the text data and inline boxes. A text can consist of multiple text nodes, for instance, if there are
     // different font sizes or weights inside the text. For instance:  "This is a <b>bold</b> text". In this example
@@ -65,51 +99,36 @@ pub fn compute_inline_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
         let Some(node) = tree.0.get_node_mut(*child) else {
             continue;
         };
-        node.clear_text_layout();
 
         if let Some(text) = node.text_data() {
             // We found a text node
-            if text.is_empty() {
-                continue;
-            }
+            let text = text.to_string();
+            node.clear_text_layout();
+
+            let font_size = node
+                .get_property("font-size")
+                .map_or(16.0, gosub_interface::css3::CssProperty::unit_to_px);
+            let text_style = TextStyle::from_node(node, font_size);
+
+            let from = str_buf.len();
+            white_space.push(&text, &text_style, &mut str_buf);
 
-            // Empty or whitespace only text nodes are ignored
-            let only_whitespace = text.chars().all(char::is_whitespace);
-            if only_whitespace {
+            // Text that only consists of collapsed white space adds nothing
+            if str_buf.len() == from {
                 continue;
             }
 
-            // We add a space between the text nodes, so that the text is not glued together
-            str_buf.push(' ');
-            str_buf.push_str(text);
+            // The text is needed for finding fallback fonts, after the node properties have been read
+            let text = str_buf[from..].to_string();
 
-            // @TODO: default font family can be different per platform
-            let font_family = node
-                .get_property("font-family")
-                .and_then(|s| s.as_string())
-                .map_or("sans-serif".to_string(), std::string::ToString::to_string);
-
-            let font_size = node
-                .get_property("font-size")
-                .map_or(16.0, gosub_interface::css3::CssProperty::unit_to_px);
-            let alignment = parse_alignment(node);
-            let font_weight = parse_font_weight(node);
-            let font_style = parse_font_style(node);
-            let var_axes = parse_font_axes(node);
-            let line_height = node
-                .get_property("line-height")
-                .and_then(gosub_interface::css3::CssProperty::as_number);
-            let word_spacing = node
-                .get_property("word-spacing")
-                .map(gosub_interface::css3::CssProperty::unit_to_px);
-            let letter_spacing = node
-                .get_property("letter-spacing")
-                .map(gosub_interface::css3::CssProperty::unit_to_px);
-
-            let font_info = <C::FontManager as FontManager>::FontInfo::new(&font_family)
-                .unwrap()
-                .with_weight(font_weight.value() as i32)
-                .with_style(font_style);
+            let mut data = node_text_data::<C>(
+                node,
+                font_size,
+                &text,
+                text_style.overflow_wrap,
+                str_buf.len(),
+                child_node_id,
+            );
 
             let mut underline = false;
             let mut overline = false;
@@ -181,29 +200,18 @@ pub fn compute_inline_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
                 }
             }
 
-            text_node_data.push(TextNodeData {
-                font_info,
-                font_size,
-                line_height,
-                word_spacing,
-                letter_spacing,
-                alignment,
-                var_axes,
-
-                decoration: Decoration {
-                    underline,
-                    overline,
-                    line_through,
-                    color: decoration_color,
-                    style,
-                    width: decoration_width,
-                    underline_offset,
-                    x_offset: 0.0,
-                },
-
-                to: str_buf.len(),
-                id: child_node_id,
-            });
+            data.decoration = Decoration {
+                underline,
+                overline,
+                line_through,
+                color: decoration_color,
+                style,
+                width: decoration_width,
+                underline_offset,
+                x_offset: 0.0,
+            };
+
+            text_node_data.push(data);
         } else {
             // We found an inline box
             let out = tree.compute_child_layout(child_node_id, layout_input);
@@ -221,6 +229,8 @@ pub fn compute_inline_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
                 out.content_size
             };
 
+            white_space.push_box(&mut str_buf);
+
             inline_boxes.push(parley::InlineBox {
                 id: child_node_id.into(),
                 index: str_buf.len(),
@@ -240,154 +250,11 @@ pub fn compute_inline_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
         str_buf.push(0 as char);
     }
 
-    // We use the parley layout engine to generate the text layout
-    let mut layout_cx: parley::LayoutContext<usize> = parley::LayoutContext::new();
-    // let mut scale_cx = ScaleContext::new();
-
-    let mut font_context = FONT_CX.lock().unwrap();
-
-    let mut builder = layout_cx.ranged_builder(&mut font_context, &str_buf, 1.0);
-    let mut align = parley::Alignment::default();
-
-    // The first text node is the default style for the text. This is why this is treated separately.
-    if let Some(default) = text_node_data.first() {
-        let info: &<<C as HasFontManager>::FontManager as FontManager>::FontInfo = default.font_info();
-
-        builder.push_default(parley::StyleProperty::FontStack(parley::FontStack::Single(
-            parley::FontFamily::Named(info.family().into()),
-        )));
-        builder.push_default(parley::StyleProperty::FontSize(default.font_size));
-        if let Some(line_height) = default.line_height {
-            builder.push_default(parley::StyleProperty::LineHeight(line_height));
-        }
-        if let Some(word_spacing) = default.word_spacing {
-            builder.push_default(parley::StyleProperty::WordSpacing(word_spacing));
-        }
-        if let Some(letter_spacing) = default.letter_spacing {
-            builder.push_default(parley::StyleProperty::LetterSpacing(letter_spacing));
-        }
-        builder.push_default(parley::StyleProperty::FontWeight(FontWeight::new(info.weight() as f32)));
-        builder.push_default(parley::StyleProperty::FontStyle(match info.style() {
-            FontStyle::Normal => parley::FontStyle::Normal,
-            FontStyle::Italic => parley::FontStyle::Italic,
-            FontStyle::Oblique => parley::FontStyle::Oblique(None),
-        }));
-        builder.push_default(parley::StyleProperty::FontVariations(parley::FontSettings::List(
-            default.var_axes.as_slice().into(),
-        )));
-
-        if default.decoration.overline && default.decoration.underline {
-            builder.push_default(parley::StyleProperty::Underline(true));
-
-            builder.push_default(parley::StyleProperty::UnderlineSize(Some(
-                default.decoration.width * 2.0,
-            )));
-            builder.push_default(parley::StyleProperty::UnderlineOffset(Some(
-                default.decoration.underline_offset,
-            )));
-        } else if default.decoration.overline {
-            builder.push_default(parley::StyleProperty::Underline(true));
-
-            builder.push_default(parley::StyleProperty::UnderlineSize(Some(default.decoration.width)));
-        } else if default.decoration.underline {
-            builder.push_default(parley::StyleProperty::Underline(true));
-
-            builder.push_default(parley::StyleProperty::UnderlineSize(Some(default.decoration.width)));
-            builder.push_default(parley::StyleProperty::UnderlineOffset(Some(
-                default.decoration.underline_offset,
-            )));
-        }
-
-        builder.push_default(parley::StyleProperty::Brush(0));
-
-        align = default.alignment;
-
-        let mut from = default.to;
-
-        for (idx, text_node) in text_node_data.get(1..).unwrap_or_default().iter().enumerate() {
-            let info: &<<C as HasFontManager>::FontManager as FontManager>::FontInfo = text_node.font_info();
-
-            builder.push(
-                parley::StyleProperty::FontStack(parley::FontStack::Source(info.family().into())),
-                from..text_node.to,
-            );
-            builder.push(parley::StyleProperty::FontSize(text_node.font_size), from..text_node.to);
-            if let Some(line_height) = text_node.line_height {
-                builder.push(parley::StyleProperty::LineHeight(line_height), from..text_node.to);
-            }
-            if let Some(word_spacing) = text_node.word_spacing {
-                builder.push(parley::StyleProperty::WordSpacing(word_spacing), from..text_node.to);
-            }
-            if let Some(letter_spacing) = text_node.letter_spacing {
-                builder.push(parley::StyleProperty::LetterSpacing(letter_spacing), from..text_node.to);
-            }
-            builder.push(
-                parley::StyleProperty::FontWeight(FontWeight::new(info.weight() as f32)),
-                from..text_node.to,
-            );
-            builder.push(
-                parley::StyleProperty::FontStyle(match info.style() {
-                    FontStyle::Normal => parley::FontStyle::Normal,
-                    FontStyle::Italic => parley::FontStyle::Italic,
-                    FontStyle::Oblique => parley::FontStyle::Oblique(None),
-                }),
-                from..text_node.to,
-            );
-            builder.push(
-                parley::StyleProperty::FontVariations(parley::FontSettings::List(text_node.var_axes.as_slice().into())),
-                from..text_node.to,
-            );
-
-            builder.push(parley::StyleProperty::Brush(idx), from..text_node.to);
+    let align = text_node_data
+        .first()
+        .map_or(parley::Alignment::default(), |default| default.alignment);
 
-            if default.decoration.overline && default.decoration.underline {
-                builder.push(parley::StyleProperty::Underline(true), from..text_node.to);
-
-                builder.push(
-                    parley::StyleProperty::UnderlineSize(Some(default.decoration.width * 2.0)),
-                    from..text_node.to,
-                );
-                builder.push(
-                    parley::StyleProperty::UnderlineOffset(Some(default.decoration.underline_offset + 4.0)),
-                    from..text_node.to,
-                );
-            } else if default.decoration.overline {
-                builder.push(parley::StyleProperty::Underline(true), from..text_node.to);
-
-                builder.push(
-                    parley::StyleProperty::UnderlineSize(Some(default.decoration.width)),
-                    from..text_node.to,
-                );
-                builder.push(parley::StyleProperty::UnderlineOffset(Some(4.0)), from..text_node.to);
-            } else if default.decoration.underline {
-                builder.push(parley::StyleProperty::Underline(true), from..text_node.to);
-
-                builder.push(
-                    parley::StyleProperty::UnderlineSize(Some(default.decoration.width)),
-                    from..text_node.to,
-                );
-                builder.push(
-                    parley::StyleProperty::UnderlineOffset(Some(default.decoration.underline_offset)),
-                    from..text_node.to,
-                );
-            }
-
-            builder.push(
-                parley::StyleProperty::Underline(default.decoration.underline || default.decoration.overline),
-                from..text_node.to,
-            );
-
-            from = text_node.to;
-        }
-    }
-
-    for inline_box in inline_boxes {
-        builder.push_inline_box(inline_box);
-    }
-
-    let mut layout = builder.build(&str_buf);
-
-    drop(font_context);
+    let mut layout = build_layout(&str_buf, &text_node_data, &inline_boxes);
 
     let max_width = match layout_input.available_space.width {
         AvailableSpace::Definite(width) => Some(width),
@@ -395,10 +262,22 @@ pub fn compute_inline_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
         AvailableSpace::MaxContent => None,
     };
 
-    layout.break_all_lines(max_width);
+    let mut lines = break_lines(&mut layout, floats, max_width);
+
+    // Words that do not fit on their line are broken when `overflow-wrap` allows it. The lines are broken again with
+    // break opportunities between the letters of the overflowing words.
+    let overflowing = overflowing_words(&layout, &lines, max_width, &text_node_data);
+    if !overflowing.is_empty() {
+        insert_word_breaks(&mut str_buf, &overflowing, &mut text_node_data, &mut inline_boxes);
+
+        layout = build_layout(&str_buf, &text_node_data, &inline_boxes);
+        lines = break_lines(&mut layout, floats, max_width);
+    }
+
+    let align_width = if lines.is_empty() { None } else { max_width };
 
     layout.align(
-        None,
+        align_width,
         align,
         AlignmentOptions {
             align_when_overflowing: true,
@@ -406,10 +285,15 @@ pub fn compute_inline_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
     );
 
     let content_size = Size {
-        width: layout.width().ceil(),
-        height: layout.height().ceil(),
+        width: align_width.unwrap_or(layout.width()).ceil(),
+        height: (layout.height() + lines.last().map_or(0.0, |line| line.shift)).ceil(),
     };
 
+    let offsets = lines
+        .iter()
+        .map(|line| line.offset(align, align_width.unwrap_or_default()))
+        .collect::<Vec<_>>();
+
     let mut current_node_idx = 0;
     let mut current_node_id = <C::LayoutTree as LayoutTree<C>>::NodeId::from(0);
     let mut current_to = 0;
@@ -423,35 +307,17 @@ pub fn compute_inline_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
 
     let mut ids = Vec::with_capacity(text_node_data.len());
 
-    'lines: for line in layout.lines() {
+    'lines: for (index, line) in layout.lines().enumerate() {
         let metrics = line.metrics();
+        let (dx, dy) = offsets.get(index).copied().unwrap_or_default();
 
         let height = metrics.line_height;
 
         for item in line.items() {
             match item {
                 parley::PositionedLayoutItem::GlyphRun(run) => {
-                    let mut offset = 0.0;
-
                     let grun = run.run();
-                    let fs = grun.font_size();
-
-                    let glyphs = run
-                        .glyphs()
-                        .map(|g| {
-                            let gl = Glyph {
-                                id: g.id,
-                                x: g.x + offset,
-                                y: g.y,
-                            };
-
-                            offset += g.advance;
-
-                            gl
-                        })
-                        .collect::<Vec<_>>();
-
-                    let run_y = run.baseline();
+                    let glyphs = run_glyphs(&run);
 
                     current_glyph_idx += glyphs.len();
 
@@ -466,13 +332,6 @@ pub fn compute_inline_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
                         }
                     }
 
-                    let size = geo::Size {
-                        width: run.advance(),
-                        height,
-                    };
-
-                    let coords = grun.normalized_coords().to_owned();
-
                     let data = text_node_data.get(run.style().brush);
 
                     let mut decoration = data.map(|x| x.decoration.clone()).unwrap_or_default();
@@ -492,22 +351,7 @@ pub fn compute_inline_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
                         }
                     }
 
-                    let font = grun.font().clone();
-                    let (font_data, _) = font.data.into_raw_parts();
-
-                    let text_layout = TextLayout {
-                        glyphs,
-                        size,
-                        font_size: fs,
-                        // Actual font that is resolved by the layouter which is used for these set of glyphs
-                        font_data: FontBlob::new(font_data, font.index),
-                        coords,
-                        decoration,
-                        offset: geo::Point {
-                            x: run.offset() as FP,
-                            y: run_y as FP,
-                        },
-                    };
+                    let text_layout = run_layout(&str_buf, &run, glyphs, height, (dx, dy), decoration);
 
                     let node_id = data.map_or(current_node_id, |x| {
                         <C::LayoutTree as LayoutTree<C>>::NodeId::from(x.id.into())
@@ -537,8 +381,8 @@ pub fn compute_inline_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
                             scrollbar_size: Size::ZERO,
                             border: Rect::ZERO,
                             location: Point {
-                                x: inline_box.x,
-                                y: inline_box.y,
+                                x: inline_box.x + dx,
+                                y: inline_box.y + dy,
                             },
                             order: 0,
                             padding: Rect::ZERO,
@@ -611,6 +455,515 @@ pub fn compute_inline_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
     }
 }
 
+/// Lays out the text of a replaced element, like the value of a form control, with the style of the element. When
+/// `area` (the top left corner and the size of the content box) is given, the text layouts are stored in the node: a
+/// multiline text is wrapped at the width of the content box, and a single line is centered vertically in it.
+/// Returns the size of the text, which has the height of a line when the text is empty.
+pub fn compute_replaced_text<C: HasLayouter<Layouter = TaffyLayouter>>(
+    tree: &mut LayoutDocument<C>,
+    node_id: <C::LayoutTree as LayoutTree<C>>::NodeId,
+    text: &ReplacedText,
+    area: Option<(Point<f32>, Size<f32>)>,
+) -> Size<f32> {
+    let Some(node) = tree.0.get_node_mut(node_id) else {
+        return Size::ZERO;
+    };
+
+    // An empty text still has the height of a line
+    let shown = if text.text.is_empty() { " " } else { text.text.as_str() };
+
+    let font_size = node
+        .get_property("font-size")
+        .map_or(16.0, gosub_interface::css3::CssProperty::unit_to_px);
+    let data = node_text_data::<C>(node, font_size, shown, true, shown.len(), NodeId::from(node_id.into()));
+
+    let mut layout = build_layout(shown, &[data], &[]);
+    layout.break_all_lines(area.filter(|_| text.multiline).map(|(_, size)| size.width));
+
+    let size = Size {
+        width: if text.text.is_empty() {
+            0.0
+        } else {
+            layout.width().ceil()
+        },
+        height: layout.height().ceil(),
+    };
+
+    let Some((origin, area)) = area else {
+        return size;
+    };
+
+    let dy = if text.multiline {
+        origin.y
+    } else {
+        origin.y + (area.height - size.height) / 2.0
+    };
+
+    // The line that is laid out for an empty text is not shown
+    let layouts = if text.text.is_empty() {
+        Vec::new()
+    } else {
+        positioned_layouts(shown, &layout, (origin.x, dy))
+    };
+
+    if let Some(node) = tree.0.get_node_mut(node_id) {
+        node.clear_text_layout();
+        for layout in layouts {
+            node.add_text_layout(layout);
+        }
+    }
+
+    size
+}
+
+/// Lays out the rows of the list of a replaced element, like the options of an open `<select>`, one per line. The
+/// text layouts are added to the layouts of the node, with the first row starting at `origin`.
+pub fn compute_replaced_list<C: HasLayouter<Layouter = TaffyLayouter>>(
+    tree: &mut LayoutDocument<C>,
+    node_id: <C::LayoutTree as LayoutTree<C>>::NodeId,
+    list: &[String],
+    origin: Point<f32>,
+) {
+    let Some(node) = tree.0.get_node_mut(node_id) else {
+        return;
+    };
+
+    let text = list.join("\n");
+    let font_size = node
+        .get_property("font-size")
+        .map_or(16.0, gosub_interface::css3::CssProperty::unit_to_px);
+    let data = node_text_data::<C>(node, font_size, &text, false, text.len(), NodeId::from(node_id.into()));
+
+    let mut layout = build_layout(&text, &[data], &[]);
+    layout.break_all_lines(None);
+
+    for text_layout in positioned_layouts(&text, &layout, (origin.x, origin.y)) {
+        node.add_text_layout(text_layout);
+    }
+}
+
+/// Returns the text layouts of all glyph runs in a parley layout, moved by `offset`
+fn positioned_layouts(text: &str, layout: &parley::Layout<usize>, offset: (f32, f32)) -> Vec<TextLayout> {
+    let mut layouts = Vec::new();
+
+    for line in layout.lines() {
+        let height = line.metrics().line_height;
+
+        for item in line.items() {
+            if let parley::PositionedLayoutItem::GlyphRun(run) = item {
+                let glyphs = run_glyphs(&run);
+                layouts.push(run_layout(text, &run, glyphs, height, offset, Decoration::default()));
+            }
+        }
+    }
+
+    layouts
+}
+
+/// Returns the glyphs of a glyph run, positioned from the start of the run
+fn run_glyphs(run: &parley::GlyphRun<'_, usize>) -> Vec<Glyph> {
+    let mut offset = 0.0;
+
+    run.glyphs()
+        .map(|g| {
+            let gl = Glyph {
+                id: g.id,
+                x: g.x + offset,
+                y: g.y,
+            };
+
+            offset += g.advance;
+
+            gl
+        })
+        .collect()
+}
+
+/// Returns the text layout of a glyph run on a line with the given height. `offset` moves the run from its place in
+/// the parley layout.
+fn run_layout(
+    text: &str,
+    run: &parley::GlyphRun<'_, usize>,
+    glyphs: Vec<Glyph>,
+    height: f32,
+    offset: (f32, f32),
+    decoration: Decoration,
+) -> TextLayout {
+    let grun = run.run();
+    let (text, clusters) = run_clusters(text, grun);
+
+    let font = grun.font().clone();
+    let (font_data, _) = font.data.into_raw_parts();
+
+    TextLayout {
+        glyphs,
+        size: geo::Size {
+            width: run.advance(),
+            height,
+        },
+        font_size: grun.font_size(),
+        // Actual font that is resolved by the layouter which is used for these set of glyphs
+        font_data: FontBlob::new(font_data, font.index),
+        coords: grun.normalized_coords().to_owned(),
+        decoration,
+        text,
+        clusters,
+        offset: geo::Point {
+            x: (run.offset() + offset.0) as FP,
+            y: (run.baseline() + offset.1) as FP,
+        },
+    }
+}
+
+/// Returns the text of a glyph run and its clusters. The characters that are added to the text for line breaking are
+/// left out.
+fn run_clusters(text: &str, run: &parley::Run<'_, usize>) -> (String, Vec<TextCluster>) {
+    let mut x = 0.0;
+    let mut parts = run
+        .visual_clusters()
+        .map(|cluster| {
+            let part = (cluster.text_range(), x, cluster.advance());
+            x += cluster.advance();
+            part
+        })
+        .collect::<Vec<_>>();
+
+    // The text is kept in logical order, also when the run is painted from right to left
+    parts.sort_by_key(|(range, _, _)| range.start);
+
+    let mut run_text = String::new();
+    let mut clusters = parts
+        .into_iter()
+        .map(|(range, x, advance)| {
+            let start = run_text.len();
+            if let Some(part) = text.get(range) {
+                run_text.extend(part.chars().filter(|c| !is_line_break_control(*c) && *c != '\0'));
+            }
+
+            TextCluster {
+                text: start..run_text.len(),
+                x,
+                advance,
+            }
+        })
+        .collect::<Vec<_>>();
+    clusters.sort_by(|a, b| a.x.total_cmp(&b.x));
+
+    (run_text, clusters)
+}
+
+/// Breaks the text into lines. Lines next to floats are shortened, and each of them is moved to its place between
+/// the floats after the alignment.
+fn break_lines(
+    layout: &mut parley::Layout<usize>,
+    floats: Option<FloatArea>,
+    max_width: Option<f32>,
+) -> Vec<FloatLine> {
+    match (floats, max_width) {
+        (Some(floats), Some(width)) if !floats.is_empty() => break_lines_around(layout, floats, width),
+        _ => {
+            layout.break_all_lines(max_width);
+            Vec::new()
+        }
+    }
+}
+
+/// Returns the text ranges of the lines that overflow, in text nodes where `overflow-wrap` allows breaking words
+fn overflowing_words<C: HasFontManager>(
+    layout: &parley::Layout<usize>,
+    lines: &[FloatLine],
+    max_width: Option<f32>,
+    text_node_data: &[TextNodeData<C>],
+) -> Vec<Range<usize>> {
+    let Some(max_width) = max_width else {
+        return Vec::new();
+    };
+
+    if !text_node_data.iter().any(|data| data.overflow_wrap) {
+        return Vec::new();
+    }
+
+    let mut ranges = Vec::new();
+
+    for (index, line) in layout.lines().enumerate() {
+        let metrics = line.metrics();
+        let width = lines.get(index).map_or(max_width, |line| line.end - line.start);
+
+        if metrics.advance - metrics.trailing_whitespace <= width {
+            continue;
+        }
+
+        let text = line.text_range();
+        let mut from = 0;
+        for data in text_node_data {
+            let start = text.start.max(from);
+            let end = text.end.min(data.to);
+            if data.overflow_wrap && start < end {
+                ranges.push(start..end);
+            }
+
+            from = data.to;
+        }
+    }
+
+    ranges
+}
+
+/// Inserts break opportunities between the letters in the given ranges of the text, and moves the offsets of the
+/// text nodes and inline boxes after them
+fn insert_word_breaks<C: HasFontManager>(
+    text: &mut String,
+    ranges: &[Range<usize>],
+    text_node_data: &mut [TextNodeData<C>],
+    inline_boxes: &mut [parley::InlineBox],
+) {
+    // From the end of the text, so the ranges that are left keep their offsets
+    for range in ranges.iter().rev() {
+        let Some(words) = text.get(range.clone()).map(break_words) else {
+            continue;
+        };
+        let added = words.len() - range.len();
+
+        text.replace_range(range.clone(), &words);
+
+        for to in text_node_data.iter_mut().map(|data| &mut data.to) {
+            if *to >= range.end {
+                *to += added;
+            }
+        }
+        for inline_box in inline_boxes.iter_mut() {
+            if inline_box.index >= range.end {
+                inline_box.index += added;
+            }
+        }
+    }
+}
+
+/// Line box that is shortened by floats
+#[derive(Debug, Clone, Copy)]
+struct FloatLine {
+    /// Start and end of the space between the floats
+    start: f32,
+    end: f32,
+    /// How far the line is moved down to get past floats
+    shift: f32,
+}
+
+impl FloatLine {
+    /// Returns how far the line is moved from where it is aligned in the full width of the container
+    fn offset(&self, align: parley::Alignment, width: f32) -> (f32, f32) {
+        let factor = match align {
+            parley::Alignment::Middle => 0.5,
+            parley::Alignment::End | parley::Alignment::Right => 1.0,
+            _ => 0.0,
+        };
+
+        (self.start + factor * (self.end - self.start - width), self.shift)
+    }
+}
+
+/// Breaks the text into lines that fit between the floats next to them. A line that does not fit next to the floats
+/// is moved down until it does.
+fn break_lines_around(layout: &mut parley::Layout<usize>, floats: FloatArea, width: f32) -> Vec<FloatLine> {
+    let mut lines = Vec::new();
+    let mut breaker = layout.break_lines();
+
+    // Top of the next line, and where it would be without floats
+    let mut y = 0.0;
+    let mut stacked = 0.0;
+
+    loop {
+        let (mut start, mut end) = floats.line(y, 0.0);
+        let Some((mut advance, mut height)) = breaker.break_next(end - start) else {
+            break;
+        };
+
+        // Floats further down can make the line shorter
+        let (band_start, band_end) = floats.line(y, height);
+        if band_end - band_start < end - start && breaker.revert() {
+            (start, end) = (band_start, band_end);
+            let Some(line) = breaker.break_next(end - start) else {
+                break;
+            };
+            (advance, height) = line;
+        }
+
+        if advance > end - start && end - start < width {
+            if let Some(next) = floats.next_line(y) {
+                if breaker.revert() {
+                    y = next;
+                    continue;
+                }
+            }
+        }
+
+        lines.push(FloatLine {
+            start,
+            end,
+            shift: y - stacked,
+        });
+
+        y += height;
+        stacked += height;
+    }
+
+    breaker.finish();
+
+    lines
+}
+
+/// Builds the parley layout of the text of an inline formatting context, with the styles of its text nodes and its
+/// inline boxes
+fn build_layout<C: HasFontManager>(
+    text: &str,
+    text_node_data: &[TextNodeData<C>],
+    inline_boxes: &[parley::InlineBox],
+) -> parley::Layout<usize> {
+    // We use the parley layout engine to generate the text layout
+    let mut layout_cx: parley::LayoutContext<usize> = parley::LayoutContext::new();
+    // let mut scale_cx = ScaleContext::new();
+
+    let mut font_context = FONT_CX.lock().unwrap();
+
+    let mut builder = layout_cx.ranged_builder(&mut font_context, text, 1.0);
+
+    // The first text node is the default style for the text. This is why this is treated separately.
+    if let Some(default) = text_node_data.first() {
+        let info: &<<C as HasFontManager>::FontManager as FontManager>::FontInfo = default.font_info();
+
+        builder.push_default(parley::StyleProperty::FontStack(parley::FontStack::Source(
+            default.font_stack.as_str().into(),
+        )));
+        builder.push_default(parley::StyleProperty::FontSize(default.font_size));
+        if let Some(line_height) = default.line_height {
+            builder.push_default(parley::StyleProperty::LineHeight(line_height));
+        }
+        if let Some(word_spacing) = default.word_spacing {
+            builder.push_default(parley::StyleProperty::WordSpacing(word_spacing));
+        }
+        if let Some(letter_spacing) = default.letter_spacing {
+            builder.push_default(parley::StyleProperty::LetterSpacing(letter_spacing));
+        }
+        builder.push_default(parley::StyleProperty::FontWeight(FontWeight::new(info.weight() as f32)));
+        builder.push_default(parley::StyleProperty::FontStyle(match info.style() {
+            FontStyle::Normal => parley::FontStyle::Normal,
+            FontStyle::Italic => parley::FontStyle::Italic,
+            FontStyle::Oblique => parley::FontStyle::Oblique(None),
+        }));
+        builder.push_default(parley::StyleProperty::FontVariations(parley::FontSettings::List(
+            default.var_axes.as_slice().into(),
+        )));
+
+        if default.decoration.overline && default.decoration.underline {
+            builder.push_default(parley::StyleProperty::Underline(true));
+
+            builder.push_default(parley::StyleProperty::UnderlineSize(Some(
+                default.decoration.width * 2.0,
+            )));
+            builder.push_default(parley::StyleProperty::UnderlineOffset(Some(
+                default.decoration.underline_offset,
+            )));
+        } else if default.decoration.overline {
+            builder.push_default(parley::StyleProperty::Underline(true));
+
+            builder.push_default(parley::StyleProperty::UnderlineSize(Some(default.decoration.width)));
+        } else if default.decoration.underline {
+            builder.push_default(parley::StyleProperty::Underline(true));
+
+            builder.push_default(parley::StyleProperty::UnderlineSize(Some(default.decoration.width)));
+            builder.push_default(parley::StyleProperty::UnderlineOffset(Some(
+                default.decoration.underline_offset,
+            )));
+        }
+
+        builder.push_default(parley::StyleProperty::Brush(0));
+
+        let mut from = default.to;
+
+        for (idx, text_node) in text_node_data.get(1..).unwrap_or_default().iter().enumerate() {
+            let info: &<<C as HasFontManager>::FontManager as FontManager>::FontInfo = text_node.font_info();
+
+            builder.push(
+                parley::StyleProperty::FontStack(parley::FontStack::Source(text_node.font_stack.as_str().into())),
+                from..text_node.to,
+            );
+            builder.push(parley::StyleProperty::FontSize(text_node.font_size), from..text_node.to);
+            if let Some(line_height) = text_node.line_height {
+                builder.push(parley::StyleProperty::LineHeight(line_height), from..text_node.to);
+            }
+            if let Some(word_spacing) = text_node.word_spacing {
+                builder.push(parley::StyleProperty::WordSpacing(word_spacing), from..text_node.to);
+            }
+            if let Some(letter_spacing) = text_node.letter_spacing {
+                builder.push(parley::StyleProperty::LetterSpacing(letter_spacing), from..text_node.to);
+            }
+            builder.push(
+                parley::StyleProperty::FontWeight(FontWeight::new(info.weight() as f32)),
+                from..text_node.to,
+            );
+            builder.push(
+                parley::StyleProperty::FontStyle(match info.style() {
+                    FontStyle::Normal => parley::FontStyle::Normal,
+                    FontStyle::Italic => parley::FontStyle::Italic,
+                    FontStyle::Oblique => parley::FontStyle::Oblique(None),
+                }),
+                from..text_node.to,
+            );
+            builder.push(
+                parley::StyleProperty::FontVariations(parley::FontSettings::List(text_node.var_axes.as_slice().into())),
+                from..text_node.to,
+            );
+
+            builder.push(parley::StyleProperty::Brush(idx + 1), from..text_node.to);
+
+            if default.decoration.overline && default.decoration.underline {
+                builder.push(parley::StyleProperty::Underline(true), from..text_node.to);
+
+                builder.push(
+                    parley::StyleProperty::UnderlineSize(Some(default.decoration.width * 2.0)),
+                    from..text_node.to,
+                );
+                builder.push(
+                    parley::StyleProperty::UnderlineOffset(Some(default.decoration.underline_offset + 4.0)),
+                    from..text_node.to,
+                );
+            } else if default.decoration.overline {
+                builder.push(parley::StyleProperty::Underline(true), from..text_node.to);
+
+                builder.push(
+                    parley::StyleProperty::UnderlineSize(Some(default.decoration.width)),
+                    from..text_node.to,
+                );
+                builder.push(parley::StyleProperty::UnderlineOffset(Some(4.0)), from..text_node.to);
+            } else if default.decoration.underline {
+                builder.push(parley::StyleProperty::Underline(true), from..text_node.to);
+
+                builder.push(
+                    parley::StyleProperty::UnderlineSize(Some(default.decoration.width)),
+                    from..text_node.to,
+                );
+                builder.push(
+                    parley::StyleProperty::UnderlineOffset(Some(default.decoration.underline_offset)),
+                    from..text_node.to,
+                );
+            }
+
+            builder.push(
+                parley::StyleProperty::Underline(default.decoration.underline || default.decoration.overline),
+                from..text_node.to,
+            );
+
+            from = text_node.to;
+        }
+    }
+
+    for inline_box in inline_boxes {
+        builder.push_inline_box(inline_box.clone());
+    }
+
+    builder.build(text)
+}
+
 /// Structure that holds information for a (partial) text that consists of a single font size, weight, etc.
 /// If a string consists of multiple font sizes, weights, etc., there will be multiple `TextNodeData` elements.
 /// For instance: "This is a <b>bold</b> text". In this example there will be three text nodes: "This is a ",
@@ -623,6 +976,9 @@ struct TextNodeData<C: HasFontManager> {
     id: NodeId,
     /// Actual font for rendering and layouting
     font_info: <<C as HasFontManager>::FontManager as FontManager>::FontInfo,
+    /// Font stack (in CSS `font-family` syntax) of the matched font followed by the fallback fonts needed
+    /// for characters that are missing in the matched font
+    font_stack: String,
     /// Font size
     font_size: f32,
     /// Line height in case of multiple lines
@@ -633,6 +989,8 @@ struct TextNodeData<C: HasFontManager> {
     letter_spacing: Option<f32>,
     /// Alignment of the text
     alignment: parley::Alignment,
+    /// Words that overflow their line can be broken
+    overflow_wrap: bool,
     /// Unknown
     var_axes: Vec<parley::FontVariation>,
     /// Decoration of the font (strikethrough, underline etc)
@@ -646,6 +1004,251 @@ impl<C: HasFontManager> TextNodeData<C> {
     }
 }
 
+/// Returns the data of a text with the font properties of the node. The text ends at `to` in the complete string and
+/// has no decoration.
+fn node_text_data<C: HasLayouter>(
+    node: &mut impl LayoutNode<C>,
+    font_size: f32,
+    text: &str,
+    overflow_wrap: bool,
+    to: usize,
+    id: NodeId,
+) -> TextNodeData<C> {
+    let font_families = parse_font_families(node);
+    let alignment = parse_alignment(node);
+    let font_weight = parse_font_weight(node);
+    let font_style = parse_font_style(node);
+    let var_axes = parse_font_axes(node);
+    let line_height = node
+        .get_property("line-height")
+        .and_then(gosub_interface::css3::CssProperty::as_number);
+    let word_spacing = node
+        .get_property("word-spacing")
+        .map(gosub_interface::css3::CssProperty::unit_to_px);
+    let letter_spacing = node
+        .get_property("letter-spacing")
+        .map(gosub_interface::css3::CssProperty::unit_to_px);
+
+    let font_stretch = parse_font_stretch(node);
+
+    let (font_info, font_stack) = resolve_font::<C>(&font_families, font_style, font_weight, font_stretch, text);
+
+    TextNodeData {
+        to,
+        id,
+        font_info,
+        font_stack,
+        font_size,
+        line_height,
+        word_spacing,
+        letter_spacing,
+        alignment,
+        overflow_wrap,
+        var_axes,
+        decoration: Decoration::default(),
+    }
+}
+
+/// Matches the font for a text node through the font manager and registers the matched font (and any fallback
+/// fonts needed for the characters in `text`) with the layout font context. Returns the matched font info and
+/// the font stack that should be used by the layouter.
+fn resolve_font<C: HasFontManager>(
+    families: &[String],
+    style: FontStyle,
+    weight: parley::FontWeight,
+    stretch: f32,
+    text: &str,
+) -> (<C::FontManager as FontManager>::FontInfo, String) {
+    let weight = weight.value() as i32;
+    let family_refs: Vec<&str> = families.iter().map(String::as_str).collect();
+
+    let manager = C::FontManager::instance();
+    let Ok(manager) = manager.read() else {
+        let info = fallback_font_info::<C>(families, style, weight, stretch);
+        let stack = quote_family(info.family());
+        return (info, stack);
+    };
+
+    let Some(info) = manager.match_font(&family_refs, style, weight, stretch) else {
+        let info = fallback_font_info::<C>(families, style, weight, stretch);
+        let stack = quote_family(info.family());
+        return (info, stack);
+    };
+
+    let fallbacks = manager.fallback_fonts(text, &info);
+    drop(manager);
+
+    let mut stack = Vec::with_capacity(fallbacks.len() + 1);
+    for font in std::iter::once(&info).chain(fallbacks.iter()) {
+        let family = register_font(font).unwrap_or_else(|| font.family().to_string());
+        let family = quote_family(&family);
+        if !stack.contains(&family) {
+            stack.push(family);
+        }
+    }
+
+    (info, stack.join(", "))
+}
+
+/// Font info for when the font manager could not find a font. The layouter falls back to its own fonts.
+fn fallback_font_info<C: HasFontManager>(
+    families: &[String],
+    style: FontStyle,
+    weight: i32,
+    stretch: f32,
+) -> <C::FontManager as FontManager>::FontInfo {
+    let family = families.first().map_or("sans-serif", String::as_str);
+
+    <C::FontManager as FontManager>::FontInfo::new(family)
+        .unwrap()
+        .with_weight(weight)
+        .with_style(style)
+        .with_stretch(stretch)
+}
+
+/// Registers the font file (or in-memory font data) of the given font with the layout font context, and
+/// returns the family name that the layouter knows the font by. Fonts are only registered once.
+fn register_font<I: FontInfo>(info: &I) -> Option<String> {
+    let index = info.index().unwrap_or(0);
+    let blob = info.data();
+    let key = match (&blob, info.path()) {
+        (Some(blob), _) => FontKey::Memory(blob.id(), index),
+        (None, Some(path)) => FontKey::File(path, index),
+        (None, None) => return None,
+    };
+
+    register_font_key(key, blob.as_ref())
+}
+
+/// Registers the font with the key with the layout font context, and returns the family name that the layouter knows
+/// the font by. The data of in-memory fonts is in `blob`.
+fn register_font_key(key: FontKey, blob: Option<&FontBlob>) -> Option<String> {
+    let index = match &key {
+        FontKey::File(_, index) | FontKey::Memory(_, index) => *index,
+    };
+
+    let mut registered = REGISTERED_FONTS.lock().unwrap();
+    release_dropped_fonts(&mut registered);
+
+    if let Some(font) = registered.get(&key) {
+        return Some(font.family.clone());
+    }
+
+    let data = match &key {
+        FontKey::File(path, _) => std::fs::read(path).ok()?,
+        FontKey::Memory(..) => blob?.as_u8().to_vec(),
+    };
+
+    let mut font_context = FONT_CX.lock().unwrap();
+    let families = font_context.collection.register_fonts(data);
+
+    let family_id = families
+        .iter()
+        .find(|(_, faces)| faces.iter().any(|f| f.index() as i32 == index))
+        .or(families.first())
+        .map(|(id, _)| *id)?;
+    let family = font_context.collection.family_name(family_id)?.to_string();
+
+    registered.insert(
+        key,
+        RegisteredFont {
+            family: family.clone(),
+            data: blob.map(|blob| Arc::downgrade(blob.data())),
+        },
+    );
+
+    Some(family)
+}
+
+/// Releases the in-memory fonts the font manager dropped, like the web fonts of a document that was navigated away
+/// from. Fonts can not be removed from a font collection one by one, so the font context starts over, and the fonts
+/// that are still used are registered again the next time they are laid out.
+fn release_dropped_fonts(registered: &mut HashMap<FontKey, RegisteredFont>) {
+    let dropped = registered
+        .values()
+        .any(|font| font.data.as_ref().is_some_and(|data| data.strong_count() == 0));
+    if !dropped {
+        return;
+    }
+
+    *FONT_CX.lock().unwrap() = font_context();
+    registered.clear();
+}
+
+/// Quotes a family name so it can be used in a font stack, unless it is a generic family
+fn quote_family(family: &str) -> String {
+    match family {
+        "serif" | "sans-serif" | "monospace" | "cursive" | "fantasy" | "system-ui" => family.to_string(),
+        _ => format!("\"{}\"", family.chars().filter(|c| *c != '"').collect::<String>()),
+    }
+}
+
+/// Parses the `font-family` property into a list of family names. Unquoted family names can consist of
+/// multiple identifiers (`Times New Roman`), which are joined by spaces.
+fn parse_font_families<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> Vec<String> {
+    let Some(prop) = node.get_property("font-family") else {
+        return vec!["sans-serif".to_string()];
+    };
+
+    let mut families = Vec::new();
+
+    if let Some(list) = prop.as_list() {
+        let mut current = Vec::new();
+        for item in list {
+            if item.is_comma() {
+                if !current.is_empty() {
+                    families.push(current.join(" "));
+                    current.clear();
+                }
+                continue;
+            }
+
+            if let Some(s) = item.as_string() {
+                current.push(s.to_string());
+            }
+        }
+
+        if !current.is_empty() {
+            families.push(current.join(" "));
+        }
+    } else if let Some(s) = prop.as_string() {
+        families.extend(
+            s.split(',')
+                .map(|f| f.trim().trim_matches(|c| c == '"' || c == '\'').to_string())
+                .filter(|f| !f.is_empty()),
+        );
+    }
+
+    if families.is_empty() {
+        families.push("sans-serif".to_string());
+    }
+
+    families
+}
+
+/// Parses the `font-stretch` property into a stretch factor (1.0 is normal)
+fn parse_font_stretch<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> f32 {
+    let Some(prop) = node.get_property("font-stretch") else {
+        return 1.0;
+    };
+
+    if let Some(percentage) = prop.as_percentage() {
+        return percentage / 100.0;
+    }
+
+    match prop.as_string() {
+        Some("ultra-condensed") => 0.5,
+        Some("extra-condensed") => 0.625,
+        Some("condensed") => 0.75,
+        Some("semi-condensed") => 0.875,
+        Some("semi-expanded") => 1.125,
+        Some("expanded") => 1.25,
+        Some("extra-expanded") => 1.5,
+        Some("ultra-expanded") => 2.0,
+        _ => 1.0,
+    }
+}
+
 fn parse_alignment<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> parley::Alignment {
     let Some(prop) = node.get_property("text-align") else {
         return parley::Alignment::Start;
@@ -763,3 +1366,29 @@ fn parse_font_axes<C: HasLayouter>(n: &mut impl LayoutNode<C>) -> Vec<parley::Fo
 
     vars
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropped_fonts_are_released() {
+        let blob = FontBlob::new(Arc::new(ROBOTO_FONT.to_vec()), 0);
+        let key = FontKey::Memory(blob.id(), 0);
+
+        let family = register_font_key(key.clone(), Some(&blob));
+        assert!(family.is_some());
+        assert_eq!(register_font_key(key.clone(), Some(&blob)), family);
+
+        // Other data never gets the key of data that was dropped
+        drop(blob);
+        let other = FontBlob::new(Arc::new(ROBOTO_FONT.to_vec()), 0);
+        let other_key = FontKey::Memory(other.id(), 0);
+        assert!(other_key != key);
+
+        assert!(register_font_key(other_key.clone(), Some(&other)).is_some());
+        let registered = REGISTERED_FONTS.lock().unwrap();
+        assert!(!registered.contains_key(&key));
+        assert!(registered.contains_key(&other_key));
+    }
+}
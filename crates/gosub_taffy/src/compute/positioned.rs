@@ -0,0 +1,372 @@
+use taffy::{
+    AvailableSpace, BoxSizing, Layout, LayoutInput, LayoutPartialTree, Line, MaybeMath, MaybeResolve, NodeId, Point,
+    RequestedAxis, ResolveOrZero, RunMode, Size, SizingMode,
+};
+
+use gosub_interface::config::HasLayouter;
+use gosub_interface::layout::{LayoutTree, Position};
+
+use crate::style::parse_properties::parse_css_position;
+use crate::{LayoutDocument, TaffyLayouter};
+
+/// Padding box of a containing block, relative to the root of the tree
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Block {
+    pub(crate) pos: Point<f32>,
+    pub(crate) size: Size<f32>,
+}
+
+/// Moves absolutely positioned elements into their containing block. Taffy places an absolute element in the padding
+/// box of its parent, so an element whose parent is not positioned is laid out again in the padding box of its
+/// nearest positioned ancestor. Fixed elements are laid out in the viewport, their scroll offset is applied when
+/// painting.
+pub fn compute_positioned_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
+    tree: &mut LayoutDocument<C>,
+    root: <C::LayoutTree as LayoutTree<C>>::NodeId,
+    viewport: Size<f32>,
+) {
+    let viewport = Block {
+        pos: Point::ZERO,
+        size: viewport,
+    };
+
+    let Some(layout) = tree.0.get_layout(root).map(|layout| layout.0) else {
+        return;
+    };
+    let positioned = tree
+        .0
+        .get_node_mut(root)
+        .is_some_and(|node| parse_css_position(node).is_positioned());
+
+    // Without positioned ancestors, absolute elements are placed in the initial containing block
+    let block = if positioned {
+        padding_box(layout.location, &layout)
+    } else {
+        viewport
+    };
+
+    place_children(tree, root, layout.location, positioned, block, viewport);
+}
+
+fn place_children<C: HasLayouter<Layouter = TaffyLayouter>>(
+    tree: &mut LayoutDocument<C>,
+    parent: <C::LayoutTree as LayoutTree<C>>::NodeId,
+    parent_pos: Point<f32>,
+    parent_positioned: bool,
+    block: Block,
+    viewport: Block,
+) {
+    let Some(children) = tree.0.children(parent) else {
+        return;
+    };
+
+    for child in children {
+        let Some(node) = tree.0.get_node_mut(child) else {
+            continue;
+        };
+        let position = parse_css_position(node);
+
+        if let Some(block) = containing_block(position, parent_positioned, block, viewport) {
+            layout_in_block(tree, child, block, parent_pos);
+        }
+
+        let Some(layout) = tree.0.get_layout(child).map(|layout| layout.0) else {
+            continue;
+        };
+        let pos = Point {
+            x: parent_pos.x + layout.location.x,
+            y: parent_pos.y + layout.location.y,
+        };
+
+        let positioned = position.is_positioned();
+        let block = if positioned { padding_box(pos, &layout) } else { block };
+
+        place_children(tree, child, pos, positioned, block, viewport);
+    }
+}
+
+/// Returns the block an element must be laid out in again, or `None` when taffy already placed it right. `block` is
+/// the padding box of the nearest positioned ancestor, or the initial containing block without one.
+pub(crate) fn containing_block(
+    position: Position,
+    parent_positioned: bool,
+    block: Block,
+    viewport: Block,
+) -> Option<Block> {
+    match position {
+        Position::Fixed => Some(viewport),
+        Position::Absolute if !parent_positioned => Some(block),
+        _ => None,
+    }
+}
+
+/// Returns true when the insets of an element are used in the layout. The insets of a sticky element only apply when
+/// the page is scrolled, which is handled when painting.
+pub(crate) fn has_layout_insets(position: Position) -> bool {
+    matches!(position, Position::Relative | Position::Absolute | Position::Fixed)
+}
+
+/// Lays out an absolutely positioned element again in `block`, like taffy does in the padding box of the parent. On
+/// an axis without insets the element keeps its static position.
+pub(crate) fn layout_in_block<C: HasLayouter<Layouter = TaffyLayouter>>(
+    tree: &mut LayoutDocument<C>,
+    id: <C::LayoutTree as LayoutTree<C>>::NodeId,
+    block: Block,
+    parent_pos: Point<f32>,
+) {
+    let Some(layout) = tree.0.get_layout(id).map(|layout| layout.0) else {
+        return;
+    };
+
+    let style = tree.get_taffy_style_no_update(id);
+    let area = block.size;
+
+    let margin = style.margin.map(|margin| margin.resolve_or_zero(Some(area.width)));
+    let padding = style.padding.resolve_or_zero(Some(area.width));
+    let border = style.border.resolve_or_zero(Some(area.width));
+
+    let left = style.inset.left.maybe_resolve(area.width);
+    let right = style.inset.right.maybe_resolve(area.width);
+    let top = style.inset.top.maybe_resolve(area.height);
+    let bottom = style.inset.bottom.maybe_resolve(area.height);
+
+    let box_sizing = if style.box_sizing == BoxSizing::ContentBox {
+        Size {
+            width: padding.left + padding.right + border.left + border.right,
+            height: padding.top + padding.bottom + border.top + border.bottom,
+        }
+    } else {
+        Size::ZERO
+    };
+
+    let min_size = style.min_size.maybe_resolve(area).maybe_add(box_sizing);
+    let max_size = style.max_size.maybe_resolve(area).maybe_add(box_sizing);
+    let mut known = style
+        .size
+        .maybe_resolve(area)
+        .maybe_apply_aspect_ratio(style.aspect_ratio)
+        .maybe_add(box_sizing)
+        .maybe_clamp(min_size, max_size);
+
+    known.width = stretched_size(known.width, (left, right), area.width, (margin.left, margin.right));
+    known.height = stretched_size(known.height, (top, bottom), area.height, (margin.top, margin.bottom));
+
+    let node = NodeId::from(id.into());
+    let output = tree.compute_child_layout(
+        node,
+        LayoutInput {
+            known_dimensions: known,
+            parent_size: area.map(Some),
+            available_space: area.map(AvailableSpace::Definite),
+            sizing_mode: SizingMode::ContentSize,
+            axis: RequestedAxis::Both,
+            run_mode: RunMode::PerformLayout,
+            vertical_margins_are_collapsible: Line::FALSE,
+        },
+    );
+    let size = known.unwrap_or(output.size).maybe_clamp(min_size, max_size);
+
+    let x = inset_position(
+        (left, right),
+        (block.pos.x, area.width),
+        size.width,
+        (margin.left, margin.right),
+        parent_pos.x + layout.location.x,
+    );
+    let y = inset_position(
+        (top, bottom),
+        (block.pos.y, area.height),
+        size.height,
+        (margin.top, margin.bottom),
+        parent_pos.y + layout.location.y,
+    );
+
+    tree.set_unrounded_layout(
+        node,
+        &Layout {
+            size,
+            content_size: output.content_size,
+            location: Point {
+                x: x - parent_pos.x,
+                y: y - parent_pos.y,
+            },
+            ..layout
+        },
+    );
+}
+
+/// Resolves the size on one axis. An element without a size and with insets on both sides is stretched between them.
+fn stretched_size(
+    known: Option<f32>,
+    insets: (Option<f32>, Option<f32>),
+    area: f32,
+    margin: (f32, f32),
+) -> Option<f32> {
+    match (known, insets) {
+        (None, (Some(start), Some(end))) => Some((area - margin.0 - margin.1 - start - end).max(0.0)),
+        _ => known,
+    }
+}
+
+/// Resolves the position on one axis within the block, given as (start, length). The start inset wins from the end
+/// inset, and without insets the element keeps its static position.
+fn inset_position(
+    insets: (Option<f32>, Option<f32>),
+    block: (f32, f32),
+    size: f32,
+    margin: (f32, f32),
+    static_pos: f32,
+) -> f32 {
+    match insets {
+        (Some(start), _) => block.0 + start + margin.0,
+        (None, Some(end)) => block.0 + block.1 - size - end - margin.1,
+        (None, None) => static_pos,
+    }
+}
+
+fn padding_box(pos: Point<f32>, layout: &Layout) -> Block {
+    let border = layout.border;
+
+    Block {
+        pos: Point {
+            x: pos.x + border.left,
+            y: pos.y + border.top,
+        },
+        size: Size {
+            width: (layout.size.width - border.left - border.right).max(0.0),
+            height: (layout.size.height - border.top - border.bottom).max(0.0),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use taffy::Rect;
+
+    fn block(x: f32, y: f32, width: f32, height: f32) -> Block {
+        Block {
+            pos: Point { x, y },
+            size: Size { width, height },
+        }
+    }
+
+    #[test]
+    fn containing_blocks() {
+        let ancestor = block(10.0, 20.0, 300.0, 200.0);
+        let viewport = block(0.0, 0.0, 800.0, 600.0);
+
+        let found = |position, parent_positioned| {
+            containing_block(position, parent_positioned, ancestor, viewport).map(|block| block.pos)
+        };
+
+        // Fixed elements are always placed in the viewport
+        assert_eq!(found(Position::Fixed, false), Some(viewport.pos));
+        assert_eq!(found(Position::Fixed, true), Some(viewport.pos));
+
+        // Taffy places absolute elements in a positioned parent already
+        assert_eq!(found(Position::Absolute, false), Some(ancestor.pos));
+        assert_eq!(found(Position::Absolute, true), None);
+
+        assert_eq!(found(Position::Static, false), None);
+        assert_eq!(found(Position::Relative, false), None);
+        assert_eq!(found(Position::Sticky, false), None);
+    }
+
+    #[test]
+    fn sticky_insets_are_left_for_painting() {
+        assert!(has_layout_insets(Position::Relative));
+        assert!(has_layout_insets(Position::Absolute));
+        assert!(has_layout_insets(Position::Fixed));
+
+        assert!(!has_layout_insets(Position::Static));
+        assert!(!has_layout_insets(Position::Sticky));
+    }
+
+    #[test]
+    fn absolute_insets() {
+        let block = (10.0, 300.0);
+        let margin = (5.0, 7.0);
+
+        assert_eq!(inset_position((Some(20.0), None), block, 50.0, margin, 99.0), 35.0);
+        assert_eq!(inset_position((None, Some(20.0)), block, 50.0, margin, 99.0), 233.0);
+        // The start inset wins when both are given
+        assert_eq!(
+            inset_position((Some(20.0), Some(20.0)), block, 50.0, margin, 99.0),
+            35.0
+        );
+        // Without insets the element stays at its static position
+        assert_eq!(inset_position((None, None), block, 50.0, margin, 99.0), 99.0);
+    }
+
+    #[test]
+    fn stretched_between_insets() {
+        assert_eq!(
+            stretched_size(None, (Some(10.0), Some(20.0)), 300.0, (5.0, 5.0)),
+            Some(260.0)
+        );
+        assert_eq!(
+            stretched_size(None, (Some(200.0), Some(200.0)), 300.0, (0.0, 0.0)),
+            Some(0.0)
+        );
+
+        // An explicit size is not stretched, neither is an element with only one inset
+        assert_eq!(
+            stretched_size(Some(50.0), (Some(10.0), Some(20.0)), 300.0, (0.0, 0.0)),
+            Some(50.0)
+        );
+        assert_eq!(stretched_size(None, (Some(10.0), None), 300.0, (0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn fixed_insets_in_the_viewport() {
+        let viewport = block(0.0, 0.0, 800.0, 600.0);
+        let size = Size {
+            width: 100.0,
+            height: 40.0,
+        };
+
+        // `right: 0; bottom: 10px` places the element in the corner of the viewport, wherever its parent is
+        let x = inset_position(
+            (None, Some(0.0)),
+            (viewport.pos.x, viewport.size.width),
+            size.width,
+            (0.0, 0.0),
+            500.0,
+        );
+        let y = inset_position(
+            (None, Some(10.0)),
+            (viewport.pos.y, viewport.size.height),
+            size.height,
+            (0.0, 0.0),
+            900.0,
+        );
+
+        assert_eq!((x, y), (700.0, 550.0));
+    }
+
+    #[test]
+    fn padding_box_excludes_the_border() {
+        let mut layout = Layout::new();
+        layout.size = Size {
+            width: 200.0,
+            height: 100.0,
+        };
+        layout.border = Rect {
+            left: 1.0,
+            right: 2.0,
+            top: 3.0,
+            bottom: 4.0,
+        };
+
+        let block = padding_box(Point { x: 10.0, y: 20.0 }, &layout);
+        assert_eq!(block.pos, Point { x: 11.0, y: 23.0 });
+        assert_eq!(
+            block.size,
+            Size {
+                width: 197.0,
+                height: 93.0
+            }
+        );
+    }
+}
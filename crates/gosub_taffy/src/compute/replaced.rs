@@ -0,0 +1,177 @@
+use gosub_interface::config::HasLayouter;
+use gosub_interface::layout::{IntrinsicSize, LayoutTree, ReplacedText};
+use taffy::{
+    compute_leaf_layout, AvailableSpace, LayoutInput, LayoutOutput, Point, ResolveOrZero, RunMode, Size, Style,
+};
+
+use crate::compute::inline::{compute_replaced_list, compute_replaced_text};
+use crate::{LayoutDocument, TaffyLayouter};
+
+/// Size of replaced elements without any natural dimensions (CSS default object size)
+const DEFAULT_SIZE: Size<f32> = Size {
+    width: 300.0,
+    height: 150.0,
+};
+
+/// Computes the layout of a replaced element (for instance an inline `<svg>`). The element has no children
+/// that take part in the layout, so its size comes from CSS or from its natural dimensions.
+pub fn compute_replaced_layout(inputs: LayoutInput, style: &Style, intrinsic: IntrinsicSize) -> LayoutOutput {
+    compute_leaf_layout(inputs, style, |known, available| {
+        measure_replaced(intrinsic, known, available)
+    })
+}
+
+/// Computes the layout of a replaced element that shows a text, like a form control. A dimension the element has
+/// no natural size for is taken from the text, and the text is laid out in the content box.
+pub fn compute_replaced_text_layout<C: HasLayouter<Layouter = TaffyLayouter>>(
+    tree: &mut LayoutDocument<C>,
+    node_id: <C::LayoutTree as LayoutTree<C>>::NodeId,
+    inputs: LayoutInput,
+    mut intrinsic: IntrinsicSize,
+    text: &ReplacedText,
+) -> LayoutOutput {
+    if intrinsic.width.is_none() || intrinsic.height.is_none() {
+        let measured = compute_replaced_text(tree, node_id, text, None);
+
+        intrinsic.width = intrinsic.width.or(Some(measured.width + text.end_space));
+        intrinsic.height = intrinsic.height.or(Some(measured.height));
+    }
+
+    let style = tree.get_taffy_style(node_id);
+    let padding = style.padding.resolve_or_zero(inputs.parent_size.width);
+    let border = style.border.resolve_or_zero(inputs.parent_size.width);
+
+    let output = compute_replaced_layout(inputs, style, intrinsic);
+
+    if inputs.run_mode == RunMode::PerformLayout {
+        let origin = Point {
+            x: padding.left + border.left,
+            y: padding.top + border.top,
+        };
+        let area = Size {
+            width: (output.size.width - origin.x - padding.right - border.right).max(0.0),
+            height: (output.size.height - origin.y - padding.bottom - border.bottom).max(0.0),
+        };
+
+        compute_replaced_text(tree, node_id, text, Some((origin, area)));
+
+        if !text.list.is_empty() {
+            let list_origin = Point {
+                x: origin.x,
+                y: output.size.height,
+            };
+            compute_replaced_list(tree, node_id, &text.list, list_origin);
+        }
+    }
+
+    output
+}
+
+/// Resolves the content size of a replaced element. Dimensions set by CSS win, a missing dimension is derived
+/// from the other one through the ratio, and otherwise the natural size is used.
+fn measure_replaced(intrinsic: IntrinsicSize, known: Size<Option<f32>>, available: Size<AvailableSpace>) -> Size<f32> {
+    let ratio = intrinsic.ratio.filter(|r| *r > 0.0);
+
+    // A dimension set by CSS determines the other dimension through the ratio, before any natural dimension
+    let width = known
+        .width
+        .or_else(|| known.height.zip(ratio).map(|(height, ratio)| height * ratio))
+        .or(intrinsic.width);
+    let height = known
+        .height
+        .or_else(|| known.width.zip(ratio).map(|(width, ratio)| width / ratio))
+        .or(intrinsic.height);
+
+    match (width, height, ratio) {
+        (Some(width), Some(height), _) => Size { width, height },
+        (Some(width), None, Some(ratio)) => Size {
+            width,
+            height: width / ratio,
+        },
+        (None, Some(height), Some(ratio)) => Size {
+            width: height * ratio,
+            height,
+        },
+        (Some(width), None, None) => Size {
+            width,
+            height: DEFAULT_SIZE.height,
+        },
+        (None, Some(height), None) => Size {
+            width: DEFAULT_SIZE.width,
+            height,
+        },
+        (None, None, Some(ratio)) => {
+            // Only a ratio: the element fills the available width
+            let width = match available.width {
+                AvailableSpace::Definite(width) => width,
+                _ => DEFAULT_SIZE.width,
+            };
+
+            Size {
+                width,
+                height: width / ratio,
+            }
+        }
+        (None, None, None) => DEFAULT_SIZE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AVAILABLE: Size<AvailableSpace> = Size {
+        width: AvailableSpace::Definite(800.0),
+        height: AvailableSpace::MaxContent,
+    };
+
+    fn intrinsic(width: Option<f32>, height: Option<f32>, ratio: Option<f32>) -> IntrinsicSize {
+        IntrinsicSize { width, height, ratio }
+    }
+
+    #[test]
+    fn replaced_sizes() {
+        let size = measure_replaced(intrinsic(Some(100.0), Some(50.0), Some(2.0)), Size::NONE, AVAILABLE);
+        assert_eq!(
+            size,
+            Size {
+                width: 100.0,
+                height: 50.0
+            }
+        );
+
+        let known = Size {
+            width: Some(40.0),
+            height: None,
+        };
+        let size = measure_replaced(intrinsic(Some(100.0), Some(50.0), Some(2.0)), known, AVAILABLE);
+        assert_eq!(
+            size,
+            Size {
+                width: 40.0,
+                height: 20.0
+            }
+        );
+
+        let size = measure_replaced(intrinsic(None, Some(30.0), Some(2.0)), Size::NONE, AVAILABLE);
+        assert_eq!(
+            size,
+            Size {
+                width: 60.0,
+                height: 30.0
+            }
+        );
+
+        let size = measure_replaced(intrinsic(None, None, Some(4.0)), Size::NONE, AVAILABLE);
+        assert_eq!(
+            size,
+            Size {
+                width: 800.0,
+                height: 200.0
+            }
+        );
+
+        let size = measure_replaced(IntrinsicSize::default(), Size::NONE, AVAILABLE);
+        assert_eq!(size, DEFAULT_SIZE);
+    }
+}
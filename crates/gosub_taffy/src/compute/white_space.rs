@@ -0,0 +1,496 @@
+use gosub_interface::config::HasLayouter;
+use gosub_interface::css3::{CssProperty, CssValue};
+use gosub_interface::layout::LayoutNode;
+
+/// Zero width space, a line break opportunity that is not rendered
+const BREAK_OPPORTUNITY: char = '\u{200b}';
+/// Word joiner, which prevents a line break without being rendered
+const NO_BREAK: char = '\u{2060}';
+/// Advance of a space in `em`, used to convert a `tab-size` length into a number of spaces
+const SPACE_ADVANCE: f32 = 0.25;
+
+/// How white space in a text is collapsed (`white-space-collapse`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhiteSpaceCollapse {
+    /// Sequences of white space and segment breaks collapse into a single space
+    #[default]
+    Collapse,
+    /// All white space is removed
+    Discard,
+    /// All white space is preserved
+    Preserve,
+    /// Segment breaks are preserved, other white space collapses
+    PreserveBreaks,
+    /// Spaces and tabs are preserved, segment breaks become spaces
+    PreserveSpaces,
+    /// Like `Preserve`, but every space is a line break opportunity
+    BreakSpaces,
+}
+
+/// How words are broken at the end of a line (`word-break`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WordBreak {
+    #[default]
+    Normal,
+    /// Lines can be broken between any two letters
+    BreakAll,
+    /// CJK text is not broken inside words
+    KeepAll,
+}
+
+/// Letter case changes of `text-transform`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    Uppercase,
+    Lowercase,
+    Capitalize,
+}
+
+/// Value of `text-transform`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextTransform {
+    pub case: Option<Case>,
+    pub full_width: bool,
+    pub full_size_kana: bool,
+}
+
+/// Properties that control how the text of a node is turned into the text that is laid out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    pub collapse: WhiteSpaceCollapse,
+    /// Lines can be wrapped at soft wrap opportunities (`text-wrap-mode`)
+    pub wrap: bool,
+    pub transform: TextTransform,
+    /// Width of a tab in spaces
+    pub tab_size: usize,
+    pub word_break: WordBreak,
+    /// Words that do not fit on a line can be broken anywhere (`overflow-wrap: anywhere | break-word`)
+    pub overflow_wrap: bool,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            collapse: WhiteSpaceCollapse::default(),
+            wrap: true,
+            transform: TextTransform::default(),
+            tab_size: 8,
+            word_break: WordBreak::default(),
+            overflow_wrap: false,
+        }
+    }
+}
+
+impl TextStyle {
+    /// Reads the text properties of a (text) node. `font_size` resolves a `tab-size` length.
+    pub fn from_node<C: HasLayouter>(node: &mut impl LayoutNode<C>, font_size: f32) -> Self {
+        let mut style = Self::default();
+
+        if let Some(white_space) = node.get_property("white-space").and_then(|prop| prop.as_string()) {
+            (style.collapse, style.wrap) = match white_space {
+                "pre" => (WhiteSpaceCollapse::Preserve, false),
+                "nowrap" => (WhiteSpaceCollapse::Collapse, false),
+                "pre-wrap" => (WhiteSpaceCollapse::Preserve, true),
+                "break-spaces" => (WhiteSpaceCollapse::BreakSpaces, true),
+                "pre-line" => (WhiteSpaceCollapse::PreserveBreaks, true),
+                _ => (WhiteSpaceCollapse::Collapse, true),
+            };
+        }
+
+        // The longhands win over the `white-space` shorthand
+        if let Some(collapse) = node
+            .get_property("white-space-collapse")
+            .and_then(|prop| prop.as_string())
+        {
+            style.collapse = match collapse {
+                "discard" => WhiteSpaceCollapse::Discard,
+                "preserve" => WhiteSpaceCollapse::Preserve,
+                "preserve-breaks" => WhiteSpaceCollapse::PreserveBreaks,
+                "preserve-spaces" => WhiteSpaceCollapse::PreserveSpaces,
+                "break-spaces" => WhiteSpaceCollapse::BreakSpaces,
+                _ => WhiteSpaceCollapse::Collapse,
+            };
+        }
+
+        if let Some(wrap) = node.get_property("text-wrap").and_then(|prop| prop.as_string()) {
+            style.wrap = wrap != "nowrap";
+        }
+
+        if let Some(prop) = node.get_property("text-transform") {
+            let mut keywords = Vec::new();
+            if let Some(list) = prop.as_list() {
+                keywords.extend(list.iter().filter_map(|value| value.as_string()));
+            } else if let Some(keyword) = prop.as_string() {
+                keywords.push(keyword);
+            }
+
+            for keyword in keywords {
+                match keyword {
+                    "uppercase" => style.transform.case = Some(Case::Uppercase),
+                    "lowercase" => style.transform.case = Some(Case::Lowercase),
+                    "capitalize" => style.transform.case = Some(Case::Capitalize),
+                    "full-width" => style.transform.full_width = true,
+                    "full-size-kana" => style.transform.full_size_kana = true,
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(prop) = node.get_property("tab-size") {
+            let spaces = match prop.as_number() {
+                Some(spaces) => spaces,
+                None => prop.unit_to_px() / (font_size * SPACE_ADVANCE),
+            };
+
+            style.tab_size = spaces.round().max(0.0) as usize;
+        }
+
+        match node.get_property("word-break").and_then(|prop| prop.as_string()) {
+            Some("break-all") => style.word_break = WordBreak::BreakAll,
+            Some("keep-all") => style.word_break = WordBreak::KeepAll,
+            Some("break-word") => style.overflow_wrap = true,
+            _ => {}
+        }
+
+        // `word-wrap` is the legacy name of `overflow-wrap`
+        for name in ["overflow-wrap", "word-wrap"] {
+            if let Some(wrap) = node.get_property(name).and_then(|prop| prop.as_string()) {
+                style.overflow_wrap |= wrap == "anywhere" || wrap == "break-word";
+            }
+        }
+
+        style
+    }
+}
+
+/// Turns the text of the text nodes in an inline formatting context into the text that is laid out. White space is
+/// processed across the boundaries of the nodes, so a space at the end of one node collapses with a space at the
+/// start of the next one.
+#[derive(Debug)]
+pub struct WhiteSpaceProcessor {
+    /// Collapsible space that is only added when more content follows, so spaces at the end of a line disappear
+    pending: Option<char>,
+    /// No content is added since the start of the paragraph or the last preserved line break. Collapsible spaces at
+    /// the start of a line are removed.
+    line_start: bool,
+    /// Number of characters since the last line break, for tab stops
+    column: usize,
+    /// Last added character, to find the start of words and to add break opportunities between letters
+    previous: Option<char>,
+}
+
+impl Default for WhiteSpaceProcessor {
+    fn default() -> Self {
+        Self {
+            pending: None,
+            line_start: true,
+            column: 0,
+            previous: None,
+        }
+    }
+}
+
+impl WhiteSpaceProcessor {
+    /// Processes the text of a node and appends it to `out`
+    pub fn push(&mut self, text: &str, style: &TextStyle, out: &mut String) {
+        // A space that can not be wrapped is a no-break space
+        let space = if style.wrap { ' ' } else { '\u{a0}' };
+
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            // A CR LF pair is a single segment break
+            let c = match c {
+                '\r' if chars.peek() == Some(&'\n') => continue,
+                '\r' => '\n',
+                c => c,
+            };
+
+            if !matches!(c, ' ' | '\t' | '\n' | '\u{c}') {
+                self.flush(out);
+                self.push_char(c, style, out);
+                continue;
+            }
+
+            match style.collapse {
+                WhiteSpaceCollapse::Discard => {}
+                WhiteSpaceCollapse::PreserveBreaks if c == '\n' => {
+                    // Collapsible spaces around a preserved line break are removed
+                    self.pending = None;
+                    self.add('\n', out);
+                }
+                WhiteSpaceCollapse::Collapse | WhiteSpaceCollapse::PreserveBreaks => {
+                    if !self.line_start {
+                        self.pending = Some(space);
+                    }
+                }
+                WhiteSpaceCollapse::Preserve | WhiteSpaceCollapse::PreserveSpaces | WhiteSpaceCollapse::BreakSpaces => {
+                    self.flush(out);
+
+                    match c {
+                        '\n' if style.collapse != WhiteSpaceCollapse::PreserveSpaces => self.add('\n', out),
+                        '\t' if style.tab_size > 0 => {
+                            let spaces = style.tab_size - self.column % style.tab_size;
+                            for _ in 0..spaces {
+                                self.add(space, out);
+                            }
+                        }
+                        '\t' => {}
+                        _ => {
+                            self.add(space, out);
+                            if style.collapse == WhiteSpaceCollapse::BreakSpaces && style.wrap {
+                                out.push(BREAK_OPPORTUNITY);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Content that is not text, like an inline box, is added at the end of `out`. A pending space is added before
+    /// it.
+    pub fn push_box(&mut self, out: &mut String) {
+        self.flush(out);
+        self.line_start = false;
+        self.column += 1;
+        self.previous = Some('\u{fffc}');
+    }
+
+    /// Adds the pending collapsible space, because content follows it
+    fn flush(&mut self, out: &mut String) {
+        if let Some(space) = self.pending.take() {
+            self.add(space, out);
+        }
+    }
+
+    /// Adds a character that is not white space, with the text transform and break opportunities of the style
+    fn push_char(&mut self, c: char, style: &TextStyle, out: &mut String) {
+        let word_start = self.previous.is_none_or(|previous| !previous.is_alphanumeric());
+
+        if let Some(previous) = self.previous.filter(|previous| !previous.is_whitespace()) {
+            match style.word_break {
+                WordBreak::BreakAll if style.wrap => out.push(BREAK_OPPORTUNITY),
+                WordBreak::KeepAll if is_cjk(previous) && is_cjk(c) => out.push(NO_BREAK),
+                _ => {}
+            }
+        }
+
+        let transform = style.transform;
+        let mut add = |c: char| {
+            let c = if transform.full_width { full_width(c) } else { c };
+            let c = if transform.full_size_kana { full_size_kana(c) } else { c };
+            self.add(c, out);
+        };
+
+        match transform.case {
+            Some(Case::Uppercase) => c.to_uppercase().for_each(&mut add),
+            Some(Case::Lowercase) => c.to_lowercase().for_each(&mut add),
+            Some(Case::Capitalize) if word_start => c.to_uppercase().for_each(&mut add),
+            _ => add(c),
+        }
+    }
+
+    fn add(&mut self, c: char, out: &mut String) {
+        out.push(c);
+
+        if c == '\n' {
+            self.line_start = true;
+            self.column = 0;
+        } else {
+            self.line_start = false;
+            self.column += 1;
+        }
+
+        self.previous = Some(c);
+    }
+}
+
+/// Returns true for the characters that are added to the text to control line breaking, which are not part of the
+/// text itself
+pub fn is_line_break_control(c: char) -> bool {
+    c == BREAK_OPPORTUNITY || c == NO_BREAK
+}
+
+/// Inserts a break opportunity between the letters of the words in `text`, for words that overflow their line
+pub fn break_words(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 2);
+
+    let mut previous: Option<char> = None;
+    for c in text.chars() {
+        if previous.is_some_and(|previous| !previous.is_whitespace() && previous != BREAK_OPPORTUNITY)
+            && !c.is_whitespace()
+            && c != BREAK_OPPORTUNITY
+        {
+            out.push(BREAK_OPPORTUNITY);
+        }
+
+        out.push(c);
+        previous = Some(c);
+    }
+
+    out
+}
+
+/// Returns true for ideographic and syllabic characters of Chinese, Japanese and Korean
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30ff}' | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{ac00}'..='\u{d7af}' | '\u{f900}'..='\u{faff}'
+    )
+}
+
+/// Maps a character to its full-width form
+fn full_width(c: char) -> char {
+    match c {
+        '!'..='~' => char::from_u32(c as u32 + 0xfee0).unwrap_or(c),
+        c => c,
+    }
+}
+
+/// Maps small kana to their full size form
+fn full_size_kana(c: char) -> char {
+    match c {
+        'ぁ' => 'あ',
+        'ぃ' => 'い',
+        'ぅ' => 'う',
+        'ぇ' => 'え',
+        'ぉ' => 'お',
+        'っ' => 'つ',
+        'ゃ' => 'や',
+        'ゅ' => 'ゆ',
+        'ょ' => 'よ',
+        'ゎ' => 'わ',
+        'ゕ' => 'か',
+        'ゖ' => 'け',
+        'ァ' => 'ア',
+        'ィ' => 'イ',
+        'ゥ' => 'ウ',
+        'ェ' => 'エ',
+        'ォ' => 'オ',
+        'ッ' => 'ツ',
+        'ャ' => 'ヤ',
+        'ュ' => 'ユ',
+        'ョ' => 'ヨ',
+        'ヮ' => 'ワ',
+        'ヵ' => 'カ',
+        'ヶ' => 'ケ',
+        'ｧ' => 'ｱ',
+        'ｨ' => 'ｲ',
+        'ｩ' => 'ｳ',
+        'ｪ' => 'ｴ',
+        'ｫ' => 'ｵ',
+        'ｯ' => 'ﾂ',
+        'ｬ' => 'ﾔ',
+        'ｭ' => 'ﾕ',
+        'ｮ' => 'ﾖ',
+        c => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(nodes: &[(&str, TextStyle)]) -> String {
+        let mut processor = WhiteSpaceProcessor::default();
+        let mut out = String::new();
+
+        for (text, style) in nodes {
+            processor.push(text, style, &mut out);
+        }
+
+        out
+    }
+
+    fn style(collapse: WhiteSpaceCollapse, wrap: bool) -> TextStyle {
+        TextStyle {
+            collapse,
+            wrap,
+            ..TextStyle::default()
+        }
+    }
+
+    #[test]
+    fn collapses_across_nodes() {
+        let normal = TextStyle::default();
+
+        assert_eq!(process(&[("a", normal), ("b", normal), ("c", normal)]), "abc");
+        assert_eq!(
+            process(&[("  hello \n ", normal), ("  world  ", normal)]),
+            "hello world"
+        );
+        assert_eq!(process(&[("\n\t  ", normal)]), "");
+        assert_eq!(
+            process(&[("one ", normal), ("two", style(WhiteSpaceCollapse::Collapse, false))]),
+            "one two"
+        );
+        assert_eq!(
+            process(&[("no  wrap", style(WhiteSpaceCollapse::Collapse, false))]),
+            "no\u{a0}wrap"
+        );
+    }
+
+    #[test]
+    fn preserves_white_space() {
+        let pre = style(WhiteSpaceCollapse::Preserve, true);
+        assert_eq!(process(&[("  a\r\n\tb  ", pre)]), "  a\n        b  ");
+
+        let tabs = TextStyle { tab_size: 4, ..pre };
+        assert_eq!(process(&[("ab\tc", tabs)]), "ab  c");
+
+        let pre_line = style(WhiteSpaceCollapse::PreserveBreaks, true);
+        assert_eq!(process(&[("  one   \n   two  ", pre_line)]), "one\ntwo");
+
+        let spaces = style(WhiteSpaceCollapse::PreserveSpaces, true);
+        assert_eq!(process(&[("a \nb", spaces)]), "a  b");
+
+        assert_eq!(process(&[(" a b ", style(WhiteSpaceCollapse::Discard, true))]), "ab");
+        assert_eq!(
+            process(&[("a  b", style(WhiteSpaceCollapse::Preserve, false))]),
+            "a\u{a0}\u{a0}b"
+        );
+    }
+
+    #[test]
+    fn transforms_text() {
+        let transform = |case, full_width| TextStyle {
+            transform: TextTransform {
+                case,
+                full_width,
+                full_size_kana: false,
+            },
+            ..TextStyle::default()
+        };
+
+        assert_eq!(
+            process(&[("straße", transform(Some(Case::Uppercase), false))]),
+            "STRASSE"
+        );
+        assert_eq!(process(&[("HeLLo", transform(Some(Case::Lowercase), false))]), "hello");
+        assert_eq!(
+            process(&[
+                ("hello wor", transform(Some(Case::Capitalize), false)),
+                ("ld (again)", transform(Some(Case::Capitalize), false))
+            ]),
+            "Hello World (Again)"
+        );
+        assert_eq!(process(&[("a1-b", transform(None, true))]), "ａ１－ｂ");
+    }
+
+    #[test]
+    fn break_opportunities() {
+        let break_all = TextStyle {
+            word_break: WordBreak::BreakAll,
+            ..TextStyle::default()
+        };
+        assert_eq!(process(&[("ab cd", break_all)]), "a\u{200b}b c\u{200b}d");
+
+        let keep_all = TextStyle {
+            word_break: WordBreak::KeepAll,
+            ..TextStyle::default()
+        };
+        assert_eq!(process(&[("日本 ab", keep_all)]), "日\u{2060}本 ab");
+
+        assert_eq!(break_words("abc de"), "a\u{200b}b\u{200b}c d\u{200b}e");
+    }
+}
@@ -12,8 +12,12 @@ use gosub_interface::font::HasFontManager;
 use gosub_interface::layout::{Layout as TLayout, LayoutCache, LayoutNode, LayoutTree, Layouter};
 use gosub_shared::geo::{Point, Rect, Size, SizeU32};
 use gosub_shared::types::Result;
+use gosub_shared::{timing_start, timing_stop};
 
+use crate::compute::float::{compute_float_layout, has_floats, update_has_floats};
 use crate::compute::inline::compute_inline_layout;
+use crate::compute::positioned::compute_positioned_layout;
+use crate::compute::replaced::{compute_replaced_layout, compute_replaced_text_layout};
 use crate::style::get_style_from_node;
 use crate::text::TextLayout;
 
@@ -99,6 +103,8 @@ pub struct TaffyLayouter;
 pub enum Display {
     Inline,
     InlineBlock,
+    /// `flow-root`, a block that establishes a new block formatting context
+    FlowRoot,
     Table,
     #[default]
     Taffy,
@@ -110,6 +116,8 @@ pub struct Cache {
     taffy: TaffyCache,
     style: Style,
     display: Display,
+    /// Whether the block formatting context of the node has floats
+    has_floats: bool,
 }
 
 impl Deref for Cache {
@@ -145,6 +153,8 @@ impl<B: HasLayouter<Layouter = TaffyLayouter> + HasFontManager> Layouter<B> for
         root: <B::LayoutTree as LayoutTree<B>>::NodeId,
         space: SizeU32,
     ) -> Result<()> {
+        let t_id = timing_start!("layout.compute");
+
         let size = taffy::Size {
             width: AvailableSpace::Definite(space.width as f32),
             height: AvailableSpace::Definite(space.height as f32),
@@ -161,6 +171,15 @@ impl<B: HasLayouter<Layouter = TaffyLayouter> + HasFontManager> Layouter<B> for
         // Now let taffy compute the layout of the tree.
         compute_root_layout(&mut tree, TaffyId::from(root.into()), size);
 
+        // Taffy places absolute elements in their parent, they are moved into their containing block afterwards
+        let viewport = taffy::Size {
+            width: space.width as f32,
+            height: space.height as f32,
+        };
+        compute_positioned_layout(&mut tree, root, viewport);
+
+        timing_stop!(t_id);
+
         Ok(())
     }
 }
@@ -173,14 +192,13 @@ impl TaffyLayouter {
         // Convert our CSS properties into Taffy properties and store them in a cache.
         tree.update_style(root);
 
-        let Some(children) = tree.0.children(root) else {
-            return;
-        };
-
         // Recursively precompute the style for all children of the current node.
-        for child in children {
+        for child in tree.0.children(root).unwrap_or_default() {
             Self::precompute_style(tree, <C::LayoutTree as LayoutTree<C>>::NodeId::from(child.into()));
         }
+
+        // The floats of the children are known now
+        update_has_floats(tree, root);
     }
 }
 
@@ -345,13 +363,19 @@ impl<C: HasLayouter<Layouter = TaffyLayouter>> LayoutPartialTree for LayoutDocum
         compute_cached_layout(self, node_id, inputs, |tree, node_id_taffy, inputs| {
             let node_id = <C::LayoutTree as LayoutTree<C>>::NodeId::from(node_id_taffy.into());
 
+            let mut intrinsic_size = None;
+            let mut replaced_text = None;
+
             if let Some(node) = tree.0.get_node_mut(node_id) {
                 // If we are an inline parent, we should compute the inline layout
                 if node.is_anon_inline_parent() {
                     println!("Node: {node_id:?} is inline parent");
                     // Any text nodes are always inline, so they are handled in this function
-                    return compute_inline_layout(tree, node_id, inputs);
+                    return compute_inline_layout(tree, node_id, inputs, None);
                 }
+
+                intrinsic_size = node.intrinsic_size();
+                replaced_text = node.replaced_text();
             }
 
             // let has_children = tree.0.child_count(node_id) > 0; //TODO: this isn't optimal, since we are now requesting the same node twice (up in get_cache and here)
@@ -359,8 +383,22 @@ impl<C: HasLayouter<Layouter = TaffyLayouter>> LayoutPartialTree for LayoutDocum
 
             // @TODO: somehow we should implement table layout here as well. This could be doable with a Grid layout aparently.
 
-            match style.display {
+            // Replaced elements are sized by their natural dimensions, regardless of their display type
+            if let Some(intrinsic_size) = intrinsic_size {
+                if style.display != TaffyDisplay::None {
+                    return match replaced_text {
+                        Some(text) => compute_replaced_text_layout(tree, node_id, inputs, intrinsic_size, &text),
+                        None => compute_replaced_layout(inputs, style, intrinsic_size),
+                    };
+                }
+            }
+
+            let display = style.display;
+
+            match display {
                 TaffyDisplay::None => compute_hidden_layout(tree, node_id_taffy),
+                // Taffy knows nothing about floats, block formatting contexts with floats are laid out by us
+                TaffyDisplay::Block if has_floats(tree, node_id) => compute_float_layout(tree, node_id, inputs),
                 TaffyDisplay::Block => compute_block_layout(tree, node_id_taffy, inputs),
                 TaffyDisplay::Flex => compute_flexbox_layout(tree, node_id_taffy, inputs),
                 TaffyDisplay::Grid => compute_grid_layout(tree, node_id_taffy, inputs),
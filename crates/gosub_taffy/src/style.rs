@@ -5,7 +5,7 @@ use gosub_interface::config::HasLayouter;
 use gosub_interface::layout::LayoutNode;
 
 mod parse;
-mod parse_properties;
+pub(crate) mod parse_properties;
 
 const SCROLLBAR_WIDTH: f32 = 16.0;
 
@@ -19,7 +19,8 @@ pub fn get_style_from_node<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> (St
     let size = parse_properties::parse_size(node);
     let min_size = parse_properties::parse_min_size(node);
     let max_size = parse_properties::parse_max_size(node);
-    let aspect_ratio = parse_properties::parse_aspect_ratio(node);
+    let aspect_ratio =
+        parse_properties::parse_aspect_ratio(node).or_else(|| node.intrinsic_size().and_then(|size| size.ratio));
     let margin = parse_properties::parse_margin(node);
     let padding = parse_properties::parse_padding(node);
     let border = parse_properties::parse_border(node);
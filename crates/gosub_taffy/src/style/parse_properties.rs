@@ -2,15 +2,19 @@ use regex::Regex;
 use taffy::prelude::*;
 use taffy::{Overflow, Point, TextAlign};
 
+use crate::compute::positioned::has_layout_insets;
 use crate::style::parse::{
     parse_align_c, parse_align_i, parse_dimension, parse_grid_auto, parse_grid_placement, parse_len, parse_len_auto,
     parse_tracking_sizing_function,
 };
 use gosub_interface::config::HasLayouter;
 use gosub_interface::css3::CssProperty;
-use gosub_interface::layout::LayoutNode;
+use gosub_interface::layout::{self, LayoutNode};
 
 pub fn parse_display<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> (Display, crate::Display) {
+    // Floated elements are laid out as blocks
+    let floated = parse_float(node) != CssFloat::None;
+
     let Some(display) = node.get_property("display") else {
         return (Display::Block, crate::Display::Taffy);
     };
@@ -24,6 +28,8 @@ pub fn parse_display<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> (Display,
         "block" => (Display::Block, crate::Display::Taffy),
         "flex" => (Display::Flex, crate::Display::Taffy),
         "grid" => (Display::Grid, crate::Display::Taffy),
+        "flow-root" => (Display::Block, crate::Display::FlowRoot),
+        "inline-block" | "inline" if floated => (Display::Block, crate::Display::Taffy),
         "inline-block" => (Display::Block, crate::Display::InlineBlock),
         "inline" => (Display::Block, crate::Display::Inline),
         "table" => (Display::Block, crate::Display::Table),
@@ -36,7 +42,10 @@ pub fn parse_overflow<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> Point<Ov
         match str {
             "visible" => Overflow::Visible,
             "hidden" => Overflow::Hidden,
+            "clip" => Overflow::Clip,
             "scroll" => Overflow::Scroll,
+            // The scrollbars of `auto` are painted over the content, so no space is reserved for them
+            "auto" | "overlay" => Overflow::Hidden,
             _ => Overflow::Visible,
         }
     }
@@ -60,26 +69,40 @@ pub fn parse_overflow<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> Point<Ov
         }
     }
 
+    // When one axis scrolls, the other one can not be visible
+    let scrolls = |overflow: Overflow| matches!(overflow, Overflow::Hidden | Overflow::Scroll);
+    if scrolls(overflow.x) && !scrolls(overflow.y) {
+        overflow.y = Overflow::Hidden;
+    } else if scrolls(overflow.y) && !scrolls(overflow.x) {
+        overflow.x = Overflow::Hidden;
+    }
+
     overflow
 }
 
-pub fn parse_position<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> Position {
-    let Some(position) = node.get_property("position") else {
-        return Position::Relative;
-    };
-
-    let Some(value) = position.as_string() else {
-        return Position::Relative;
-    };
+/// Returns the `position` of an element. Taffy only knows relative and absolute positioning, the containing blocks of
+/// absolute and fixed elements are resolved after the layout (see `compute::positioned`).
+pub fn parse_css_position<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> layout::Position {
+    layout::Position::from_keyword(node.get_property("position").and_then(|prop| prop.as_string()))
+}
 
-    match value {
-        "relative" => Position::Relative,
-        "absolute" => Position::Absolute,
+pub fn parse_position<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> Position {
+    match parse_css_position(node) {
+        layout::Position::Absolute | layout::Position::Fixed => Position::Absolute,
         _ => Position::Relative,
     }
 }
 
 pub fn parse_inset<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> Rect<LengthPercentageAuto> {
+    if !has_layout_insets(parse_css_position(node)) {
+        return Rect {
+            top: LengthPercentageAuto::Auto,
+            right: LengthPercentageAuto::Auto,
+            bottom: LengthPercentageAuto::Auto,
+            left: LengthPercentageAuto::Auto,
+        };
+    }
+
     Rect {
         top: parse_len_auto(node, "top"),
         right: parse_len_auto(node, "right"),
@@ -88,6 +111,48 @@ pub fn parse_inset<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> Rect<Length
     }
 }
 
+/// Value of the `float` property. Taffy has no floats, they are placed by `compute::float`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CssFloat {
+    None,
+    Left,
+    Right,
+}
+
+pub fn parse_float<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> CssFloat {
+    let Some(float) = node.get_property("float") else {
+        return CssFloat::None;
+    };
+
+    match float.as_string() {
+        Some("left") => CssFloat::Left,
+        Some("right") => CssFloat::Right,
+        _ => CssFloat::None,
+    }
+}
+
+/// Value of the `clear` property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CssClear {
+    None,
+    Left,
+    Right,
+    Both,
+}
+
+pub fn parse_clear<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> CssClear {
+    let Some(clear) = node.get_property("clear") else {
+        return CssClear::None;
+    };
+
+    match clear.as_string() {
+        Some("left") => CssClear::Left,
+        Some("right") => CssClear::Right,
+        Some("both") => CssClear::Both,
+        _ => CssClear::None,
+    }
+}
+
 pub fn parse_size<C: HasLayouter>(node: &mut impl LayoutNode<C>) -> Size<Dimension> {
     Size {
         width: parse_dimension(node, "width"),
@@ -1,5 +1,5 @@
 use gosub_interface::font::FontBlob;
-use gosub_interface::layout::{Decoration, TextLayout as TLayout};
+use gosub_interface::layout::{Decoration, TextCluster, TextLayout as TLayout};
 use gosub_shared::font::Glyph;
 use gosub_shared::geo::{Point, Size};
 use std::fmt;
@@ -21,6 +21,10 @@ pub struct TextLayout {
     pub size: Size,
     /// Coordinates of the text
     pub coords: Vec<i16>,
+    /// Text the glyphs are shaped from
+    pub text: String,
+    /// Clusters of the glyphs, from left to right
+    pub clusters: Vec<TextCluster>,
 }
 
 impl Debug for TextLayout {
@@ -62,4 +66,12 @@ impl TLayout for TextLayout {
     fn size(&self) -> Size {
         self.size
     }
+
+    fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn clusters(&self) -> &[TextCluster] {
+        &self.clusters
+    }
 }
@@ -1,5 +1,5 @@
 use smallvec::SmallVec;
-use vello::kurbo::{Arc, BezPath, Cap, Join, RoundedRectRadii, Stroke};
+use vello::kurbo::{Arc, BezPath, Cap, Join, Rect as KurboRect, RoundedRectRadii, Stroke};
 use vello::Scene;
 
 use crate::{Brush, Rect, Transform, VelloBackend};
@@ -555,6 +555,53 @@ impl TBorderRadius for BorderRadius {
     }
 }
 
+impl BorderRadius {
+    /// Returns the outline of `rect` with these corners. Unlike `RoundedRect`, the corners can be elliptical.
+    pub(crate) fn path(&self, rect: KurboRect) -> BezPath {
+        use std::f64::consts::{FRAC_PI_2, PI};
+
+        let (tl, tr, br, bl) = (
+            self.top_left.radii_f64(),
+            self.top_right.radii_f64(),
+            self.bottom_right.radii_f64(),
+            self.bottom_left.radii_f64(),
+        );
+
+        // Each corner is a quarter of an ellipse, starting at the end of the previous side
+        let corners = [
+            (
+                (rect.x1 - tr.0, rect.y0 + tr.1),
+                tr,
+                -FRAC_PI_2,
+                (rect.x1, rect.y1 - br.1),
+            ),
+            ((rect.x1 - br.0, rect.y1 - br.1), br, 0.0, (rect.x0 + bl.0, rect.y1)),
+            (
+                (rect.x0 + bl.0, rect.y1 - bl.1),
+                bl,
+                FRAC_PI_2,
+                (rect.x0, rect.y0 + tl.1),
+            ),
+            ((rect.x0 + tl.0, rect.y0 + tl.1), tl, PI, (rect.x1 - tr.0, rect.y0)),
+        ];
+
+        let mut path = BezPath::new();
+        path.move_to((rect.x0 + tl.0, rect.y0));
+        path.line_to((rect.x1 - tr.0, rect.y0));
+
+        for (center, radii, start, next) in corners {
+            if radii.0 > 0.0 && radii.1 > 0.0 {
+                let arc = Arc::new(center, radii, start, FRAC_PI_2, 0.0);
+                path.extend(arc.append_iter(0.1));
+            }
+            path.line_to(next);
+        }
+
+        path.close_path();
+        path
+    }
+}
+
 impl From<BorderRadius> for RoundedRectRadii {
     fn from(value: BorderRadius) -> Self {
         RoundedRectRadii::new(
@@ -2,7 +2,7 @@ use crate::{Convert, VelloBackend};
 use gosub_interface::render_backend::{ColorStop, ColorStops, Gradient as TGradient};
 use gosub_shared::geo::{Point, FP};
 use vello::peniko::color::DynamicColor;
-use vello::peniko::{ColorStop as VelloColorStop, ColorStops as VelloColorStops, Gradient as VelloGradient};
+use vello::peniko::{ColorStop as VelloColorStop, ColorStops as VelloColorStops, Extend, Gradient as VelloGradient};
 
 pub struct Gradient(pub(crate) VelloGradient);
 
@@ -51,6 +51,10 @@ impl TGradient<VelloBackend> for Gradient {
 
         Gradient(gradient)
     }
+
+    fn repeat(self) -> Self {
+        Gradient(self.0.with_extend(Extend::Repeat))
+    }
 }
 
 impl Convert<VelloColorStops> for ColorStops<VelloBackend> {
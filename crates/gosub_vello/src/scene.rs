@@ -1,7 +1,10 @@
-use gosub_interface::render_backend::{Point, RenderBackend, RenderRect, RenderText, Scene as TScene, FP};
+use gosub_interface::render_backend::{
+    BlendMode, ClipShape, Point, RenderBackend, RenderBlurredRect, RenderRect, RenderText, Scene as TScene, ShadowClip,
+    FP,
+};
 use std::fmt::{Debug, Formatter};
-use vello::kurbo::RoundedRect;
-use vello::peniko::Fill;
+use vello::kurbo::{Affine, Ellipse, Rect as KurboRect, RoundedRect, RoundedRectRadii, Shape};
+use vello::peniko::{Color, Compose, Fill, Mix};
 use vello::Scene as VelloScene;
 
 use crate::debug::text::render_text_simple;
@@ -35,8 +38,8 @@ impl TScene<VelloBackend> for Scene {
         let brush_transform = rect.brush_transform.as_ref().map(|t| t.0);
 
         if let Some(radius) = &rect.radius {
-            let shape = RoundedRect::from_rect(rect.rect.0, radius.clone());
-            self.0.fill(Fill::NonZero, affine, brush, brush_transform, &shape);
+            self.0
+                .fill(Fill::NonZero, affine, brush, brush_transform, &radius.path(rect.rect.0));
         } else {
             self.0.fill(Fill::NonZero, affine, brush, brush_transform, &rect.rect.0);
         }
@@ -57,6 +60,59 @@ impl TScene<VelloBackend> for Scene {
         Text::show(&mut self.0, text);
     }
 
+    fn draw_blurred_rect(&mut self, rect: &RenderBlurredRect<VelloBackend>) {
+        let radii = rect
+            .radius
+            .clone()
+            .map_or(RoundedRectRadii::from_single_radius(0.0), RoundedRectRadii::from);
+        let std_dev = f64::from(rect.std_dev.max(0.0));
+        let color = rect.color.0;
+
+        match &rect.clip {
+            ShadowClip::None => blurred_rect(&mut self.0, rect.rect.0, radii, color, std_dev),
+            ShadowClip::Outside(shape, radius) => {
+                let bounds = rect.rect.0.inflate(3.0 * std_dev, 3.0 * std_dev);
+
+                // The box is cut out of the shadow in a group of its own, so the content below stays intact
+                self.0
+                    .push_layer(vello::peniko::BlendMode::default(), 1.0, Affine::IDENTITY, &bounds);
+                blurred_rect(&mut self.0, rect.rect.0, radii, color, std_dev);
+
+                let cut_out = vello::peniko::BlendMode::new(Mix::Normal, Compose::DestOut);
+                self.0.push_layer(cut_out, 1.0, Affine::IDENTITY, &bounds);
+                fill_shape(&mut self.0, shape.0, radius.clone(), Color::BLACK);
+                self.0.pop_layer();
+
+                self.0.pop_layer();
+            }
+            ShadowClip::Inset(shape, radius) => {
+                let clip = radius
+                    .as_ref()
+                    .map_or_else(|| shape.0.to_path(0.1), |radius| radius.path(shape.0));
+
+                self.0
+                    .push_layer(vello::peniko::BlendMode::default(), 1.0, Affine::IDENTITY, &clip);
+                self.0.fill(Fill::NonZero, Affine::IDENTITY, color, None, &shape.0);
+
+                let cut_out = vello::peniko::BlendMode::new(Mix::Normal, Compose::DestOut);
+                self.0.push_layer(cut_out, 1.0, Affine::IDENTITY, &shape.0);
+                blurred_rect(&mut self.0, rect.rect.0, radii, Color::BLACK, std_dev);
+                self.0.pop_layer();
+
+                self.0.pop_layer();
+            }
+        }
+    }
+
+    fn draw_blurred_text(&mut self, text: &RenderText<VelloBackend>, std_dev: FP) {
+        if std_dev < 0.5 {
+            Text::show(&mut self.0, text);
+            return;
+        }
+
+        Text::show_blurred(&mut self.0, text, std_dev);
+    }
+
     fn debug_draw_simple_text(&mut self, text: &str, pos: Point, size: FP) {
         render_text_simple(self, text, pos, size);
     }
@@ -73,11 +129,116 @@ impl TScene<VelloBackend> for Scene {
         self.0.reset();
     }
 
+    fn push_layer(&mut self, clip: &ClipShape<VelloBackend>, opacity: FP, blend_mode: BlendMode) {
+        let mix = match blend_mode {
+            // A layer that only clips can skip the blending step
+            BlendMode::Normal if opacity >= 1.0 => Mix::Clip,
+            _ => mix(blend_mode),
+        };
+        let blend = vello::peniko::BlendMode::new(mix, Compose::SrcOver);
+
+        match clip {
+            ClipShape::None => {
+                let everything = KurboRect::new(-1e9, -1e9, 1e9, 1e9);
+                self.0.push_layer(blend, opacity, Affine::IDENTITY, &everything);
+            }
+            ClipShape::Rect(rect, None) => {
+                self.0.push_layer(blend, opacity, Affine::IDENTITY, &rect.0);
+            }
+            ClipShape::Rect(rect, Some(radius)) => {
+                self.0
+                    .push_layer(blend, opacity, Affine::IDENTITY, &radius.path(rect.0));
+            }
+            ClipShape::Ellipse {
+                center,
+                radius_x,
+                radius_y,
+            } => {
+                let center = (f64::from(center.x), f64::from(center.y));
+                let shape = Ellipse::new(center, (f64::from(*radius_x), f64::from(*radius_y)), 0.0);
+                self.0.push_layer(blend, opacity, Affine::IDENTITY, &shape);
+            }
+        }
+    }
+
+    fn pop_layer(&mut self) {
+        self.0.pop_layer();
+    }
+
     fn new() -> Self {
         VelloScene::new().into()
     }
 }
 
+/// Draws a blurred rectangle. Vello only blurs rectangles with a single radius, so with different radii each corner
+/// is drawn in a quarter of its own.
+fn blurred_rect(scene: &mut VelloScene, rect: KurboRect, radii: RoundedRectRadii, color: Color, std_dev: f64) {
+    if std_dev <= 0.0 {
+        scene.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            color,
+            None,
+            &RoundedRect::from_rect(rect, radii),
+        );
+        return;
+    }
+
+    if let Some(radius) = radii.as_single_radius() {
+        scene.draw_blurred_rounded_rect(Affine::IDENTITY, rect, color, radius, std_dev);
+        return;
+    }
+
+    // The quarters meet on whole pixels, so there are no seams between them
+    let outer = rect.inflate(3.0 * std_dev, 3.0 * std_dev);
+    let center = rect.center().round();
+
+    let quarters = [
+        (KurboRect::new(outer.x0, outer.y0, center.x, center.y), radii.top_left),
+        (KurboRect::new(center.x, outer.y0, outer.x1, center.y), radii.top_right),
+        (
+            KurboRect::new(center.x, center.y, outer.x1, outer.y1),
+            radii.bottom_right,
+        ),
+        (
+            KurboRect::new(outer.x0, center.y, center.x, outer.y1),
+            radii.bottom_left,
+        ),
+    ];
+
+    for (quarter, radius) in quarters {
+        scene.draw_blurred_rounded_rect_in(&quarter, Affine::IDENTITY, rect, color, radius, std_dev);
+    }
+}
+
+fn fill_shape(scene: &mut VelloScene, rect: KurboRect, radius: Option<crate::BorderRadius>, color: Color) {
+    match radius {
+        Some(radius) => scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &radius.path(rect)),
+        None => scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &rect),
+    }
+}
+
+fn mix(blend_mode: BlendMode) -> Mix {
+    match blend_mode {
+        BlendMode::Normal => Mix::Normal,
+        BlendMode::Multiply => Mix::Multiply,
+        BlendMode::Screen => Mix::Screen,
+        BlendMode::Overlay => Mix::Overlay,
+        BlendMode::Darken => Mix::Darken,
+        BlendMode::Lighten => Mix::Lighten,
+        BlendMode::ColorDodge => Mix::ColorDodge,
+        BlendMode::ColorBurn => Mix::ColorBurn,
+        BlendMode::HardLight => Mix::HardLight,
+        BlendMode::SoftLight => Mix::SoftLight,
+        BlendMode::Difference => Mix::Difference,
+        BlendMode::Exclusion => Mix::Exclusion,
+        BlendMode::Hue => Mix::Hue,
+        BlendMode::Saturation => Mix::Saturation,
+        BlendMode::Color => Mix::Color,
+        BlendMode::Luminosity => Mix::Luminosity,
+    }
+}
+
 impl From<VelloScene> for Scene {
     fn from(scene: VelloScene) -> Self {
         Self(scene)
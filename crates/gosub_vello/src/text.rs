@@ -3,8 +3,16 @@ use gosub_interface::font::FontBlob;
 use gosub_interface::layout::{Decoration, TextLayout};
 use gosub_interface::render_backend::{RenderText, Text as TText};
 use gosub_shared::geo::{NormalizedCoord, Point, FP};
+use skrifa::instance::{LocationRef, Size};
+use skrifa::outline::{DrawSettings, OutlinePen};
+use skrifa::raw::types::F2Dot14;
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::sync::Arc;
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap};
 use vello::kurbo::{Affine, Line, Stroke};
-use vello::peniko::{Blob, Brush, Color, Fill, Font as PenikoFont, StyleRef};
+use vello::peniko::{
+    BlendMode, Blob, Brush, Color, Compose, Fill, Font as PenikoFont, Image, ImageFormat, Mix, StyleRef,
+};
 use vello::Scene;
 use vello_encoding::Glyph;
 
@@ -20,11 +28,133 @@ pub struct Text {
 
 impl Text {
     pub(crate) fn show(scene: &mut Scene, render: &RenderText<VelloBackend>) {
-        let brush = &render.brush.0;
+        if matches!(render.brush.0, Brush::Solid(_)) && render.brush_transform.is_none() {
+            Self::draw(scene, render, &render.brush.0);
+            return;
+        }
+
+        // Glyphs are drawn in the coordinate space of the text, while gradients and images are positioned on the
+        // page. The glyphs are drawn as a mask instead, which the brush is painted into.
+        let transform = render.transform.map_or(Affine::IDENTITY, |t| t.0);
+        let height = render.rect.0.height();
+        let bounds = render.rect.0.inflate(height, height);
+
+        scene.push_layer(BlendMode::default(), 1.0, transform, &bounds);
+        Self::draw(scene, render, &Brush::Solid(Color::BLACK));
+
+        scene.push_layer(BlendMode::new(Mix::Normal, Compose::SrcIn), 1.0, transform, &bounds);
+        scene.fill(
+            Fill::NonZero,
+            transform,
+            &render.brush.0,
+            render.brush_transform.map(|t| t.0),
+            &bounds,
+        );
+        scene.pop_layer();
+
+        scene.pop_layer();
+    }
+
+    /// Draws the text with a gaussian blur, like a text shadow. Vello has no blur filter, so the glyphs are
+    /// rasterized into an alpha mask on the cpu, which is blurred and drawn as an image in the color of the text.
+    pub(crate) fn show_blurred(scene: &mut Scene, render: &RenderText<VelloBackend>, std_dev: FP) {
+        let Brush::Solid(color) = render.brush.0 else {
+            Self::show(scene, render);
+            return;
+        };
+
+        // Glyphs reach out of the text box (ie: descenders), and the blur spreads another 3 standard deviations
+        let rect = render.rect.0;
+        let margin = (rect.height() + 3.0 * f64::from(std_dev)).ceil();
+        let bounds = rect.inflate(margin, margin);
+
+        let width = bounds.width().ceil() as u32;
+        let height = bounds.height().ceil() as u32;
+        let Some(mut pixmap) = Pixmap::new(width, height) else {
+            return;
+        };
+
+        let origin_x = (rect.x0 - bounds.x0) as f32;
+        let origin_y = (rect.y0 - bounds.y0) as f32;
+        let paint = Paint::default();
+
+        for text in &render.text {
+            let x = origin_x + text.offset.x;
+            let y = origin_y + text.offset.y;
+
+            let Ok(font) = FontRef::from_index((*text.font_data.data).as_ref(), text.font_data.index) else {
+                continue;
+            };
+            let outlines = font.outline_glyphs();
+            let coords = text.coords.iter().map(|c| F2Dot14::from_bits(*c)).collect::<Vec<_>>();
+
+            for glyph in &text.glyphs {
+                let Some(outline) = outlines.get(GlyphId::new(glyph.id)) else {
+                    continue;
+                };
+
+                let mut pen = MaskPen(PathBuilder::new());
+                let settings = DrawSettings::unhinted(Size::new(text.fs), LocationRef::new(&coords));
+                if outline.draw(settings, &mut pen).is_err() {
+                    continue;
+                }
+                let Some(path) = pen.0.finish() else {
+                    continue;
+                };
+
+                // Outlines point upwards from the baseline, while the page points downwards
+                let transform = tiny_skia::Transform::from_row(1.0, 0.0, 0.0, -1.0, x + glyph.x, y + glyph.y);
+                pixmap.fill_path(&path, &paint, FillRule::Winding, transform, None);
+            }
+
+            let decoration = &text.decoration;
+            let line_width = decoration.width;
+            let text_width = rect.width() as f32;
+            let mut lines = vec![];
+            if decoration.underline {
+                lines.push(rect.height() as f32 + decoration.underline_offset);
+            }
+            if decoration.overline {
+                lines.push(0.0);
+            }
+            if decoration.line_through {
+                lines.push(rect.height() as f32 / 2.0);
+            }
+
+            for line_y in lines {
+                let line = tiny_skia::Rect::from_xywh(
+                    origin_x + decoration.x_offset,
+                    origin_y + line_y - line_width / 2.0,
+                    text_width - decoration.x_offset,
+                    line_width,
+                );
+                if let Some(line) = line {
+                    pixmap.fill_rect(line, &paint, tiny_skia::Transform::identity(), None);
+                }
+            }
+        }
+
+        let mask = pixmap.pixels().iter().map(|p| f32::from(p.alpha())).collect::<Vec<_>>();
+        let mask = gaussian_blur(&mask, width as usize, height as usize, std_dev);
+
+        let color = color.to_rgba8();
+        let data = mask
+            .iter()
+            .flat_map(|alpha| {
+                let alpha = (alpha * f32::from(color.a) / 255.0).round().clamp(0.0, 255.0) as u8;
+                [color.r, color.g, color.b, alpha]
+            })
+            .collect::<Vec<_>>();
+
+        let image = Image::new(Blob::new(Arc::new(data)), ImageFormat::Rgba8, width, height);
+        let transform = render.transform.map_or(Affine::IDENTITY, |t| t.0);
+        scene.draw_image(&image, transform * Affine::translate((bounds.x0, bounds.y0)));
+    }
+
+    fn draw(scene: &mut Scene, render: &RenderText<VelloBackend>, brush: &Brush) {
         let style: StyleRef = Fill::NonZero.into();
 
         let transform = render.transform.map_or(Affine::IDENTITY, |t| t.0);
-        let brush_transform = render.brush_transform.map(|t| t.0);
 
         let x = render.rect.0.x0;
         let y = render.rect.0.y0;
@@ -40,7 +170,6 @@ impl Text {
                 .draw_glyphs(&peniko_font)
                 .font_size(text.fs)
                 .transform(transform)
-                .glyph_transform(brush_transform)
                 .normalized_coords(&text.coords)
                 .brush(brush)
                 .draw(style, text.glyphs.iter().copied());
@@ -87,6 +216,63 @@ impl Text {
     }
 }
 
+/// Collects the outline of a glyph into a path for the alpha mask of blurred text
+struct MaskPen(PathBuilder);
+
+impl OutlinePen for MaskPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.0.quad_to(cx0, cy0, x, y);
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.0.cubic_to(cx0, cy0, cx1, cy1, x, y);
+    }
+
+    fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+/// Blurs an alpha mask with a gaussian blur, first horizontally and then vertically
+fn gaussian_blur(mask: &[f32], width: usize, height: usize, std_dev: FP) -> Vec<f32> {
+    let radius = (3.0 * std_dev).ceil() as isize;
+    let weights = (-radius..=radius)
+        .map(|offset| (-(offset * offset) as FP / (2.0 * std_dev * std_dev)).exp())
+        .collect::<Vec<_>>();
+    let total = weights.iter().sum::<f32>();
+    let weights = weights.iter().map(|w| w / total).collect::<Vec<_>>();
+
+    let blur = |src: &[f32], idx: &dyn Fn(usize, usize) -> usize, len: usize, lines: usize| {
+        let mut dst = vec![0.0; src.len()];
+        for line in 0..lines {
+            for pos in 0..len {
+                dst[idx(line, pos)] = weights
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(k, weight)| {
+                        let src_pos = pos as isize + k as isize - radius;
+                        (0..len as isize)
+                            .contains(&src_pos)
+                            .then(|| src[idx(line, src_pos as usize)] * weight)
+                    })
+                    .sum();
+            }
+        }
+        dst
+    };
+
+    let horizontal = blur(mask, &|y, x| y * width + x, width, height);
+    blur(&horizontal, &|x, y| y * width + x, height, width)
+}
+
 impl TText for Text {
     fn new(layout: &impl TextLayout) -> Self {
         let glyphs = layout
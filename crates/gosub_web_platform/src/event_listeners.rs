@@ -1,5 +1,5 @@
 use crate::callback::{Callback, FutureExecutor};
-use gosub_interface::input::{InputEvent, MouseButton};
+use gosub_interface::input::{InputEvent, Key, MouseButton};
 use gosub_shared::geo::Point;
 use std::fmt::Debug;
 
@@ -19,7 +19,7 @@ pub struct MouseButtonEvent {
 
 #[derive(Debug, Clone, Copy)]
 pub struct KeyboardEvent {
-    pub key: char,
+    pub key: Key,
 }
 
 #[derive(Debug, Clone, Copy)]
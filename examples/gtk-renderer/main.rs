@@ -15,6 +15,7 @@ use gosub_interface::config::{
     HasChrome, HasCssSystem, HasDocument, HasHtmlParser, HasLayouter, HasRenderBackend, HasRenderTree, HasTreeDrawer,
     ModuleConfiguration,
 };
+use gosub_interface::draw::FindResult;
 use gosub_interface::font::HasFontManager;
 use gosub_interface::instance::{Handles, InstanceId};
 use gosub_interface::render_backend::RenderBackend;
@@ -84,6 +85,10 @@ impl ChromeHandle<Config> for GtkChromeHandle {
         info!("Drawing scene");
         self.0.unbounded_send(scene).unwrap();
     }
+
+    fn find_result(&self, result: FindResult, _: InstanceId) {
+        info!("Found {} matches, active: {:?}", result.matches, result.active);
+    }
 }
 
 fn main() -> glib::ExitCode {
@@ -1,12 +1,12 @@
 use crate::window::{Window, WindowState};
 use gosub_instance::{DebugEvent, InstanceMessage};
 use gosub_interface::config::ModuleConfiguration;
-use gosub_interface::input::{InputEvent, MouseButton};
+use gosub_interface::input::{InputEvent, Key, MouseButton};
 use gosub_interface::render_backend::{Point, RenderBackend, SizeU32, FP};
 use gosub_shared::types::Result;
-use winit::event::{ElementState, MouseScrollDelta, WindowEvent};
+use winit::event::{ElementState, KeyEvent, MouseScrollDelta, WindowEvent};
 use winit::event_loop::ActiveEventLoop;
-use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
+use winit::keyboard::{KeyCode, ModifiersState, NamedKey, PhysicalKey};
 
 impl<C: ModuleConfiguration> Window<'_, C> {
     pub fn event(&mut self, el: &ActiveEventLoop, backend: &mut C::RenderBackend, event: WindowEvent) -> Result<()> {
@@ -72,22 +72,46 @@ impl<C: ModuleConfiguration> Window<'_, C> {
             }
 
             WindowEvent::KeyboardInput { event, .. } => {
-                let Some(tab) = self.tabs.get_current_tab() else {
+                if self.find_key(&event)? {
                     return Ok(());
-                };
+                }
 
-                let winit::keyboard::Key::Character(code) = event.logical_key else {
+                let Some(tab) = self.tabs.get_current_tab() else {
                     return Ok(());
                 };
 
-                let Some(code) = code.to_string().chars().next() else {
-                    return Ok(());
+                let key = match &event.logical_key {
+                    winit::keyboard::Key::Character(code) => {
+                        let Some(code) = code.chars().next() else {
+                            return Ok(());
+                        };
+
+                        Key::Character(code)
+                    }
+                    winit::keyboard::Key::Named(named) => match named {
+                        NamedKey::Space => Key::Character(' '),
+                        NamedKey::Backspace => Key::Backspace,
+                        NamedKey::Delete => Key::Delete,
+                        NamedKey::Enter => Key::Enter,
+                        NamedKey::Tab => Key::Tab,
+                        NamedKey::Escape => Key::Escape,
+                        NamedKey::ArrowLeft => Key::ArrowLeft,
+                        NamedKey::ArrowRight => Key::ArrowRight,
+                        NamedKey::ArrowUp => Key::ArrowUp,
+                        NamedKey::ArrowDown => Key::ArrowDown,
+                        NamedKey::Home => Key::Home,
+                        NamedKey::End => Key::End,
+                        NamedKey::Shift => Key::Shift,
+                        NamedKey::Control => Key::Control,
+                        _ => return Ok(()),
+                    },
+                    _ => return Ok(()),
                 };
 
                 let input = match event.state {
                     ElementState::Pressed => InputEvent::KeyboardDown,
                     ElementState::Released => InputEvent::KeyboardUp,
-                }(code);
+                }(key);
 
                 tab.tx.blocking_send(InstanceMessage::Input(input))?;
 
@@ -216,4 +240,100 @@ impl<C: ModuleConfiguration> Window<'_, C> {
 
         Ok(())
     }
+
+    /// Handles the keys of the find bar: Ctrl+F opens it, F3 and Enter go to the next match and Shift+F3 and
+    /// Shift+Enter to the previous one, and Escape closes it. Returns true when the key is used by the find bar, so
+    /// it does not go to the page.
+    fn find_key(&mut self, event: &KeyEvent) -> Result<bool> {
+        let mods = self.mods.state();
+        let pressed = event.state == ElementState::Pressed;
+
+        let control = mods.contains(ModifiersState::CONTROL);
+        if control && event.physical_key == PhysicalKey::Code(KeyCode::KeyF) {
+            if pressed && self.find.is_none() {
+                self.find = Some(String::new());
+                self.set_find_title();
+            }
+            return Ok(true);
+        }
+
+        let forward = !mods.contains(ModifiersState::SHIFT);
+        if event.physical_key == PhysicalKey::Code(KeyCode::F3) {
+            if pressed {
+                self.send_find(forward)?;
+            }
+            return Ok(true);
+        }
+
+        let Some(query) = &mut self.find else {
+            return Ok(false);
+        };
+
+        match &event.logical_key {
+            winit::keyboard::Key::Named(NamedKey::Escape) => {
+                if pressed {
+                    // An empty query removes the highlights
+                    query.clear();
+                    self.send_find(true)?;
+                    self.find = None;
+                    self.set_find_title();
+                }
+            }
+            winit::keyboard::Key::Named(NamedKey::Enter) => {
+                if pressed {
+                    self.send_find(forward)?;
+                }
+            }
+            winit::keyboard::Key::Named(NamedKey::Backspace) => {
+                if pressed {
+                    query.pop();
+                    self.send_find(true)?;
+                    self.set_find_title();
+                }
+            }
+            winit::keyboard::Key::Named(NamedKey::Space) if !control => {
+                if pressed {
+                    query.push(' ');
+                    self.send_find(true)?;
+                    self.set_find_title();
+                }
+            }
+            winit::keyboard::Key::Character(text) if !control => {
+                if pressed {
+                    query.push_str(text);
+                    self.send_find(true)?;
+                    self.set_find_title();
+                }
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Searches the page of the current tab for the query of the find bar
+    fn send_find(&mut self, forward: bool) -> Result<()> {
+        let Some(query) = self.find.clone() else {
+            return Ok(());
+        };
+        let Some(tab) = self.tabs.get_current_tab() else {
+            return Ok(());
+        };
+
+        tab.tx.blocking_send(InstanceMessage::Find {
+            query,
+            case_sensitive: false,
+            forward,
+        })?;
+
+        Ok(())
+    }
+
+    /// Shows the query of the find bar in the title of the window
+    fn set_find_title(&self) {
+        match &self.find {
+            Some(query) => self.window.set_title(&format!("Gosub Browser - Find: {query}")),
+            None => self.window.set_title("Gosub Browser"),
+        }
+    }
 }
@@ -18,7 +18,7 @@ use gosub_rendering::render_tree::RenderTree;
 use gosub_shared::types::Result;
 use gosub_taffy::TaffyLayouter;
 use gosub_vello::VelloBackend;
-use log::LevelFilter;
+use log::{info, LevelFilter};
 use simple_logger::SimpleLogger;
 use url::Url;
 
@@ -28,6 +28,7 @@ pub mod tabs;
 pub mod window;
 use gosub_instance::DebugEvent;
 use gosub_interface::chrome::ChromeHandle;
+use gosub_interface::draw::FindResult;
 use gosub_interface::font::HasFontManager;
 use gosub_interface::instance::InstanceId;
 use gosub_interface::render_backend::RenderBackend;
@@ -98,6 +99,13 @@ impl<C: HasRenderBackend> ChromeHandle<C> for WinitEventLoopHandle<C> {
             .proxy
             .send_event(CustomEventInternal::DrawScene(scene, size, instance, self.window));
     }
+
+    fn find_result(&self, result: FindResult, instance: InstanceId) {
+        info!(
+            "Found {} matches in instance {}, active: {:?}",
+            result.matches, instance.0, result.active
+        );
+    }
 }
 
 fn main() -> Result<()> {
@@ -167,6 +175,29 @@ fn main() -> Result<()> {
                     println!("{node}");
                 }
 
+                "a11y" => {
+                    let (sender, receiver) = mpsc::channel();
+
+                    if let Err(e) = p.send_event(CustomEventInternal::Debug(DebugEvent::SendAccessibilityTree(sender)))
+                    {
+                        eprintln!("Error sending event: {e:?}");
+                        continue;
+                    }
+
+                    let tree = match receiver.recv() {
+                        Ok(tree) => tree,
+                        Err(e) => {
+                            eprintln!("Error receiving accessibility tree: {e:?}");
+                            continue;
+                        }
+                    };
+
+                    match serde_json::to_string_pretty(&tree) {
+                        Ok(json) => println!("{json}"),
+                        Err(e) => eprintln!("Error serializing accessibility tree: {e:?}"),
+                    }
+                }
+
                 "add" => {
                     if let Err(e) = p.send_event(CustomEventInternal::Debug(DebugEvent::SelectElement(u64::MAX))) {
                         eprintln!("Error sending event: {e:?}");
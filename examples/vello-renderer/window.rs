@@ -51,6 +51,8 @@ pub struct Window<'a, C: ModuleConfiguration> {
     pub(crate) renderer_data: <C::RenderBackend as RenderBackend>::WindowData<'a>,
     pub(crate) tabs: Tabs,
     pub(crate) mods: Modifiers,
+    /// Query of the find bar while it is open. Typed characters go to the query instead of the page.
+    pub(crate) find: Option<String>,
     #[allow(dead_code)]
     pub(crate) handles: Handles<C>,
 }
@@ -100,6 +102,7 @@ impl<C: ModuleConfiguration<ChromeHandle = WinitEventLoopHandle<C>>> Window<'_,
             renderer_data,
             tabs: Tabs::default(),
             mods: Modifiers::default(),
+            find: None,
             handles,
         })
     }
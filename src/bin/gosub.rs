@@ -0,0 +1,548 @@
+use anyhow::{anyhow, bail};
+use clap::{Args, Parser, Subcommand};
+use gosub_css3::matcher::styling::query_selector_all;
+use gosub_css3::stylesheet::CssStylesheet;
+use gosub_css3::system::Css3System;
+use gosub_css3::tokenizer::{TokenType, Tokenizer};
+use gosub_css3::Css3;
+use gosub_html5::document::builder::DocumentBuilderImpl;
+use gosub_html5::document::document_impl::DocumentImpl;
+use gosub_html5::document::fragment::DocumentFragmentImpl;
+use gosub_html5::document::loader::BlockingResourceLoader;
+use gosub_html5::parser::Html5Parser;
+use gosub_interface::config::{HasCssSystem, HasDocument, HasHtmlParser, HasLayouter, HasRenderTree};
+use gosub_interface::css3::CssOrigin;
+use gosub_interface::document::{Document, DocumentBuilder};
+use gosub_interface::font::HasFontManager;
+use gosub_interface::layout::{Layout, Layouter};
+use gosub_interface::node::{CommentDataType, DocTypeDataType, ElementDataType, Node, NodeType, TextDataType};
+use gosub_rendering::background::Edges;
+use gosub_rendering::render_tree::{RenderNodeData, RenderTree};
+use gosub_shared::byte_stream::{ByteStream, Encoding, Location};
+use gosub_shared::config::ParserConfig;
+use gosub_shared::errors::CssError;
+use gosub_shared::geo::{Point, SizeU32};
+use gosub_shared::node::NodeId;
+use gosub_shared::timing::Scale;
+use gosub_shared::types::{ParseError, Result};
+use gosub_shared::{timing_display, timing_export};
+use gosub_taffy::TaffyLayouter;
+use serde_json::{json, Value};
+use simple_logger::SimpleLogger;
+use std::fs;
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Instant;
+use url::Url;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Config;
+
+impl HasCssSystem for Config {
+    type CssSystem = Css3System;
+}
+impl HasDocument for Config {
+    type Document = DocumentImpl<Self>;
+    type DocumentFragment = DocumentFragmentImpl<Self>;
+    type DocumentBuilder = DocumentBuilderImpl;
+}
+
+impl HasHtmlParser for Config {
+    type HtmlParser = Html5Parser<'static, Self>;
+}
+
+impl HasLayouter for Config {
+    type Layouter = TaffyLayouter;
+    type LayoutTree = RenderTree<Self>;
+}
+
+impl HasRenderTree for Config {
+    type RenderTree = RenderTree<Self>;
+}
+
+impl HasFontManager for Config {
+    type FontManager = gosub_fontmanager::FontManager;
+}
+
+/// Inspects how gosub parses, styles and lays out a page
+#[derive(Parser)]
+#[command(name = "gosub", version)]
+struct Cli {
+    /// Enable debug logging
+    #[arg(short, long, global = true)]
+    debug: bool,
+
+    /// Print the timings of all phases when done
+    #[arg(long, global = true)]
+    timings: bool,
+
+    /// Write a Chrome trace of the timings to this file (viewable in Perfetto)
+    #[arg(long, global = true, value_name = "FILE")]
+    trace: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the DOM tree with the id of every node
+    Dom(Input),
+    /// Print the text content of the page
+    Text(Input),
+    /// Print the computed style of a node
+    Style {
+        #[command(flatten)]
+        input: Input,
+        /// Id of the node, as printed by the `dom` command
+        #[arg(long, conflicts_with = "selector")]
+        node: Option<usize>,
+        /// CSS selector of the node. The first matching element is used.
+        #[arg(long)]
+        selector: Option<String>,
+    },
+    /// Print the layout box tree with the geometry of every box as JSON
+    Layout {
+        #[command(flatten)]
+        input: Input,
+        #[command(flatten)]
+        viewport: Viewport,
+    },
+    /// Print the render tree after layout
+    Render {
+        #[command(flatten)]
+        input: Input,
+        #[command(flatten)]
+        viewport: Viewport,
+    },
+    /// Print the HTML parse errors and the log messages of all stylesheets
+    Errors(Input),
+    /// Parse a single stylesheet and print its log messages
+    Css {
+        #[command(flatten)]
+        input: Input,
+        /// Only print the tokens generated by the tokenizer
+        #[arg(long)]
+        tokens: bool,
+        /// Continue parsing after errors
+        #[arg(long)]
+        ignore_errors: bool,
+        /// Check if the values of each property match the property syntax
+        #[arg(long)]
+        match_values: bool,
+    },
+}
+
+#[derive(Args)]
+struct Input {
+    /// File, url or `-` to read from stdin
+    input: String,
+    /// Url of the input read from stdin, which relative urls (like stylesheets) are resolved against
+    #[arg(long, value_name = "URL")]
+    base: Option<Url>,
+}
+
+#[derive(Args)]
+struct Viewport {
+    /// Width of the viewport in pixels
+    #[arg(long, default_value_t = 1280)]
+    width: u32,
+    /// Height of the viewport in pixels
+    #[arg(long, default_value_t = 720)]
+    height: u32,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    if cli.debug {
+        SimpleLogger::new().init()?;
+    }
+
+    match cli.command {
+        Command::Dom(input) => {
+            let (doc, _) = load_document(&input)?;
+            print_dom(&doc, NodeId::root(), 0);
+        }
+        Command::Text(input) => {
+            let (doc, _) = load_document(&input)?;
+            print_text(&doc, NodeId::root());
+        }
+        Command::Style { input, node, selector } => {
+            let (doc, _) = load_document(&input)?;
+            print_style(&doc, node, selector.as_deref())?;
+        }
+        Command::Layout { input, viewport } => {
+            let (doc, _) = load_document(&input)?;
+            let tree = layout_document(&doc, &viewport)?;
+
+            let boxes = layout_json(&tree, tree.root, Point::ZERO);
+            println!("{}", serde_json::to_string_pretty(&boxes)?);
+        }
+        Command::Render { input, viewport } => {
+            let (doc, _) = load_document(&input)?;
+            let tree = layout_document(&doc, &viewport)?;
+
+            println!("{}", tree.desc());
+        }
+        Command::Errors(input) => {
+            let (doc, errors) = load_document(&input)?;
+            print_errors(&errors, &doc.stylesheets);
+        }
+        Command::Css {
+            input,
+            tokens,
+            ignore_errors,
+            match_values,
+        } => {
+            let (css, url) = read_input(&input)?;
+            let source = url.map(|url| url.to_string()).unwrap_or_else(|| input.input.clone());
+
+            if tokens {
+                print_tokens(&css);
+            } else {
+                let config = ParserConfig {
+                    source: Some(source.clone()),
+                    ignore_errors,
+                    match_values,
+                    ..Default::default()
+                };
+                parse_stylesheet(&css, &source, config)?;
+            }
+        }
+    }
+
+    if cli.timings {
+        timing_display!(true, Scale::Auto);
+    }
+
+    if let Some(path) = cli.trace {
+        timing_export!(&path)?;
+        eprintln!("Trace written to {path}");
+    }
+
+    Ok(())
+}
+
+/// Reads the input from a file, a url or stdin. Returns the data and the url of the input, which is used to
+/// resolve relative urls (like stylesheets) against. Input from stdin has the url given with `--base`.
+fn read_input(input: &Input) -> Result<(String, Option<Url>)> {
+    if input.input == "-" {
+        let mut data = String::new();
+        std::io::stdin().read_to_string(&mut data)?;
+        return Ok((data, input.base.clone()));
+    }
+    let input = input.input.as_str();
+
+    if let Ok(url) = Url::parse(input) {
+        match url.scheme() {
+            "http" | "https" => {
+                let mut response = ureq::get(url.as_str()).call()?;
+                if response.status() != 200 {
+                    bail!("Could not get url. Status code {}", response.status());
+                }
+                return Ok((response.body_mut().read_to_string()?, Some(url)));
+            }
+            "file" => {
+                let path = url.to_file_path().map_err(|()| anyhow!("Invalid file url: {url}"))?;
+                return Ok((fs::read_to_string(path)?, Some(url)));
+            }
+            // Anything else is treated as a path (for instance, windows paths parse as a url with a scheme)
+            _ => {}
+        }
+    }
+
+    let path = fs::canonicalize(input)?;
+    let data = fs::read_to_string(&path)?;
+
+    Ok((data, Url::from_file_path(&path).ok()))
+}
+
+fn load_document(input: &Input) -> Result<(DocumentImpl<Config>, Vec<ParseError>)> {
+    let (html, url) = read_input(input)?;
+    if url.is_none() {
+        eprintln!("Warning: external stylesheets are not loaded for input from stdin without --base");
+    }
+
+    let mut stream = ByteStream::new(Encoding::UTF8, None);
+    stream.read_from_str(&html, Some(Encoding::UTF8));
+    stream.close();
+
+    let mut doc = <DocumentBuilderImpl as DocumentBuilder<Config>>::new_document(url.clone());
+
+    // External resources of documents with a url are fetched once the document is parsed
+    let loader = url.map(|url| Arc::new(BlockingResourceLoader::new(url)));
+    if let Some(loader) = &loader {
+        doc.set_resource_loader(loader.clone());
+    }
+
+    let errors = Html5Parser::<Config>::parse_document(&mut stream, &mut doc, None)?;
+
+    if let Some(loader) = loader {
+        loader.load_into::<Config>(&mut doc);
+    }
+
+    Ok((doc, errors))
+}
+
+fn layout_document(doc: &DocumentImpl<Config>, viewport: &Viewport) -> Result<RenderTree<Config>> {
+    let mut tree = RenderTree::<Config>::from_document(doc);
+
+    let root = tree.root;
+    <TaffyLayouter as Layouter<Config>>::layout(
+        &TaffyLayouter,
+        &mut tree,
+        root,
+        SizeU32::new(viewport.width, viewport.height),
+    )?;
+
+    Ok(tree)
+}
+
+fn print_dom(doc: &DocumentImpl<Config>, id: NodeId, depth: usize) {
+    let Some(node) = doc.node_by_id(id) else {
+        return;
+    };
+
+    let desc = match node.type_of() {
+        NodeType::DocumentNode => "#document".to_string(),
+        NodeType::DocTypeNode => node
+            .get_doctype_data()
+            .map(|data| format!("<!DOCTYPE {}>", data.name()))
+            .unwrap_or_default(),
+        NodeType::ElementNode => node.get_element_data().map(describe_element).unwrap_or_default(),
+        NodeType::TextNode => {
+            let text = node.get_text_data().map(|data| data.value().trim()).unwrap_or_default();
+            if text.is_empty() {
+                return;
+            }
+            format!("{text:?}")
+        }
+        NodeType::CommentNode => node
+            .get_comment_data()
+            .map(|data| format!("<!--{}-->", data.value()))
+            .unwrap_or_default(),
+    };
+
+    println!("{}{}: {desc}", "  ".repeat(depth), id.as_usize());
+
+    for child in node.children() {
+        print_dom(doc, *child, depth + 1);
+    }
+}
+
+fn describe_element(data: &impl ElementDataType<Config>) -> String {
+    let mut attributes: Vec<_> = data.attributes().iter().collect();
+    attributes.sort();
+
+    let mut desc = format!("<{}", data.name());
+    for (name, value) in attributes {
+        desc.push_str(&format!(" {name}=\"{value}\""));
+    }
+    desc.push('>');
+
+    desc
+}
+
+fn print_text(doc: &DocumentImpl<Config>, id: NodeId) {
+    let Some(node) = doc.node_by_id(id) else {
+        return;
+    };
+
+    if let Some(data) = node.get_text_data() {
+        let text = data.value().trim();
+        if !text.is_empty() {
+            println!("{text}");
+        }
+    }
+
+    for child in node.children() {
+        print_text(doc, *child);
+    }
+}
+
+fn print_style(doc: &DocumentImpl<Config>, node: Option<usize>, selector: Option<&str>) -> Result<()> {
+    let id = match (node, selector) {
+        (Some(id), _) => NodeId::from(id),
+        (None, Some(selector)) => *query_selector_all::<Config>(doc, selector)
+            .map_err(|e| anyhow!("Invalid selector {selector}: {}", e.message))?
+            .first()
+            .ok_or_else(|| anyhow!("No element matches {selector}"))?,
+        (None, None) => bail!("Select a node with --node or --selector"),
+    };
+
+    let element = doc
+        .node_by_id(id)
+        .ok_or_else(|| anyhow!("Node {} not found", id.as_usize()))?;
+    match element.get_element_data() {
+        Some(data) => println!("{}: {}", id.as_usize(), describe_element(data)),
+        None => println!("{}", id.as_usize()),
+    }
+
+    let tree = RenderTree::<Config>::from_document(doc);
+    let Some(node) = tree.nodes.get(&id) else {
+        println!("  (not rendered, so there is no computed style)");
+        return Ok(());
+    };
+
+    let mut properties: Vec<_> = node.properties.properties.iter().collect();
+    properties.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (name, value) in properties {
+        println!("  {name}: {value}");
+    }
+
+    Ok(())
+}
+
+/// Converts the layout of a node and its children into JSON. Positions are absolute, sizes are of the content box.
+fn layout_json(tree: &RenderTree<Config>, id: NodeId, offset: Point) -> Value {
+    let Some(node) = tree.nodes.get(&id) else {
+        return Value::Null;
+    };
+
+    let layout = &node.layout;
+    let rel = layout.rel_pos();
+    let pos = Point::new(offset.x + rel.x, offset.y + rel.y);
+    let size = layout.size();
+
+    let children: Vec<Value> = node
+        .children
+        .iter()
+        .map(|child| layout_json(tree, *child, pos))
+        .filter(|child| !child.is_null())
+        .collect();
+
+    let mut boxed = json!({
+        "id": id.as_usize(),
+        "name": node.name,
+        "x": pos.x,
+        "y": pos.y,
+        "width": size.width,
+        "height": size.height,
+        "padding": edges_json(Edges::from_layout_rect(layout.padding())),
+        "border": edges_json(Edges::from_layout_rect(layout.border())),
+        "children": children,
+    });
+
+    match &node.data {
+        RenderNodeData::Text(text) => boxed["text"] = json!(text.text),
+        RenderNodeData::AnonymousInline => boxed["anonymous"] = json!(true),
+        _ => {}
+    }
+
+    boxed
+}
+
+fn edges_json(edges: Edges) -> Value {
+    json!({
+        "top": edges.top,
+        "right": edges.right,
+        "bottom": edges.bottom,
+        "left": edges.left,
+    })
+}
+
+fn print_errors(errors: &[ParseError], stylesheets: &[CssStylesheet]) {
+    println!("HTML parse errors ({}):", errors.len());
+    for e in errors {
+        println!("  {} {}", location(&e.location), e.message);
+    }
+
+    for sheet in stylesheets {
+        println!();
+        println!("Stylesheet {} ({} messages):", sheet.url, sheet.parse_log.len());
+        for log in &sheet.parse_log {
+            println!("  {} [{}] {}", location(&log.location), log.severity, log.message);
+        }
+    }
+}
+
+fn location(location: &Location) -> String {
+    format!("{}:{}", location.line, location.column)
+}
+
+fn parse_stylesheet(css: &str, source: &str, config: ParserConfig) -> Result<()> {
+    let now = Instant::now();
+    let result = Css3::parse_str(css, config, CssOrigin::Author, source);
+    println!(
+        "Running css3 parser of ({}) took {} ms.",
+        byte_size(css.len() as u64),
+        now.elapsed().as_millis()
+    );
+
+    let sheet = match result {
+        Ok(sheet) => sheet,
+        Err(err) => {
+            let message = err.to_string();
+            display_snippet(css, err);
+            bail!(message);
+        }
+    };
+
+    println!(
+        "{} rules, {} font faces, {} messages",
+        sheet.rules.len(),
+        sheet.font_faces.len(),
+        sheet.parse_log.len()
+    );
+    for log in &sheet.parse_log {
+        println!("  {} [{}] {}", location(&log.location), log.severity, log.message);
+    }
+
+    Ok(())
+}
+
+/// Print snippet where the error occurred
+fn display_snippet(css: &str, err: CssError) {
+    let Some(loc) = err.location else {
+        println!("Error: {}", err.message);
+        return;
+    };
+
+    let lines: Vec<&str> = css.split('\n').collect();
+    let line_nr = loc.line.saturating_sub(1).min(lines.len().saturating_sub(1));
+    let col_nr = loc.column.saturating_sub(2);
+
+    if col_nr > 1000 {
+        println!("Error is too far to the right to display.");
+        return;
+    }
+
+    // Print 5 lines around the line with the error, with a pointer to the error
+    println!();
+    let first = line_nr.saturating_sub(5);
+    for (n, line) in lines.iter().enumerate().skip(first).take(line_nr - first + 6) {
+        println!("{:<5}|{line}", n + 1);
+        if n == line_nr {
+            println!("   ---{}^", "-".repeat(col_nr));
+        }
+    }
+    println!();
+}
+
+/// Returns a human-readable byte size (1024-based)
+fn byte_size(bytes: u64) -> String {
+    let sizes = ["B", "KB", "MB", "GB", "TB"];
+    if bytes == 0 {
+        return "0 B".to_string();
+    }
+    let i = ((bytes as f64).log(1024.0).floor() as usize).min(sizes.len() - 1);
+    format!("{:.2} {}", bytes as f64 / 1024_f64.powi(i as i32), sizes[i])
+}
+
+fn print_tokens(css: &str) {
+    let mut stream = ByteStream::new(Encoding::UTF8, None);
+    stream.read_from_str(css, Some(Encoding::UTF8));
+    stream.close();
+
+    let mut tokenizer = Tokenizer::new(&mut stream, Location::default());
+    loop {
+        let token = tokenizer.consume();
+        println!("{token:?}");
+
+        if token.token_type == TokenType::Eof {
+            break;
+        }
+    }
+}
@@ -3,7 +3,8 @@ use gosub_interface::config::{HasDocument, HasHtmlParser};
 use {
     cookie::CookieJar,
     core::fmt::Debug,
-    gosub_interface::document::DocumentBuilder,
+    gosub_html5::document::loader::BlockingResourceLoader,
+    gosub_interface::document::{Document, DocumentBuilder},
     gosub_interface::html5::Html5Parser as Html5ParserT,
     gosub_net::dns::{Dns, ResolveType},
     gosub_net::errors::Error,
@@ -13,6 +14,7 @@ use {
     gosub_shared::byte_stream::{ByteStream, Encoding},
     gosub_shared::types::{ParseError, Result},
     gosub_shared::{timing_start, timing_stop},
+    std::sync::Arc,
     url::Url,
 };
 
@@ -130,11 +132,15 @@ fn fetch_url<C: HasHtmlParser>(
 
     println!("resp: {:?}", fetch_response.response);
 
-    let t_id = timing_start!("html.parse", parts.as_str());
+    let t_id = timing_start!("html5.parse", parts.as_str());
 
     let mut stream = ByteStream::new(Encoding::UTF8, None);
     let _ = stream.read_from_bytes(&fetch_response.response.body);
-    fetch_response.document = C::DocumentBuilder::new_document(Some(parts));
+    fetch_response.document = C::DocumentBuilder::new_document(Some(parts.clone()));
+
+    // The document is parsed synchronously, so its external resources are fetched all at once after parsing
+    let loader = Arc::new(BlockingResourceLoader::new(parts));
+    fetch_response.document.set_resource_loader(loader.clone());
 
     match C::HtmlParser::parse(&mut stream, &mut fetch_response.document, None) {
         Ok(parse_errors) => {
@@ -147,6 +153,8 @@ fn fetch_url<C: HasHtmlParser>(
 
     timing_stop!(t_id);
 
+    loader.load_into::<C>(&mut fetch_response.document);
+
     Ok(fetch_response)
 }
 